@@ -0,0 +1,210 @@
+//! `--batch`: comparing many pairs of trees listed in a manifest in one run, in parallel on the
+//! shared thread pool.
+
+use crate::{Cli, DiffConfig, build_trees, construct_diff, html_report, json_report, summary_report};
+use rayon::prelude::*;
+use semdiff_core::timing::DifferTimings;
+use semdiff_core::{DiffOptions, DiffSummary};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// One entry of the manifest. Paths are relative to the directory of the manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchPair {
+    expected: PathBuf,
+    actual: PathBuf,
+    /// Report of this pair: JSON for a `.json` path, HTML for a `.html` path.
+    output: PathBuf,
+}
+
+/// The index written by `--batch-index`, listing every pair in manifest order.
+#[derive(Debug, Serialize)]
+struct BatchIndex<'a> {
+    /// Whether every pair was compared without changes.
+    passed: bool,
+    pairs: Vec<BatchIndexEntry<'a>>,
+}
+
+/// One pair of the index, with its paths as written in the manifest.
+#[derive(Debug, Serialize)]
+struct BatchIndexEntry<'a> {
+    expected: &'a Path,
+    actual: &'a Path,
+    output: &'a Path,
+    status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    unchanged: usize,
+    modified: usize,
+    added: usize,
+    deleted: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchStatus {
+    Unchanged,
+    Changed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatchOutput {
+    Json,
+    Html,
+}
+
+impl BatchPair {
+    fn output_kind(&self) -> Result<BatchOutput, String> {
+        match self.output.extension().and_then(OsStr::to_str) {
+            Some("json") => Ok(BatchOutput::Json),
+            Some("html") => Ok(BatchOutput::Html),
+            _ => Err(format!("output {} must end in .json or .html", self.output.display())),
+        }
+    }
+}
+
+pub(crate) fn run(cli: &Cli, manifest: &Path, diff_options: &DiffOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    let listed = serde_json::from_slice::<Vec<BatchPair>>(&fs::read(manifest)?)
+        .map_err(|error| format!("invalid batch manifest {}: {error}", manifest.display()))?;
+    let pairs = listed
+        .iter()
+        .map(|pair| BatchPair {
+            expected: base.join(&pair.expected),
+            actual: base.join(&pair.actual),
+            output: base.join(&pair.output),
+        })
+        .collect::<Vec<_>>();
+    let kinds = pairs
+        .iter()
+        .map(BatchPair::output_kind)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut diff_config = DiffConfig::from_cli(cli);
+    diff_config.render_visuals = kinds.contains(&BatchOutput::Html);
+    let summaries = pairs
+        .par_iter()
+        .zip(&kinds)
+        .map(|(pair, &kind)| run_pair(cli, &diff_config, diff_options, pair, kind))
+        .collect::<Vec<_>>();
+    if let Some(path) = &cli.batch_index {
+        write_index(path, &listed, &summaries)?;
+    }
+    let mut failed = 0;
+    let mut stdout = io::stdout().lock();
+    for (pair, summary) in pairs.iter().zip(summaries) {
+        if summary.is_err() {
+            failed += 1;
+        }
+        if cli.silent {
+            continue;
+        }
+        writeln!(
+            stdout,
+            "== {} -> {} ({})",
+            pair.expected.display(),
+            pair.actual.display(),
+            pair.output.display()
+        )?;
+        match summary {
+            Ok((summary, _)) => stdout.write_all(&summary)?,
+            Err(error) => writeln!(stdout, "error: {error}")?,
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{failed} of {} batch pairs failed", pairs.len()).into());
+    }
+    Ok(())
+}
+
+/// Writes the `--batch-index` of the `listed` pairs from their results.
+fn write_index(
+    path: &Path,
+    listed: &[BatchPair],
+    results: &[Result<(Vec<u8>, DiffSummary), String>],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pairs = listed
+        .iter()
+        .zip(results)
+        .map(|(pair, result)| {
+            let (status, error, counts) = match result {
+                Ok((_, counts)) if counts.has_changes() => (BatchStatus::Changed, None, *counts),
+                Ok((_, counts)) => (BatchStatus::Unchanged, None, *counts),
+                Err(error) => (BatchStatus::Failed, Some(error.as_str()), DiffSummary::default()),
+            };
+            BatchIndexEntry {
+                expected: &pair.expected,
+                actual: &pair.actual,
+                output: &pair.output,
+                status,
+                error,
+                unchanged: counts.unchanged,
+                modified: counts.modified,
+                added: counts.added,
+                deleted: counts.deleted,
+            }
+        })
+        .collect::<Vec<_>>();
+    let index = BatchIndex {
+        passed: pairs.iter().all(|pair| pair.status == BatchStatus::Unchanged),
+        pairs,
+    };
+    let mut out = File::create(path).map_err(|error| format!("cannot create {}: {error}", path.display()))?;
+    serde_json::to_writer_pretty(&mut out, &index)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Compares one pair, writing its report, and returns its summary text and counts.
+fn run_pair(
+    cli: &Cli,
+    diff_config: &DiffConfig,
+    diff_options: &DiffOptions,
+    pair: &BatchPair,
+    kind: BatchOutput,
+) -> Result<(Vec<u8>, DiffSummary), String> {
+    let (expected, actual) = build_trees(cli, diff_config, pair.expected.clone(), pair.actual.clone(), Vec::new())
+        .map_err(|error| error.to_string())?;
+    let diff_score =
+        (cli.diff_score || cli.diff_score_weights.is_some()).then(|| cli.diff_score_weights.unwrap_or_default());
+    let timings = DifferTimings::new();
+    let mut summary = Vec::new();
+    macro_rules! run {
+        ($report:expr) => {{
+            let report = ($report, summary_report(&mut summary, diff_score, cli.stat, cli.by_type));
+            let diff = construct_diff(diff_config, &timings);
+            if cli.keep_going {
+                semdiff_core::calc_diff_collect(expected, actual, &diff, report, diff_options)
+                    .map_err(|errors| errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))?
+            } else {
+                semdiff_core::calc_diff_with_options(expected, actual, &diff, report, diff_options)
+                    .map_err(|error| error.to_string())?
+            }
+        }};
+    }
+    let counts = match kind {
+        BatchOutput::Json => {
+            let file = File::create_new(&pair.output)
+                .map_err(|error| format!("cannot create {}: {error}", pair.output.display()))?;
+            run!(json_report(
+                file,
+                diff_score,
+                false,
+                cli.borderline_band,
+                cli.report_precision,
+                None
+            ))
+        }
+        BatchOutput::Html => run!(html_report(
+            pair.output.clone(),
+            cli.borderline_band,
+            cli.html_paginate_by_dir,
+            cli.html_changes_only
+        )),
+    };
+    Ok((summary, counts))
+}
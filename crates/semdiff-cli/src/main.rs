@@ -1,23 +1,75 @@
-use clap::Parser;
-use semdiff_core::fs::{FileLeaf, FsNode};
-use semdiff_core::{DetailReporter, DiffAndReport, DiffCalculator, DiffReport};
+use clap::{CommandFactory, Parser};
+use semdiff_core::archive::{ArchiveLimits, is_archive_name};
+use semdiff_core::collect::{ChangedFileCollector, CollectChanged};
+use semdiff_core::fs::{FileLeaf, FileSizeFilter, FsNode, OpenFileLimit};
+use semdiff_core::ignore::{IGNORE_FILE_NAME, IgnoreRules};
+use semdiff_core::memory::MemoryBudget;
+use semdiff_core::tally::{ChangeTally, CountChanges};
+use semdiff_core::timing::DifferTimings;
+use semdiff_core::update::{ExpectedUpdates, RecordUpdates, UpdateKind};
+use semdiff_core::{
+    DetailReporter, DiffAndReport, DiffCalculator, DiffOptions, DiffReport, DiffSummary, DifferOrder, Reporter,
+    Sampling,
+};
+use semdiff_output::drift::ReportDrift;
 use semdiff_output::html::HtmlReport;
 use semdiff_output::json::JsonReport;
+use semdiff_output::names::NameListReport;
+use semdiff_output::score::DiffScoreWeights;
 use semdiff_output::summary::SummaryReport;
+use serde::Serialize;
+use std::env;
 use std::ffi::OsStr;
-use std::fs::File;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::iter;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tui::TuiSession;
+
+mod batch;
+mod tui;
 
 #[derive(Debug, clap::Parser)]
 #[command(name = "semdiff", version, about = "Semantic diff tool")]
 struct Cli {
-    /// Path to the expected input file or directory.
-    #[arg(value_name = "EXPECTED")]
-    expected: PathBuf,
-    /// Path to the actual input file or directory.
-    #[arg(value_name = "ACTUAL")]
-    actual: PathBuf,
+    /// Path to the expected input file or directory, or the http://, https://, or s3:// URL of a
+    /// manifest that lists a remote tree.
+    #[arg(value_name = "EXPECTED", required_unless_present_any = ["list_formats", "batch"])]
+    expected: Option<PathBuf>,
+    /// Path to the actual input file or directory, or the http://, https://, or s3:// URL of a
+    /// manifest that lists a remote tree.
+    #[arg(value_name = "ACTUAL", required_unless_present_any = ["list_formats", "batch"])]
+    actual: Option<PathBuf>,
+    /// Print the file kinds each differ accepts, with their usual extensions, and exit.
+    #[arg(long, exclusive = true)]
+    list_formats: bool,
+    /// Compare every pair of trees listed in MANIFEST, a JSON array of {"expected", "actual",
+    /// "output"} objects with paths relative to the manifest, in one run. Each pair writes a JSON
+    /// or HTML report to its output, chosen by extension, and a summary of every pair is printed.
+    #[arg(
+        long,
+        value_name = "MANIFEST",
+        conflicts_with_all = [
+            "expected", "actual", "candidates", "output", "format", "output_json", "output_ndjson",
+            "output_html", "compare_reports", "names_only", "collect_diffs", "profile_types", "update",
+            "allow_missing", "verdict_json", "report_top_offenders", "numstat", "tui"
+        ]
+    )]
+    batch: Option<PathBuf>,
+    /// With --batch, also write a JSON index of every pair to PATH: its paths, its output, whether
+    /// it is unchanged, changed, or failed, and its counts of unchanged, modified, added, and
+    /// deleted files.
+    #[arg(long, value_name = "PATH", requires = "batch")]
+    batch_index: Option<PathBuf>,
+    /// Another actual directory to accept: a file is unchanged if it matches the file at the same
+    /// path in ACTUAL or in any --actual, and otherwise shows the diff against the closest one. May
+    /// be repeated.
+    #[arg(long = "actual", value_name = "DIR", conflicts_with_all = ["collect_diffs", "compare_reports"])]
+    candidates: Vec<PathBuf>,
     /// Write JSON/HTML reports to PATH; if omitted, prints a summary to stdout.
     #[arg(long, hide = true)]
     output: Option<PathBuf>,
@@ -27,70 +79,576 @@ struct Cli {
     /// Write JSON report to PATH. Use "-" or omit the value after --output-json to write to stdout.
     #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
     output_json: Option<PathBuf>,
+    /// Write newline-delimited JSON to PATH, one line per entry as it completes and a final summary
+    /// line. Use "-" or omit the value to write to stdout.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-", conflicts_with = "output_json")]
+    output_ndjson: Option<PathBuf>,
+    /// Flush --output-ndjson lines once MS milliseconds have passed since the last flush, checked
+    /// as lines are written. Defaults to every line on stdout and only at the end for a file.
+    #[arg(long, value_name = "MS", requires = "output_ndjson")]
+    json_flush_interval: Option<u64>,
+    /// Round non-integer numbers in the JSON report, such as durations and diff ratios, to N
+    /// decimal places.
+    #[arg(long, value_name = "N")]
+    report_precision: Option<u8>,
     /// Write HTML report to PATH.
     #[arg(long)]
     output_html: Option<PathBuf>,
+    /// Write an HTML page per directory, linked to its subdirectories and parents, instead of
+    /// listing every entry on the root page.
+    #[arg(long)]
+    html_paginate_by_dir: bool,
+    /// Only count unchanged entries in the HTML report, without previews, detail pages, or assets
+    /// for them.
+    #[arg(long)]
+    html_changes_only: bool,
+    /// Treat EXPECTED and ACTUAL as JSON reports from two runs and print which entries newly changed,
+    /// stopped changing, or whose stats moved.
+    #[arg(long)]
+    compare_reports: bool,
     /// Suppress summary output to stdout unless stdout is explicitly selected.
     #[arg(long)]
     silent: bool,
+    /// Print the summary as a table of unchanged/modified/added/deleted counts per compared type.
+    #[arg(long, conflicts_with = "silent")]
+    stat: bool,
+    /// Follow the summary with the changes of each compared type, such as "image: 3 modified, 1
+    /// added".
+    #[arg(long, conflicts_with_all = ["silent", "stat"])]
+    by_type: bool,
+    /// Print a tab-separated line per modified file instead of the summary, like `git diff
+    /// --numstat`: added and deleted counts (lines of text, bytes of binary files, or a single
+    /// measure such as differing pixels), the compared type, and the path.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output", "format", "output_json", "output_ndjson", "output_html", "compare_reports", "silent", "stat",
+            "by_type", "names_only"
+        ]
+    )]
+    numstat: bool,
+    /// Browse the results in a terminal view that shows each file's status as it is compared, with
+    /// the details of the selected file. Prints the summary instead when stdout is not a terminal.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output", "format", "output_json", "output_ndjson", "output_html", "compare_reports", "silent", "stat",
+            "by_type", "numstat", "names_only"
+        ]
+    )]
+    tui: bool,
     /// Ignore object key order when comparing JSON.
     #[arg(long)]
     json_ignore_object_key_order: bool,
     /// Ignore JSON values matching the JSONPath expression (RFC 9535) when comparing JSON.
     #[arg(long, value_name = "JSONPATH")]
     json_ignore_path: Vec<semdiff_differ_json::json_path::JsonPath>,
-    /// Max OkLab+alpha distance to treat two image pixels as equal.
-    #[arg(long, default_value_t = 0.0)]
+    /// Round non-integer JSON numbers on both sides to N decimal places before comparing.
+    #[arg(long, value_name = "N")]
+    json_round_decimals: Option<u8>,
+    /// Compare JSON objects and arrays nested more than N levels below the root as a whole, shown
+    /// collapsed.
+    #[arg(long, value_name = "N")]
+    json_max_depth: Option<usize>,
+    /// Compare JSON arrays of scalars as multisets: reordered elements are unchanged, and only
+    /// elements whose count differs are shown as added or deleted.
+    #[arg(long)]
+    json_array_as_multiset: bool,
+    /// Compare the JSON arrays at the JSON pointer (RFC 6901; `*` matches any member or index)
+    /// regardless of the order of their elements, which may also be objects or arrays.
+    #[arg(long, value_name = "POINTER")]
+    json_unordered_path: Vec<semdiff_differ_json::json_pointer::JsonPointerPattern>,
+    /// Compare the JSON strings at POINTER only by their shape: iso8601 (a date or timestamp),
+    /// uuid, or regex:PATTERN. Values of that shape on both sides are equal whatever they hold,
+    /// and are shown as "<SHAPE>". Repeatable; `*` in POINTER matches any member or index.
+    #[arg(long, value_name = "POINTER=SHAPE")]
+    json_match_shape: Vec<semdiff_differ_json::shape::ShapeRule>,
+    /// How the documents of JSON files that hold several, such as JSON Lines, are paired up: in
+    /// order (position) or by the values at --json-document-key (key).
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = JsonDocumentMatch::Position)]
+    json_match_documents: JsonDocumentMatch,
+    /// JSON pointer to a value that identifies a document with `--json-match-documents key`
+    /// (repeatable; default /kind and /metadata/name).
+    #[arg(long, value_name = "POINTER")]
+    json_document_key: Vec<semdiff_differ_json::json_pointer::JsonPointerPattern>,
+    /// Color keys, strings, numbers, booleans, and null in the JSON views of the HTML report.
+    #[arg(long)]
+    json_highlight: bool,
+    /// List at most N changed rows per table for SQLite databases; further changes are only
+    /// counted.
+    #[arg(long, value_name = "N", default_value_t = semdiff_differ_sqlite::DEFAULT_MAX_ROW_CHANGES)]
+    sqlite_max_row_changes: usize,
+    /// Decode *.pb and *.binpb files with the FileDescriptorSet at PATH, as written by `protoc
+    /// --include_imports --descriptor_set_out`, and compare the messages like JSON.
+    #[arg(long, value_name = "PATH", requires = "protobuf_message")]
+    protobuf_descriptor_set: Option<PathBuf>,
+    /// Full name of the message type of the protobuf files, such as `my.package.Message`.
+    #[arg(long, value_name = "NAME", requires = "protobuf_descriptor_set")]
+    protobuf_message: Option<String>,
+    /// Compare repeated protobuf fields regardless of the order of their elements.
+    #[arg(long, requires = "protobuf_descriptor_set")]
+    protobuf_unordered_repeated: bool,
+    /// Compare source code in known languages, by file extension, as tokens: comments and
+    /// formatting are ignored.
+    #[arg(long)]
+    text_semantic: bool,
+    /// Zero LEN bytes at offset START on both sides before comparing binary files; repeatable.
+    /// Numbers are decimal or 0x-prefixed hexadecimal.
+    #[arg(long, value_name = "START:LEN")]
+    binary_ignore_range: Vec<semdiff_differ_binary::ByteRange>,
+    /// Which files the binary differ compares when no other differ handled them: always, only
+    /// files of a known binary kind (known-binary), or never. Files it does not compare are
+    /// reported as skipped (unsupported).
+    #[arg(long, value_name = "MODE", default_value = "always")]
+    binary_fallback: semdiff_differ_binary::BinaryFallback,
+    /// Compare binary files as equal when their bytes only differ in line endings (CRLF, CR, or
+    /// LF), such as text files detected as binary because of a stray control byte.
+    #[arg(long)]
+    binary_normalize_line_endings: bool,
+    /// Named tolerance defaults for image and audio comparison.
+    #[arg(long, value_enum, default_value_t = ToleranceProfile::Strict)]
+    profile: ToleranceProfile,
+    /// Max OkLab+alpha distance to treat two image pixels as equal; overrides --profile.
+    #[arg(long)]
+    image_max_distance: Option<f32>,
+    /// Max ratio of differing pixels to treat images as equal; overrides --profile.
+    #[arg(long)]
+    image_max_diff_ratio: Option<f32>,
+    /// Treat images as equal when no connected region of differing pixels is larger than PIXELS,
+    /// instead of checking the ratio of differing pixels.
+    #[arg(long, value_name = "PIXELS", conflicts_with = "image_max_diff_ratio")]
+    image_max_diff_region: Option<u64>,
+    /// Divide images into a grid of COLS × ROWS tiles and report which tiles changed.
+    #[arg(long, num_args = 2, value_names = ["COLS", "ROWS"])]
+    image_tiles: Option<Vec<u32>>,
+    /// Transfer function of input image pixel values: srgb or linear.
+    #[arg(long, value_name = "COLORSPACE", default_value = "srgb")]
+    image_colorspace: semdiff_differ_image::ImageColorSpace,
+    /// Weights of the squared OkLab L, a, b, and alpha differences in the pixel distance, such as
+    /// 2,1,1,1 to weigh lightness over chroma.
+    #[arg(long, value_name = "L,a,b,alpha", default_value = "1,1,1,1")]
+    image_weights: semdiff_differ_image::ImageChannelWeights,
+    /// Also compare EXIF metadata (camera, GPS, timestamps) of images, reporting images whose
+    /// metadata differs as modified even when their pixels match.
+    #[arg(long)]
+    image_diff_metadata: bool,
+    /// Max allowed temporal shift (seconds) when aligning audio; overrides --profile.
+    #[arg(long)]
+    audio_shift_tolerance_seconds: Option<f32>,
+    /// Max LUFS difference in dB to treat audio as equal; overrides --profile.
+    #[arg(long)]
+    audio_lufs_tolerance_db: Option<f32>,
+    /// Per-bin spectral magnitude tolerance for audio comparison; overrides --profile.
+    #[arg(long)]
+    audio_spectral_tolerance: Option<f32>,
+    /// Max ratio of differing spectrogram bins to treat audio as equal; overrides --profile.
+    #[arg(long)]
+    audio_spectrogram_diff_rate_tolerance: Option<f64>,
+    /// Match the overall loudness of ACTUAL to EXPECTED before comparing audio, so pure gain changes
+    /// are not differences; the gain is still reported.
+    #[arg(long)]
+    audio_ignore_gain: bool,
+    /// Treat audio as equal when the normalized waveform cross-correlation is at least THRESHOLD
+    /// (up to 1.0), skipping the spectrogram comparison; the loudness tolerance still applies.
+    #[arg(long, value_name = "THRESHOLD")]
+    audio_correlation_threshold: Option<f32>,
+    /// FFT hop size in samples for audio spectrograms (64-2048); defaults to 1024 (50% overlap).
+    #[arg(long, value_name = "SAMPLES", value_parser = clap::value_parser!(u16).range(semdiff_differ_audio::MIN_FFT_HOP_SIZE as i64..=semdiff_differ_audio::MAX_FFT_HOP_SIZE as i64))]
+    audio_hop_size: Option<u16>,
+    /// Color (#rrggbb) of audio waveforms in the HTML report instead of the theme color.
+    #[arg(long, value_name = "COLOR")]
+    audio_waveform_color: Option<semdiff_differ_audio::HexColor>,
+    /// Color (#rrggbb) of audio spectrograms in the HTML report instead of the theme color.
+    #[arg(long, value_name = "COLOR")]
+    audio_spectrogram_color: Option<semdiff_differ_audio::HexColor>,
+    /// Color (#rrggbb) of audio spectrogram diffs in the HTML report instead of the theme color.
+    #[arg(long, value_name = "COLOR")]
+    audio_diff_color: Option<semdiff_differ_audio::HexColor>,
+    /// Label audio spectrograms in the HTML report with frequency (Hz) and time (seconds) axes.
+    #[arg(long)]
+    audio_axes: bool,
+    /// List unchanged audio in the HTML report with only a waveform thumbnail, skipping its audio
+    /// copy, per-channel images, and detail page.
+    #[arg(long)]
+    audio_minimal_unchanged: bool,
+    /// Write the decoded samples, spectrograms, and aligned samples of every reported audio file
+    /// to DIR as NumPy .npy files, and list them in the report.
+    #[arg(long, value_name = "DIR")]
+    audio_dump_data: Option<PathBuf>,
+    /// Only align and compare audio between START and END seconds; decoding stops after END.
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    audio_range: Option<Vec<f32>>,
+    /// Report audio files whose samples are equal but whose tags (ID3, Vorbis comments, cover art)
+    /// differ as modified; by default the tag changes are only noted.
+    #[arg(long)]
+    audio_compare_tags: bool,
+    /// Add a 0-100 diff score for the whole run to the JSON and summary output.
+    #[arg(long)]
+    diff_score: bool,
+    /// Weights for the diff score as KIND=WEIGHT pairs (added, deleted, modified); implies --diff-score.
+    #[arg(long, value_name = "WEIGHTS")]
+    diff_score_weights: Option<DiffScoreWeights>,
+    /// Report empty directories that exist on only one side as added/deleted.
+    #[arg(long)]
+    report_empty_dirs: bool,
+    /// Diff a deleted file against an added file as a rename when their content similarity (0.0-1.0)
+    /// is at least RATIO.
+    #[arg(long, value_name = "RATIO", conflicts_with = "names_only")]
+    rename_threshold: Option<f64>,
+    /// Walk the trees level by level, so files near the root are compared and streamed first.
+    #[arg(long)]
+    breadth_first: bool,
+    /// Pair up names that differ only in Unicode normalization, such as "é" written precomposed
+    /// (NFC) on one side and decomposed (NFD) on the other.
+    #[arg(long)]
+    normalize_names: bool,
+    /// Pair up names that differ only in letter case, such as README.md and readme.md.
+    #[arg(long)]
+    ignore_name_case: bool,
+    /// Flag files that parse as JSON, an image, or audio on one side only (such as valid JSON that
+    /// became malformed) as format regressions before they fall back to the text or binary differ.
+    #[arg(long)]
+    report_format_regressions: bool,
+    /// Fail if a file whose extension names a JSON, image, audio, or key-value format cannot be
+    /// read as that format, instead of comparing it as text or binary.
+    #[arg(long)]
+    strict_extensions: bool,
+    /// Report files with the same bytes on both sides as unchanged without decoding them, which
+    /// saves time on large trees of images and audio. Reports list them as compared by
+    /// "identical".
+    #[arg(long, conflicts_with = "names_only")]
+    identical_fast_path: bool,
+    /// Carry on after a directory or file cannot be read or compared, report everything else, and
+    /// print every error at the end instead of stopping at the first one.
+    #[arg(long)]
+    keep_going: bool,
+    /// Flag entries whose closest tolerance check measured within FRACTION of its threshold as
+    /// borderline in the JSON and HTML reports.
+    #[arg(long, value_name = "FRACTION")]
+    borderline_band: Option<f64>,
+    /// Only list paths that exist on one side ("+ path" added, "- path" deleted) without reading any
+    /// files.
+    #[arg(
+        long,
+        conflicts_with_all = ["output", "format", "output_json", "output_ndjson", "output_html", "compare_reports", "stat"]
+    )]
+    names_only: bool,
+    /// Copy every file reported as modified or added from ACTUAL into DIR, keeping its relative
+    /// path.
+    #[arg(long, value_name = "DIR", conflicts_with = "names_only")]
+    collect_diffs: Option<PathBuf>,
+    /// After reporting, accept the changes by overwriting EXPECTED with ACTUAL: modified and added
+    /// files are copied over and deleted ones removed. Asks for confirmation first.
+    #[arg(long, conflicts_with_all = ["candidates", "names_only", "compare_reports"])]
+    update: bool,
+    /// Apply --update without asking for confirmation.
+    #[arg(long, requires = "update")]
+    yes: bool,
+    /// Fail with exit status 1 when any file is modified or more than N files are added or
+    /// deleted. Every change is still reported.
+    #[arg(long, value_name = "N", conflicts_with = "compare_reports")]
+    allow_missing: Option<usize>,
+    /// Write a one-line JSON verdict to PATH ("-" for stdout): whether the run passed, and how many
+    /// files were unchanged, modified, added, and deleted. A run passes when no file was modified
+    /// and no more files were added or deleted than --allow-missing allows (none without it).
+    #[arg(long, value_name = "PATH", conflicts_with = "compare_reports")]
+    verdict_json: Option<PathBuf>,
+    /// Print the time spent in each differ (json, dom, keyvalue, text, audio, image, sqlite, protobuf, binary) to stderr at the end.
+    #[arg(long)]
+    profile_types: bool,
+    /// Print the N files that took the longest to compare and the N largest files to stderr at the
+    /// end.
+    #[arg(long, value_name = "N", conflicts_with_all = ["names_only", "compare_reports"])]
+    report_top_offenders: Option<usize>,
+    /// Max memory (MiB) for decoded image/audio buffers; files that do not fit are compared byte-wise.
+    #[arg(long, value_name = "MB")]
+    memory_budget_mb: Option<u64>,
+    /// Keep at most N files and directories open at once while reading the trees; for low
+    /// descriptor limits (EMFILE errors).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_open_files: Option<u64>,
+    /// Diff at most N files at once; the walk waits for a running diff to finish before it hands
+    /// out the next file, which bounds the memory and descriptors held by queued files.
+    #[arg(long, value_name = "N")]
+    max_in_flight: Option<NonZeroUsize>,
+    /// Skip files smaller than BYTES without reading them; they are listed as skipped in reports.
+    #[arg(long, value_name = "BYTES")]
+    min_size: Option<u64>,
+    /// Skip files larger than BYTES without reading them; they are listed as skipped in reports.
+    #[arg(long, value_name = "BYTES")]
+    max_size: Option<u64>,
+    /// Compare zip archives (.zip, .jar, .war, ...) entry by entry as directories, including
+    /// archives inside them.
+    #[arg(long, conflicts_with_all = ["update", "collect_diffs"])]
+    archives: bool,
+    /// Open archives nested at most N deep with --archives; deeper ones are compared as files.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = semdiff_core::archive::DEFAULT_MAX_DEPTH,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "archives"
+    )]
+    archive_max_depth: usize,
+    /// Skip archive entries that decompress to more than BYTES with --archives.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = semdiff_core::archive::DEFAULT_MAX_ENTRY_SIZE,
+        requires = "archives"
+    )]
+    archive_max_entry_size: u64,
+    /// Leave out paths matching PATTERN (gitignore syntax), applied after the .semdiffignore files;
+    /// may be repeated.
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+    /// Compare only a deterministic sample of RATE (above 0, up to 1) of the files, picked by
+    /// hashing each path with --seed; reports state the rate and how many files were left out.
+    #[arg(long, value_name = "RATE")]
+    sample_rate: Option<f64>,
+    /// Seed for --sample-rate; the same seed samples the same paths on every run.
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "sample_rate")]
+    seed: u64,
+    /// Try the named differs first, in order, for files with extension EXT; `*` as EXT applies
+    /// to every other file. DIFFERS is a comma-separated list of json, dom, keyvalue, text, audio,
+    /// image, sqlite, protobuf and binary; the remaining differs follow in their default order. Repeatable.
+    #[arg(long, value_name = "EXT=DIFFERS")]
+    differ_order: Vec<DifferOrderRule>,
+}
+
+/// Names of the differs, in the order [`construct_diff`] builds them.
+const DIFFER_NAMES: [&str; 9] = [
+    "json", "dom", "keyvalue", "text", "audio", "image", "sqlite", "protobuf", "binary",
+];
+
+/// One `--differ-order` flag.
+#[derive(Debug, Clone)]
+struct DifferOrderRule {
+    /// `None` for `*`.
+    extension: Option<String>,
+    /// Indices into [`DIFFER_NAMES`].
+    differs: Vec<usize>,
+}
+
+impl FromStr for DifferOrderRule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (extension, differs) = input
+            .split_once('=')
+            .ok_or_else(|| format!("expected EXT=DIFFERS, got `{input}`"))?;
+        let extension = match extension.trim().trim_start_matches('.') {
+            "" => return Err(format!("missing extension in `{input}`")),
+            "*" => None,
+            extension => Some(extension.to_owned()),
+        };
+        let differs = differs
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                DIFFER_NAMES
+                    .iter()
+                    .position(|differ| *differ == name)
+                    .ok_or_else(|| format!("unknown differ `{name}`; expected one of {}", DIFFER_NAMES.join(", ")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(DifferOrderRule { extension, differs })
+    }
+}
+
+/// How the documents of multi-document JSON files are paired up.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum JsonDocumentMatch {
+    /// In the order they appear.
+    Position,
+    /// By the values at --json-document-key.
+    Key,
+}
+
+/// Built-in defaults for the image and audio tolerance flags.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ToleranceProfile {
+    /// Everything must match exactly.
+    Strict,
+    /// Ignores re-encoding noise in images, such as PNG optimizers or color management.
+    WebAssets,
+    /// Tolerates lossy re-encoding of images and audio, such as JPEG or MP3.
+    LossyMedia,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tolerances {
     image_max_distance: f32,
-    /// Max ratio of differing pixels to treat images as equal.
-    #[arg(long, default_value_t = 0.0)]
     image_max_diff_ratio: f32,
-    /// Max allowed temporal shift (seconds) when aligning audio.
-    #[arg(long, default_value_t = 0.0)]
     audio_shift_tolerance_seconds: f32,
-    /// Max LUFS difference in dB to treat audio as equal.
-    #[arg(long, default_value_t = 0.0)]
     audio_lufs_tolerance_db: f32,
-    /// Per-bin spectral magnitude tolerance for audio comparison.
-    #[arg(long, default_value_t = 0.0)]
     audio_spectral_tolerance: f32,
-    /// Max ratio of differing spectrogram bins to treat audio as equal.
-    #[arg(long, default_value_t = 0.0)]
     audio_spectrogram_diff_rate_tolerance: f64,
 }
 
+impl ToleranceProfile {
+    fn tolerances(self) -> Tolerances {
+        match self {
+            ToleranceProfile::Strict => Tolerances {
+                image_max_distance: 0.0,
+                image_max_diff_ratio: 0.0,
+                audio_shift_tolerance_seconds: 0.0,
+                audio_lufs_tolerance_db: 0.0,
+                audio_spectral_tolerance: 0.0,
+                audio_spectrogram_diff_rate_tolerance: 0.0,
+            },
+            ToleranceProfile::WebAssets => Tolerances {
+                image_max_distance: 0.02,
+                image_max_diff_ratio: 0.001,
+                audio_shift_tolerance_seconds: 0.0,
+                audio_lufs_tolerance_db: 0.1,
+                audio_spectral_tolerance: 0.5,
+                audio_spectrogram_diff_rate_tolerance: 0.001,
+            },
+            ToleranceProfile::LossyMedia => Tolerances {
+                image_max_distance: 0.05,
+                image_max_diff_ratio: 0.01,
+                audio_shift_tolerance_seconds: 0.05,
+                audio_lufs_tolerance_db: 1.0,
+                audio_spectral_tolerance: 2.0,
+                audio_spectrogram_diff_rate_tolerance: 0.05,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct DiffConfig {
     json_ignore_object_key_order: bool,
     json_ignore_path: Vec<semdiff_differ_json::json_path::JsonPath>,
+    json_round_decimals: Option<u8>,
+    json_max_depth: Option<usize>,
+    json_array_as_multiset: bool,
+    json_unordered_path: Vec<semdiff_differ_json::json_pointer::JsonPointerPattern>,
+    json_match_shape: Vec<semdiff_differ_json::shape::ShapeRule>,
+    json_document_matching: semdiff_differ_json::DocumentMatching,
+    json_highlight: bool,
+    sqlite_max_row_changes: usize,
+    protobuf_schema: Option<Arc<semdiff_differ_protobuf::ProtobufSchema>>,
+    protobuf_unordered_repeated: bool,
+    text_semantic: bool,
+    binary_ignore_range: Vec<semdiff_differ_binary::ByteRange>,
+    binary_fallback: semdiff_differ_binary::BinaryFallback,
+    binary_normalize_line_endings: bool,
     image_max_distance: f32,
     image_max_diff_ratio: f32,
+    image_max_diff_region: Option<u64>,
+    image_tiles: Option<semdiff_differ_image::ImageTiles>,
+    image_colorspace: semdiff_differ_image::ImageColorSpace,
+    image_weights: semdiff_differ_image::ImageChannelWeights,
+    image_diff_metadata: bool,
     audio_shift_tolerance_seconds: f32,
     audio_lufs_tolerance_db: f32,
     audio_spectral_tolerance: f32,
     audio_spectrogram_diff_rate_tolerance: f64,
+    audio_ignore_gain: bool,
+    audio_correlation_threshold: Option<f32>,
+    audio_hop_size: usize,
+    audio_colors: semdiff_differ_audio::AudioColors,
+    audio_axes: bool,
+    audio_minimal_unchanged: bool,
+    audio_dump_data: Option<PathBuf>,
+    audio_range: Option<semdiff_differ_audio::AudioRange>,
+    audio_compare_tags: bool,
+    memory_budget_mb: Option<u64>,
+    /// Shared by every tree of the run, including the pairs of `--batch`.
+    open_file_limit: Option<OpenFileLimit>,
+    render_visuals: bool,
 }
 
 impl DiffConfig {
     fn from_cli(cli: &Cli) -> Self {
+        let profile = cli.profile.tolerances();
         Self {
             json_ignore_object_key_order: cli.json_ignore_object_key_order,
             json_ignore_path: cli.json_ignore_path.clone(),
-            image_max_distance: cli.image_max_distance,
-            image_max_diff_ratio: cli.image_max_diff_ratio,
-            audio_shift_tolerance_seconds: cli.audio_shift_tolerance_seconds,
-            audio_lufs_tolerance_db: cli.audio_lufs_tolerance_db,
-            audio_spectral_tolerance: cli.audio_spectral_tolerance,
-            audio_spectrogram_diff_rate_tolerance: cli.audio_spectrogram_diff_rate_tolerance,
+            json_round_decimals: cli.json_round_decimals,
+            json_max_depth: cli.json_max_depth,
+            json_array_as_multiset: cli.json_array_as_multiset,
+            json_unordered_path: cli.json_unordered_path.clone(),
+            json_match_shape: cli.json_match_shape.clone(),
+            json_document_matching: match cli.json_match_documents {
+                JsonDocumentMatch::Position => semdiff_differ_json::DocumentMatching::Position,
+                JsonDocumentMatch::Key if cli.json_document_key.is_empty() => {
+                    semdiff_differ_json::DocumentMatching::Key(semdiff_differ_json::DocumentMatching::default_key())
+                }
+                JsonDocumentMatch::Key => semdiff_differ_json::DocumentMatching::Key(cli.json_document_key.clone()),
+            },
+            json_highlight: cli.json_highlight,
+            sqlite_max_row_changes: cli.sqlite_max_row_changes,
+            protobuf_schema: protobuf_schema(cli).map(Arc::new),
+            protobuf_unordered_repeated: cli.protobuf_unordered_repeated,
+            text_semantic: cli.text_semantic,
+            binary_ignore_range: cli.binary_ignore_range.clone(),
+            binary_fallback: cli.binary_fallback,
+            binary_normalize_line_endings: cli.binary_normalize_line_endings,
+            image_max_distance: cli.image_max_distance.unwrap_or(profile.image_max_distance),
+            image_max_diff_ratio: cli.image_max_diff_ratio.unwrap_or(profile.image_max_diff_ratio),
+            image_max_diff_region: cli.image_max_diff_region,
+            image_tiles: cli
+                .image_tiles
+                .as_deref()
+                .and_then(|tiles| semdiff_differ_image::ImageTiles::new(tiles[0], tiles[1])),
+            image_colorspace: cli.image_colorspace,
+            image_weights: cli.image_weights,
+            image_diff_metadata: cli.image_diff_metadata,
+            audio_shift_tolerance_seconds: cli
+                .audio_shift_tolerance_seconds
+                .unwrap_or(profile.audio_shift_tolerance_seconds),
+            audio_lufs_tolerance_db: cli.audio_lufs_tolerance_db.unwrap_or(profile.audio_lufs_tolerance_db),
+            audio_spectral_tolerance: cli.audio_spectral_tolerance.unwrap_or(profile.audio_spectral_tolerance),
+            audio_spectrogram_diff_rate_tolerance: cli
+                .audio_spectrogram_diff_rate_tolerance
+                .unwrap_or(profile.audio_spectrogram_diff_rate_tolerance),
+            audio_ignore_gain: cli.audio_ignore_gain,
+            audio_correlation_threshold: cli.audio_correlation_threshold,
+            audio_hop_size: cli
+                .audio_hop_size
+                .map_or(semdiff_differ_audio::DEFAULT_FFT_HOP_SIZE, usize::from),
+            audio_colors: semdiff_differ_audio::AudioColors {
+                waveform: cli.audio_waveform_color,
+                spectrogram: cli.audio_spectrogram_color,
+                spectrogram_diff: cli.audio_diff_color,
+            },
+            audio_axes: cli.audio_axes,
+            audio_minimal_unchanged: cli.audio_minimal_unchanged,
+            audio_dump_data: cli.audio_dump_data.clone(),
+            audio_range: cli
+                .audio_range
+                .as_deref()
+                .and_then(|range| semdiff_differ_audio::AudioRange::new(range[0], range[1])),
+            audio_compare_tags: cli.audio_compare_tags,
+            memory_budget_mb: cli.memory_budget_mb,
+            open_file_limit: cli
+                .max_open_files
+                .and_then(|limit| OpenFileLimit::new(usize::try_from(limit).unwrap_or(usize::MAX))),
+            // Diff images, waveforms, and spectrograms are only shown in the HTML report.
+            render_visuals: if cli.output.is_some() || cli.format.is_some() {
+                matches!(
+                    output_target(cli.output.clone(), cli.format.as_deref()),
+                    OutputKind::Html(_)
+                )
+            } else {
+                cli.output_html.is_some()
+            },
         }
     }
 }
 
 struct DiffCalculators {
     json: semdiff_differ_json::JsonDiffCalculator,
+    dom: semdiff_differ_dom::DomDiffCalculator,
+    keyvalue: semdiff_differ_keyvalue::KeyValueDiffCalculator,
     text: semdiff_differ_text::TextDiffCalculator,
     audio: semdiff_differ_audio::AudioDiffCalculator,
     image: semdiff_differ_image::ImageDiffCalculator,
+    sqlite: semdiff_differ_sqlite::SqliteDiffCalculator,
+    protobuf: semdiff_differ_protobuf::ProtobufDiffCalculator,
     binary: semdiff_differ_binary::BinaryDiffCalculator,
 }
 
@@ -103,15 +661,122 @@ enum OutputKind {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    if cli.list_formats {
+        return Ok(list_formats(io::stdout().lock())?);
+    }
+    if let Some(&[start, end]) = cli.audio_range.as_deref()
+        && semdiff_differ_audio::AudioRange::new(start, end).is_none()
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--audio-range START must be at least 0 and less than END",
+            )
+            .exit();
+    }
+    if let Some(&[columns, rows]) = cli.image_tiles.as_deref()
+        && semdiff_differ_image::ImageTiles::new(columns, rows).is_none()
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--image-tiles COLS and ROWS must be at least 1",
+            )
+            .exit();
+    }
+    let sampling = cli.sample_rate.map(|rate| {
+        Sampling::new(rate, cli.seed).unwrap_or_else(|| {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::ValueValidation,
+                    "--sample-rate must be greater than 0 and at most 1",
+                )
+                .exit()
+        })
+    });
+    let diff_options = diff_options(&cli, sampling);
+    if let Some(manifest) = &cli.batch {
+        return batch::run(&cli, manifest, &diff_options);
+    }
+    let (Some(expected_root), Some(actual_root)) = (cli.expected.clone(), cli.actual.clone()) else {
+        unreachable!("EXPECTED and ACTUAL are required without --list-formats or --batch");
+    };
+    if cli.compare_reports {
+        return compare_reports(&expected_root, &actual_root);
+    }
+    if cli.update && !(expected_root.is_dir() && actual_root.is_dir()) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--update needs EXPECTED and ACTUAL to be directories",
+            )
+            .exit();
+    }
+    if (actual_root.is_file() || remote_url(&actual_root).is_some())
+        && (cli.collect_diffs.is_some() || !cli.candidates.is_empty())
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--collect-diffs and --actual need ACTUAL to be a directory",
+            )
+            .exit();
+    }
     let diff_config = DiffConfig::from_cli(&cli);
-    let expected = FsNode::new_root(cli.expected);
-    let actual = FsNode::new_root(cli.actual);
+    let updates = cli
+        .update
+        .then(|| ExpectedUpdates::new().with_name_matching(diff_options.name_matching()));
+    let tally = cli.allow_missing.is_some().then(ChangeTally::new);
+    let collector = cli
+        .collect_diffs
+        .clone()
+        .map(|target| ChangedFileCollector::new(&actual_root, target).with_name_matching(diff_options.name_matching()));
+    let (expected, actual) = build_trees(
+        &cli,
+        &diff_config,
+        expected_root.clone(),
+        actual_root.clone(),
+        cli.candidates.clone(),
+    )?;
+    if cli.names_only {
+        let (expected, actual) = (expected.with_names_only(true), actual.with_names_only(true));
+        let report = CountChanges::new(NameListReport::new(io::stdout())).with_tally(tally.as_ref());
+        let summary = compare(cli.keep_going, expected, actual, &[], report, &diff_options)?;
+        write_verdict(cli.verdict_json.as_deref(), summary, cli.allow_missing)?;
+        return check_allow_missing(cli.allow_missing, tally.as_ref());
+    }
     macro_rules! run {
-        ($report:expr) => {{
-            let diff = construct_diff(&diff_config);
-            semdiff_core::calc_diff(expected, actual, &diff, $report)?;
+        ($report:expr) => {
+            run!($report, &diff_options)
+        };
+        ($report:expr, $options:expr) => {{
+            let timings = DifferTimings::new().with_entries(cli.report_top_offenders.is_some());
+            let diff = construct_diff(&diff_config, &timings);
+            let report = RecordUpdates::new(CollectChanged::new($report).with_collector(collector))
+                .with_updates(updates.as_ref());
+            let report = CountChanges::new(report).with_tally(tally.as_ref());
+            let summary = compare(cli.keep_going, expected, actual, &diff, report, $options)?;
+            write_verdict(cli.verdict_json.as_deref(), summary, cli.allow_missing)?;
+            if cli.profile_types {
+                timings.write_summary(io::stderr())?;
+            }
+            if let Some(count) = cli.report_top_offenders {
+                timings.write_top_entries(io::stderr(), count)?;
+            }
         }};
     }
+    let diff_score =
+        (cli.diff_score || cli.diff_score_weights.is_some()).then(|| cli.diff_score_weights.unwrap_or_default());
+    let ndjson = cli.output_ndjson.is_some();
+    let borderline_band = cli.borderline_band;
+    let paginate_by_dir = cli.html_paginate_by_dir;
+    let report_precision = cli.report_precision;
+    let html_changes_only = cli.html_changes_only;
+    let json_flush_interval = cli.json_flush_interval.map(Duration::from_millis);
+    // Lines on stdout are usually read as they come, so they are not held back by default.
+    let stdout_flush_interval = Some(json_flush_interval.unwrap_or(Duration::ZERO));
+    let stat = cli.stat;
+    let by_type = cli.by_type;
     #[track_caller]
     fn create_file(path: impl AsRef<Path>) -> File {
         File::create_new(path).expect("Failed to create output file")
@@ -121,72 +786,379 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let output_kind = output_target(cli.output.clone(), cli.format.as_deref());
         match output_kind {
             OutputKind::Html(path) => {
-                run!(HtmlReport::new(path));
+                run!(html_report(path, borderline_band, paginate_by_dir, html_changes_only));
             }
             OutputKind::JsonToFile(path) => {
-                run!(JsonReport::new(create_file(path)));
+                run!(json_report(
+                    create_file(path),
+                    diff_score,
+                    false,
+                    borderline_band,
+                    report_precision,
+                    json_flush_interval
+                ));
             }
             OutputKind::JsonToStdout => {
-                run!(JsonReport::new(io::stdout()));
+                run!(json_report(
+                    io::stdout(),
+                    diff_score,
+                    false,
+                    borderline_band,
+                    report_precision,
+                    stdout_flush_interval
+                ));
             }
             OutputKind::Summary => {
-                run!(SummaryReport::new(io::stdout()));
+                run!(summary_report(io::stdout(), diff_score, stat, by_type));
             }
         }
+    } else if cli.tui && io::stdout().is_terminal() {
+        let (session, lines) = TuiSession::start();
+        let options = diff_options.clone().with_progress(Some(session.progress()));
+        run!(
+            json_report(
+                lines,
+                diff_score,
+                true,
+                borderline_band,
+                report_precision,
+                Some(Duration::ZERO)
+            ),
+            &options
+        );
+        session.wait()?;
+    } else if cli.numstat {
+        let report = JsonReport::new_numstat(BufWriter::new(io::stdout()));
+        match report_precision {
+            Some(decimals) => run!(report.with_precision(decimals)),
+            None => run!(report),
+        }
     } else {
-        match (cli.output_json, cli.output_html, cli.silent) {
+        match (cli.output_json.or(cli.output_ndjson), cli.output_html, cli.silent) {
             (Some(output_json), output_html, silent) if output_json.as_path() == "-" => {
                 if silent {
                     eprintln!("WARNING: --silent is ignored when outputting to stdout");
                 }
                 match output_html {
-                    Some(output_html) => run!((JsonReport::new(io::stdout()), HtmlReport::new(output_html))),
-                    None => run!(JsonReport::new(io::stdout())),
+                    Some(output_html) => run!((
+                        json_report(
+                            io::stdout(),
+                            diff_score,
+                            ndjson,
+                            borderline_band,
+                            report_precision,
+                            stdout_flush_interval
+                        ),
+                        html_report(output_html, borderline_band, paginate_by_dir, html_changes_only)
+                    )),
+                    None => run!(json_report(
+                        io::stdout(),
+                        diff_score,
+                        ndjson,
+                        borderline_band,
+                        report_precision,
+                        stdout_flush_interval
+                    )),
                 }
             }
             (Some(output_json), Some(output_html), false) => run!((
-                (JsonReport::new(create_file(output_json)), HtmlReport::new(output_html)),
-                SummaryReport::new(io::stdout())
+                (
+                    json_report(
+                        create_file(output_json),
+                        diff_score,
+                        ndjson,
+                        borderline_band,
+                        report_precision,
+                        json_flush_interval
+                    ),
+                    html_report(output_html, borderline_band, paginate_by_dir, html_changes_only)
+                ),
+                summary_report(io::stdout(), diff_score, stat, by_type)
             )),
             (Some(output_json), None, false) => run!((
-                JsonReport::new(create_file(output_json)),
-                SummaryReport::new(io::stdout())
+                json_report(
+                    create_file(output_json),
+                    diff_score,
+                    ndjson,
+                    borderline_band,
+                    report_precision,
+                    json_flush_interval
+                ),
+                summary_report(io::stdout(), diff_score, stat, by_type)
             )),
-            (None, Some(output_html), false) => run!((HtmlReport::new(output_html), SummaryReport::new(io::stdout()))),
-            (None, None, false) => run!(SummaryReport::new(io::stdout())),
+            (None, Some(output_html), false) => {
+                run!((
+                    html_report(output_html, borderline_band, paginate_by_dir, html_changes_only),
+                    summary_report(io::stdout(), diff_score, stat, by_type)
+                ))
+            }
+            (None, None, false) => run!(summary_report(io::stdout(), diff_score, stat, by_type)),
             (Some(output_json), Some(output_html), true) => {
-                run!((JsonReport::new(create_file(output_json)), HtmlReport::new(output_html)))
+                run!((
+                    json_report(
+                        create_file(output_json),
+                        diff_score,
+                        ndjson,
+                        borderline_band,
+                        report_precision,
+                        json_flush_interval
+                    ),
+                    html_report(output_html, borderline_band, paginate_by_dir, html_changes_only)
+                ))
+            }
+            (Some(output_json), None, true) => run!(json_report(
+                create_file(output_json),
+                diff_score,
+                ndjson,
+                borderline_band,
+                report_precision,
+                json_flush_interval
+            )),
+            (None, Some(output_html), true) => run!(html_report(
+                output_html,
+                borderline_band,
+                paginate_by_dir,
+                html_changes_only
+            )),
+            // The verdict is the only output, so the summary is computed and thrown away.
+            (None, None, true) if cli.verdict_json.is_some() => {
+                run!(summary_report(io::sink(), None, false, false))
             }
-            (Some(output_json), None, true) => run!(JsonReport::new(create_file(output_json))),
-            (None, Some(output_html), true) => run!(HtmlReport::new(output_html)),
             (None, None, true) => eprintln!(
                 "WARNING: --silent is set but no output target was specified; nothing will be processed or output"
             ),
         }
     }
+    if let Some(updates) = &updates {
+        update_expected(updates, &expected_root, &actual_root, cli.yes)?;
+    }
+    check_allow_missing(cli.allow_missing, tally.as_ref())
+}
+
+/// The pass/fail verdict written by `--verdict-json`.
+#[derive(Serialize)]
+struct RunVerdict {
+    passed: bool,
+    unchanged: usize,
+    modified: usize,
+    added: usize,
+    deleted: usize,
+}
+
+/// Writes the counts of the run and whether it passed to `path` (`-` for stdout). A run passes when
+/// no file was modified and at most `allowed` files were added or deleted, as with
+/// `--allow-missing`; without it, no change is allowed.
+fn write_verdict(
+    path: Option<&Path>,
+    summary: DiffSummary,
+    allowed: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let verdict = RunVerdict {
+        passed: summary.modified == 0 && summary.added + summary.deleted <= allowed.unwrap_or(0),
+        unchanged: summary.unchanged,
+        modified: summary.modified,
+        added: summary.added,
+        deleted: summary.deleted,
+    };
+    let mut out: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(path)?)
+    };
+    serde_json::to_writer(&mut out, &verdict)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Fails the run under `--allow-missing` when a file was modified, or when more files were added
+/// or deleted than allowed.
+fn check_allow_missing(allowed: Option<usize>, tally: Option<&ChangeTally>) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(allowed), Some(tally)) = (allowed, tally) else {
+        return Ok(());
+    };
+    let counts = tally.counts();
+    if counts.modified > 0 {
+        return Err(format!("{} files modified", counts.modified).into());
+    }
+    if counts.missing() > allowed {
+        return Err(format!(
+            "{} files added and {} deleted, more than the {allowed} allowed by --allow-missing",
+            counts.added, counts.deleted
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Lists the changes recorded by `--update` on stderr and, once confirmed, writes them back to
+/// `expected`.
+fn update_expected(
+    updates: &ExpectedUpdates,
+    expected: &Path,
+    actual: &Path,
+    confirmed: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if updates.is_empty() {
+        eprintln!("Nothing to update in {}", expected.display());
+        return Ok(());
+    }
+    let changes = updates.changes();
+    eprintln!("Changes to write to {}:", expected.display());
+    for (name, kind) in &changes {
+        let sign = match kind {
+            UpdateKind::Modified => '~',
+            UpdateKind::Added => '+',
+            UpdateKind::Deleted => '-',
+        };
+        eprintln!("{sign} {name}");
+    }
+    if !confirmed {
+        if !io::stdin().is_terminal() {
+            return Err("--update needs confirmation; pass --yes to update without a prompt".into());
+        }
+        eprint!("Update {} files? [y/N] ", changes.len());
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("Not updated");
+            return Ok(());
+        }
+    }
+    updates.apply(expected, actual)?;
+    eprintln!("Updated {} files in {}", changes.len(), expected.display());
+    Ok(())
+}
+
+/// Prints the kinds of files each differ accepts, in the default differ order, with the extensions
+/// that the file tree gives each kind when the content does not tell.
+fn list_formats(mut out: impl Write) -> io::Result<()> {
+    let differs: [(&str, Vec<&str>, Option<&str>); 9] = [
+        ("json", semdiff_differ_json::supported_kinds(), None),
+        ("dom", semdiff_differ_dom::supported_kinds(), None),
+        (
+            "keyvalue",
+            semdiff_differ_keyvalue::supported_kinds(),
+            Some("files named .env, .env.*, *.env, or *.properties"),
+        ),
+        (
+            "text",
+            semdiff_differ_text::supported_kinds(),
+            Some("and any other file that is printable UTF-8"),
+        ),
+        (
+            "audio",
+            semdiff_differ_audio::supported_kinds(),
+            Some("any kind that symphonia can decode"),
+        ),
+        ("image", semdiff_differ_image::supported_kinds(), None),
+        ("sqlite", semdiff_differ_sqlite::supported_kinds(), None),
+        (
+            "protobuf",
+            semdiff_differ_protobuf::supported_kinds(),
+            Some("files named *.pb or *.binpb, with --protobuf-descriptor-set"),
+        ),
+        ("binary", Vec::new(), Some("any file")),
+    ];
+    debug_assert!(differs.iter().map(|(name, ..)| *name).eq(DIFFER_NAMES));
+    for (name, kinds, note) in differs {
+        writeln!(out, "{name}")?;
+        for kind in kinds {
+            let extensions = mime_guess::get_mime_extensions_str(kind)
+                .unwrap_or_default()
+                .iter()
+                .filter(|extension| mime_guess::from_ext(extension).first_raw() == Some(kind))
+                .map(|extension| format!(".{extension}"))
+                .collect::<Vec<_>>();
+            if extensions.is_empty() {
+                writeln!(out, "  {kind}")?;
+            } else {
+                writeln!(out, "  {kind:<32} {}", extensions.join(" "))?;
+            }
+        }
+        if let Some(note) = note {
+            writeln!(out, "  ({note})")?;
+        }
+    }
+    Ok(())
+}
+
+fn compare_reports(baseline: &Path, current: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fn read_report(path: &Path, which: &'static str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("failed to open report {}: {err}", path.display()))?;
+        let report = ReportDrift::parse_report(&text, which).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(report)
+    }
+    let drift = ReportDrift::from_reports(&read_report(baseline, "baseline")?, &read_report(current, "current")?)?;
+    drift.write_summary(io::stdout())?;
     Ok(())
 }
 
 fn build_diff_calculators(config: &DiffConfig) -> DiffCalculators {
+    let memory_budget = config.memory_budget_mb.map_or_else(MemoryBudget::unlimited, |mb| {
+        MemoryBudget::new(mb.saturating_mul(1024 * 1024))
+    });
+    let json = semdiff_differ_json::JsonDiffCalculator::new(
+        config.json_ignore_object_key_order,
+        config.json_ignore_path.clone(),
+    )
+    .with_round_decimals(config.json_round_decimals)
+    .with_max_depth(config.json_max_depth)
+    .with_arrays_as_multisets(config.json_array_as_multiset)
+    .with_unordered_paths(config.json_unordered_path.clone())
+    .with_shape_rules(config.json_match_shape.clone())
+    .with_document_matching(config.json_document_matching.clone());
     DiffCalculators {
-        json: semdiff_differ_json::JsonDiffCalculator::new(
-            config.json_ignore_object_key_order,
-            config.json_ignore_path.clone(),
-        ),
-        text: semdiff_differ_text::TextDiffCalculator,
+        protobuf: semdiff_differ_protobuf::ProtobufDiffCalculator::default()
+            .with_schema(config.protobuf_schema.clone())
+            .with_json_options(json.clone())
+            .with_unordered_repeated(config.protobuf_unordered_repeated),
+        json,
+        dom: semdiff_differ_dom::DomDiffCalculator,
+        keyvalue: semdiff_differ_keyvalue::KeyValueDiffCalculator,
+        text: semdiff_differ_text::TextDiffCalculator::default().with_semantic(config.text_semantic),
         audio: semdiff_differ_audio::AudioDiffCalculator::new(
             config.audio_shift_tolerance_seconds,
             config.audio_lufs_tolerance_db,
             config.audio_spectral_tolerance,
             config.audio_spectrogram_diff_rate_tolerance,
-        ),
-        image: semdiff_differ_image::ImageDiffCalculator::new(config.image_max_distance, config.image_max_diff_ratio),
-        binary: semdiff_differ_binary::BinaryDiffCalculator,
+        )
+        .with_hop_size(config.audio_hop_size)
+        .with_ignore_gain(config.audio_ignore_gain)
+        .with_correlation_threshold(config.audio_correlation_threshold)
+        .with_visuals(config.render_visuals)
+        .with_memory_budget(memory_budget.clone())
+        .with_range(config.audio_range)
+        .with_compare_tags(config.audio_compare_tags)
+        .with_raw_data(config.audio_dump_data.is_some()),
+        image: semdiff_differ_image::ImageDiffCalculator::new(config.image_max_distance, config.image_max_diff_ratio)
+            .with_max_diff_region(config.image_max_diff_region)
+            .with_tiles(config.image_tiles)
+            .with_color_space(config.image_colorspace)
+            .with_channel_weights(config.image_weights)
+            .with_metadata(config.image_diff_metadata)
+            .with_visuals(config.render_visuals)
+            .with_memory_budget(memory_budget),
+        sqlite: semdiff_differ_sqlite::SqliteDiffCalculator::default()
+            .with_max_row_changes(config.sqlite_max_row_changes),
+        binary: semdiff_differ_binary::BinaryDiffCalculator::default()
+            .with_ignore_ranges(config.binary_ignore_range.clone())
+            .with_fallback(config.binary_fallback)
+            .with_normalize_line_endings(config.binary_normalize_line_endings),
     }
 }
 
-fn construct_diff<R: Sync>(config: &DiffConfig) -> Vec<Box<dyn DiffReport<FileLeaf, R>>>
+fn construct_diff<R: Reporter + Sync>(
+    config: &DiffConfig,
+    timings: &DifferTimings,
+) -> Vec<Box<dyn DiffReport<FileLeaf, R>>>
 where
+    semdiff_differ_dom::DomDiffReporter:
+        DetailReporter<<semdiff_differ_dom::DomDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
+    semdiff_differ_keyvalue::KeyValueDiffReporter: DetailReporter<<semdiff_differ_keyvalue::KeyValueDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
     semdiff_differ_text::TextDiffReporter:
         DetailReporter<<semdiff_differ_text::TextDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
     semdiff_differ_json::JsonDiffReporter:
@@ -195,30 +1167,288 @@ where
         DetailReporter<<semdiff_differ_audio::AudioDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
     semdiff_differ_image::ImageDiffReporter:
         DetailReporter<<semdiff_differ_image::ImageDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
+    semdiff_differ_sqlite::SqliteDiffReporter:
+        DetailReporter<<semdiff_differ_sqlite::SqliteDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
+    semdiff_differ_protobuf::ProtobufDiffReporter: DetailReporter<<semdiff_differ_protobuf::ProtobufDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
     semdiff_differ_binary::BinaryDiffReporter:
         DetailReporter<<semdiff_differ_binary::BinaryDiffCalculator as DiffCalculator<FileLeaf>>::Diff, FileLeaf, R>,
 {
     let DiffCalculators {
         json,
+        dom,
+        keyvalue,
         text,
         audio,
         image,
+        sqlite,
+        protobuf,
         binary,
     } = build_diff_calculators(config);
     vec![
-        Box::new(DiffAndReport::new(json, semdiff_differ_json::JsonDiffReporter)) as Box<dyn DiffReport<FileLeaf, R>>,
-        Box::new(DiffAndReport::new(text, semdiff_differ_text::TextDiffReporter)) as Box<dyn DiffReport<FileLeaf, R>>,
-        Box::new(DiffAndReport::new(
-            audio,
-            semdiff_differ_audio::AudioDiffReporter::default(),
+        Box::new(timings.wrap(
+            "json",
+            DiffAndReport::new(
+                json,
+                semdiff_differ_json::JsonDiffReporter::default().with_syntax_highlight(config.json_highlight),
+            ),
         )) as Box<dyn DiffReport<FileLeaf, R>>,
-        Box::new(DiffAndReport::new(image, semdiff_differ_image::ImageDiffReporter))
+        Box::new(timings.wrap("dom", DiffAndReport::new(dom, semdiff_differ_dom::DomDiffReporter)))
             as Box<dyn DiffReport<FileLeaf, R>>,
-        Box::new(DiffAndReport::new(binary, semdiff_differ_binary::BinaryDiffReporter))
+        Box::new(timings.wrap(
+            "keyvalue",
+            DiffAndReport::new(keyvalue, semdiff_differ_keyvalue::KeyValueDiffReporter),
+        )) as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(timings.wrap("text", DiffAndReport::new(text, semdiff_differ_text::TextDiffReporter)))
             as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(
+            timings.wrap(
+                "audio",
+                DiffAndReport::new(
+                    audio,
+                    semdiff_differ_audio::AudioDiffReporter::new()
+                        .with_hop_size(config.audio_hop_size)
+                        .with_colors(config.audio_colors)
+                        .with_axes(config.audio_axes)
+                        .with_minimal_unchanged(config.audio_minimal_unchanged)
+                        .with_data_dump(
+                            config
+                                .audio_dump_data
+                                .clone()
+                                .map(semdiff_differ_audio::dump::AudioDataDump::new),
+                        ),
+                ),
+            ),
+        ) as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(timings.wrap(
+            "image",
+            DiffAndReport::new(image, semdiff_differ_image::ImageDiffReporter),
+        )) as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(timings.wrap(
+            "sqlite",
+            DiffAndReport::new(sqlite, semdiff_differ_sqlite::SqliteDiffReporter),
+        )) as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(
+            timings.wrap(
+                "protobuf",
+                DiffAndReport::new(
+                    protobuf,
+                    semdiff_differ_protobuf::ProtobufDiffReporter::default()
+                        .with_schema(config.protobuf_schema.clone())
+                        .with_syntax_highlight(config.json_highlight),
+                ),
+            ),
+        ) as Box<dyn DiffReport<FileLeaf, R>>,
+        Box::new(timings.wrap(
+            "binary",
+            DiffAndReport::new(
+                binary,
+                semdiff_differ_binary::BinaryDiffReporter::default().with_fallback(config.binary_fallback),
+            ),
+        )) as Box<dyn DiffReport<FileLeaf, R>>,
     ]
 }
 
+/// Reads `--protobuf-descriptor-set`, exiting with a usage error if it cannot be read or lacks
+/// `--protobuf-message`.
+fn protobuf_schema(cli: &Cli) -> Option<semdiff_differ_protobuf::ProtobufSchema> {
+    let path = cli.protobuf_descriptor_set.as_deref()?;
+    let message = cli.protobuf_message.as_deref()?;
+    let schema = fs::read(path).map_err(|error| error.to_string()).and_then(|bytes| {
+        semdiff_differ_protobuf::ProtobufSchema::from_descriptor_set(&bytes, message).map_err(|error| error.to_string())
+    });
+    match schema {
+        Ok(schema) => Some(schema),
+        Err(error) => Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                format!("--protobuf-descriptor-set {}: {error}", path.display()),
+            )
+            .exit(),
+    }
+}
+
+/// Compares the trees, stopping at the first error unless `--keep-going` is set, in which case
+/// every error is printed to stderr once the reports are written.
+fn compare<R>(
+    keep_going: bool,
+    expected: FsNode,
+    actual: FsNode,
+    diff: &[Box<dyn DiffReport<FileLeaf, R>>],
+    report: R,
+    options: &DiffOptions,
+) -> Result<DiffSummary, Box<dyn std::error::Error>>
+where
+    R: Reporter + Sync,
+{
+    if !keep_going {
+        return Ok(semdiff_core::calc_diff_with_options(
+            expected, actual, diff, report, options,
+        )?);
+    }
+    match semdiff_core::calc_diff_collect(expected, actual, diff, report, options) {
+        Ok(summary) => Ok(summary),
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("error: {error}");
+            }
+            Err(format!("{} errors while comparing", errors.len()).into())
+        }
+    }
+}
+
+/// The environment variable whose value is sent as the `Authorization` header when fetching a
+/// remote tree, such as `Bearer <token>`.
+const AUTHORIZATION_VAR: &str = "SEMDIFF_HTTP_AUTHORIZATION";
+
+/// The URL a root is given as, if it is one rather than a path.
+fn remote_url(root: &Path) -> Option<&str> {
+    root.to_str().filter(|root| root.contains("://"))
+}
+
+/// Roots the two trees at `expected_root` and `actual_root`, applying the `.semdiffignore` files
+/// of every root, `--exclude`, and the size limits.
+fn build_trees(
+    cli: &Cli,
+    diff_config: &DiffConfig,
+    expected_root: PathBuf,
+    actual_root: PathBuf,
+    candidates: Vec<PathBuf>,
+) -> Result<(FsNode, FsNode), Box<dyn std::error::Error>> {
+    let size_filter =
+        (cli.min_size.is_some() || cli.max_size.is_some()).then(|| FileSizeFilter::new(cli.min_size, cli.max_size));
+    let mut ignore_rules = IgnoreRules::read_file(&expected_root.join(IGNORE_FILE_NAME))?;
+    for actual in iter::once(&actual_root).chain(&candidates) {
+        ignore_rules.extend(IgnoreRules::read_file(&actual.join(IGNORE_FILE_NAME))?);
+    }
+    for pattern in &cli.exclude {
+        ignore_rules.add_pattern(pattern);
+    }
+    let archive_limits = cli
+        .archives
+        .then(|| ArchiveLimits::new(cli.archive_max_depth, cli.archive_max_entry_size))
+        .flatten();
+    // A zip archive given as a root is compared as the directory it holds, and any other file as
+    // a tree of that one file. Two files are both named after EXPECTED so that they pair up.
+    let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into_owned());
+    let expected_file_name = expected_root.is_file().then(|| file_name(&expected_root)).flatten();
+    let root = |path: PathBuf| {
+        if let Some(url) = remote_url(&path) {
+            FsNode::new_remote_root(url, env::var(AUTHORIZATION_VAR).ok())
+        } else if path.is_file() && is_archive_name(&path.to_string_lossy()) {
+            FsNode::new_archive_root(path)
+        } else if path.is_file() {
+            let name = expected_file_name
+                .clone()
+                .or_else(|| file_name(&path))
+                .unwrap_or_default();
+            Ok(FsNode::new_file_root(path, name))
+        } else {
+            Ok(FsNode::new_root(path))
+        }
+    };
+    let (expected, actual) = (root(expected_root)?, root(actual_root)?);
+    let (expected, actual) = match size_filter {
+        Some(size_filter) => (
+            expected.with_size_filter(size_filter),
+            actual.with_size_filter(size_filter),
+        ),
+        None => (expected, actual),
+    };
+    Ok((
+        expected
+            .with_ignore_rules(ignore_rules.clone())
+            .with_open_file_limit(diff_config.open_file_limit.clone())
+            .with_archives(archive_limits),
+        actual
+            .with_ignore_rules(ignore_rules)
+            .with_archives(archive_limits)
+            .with_candidates(candidates)
+            .with_open_file_limit(diff_config.open_file_limit.clone()),
+    ))
+}
+
+fn diff_options(cli: &Cli, sampling: Option<Sampling>) -> DiffOptions {
+    DiffOptions::new()
+        .with_empty_nodes(cli.report_empty_dirs)
+        .with_names_only(cli.names_only)
+        .with_rename_threshold(cli.rename_threshold)
+        .with_breadth_first(cli.breadth_first)
+        .with_normalized_names(cli.normalize_names)
+        .with_case_folded_names(cli.ignore_name_case)
+        .with_format_regressions(cli.report_format_regressions)
+        .with_strict_extensions(cli.strict_extensions)
+        .with_identical_fast_path(cli.identical_fast_path)
+        .with_sampling(sampling)
+        .with_max_in_flight(cli.max_in_flight)
+        .with_differ_order(cli.differ_order.iter().cloned().fold(
+            DifferOrder::new(),
+            |order, rule| match rule.extension {
+                Some(extension) => order.with_extension(&extension, rule.differs),
+                None => order.with_fallback(rule.differs),
+            },
+        ))
+}
+
+fn json_report<W: Write>(
+    writer: W,
+    diff_score: Option<DiffScoreWeights>,
+    ndjson: bool,
+    borderline_band: Option<f64>,
+    precision: Option<u8>,
+    flush_interval: Option<Duration>,
+) -> JsonReport<BufWriter<W>> {
+    let writer = BufWriter::new(writer);
+    let report = if ndjson {
+        JsonReport::new_ndjson(writer)
+    } else {
+        JsonReport::new(writer)
+    };
+    let report = match flush_interval {
+        Some(interval) => report.with_flush_interval(interval),
+        None => report,
+    };
+    let report = match precision {
+        Some(decimals) => report.with_precision(decimals),
+        None => report,
+    };
+    let report = match diff_score {
+        Some(weights) => report.with_diff_score(weights),
+        None => report,
+    };
+    match borderline_band {
+        Some(band) => report.with_borderline_band(band),
+        None => report,
+    }
+}
+
+fn html_report(path: PathBuf, borderline_band: Option<f64>, paginate_by_dir: bool, changes_only: bool) -> HtmlReport {
+    let report = HtmlReport::new(path);
+    let report = if paginate_by_dir {
+        report.with_paginate_by_dir()
+    } else {
+        report
+    };
+    let report = if changes_only {
+        report.with_changes_only()
+    } else {
+        report
+    };
+    match borderline_band {
+        Some(band) => report.with_borderline_band(band),
+        None => report,
+    }
+}
+
+fn summary_report<W>(writer: W, diff_score: Option<DiffScoreWeights>, stat: bool, by_type: bool) -> SummaryReport<W> {
+    let report = SummaryReport::new(writer);
+    let report = if stat { report.with_per_type_table() } else { report };
+    let report = if by_type { report.with_type_breakdown() } else { report };
+    match diff_score {
+        Some(weights) => report.with_diff_score(weights),
+        None => report,
+    }
+}
+
 fn output_target(output: Option<PathBuf>, format: Option<&str>) -> OutputKind {
     match format {
         Some("json") => output.map_or(OutputKind::JsonToStdout, OutputKind::JsonToFile),
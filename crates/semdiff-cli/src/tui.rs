@@ -0,0 +1,405 @@
+//! `--tui`: a terminal view of the compared tree that fills in each file's status as the diff runs
+//! and shows the details of the selected file. Files show up as running from the [`Progress`] of the
+//! run, and get their status from the NDJSON lines of the JSON report.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use semdiff_core::{Progress, ProgressKind};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long the view waits for a key before it picks up new lines.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What the view is told about the run.
+enum Message {
+    Line(Value),
+    Progress(String, ProgressKind),
+    Finished,
+}
+
+/// Hands each complete line written to it to the view. Dropping it tells the view that the run has
+/// finished.
+pub(crate) struct LineSender {
+    pending: Vec<u8>,
+    sender: Sender<Message>,
+}
+
+impl Write for LineSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line = self.pending.drain(..=end).collect::<Vec<_>>();
+            if let Ok(value) = serde_json::from_slice(&line) {
+                // The view only goes away when the process is about to exit.
+                let _ = self.sender.send(Message::Line(value));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LineSender {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Finished);
+    }
+}
+
+/// The view running on its own thread while the diff writes to the [`LineSender`].
+pub(crate) struct TuiSession {
+    handle: Option<JoinHandle<io::Result<()>>>,
+    abort: Arc<AtomicBool>,
+    sender: Sender<Message>,
+}
+
+impl TuiSession {
+    pub(crate) fn start() -> (TuiSession, LineSender) {
+        let (sender, receiver) = mpsc::channel();
+        let abort = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let abort = Arc::clone(&abort);
+            move || {
+                let mut terminal = ratatui::init();
+                let result = TuiApp::new(receiver).run(&mut terminal, &abort);
+                ratatui::restore();
+                if let Ok(Exit::Interrupted) = result {
+                    eprintln!("semdiff: interrupted");
+                    std::process::exit(130);
+                }
+                result.map(drop)
+            }
+        });
+        let session = TuiSession {
+            handle: Some(handle),
+            abort,
+            sender: sender.clone(),
+        };
+        let sender = LineSender {
+            pending: Vec::new(),
+            sender,
+        };
+        (session, sender)
+    }
+
+    /// Shows each file as running from when it is scheduled until its line is written.
+    pub(crate) fn progress(&self) -> Progress {
+        let sender = self.sender.clone();
+        Progress::new(move |event| {
+            let _ = sender.send(Message::Progress(event.path.to_owned(), event.kind));
+        })
+    }
+
+    /// Waits until the user closes the view after the run.
+    pub(crate) fn wait(mut self) -> io::Result<()> {
+        self.join()
+    }
+
+    fn join(&mut self) -> io::Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("the terminal view panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TuiSession {
+    /// Closes the view without waiting for the user when the run fails, so that the terminal is
+    /// restored before the error is printed.
+    fn drop(&mut self) {
+        self.abort.store(true, Ordering::Relaxed);
+        let _ = self.join();
+    }
+}
+
+enum Exit {
+    Closed,
+    /// Closed by the user before the run finished.
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Scheduled and being compared.
+    Running,
+    /// Compared, but its line has not been written yet.
+    Compared,
+    Unchanged,
+    Modified,
+    Added,
+    Deleted,
+    Skipped,
+    TypeChange,
+}
+
+impl Status {
+    fn marker(self) -> Span<'static> {
+        match self {
+            Status::Running => Span::styled("… ", Style::new().fg(Color::Cyan)),
+            Status::Compared => Span::styled("· ", Style::new().fg(Color::DarkGray)),
+            Status::Unchanged => Span::styled("  ", Style::new()),
+            Status::Modified => Span::styled("M ", Style::new().fg(Color::Yellow)),
+            Status::Added => Span::styled("A ", Style::new().fg(Color::Green)),
+            Status::Deleted => Span::styled("D ", Style::new().fg(Color::Red)),
+            Status::Skipped => Span::styled("S ", Style::new().fg(Color::DarkGray)),
+            Status::TypeChange => Span::styled("T ", Style::new().fg(Color::Magenta)),
+        }
+    }
+
+    fn is_change(self) -> bool {
+        !matches!(
+            self,
+            Status::Running | Status::Compared | Status::Unchanged | Status::Skipped
+        )
+    }
+}
+
+struct Entry {
+    status: Status,
+    /// The line of the entry, shown as its details, or `null` until it is written.
+    line: Value,
+}
+
+/// A row of the tree: a directory leading to entries, or an entry.
+struct Row {
+    depth: usize,
+    label: String,
+    /// The path of the entry, or `None` for a directory.
+    name: Option<String>,
+}
+
+struct TuiApp {
+    receiver: Receiver<Message>,
+    entries: BTreeMap<String, Entry>,
+    summary: Option<Value>,
+    finished: bool,
+    changes_only: bool,
+    details_only: bool,
+    list: ListState,
+}
+
+impl TuiApp {
+    fn new(receiver: Receiver<Message>) -> TuiApp {
+        TuiApp {
+            receiver,
+            entries: BTreeMap::new(),
+            summary: None,
+            finished: false,
+            changes_only: false,
+            details_only: false,
+            list: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal, abort: &AtomicBool) -> io::Result<Exit> {
+        loop {
+            if abort.load(Ordering::Relaxed) {
+                return Ok(Exit::Closed);
+            }
+            self.receive();
+            let rows = self.rows();
+            terminal.draw(|frame| self.draw(frame, &rows))?;
+            if !event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let selected = self.list.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Esc if self.details_only => self.details_only = false,
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    return Ok(if self.finished { Exit::Closed } else { Exit::Interrupted });
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.list.select(Some(selected.saturating_sub(1))),
+                KeyCode::Down | KeyCode::Char('j') => self.list.select(Some(selected + 1)),
+                KeyCode::PageUp => self.list.select(Some(selected.saturating_sub(20))),
+                KeyCode::PageDown => self.list.select(Some(selected + 20)),
+                KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+                KeyCode::End | KeyCode::Char('G') => self.list.select(Some(rows.len().saturating_sub(1))),
+                KeyCode::Enter => self.details_only = !self.details_only,
+                KeyCode::Char('c') => {
+                    self.changes_only = !self.changes_only;
+                    self.list.select_first();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Takes in the lines written since the last frame.
+    fn receive(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Line(line)) => self.add_line(line),
+                Ok(Message::Progress(name, kind)) => self.add_progress(name, kind),
+                Err(TryRecvError::Empty) => return,
+                Ok(Message::Finished) | Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn add_line(&mut self, line: Value) {
+        let status = match (line["type"].as_str(), line["status"].as_str()) {
+            (Some("entry"), Some("unchanged")) => Status::Unchanged,
+            (Some("entry"), Some("modified")) => Status::Modified,
+            (Some("entry"), Some("added")) => Status::Added,
+            (Some("entry"), Some("deleted")) => Status::Deleted,
+            (Some("skipped"), _) => Status::Skipped,
+            (Some("type_change"), _) => Status::TypeChange,
+            (Some("summary"), _) => {
+                self.summary = Some(line);
+                return;
+            }
+            _ => return,
+        };
+        if let Some(name) = line["name"].as_str() {
+            self.entries.insert(name.to_owned(), Entry { status, line });
+        }
+    }
+
+    fn add_progress(&mut self, name: String, kind: ProgressKind) {
+        match kind {
+            ProgressKind::Scheduled => {
+                self.entries.entry(name).or_insert(Entry {
+                    status: Status::Running,
+                    line: Value::Null,
+                });
+            }
+            ProgressKind::Completed => {
+                if let Some(entry) = self.entries.get_mut(&name)
+                    && entry.status == Status::Running
+                {
+                    entry.status = Status::Compared;
+                }
+            }
+        }
+    }
+
+    /// The entries as an indented tree, with a row for each directory above them.
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut open: Vec<&str> = Vec::new();
+        let entries = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !self.changes_only || entry.status.is_change());
+        for (name, _) in entries {
+            let mut components = name.split('/').collect::<Vec<_>>();
+            let file = components.pop().unwrap_or_default();
+            let shared = open.iter().zip(&components).take_while(|(a, b)| a == b).count();
+            open.truncate(shared);
+            for directory in &components[shared..] {
+                rows.push(Row {
+                    depth: open.len(),
+                    label: format!("{directory}/"),
+                    name: None,
+                });
+                open.push(directory);
+            }
+            rows.push(Row {
+                depth: open.len(),
+                label: file.to_owned(),
+                name: Some(name.clone()),
+            });
+        }
+        rows
+    }
+
+    fn draw(&mut self, frame: &mut Frame, rows: &[Row]) {
+        if let Some(selected) = self.list.selected()
+            && selected >= rows.len()
+        {
+            self.list.select(Some(rows.len().saturating_sub(1)));
+        }
+        let [header, body, footer] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        frame.render_widget(Paragraph::new(self.header()), header);
+        frame.render_widget(
+            Paragraph::new("↑/↓ move  Enter details  c changes only  q quit").style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+
+        let selected = self
+            .list
+            .selected()
+            .and_then(|index| rows.get(index))
+            .and_then(|row| row.name.as_ref())
+            .and_then(|name| self.entries.get(name));
+        let details = match selected {
+            Some(entry) if entry.line.is_null() => String::from("comparing…"),
+            Some(entry) => serde_json::to_string_pretty(&entry.line).unwrap_or_default(),
+            None => String::new(),
+        };
+        let details = Paragraph::new(details)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title("Details"));
+        if self.details_only {
+            frame.render_widget(details, body);
+            return;
+        }
+        let [tree, side] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(body);
+        let items = rows.iter().map(|row| {
+            let marker = match &row.name {
+                Some(name) => self.entries[name].status.marker(),
+                None => Span::raw("  "),
+            };
+            Line::from(vec![
+                marker,
+                Span::raw("  ".repeat(row.depth)),
+                Span::raw(row.label.as_str()),
+            ])
+        });
+        let list = List::new(items)
+            .block(Block::bordered().title("Files"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree, &mut self.list);
+        frame.render_widget(details, side);
+    }
+
+    fn header(&self) -> Line<'static> {
+        let count = |status: Status| self.entries.values().filter(|entry| entry.status == status).count();
+        let (unchanged, modified, added, deleted) = match &self.summary {
+            Some(summary) => {
+                let count = |key: &str| summary[key].as_u64().unwrap_or(0) as usize;
+                (count("unchanged"), count("modified"), count("added"), count("deleted"))
+            }
+            None => (
+                count(Status::Unchanged),
+                count(Status::Modified),
+                count(Status::Added),
+                count(Status::Deleted),
+            ),
+        };
+        let state = if self.finished {
+            "done".to_owned()
+        } else {
+            format!("comparing… ({} running)", count(Status::Running))
+        };
+        Line::from(format!(
+            "semdiff {state}  {unchanged} unchanged, {modified} modified, {added} added, {deleted} deleted"
+        ))
+        .style(Style::new().add_modifier(Modifier::BOLD))
+    }
+}
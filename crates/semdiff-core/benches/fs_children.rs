@@ -0,0 +1,36 @@
+//! Times `FsNode::children` on a directory of many small files.
+//!
+//! Run with `cargo bench -p semdiff-core --bench fs_children`.
+
+use semdiff_core::NodeTraverse;
+use semdiff_core::fs::FsNode;
+use std::fs;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const FILE_COUNT: usize = 10_000;
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let root = std::env::temp_dir().join(format!("semdiff-core-bench-fs-children-{nanos}"));
+    fs::create_dir(&root).unwrap();
+    for i in 0..FILE_COUNT {
+        fs::write(root.join(format!("file-{i:05}.txt")), format!("content of file {i}\n")).unwrap();
+    }
+
+    let mut total = 0;
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let mut node = FsNode::new_root(root.clone());
+        let children = node.children().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(children.len(), FILE_COUNT);
+        total += children.len();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "fs_children: {FILE_COUNT} files, {:?}/iter ({total} leaves in {ITERATIONS} iterations)",
+        elapsed / ITERATIONS
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
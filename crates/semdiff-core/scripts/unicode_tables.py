@@ -0,0 +1,66 @@
+#!/usr/bin/env python3
+"""Generates src/normalize/tables.rs from the Unicode data bundled with Python.
+
+Usage: python3 scripts/unicode_tables.py > src/normalize/tables.rs
+"""
+
+import unicodedata
+
+
+def rust_char(code):
+    return "'\\u{%x}'" % code
+
+
+def is_hangul_syllable(code):
+    return 0xAC00 <= code < 0xAC00 + 11172
+
+
+def main():
+    decompositions = []
+    compositions = []
+    combining_classes = []
+    for code in range(0x110000):
+        c = chr(code)
+        combining = unicodedata.combining(c)
+        if combining:
+            if combining_classes and combining_classes[-1][1] == code - 1 and combining_classes[-1][2] == combining:
+                combining_classes[-1][1] = code
+            else:
+                combining_classes.append([code, code, combining])
+        if is_hangul_syllable(code):
+            continue
+        decomposition = unicodedata.decomposition(c)
+        if not decomposition or decomposition.startswith("<"):
+            continue
+        parts = [int(part, 16) for part in decomposition.split()]
+        decompositions.append((code, parts[0], parts[1] if len(parts) > 1 else 0))
+        # A primary composite is what NFC produces from its own decomposition; this leaves out
+        # singletons, non-starter decompositions, and the composition exclusions.
+        if len(parts) == 2 and unicodedata.normalize("NFC", chr(parts[0]) + chr(parts[1])) == c:
+            compositions.append((parts[0], parts[1], code))
+    compositions.sort()
+
+    print("// Generated by scripts/unicode_tables.py from Unicode %s; do not edit." % unicodedata.unidata_version)
+    print()
+    print("/// Canonical decompositions by one step: a character, its first part, and its second part or")
+    print("/// `'\\0'` for a singleton. Sorted by character; Hangul syllables are decomposed algorithmically.")
+    print("pub(super) const DECOMPOSITIONS: &[(char, char, char)] = &[")
+    for code, first, second in decompositions:
+        print("    (%s, %s, %s)," % (rust_char(code), rust_char(first), rust_char(second)))
+    print("];")
+    print()
+    print("/// Pairs that canonically compose into a primary composite, sorted by pair.")
+    print("pub(super) const COMPOSITIONS: &[(char, char, char)] = &[")
+    for first, second, code in compositions:
+        print("    (%s, %s, %s)," % (rust_char(first), rust_char(second), rust_char(code)))
+    print("];")
+    print()
+    print("/// Ranges of characters with a non-zero canonical combining class, sorted by start.")
+    print("pub(super) const COMBINING_CLASSES: &[(char, char, u8)] = &[")
+    for start, end, combining in combining_classes:
+        print("    (%s, %s, %d)," % (rust_char(start), rust_char(end), combining))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
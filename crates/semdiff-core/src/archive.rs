@@ -0,0 +1,429 @@
+//! Reading zip archives as directories of a file tree, see [`crate::fs::FsNode::with_archives`].
+//!
+//! Only the central directory is read up front; an entry is decompressed when the directory it is
+//! in is listed. Entries are either stored or deflated, as in practically every zip, jar, and war;
+//! encrypted entries and other compression methods are unreadable.
+
+use crate::fs::FileContent;
+use flate2::read::DeflateDecoder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Extensions of files that are read as archives: zip itself and the formats built on it.
+const ARCHIVE_EXTENSIONS: [&str; 8] = ["zip", "jar", "war", "ear", "aar", "apk", "whl", "nupkg"];
+
+/// Archives nested deeper than this are compared as files by default.
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+/// Entries that decompress to more than this are skipped as too large by default.
+pub const DEFAULT_MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Guards against zip bombs: how deep archives are opened inside each other, and how large an
+/// entry may decompress to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    max_depth: usize,
+    max_entry_size: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        ArchiveLimits {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_entry_size: DEFAULT_MAX_ENTRY_SIZE,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    /// Opens archives up to `max_depth` levels deep, where an archive in the tree itself is at
+    /// level 1; returns `None` when `max_depth` is 0.
+    pub fn new(max_depth: usize, max_entry_size: u64) -> Option<ArchiveLimits> {
+        (max_depth > 0).then_some(ArchiveLimits {
+            max_depth,
+            max_entry_size,
+        })
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn max_entry_size(&self) -> u64 {
+        self.max_entry_size
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("not a zip archive: {0}")]
+    Malformed(&'static str),
+    #[error("entry decompresses to more than the limit")]
+    TooLarge,
+    #[error("entry cannot be read: {0}")]
+    Unreadable(&'static str),
+}
+
+/// Whether the file `name` is read as an archive, by extension.
+pub fn is_archive_name(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, extension)| ARCHIVE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+/// The central directory of a zip archive, over the bytes of the whole archive.
+#[derive(Debug)]
+pub(crate) struct ZipArchive {
+    content: Arc<FileContent>,
+    /// Files by their path in the archive, without a leading `/`.
+    files: BTreeMap<String, ZipEntry>,
+    /// Every directory, explicit or implied by the paths of files, with a trailing `/`.
+    directories: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+struct ZipEntry {
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    local_header_offset: u64,
+}
+
+/// A child of a directory in an archive.
+pub(crate) enum ArchiveChild<'a> {
+    Directory(&'a str),
+    File(&'a str),
+}
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+const FLAG_ENCRYPTED: u16 = 1;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+impl ZipArchive {
+    pub(crate) fn parse(content: Arc<FileContent>) -> Result<ZipArchive, ArchiveError> {
+        let bytes = &content[..];
+        // The end record is 22 bytes, followed by a comment of up to 64 KiB.
+        let search_start = bytes.len().saturating_sub(22 + 0xffff);
+        let end = (search_start..=bytes.len().saturating_sub(22))
+            .rev()
+            .find(|&offset| u32_at(bytes, offset) == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or(ArchiveError::Malformed("no end of central directory"))?;
+        let field = |offset| u16_at(bytes, end + offset).ok_or(ArchiveError::Malformed("truncated end record"));
+        let mut count = u64::from(field(10)?);
+        let mut directory_offset = u64::from(u32_at(bytes, end + 16).unwrap_or(0));
+        if count == 0xffff || directory_offset == 0xffff_ffff {
+            let locator = end
+                .checked_sub(20)
+                .filter(|&locator| u32_at(bytes, locator) == Some(ZIP64_LOCATOR))
+                .ok_or(ArchiveError::Malformed("no zip64 locator"))?;
+            let record = offset_in(bytes, u64_at(bytes, locator + 8))?;
+            if u32_at(bytes, record) != Some(ZIP64_END_OF_CENTRAL_DIRECTORY) {
+                return Err(ArchiveError::Malformed("no zip64 end record"));
+            }
+            count = u64_at(bytes, record + 32).ok_or(ArchiveError::Malformed("truncated zip64 end record"))?;
+            directory_offset =
+                u64_at(bytes, record + 48).ok_or(ArchiveError::Malformed("truncated zip64 end record"))?;
+        }
+        let mut archive = ZipArchive {
+            files: BTreeMap::new(),
+            directories: BTreeSet::new(),
+            content: content.clone(),
+        };
+        let mut offset = match count {
+            0 => 0,
+            _ => offset_in(bytes, Some(directory_offset))?,
+        };
+        for _ in 0..count {
+            let header = bytes
+                .get(offset..offset + 46)
+                .filter(|header| u32_at(header, 0) == Some(CENTRAL_DIRECTORY_HEADER))
+                .ok_or(ArchiveError::Malformed("truncated central directory"))?;
+            let name_len = usize::from(u16_at(header, 28).unwrap());
+            let extra_len = usize::from(u16_at(header, 30).unwrap());
+            let comment_len = usize::from(u16_at(header, 32).unwrap());
+            let name = bytes
+                .get(offset + 46..offset + 46 + name_len)
+                .ok_or(ArchiveError::Malformed("truncated entry name"))?;
+            let extra = bytes
+                .get(offset + 46 + name_len..offset + 46 + name_len + extra_len)
+                .ok_or(ArchiveError::Malformed("truncated extra field"))?;
+            let mut entry = ZipEntry {
+                flags: u16_at(header, 8).unwrap(),
+                method: u16_at(header, 10).unwrap(),
+                crc: u32_at(header, 16).unwrap(),
+                compressed_size: u64::from(u32_at(header, 20).unwrap()),
+                uncompressed_size: u64::from(u32_at(header, 24).unwrap()),
+                local_header_offset: u64::from(u32_at(header, 42).unwrap()),
+            };
+            entry.read_zip64_extra(extra);
+            offset += 46 + name_len + extra_len + comment_len;
+            archive.insert(&String::from_utf8_lossy(name), entry);
+        }
+        Ok(archive)
+    }
+
+    /// Adds an entry under its path with `.` and empty segments removed, along with the
+    /// directories it implies. The first of several entries with one path wins.
+    fn insert(&mut self, name: &str, entry: ZipEntry) {
+        let is_directory = name.ends_with('/');
+        let segments = name
+            .split(['/', '\\'])
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect::<Vec<_>>();
+        let Some((file_name, parents)) = segments.split_last() else {
+            return;
+        };
+        let mut path = String::new();
+        for parent in parents {
+            path.push_str(parent);
+            path.push('/');
+            self.directories.insert(path.clone());
+        }
+        path.push_str(file_name);
+        if is_directory {
+            path.push('/');
+            self.directories.insert(path);
+        } else if !self.directories.contains(&format!("{path}/")) {
+            self.files.entry(path).or_insert(entry);
+        }
+    }
+
+    /// The directories and files directly in `prefix`, which is empty for the root and ends with
+    /// `/` otherwise. A name that is both a file and a directory is a directory.
+    pub(crate) fn children(&self, prefix: &str) -> Vec<ArchiveChild<'_>> {
+        fn direct_child<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+            let name = path.strip_prefix(prefix)?;
+            let name = name.strip_suffix('/').unwrap_or(name);
+            (!name.is_empty() && !name.contains('/')).then_some(name)
+        }
+        let directories = self.directories.range(prefix.to_owned()..);
+        let mut children = directories
+            .take_while(|path| path.starts_with(prefix))
+            .filter_map(|path| direct_child(path, prefix))
+            .map(ArchiveChild::Directory)
+            .collect::<Vec<_>>();
+        let files = self.files.range(prefix.to_owned()..).map(|(path, _)| path);
+        children.extend(
+            files
+                .take_while(|path| path.starts_with(prefix))
+                .filter(|path| !self.directories.contains(&format!("{path}/")))
+                .filter_map(|path| direct_child(path, prefix))
+                .map(ArchiveChild::File),
+        );
+        children
+    }
+
+    /// The size the file at `path` declares it decompresses to.
+    pub(crate) fn declared_size(&self, path: &str) -> u64 {
+        self.files.get(path).map_or(0, |entry| entry.uncompressed_size)
+    }
+
+    /// Decompresses the file at `path`, failing once it exceeds `max_size` bytes whatever size it
+    /// declares.
+    pub(crate) fn read(&self, path: &str, max_size: u64) -> Result<Vec<u8>, ArchiveError> {
+        let entry = self.files.get(path).ok_or(ArchiveError::Unreadable("no such entry"))?;
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(ArchiveError::Unreadable("encrypted"));
+        }
+        if entry.uncompressed_size > max_size {
+            return Err(ArchiveError::TooLarge);
+        }
+        let bytes = &self.content[..];
+        let header =
+            offset_in(bytes, Some(entry.local_header_offset)).map_err(|_| ArchiveError::Unreadable("truncated"))?;
+        if u32_at(bytes, header) != Some(LOCAL_FILE_HEADER) {
+            return Err(ArchiveError::Unreadable("no local file header"));
+        }
+        let (Some(name_len), Some(extra_len)) = (u16_at(bytes, header + 26), u16_at(bytes, header + 28)) else {
+            return Err(ArchiveError::Unreadable("truncated"));
+        };
+        let start = header + 30 + usize::from(name_len) + usize::from(extra_len);
+        let compressed = usize::try_from(entry.compressed_size)
+            .ok()
+            .and_then(|len| bytes.get(start..start.checked_add(len)?))
+            .ok_or(ArchiveError::Unreadable("truncated"))?;
+        let content = match entry.method {
+            METHOD_STORED => compressed.to_vec(),
+            METHOD_DEFLATED => {
+                let mut content = Vec::new();
+                DeflateDecoder::new(compressed)
+                    .take(max_size.saturating_add(1))
+                    .read_to_end(&mut content)
+                    .map_err(|_| ArchiveError::Unreadable("corrupt deflate stream"))?;
+                content
+            }
+            _ => return Err(ArchiveError::Unreadable("unsupported compression method")),
+        };
+        if content.len() as u64 > max_size {
+            return Err(ArchiveError::TooLarge);
+        }
+        let mut crc = flate2::Crc::new();
+        crc.update(&content);
+        if crc.sum() != entry.crc {
+            return Err(ArchiveError::Unreadable("checksum mismatch"));
+        }
+        Ok(content)
+    }
+}
+
+impl ZipEntry {
+    /// Replaces the sizes and offset saturated at `0xffffffff` with their values in the zip64
+    /// extra field, which holds only those, in this order.
+    fn read_zip64_extra(&mut self, mut extra: &[u8]) {
+        while let (Some(id), Some(len)) = (u16_at(extra, 0), u16_at(extra, 2)) {
+            let Some(data) = extra.get(4..4 + usize::from(len)) else {
+                return;
+            };
+            if id == ZIP64_EXTRA_FIELD {
+                let mut values = data.chunks_exact(8).map(|value| u64_at(value, 0).unwrap());
+                for field in [
+                    &mut self.uncompressed_size,
+                    &mut self.compressed_size,
+                    &mut self.local_header_offset,
+                ] {
+                    if *field == 0xffff_ffff {
+                        match values.next() {
+                            Some(value) => *field = value,
+                            None => return,
+                        }
+                    }
+                }
+                return;
+            }
+            extra = &extra[4 + usize::from(len)..];
+        }
+    }
+}
+
+fn offset_in(bytes: &[u8], offset: Option<u64>) -> Result<usize, ArchiveError> {
+    offset
+        .and_then(|offset| usize::try_from(offset).ok())
+        .filter(|&offset| offset < bytes.len())
+        .ok_or(ArchiveError::Malformed("offset out of range"))
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().unwrap()))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().unwrap()))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(offset..offset + 8)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    /// A zip of `(name, content)` entries, deflated when `deflate` is set.
+    pub(crate) fn zip(entries: &[(&str, &[u8])], deflate: bool) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+        for (name, content) in entries {
+            let mut crc = flate2::Crc::new();
+            crc.update(content);
+            let (method, data) = if deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                (METHOD_DEFLATED, encoder.finish().unwrap())
+            } else {
+                (METHOD_STORED, content.to_vec())
+            };
+            let offset = archive.len() as u32;
+            let fields = |out: &mut Vec<u8>| {
+                out.extend(method.to_le_bytes());
+                out.extend([0; 4]);
+                out.extend(crc.sum().to_le_bytes());
+                out.extend((data.len() as u32).to_le_bytes());
+                out.extend((content.len() as u32).to_le_bytes());
+                out.extend((name.len() as u16).to_le_bytes());
+                out.extend([0; 2]);
+            };
+            archive.extend(LOCAL_FILE_HEADER.to_le_bytes());
+            archive.extend([20, 0, 0, 0]);
+            fields(&mut archive);
+            archive.extend(name.as_bytes());
+            archive.extend(&data);
+            directory.extend(CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            fields(&mut directory);
+            // Comment length, disk, and attributes.
+            directory.extend([0; 10]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let directory_offset = archive.len() as u32;
+        archive.extend(&directory);
+        archive.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend([0; 4]);
+        archive.extend((entries.len() as u16).to_le_bytes());
+        archive.extend((entries.len() as u16).to_le_bytes());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend([0; 2]);
+        archive
+    }
+
+    fn parse(bytes: Vec<u8>) -> ZipArchive {
+        ZipArchive::parse(Arc::new(FileContent::Owned(bytes))).unwrap()
+    }
+
+    #[test]
+    fn zip_archive_lists_directories_and_reads_entries() {
+        for deflate in [false, true] {
+            let archive = parse(zip(
+                &[("a.txt", b"hello"), ("./dir/b.txt", b"world"), ("empty/", b"")],
+                deflate,
+            ));
+            let names = |prefix| {
+                archive
+                    .children(prefix)
+                    .into_iter()
+                    .map(|child| match child {
+                        ArchiveChild::Directory(name) => format!("{name}/"),
+                        ArchiveChild::File(name) => name.to_owned(),
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(names(""), ["dir/", "empty/", "a.txt"]);
+            assert_eq!(names("dir/"), ["b.txt"]);
+            assert_eq!(archive.read("dir/b.txt", 5).unwrap(), b"world");
+            assert!(matches!(archive.read("dir/b.txt", 4), Err(ArchiveError::TooLarge)));
+        }
+    }
+
+    #[test]
+    fn zip_archive_rejects_bombs_and_corrupt_entries() {
+        // An entry that declares a small size but decompresses to more is cut off at the limit.
+        let mut bomb = zip(&[("bomb.txt", &[0; 4096])], true);
+        let declared = bomb.len() - 22 - (46 + 8) + 24;
+        bomb[declared..declared + 4].copy_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(
+            parse(bomb).read("bomb.txt", 1024),
+            Err(ArchiveError::TooLarge)
+        ));
+
+        let mut corrupt = zip(&[("a.txt", b"hello")], false);
+        corrupt[30 + 5] = b'j';
+        assert!(matches!(
+            parse(corrupt).read("a.txt", 1024),
+            Err(ArchiveError::Unreadable(_))
+        ));
+        assert!(ZipArchive::parse(Arc::new(FileContent::Owned(b"PK not a zip".to_vec()))).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+//! Copies of the files that were reported as modified or added, for inspecting them afterwards.
+
+use crate::{
+    DetailReporter, EitherError, EntryType, FormatRegression, MayUnsupported, NameMatching, Reporter, Sampling,
+    SkipReason, ToleranceCheck,
+};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Copies files from the `actual` tree at `source` to the same relative path under `target`.
+#[derive(Debug, Clone)]
+pub struct ChangedFileCollector {
+    source: PathBuf,
+    target: PathBuf,
+    names: NameMatching,
+}
+
+#[derive(Debug, Error)]
+#[error("failed to collect {name} into {target}: {source}")]
+pub struct CollectError {
+    name: String,
+    target: String,
+    #[source]
+    source: io::Error,
+}
+
+impl ChangedFileCollector {
+    pub fn new(source: impl Into<PathBuf>, target: impl Into<PathBuf>) -> ChangedFileCollector {
+        ChangedFileCollector {
+            source: source.into(),
+            target: target.into(),
+            names: NameMatching::default(),
+        }
+    }
+
+    /// Finds each leaf in `source` as the run paired names, since reports use the spelling from
+    /// `expected`. The copy keeps the reported name.
+    pub fn with_name_matching(self, names: NameMatching) -> ChangedFileCollector {
+        ChangedFileCollector { names, ..self }
+    }
+
+    fn collect(&self, name: &str) -> Result<(), CollectError> {
+        let destination = self.target.join(name);
+        let copy = || {
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(self.names.locate(&self.source, name), &destination)
+        };
+        copy().map(drop).map_err(|source| CollectError {
+            name: name.to_owned(),
+            target: self.target.display().to_string(),
+            source,
+        })
+    }
+}
+
+/// Wraps a reporter so that, once a differ has reported a leaf as modified or added to it, the
+/// leaf is copied by a [`ChangedFileCollector`]. Every other call is forwarded unchanged.
+#[derive(Debug)]
+pub struct CollectChanged<R> {
+    inner: R,
+    collector: Option<ChangedFileCollector>,
+}
+
+impl<R> CollectChanged<R> {
+    /// Wraps `inner` without copying anything until a collector is set.
+    pub fn new(inner: R) -> CollectChanged<R> {
+        CollectChanged { inner, collector: None }
+    }
+
+    pub fn with_collector(self, collector: Option<ChangedFileCollector>) -> CollectChanged<R> {
+        CollectChanged { collector, ..self }
+    }
+
+    fn collect<E>(
+        &self,
+        name: &str,
+        result: Result<MayUnsupported<()>, E>,
+    ) -> Result<MayUnsupported<()>, EitherError<E, CollectError>> {
+        let result = result.map_err(EitherError::Left)?;
+        if let (MayUnsupported::Ok(()), Some(collector)) = (&result, &self.collector) {
+            collector.collect(name).map_err(EitherError::Right)?;
+        }
+        Ok(result)
+    }
+}
+
+impl<D, Diff, T, R> DetailReporter<Diff, T, CollectChanged<R>> for D
+where
+    D: DetailReporter<Diff, T, R>,
+{
+    type Error = EitherError<D::Error, CollectError>;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &CollectChanged<R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.report_unchanged(name, diff, &reporter.inner)
+            .map_err(EitherError::Left)
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &CollectChanged<R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.collect(name, self.report_modified(name, diff, &reporter.inner))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &CollectChanged<R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.collect(name, self.report_added(name, data, &reporter.inner))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &CollectChanged<R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.report_deleted(name, data, &reporter.inner)
+            .map_err(EitherError::Left)
+    }
+}
+
+impl<R: Reporter> Reporter for CollectChanged<R> {
+    type Error = R::Error;
+
+    fn start(&mut self) -> Result<(), Self::Error> {
+        self.inner.start()
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        self.inner.finish()
+    }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.inner.report_kind_mismatch(name, expected_kind, actual_kind)
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.inner.report_format_regression(name, regression)
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        self.inner.report_sampling(sampling, left_out)
+    }
+
+    fn report_change_magnitude(&self, name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        self.inner.report_change_magnitude(name, magnitude)
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_reencoded(name)
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_identical(name)
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        self.inner.report_tolerance_check(name, check)
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.inner.report_skipped(name, reason)
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.inner.report_type_changed(name, expected, actual)
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_added_empty_node(name)
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_deleted_empty_node(name)
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_added_leaf(name)
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_deleted_leaf(name)
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        self.inner.report_renamed(name, from, similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct Accepting;
+
+    impl DetailReporter<(), (), ()> for Accepting {
+        type Error = convert::Infallible;
+
+        fn report_unchanged(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_modified(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_added(&self, name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            // Leaves this reporter does not support are left to the next differ, and not copied.
+            if name.ends_with(".bin") {
+                return Ok(MayUnsupported::Unsupported);
+            }
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_deleted(&self, _name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+    }
+
+    #[test]
+    fn collect_changed_copies_modified_and_added_leaves() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let root = std::env::temp_dir().join(format!("semdiff-core-collect-{nanos}"));
+        let (source, target) = (root.join("actual"), root.join("collected"));
+        fs::create_dir_all(source.join("dir")).unwrap();
+        for name in ["same.txt", "dir/changed.txt", "new.txt", "new.bin"] {
+            fs::write(source.join(name), name).unwrap();
+        }
+
+        let reporter = CollectChanged::new(()).with_collector(Some(ChangedFileCollector::new(&source, &target)));
+        Accepting.report_unchanged("same.txt", &(), &reporter).unwrap();
+        Accepting.report_modified("dir/changed.txt", &(), &reporter).unwrap();
+        Accepting.report_added("new.txt", &(), &reporter).unwrap();
+        Accepting.report_added("new.bin", &(), &reporter).unwrap();
+        Accepting.report_deleted("gone.txt", &(), &reporter).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("dir/changed.txt")).unwrap(),
+            "dir/changed.txt"
+        );
+        assert_eq!(fs::read_to_string(target.join("new.txt")).unwrap(), "new.txt");
+        assert!(!target.join("same.txt").exists());
+        assert!(!target.join("new.bin").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn collect_changed_finds_leaves_spelled_differently_in_actual() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let root = std::env::temp_dir().join(format!("semdiff-core-collect-names-{nanos}"));
+        let (source, target) = (root.join("actual"), root.join("collected"));
+        fs::create_dir_all(source.join("dir")).unwrap();
+        fs::write(source.join("dir/a.txt"), "a").unwrap();
+
+        let names = crate::DiffOptions::new().with_case_folded_names(true).name_matching();
+        let collector = ChangedFileCollector::new(&source, &target).with_name_matching(names);
+        let reporter = CollectChanged::new(()).with_collector(Some(collector));
+        Accepting.report_modified("Dir/A.txt", &(), &reporter).unwrap();
+        assert_eq!(fs::read_to_string(target.join("Dir/A.txt")).unwrap(), "a");
+
+        let reporter = CollectChanged::new(()).with_collector(Some(ChangedFileCollector::new(&source, &target)));
+        assert!(Accepting.report_modified("Dir/B.txt", &(), &reporter).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
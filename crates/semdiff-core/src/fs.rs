@@ -1,23 +1,98 @@
-use crate::{LeafTraverse, NodeTraverse, TraversalNode};
+use crate::archive::{ArchiveChild, ArchiveError, ArchiveLimits, ZipArchive, is_archive_name};
+use crate::ignore::IgnoreRules;
+use crate::remote::{RemoteChild, RemoteError, RemoteTree};
+use crate::{DiffCalculator, LeafTraverse, MayUnsupported, NodeTraverse, SkipReason, TraversalNode};
 use memmap2::Mmap;
 use mime::Mime;
-use std::fs::{File, OpenOptions};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs::{DirEntry, File, OpenOptions};
 use std::io;
+use std::iter;
+use std::mem;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex};
 use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub struct FileLeaf {
     pub name: String,
     pub kind: Mime,
-    pub content: Arc<Mmap>,
+    pub content: Arc<FileContent>,
+    /// The files at the same path in the other roots of [`FsNode::with_candidates`].
+    candidates: Vec<FileLeaf>,
+}
+
+impl FileLeaf {
+    /// Wraps an in-memory buffer, so differs can run without a file on disk.
+    pub fn from_bytes(name: impl Into<String>, kind: Mime, content: impl Into<Vec<u8>>) -> FileLeaf {
+        FileLeaf {
+            name: name.into(),
+            kind,
+            content: Arc::new(FileContent::Owned(content.into())),
+            candidates: Vec::new(),
+        }
+    }
+}
+
+/// Bytes of a [`FileLeaf`]: a memory-mapped file, or a buffer owned in memory.
+#[derive(Debug)]
+pub enum FileContent {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileContent::Mapped(content) => content,
+            FileContent::Owned(content) => content,
+        }
+    }
+}
+
+/// Runs `calculator` on two `(kind, bytes)` pairs and returns its typed diff.
+///
+/// This is the supported entry point for embedding a single differ, such as
+/// `ImageDiffCalculator`, without files on disk or a directory walk.
+pub fn diff_bytes<C: DiffCalculator<FileLeaf>>(
+    calculator: &C,
+    expected: (Mime, Vec<u8>),
+    actual: (Mime, Vec<u8>),
+) -> Result<MayUnsupported<C::Diff>, C::Error> {
+    let (expected_kind, expected) = expected;
+    let (actual_kind, actual) = actual;
+    calculator.diff(
+        "",
+        FileLeaf::from_bytes("", expected_kind, expected),
+        FileLeaf::from_bytes("", actual_kind, actual),
+    )
 }
 
 impl LeafTraverse for FileLeaf {
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn kind(&self) -> Option<&str> {
+        Some(self.kind.essence_str())
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.content.len() as u64)
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        Some(&self.content)
+    }
+
+    fn candidates(&self) -> &[FileLeaf] {
+        &self.candidates
+    }
 }
 
 #[derive(Debug, Error)]
@@ -30,12 +105,142 @@ pub enum FsTreeError {
     Open(io::Error),
     #[error("unsupported file type: {0:?}")]
     UnsupportedFileType(PathBuf),
+    #[error("failed to read archive {0:?}: {1}")]
+    Archive(PathBuf, #[source] ArchiveError),
+    #[error("failed to read remote tree {0}: {1}")]
+    Remote(String, #[source] RemoteError),
+}
+
+/// Excludes regular files whose size is outside a range before they are opened.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSizeFilter {
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+impl FileSizeFilter {
+    pub fn new(min: Option<u64>, max: Option<u64>) -> FileSizeFilter {
+        FileSizeFilter { min, max }
+    }
+
+    pub fn accepts(&self, len: u64) -> bool {
+        self.rejection(len).is_none()
+    }
+
+    fn rejection(&self, len: u64) -> Option<SkipReason> {
+        if self.min.is_some_and(|min| len < min) {
+            Some(SkipReason::TooSmall)
+        } else if self.max.is_some_and(|max| len > max) {
+            Some(SkipReason::TooLarge)
+        } else {
+            None
+        }
+    }
+}
+
+/// Bounds how many files and directories are open at once while trees are read, shared by every
+/// tree built with a clone of it.
+///
+/// Leaves do not keep their file open: the mapping stays valid after the handle is closed, so a
+/// file is only open while it is being mapped. Without a limit, that is up to one file per
+/// thread reading a directory, which can exceed a low descriptor limit on machines with many
+/// cores.
+#[derive(Debug, Clone)]
+pub struct OpenFileLimit {
+    inner: Arc<OpenFileLimitInner>,
+}
+
+#[derive(Debug)]
+struct OpenFileLimitInner {
+    limit: usize,
+    open: Mutex<usize>,
+    released: Condvar,
+}
+
+impl OpenFileLimit {
+    /// Returns `None` when `limit` is 0.
+    pub fn new(limit: usize) -> Option<OpenFileLimit> {
+        (limit > 0).then(|| OpenFileLimit {
+            inner: Arc::new(OpenFileLimitInner {
+                limit,
+                open: Mutex::new(0),
+                released: Condvar::new(),
+            }),
+        })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Waits until fewer than the limit are open, then counts one more until the permit drops.
+    fn acquire(&self) -> OpenFilePermit<'_> {
+        let inner = &*self.inner;
+        let mut open = inner
+            .released
+            .wait_while(inner.open.lock().unwrap(), |open| *open >= inner.limit)
+            .unwrap();
+        *open += 1;
+        OpenFilePermit { limit: self }
+    }
+}
+
+struct OpenFilePermit<'a> {
+    limit: &'a OpenFileLimit,
+}
+
+impl Drop for OpenFilePermit<'_> {
+    fn drop(&mut self) {
+        let inner = &*self.limit.inner;
+        *inner.open.lock().unwrap() -= 1;
+        inner.released.notify_one();
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct FsNode {
     abs_path: PathBuf,
     name: String,
+    /// Path from the root, matched against `ignore_rules`.
+    path: String,
+    size_filter: Option<FileSizeFilter>,
+    ignore_rules: Option<Arc<IgnoreRules>>,
+    names_only: bool,
+    open_file_limit: Option<OpenFileLimit>,
+    archive_limits: Option<ArchiveLimits>,
+    /// Set when this node is an archive, or a directory in one, rather than a directory on disk.
+    archive: Option<Box<ArchiveDir>>,
+    /// Set when this node is a directory of a remote tree, see [`FsNode::new_remote_root`].
+    remote: Option<Box<RemoteDir>>,
+    /// The same directory in the other roots of [`FsNode::with_candidates`].
+    candidates: Vec<PathBuf>,
+    /// Set when this node is the root of a single file, see [`FsNode::new_file_root`].
+    file: Option<Box<FileRoot>>,
+    skipped: Vec<(String, SkipReason)>,
+}
+
+/// The only leaf of a root that is a single file, at the root's path.
+#[derive(Clone, Debug)]
+struct FileRoot {
+    name: String,
+}
+
+/// A directory in a remote tree, which is its root for an empty `prefix`.
+#[derive(Clone, Debug)]
+struct RemoteDir {
+    tree: Arc<RemoteTree>,
+    /// Path of the directory in the tree, ending with `/` unless empty.
+    prefix: String,
+}
+
+/// A directory in an archive, which is the archive itself for an empty `prefix`.
+#[derive(Clone, Debug)]
+struct ArchiveDir {
+    archive: Arc<ZipArchive>,
+    /// Path of the directory in the archive, ending with `/` unless empty.
+    prefix: String,
+    /// How deeply the archive is nested, from 1 for an archive on disk.
+    depth: usize,
 }
 
 impl FsNode {
@@ -43,11 +248,155 @@ impl FsNode {
         FsNode {
             abs_path: path,
             name: "".to_owned(),
+            path: "".to_owned(),
+            size_filter: None,
+            ignore_rules: None,
+            names_only: false,
+            open_file_limit: None,
+            archive_limits: None,
+            archive: None,
+            remote: None,
+            candidates: Vec::new(),
+            file: None,
+            skipped: Vec::new(),
         }
     }
 
-    fn new(abs_path: PathBuf, name: String) -> Self {
-        Self { abs_path, name }
+    /// Reads the single file at `path` as a tree whose only leaf is named `name`, so that two
+    /// files are compared by giving both the same name. The kind of the leaf is still detected
+    /// from `path`. [`FsNode::with_candidates`] has no effect.
+    pub fn new_file_root(path: PathBuf, name: String) -> FsNode {
+        FsNode {
+            file: Some(Box::new(FileRoot { name })),
+            ..FsNode::new_root(path)
+        }
+    }
+
+    /// Reads the zip archive at `path` as the root of a tree, so that an archive can be compared
+    /// with a directory or with another archive. Archives inside it are only opened with
+    /// [`FsNode::with_archives`], and [`FsNode::with_candidates`] has no effect.
+    pub fn new_archive_root(path: PathBuf) -> Result<FsNode, FsTreeError> {
+        let content = std::fs::read(&path).map_err(FsTreeError::Open)?;
+        let archive = ZipArchive::parse(Arc::new(FileContent::Owned(content)))
+            .map_err(|err| FsTreeError::Archive(path.clone(), err))?;
+        Ok(FsNode {
+            archive: Some(Box::new(ArchiveDir {
+                archive: Arc::new(archive),
+                prefix: String::new(),
+                depth: 1,
+            })),
+            ..FsNode::new_root(path)
+        })
+    }
+
+    /// Reads the tree listed by the manifest at the `http://`, `https://`, or `s3://` URL `url`,
+    /// such as a baseline kept in object storage. The manifest lists the path of each file
+    /// relative to it, one per line. A file is fetched when its directory is listed, with
+    /// `authorization` as the `Authorization` header, and its kind is detected from its content;
+    /// a file that cannot be fetched is skipped as unreadable. `authorization` is refused over
+    /// plain http to anything but a loopback address. [`FsNode::with_candidates`] has no effect.
+    pub fn new_remote_root(url: &str, authorization: Option<String>) -> Result<FsNode, FsTreeError> {
+        let tree = RemoteTree::fetch(url, authorization).map_err(|err| FsTreeError::Remote(url.to_owned(), err))?;
+        Ok(FsNode {
+            remote: Some(Box::new(RemoteDir {
+                tree: Arc::new(tree),
+                prefix: String::new(),
+            })),
+            ..FsNode::new_root(PathBuf::from(url))
+        })
+    }
+
+    /// Skips files rejected by `size_filter` anywhere in this tree; they are neither mapped nor
+    /// diffed.
+    pub fn with_size_filter(mut self, size_filter: FileSizeFilter) -> Self {
+        self.size_filter = Some(size_filter);
+        self
+    }
+
+    /// Leaves out files and directories matched by `ignore_rules` anywhere in this tree, as if they
+    /// did not exist; ignored directories are not read.
+    pub fn with_ignore_rules(mut self, ignore_rules: IgnoreRules) -> Self {
+        self.ignore_rules = (!ignore_rules.is_empty()).then(|| Arc::new(ignore_rules));
+        self
+    }
+
+    /// Lists files anywhere in this tree without opening them. Leaves have no content and a kind
+    /// guessed from their name, which is enough for [`crate::DiffOptions::with_names_only`].
+    pub fn with_names_only(mut self, names_only: bool) -> Self {
+        self.names_only = names_only;
+        self
+    }
+
+    /// Keeps at most `open_file_limit` files and directories open at once anywhere in this tree,
+    /// and in any other tree given a clone of the same limit.
+    pub fn with_open_file_limit(mut self, open_file_limit: Option<OpenFileLimit>) -> Self {
+        self.open_file_limit = open_file_limit;
+        self
+    }
+
+    /// Reads zip archives anywhere in this tree, such as `.zip`, `.jar`, and `.war` files, as
+    /// directories of their entries, down to the nesting depth of `archive_limits`. An entry is
+    /// decompressed when its directory is listed; entries larger than the limit, and ones that
+    /// cannot be decompressed, are skipped. Archives only exist in the main root, not in the
+    /// candidate roots of [`FsNode::with_candidates`].
+    pub fn with_archives(mut self, archive_limits: Option<ArchiveLimits>) -> Self {
+        self.archive_limits = archive_limits;
+        self
+    }
+
+    /// Reads `roots` along with this tree as alternative versions of it. A path present in any of
+    /// them is a child; its file from the first root that has one is the leaf, and the others are
+    /// its [`LeafTraverse::candidates`]. Where one root has a file and another a directory, the
+    /// first one wins.
+    pub fn with_candidates(mut self, roots: Vec<PathBuf>) -> Self {
+        self.candidates = roots;
+        self
+    }
+
+    fn child(&self, abs_path: PathBuf, name: String) -> Self {
+        Self {
+            abs_path,
+            path: self.child_path(&name),
+            name,
+            size_filter: self.size_filter,
+            ignore_rules: self.ignore_rules.clone(),
+            names_only: self.names_only,
+            open_file_limit: self.open_file_limit.clone(),
+            archive_limits: self.archive_limits,
+            archive: None,
+            remote: None,
+            candidates: Vec::new(),
+            file: None,
+            skipped: Vec::new(),
+        }
+    }
+
+    fn archive_child(&self, name: String, archive: ArchiveDir) -> Self {
+        Self {
+            archive: Some(Box::new(archive)),
+            ..self.child(self.abs_path.join(&name), name)
+        }
+    }
+
+    fn remote_child(&self, name: String, remote: RemoteDir) -> Self {
+        Self {
+            remote: Some(Box::new(remote)),
+            ..self.child(self.abs_path.join(&name), name)
+        }
+    }
+
+    /// The archive in `content` if the file `name` is one that is opened at `depth`.
+    fn open_archive(&self, name: &str, content: &Arc<FileContent>, depth: usize) -> Option<ArchiveDir> {
+        let limits = self.archive_limits?;
+        if depth > limits.max_depth() || !is_archive_name(name) {
+            return None;
+        }
+        let archive = ZipArchive::parse(content.clone()).ok()?;
+        Some(ArchiveDir {
+            archive: Arc::new(archive),
+            prefix: String::new(),
+            depth,
+        })
     }
 }
 
@@ -64,32 +413,299 @@ impl NodeTraverse for FsNode {
         &mut self,
     ) -> Result<impl Iterator<Item = Result<TraversalNode<Self, Self::Leaf>, Self::TraverseError>>, Self::TraverseError>
     {
-        let entries = match std::fs::read_dir(&self.abs_path) {
-            Ok(entries) => entries,
-            Err(err) => return Err(FsTreeError::ReadDir(err)),
+        let children = match (self.archive.clone(), self.remote.clone(), self.file.clone()) {
+            (Some(archive), _, _) => self.read_archive_dir(&archive),
+            (None, Some(remote), _) => self.read_remote_dir(&remote),
+            (None, None, Some(file)) => vec![self.read_file(&self.abs_path, file.name)],
+            (None, None, None) => self.read_dir()?,
+        };
+        Ok(children.into_iter())
+    }
+
+    fn take_skipped(&mut self) -> Vec<(String, SkipReason)> {
+        mem::take(&mut self.skipped)
+    }
+}
+
+enum ReadEntry {
+    Child(TraversalNode<FsNode, FileLeaf>),
+    Skipped(String, SkipReason),
+    Ignored,
+}
+
+impl FsNode {
+    #[allow(clippy::type_complexity)]
+    fn read_dir(&mut self) -> Result<Vec<Result<TraversalNode<FsNode, FileLeaf>, FsTreeError>>, FsTreeError> {
+        let mut entries = Vec::new();
+        for dir in iter::once(&self.abs_path).chain(&self.candidates) {
+            let _permit = self.open_file_limit.as_ref().map(OpenFileLimit::acquire);
+            match std::fs::read_dir(dir) {
+                Ok(dir_entries) => entries.extend(dir_entries),
+                Err(err) => return Err(FsTreeError::ReadDir(err)),
+            }
+        }
+
+        // Opening, mapping, and sniffing each file dominates on directories of many small files,
+        // so leaves are built in parallel; collecting keeps the `read_dir` order and errors.
+        let entries = entries
+            .into_par_iter()
+            .map(|entry| self.read_entry(entry))
+            .collect::<Vec<_>>();
+        self.skipped.clear();
+        let mut children = Vec::with_capacity(entries.len());
+        // Index in `children` of each name, to merge the entries of candidate roots.
+        let mut positions = HashMap::new();
+        for entry in entries {
+            match entry {
+                Ok(ReadEntry::Child(child)) if self.candidates.is_empty() => children.push(Ok(child)),
+                Ok(ReadEntry::Child(child)) => {
+                    let name = match &child {
+                        TraversalNode::Node(node) => node.name.clone(),
+                        TraversalNode::Leaf(leaf) => leaf.name.clone(),
+                    };
+                    match positions.entry(name) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(children.len());
+                            children.push(Ok(child));
+                        }
+                        Entry::Occupied(entry) => match (&mut children[*entry.get()], child) {
+                            (Ok(TraversalNode::Node(node)), TraversalNode::Node(candidate))
+                                if node.archive.is_none() && candidate.archive.is_none() =>
+                            {
+                                node.candidates.push(candidate.abs_path)
+                            }
+                            (Ok(TraversalNode::Leaf(leaf)), TraversalNode::Leaf(candidate)) => {
+                                leaf.candidates.push(candidate)
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+                Ok(ReadEntry::Skipped(name, reason)) => {
+                    if !self.skipped.iter().any(|(skipped, _)| *skipped == name) {
+                        self.skipped.push((name, reason));
+                    }
+                }
+                Ok(ReadEntry::Ignored) => {}
+                Err(err) => children.push(Err(err)),
+            }
+        }
+        Ok(children)
+    }
+
+    /// Lists a directory in an archive, decompressing its files.
+    #[allow(clippy::type_complexity)]
+    fn read_archive_dir(&mut self, dir: &ArchiveDir) -> Vec<Result<TraversalNode<FsNode, FileLeaf>, FsTreeError>> {
+        let entries = dir
+            .archive
+            .children(&dir.prefix)
+            .into_par_iter()
+            .map(|child| self.read_archive_entry(dir, child))
+            .collect::<Vec<_>>();
+        self.skipped.clear();
+        let mut children = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match entry {
+                ReadEntry::Child(child) => children.push(Ok(child)),
+                ReadEntry::Skipped(name, reason) => self.skipped.push((name, reason)),
+                ReadEntry::Ignored => {}
+            }
+        }
+        children
+    }
+
+    fn read_archive_entry(&self, dir: &ArchiveDir, child: ArchiveChild) -> ReadEntry {
+        let (name, is_dir) = match child {
+            ArchiveChild::Directory(name) => (name, true),
+            ArchiveChild::File(name) => (name, false),
+        };
+        if let Some(ignore_rules) = &self.ignore_rules
+            && ignore_rules.is_ignored(&self.child_path(name), is_dir)
+        {
+            return ReadEntry::Ignored;
+        }
+        let path = format!("{}{name}", dir.prefix);
+        if is_dir {
+            let archive = ArchiveDir {
+                archive: dir.archive.clone(),
+                prefix: format!("{path}/"),
+                depth: dir.depth,
+            };
+            return ReadEntry::Child(TraversalNode::Node(self.archive_child(name.to_owned(), archive)));
+        }
+        let rejection = |len| self.size_filter.and_then(|size_filter| size_filter.rejection(len));
+        if let Some(reason) = rejection(dir.archive.declared_size(&path)) {
+            return ReadEntry::Skipped(name.to_owned(), reason);
+        }
+        let max_entry_size = self.archive_limits.unwrap_or_default().max_entry_size();
+        let content = match dir.archive.read(&path, max_entry_size) {
+            Ok(content) => content,
+            Err(ArchiveError::TooLarge) => return ReadEntry::Skipped(name.to_owned(), SkipReason::TooLarge),
+            Err(_) => return ReadEntry::Skipped(name.to_owned(), SkipReason::Unreadable),
         };
+        // The declared size may not be the real one.
+        if let Some(reason) = rejection(content.len() as u64) {
+            return ReadEntry::Skipped(name.to_owned(), reason);
+        }
+        let content = Arc::new(FileContent::Owned(content));
+        if let Some(archive) = self.open_archive(name, &content, dir.depth + 1) {
+            return ReadEntry::Child(TraversalNode::Node(self.archive_child(name.to_owned(), archive)));
+        }
+        ReadEntry::Child(TraversalNode::Leaf(FileLeaf {
+            name: name.to_owned(),
+            kind: detect_file_kind(Path::new(name), &content),
+            content,
+            candidates: Vec::new(),
+        }))
+    }
+
+    /// Lists a directory of a remote tree, fetching its files.
+    #[allow(clippy::type_complexity)]
+    fn read_remote_dir(&mut self, dir: &RemoteDir) -> Vec<Result<TraversalNode<FsNode, FileLeaf>, FsTreeError>> {
+        let entries = dir
+            .tree
+            .children(&dir.prefix)
+            .into_par_iter()
+            .map(|child| self.read_remote_entry(dir, child))
+            .collect::<Vec<_>>();
+        self.skipped.clear();
+        let mut children = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match entry {
+                ReadEntry::Child(child) => children.push(Ok(child)),
+                ReadEntry::Skipped(name, reason) => self.skipped.push((name, reason)),
+                ReadEntry::Ignored => {}
+            }
+        }
+        children
+    }
 
-        Ok(entries.map(|entry| {
-            let entry = entry.map_err(FsTreeError::ReadDir)?;
-            let file_type = entry.file_type().map_err(FsTreeError::Metadata)?;
-            let name = entry.file_name();
-            let abs_path = entry.path();
-            let name = name.to_string_lossy().into_owned();
-            if file_type.is_dir() {
-                Ok(TraversalNode::Node(FsNode::new(abs_path, name)))
-            } else if file_type.is_file() {
-                let file = open_regular_file(&abs_path)?;
-                let content = unsafe { Mmap::map(&file) }.map_err(FsTreeError::Open)?;
-                let kind = detect_file_kind(&abs_path, &content);
-                let leaf = FileLeaf {
-                    name,
-                    kind,
-                    content: Arc::new(content),
-                };
-                Ok(TraversalNode::Leaf(leaf))
+    fn read_remote_entry(&self, dir: &RemoteDir, child: RemoteChild) -> ReadEntry {
+        let (name, is_dir) = match child {
+            RemoteChild::Directory(name) => (name, true),
+            RemoteChild::File(name) => (name, false),
+        };
+        if let Some(ignore_rules) = &self.ignore_rules
+            && ignore_rules.is_ignored(&self.child_path(name), is_dir)
+        {
+            return ReadEntry::Ignored;
+        }
+        let path = format!("{}{name}", dir.prefix);
+        if is_dir {
+            let remote = RemoteDir {
+                tree: dir.tree.clone(),
+                prefix: format!("{path}/"),
+            };
+            return ReadEntry::Child(TraversalNode::Node(self.remote_child(name.to_owned(), remote)));
+        }
+        if self.names_only {
+            return ReadEntry::Child(TraversalNode::Leaf(FileLeaf {
+                name: name.to_owned(),
+                kind: named_kind(Path::new(name))
+                    .unwrap_or_else(|| mime_guess::from_path(name).first_or_octet_stream()),
+                content: Arc::new(FileContent::Owned(Vec::new())),
+                candidates: Vec::new(),
+            }));
+        }
+        let Ok(content) = dir.tree.read(&path) else {
+            return ReadEntry::Skipped(name.to_owned(), SkipReason::Unreadable);
+        };
+        if let Some(reason) = self
+            .size_filter
+            .and_then(|size_filter| size_filter.rejection(content.len() as u64))
+        {
+            return ReadEntry::Skipped(name.to_owned(), reason);
+        }
+        let content = Arc::new(FileContent::Owned(content));
+        if let Some(archive) = self.open_archive(name, &content, 1) {
+            return ReadEntry::Child(TraversalNode::Node(self.archive_child(name.to_owned(), archive)));
+        }
+        ReadEntry::Child(TraversalNode::Leaf(FileLeaf {
+            name: name.to_owned(),
+            kind: detect_file_kind(Path::new(name), &content),
+            content,
+            candidates: Vec::new(),
+        }))
+    }
+
+    fn child_path(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{}", self.path, name)
+        }
+    }
+
+    fn read_entry(&self, entry: io::Result<DirEntry>) -> Result<ReadEntry, FsTreeError> {
+        let entry = entry.map_err(FsTreeError::ReadDir)?;
+        let file_type = entry.file_type().map_err(FsTreeError::Metadata)?;
+        if let Some(ignore_rules) = &self.ignore_rules
+            && ignore_rules.is_ignored(
+                &self.child_path(&entry.file_name().to_string_lossy()),
+                file_type.is_dir(),
+            )
+        {
+            return Ok(ReadEntry::Ignored);
+        }
+        if file_type.is_file()
+            && let Some(size_filter) = &self.size_filter
+        {
+            let len = entry.metadata().map_err(FsTreeError::Metadata)?.len();
+            if let Some(reason) = size_filter.rejection(len) {
+                return Ok(ReadEntry::Skipped(
+                    entry.file_name().to_string_lossy().into_owned(),
+                    reason,
+                ));
+            }
+        }
+        let name = entry.file_name();
+        let abs_path = entry.path();
+        let name = name.to_string_lossy().into_owned();
+        if is_special_file(&file_type) {
+            return Ok(ReadEntry::Skipped(name, SkipReason::SpecialFile));
+        }
+        if file_type.is_dir() {
+            Ok(ReadEntry::Child(TraversalNode::Node(self.child(abs_path, name))))
+        } else if file_type.is_file() {
+            self.read_file(&abs_path, name).map(ReadEntry::Child)
+        } else {
+            Err(FsTreeError::UnsupportedFileType(abs_path))
+        }
+    }
+
+    /// The leaf `name` for the regular file at `abs_path`, or the directory it holds if it is an
+    /// archive that is opened.
+    fn read_file(&self, abs_path: &Path, name: String) -> Result<TraversalNode<FsNode, FileLeaf>, FsTreeError> {
+        if self.names_only {
+            let leaf = FileLeaf {
+                name,
+                kind: named_kind(abs_path).unwrap_or_else(|| mime_guess::from_path(abs_path).first_or_octet_stream()),
+                content: Arc::new(FileContent::Owned(Vec::new())),
+                candidates: Vec::new(),
+            };
+            return Ok(TraversalNode::Leaf(leaf));
+        }
+        // The mapping outlives the handle, which is closed, and its permit released, right away.
+        let content = {
+            let _permit = self.open_file_limit.as_ref().map(OpenFileLimit::acquire);
+            let file = open_regular_file(abs_path)?;
+            // Empty files cannot be mapped on every platform, and there is nothing to map.
+            if file.metadata().map_err(FsTreeError::Metadata)?.len() == 0 {
+                FileContent::Owned(Vec::new())
             } else {
-                Err(FsTreeError::UnsupportedFileType(abs_path))
+                FileContent::Mapped(unsafe { Mmap::map(&file) }.map_err(FsTreeError::Open)?)
             }
+        };
+        let content = Arc::new(content);
+        if let Some(archive) = self.open_archive(&name, &content, 1) {
+            return Ok(TraversalNode::Node(self.archive_child(name, archive)));
+        }
+        let kind = detect_file_kind(abs_path, &content);
+        Ok(TraversalNode::Leaf(FileLeaf {
+            name,
+            kind,
+            content,
+            candidates: Vec::new(),
         }))
     }
 }
@@ -99,6 +715,8 @@ fn detect_file_kind(path: &Path, body: &[u8]) -> Mime {
         && let Ok(mime) = kind.mime_type().parse()
     {
         mime
+    } else if let Some(mime) = named_kind(path) {
+        mime
     } else if let Some(mime) = mime_guess::from_path(path).first() {
         mime
     } else {
@@ -106,6 +724,37 @@ fn detect_file_kind(path: &Path, body: &[u8]) -> Mime {
     }
 }
 
+/// Dotenv files (`.env`, `.env.local`, `app.env`), Java `.properties` files, and serialized
+/// protobuf messages (`.pb`, `.binpb`), which have no registered MIME type.
+fn named_kind(path: &Path) -> Option<Mime> {
+    let file_name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    let mime = if file_name == ".env" || file_name.starts_with(".env.") || file_name.ends_with(".env") {
+        "text/x-dotenv"
+    } else if file_name.ends_with(".properties") {
+        "text/x-java-properties"
+    } else if file_name.ends_with(".pb") || file_name.ends_with(".binpb") {
+        "application/x-protobuf"
+    } else {
+        return None;
+    };
+    mime.parse().ok()
+}
+
+/// Fifos, sockets, and devices, which are skipped rather than opened: reading a fifo may block
+/// forever, and a device may never end. Symlinks are not special files; they are rejected when
+/// opened.
+#[cfg(unix)]
+fn is_special_file(file_type: &std::fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_fifo() || file_type.is_socket() || file_type.is_block_device() || file_type.is_char_device()
+}
+
+#[cfg(not(unix))]
+fn is_special_file(_file_type: &std::fs::FileType) -> bool {
+    false
+}
+
 #[cfg(unix)]
 fn open_regular_file(path: &Path) -> Result<File, FsTreeError> {
     use std::os::unix::fs::OpenOptionsExt;
@@ -186,6 +835,24 @@ mod tests {
         fs::remove_dir_all(root).unwrap();
     }
 
+    #[test]
+    fn file_root_has_the_file_as_its_only_leaf() {
+        let root = unique_temp_path("file-root");
+        fs::create_dir(&root).unwrap();
+        let path = root.join("actual.json");
+        fs::write(&path, r#"{"a": 1}"#).unwrap();
+
+        let mut node = FsNode::new_file_root(path, "expected.json".to_owned());
+        let children = node.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        let [TraversalNode::Leaf(leaf)] = &children[..] else {
+            panic!("a file root should have exactly one leaf");
+        };
+        assert_eq!(leaf.name, "expected.json");
+        assert_eq!(leaf.kind, mime::APPLICATION_JSON);
+        assert_eq!(&leaf.content[..], br#"{"a": 1}"#);
+        fs::remove_dir_all(root).unwrap();
+    }
+
     #[cfg(any(unix, windows))]
     #[test]
     fn fs_node_rejects_symlink_file_children() {
@@ -234,4 +901,267 @@ mod tests {
         fs::remove_dir_all(root).unwrap();
         fs::remove_file(outside_secret).unwrap();
     }
+
+    #[test]
+    fn fs_node_skips_files_outside_size_range() {
+        let root = unique_temp_path("size-filter");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("empty.txt"), "").unwrap();
+        fs::write(root.join("small.txt"), "hello").unwrap();
+        fs::write(root.join("large.txt"), "hello, world").unwrap();
+
+        let size_filter = FileSizeFilter::new(Some(1), Some(5));
+        let mut node = FsNode::new_root(root.clone()).with_size_filter(size_filter);
+        let names = node
+            .children()
+            .unwrap()
+            .map(|child| match child.unwrap() {
+                TraversalNode::Leaf(leaf) => leaf.name,
+                TraversalNode::Node(_) => panic!("regular file was returned as a node"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["small.txt"]);
+        let mut skipped = node.take_skipped();
+        skipped.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            skipped,
+            [
+                ("empty.txt".to_owned(), SkipReason::TooSmall),
+                ("large.txt".to_owned(), SkipReason::TooLarge),
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_reads_empty_files_and_skips_special_files() {
+        let root = unique_temp_path("special-files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("empty.txt"), "").unwrap();
+        #[cfg(unix)]
+        {
+            let fifo = std::ffi::CString::new(root.join("pipe").into_os_string().into_encoded_bytes()).unwrap();
+            assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        }
+
+        let mut node = FsNode::new_root(root.clone());
+        let children = node.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        let [TraversalNode::Leaf(leaf)] = &children[..] else {
+            panic!("only the empty file should be listed");
+        };
+        assert_eq!(leaf.name, "empty.txt");
+        assert!(leaf.content.is_empty());
+        #[cfg(unix)]
+        assert_eq!(node.take_skipped(), [("pipe".to_owned(), SkipReason::SpecialFile)]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_reads_every_file_within_the_open_file_limit() {
+        let root = unique_temp_path("open-file-limit");
+        fs::create_dir(&root).unwrap();
+        for index in 0..32 {
+            fs::write(root.join(format!("{index}.txt")), index.to_string()).unwrap();
+        }
+
+        let limit = OpenFileLimit::new(1).unwrap();
+        let mut node = FsNode::new_root(root.clone()).with_open_file_limit(Some(limit.clone()));
+        assert_eq!(node.children().unwrap().map(Result::unwrap).count(), 32);
+        assert_eq!(*limit.inner.open.lock().unwrap(), 0);
+        assert!(OpenFileLimit::new(0).is_none());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_reads_nested_archives_as_directories() {
+        use crate::archive::tests::zip;
+
+        let root = unique_temp_path("archives");
+        fs::create_dir(&root).unwrap();
+        let inner = zip(&[("c.txt", b"inner")], true);
+        let outer = zip(
+            &[("lib/inner.jar", &inner), ("a.txt", b"outer"), ("big.bin", &[0; 1024])],
+            false,
+        );
+        fs::write(root.join("app.war"), outer).unwrap();
+        fs::write(root.join("broken.zip"), "not a zip").unwrap();
+
+        // Every leaf by its path from the root, with the names of the nodes above it.
+        fn leaves(node: &mut FsNode, prefix: &str, out: &mut Vec<String>) {
+            for child in node.children().unwrap() {
+                match child.unwrap() {
+                    TraversalNode::Node(mut child) => {
+                        let prefix = format!("{prefix}{}/", child.name());
+                        leaves(&mut child, &prefix, out);
+                    }
+                    TraversalNode::Leaf(leaf) => out.push(format!("{prefix}{}", leaf.name)),
+                }
+            }
+            out.extend(
+                node.take_skipped()
+                    .into_iter()
+                    .map(|(name, reason)| format!("{prefix}{name} ({})", reason.as_str())),
+            );
+        }
+        let read = |limits| {
+            let mut node = FsNode::new_root(root.clone()).with_archives(limits);
+            let mut out = Vec::new();
+            leaves(&mut node, "", &mut out);
+            out.sort();
+            out
+        };
+
+        assert_eq!(
+            read(ArchiveLimits::new(2, 512)),
+            [
+                "app.war/a.txt",
+                "app.war/big.bin (too_large)",
+                "app.war/lib/inner.jar/c.txt",
+                "broken.zip"
+            ]
+        );
+        assert_eq!(
+            read(ArchiveLimits::new(1, 1024)),
+            [
+                "app.war/a.txt",
+                "app.war/big.bin",
+                "app.war/lib/inner.jar",
+                "broken.zip"
+            ]
+        );
+        assert_eq!(read(None), ["app.war", "broken.zip"]);
+
+        // An archive can also be the root itself.
+        let mut archive_root = FsNode::new_archive_root(root.join("app.war")).unwrap();
+        let mut out = Vec::new();
+        leaves(&mut archive_root, "", &mut out);
+        out.sort();
+        assert_eq!(out, ["a.txt", "big.bin", "lib/inner.jar"]);
+        assert!(matches!(
+            FsNode::new_archive_root(root.join("broken.zip")),
+            Err(FsTreeError::Archive(..))
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_prunes_ignored_entries() {
+        let root = unique_temp_path("ignore-rules");
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("build/out.bin"), "out").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/debug.log"), "log").unwrap();
+
+        let ignore_rules = IgnoreRules::parse("build/\n*.log\n");
+        let mut node = FsNode::new_root(root.clone()).with_ignore_rules(ignore_rules);
+        let children = node.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        let [TraversalNode::Node(mut src)] = <[_; 1]>::try_from(children).unwrap() else {
+            panic!("only the src directory should be listed");
+        };
+        let names = src
+            .children()
+            .unwrap()
+            .map(|child| match child.unwrap() {
+                TraversalNode::Leaf(leaf) => leaf.name,
+                TraversalNode::Node(_) => panic!("regular file was returned as a node"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(src.name(), "src");
+        assert_eq!(names, ["main.rs"]);
+        assert!(node.take_skipped().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_merges_candidate_roots_by_name() {
+        let root = unique_temp_path("candidates");
+        let (first, second) = (root.join("first"), root.join("second"));
+        fs::create_dir_all(first.join("dir")).unwrap();
+        fs::create_dir_all(second.join("dir")).unwrap();
+        fs::write(first.join("dir/out.txt"), "first").unwrap();
+        fs::write(second.join("dir/out.txt"), "second").unwrap();
+        fs::write(second.join("dir/extra.txt"), "extra").unwrap();
+
+        let mut node = FsNode::new_root(first).with_candidates(vec![second]);
+        let children = node.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        let [TraversalNode::Node(mut dir)] = <[_; 1]>::try_from(children).unwrap() else {
+            panic!("the directory should be listed once");
+        };
+        let mut leaves = dir
+            .children()
+            .unwrap()
+            .map(|child| match child.unwrap() {
+                TraversalNode::Leaf(leaf) => leaf,
+                TraversalNode::Node(_) => panic!("regular file was returned as a node"),
+            })
+            .collect::<Vec<_>>();
+        leaves.sort_by(|a, b| a.name.cmp(&b.name));
+        let contents = |leaf: &FileLeaf| {
+            iter::once(leaf)
+                .chain(leaf.candidates())
+                .map(|leaf| String::from_utf8(leaf.content.to_vec()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(leaves.len(), 2);
+        assert_eq!(contents(&leaves[0]), ["extra"]);
+        assert_eq!(contents(&leaves[1]), ["first", "second"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fs_node_reads_remote_trees_and_skips_files_it_cannot_fetch() {
+        let base = crate::remote::tests::serve(
+            &[
+                ("/golden/manifest.txt", b"sub/data.bin\nmissing.txt\nnotes.txt\n"),
+                ("/golden/sub/data.bin", b"SQLite format 3\0"),
+                ("/golden/notes.txt", b"hello"),
+            ],
+            None,
+        );
+        let mut root = FsNode::new_remote_root(&format!("{base}/golden/manifest.txt"), None).unwrap();
+        let children = root.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(
+            root.take_skipped(),
+            [("missing.txt".to_owned(), SkipReason::Unreadable)]
+        );
+        let [TraversalNode::Node(sub), TraversalNode::Leaf(notes)] = &children[..] else {
+            panic!("unexpected children: {children:?}");
+        };
+        assert_eq!(&notes.content[..], b"hello");
+        let mut sub = sub.clone();
+        let Some(Ok(TraversalNode::Leaf(data))) = sub.children().unwrap().next() else {
+            panic!("sub/data.bin was not listed");
+        };
+        assert_eq!(data.kind.essence_str(), "application/vnd.sqlite3");
+
+        assert!(matches!(
+            FsNode::new_remote_root("ftp://example.com/golden", None),
+            Err(FsTreeError::Remote(_, RemoteError::UnsupportedScheme(_)))
+        ));
+    }
+
+    #[test]
+    fn detect_file_kind_recognizes_files_by_name() {
+        let kind = |name| detect_file_kind(Path::new(name), b"KEY=value\n");
+        assert_eq!(kind("app/.env").essence_str(), "text/x-dotenv");
+        assert_eq!(kind(".env.production").essence_str(), "text/x-dotenv");
+        assert_eq!(kind("staging.env").essence_str(), "text/x-dotenv");
+        assert_eq!(kind("messages.properties").essence_str(), "text/x-java-properties");
+        assert_eq!(kind("request.binpb").essence_str(), "application/x-protobuf");
+        assert_eq!(kind("environment.txt").essence_str(), "text/plain");
+    }
+
+    #[test]
+    fn detect_file_kind_recognizes_sqlite_by_header() {
+        let mut header = b"SQLite format 3\0".to_vec();
+        header.resize(100, 0);
+        assert_eq!(
+            detect_file_kind(Path::new("data.bin"), &header).essence_str(),
+            "application/vnd.sqlite3"
+        );
+    }
 }
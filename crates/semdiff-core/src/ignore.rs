@@ -0,0 +1,230 @@
+//! Path exclusion with gitignore syntax, used by [`crate::fs::FsNode::with_ignore_rules`].
+
+use std::io;
+use std::path::Path;
+
+/// Name of the ignore file read from the root of each compared tree.
+pub const IGNORE_FILE_NAME: &str = ".semdiffignore";
+
+/// An ordered list of gitignore patterns; the last pattern matching a path decides whether it is
+/// ignored, so a later `!pattern` re-includes what an earlier one excluded.
+///
+/// Supported syntax: `#` comments, `!` negation, `*`, `?`, `[a-z]`/`[!a-z]` classes, `**` as a
+/// whole path segment, a trailing `/` for directories only, and a leading or inner `/` to anchor
+/// the pattern at the root. A pattern without a `/` matches a name at any depth.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+    segments: Vec<Vec<char>>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> IgnoreRules {
+        IgnoreRules::default()
+    }
+
+    /// Parses the lines of an ignore file.
+    pub fn parse(content: &str) -> IgnoreRules {
+        let mut rules = IgnoreRules::new();
+        for line in content.lines() {
+            rules.add_pattern(line);
+        }
+        rules
+    }
+
+    /// Reads the ignore file at `path`; a missing file, or one whose parent is not a directory, has
+    /// no rules.
+    pub fn read_file(path: &Path) -> io::Result<IgnoreRules> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(IgnoreRules::parse(&content)),
+            Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+                Ok(IgnoreRules::new())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Appends one pattern, taking precedence over all earlier ones. Blank lines and comments are
+    /// ignored.
+    pub fn add_pattern(&mut self, pattern: &str) {
+        if let Some(rule) = IgnoreRule::parse(pattern) {
+            self.rules.push(rule);
+        }
+    }
+
+    /// Appends all patterns of `other` after the patterns of `self`.
+    pub fn extend(&mut self, other: IgnoreRules) {
+        self.rules.extend(other.rules);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path`, relative to the root and separated by `/`, is excluded.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let components = path.split('/').collect::<Vec<_>>();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(&components, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<IgnoreRule> {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.starts_with('#') {
+            return None;
+        }
+        let line = trim_unescaped_trailing_spaces(line);
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (
+                false,
+                line.strip_prefix('\\')
+                    .filter(|rest| rest.starts_with(['!', '#']))
+                    .unwrap_or(line),
+            ),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(IgnoreRule {
+            negated,
+            dir_only,
+            anchored,
+            segments: line.split('/').map(|segment| segment.chars().collect()).collect(),
+        })
+    }
+
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            matches_segments(&self.segments, components)
+        } else {
+            components
+                .last()
+                .is_some_and(|name| glob_matches(&self.segments[0], &name.chars().collect::<Vec<_>>()))
+        }
+    }
+}
+
+fn trim_unescaped_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn matches_segments(segments: &[Vec<char>], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((segment, rest)) if segment[..] == ['*', '*'] => {
+            // A trailing `**` matches everything inside, but not the directory itself.
+            let min = usize::from(rest.is_empty());
+            (min..=components.len()).any(|skip| matches_segments(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => components.split_first().is_some_and(|(component, components)| {
+            glob_matches(segment, &component.chars().collect::<Vec<_>>()) && matches_segments(rest, components)
+        }),
+    }
+}
+
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob_matches(rest, &text[1..]),
+        Some(('[', rest)) => match (text.split_first(), parse_class(rest)) {
+            (Some((&c, text)), Some((matches, rest))) => matches(c) && glob_matches(rest, text),
+            (None, _) => false,
+            // An unterminated class is a literal `[`.
+            (Some((&c, text)), None) => c == '[' && glob_matches(rest, text),
+        },
+        Some(('\\', [escaped, rest @ ..])) => text.first() == Some(escaped) && glob_matches(rest, &text[1..]),
+        Some((&literal, rest)) => text.first() == Some(&literal) && glob_matches(rest, &text[1..]),
+    }
+}
+
+/// Parses a character class after its `[`, returning its predicate and the pattern after `]`.
+fn parse_class(pattern: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match pattern.split_first() {
+        Some(('!' | '^', body)) => (true, body),
+        _ => (false, pattern),
+    };
+    // A `]` right after the opening bracket is a member, not the end.
+    let end = body.iter().skip(1).position(|&c| c == ']')? + 1;
+    let (members, rest) = (&body[..end], &body[end + 1..]);
+    let matches = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+        while i < members.len() {
+            if i + 2 < members.len() && members[i + 1] == '-' {
+                found |= (members[i]..=members[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= members[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matches, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_rules_follow_gitignore_syntax() {
+        let rules = IgnoreRules::parse(
+            "# generated output\n\
+             *.log\n\
+             !keep.log\n\
+             build/\n\
+             /root-only.txt\n\
+             docs/**/draft-?.md\n\
+             cache/**\n\
+             [Tt]emp\n\
+             \\#literal\n",
+        );
+
+        assert!(rules.is_ignored("a.log", false));
+        assert!(rules.is_ignored("nested/dir/a.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(rules.is_ignored("build", true));
+        assert!(rules.is_ignored("nested/build", true));
+        assert!(!rules.is_ignored("build", false));
+        assert!(rules.is_ignored("root-only.txt", false));
+        assert!(!rules.is_ignored("nested/root-only.txt", false));
+        assert!(rules.is_ignored("docs/draft-1.md", false));
+        assert!(rules.is_ignored("docs/a/b/draft-2.md", false));
+        assert!(!rules.is_ignored("docs/draft-10.md", false));
+        assert!(!rules.is_ignored("cache", true));
+        assert!(rules.is_ignored("cache/entry", false));
+        assert!(rules.is_ignored("Temp", true));
+        assert!(rules.is_ignored("temp", false));
+        assert!(!rules.is_ignored("tmp", false));
+        assert!(rules.is_ignored("#literal", false));
+        assert!(!rules.is_ignored("generated output", false));
+    }
+}
@@ -0,0 +1,119 @@
+//! Trees built in memory, for comparing content that does not come from a file system, such as
+//! the rows of a database or the responses of an API.
+
+use crate::fs::FileLeaf;
+use crate::{NodeTraverse, TraversalNode};
+use mime::Mime;
+use std::collections::BTreeMap;
+use std::convert;
+use std::mem;
+
+/// A directory of [`MemNode`]s and in-memory [`FileLeaf`]s. Its leaves are `FileLeaf`s, so every
+/// differ that compares files compares them too.
+#[derive(Debug, Clone)]
+pub struct MemNode {
+    name: String,
+    children: BTreeMap<String, MemChild>,
+}
+
+#[derive(Debug, Clone)]
+enum MemChild {
+    Node(MemNode),
+    Leaf(FileLeaf),
+}
+
+impl MemNode {
+    /// An empty node; the name of a root is not part of the reported paths.
+    pub fn new(name: impl Into<String>) -> MemNode {
+        MemNode {
+            name: name.into(),
+            children: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `node` as a child, replacing any child of the same name.
+    pub fn with_node(mut self, node: MemNode) -> Self {
+        self.children.insert(node.name.clone(), MemChild::Node(node));
+        self
+    }
+
+    /// Adds `leaf` as a child, replacing any child of the same name.
+    pub fn with_leaf(mut self, leaf: FileLeaf) -> Self {
+        self.children.insert(leaf.name.clone(), MemChild::Leaf(leaf));
+        self
+    }
+
+    /// Adds a leaf named `name` holding `content` of kind `kind`, as [`FileLeaf::from_bytes`].
+    pub fn with_file(self, name: impl Into<String>, kind: Mime, content: impl Into<Vec<u8>>) -> Self {
+        self.with_leaf(FileLeaf::from_bytes(name, kind, content))
+    }
+}
+
+impl NodeTraverse for MemNode {
+    type Leaf = FileLeaf;
+
+    type TraverseError = convert::Infallible;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn children(
+        &mut self,
+    ) -> Result<impl Iterator<Item = Result<TraversalNode<Self, Self::Leaf>, Self::TraverseError>>, Self::TraverseError>
+    {
+        Ok(mem::take(&mut self.children).into_values().map(|child| {
+            Ok(match child {
+                MemChild::Node(node) => TraversalNode::Node(node),
+                MemChild::Leaf(leaf) => TraversalNode::Leaf(leaf),
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiffOptions, LeafTraverse, Reporter, calc_diff_with_options};
+
+    struct NoReport;
+
+    impl Reporter for NoReport {
+        type Error = convert::Infallible;
+
+        fn start(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn finish(self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mem_nodes_are_compared_like_directories() {
+        let expected = MemNode::new("expected")
+            .with_file("same.json", mime::APPLICATION_JSON, "{}")
+            .with_node(MemNode::new("old").with_file("gone.txt", mime::TEXT_PLAIN, "bye"));
+        let actual = MemNode::new("actual")
+            .with_file("same.json", mime::APPLICATION_JSON, "{}")
+            .with_node(MemNode::new("new").with_file("fresh.txt", mime::TEXT_PLAIN, "hi"));
+
+        // Without differs, only identical leaves are compared, and only the names of the rest.
+        let options = DiffOptions::new().with_identical_fast_path(true);
+        let summary = calc_diff_with_options(expected.clone(), actual.clone(), &[], NoReport, &options).unwrap();
+        assert_eq!(summary.unchanged, 1);
+        let options = DiffOptions::new().with_names_only(true);
+        let summary = calc_diff_with_options(expected, actual, &[], NoReport, &options).unwrap();
+        assert_eq!((summary.added, summary.deleted), (1, 1));
+
+        let mut node = MemNode::new("root")
+            .with_file("b", mime::TEXT_PLAIN, "1")
+            .with_node(MemNode::new("a"))
+            .with_file("b", mime::TEXT_PLAIN, "2");
+        let children = node.children().unwrap().map(Result::unwrap).collect::<Vec<_>>();
+        assert!(matches!(&children[..], [TraversalNode::Node(a), TraversalNode::Leaf(b)]
+            if a.name() == "a" && b.bytes() == Some(b"2".as_slice())));
+        assert!(node.children().unwrap().next().is_none());
+    }
+}
@@ -1,12 +1,37 @@
 use rayon::Scope;
+use rayon::prelude::*;
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert;
 use std::error::Error;
+use std::fmt;
+use std::iter;
 use std::mem;
+use std::num::NonZeroUsize;
 use std::ops::{Deref, DerefMut};
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use thiserror::Error;
 
+pub use order::DifferOrder;
+pub use sample::Sampling;
+
+pub mod archive;
+pub mod collect;
 pub mod fs;
+pub mod ignore;
+pub mod in_memory;
+pub mod memory;
+mod normalize;
+pub mod order;
+pub mod remote;
+pub mod sample;
+pub mod tally;
+pub mod timing;
+pub mod update;
 
 #[cfg(test)]
 mod tests;
@@ -68,6 +93,82 @@ where
 
 pub trait LeafTraverse {
     fn name(&self) -> &str;
+    /// Detected content kind (such as a MIME type), if the leaf knows it.
+    fn kind(&self) -> Option<&str> {
+        None
+    }
+    /// Size of the content in bytes, if the leaf knows it.
+    fn size(&self) -> Option<u64> {
+        None
+    }
+    /// The raw content, if the leaf holds it. A deleted and an added leaf with the same content
+    /// are paired up as a rename without asking the differs (see
+    /// [`DiffOptions::with_rename_threshold`]).
+    fn bytes(&self) -> Option<&[u8]> {
+        None
+    }
+    /// Other leaves at the same path that `expected` may match instead of this one, such as the
+    /// outputs of repeated runs of a nondeterministic test.
+    fn candidates(&self) -> &[Self]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+}
+
+/// Why a leaf was left out of the comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Smaller than the traversal's minimum size.
+    TooSmall,
+    /// Larger than the traversal's maximum size.
+    TooLarge,
+    /// No differ handled the leaf.
+    Unsupported,
+    /// Neither a regular file nor a directory, such as a fifo, socket, or device.
+    SpecialFile,
+    /// An archive entry that could not be decompressed, such as an encrypted or corrupt one, or a
+    /// file of a remote tree that could not be fetched.
+    Unreadable,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::TooSmall => "too_small",
+            SkipReason::TooLarge => "too_large",
+            SkipReason::Unsupported => "unsupported",
+            SkipReason::SpecialFile => "special_file",
+            SkipReason::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// Whether a child is a node or a leaf, for [`Reporter::report_type_changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+    Node,
+    Leaf,
+}
+
+impl EntryType {
+    /// The name of the type in a file system, where nodes are directories and leaves are files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryType::Node => "directory",
+            EntryType::Leaf => "file",
+        }
+    }
+}
+
+impl<Node, Leaf> TraversalNode<Node, Leaf> {
+    fn entry_type(&self) -> EntryType {
+        match self {
+            TraversalNode::Node(_) => EntryType::Node,
+            TraversalNode::Leaf(_) => EntryType::Leaf,
+        }
+    }
 }
 
 pub trait NodeTraverse: Sized {
@@ -78,10 +179,83 @@ pub trait NodeTraverse: Sized {
     fn children(
         &mut self,
     ) -> Result<impl Iterator<Item = Result<TraversalNode<Self, Self::Leaf>, Self::TraverseError>>, Self::TraverseError>;
+    /// Names of the children the traversal left out of the last [`NodeTraverse::children`] call,
+    /// with the reason; they are passed to [`Reporter::report_skipped`].
+    fn take_skipped(&mut self) -> Vec<(String, SkipReason)> {
+        Vec::new()
+    }
 }
 
 pub trait Diff {
     fn equal(&self) -> bool;
+    /// How different the two sides are, from 0.0 (equal) to 1.0 (entirely different), if the
+    /// differ can tell.
+    fn magnitude(&self) -> Option<f64> {
+        None
+    }
+    /// Whether the two sides are byte-for-byte identical, if the differ compares decoded content
+    /// and can therefore find equality between differently encoded files.
+    fn byte_identical(&self) -> Option<bool> {
+        None
+    }
+    /// The tolerances that decided [`Diff::equal`], with what was measured against each.
+    fn tolerance_checks(&self) -> Vec<ToleranceCheck> {
+        Vec::new()
+    }
+}
+
+/// A metric compared against a tolerance; the sides count as equal only while `measured` is at
+/// most `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToleranceCheck {
+    pub metric: &'static str,
+    pub measured: f64,
+    pub threshold: f64,
+}
+
+impl ToleranceCheck {
+    /// How far `measured` is from `threshold`, as a fraction of `threshold`; `None` for a zero
+    /// tolerance, where any difference is a clear one.
+    pub fn relative_margin(&self) -> Option<f64> {
+        (self.threshold > 0.0).then(|| (self.measured - self.threshold).abs() / self.threshold)
+    }
+
+    /// Whether `measured` is within `band` (a fraction of `threshold`) of `threshold` on either
+    /// side, so a small change could flip the result.
+    pub fn is_borderline(&self, band: f64) -> bool {
+        self.relative_margin().is_some_and(|margin| margin <= band)
+    }
+}
+
+/// A pair of leaves of which only one side could be read as a differ's format, such as valid JSON
+/// in `expected` and malformed JSON in `actual`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatRegression {
+    /// Name of the format, like `"JSON"`.
+    pub format: &'static str,
+    /// Whether `expected` is the side that parsed; otherwise only `actual` did.
+    pub expected_parsed: bool,
+}
+
+impl FormatRegression {
+    /// Returns `None` unless exactly one of the sides parsed as `format`.
+    pub fn from_parsed(format: &'static str, expected_parsed: bool, actual_parsed: bool) -> Option<FormatRegression> {
+        (expected_parsed != actual_parsed).then_some(FormatRegression {
+            format,
+            expected_parsed,
+        })
+    }
+}
+
+impl fmt::Display for FormatRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (parsed, failed) = if self.expected_parsed {
+            ("expected", "actual")
+        } else {
+            ("actual", "expected")
+        };
+        write!(f, "{parsed} parsed as {}, {failed} did not", self.format)
+    }
 }
 
 #[derive(Debug)]
@@ -90,10 +264,43 @@ pub enum MayUnsupported<T> {
     Unsupported,
 }
 
+impl<T> MayUnsupported<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> MayUnsupported<U> {
+        match self {
+            MayUnsupported::Ok(value) => MayUnsupported::Ok(f(value)),
+            MayUnsupported::Unsupported => MayUnsupported::Unsupported,
+        }
+    }
+}
+
+/// Whether a pair of leaves diffed by [`DiffReport::diff`] was reported as unchanged or modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOutcome {
+    Unchanged,
+    Modified,
+}
+
 pub trait DiffCalculator<T> {
     type Error: Error + Send + 'static;
     type Diff: Diff + Send;
     fn diff(&self, name: &str, expected: T, actual: T) -> Result<MayUnsupported<Self::Diff>, Self::Error>;
+    /// How alike a deleted and an added leaf are, from 0.0 (unrelated) to 1.0 (equal), if the
+    /// differ can tell; used to pair them up as a rename (see [`DiffOptions::with_rename_threshold`]).
+    fn similarity(&self, _expected: &T, _actual: &T) -> Option<f64> {
+        None
+    }
+    /// Whether exactly one of `expected` and `actual` is in this differ's format, asked after
+    /// [`DiffCalculator::diff`] returned [`MayUnsupported::Unsupported`] for them when enabled
+    /// through [`DiffOptions::with_format_regressions`].
+    fn format_regression(&self, _expected: &T, _actual: &T) -> Option<FormatRegression> {
+        None
+    }
+    /// Whether `leaf` has a kind this differ handles, usually guessed from its extension, but
+    /// cannot be read in that format; asked when this differ did not handle the leaf and strict
+    /// extensions are enabled through [`DiffOptions::with_strict_extensions`].
+    fn unreadable(&self, _leaf: &T) -> bool {
+        false
+    }
 }
 
 pub trait DetailReporter<Diff, T, Reporter> {
@@ -189,9 +396,22 @@ pub trait DiffReport<T, Reporter>: __sealed::Sealed + Sync {
         expected: T,
         actual: T,
         reporter: &Reporter,
-    ) -> Result<MayUnsupported<()>, Box<dyn Error + Send>>;
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>>;
     fn added(&self, name: &str, data: T, reporter: &Reporter) -> Result<MayUnsupported<()>, Box<dyn Error + Send>>;
     fn deleted(&self, name: &str, data: T, reporter: &Reporter) -> Result<MayUnsupported<()>, Box<dyn Error + Send>>;
+    /// Diffs `expected` against each of `candidates` and reports only one of them: the first that is
+    /// equal, or else the one with the smallest [`Diff::magnitude`]. Candidates the differ does not
+    /// support are passed over.
+    fn diff_candidates(
+        &self,
+        name: &str,
+        expected: T,
+        candidates: Vec<T>,
+        reporter: &Reporter,
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>>;
+    fn similarity(&self, expected: &T, actual: &T) -> Option<f64>;
+    fn format_regression(&self, expected: &T, actual: &T) -> Option<FormatRegression>;
+    fn unreadable(&self, leaf: &T) -> bool;
 }
 
 #[derive(Debug)]
@@ -208,12 +428,55 @@ impl<DiffCalculator, DetailReporter> DiffAndReport<DiffCalculator, DetailReporte
 
 impl<DiffCalculator, DetailReporter> __sealed::Sealed for DiffAndReport<DiffCalculator, DetailReporter> {}
 
+impl<D, R> DiffAndReport<D, R> {
+    fn report_diff<T, Reporter>(
+        &self,
+        name: &str,
+        diff: &D::Diff,
+        reporter: &Reporter,
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>>
+    where
+        D: DiffCalculator<T>,
+        R: DetailReporter<D::Diff, T, Reporter>,
+        Reporter: crate::Reporter,
+    {
+        for check in diff.tolerance_checks() {
+            reporter
+                .report_tolerance_check(name, &check)
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+        }
+        if diff.equal() {
+            if diff.byte_identical() == Some(false) {
+                reporter
+                    .report_reencoded(name)
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+            }
+            let result = self
+                .report
+                .report_unchanged(name, diff, reporter)
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+            Ok(result.map(|()| DiffOutcome::Unchanged))
+        } else {
+            let result = self
+                .report
+                .report_modified(name, diff, reporter)
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+            if let (MayUnsupported::Ok(()), Some(magnitude)) = (&result, diff.magnitude()) {
+                reporter
+                    .report_change_magnitude(name, magnitude)
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+            }
+            Ok(result.map(|()| DiffOutcome::Modified))
+        }
+    }
+}
+
 impl<D, R, T, Reporter> DiffReport<T, Reporter> for DiffAndReport<D, R>
 where
     D: DiffCalculator<T> + Sync,
     R: DetailReporter<D::Diff, T, Reporter> + Sync,
-    T: Send,
-    Reporter: Sync,
+    T: Send + Clone,
+    Reporter: crate::Reporter + Sync,
 {
     fn diff(
         &self,
@@ -221,7 +484,7 @@ where
         expected: T,
         actual: T,
         reporter: &Reporter,
-    ) -> Result<MayUnsupported<()>, Box<dyn Error + Send>> {
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>> {
         let diff = self
             .diff
             .diff(name, expected, actual)
@@ -229,15 +492,7 @@ where
         let MayUnsupported::Ok(diff) = diff else {
             return Ok(MayUnsupported::Unsupported);
         };
-        if diff.equal() {
-            self.report
-                .report_unchanged(name, &diff, reporter)
-                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
-        } else {
-            self.report
-                .report_modified(name, &diff, reporter)
-                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
-        }
+        self.report_diff(name, &diff, reporter)
     }
 
     fn added(&self, name: &str, data: T, reporter: &Reporter) -> Result<MayUnsupported<()>, Box<dyn Error + Send>> {
@@ -251,12 +506,124 @@ where
             .report_deleted(name, &data, reporter)
             .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
     }
+
+    fn diff_candidates(
+        &self,
+        name: &str,
+        expected: T,
+        candidates: Vec<T>,
+        reporter: &Reporter,
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>> {
+        let mut closest: Option<(D::Diff, f64)> = None;
+        for actual in candidates {
+            let diff = self
+                .diff
+                .diff(name, expected.clone(), actual)
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+            let MayUnsupported::Ok(diff) = diff else {
+                continue;
+            };
+            if diff.equal() {
+                return self.report_diff(name, &diff, reporter);
+            }
+            // A diff without a magnitude is only chosen when no other candidate has one.
+            let magnitude = diff.magnitude().unwrap_or(f64::INFINITY);
+            if closest.as_ref().is_none_or(|(_, closest)| magnitude < *closest) {
+                closest = Some((diff, magnitude));
+            }
+        }
+        match closest {
+            Some((diff, _)) => self.report_diff(name, &diff, reporter),
+            None => Ok(MayUnsupported::Unsupported),
+        }
+    }
+
+    fn similarity(&self, expected: &T, actual: &T) -> Option<f64> {
+        self.diff.similarity(expected, actual)
+    }
+
+    fn format_regression(&self, expected: &T, actual: &T) -> Option<FormatRegression> {
+        self.diff.format_regression(expected, actual)
+    }
+
+    fn unreadable(&self, leaf: &T) -> bool {
+        self.diff.unreadable(leaf)
+    }
 }
 
 pub trait Reporter {
     type Error: Error + Send + 'static;
     fn start(&mut self) -> Result<(), Self::Error>;
     fn finish(self) -> Result<(), Self::Error>;
+    /// Called before diffing a pair of leaves whose detected kinds differ.
+    fn report_kind_mismatch(&self, _name: &str, _expected_kind: &str, _actual_kind: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called before diffing a pair of leaves with the remaining differs when a differ found that
+    /// only one side is in its format, when enabled through [`DiffOptions::with_format_regressions`].
+    fn report_format_regression(&self, _name: &str, _regression: &FormatRegression) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called once after every leaf has been reported when only a sample of the leaves was
+    /// compared through [`DiffOptions::with_sampling`], with how many leaves were left out.
+    fn report_sampling(&self, _sampling: &Sampling, _left_out: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called after a modified leaf has been reported, with its [`Diff::magnitude`].
+    fn report_change_magnitude(&self, _name: &str, _magnitude: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called before an unchanged leaf is reported when its decoded content is equal but its
+    /// bytes differ (see [`Diff::byte_identical`]).
+    fn report_reencoded(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called instead of the differs for a pair of leaves with the same bytes, when enabled
+    /// through [`DiffOptions::with_identical_fast_path`]. The leaf counts as unchanged.
+    fn report_identical(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called before a diffed leaf is reported, once for each of its [`Diff::tolerance_checks`].
+    fn report_tolerance_check(&self, _name: &str, _check: &ToleranceCheck) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called for a leaf that was left out of the comparison, either by the traversal or because
+    /// no differ handled it.
+    fn report_skipped(&self, _name: &str, _reason: SkipReason) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called for a node without children that only exists in `actual`, when enabled through
+    /// [`DiffOptions::with_empty_nodes`].
+    fn report_added_empty_node(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called for a node without children that only exists in `expected`, when enabled through
+    /// [`DiffOptions::with_empty_nodes`].
+    fn report_deleted_empty_node(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called instead of the differs for a leaf that only exists in `actual`, when enabled through
+    /// [`DiffOptions::with_names_only`].
+    fn report_added_leaf(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called instead of the differs for a leaf that only exists in `expected`, when enabled
+    /// through [`DiffOptions::with_names_only`].
+    fn report_deleted_leaf(&self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called for a name that is a node on one side and a leaf on the other, before either is
+    /// reported. They are not compared: the node's children and the leaf are still reported as
+    /// added or deleted.
+    fn report_type_changed(&self, _name: &str, _expected: EntryType, _actual: EntryType) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called before diffing a leaf that only exists in `actual` against the leaf `from` that only
+    /// exists in `expected`, when they were paired up as a rename through
+    /// [`DiffOptions::with_rename_threshold`].
+    fn report_renamed(&self, _name: &str, _from: &str, _similarity: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 impl<R1, R2> Reporter for (R1, R2)
@@ -279,6 +646,110 @@ where
         result2.map_err(EitherError::Right)?;
         Ok(())
     }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.0
+            .report_kind_mismatch(name, expected_kind, actual_kind)
+            .map_err(EitherError::Left)?;
+        self.1
+            .report_kind_mismatch(name, expected_kind, actual_kind)
+            .map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.0
+            .report_format_regression(name, regression)
+            .map_err(EitherError::Left)?;
+        self.1
+            .report_format_regression(name, regression)
+            .map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        self.0.report_sampling(sampling, left_out).map_err(EitherError::Left)?;
+        self.1.report_sampling(sampling, left_out).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_change_magnitude(&self, name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        self.0
+            .report_change_magnitude(name, magnitude)
+            .map_err(EitherError::Left)?;
+        self.1
+            .report_change_magnitude(name, magnitude)
+            .map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_reencoded(name).map_err(EitherError::Left)?;
+        self.1.report_reencoded(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_identical(name).map_err(EitherError::Left)?;
+        self.1.report_identical(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        self.0.report_tolerance_check(name, check).map_err(EitherError::Left)?;
+        self.1.report_tolerance_check(name, check).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.0.report_skipped(name, reason).map_err(EitherError::Left)?;
+        self.1.report_skipped(name, reason).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.0
+            .report_type_changed(name, expected, actual)
+            .map_err(EitherError::Left)?;
+        self.1
+            .report_type_changed(name, expected, actual)
+            .map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_added_empty_node(name).map_err(EitherError::Left)?;
+        self.1.report_added_empty_node(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_deleted_empty_node(name).map_err(EitherError::Left)?;
+        self.1.report_deleted_empty_node(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_added_leaf(name).map_err(EitherError::Left)?;
+        self.1.report_added_leaf(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.0.report_deleted_leaf(name).map_err(EitherError::Left)?;
+        self.1.report_deleted_leaf(name).map_err(EitherError::Right)?;
+        Ok(())
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        self.0
+            .report_renamed(name, from, similarity)
+            .map_err(EitherError::Left)?;
+        self.1
+            .report_renamed(name, from, similarity)
+            .map_err(EitherError::Right)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -289,78 +760,704 @@ pub enum CalcDiffError<TraverseError, ReporterError> {
     ReporterError(#[source] ReporterError),
     #[error("{0}")]
     DiffError(#[source] Box<dyn Error + Send>),
-    #[error("No diff report matched")]
-    NoDiffReportMatched,
+    #[error("{side} {name} is {kind} by its extension but could not be read as one")]
+    Unreadable {
+        name: String,
+        side: &'static str,
+        kind: String,
+    },
+    /// The [`CancelToken`] of the run was cancelled.
+    #[error("the comparison was cancelled")]
+    Cancelled,
+}
+
+/// How many leaves a [`calc_diff`] run reported in each state. Leaves that were sampled out,
+/// skipped, or only compared by name on both sides are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    pub unchanged: usize,
+    pub modified: usize,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+impl DiffSummary {
+    /// Whether any leaf was modified, added, or deleted.
+    pub fn has_changes(&self) -> bool {
+        self.modified + self.added + self.deleted > 0
+    }
+}
+
+/// The leaves counted while the differs run in parallel, gathered into a [`DiffSummary`] at the
+/// end.
+#[derive(Debug, Default)]
+struct RunCounts {
+    unchanged: AtomicUsize,
+    modified: AtomicUsize,
+    added: AtomicUsize,
+    deleted: AtomicUsize,
+    sampled_out: AtomicUsize,
+}
+
+impl RunCounts {
+    fn summary(&self) -> DiffSummary {
+        DiffSummary {
+            unchanged: self.unchanged.load(AtomicOrdering::Relaxed),
+            modified: self.modified.load(AtomicOrdering::Relaxed),
+            added: self.added.load(AtomicOrdering::Relaxed),
+            deleted: self.deleted.load(AtomicOrdering::Relaxed),
+        }
+    }
+}
+
+/// Bounds the leaf diffs that have been spawned and not yet finished (see
+/// [`DiffOptions::with_max_in_flight`]).
+#[derive(Debug)]
+struct InFlight {
+    limit: Option<NonZeroUsize>,
+    running: AtomicUsize,
+}
+
+impl InFlight {
+    fn new(limit: Option<NonZeroUsize>) -> InFlight {
+        InFlight {
+            limit,
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits until another leaf diff may start, and holds its slot until the returned guard is
+    /// dropped, even by a panicking differ. Pending tasks of the pool are run meanwhile, so the
+    /// wait cannot starve a pool with a single thread.
+    fn acquire(&self) -> InFlightSlot<'_> {
+        let slot = InFlightSlot(self);
+        let Some(limit) = self.limit else {
+            return slot;
+        };
+        while self
+            .running
+            .fetch_update(AtomicOrdering::AcqRel, AtomicOrdering::Acquire, |running| {
+                (running < limit.get()).then_some(running + 1)
+            })
+            .is_err()
+        {
+            if rayon::yield_now() != Some(rayon::Yield::Executed) {
+                thread::yield_now();
+            }
+        }
+        slot
+    }
+}
+
+/// A running leaf diff counted by [`InFlight::acquire`], released on drop.
+struct InFlightSlot<'a>(&'a InFlight);
+
+impl Drop for InFlightSlot<'_> {
+    fn drop(&mut self) {
+        if self.0.limit.is_some() {
+            self.0.running.fetch_sub(1, AtomicOrdering::Release);
+        }
+    }
+}
+
+/// Decides from its path from the root and its type whether a child is walked at all; see
+/// [`DiffOptions::with_entry_filter`].
+///
+/// The filter is given an [`EntryType`] rather than the [`TraversalNode`] itself: it is held by
+/// [`DiffOptions`], which is shared by walks over any kind of [`NodeTraverse`] and so cannot name the
+/// node type of one of them. A filter that needs more than the path and the type, such as the size
+/// of a file, belongs in the tree, the way [`fs::FileSizeFilter`] is.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct EntryFilter(Arc<dyn Fn(&str, EntryType) -> bool + Send + Sync>);
+
+impl EntryFilter {
+    /// Keeps the children for which `keep` returns `true`, such as
+    /// `|path, _| !path.ends_with("node_modules")`.
+    pub fn new(keep: impl Fn(&str, EntryType) -> bool + Send + Sync + 'static) -> EntryFilter {
+        EntryFilter(Arc::new(keep))
+    }
+
+    fn keeps(&self, path: &str, entry_type: EntryType) -> bool {
+        (self.0)(path, entry_type)
+    }
+}
+
+impl fmt::Debug for EntryFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EntryFilter(..)")
+    }
+}
+
+/// Whether a leaf has just been handed out to be diffed or has just been diffed, for
+/// [`DiffOptions::with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    Scheduled,
+    Completed,
+}
+
+/// A step of a run passed to the [`Progress`] callback: the path of a leaf from the root, and what
+/// happened to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent<'a> {
+    pub path: &'a str,
+    pub kind: ProgressKind,
+}
+
+/// Called as leaves are scheduled and completed; see [`DiffOptions::with_progress`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct Progress(Arc<dyn Fn(ProgressEvent<'_>) + Send + Sync>);
+
+impl Progress {
+    pub fn new(callback: impl Fn(ProgressEvent<'_>) + Send + Sync + 'static) -> Progress {
+        Progress(Arc::new(callback))
+    }
+
+    fn report(&self, path: &str, kind: ProgressKind) {
+        (self.0)(ProgressEvent { path, kind })
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress(..)")
+    }
+}
+
+/// Stops a run from another thread, such as the UI thread of an application; see
+/// [`DiffOptions::with_cancel_token`]. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// Options controlling how [`calc_diff_with_options`] walks the two trees.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    report_empty_nodes: bool,
+    names_only: bool,
+    rename_threshold: Option<f64>,
+    breadth_first: bool,
+    normalize_names: bool,
+    fold_name_case: bool,
+    format_regressions: bool,
+    strict_extensions: bool,
+    sampling: Option<Sampling>,
+    differ_order: DifferOrder,
+    max_in_flight: Option<NonZeroUsize>,
+    entry_filter: Option<EntryFilter>,
+    progress: Option<Progress>,
+    identical_fast_path: bool,
+    cancel_token: Option<CancelToken>,
+    /// Set by [`calc_diff_collect`] to carry on past errors.
+    collect_errors: bool,
+}
+
+impl DiffOptions {
+    pub fn new() -> DiffOptions {
+        DiffOptions::default()
+    }
+
+    /// Reports nodes without children that exist on only one side, which otherwise produce no
+    /// events because only leaves are diffed.
+    pub fn with_empty_nodes(mut self, report_empty_nodes: bool) -> Self {
+        self.report_empty_nodes = report_empty_nodes;
+        self
+    }
+
+    /// Only compares which leaves exist: leaves on both sides are not diffed, and leaves on one
+    /// side go to [`Reporter::report_added_leaf`] and [`Reporter::report_deleted_leaf`] instead of
+    /// the differs.
+    pub fn with_names_only(mut self, names_only: bool) -> Self {
+        self.names_only = names_only;
+        self
+    }
+
+    /// Pairs up leaves deleted from `expected` with leaves added to `actual` whose
+    /// [`DiffCalculator::similarity`] is at least `threshold`, and diffs each pair as a rename
+    /// instead of reporting a deletion and an addition.
+    pub fn with_rename_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.rename_threshold = threshold;
+        self
+    }
+
+    /// Walks the trees level by level instead of descending into each node as it is found, so the
+    /// leaves of shallow nodes are diffed, and reach a streaming reporter, before deeper ones.
+    pub fn with_breadth_first(mut self, breadth_first: bool) -> Self {
+        self.breadth_first = breadth_first;
+        self
+    }
+
+    /// Pairs children whose names are equal after Unicode normalization (NFC), so a name stored
+    /// decomposed (NFD, as on older macOS file systems) matches its precomposed spelling.
+    /// Reports use the name from `expected`.
+    pub fn with_normalized_names(mut self, normalize_names: bool) -> Self {
+        self.normalize_names = normalize_names;
+        self
+    }
+
+    /// Pairs children whose names are equal ignoring case, as on case-insensitive file systems.
+    /// Reports use the name from `expected`.
+    pub fn with_case_folded_names(mut self, fold_name_case: bool) -> Self {
+        self.fold_name_case = fold_name_case;
+        self
+    }
+
+    /// Asks each differ that does not handle a pair of leaves whether only one side is in its
+    /// format, and passes the first such finding to [`Reporter::report_format_regression`] before
+    /// the pair falls through to the next differs.
+    pub fn with_format_regressions(mut self, format_regressions: bool) -> Self {
+        self.format_regressions = format_regressions;
+        self
+    }
+
+    /// Fails with [`CalcDiffError::Unreadable`] when a leaf cannot be read in the format of a
+    /// differ that handles its kind, such as a corrupt `.png`, instead of letting it be diffed by
+    /// the text or binary differ.
+    pub fn with_strict_extensions(mut self, strict_extensions: bool) -> Self {
+        self.strict_extensions = strict_extensions;
+        self
+    }
+
+    /// Compares only the leaves that `sampling` picks by their path, for a quick check of a large
+    /// tree. Leaves left out are not read and appear in no report; their number is passed to
+    /// [`Reporter::report_sampling`].
+    pub fn with_sampling(mut self, sampling: Option<Sampling>) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Tries the differs in the order `differ_order` picks for each leaf's extension, instead of
+    /// the order of the list passed to [`calc_diff_with_options`]. This applies to added and
+    /// deleted leaves too.
+    pub fn with_differ_order(mut self, differ_order: DifferOrder) -> Self {
+        self.differ_order = differ_order;
+        self
+    }
+
+    /// Lets at most `max_in_flight` leaves be diffed at once. The walk waits for a running diff to
+    /// finish before it hands out the next leaf, so a huge tree does not queue up a task, and
+    /// keep open the files it needs, for each of its leaves. Without a limit every leaf is handed
+    /// out as soon as it is found.
+    pub fn with_max_in_flight(mut self, max_in_flight: Option<NonZeroUsize>) -> Self {
+        self.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Leaves out the children that `entry_filter` rejects, on both sides: a node is not
+    /// descended into, and a leaf is not diffed. They appear in no report, not even as skipped.
+    pub fn with_entry_filter(mut self, entry_filter: Option<EntryFilter>) -> Self {
+        self.entry_filter = entry_filter;
+        self
+    }
+
+    /// Reports a pair of leaves whose [`LeafTraverse::bytes`] are equal through
+    /// [`Reporter::report_identical`] without handing them to the differs, which saves decoding
+    /// large images or audio that did not change. Comparing the bytes reads both files in full,
+    /// which the differs may not need to do, and leaves without bytes are always diffed. A
+    /// corrupt file passes when both sides are the same, even with
+    /// [`DiffOptions::with_strict_extensions`].
+    ///
+    /// The bytes are compared directly, with those of `actual` and of every rename candidate, and
+    /// the comparison stops at the first differing byte.
+    pub fn with_identical_fast_path(mut self, identical_fast_path: bool) -> Self {
+        self.identical_fast_path = identical_fast_path;
+        self
+    }
+
+    /// Calls `progress` with [`ProgressKind::Scheduled`] when a leaf is handed out to be diffed,
+    /// compared against nothing when it was added or deleted, and with [`ProgressKind::Completed`]
+    /// once it is done, whether or not that failed. Leaves are diffed in parallel, so the
+    /// callback is called from many threads at once, and completions come in any order.
+    pub fn with_progress(mut self, progress: Option<Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Stops the run once `cancel_token` is cancelled: no node is read and no leaf is diffed after
+    /// that, while the diffs already running finish. The run then fails with
+    /// [`CalcDiffError::Cancelled`] without calling [`Reporter::finish`], so the reporter has seen
+    /// an arbitrary part of the leaves and should be discarded. [`calc_diff_with_options`] returns
+    /// it in place of any other error, and [`calc_diff_collect`] returns it after all the others.
+    pub fn with_cancel_token(mut self, cancel_token: Option<CancelToken>) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Whether the child `child` of the node at path `parent` is left out by the entry filter.
+    fn filters_out<Node, Leaf>(&self, parent: &str, child: &TraversalNode<Node, Leaf>) -> bool
+    where
+        Node: NodeTraverse,
+        Leaf: LeafTraverse,
+    {
+        let Some(entry_filter) = &self.entry_filter else {
+            return false;
+        };
+        let entry_type = match child {
+            TraversalNode::Node(_) => EntryType::Node,
+            TraversalNode::Leaf(_) => EntryType::Leaf,
+        };
+        let name = child_name(child);
+        if parent.is_empty() {
+            !entry_filter.keeps(name, entry_type)
+        } else {
+            !entry_filter.keeps(&format!("{parent}/{name}"), entry_type)
+        }
+    }
+
+    /// Whether the leaf named `leaf` in the node at path `parent` is left out by sampling.
+    fn samples_out(&self, parent: &str, leaf: &str) -> bool {
+        self.sampling.is_some_and(|sampling| !sampling.includes(parent, leaf))
+    }
+
+    /// How children are paired by name, for finding a reported entry on the `actual` side.
+    pub fn name_matching(&self) -> NameMatching {
+        NameMatching {
+            normalize: self.normalize_names,
+            fold_case: self.fold_name_case,
+        }
+    }
+
+    /// The form of `name` that is compared when pairing children.
+    fn name_key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        self.name_matching().key(name)
+    }
+
+    /// Orders children like [`TraversalNode`]'s `Ord`, comparing names by [`DiffOptions::name_key`].
+    fn cmp_children<Node, Leaf>(&self, a: &TraversalNode<Node, Leaf>, b: &TraversalNode<Node, Leaf>) -> Ordering
+    where
+        Node: NodeTraverse,
+        Leaf: LeafTraverse,
+    {
+        if !self.normalize_names && !self.fold_name_case {
+            return a.cmp(b);
+        }
+        let is_leaf = |node: &TraversalNode<Node, Leaf>| matches!(node, TraversalNode::Leaf(_));
+        is_leaf(a)
+            .cmp(&is_leaf(b))
+            .then_with(|| self.name_key(child_name(a)).cmp(&self.name_key(child_name(b))))
+    }
+}
+
+/// How [`DiffOptions::with_normalized_names`] and [`DiffOptions::with_case_folded_names`] pair
+/// children. Reports use the names from `expected`, which may be spelled differently on disk in
+/// `actual`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameMatching {
+    normalize: bool,
+    fold_case: bool,
+}
+
+impl NameMatching {
+    fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let name = if self.fold_case {
+            Cow::Owned(name.chars().flat_map(char::to_lowercase).collect())
+        } else {
+            Cow::Borrowed(name)
+        };
+        if !self.normalize {
+            return name;
+        }
+        match name {
+            Cow::Borrowed(name) => normalize::nfc(name),
+            Cow::Owned(name) => Cow::Owned(normalize::nfc(&name).into_owned()),
+        }
+    }
+
+    /// The path below `root` of the entry reported as `name`. Each component that does not exist
+    /// as spelled is looked up among the entries of its directory by comparing names as the walk
+    /// pairs them; the path is returned as spelled when nothing matches.
+    pub fn locate(&self, root: &Path, name: &str) -> PathBuf {
+        let mut path = root.to_path_buf();
+        for component in name.split('/') {
+            let spelled = path.join(component);
+            if *self == NameMatching::default() || spelled.symlink_metadata().is_ok() {
+                path = spelled;
+                continue;
+            }
+            let key = self.key(component);
+            let mut matching = std::fs::read_dir(&path)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|entry| self.key(entry) == key)
+                .collect::<Vec<_>>();
+            // Names that only differ in their raw form are paired in this order by the walk.
+            matching.sort();
+            path = match matching.first() {
+                Some(entry) => path.join(entry),
+                None => spelled,
+            };
+        }
+        path
+    }
+}
+
+fn child_name<Node, Leaf>(node: &TraversalNode<Node, Leaf>) -> &str
+where
+    Node: NodeTraverse,
+    Leaf: LeafTraverse,
+{
+    match node {
+        TraversalNode::Node(node) => node.name(),
+        TraversalNode::Leaf(leaf) => leaf.name(),
+    }
 }
 
 pub fn calc_diff<N, R>(
+    expected: N,
+    actual: N,
+    diff: &[Box<dyn DiffReport<N::Leaf, R>>],
+    reporter: R,
+) -> Result<DiffSummary, CalcDiffError<N::TraverseError, R::Error>>
+where
+    N: NodeTraverse + Send,
+    N::Leaf: Send + Sync,
+    R: Reporter + Sync,
+{
+    calc_diff_with_options(expected, actual, diff, reporter, &DiffOptions::default())
+}
+
+pub fn calc_diff_with_options<N, R>(
+    expected: N,
+    actual: N,
+    diff: &[Box<dyn DiffReport<N::Leaf, R>>],
+    reporter: R,
+    options: &DiffOptions,
+) -> Result<DiffSummary, CalcDiffError<N::TraverseError, R::Error>>
+where
+    N: NodeTraverse + Send,
+    N::Leaf: Send + Sync,
+    R: Reporter + Sync,
+{
+    // A cancelled run fails as cancelled, whatever else went wrong before it stopped.
+    walk(expected, actual, diff, reporter, options).map_err(|mut errors| {
+        errors
+            .pop_if(|error| matches!(error, CalcDiffError::Cancelled))
+            .unwrap_or_else(|| errors.remove(0))
+    })
+}
+
+/// Every error of a run of [`calc_diff_collect`], in the order they occurred.
+pub type CalcDiffErrors<TraverseError, ReporterError> = Vec<CalcDiffError<TraverseError, ReporterError>>;
+
+/// Compares the trees like [`calc_diff_with_options`], but carries on after an error instead of
+/// stopping at the first one: a node whose children cannot be listed, a child that cannot be
+/// read, and a leaf that fails to diff are left out, and everything else is still reported.
+/// The reporter is finished either way unless the run is cancelled, and every error is returned in
+/// the order it occurred.
+pub fn calc_diff_collect<N, R>(
+    expected: N,
+    actual: N,
+    diff: &[Box<dyn DiffReport<N::Leaf, R>>],
+    reporter: R,
+    options: &DiffOptions,
+) -> Result<DiffSummary, CalcDiffErrors<N::TraverseError, R::Error>>
+where
+    N: NodeTraverse + Send,
+    N::Leaf: Send + Sync,
+    R: Reporter + Sync,
+{
+    let options = DiffOptions {
+        collect_errors: true,
+        ..options.clone()
+    };
+    walk(expected, actual, diff, reporter, &options)
+}
+
+/// Walks the trees, stopping at the first error unless `options.collect_errors` is set.
+/// Fails with at least one error, the last of which is [`CalcDiffError::Cancelled`] when the run
+/// was cancelled.
+fn walk<N, R>(
     expected: N,
     actual: N,
     diff: &[Box<dyn DiffReport<N::Leaf, R>>],
     mut reporter: R,
-) -> Result<(), CalcDiffError<N::TraverseError, R::Error>>
+    options: &DiffOptions,
+) -> Result<DiffSummary, CalcDiffErrors<N::TraverseError, R::Error>>
 where
     N: NodeTraverse + Send,
-    N::Leaf: Send,
+    N::Leaf: Send + Sync,
     R: Reporter + Sync,
 {
-    reporter.start().map_err(CalcDiffError::ReporterError)?;
-    let errors = Mutex::new(None);
+    reporter
+        .start()
+        .map_err(|error| vec![CalcDiffError::ReporterError(error)])?;
+    let errors = Mutex::new(Vec::new());
+    let unpaired = UnpairedLeaves::default();
+    let counts = RunCounts::default();
+    let in_flight = InFlight::new(options.max_in_flight);
+    let mut pending = VecDeque::from([PendingNode {
+        name: String::new(),
+        expected: Some(expected),
+        actual: Some(actual),
+    }]);
     rayon::scope(|scope| {
-        if let Err(error) = calc_diff_inner::<N, R, R::Error>(
-            &mut String::new(),
-            Some(expected),
-            Some(actual),
-            diff,
-            &reporter,
-            scope,
-            &errors,
-        ) {
-            record_error(&errors, error);
+        while let Some(PendingNode {
+            mut name,
+            expected,
+            actual,
+        }) = pending.pop_front()
+        {
+            if options.cancelled() {
+                break;
+            }
+            if let Err(error) = calc_diff_inner::<N, R, R::Error>(
+                &mut name,
+                expected,
+                actual,
+                diff,
+                &reporter,
+                options,
+                &unpaired,
+                &counts,
+                &in_flight,
+                scope,
+                &errors,
+                &mut pending,
+            ) {
+                record_error(&errors, error);
+                if !options.collect_errors {
+                    break;
+                }
+            }
         }
     });
-    if let Some(error) = errors.lock().unwrap().take() {
-        return Err(error);
+    let stops = |errors: &Mutex<Vec<_>>| !options.collect_errors && !errors.lock().unwrap().is_empty();
+    let fail = |errors: Mutex<Vec<_>>| {
+        let mut errors = errors.into_inner().unwrap();
+        if options.cancelled() {
+            errors.push(CalcDiffError::Cancelled);
+        }
+        Err(errors)
+    };
+    if stops(&errors) || options.cancelled() {
+        return fail(errors);
+    }
+    if let Some(threshold) = options.rename_threshold {
+        diff_renames::<N, R, R::Error>(
+            unpaired, threshold, diff, &reporter, options, &counts, &in_flight, &errors,
+        );
+        if stops(&errors) || options.cancelled() {
+            return fail(errors);
+        }
+    }
+    if let Some(sampling) = &options.sampling
+        && let Err(error) = reporter.report_sampling(sampling, counts.sampled_out.load(AtomicOrdering::Relaxed))
+    {
+        record_error(&errors, CalcDiffError::ReporterError(error));
+        if stops(&errors) {
+            return fail(errors);
+        }
+    }
+    if let Err(error) = reporter.finish() {
+        record_error(&errors, CalcDiffError::ReporterError(error));
+    }
+    let errors = errors.into_inner().unwrap();
+    if errors.is_empty() {
+        Ok(counts.summary())
+    } else {
+        Err(errors)
     }
-    reporter.finish().map_err(CalcDiffError::ReporterError)?;
-    Ok(())
 }
 
+/// Runs the differs on a single pair of leaves, as [`calc_diff_with_options`] does for leaves at
+/// the same path, and reports the result to `reporter`. Unlike a tree walk, it neither starts nor
+/// finishes the reporter, so the caller can read what was reported.
+///
+/// The options that apply to single leaves are honored: the differ order, format regressions, and
+/// strict extensions.
+pub fn calc_leaf_diff<L, R>(
+    name: &str,
+    expected: &L,
+    actual: &L,
+    diff: &[Box<dyn DiffReport<L, R>>],
+    reporter: &R,
+    options: &DiffOptions,
+) -> Result<DiffSummary, CalcDiffError<convert::Infallible, R::Error>>
+where
+    L: LeafTraverse + Clone,
+    R: Reporter + Sync,
+{
+    let counts = RunCounts::default();
+    run_diff(diff, reporter, options, &counts, name, expected, actual)?;
+    Ok(counts.summary())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn calc_diff_inner<'scope, N, R, RE>(
     name: &mut String,
     expected: Option<N>,
     actual: Option<N>,
     diff: &'scope [Box<dyn DiffReport<N::Leaf, R>>],
     reporter: &'scope R,
+    options: &'scope DiffOptions,
+    unpaired: &UnpairedLeaves<N::Leaf>,
+    counts: &'scope RunCounts,
+    in_flight: &'scope InFlight,
     scope: &Scope<'scope>,
-    errors: &'scope Mutex<Option<CalcDiffError<N::TraverseError, RE>>>,
+    errors: &'scope Mutex<Vec<CalcDiffError<N::TraverseError, RE>>>,
+    pending: &mut VecDeque<PendingNode<N>>,
 ) -> Result<(), CalcDiffError<N::TraverseError, RE>>
 where
     N: NodeTraverse,
     N::Leaf: Send,
-    R: Reporter + Sync,
+    R: Reporter<Error = RE> + Sync,
     RE: Send + 'scope,
 {
+    if options.cancelled() {
+        return Ok(());
+    }
     match (expected, actual) {
-        (Some(mut expected), Some(mut actual)) => {
-            let mut expected = expected
+        (Some(mut expected_node), Some(mut actual_node)) => {
+            let mut expected = expected_node
                 .children()
                 .map_err(CalcDiffError::TraverseError)?
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(CalcDiffError::TraverseError)?;
-            let mut actual = actual
+                .filter_map(|child| child_or_record(child, options, errors))
+                .filter(|child| !matches!(child, Ok(child) if options.filters_out(name, child)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut actual = actual_node
                 .children()
                 .map_err(CalcDiffError::TraverseError)?
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(CalcDiffError::TraverseError)?;
-            expected.sort_unstable();
-            actual.sort_unstable();
+                .filter_map(|child| child_or_record(child, options, errors))
+                .filter(|child| !matches!(child, Ok(child) if options.filters_out(name, child)))
+                .collect::<Result<Vec<_>, _>>()?;
+            // A child skipped on both sides is reported once.
+            let skipped = BTreeMap::from_iter(
+                expected_node
+                    .take_skipped()
+                    .into_iter()
+                    .chain(actual_node.take_skipped()),
+            );
+            report_skipped(name, skipped, reporter)?;
+            // Names that only differ in their raw form stay in a fixed order.
+            expected.sort_by(|a, b| options.cmp_children(a, b).then_with(|| a.cmp(b)));
+            actual.sort_by(|a, b| options.cmp_children(a, b).then_with(|| a.cmp(b)));
+            report_type_changes(name, &expected, &actual, options, reporter)?;
             let mut expected_iter = expected.into_iter().peekable();
             let mut actual_iter = actual.into_iter().peekable();
 
             loop {
                 let pair = match (expected_iter.peek(), actual_iter.peek()) {
-                    (Some(expected), Some(actual)) => match expected.cmp(actual) {
+                    (Some(expected), Some(actual)) => match options.cmp_children(expected, actual) {
                         Ordering::Less => (expected_iter.next(), None),
                         Ordering::Equal => (expected_iter.next(), actual_iter.next()),
                         Ordering::Greater => (None, actual_iter.next()),
@@ -374,13 +1471,33 @@ where
                     (Some(expected), Some(actual)) => match (expected, actual) {
                         (TraversalNode::Node(expected), TraversalNode::Node(actual)) => {
                             let mut name = AppendedName::new(name, expected.name());
-                            calc_diff_inner(&mut name, Some(expected), Some(actual), diff, reporter, scope, errors)?;
+                            descend(
+                                &mut name,
+                                Some(expected),
+                                Some(actual),
+                                diff,
+                                reporter,
+                                options,
+                                unpaired,
+                                counts,
+                                in_flight,
+                                scope,
+                                errors,
+                                pending,
+                            )?;
                         }
+                        (TraversalNode::Leaf(expected), TraversalNode::Leaf(_))
+                            if options.samples_out(name, expected.name()) =>
+                        {
+                            counts.sampled_out.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        (TraversalNode::Leaf(_), TraversalNode::Leaf(_)) if options.names_only => {}
                         (TraversalNode::Leaf(expected), TraversalNode::Leaf(actual)) => {
                             let name = AppendedName::new(name, expected.name());
-                            let name = name.clone();
-                            spawn_task(scope, errors, move || {
-                                run_diff::<N, R, RE>(diff, reporter, &name, &expected, &actual)
+                            spawn_task(scope, options, errors, in_flight, name.clone(), move |name| {
+                                run_diff::<N::Leaf, R, N::TraverseError, RE>(
+                                    diff, reporter, options, counts, name, &expected, &actual,
+                                )
                             });
                         }
                         _ => unreachable!(),
@@ -388,26 +1505,78 @@ where
                     (Some(expected), None) => match expected {
                         TraversalNode::Node(node) => {
                             let mut name = AppendedName::new(name, node.name());
-                            calc_diff_inner(&mut name, Some(node), None, diff, reporter, scope, errors)?;
+                            descend(
+                                &mut name,
+                                Some(node),
+                                None,
+                                diff,
+                                reporter,
+                                options,
+                                unpaired,
+                                counts,
+                                in_flight,
+                                scope,
+                                errors,
+                                pending,
+                            )?;
+                        }
+                        TraversalNode::Leaf(leaf) if options.samples_out(name, leaf.name()) => {
+                            counts.sampled_out.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        TraversalNode::Leaf(leaf) if options.names_only => {
+                            let name = AppendedName::new(name, leaf.name());
+                            reporter
+                                .report_deleted_leaf(&name)
+                                .map_err(CalcDiffError::ReporterError)?;
+                            counts.deleted.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        TraversalNode::Leaf(leaf) if options.rename_threshold.is_some() => {
+                            let name = AppendedName::new(name, leaf.name());
+                            unpaired.deleted.lock().unwrap().push((name.clone(), leaf));
                         }
                         TraversalNode::Leaf(leaf) => {
                             let name = AppendedName::new(name, leaf.name());
-                            let name = name.clone();
-                            spawn_task(scope, errors, move || {
-                                run_deleted::<N, R, RE>(diff, reporter, &name, &leaf)
+                            spawn_task(scope, options, errors, in_flight, name.clone(), move |name| {
+                                run_deleted::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
                             });
                         }
                     },
                     (None, Some(actual)) => match actual {
                         TraversalNode::Node(node) => {
                             let mut name = AppendedName::new(name, node.name());
-                            calc_diff_inner(&mut name, None, Some(node), diff, reporter, scope, errors)?;
+                            descend(
+                                &mut name,
+                                None,
+                                Some(node),
+                                diff,
+                                reporter,
+                                options,
+                                unpaired,
+                                counts,
+                                in_flight,
+                                scope,
+                                errors,
+                                pending,
+                            )?;
+                        }
+                        TraversalNode::Leaf(leaf) if options.samples_out(name, leaf.name()) => {
+                            counts.sampled_out.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        TraversalNode::Leaf(leaf) if options.names_only => {
+                            let name = AppendedName::new(name, leaf.name());
+                            reporter
+                                .report_added_leaf(&name)
+                                .map_err(CalcDiffError::ReporterError)?;
+                            counts.added.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        TraversalNode::Leaf(leaf) if options.rename_threshold.is_some() => {
+                            let name = AppendedName::new(name, leaf.name());
+                            unpaired.added.lock().unwrap().push((name.clone(), leaf));
                         }
                         TraversalNode::Leaf(leaf) => {
                             let name = AppendedName::new(name, leaf.name());
-                            let name = name.clone();
-                            spawn_task(scope, errors, move || {
-                                run_added::<N, R, RE>(diff, reporter, &name, &leaf)
+                            spawn_task(scope, options, errors, in_flight, name.clone(), move |name| {
+                                run_added::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
                             });
                         }
                     },
@@ -415,63 +1584,387 @@ where
             }
         }
         (Some(mut expected), None) => {
+            let mut empty = true;
             for result in expected.children().map_err(CalcDiffError::TraverseError)? {
-                let node = result.map_err(CalcDiffError::TraverseError)?;
+                empty = false;
+                let Some(node) = child_or_record(result, options, errors) else {
+                    continue;
+                };
+                let node = node?;
+                if options.filters_out(name, &node) {
+                    continue;
+                }
                 match node {
                     TraversalNode::Node(node) => {
                         let mut name = AppendedName::new(name, node.name());
-                        calc_diff_inner(&mut name, Some(node), None, diff, reporter, scope, errors)?;
+                        descend(
+                            &mut name,
+                            Some(node),
+                            None,
+                            diff,
+                            reporter,
+                            options,
+                            unpaired,
+                            counts,
+                            in_flight,
+                            scope,
+                            errors,
+                            pending,
+                        )?;
+                    }
+                    TraversalNode::Leaf(leaf) if options.samples_out(name, leaf.name()) => {
+                        counts.sampled_out.fetch_add(1, AtomicOrdering::Relaxed);
+                    }
+                    TraversalNode::Leaf(leaf) if options.names_only => {
+                        let name = AppendedName::new(name, leaf.name());
+                        reporter
+                            .report_deleted_leaf(&name)
+                            .map_err(CalcDiffError::ReporterError)?;
+                        counts.deleted.fetch_add(1, AtomicOrdering::Relaxed);
+                    }
+                    TraversalNode::Leaf(leaf) if options.rename_threshold.is_some() => {
+                        let name = AppendedName::new(name, leaf.name());
+                        unpaired.deleted.lock().unwrap().push((name.clone(), leaf));
                     }
                     TraversalNode::Leaf(leaf) => {
                         let name = AppendedName::new(name, leaf.name());
-                        let name = name.clone();
-                        spawn_task(scope, errors, move || {
-                            run_deleted::<N, R, RE>(diff, reporter, &name, &leaf)
+                        spawn_task(scope, options, errors, in_flight, name.clone(), move |name| {
+                            run_deleted::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
                         });
                     }
                 }
             }
+            let skipped = expected.take_skipped();
+            empty &= skipped.is_empty();
+            report_skipped(name, skipped, reporter)?;
+            if empty && options.report_empty_nodes {
+                reporter
+                    .report_deleted_empty_node(name)
+                    .map_err(CalcDiffError::ReporterError)?;
+            }
         }
         (None, Some(mut actual)) => {
+            let mut empty = true;
             for result in actual.children().map_err(CalcDiffError::TraverseError)? {
-                let node = result.map_err(CalcDiffError::TraverseError)?;
+                empty = false;
+                let Some(node) = child_or_record(result, options, errors) else {
+                    continue;
+                };
+                let node = node?;
+                if options.filters_out(name, &node) {
+                    continue;
+                }
                 match node {
                     TraversalNode::Node(node) => {
                         let mut name = AppendedName::new(name, node.name());
-                        calc_diff_inner(&mut name, Some(node), None, diff, reporter, scope, errors)?;
+                        descend(
+                            &mut name,
+                            None,
+                            Some(node),
+                            diff,
+                            reporter,
+                            options,
+                            unpaired,
+                            counts,
+                            in_flight,
+                            scope,
+                            errors,
+                            pending,
+                        )?;
+                    }
+                    TraversalNode::Leaf(leaf) if options.samples_out(name, leaf.name()) => {
+                        counts.sampled_out.fetch_add(1, AtomicOrdering::Relaxed);
+                    }
+                    TraversalNode::Leaf(leaf) if options.names_only => {
+                        let name = AppendedName::new(name, leaf.name());
+                        reporter
+                            .report_added_leaf(&name)
+                            .map_err(CalcDiffError::ReporterError)?;
+                        counts.added.fetch_add(1, AtomicOrdering::Relaxed);
+                    }
+                    TraversalNode::Leaf(leaf) if options.rename_threshold.is_some() => {
+                        let name = AppendedName::new(name, leaf.name());
+                        unpaired.added.lock().unwrap().push((name.clone(), leaf));
                     }
                     TraversalNode::Leaf(leaf) => {
                         let name = AppendedName::new(name, leaf.name());
-                        let name = name.clone();
-                        spawn_task(scope, errors, move || {
-                            run_added::<N, R, RE>(diff, reporter, &name, &leaf)
+                        spawn_task(scope, options, errors, in_flight, name.clone(), move |name| {
+                            run_added::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
                         });
                     }
                 }
             }
+            let skipped = actual.take_skipped();
+            empty &= skipped.is_empty();
+            report_skipped(name, skipped, reporter)?;
+            if empty && options.report_empty_nodes {
+                reporter
+                    .report_added_empty_node(name)
+                    .map_err(CalcDiffError::ReporterError)?;
+            }
         }
         (None, None) => {}
     }
     Ok(())
 }
 
-fn record_error<TE, RE>(errors: &Mutex<Option<CalcDiffError<TE, RE>>>, error: CalcDiffError<TE, RE>) {
-    let mut guard = errors.lock().unwrap();
-    if guard.is_none() {
-        *guard = Some(error);
+/// A node pair whose children are yet to be compared, queued by [`DiffOptions::with_breadth_first`].
+struct PendingNode<N> {
+    name: String,
+    expected: Option<N>,
+    actual: Option<N>,
+}
+
+/// Compares the children of a node pair right away, or queues them after the current level when
+/// walking breadth-first.
+#[allow(clippy::too_many_arguments)]
+fn descend<'scope, N, R, RE>(
+    name: &mut String,
+    expected: Option<N>,
+    actual: Option<N>,
+    diff: &'scope [Box<dyn DiffReport<N::Leaf, R>>],
+    reporter: &'scope R,
+    options: &'scope DiffOptions,
+    unpaired: &UnpairedLeaves<N::Leaf>,
+    counts: &'scope RunCounts,
+    in_flight: &'scope InFlight,
+    scope: &Scope<'scope>,
+    errors: &'scope Mutex<Vec<CalcDiffError<N::TraverseError, RE>>>,
+    pending: &mut VecDeque<PendingNode<N>>,
+) -> Result<(), CalcDiffError<N::TraverseError, RE>>
+where
+    N: NodeTraverse,
+    N::Leaf: Send,
+    R: Reporter<Error = RE> + Sync,
+    RE: Send + 'scope,
+{
+    if options.breadth_first {
+        pending.push_back(PendingNode {
+            name: name.clone(),
+            expected,
+            actual,
+        });
+        Ok(())
+    } else {
+        let result = calc_diff_inner(
+            name, expected, actual, diff, reporter, options, unpaired, counts, in_flight, scope, errors, pending,
+        );
+        match result {
+            Err(error) if options.collect_errors => {
+                record_error(errors, error);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+/// Passes on a child, or an error listing it. When errors are collected, the error is recorded
+/// instead and the child left out.
+fn child_or_record<C, TE, RE>(
+    child: Result<C, TE>,
+    options: &DiffOptions,
+    errors: &Mutex<Vec<CalcDiffError<TE, RE>>>,
+) -> Option<Result<C, CalcDiffError<TE, RE>>> {
+    match child {
+        Ok(child) => Some(Ok(child)),
+        Err(error) if options.collect_errors => {
+            record_error(errors, CalcDiffError::TraverseError(error));
+            None
+        }
+        Err(error) => Some(Err(CalcDiffError::TraverseError(error))),
+    }
+}
+
+fn report_skipped<R, TE, RE>(
+    name: &mut String,
+    skipped: impl IntoIterator<Item = (String, SkipReason)>,
+    reporter: &R,
+) -> Result<(), CalcDiffError<TE, RE>>
+where
+    R: Reporter<Error = RE>,
+{
+    for (child, reason) in skipped {
+        let name = AppendedName::new(name, &child);
+        reporter
+            .report_skipped(&name, reason)
+            .map_err(CalcDiffError::ReporterError)?;
+    }
+    Ok(())
+}
+
+/// Reports the names that are a node among the `expected` children and a leaf among the `actual`
+/// children, or the other way around. Nodes and leaves never pair up, since [`TraversalNode`]
+/// orders all nodes before all leaves.
+fn report_type_changes<N, R, TE, RE>(
+    name: &mut String,
+    expected: &[TraversalNode<N, N::Leaf>],
+    actual: &[TraversalNode<N, N::Leaf>],
+    options: &DiffOptions,
+    reporter: &R,
+) -> Result<(), CalcDiffError<TE, RE>>
+where
+    N: NodeTraverse,
+    R: Reporter<Error = RE>,
+{
+    let expected_types = expected
+        .iter()
+        .map(|child| (options.name_key(child_name(child)), child.entry_type()))
+        .collect::<HashMap<_, _>>();
+    for child in actual {
+        let actual_type = child.entry_type();
+        match expected_types.get(&options.name_key(child_name(child))) {
+            Some(&expected_type) if expected_type != actual_type => {
+                let name = AppendedName::new(name, child_name(child));
+                reporter
+                    .report_type_changed(&name, expected_type, actual_type)
+                    .map_err(CalcDiffError::ReporterError)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Leaves found on only one side, held back from the differs until the traversal is complete so
+/// deletions can be paired up with additions (see [`DiffOptions::with_rename_threshold`]).
+struct UnpairedLeaves<Leaf> {
+    deleted: Mutex<Vec<(String, Leaf)>>,
+    added: Mutex<Vec<(String, Leaf)>>,
+}
+
+impl<Leaf> Default for UnpairedLeaves<Leaf> {
+    fn default() -> Self {
+        UnpairedLeaves {
+            deleted: Mutex::new(Vec::new()),
+            added: Mutex::new(Vec::new()),
+        }
     }
 }
 
+/// Diffs each deleted leaf against the most similar added leaf as a rename, and reports the
+/// leaves left without a partner as deleted or added. Leaves with the same
+/// [`LeafTraverse::bytes`] are paired first, in traversal order, so moves are found without
+/// scoring every pair and even by differs that cannot tell similarity.
+#[allow(clippy::too_many_arguments)]
+fn diff_renames<N, R, RE>(
+    unpaired: UnpairedLeaves<N::Leaf>,
+    threshold: f64,
+    diff: &[Box<dyn DiffReport<N::Leaf, R>>],
+    reporter: &R,
+    options: &DiffOptions,
+    counts: &RunCounts,
+    in_flight: &InFlight,
+    errors: &Mutex<Vec<CalcDiffError<N::TraverseError, RE>>>,
+) where
+    N: NodeTraverse,
+    N::Leaf: Send + Sync,
+    R: Reporter<Error = RE> + Sync,
+    RE: Send,
+{
+    let deleted = unpaired.deleted.into_inner().unwrap();
+    let added = unpaired.added.into_inner().unwrap();
+    let mut identical = Vec::new();
+    let mut added_by_bytes = HashMap::<&[u8], VecDeque<usize>>::new();
+    for (added_index, (_, actual)) in added.iter().enumerate() {
+        if let Some(bytes) = actual.bytes() {
+            added_by_bytes.entry(bytes).or_default().push_back(added_index);
+        }
+    }
+    for (deleted_index, (_, expected)) in deleted.iter().enumerate() {
+        if let Some(added_index) = expected
+            .bytes()
+            .and_then(|bytes| added_by_bytes.get_mut(bytes))
+            .and_then(VecDeque::pop_front)
+        {
+            identical.push((deleted_index, added_index));
+        }
+    }
+    let (paired_deleted, paired_added): (HashSet<_>, HashSet<_>) = identical.iter().copied().unzip();
+    let mut candidates = deleted
+        .par_iter()
+        .enumerate()
+        .filter(|(deleted_index, _)| !paired_deleted.contains(deleted_index))
+        .flat_map_iter(|(deleted_index, (_, expected))| {
+            added
+                .iter()
+                .enumerate()
+                .filter(|(added_index, _)| !paired_added.contains(added_index))
+                .filter_map(move |(added_index, (_, actual))| {
+                    let similarity = diff.iter().find_map(|diff| diff.similarity(expected, actual))?;
+                    (similarity >= threshold).then_some((similarity, deleted_index, added_index))
+                })
+        })
+        .collect::<Vec<_>>();
+    // The most similar pairs claim their leaves first; ties fall back to traversal order so the
+    // pairing does not depend on scheduling.
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| (a.1, a.2).cmp(&(b.1, b.2))));
+    let mut deleted = deleted.into_iter().map(Some).collect::<Vec<_>>();
+    let mut added = added.into_iter().map(Some).collect::<Vec<_>>();
+    let mut renames = Vec::new();
+    let identical = identical
+        .into_iter()
+        .map(|(deleted_index, added_index)| (1.0, deleted_index, added_index));
+    for (similarity, deleted_index, added_index) in identical.chain(candidates) {
+        if deleted[deleted_index].is_some() && added[added_index].is_some() {
+            let expected = deleted[deleted_index].take().unwrap();
+            let actual = added[added_index].take().unwrap();
+            renames.push((expected, actual, similarity));
+        }
+    }
+    rayon::scope(|scope| {
+        for ((from, expected), (name, actual), similarity) in renames {
+            spawn_task(scope, options, errors, in_flight, name, move |name| {
+                reporter
+                    .report_renamed(name, &from, similarity)
+                    .map_err(CalcDiffError::ReporterError)?;
+                run_diff::<N::Leaf, R, N::TraverseError, RE>(diff, reporter, options, counts, name, &expected, &actual)
+            });
+        }
+        for (name, leaf) in deleted.into_iter().flatten() {
+            spawn_task(scope, options, errors, in_flight, name, move |name| {
+                run_deleted::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
+            });
+        }
+        for (name, leaf) in added.into_iter().flatten() {
+            spawn_task(scope, options, errors, in_flight, name, move |name| {
+                run_added::<N, R, RE>(diff, reporter, options, counts, name, &leaf)
+            });
+        }
+    });
+}
+
+fn record_error<TE, RE>(errors: &Mutex<Vec<CalcDiffError<TE, RE>>>, error: CalcDiffError<TE, RE>) {
+    errors.lock().unwrap().push(error);
+}
+
+/// Diffs the leaf at `name` on the pool, passing the name to `task`.
 fn spawn_task<'scope, TE, RE>(
     scope: &Scope<'scope>,
-    errors: &'scope Mutex<Option<CalcDiffError<TE, RE>>>,
-    task: impl FnOnce() -> Result<(), CalcDiffError<TE, RE>> + Send + 'scope,
+    options: &'scope DiffOptions,
+    errors: &'scope Mutex<Vec<CalcDiffError<TE, RE>>>,
+    in_flight: &'scope InFlight,
+    name: String,
+    task: impl FnOnce(&str) -> Result<(), CalcDiffError<TE, RE>> + Send + 'scope,
 ) where
     TE: Send + 'scope,
     RE: Send + 'scope,
 {
+    if options.cancelled() {
+        return;
+    }
+    let slot = in_flight.acquire();
+    if let Some(progress) = &options.progress {
+        progress.report(&name, ProgressKind::Scheduled);
+    }
     scope.spawn(move |_| {
-        if let Err(error) = task() {
+        // Tasks queued before the run was cancelled are dropped without diffing.
+        let result = if options.cancelled() { Ok(()) } else { task(&name) };
+        drop(slot);
+        if let Some(progress) = &options.progress {
+            progress.report(&name, ProgressKind::Completed);
+        }
+        if let Err(error) = result {
             record_error(errors, error);
         }
     });
@@ -513,69 +2006,152 @@ impl Drop for AppendedName<'_> {
     }
 }
 
-fn run_diff<N, R, RE>(
-    diff: &[Box<dyn DiffReport<N::Leaf, R>>],
+fn run_diff<L, R, TE, RE>(
+    diff: &[Box<dyn DiffReport<L, R>>],
     reporter: &R,
+    options: &DiffOptions,
+    counts: &RunCounts,
     name: &str,
-    expected: &N::Leaf,
-    actual: &N::Leaf,
-) -> Result<(), CalcDiffError<N::TraverseError, RE>>
+    expected: &L,
+    actual: &L,
+) -> Result<(), CalcDiffError<TE, RE>>
 where
-    N: NodeTraverse,
-    N::Leaf: Clone,
-    R: Reporter + Sync,
+    L: LeafTraverse + Clone,
+    R: Reporter<Error = RE> + Sync,
 {
-    for diff in diff {
-        if let MayUnsupported::Ok(()) = diff
-            .diff(name, expected.clone(), actual.clone(), reporter)
-            .map_err(CalcDiffError::DiffError)?
-        {
+    if let (Some(expected_kind), Some(actual_kind)) = (expected.kind(), actual.kind())
+        && expected_kind != actual_kind
+    {
+        reporter
+            .report_kind_mismatch(name, expected_kind, actual_kind)
+            .map_err(CalcDiffError::ReporterError)?;
+    }
+    if options.identical_fast_path
+        && let Some(expected_bytes) = expected.bytes()
+        && iter::once(actual)
+            .chain(actual.candidates())
+            .any(|actual| actual.bytes() == Some(expected_bytes))
+    {
+        counts.unchanged.fetch_add(1, AtomicOrdering::Relaxed);
+        return reporter.report_identical(name).map_err(CalcDiffError::ReporterError);
+    }
+    let mut format_regressions = options.format_regressions;
+    let mut differs = options.differ_order.ordered(name, diff);
+    while let Some(diff) = differs.next() {
+        let result = if actual.candidates().is_empty() {
+            diff.diff(name, expected.clone(), actual.clone(), reporter)
+        } else {
+            let candidates = iter::once(actual).chain(actual.candidates()).cloned().collect();
+            diff.diff_candidates(name, expected.clone(), candidates, reporter)
+        };
+        if let MayUnsupported::Ok(outcome) = result.map_err(CalcDiffError::DiffError)? {
+            match outcome {
+                DiffOutcome::Unchanged => counts.unchanged.fetch_add(1, AtomicOrdering::Relaxed),
+                DiffOutcome::Modified => counts.modified.fetch_add(1, AtomicOrdering::Relaxed),
+            };
+            for diff in differs {
+                check_readable::<L, TE, RE>(diff.as_ref(), options, name, "expected", expected)?;
+                check_readable::<L, TE, RE>(diff.as_ref(), options, name, "actual", actual)?;
+            }
             return Ok(());
         }
+        if format_regressions && let Some(regression) = diff.format_regression(expected, actual) {
+            reporter
+                .report_format_regression(name, &regression)
+                .map_err(CalcDiffError::ReporterError)?;
+            format_regressions = false;
+        }
+        check_readable::<L, TE, RE>(diff.as_ref(), options, name, "expected", expected)?;
+        check_readable::<L, TE, RE>(diff.as_ref(), options, name, "actual", actual)?;
     }
-    Err(CalcDiffError::<N::TraverseError, RE>::NoDiffReportMatched)
+    reporter
+        .report_skipped(name, SkipReason::Unsupported)
+        .map_err(CalcDiffError::ReporterError)
 }
 
 fn run_added<N, R, RE>(
     diff: &[Box<dyn DiffReport<N::Leaf, R>>],
     reporter: &R,
+    options: &DiffOptions,
+    counts: &RunCounts,
     name: &str,
     actual: &N::Leaf,
 ) -> Result<(), CalcDiffError<N::TraverseError, RE>>
 where
     N: NodeTraverse,
     N::Leaf: Clone,
-    R: Reporter + Sync,
+    R: Reporter<Error = RE> + Sync,
 {
-    for diff in diff {
+    let mut differs = options.differ_order.ordered(name, diff);
+    while let Some(diff) = differs.next() {
         if let MayUnsupported::Ok(()) = diff
             .added(name, actual.clone(), reporter)
             .map_err(CalcDiffError::DiffError)?
         {
+            counts.added.fetch_add(1, AtomicOrdering::Relaxed);
+            for diff in differs {
+                check_readable::<N::Leaf, N::TraverseError, RE>(diff.as_ref(), options, name, "actual", actual)?;
+            }
             return Ok(());
         }
+        check_readable::<N::Leaf, N::TraverseError, RE>(diff.as_ref(), options, name, "actual", actual)?;
     }
-    Err(CalcDiffError::<N::TraverseError, RE>::NoDiffReportMatched)
+    reporter
+        .report_skipped(name, SkipReason::Unsupported)
+        .map_err(CalcDiffError::ReporterError)
 }
 
 fn run_deleted<N, R, RE>(
     diff: &[Box<dyn DiffReport<N::Leaf, R>>],
     reporter: &R,
+    options: &DiffOptions,
+    counts: &RunCounts,
     name: &str,
     expected: &N::Leaf,
 ) -> Result<(), CalcDiffError<N::TraverseError, RE>>
 where
     N: NodeTraverse,
     N::Leaf: Clone,
-    R: Reporter + Sync,
+    R: Reporter<Error = RE> + Sync,
 {
-    for diff in diff {
+    let mut differs = options.differ_order.ordered(name, diff);
+    while let Some(diff) = differs.next() {
         if let MayUnsupported::Ok(()) = diff
             .deleted(name, expected.clone(), reporter)
             .map_err(CalcDiffError::DiffError)?
         {
+            counts.deleted.fetch_add(1, AtomicOrdering::Relaxed);
+            for diff in differs {
+                check_readable::<N::Leaf, N::TraverseError, RE>(diff.as_ref(), options, name, "expected", expected)?;
+            }
             return Ok(());
         }
+        check_readable::<N::Leaf, N::TraverseError, RE>(diff.as_ref(), options, name, "expected", expected)?;
     }
-    Err(CalcDiffError::<N::TraverseError, RE>::NoDiffReportMatched)
+    reporter
+        .report_skipped(name, SkipReason::Unsupported)
+        .map_err(CalcDiffError::ReporterError)
+}
+
+/// Fails when strict extensions are enabled and `leaf` has a kind that `diff` handles but cannot be
+/// read in that format. Differs ordered after the one that handled a leaf are checked as well, so
+/// that a corrupt `.png` which happens to be valid UTF-8 is not silently diffed as text.
+fn check_readable<L, TE, RE>(
+    diff: &dyn DiffReport<L, impl Reporter>,
+    options: &DiffOptions,
+    name: &str,
+    side: &'static str,
+    leaf: &L,
+) -> Result<(), CalcDiffError<TE, RE>>
+where
+    L: LeafTraverse,
+{
+    if options.strict_extensions && diff.unreadable(leaf) {
+        return Err(CalcDiffError::Unreadable {
+            name: name.to_owned(),
+            side,
+            kind: leaf.kind().unwrap_or("unknown").to_owned(),
+        });
+    }
+    Ok(())
 }
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Shared accounting of memory held by decoded buffers across concurrently running diff tasks.
+///
+/// Differs that decode large buffers reserve their estimated size before decoding and fall back
+/// to a cheaper comparison when the reservation is refused.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBudget {
+    inner: Arc<MemoryBudgetInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryBudgetInner {
+    limit: Option<u64>,
+    in_use: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn unlimited() -> MemoryBudget {
+        MemoryBudget::default()
+    }
+
+    pub fn new(limit_bytes: u64) -> MemoryBudget {
+        MemoryBudget {
+            inner: Arc::new(MemoryBudgetInner {
+                limit: Some(limit_bytes),
+                in_use: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.inner.limit
+    }
+
+    pub fn in_use(&self) -> u64 {
+        self.inner.in_use.load(Ordering::Acquire)
+    }
+
+    /// Reserves `bytes`, or returns `None` if that would exceed the limit.
+    pub fn try_reserve(&self, bytes: u64) -> Option<MemoryReservation> {
+        let mut reservation = MemoryReservation {
+            budget: self.clone(),
+            bytes: 0,
+        };
+        reservation.try_grow(bytes).then_some(reservation)
+    }
+}
+
+/// Memory reserved from a [`MemoryBudget`]; released when dropped.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    budget: MemoryBudget,
+    bytes: u64,
+}
+
+impl MemoryReservation {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Extends the reservation by `bytes`, returning `false` (and leaving it unchanged) if that
+    /// would exceed the limit.
+    pub fn try_grow(&mut self, bytes: u64) -> bool {
+        let inner = &self.budget.inner;
+        let result = inner
+            .in_use
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_use| {
+                let next = in_use.checked_add(bytes)?;
+                inner.limit.is_none_or(|limit| next <= limit).then_some(next)
+            });
+        if result.is_ok() {
+            self.bytes += bytes;
+        }
+        result.is_ok()
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.budget.inner.in_use.fetch_sub(self.bytes, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_budget_refuses_reservation_over_limit() {
+        let budget = MemoryBudget::new(100);
+        let first = budget.try_reserve(60).unwrap();
+        assert!(budget.try_reserve(50).is_none());
+        assert_eq!(budget.in_use(), 60);
+        drop(first);
+        assert_eq!(budget.in_use(), 0);
+        assert!(budget.try_reserve(100).is_some());
+    }
+
+    #[test]
+    fn memory_reservation_grows_within_limit() {
+        let budget = MemoryBudget::new(100);
+        let mut reservation = budget.try_reserve(40).unwrap();
+        assert!(reservation.try_grow(60));
+        assert!(!reservation.try_grow(1));
+        assert_eq!(reservation.bytes(), 100);
+        drop(reservation);
+        assert_eq!(budget.in_use(), 0);
+    }
+
+    #[test]
+    fn unlimited_memory_budget_accepts_any_reservation() {
+        let budget = MemoryBudget::unlimited();
+        let _large = budget.try_reserve(u64::MAX / 2).unwrap();
+        assert!(budget.try_reserve(u64::MAX / 2).is_some());
+    }
+}
@@ -0,0 +1,145 @@
+//! Unicode normalization of names, used by [`crate::DiffOptions::with_normalized_names`].
+
+use std::borrow::Cow;
+
+mod tables;
+
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_L_COUNT: u32 = 19;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+const HANGUL_N_COUNT: u32 = HANGUL_V_COUNT * HANGUL_T_COUNT;
+const HANGUL_S_COUNT: u32 = HANGUL_L_COUNT * HANGUL_N_COUNT;
+
+/// Converts `text` to Normalization Form C (canonical composition), so that precomposed and
+/// decomposed spellings of the same characters, like the NFC names of Linux and Windows and the
+/// NFD names of older macOS file systems, become equal.
+pub(crate) fn nfc(text: &str) -> Cow<'_, str> {
+    // Characters below U+0300 are already composed and never compose with what precedes them.
+    if text.chars().all(|c| c < '\u{300}') {
+        return Cow::Borrowed(text);
+    }
+    let mut decomposed = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        decompose(c, &mut decomposed);
+    }
+    reorder_combining_marks(&mut decomposed);
+    Cow::Owned(compose(&decomposed).into_iter().collect())
+}
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let code = u32::from(c);
+    if let Some(index) = code.checked_sub(HANGUL_S_BASE).filter(|&index| index < HANGUL_S_COUNT) {
+        let l = HANGUL_L_BASE + index / HANGUL_N_COUNT;
+        let v = HANGUL_V_BASE + index % HANGUL_N_COUNT / HANGUL_T_COUNT;
+        let t = HANGUL_T_BASE + index % HANGUL_T_COUNT;
+        out.extend([l, v].into_iter().filter_map(char::from_u32));
+        if t != HANGUL_T_BASE {
+            out.extend(char::from_u32(t));
+        }
+        return;
+    }
+    match tables::DECOMPOSITIONS.binary_search_by_key(&c, |&(c, _, _)| c) {
+        Ok(index) => {
+            let (_, first, second) = tables::DECOMPOSITIONS[index];
+            decompose(first, out);
+            if second != '\0' {
+                decompose(second, out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+fn combining_class(c: char) -> u8 {
+    let index = tables::COMBINING_CLASSES.partition_point(|&(start, _, _)| start <= c);
+    match index.checked_sub(1).map(|index| tables::COMBINING_CLASSES[index]) {
+        Some((_, end, class)) if c <= end => class,
+        _ => 0,
+    }
+}
+
+/// Sorts each run of combining marks by combining class, keeping the order of equal classes.
+fn reorder_combining_marks(chars: &mut [char]) {
+    let mut start = 0;
+    while start < chars.len() {
+        if combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(chars.len(), |offset| start + offset);
+        chars[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+}
+
+fn compose(chars: &[char]) -> Vec<char> {
+    let mut composed = Vec::with_capacity(chars.len());
+    let mut starter = None;
+    let mut last_class = 0;
+    for &c in chars {
+        let class = combining_class(c);
+        // A character combines with the last starter unless a character in between has a class
+        // of zero or at least its own.
+        if let Some(starter) = starter
+            && (composed.len() == starter + 1 || last_class < class)
+            && let Some(pair) = compose_pair(composed[starter], c)
+        {
+            composed[starter] = pair;
+            continue;
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+        }
+        last_class = class;
+        composed.push(c);
+    }
+    composed
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (first_code, second_code) = (u32::from(first), u32::from(second));
+    if let Some(l) = first_code.checked_sub(HANGUL_L_BASE).filter(|&l| l < HANGUL_L_COUNT)
+        && let Some(v) = second_code.checked_sub(HANGUL_V_BASE).filter(|&v| v < HANGUL_V_COUNT)
+    {
+        return char::from_u32(HANGUL_S_BASE + (l * HANGUL_V_COUNT + v) * HANGUL_T_COUNT);
+    }
+    if let Some(index) = first_code
+        .checked_sub(HANGUL_S_BASE)
+        .filter(|&index| index < HANGUL_S_COUNT)
+        && index % HANGUL_T_COUNT == 0
+        && let Some(t) = second_code
+            .checked_sub(HANGUL_T_BASE)
+            .filter(|&t| 0 < t && t < HANGUL_T_COUNT)
+    {
+        return char::from_u32(first_code + t);
+    }
+    tables::COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(first, second, _)| (first, second))
+        .ok()
+        .map(|index| tables::COMPOSITIONS[index].2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nfc_composes_decomposed_text() {
+        assert_eq!(nfc("plain.txt"), "plain.txt");
+        assert_eq!(nfc("cafe\u{301}.txt"), "caf\u{e9}.txt");
+        assert_eq!(nfc("caf\u{e9}.txt"), "caf\u{e9}.txt");
+        // Combining marks are reordered by class before composing: dot below (220) then dot
+        // above (230).
+        assert_eq!(nfc("s\u{307}\u{323}"), "\u{1e69}");
+        assert_eq!(nfc("\u{1100}\u{1161}\u{11a8}"), "\u{ac01}");
+        // U+212B ANGSTROM SIGN is a singleton that normalizes to U+00C5.
+        assert_eq!(nfc("\u{212b}"), "\u{c5}");
+    }
+}
@@ -0,0 +1,93 @@
+//! Per-extension order of the differs, used by [`crate::DiffOptions::with_differ_order`].
+
+use std::collections::HashMap;
+
+/// Which differs get the first chance at a leaf, chosen by the extension of its name.
+///
+/// Differs are referred to by their index in the list passed to [`crate::calc_diff`]. A rule only
+/// moves the differs it names to the front, in the given order; the others follow in their usual
+/// order, so every leaf still reaches every differ.
+#[derive(Debug, Clone, Default)]
+pub struct DifferOrder {
+    /// Keyed by lowercase extension, without the dot.
+    by_extension: HashMap<String, Vec<usize>>,
+    /// For leaves whose extension has no rule, including names without an extension.
+    fallback: Vec<usize>,
+}
+
+impl DifferOrder {
+    pub fn new() -> DifferOrder {
+        DifferOrder::default()
+    }
+
+    /// Tries the differs at `preferred` first for leaves named `*.extension`, compared
+    /// case-insensitively. A later rule for the same extension replaces an earlier one.
+    pub fn with_extension(mut self, extension: &str, preferred: Vec<usize>) -> Self {
+        self.by_extension
+            .insert(extension.to_ascii_lowercase(), dedup(preferred));
+        self
+    }
+
+    /// Tries the differs at `preferred` first for leaves whose extension has no rule.
+    pub fn with_fallback(mut self, preferred: Vec<usize>) -> Self {
+        self.fallback = dedup(preferred);
+        self
+    }
+
+    /// `differs` in the order they are tried for the leaf at path `name`.
+    pub(crate) fn ordered<'a, T>(&'a self, name: &str, differs: &'a [T]) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let preferred = self.preferred(name);
+        let front = preferred.iter().filter_map(|&index| differs.get(index));
+        let rest = differs
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !preferred.contains(index))
+            .map(|(_, differ)| differ);
+        front.chain(rest)
+    }
+
+    fn preferred(&self, name: &str) -> &[usize] {
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        file_name
+            .rsplit_once('.')
+            .filter(|(stem, _)| !stem.is_empty())
+            .and_then(|(_, extension)| self.by_extension.get(&extension.to_ascii_lowercase()))
+            .unwrap_or(&self.fallback)
+    }
+}
+
+/// Drops repeated indices, keeping the first occurrence.
+fn dedup(indices: Vec<usize>) -> Vec<usize> {
+    let mut unique = Vec::with_capacity(indices.len());
+    for index in indices {
+        if !unique.contains(&index) {
+            unique.push(index);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differ_order_moves_preferred_differs_to_the_front() {
+        let differs = ["json", "text", "binary"];
+        let order = DifferOrder::new()
+            .with_extension("LOG", vec![2, 2])
+            .with_fallback(vec![1]);
+        let ordered = |name| order.ordered(name, &differs).copied().collect::<Vec<_>>();
+
+        assert_eq!(ordered("dir/app.log"), ["binary", "json", "text"]);
+        assert_eq!(ordered("data.json"), ["text", "json", "binary"]);
+        assert_eq!(ordered(".log"), ["text", "json", "binary"]);
+        assert_eq!(
+            DifferOrder::new()
+                .ordered("a.log", &differs)
+                .copied()
+                .collect::<Vec<_>>(),
+            differs
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! Reading a tree served over HTTP, see [`crate::fs::FsNode::new_remote_root`].
+//!
+//! The tree is described by a manifest: a text file that lists the path of each file relative to
+//! the manifest, one per line. Only the manifest is fetched up front; a file is fetched when the
+//! directory it is in is listed, and its bytes are kept in memory from then on. `https://` and
+//! `s3://` URLs need the `https` feature, which is on by default; an `s3://bucket/path` URL is
+//! read from the bucket's HTTPS endpoint, so the bucket has to allow the request without a
+//! signature, such as a public bucket or one that accepts a bearer token.
+
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long connecting, sending a request, or waiting for the response may take before a request
+/// fails.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest response that is read, so that a misbehaving server cannot exhaust memory.
+pub const MAX_RESPONSE_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Error)]
+pub enum RemoteError {
+    #[cfg_attr(feature = "https", error("{0}:// URLs cannot be read"))]
+    #[cfg_attr(
+        not(feature = "https"),
+        error("{0}:// URLs cannot be read without the https feature")
+    )]
+    UnsupportedScheme(String),
+    #[error("invalid URL {0:?}")]
+    InvalidUrl(String),
+    #[error("refusing to send credentials to {0} over plain http; use https")]
+    InsecureAuthorization(String),
+    #[error("request failed: {0}")]
+    Request(#[source] Box<ureq::Error>),
+    #[error("response is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("access denied with HTTP status {0}; check the credentials")]
+    Denied(u16),
+    #[error("server answered with HTTP status {0}")]
+    Status(u16),
+    #[error("malformed response: {0}")]
+    Malformed(&'static str),
+}
+
+impl From<ureq::Error> for RemoteError {
+    fn from(err: ureq::Error) -> RemoteError {
+        match err {
+            ureq::Error::BodyExceedsLimit(limit) => RemoteError::TooLarge(limit),
+            err => RemoteError::Request(Box::new(err)),
+        }
+    }
+}
+
+/// An `http://` or `https://` URL split into what a request needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpUrl {
+    secure: bool,
+    host: String,
+    port: u16,
+    /// Path of the resource, starting with `/`.
+    path: String,
+}
+
+impl HttpUrl {
+    fn parse(url: &str) -> Result<HttpUrl, RemoteError> {
+        let (scheme, rest) = url.split_once("://").unwrap_or((url, ""));
+        let secure = match scheme {
+            "http" => false,
+            "https" | "s3" if cfg!(feature = "https") => true,
+            scheme => return Err(RemoteError::UnsupportedScheme(scheme.to_owned())),
+        };
+        let (authority, path) = rest.find('/').map_or((rest, "/"), |slash| rest.split_at(slash));
+        if authority.is_empty() || authority.contains('@') {
+            return Err(RemoteError::InvalidUrl(url.to_owned()));
+        }
+        if scheme == "s3" {
+            if authority.contains(':') {
+                return Err(RemoteError::InvalidUrl(url.to_owned()));
+            }
+            return Ok(HttpUrl {
+                secure,
+                host: format!("{authority}.s3.amazonaws.com"),
+                port: 443,
+                path: path.to_owned(),
+            });
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !authority.ends_with(']') => {
+                (host, port.parse().map_err(|_| RemoteError::InvalidUrl(url.to_owned()))?)
+            }
+            _ => (authority, if secure { 443 } else { 80 }),
+        };
+        Ok(HttpUrl {
+            secure,
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+
+    /// The URL of `path`, relative to the directory this URL is in.
+    fn sibling(&self, path: &str) -> HttpUrl {
+        let directory = &self.path[..=self.path.rfind('/').unwrap_or(0)];
+        HttpUrl {
+            path: format!("{directory}{}", percent_encode(path)),
+            ..self.clone()
+        }
+    }
+
+    /// Whether requests stay on this machine, where plain http does not expose credentials.
+    fn is_loopback(&self) -> bool {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+    }
+
+    /// Fetches the resource, failing once the body grows past `limit` bytes. `authorization` is
+    /// only sent over https or to a loopback address.
+    fn get(&self, agent: &ureq::Agent, authorization: Option<&str>, limit: u64) -> Result<Vec<u8>, RemoteError> {
+        if authorization.is_some() && !self.secure && !self.is_loopback() {
+            return Err(RemoteError::InsecureAuthorization(self.host.clone()));
+        }
+        let scheme = if self.secure { "https" } else { "http" };
+        let mut request = agent.get(format!("{scheme}://{}:{}{}", self.host, self.port, self.path));
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let response = request.call()?;
+        match response.status().as_u16() {
+            200 => {}
+            status @ (401 | 403) => return Err(RemoteError::Denied(status)),
+            status => return Err(RemoteError::Status(status)),
+        }
+        Ok(response.into_body().with_config().limit(limit).read_to_vec()?)
+    }
+}
+
+/// Escapes the bytes of `path` that cannot appear in a URL path as is; `/` separates segments.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// The files listed by a manifest, which are fetched from next to it.
+#[derive(Debug)]
+pub(crate) struct RemoteTree {
+    manifest: HttpUrl,
+    agent: ureq::Agent,
+    authorization: Option<String>,
+    /// The largest file that is fetched.
+    limit: u64,
+    /// Paths of files relative to the manifest, without a leading `/`.
+    files: BTreeSet<String>,
+    /// Every directory implied by the paths of files, with a trailing `/`.
+    directories: BTreeSet<String>,
+}
+
+/// A child of a directory in a remote tree.
+pub(crate) enum RemoteChild<'a> {
+    Directory(&'a str),
+    File(&'a str),
+}
+
+impl RemoteTree {
+    /// Fetches the manifest at `url`, sending `authorization` as the `Authorization` header of
+    /// this and every later request.
+    pub(crate) fn fetch(url: &str, authorization: Option<String>) -> Result<RemoteTree, RemoteError> {
+        RemoteTree::fetch_with_limit(url, authorization, MAX_RESPONSE_SIZE)
+    }
+
+    /// Like [`RemoteTree::fetch`], reading no response larger than `limit` bytes.
+    fn fetch_with_limit(url: &str, authorization: Option<String>, limit: u64) -> Result<RemoteTree, RemoteError> {
+        let manifest = HttpUrl::parse(url)?;
+        // Statuses are told apart by `HttpUrl::get`, and redirects drop the credentials.
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_connect(Some(TIMEOUT))
+            .timeout_send_request(Some(TIMEOUT))
+            .timeout_recv_response(Some(TIMEOUT))
+            .user_agent("semdiff")
+            .build()
+            .new_agent();
+        let listing = manifest.get(&agent, authorization.as_deref(), limit)?;
+        let listing = String::from_utf8(listing).map_err(|_| RemoteError::Malformed("manifest is not UTF-8"))?;
+        Ok(RemoteTree::from_listing(
+            manifest,
+            agent,
+            authorization,
+            limit,
+            &listing,
+        ))
+    }
+
+    /// Reads one path per line, skipping blank lines, `#` comments, and paths that leave the
+    /// manifest's directory.
+    fn from_listing(
+        manifest: HttpUrl,
+        agent: ureq::Agent,
+        authorization: Option<String>,
+        limit: u64,
+        listing: &str,
+    ) -> RemoteTree {
+        let mut files = BTreeSet::new();
+        let mut directories = BTreeSet::new();
+        for line in listing.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let segments = line
+                .split('/')
+                .filter(|segment| !segment.is_empty() && *segment != ".")
+                .collect::<Vec<_>>();
+            if segments.is_empty() || segments.contains(&"..") {
+                continue;
+            }
+            for end in 1..segments.len() {
+                directories.insert(format!("{}/", segments[..end].join("/")));
+            }
+            files.insert(segments.join("/"));
+        }
+        RemoteTree {
+            manifest,
+            agent,
+            authorization,
+            limit,
+            files,
+            directories,
+        }
+    }
+
+    /// The directories and files directly in the directory `prefix`, which is empty for the root
+    /// and ends with `/` otherwise. A path listed both as a file and as a directory is a directory.
+    pub(crate) fn children(&self, prefix: &str) -> Vec<RemoteChild<'_>> {
+        fn direct_child<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+            let name = path.strip_prefix(prefix)?;
+            let name = name.strip_suffix('/').unwrap_or(name);
+            (!name.is_empty() && !name.contains('/')).then_some(name)
+        }
+        let directories = self.directories.range(prefix.to_owned()..);
+        let mut children = directories
+            .take_while(|path| path.starts_with(prefix))
+            .filter_map(|path| direct_child(path, prefix))
+            .map(RemoteChild::Directory)
+            .collect::<Vec<_>>();
+        let files = self.files.range(prefix.to_owned()..);
+        children.extend(
+            files
+                .take_while(|path| path.starts_with(prefix))
+                .filter(|path| !self.directories.contains(&format!("{path}/")))
+                .filter_map(|path| direct_child(path, prefix))
+                .map(RemoteChild::File),
+        );
+        children
+    }
+
+    /// Fetches the file at `path`.
+    pub(crate) fn read(&self, path: &str) -> Result<Vec<u8>, RemoteError> {
+        self.manifest
+            .sibling(path)
+            .get(&self.agent, self.authorization.as_deref(), self.limit)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves `files` by path on a local port until the test ends, answering 404 for any other
+    /// path and 401 to requests without `authorization` when one is given. Returns the base URL.
+    pub(crate) fn serve(
+        files: &'static [(&'static str, &'static [u8])],
+        authorization: Option<&'static str>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                // Header names are case-insensitive.
+                let authorized = authorization.is_none_or(|authorization| {
+                    request
+                        .to_ascii_lowercase()
+                        .contains(&format!("authorization: {}\r\n", authorization.to_ascii_lowercase()))
+                });
+                let response = match files.iter().find(|(file, _)| *file == path) {
+                    _ if !authorized => b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n".to_vec(),
+                    Some((_, content)) => {
+                        let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", content.len());
+                        let mut response = response.into_bytes();
+                        response.extend_from_slice(content);
+                        response
+                    }
+                    None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+                };
+                let _ = stream.write_all(&response);
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn http_urls_parse_and_resolve_siblings() {
+        let url = HttpUrl::parse("http://example.com:8080/golden/manifest.txt").unwrap();
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 8080);
+        assert!(!url.secure);
+        assert_eq!(url.sibling("dir/a b.txt").path, "/golden/dir/a%20b.txt");
+        assert_eq!(HttpUrl::parse("http://example.com").unwrap().port, 80);
+        assert!(matches!(
+            HttpUrl::parse("ftp://example.com/manifest.txt"),
+            Err(RemoteError::UnsupportedScheme(scheme)) if scheme == "ftp"
+        ));
+        assert!(matches!(
+            HttpUrl::parse("s3://bucket:1/golden"),
+            Err(RemoteError::InvalidUrl(_)) | Err(RemoteError::UnsupportedScheme(_))
+        ));
+    }
+
+    #[cfg(feature = "https")]
+    #[test]
+    fn https_and_s3_urls_are_read_over_tls() {
+        let url = HttpUrl::parse("https://example.com/golden/manifest.txt").unwrap();
+        assert!(url.secure);
+        assert_eq!(url.port, 443);
+        let url = HttpUrl::parse("s3://bucket/golden/manifest.txt").unwrap();
+        assert!(url.secure);
+        assert_eq!(url.host, "bucket.s3.amazonaws.com");
+        assert_eq!(url.path, "/golden/manifest.txt");
+    }
+
+    #[cfg(not(feature = "https"))]
+    #[test]
+    fn https_and_s3_urls_need_the_https_feature() {
+        for url in ["https://example.com/manifest.txt", "s3://bucket/golden"] {
+            assert!(matches!(HttpUrl::parse(url), Err(RemoteError::UnsupportedScheme(_))));
+        }
+    }
+
+    #[test]
+    fn credentials_are_only_sent_over_https_or_to_loopback() {
+        let agent = ureq::Agent::new_with_defaults();
+        // Refused before connecting, so no server is needed.
+        let url = HttpUrl::parse("http://example.com/manifest.txt").unwrap();
+        assert!(matches!(
+            url.get(&agent, Some("Bearer secret"), MAX_RESPONSE_SIZE),
+            Err(RemoteError::InsecureAuthorization(host)) if host == "example.com"
+        ));
+        for loopback in ["http://localhost/", "http://127.0.0.1:8080/", "http://[::1]/"] {
+            assert!(HttpUrl::parse(loopback).unwrap().is_loopback(), "{loopback}");
+        }
+    }
+
+    #[test]
+    fn responses_larger_than_the_limit_are_refused() {
+        let base = serve(&[("/manifest.txt", b"a.txt\n"), ("/a.txt", b"hello world")], None);
+        let url = format!("{base}/manifest.txt");
+        assert!(matches!(
+            RemoteTree::fetch_with_limit(&url, None, 3),
+            Err(RemoteError::TooLarge(3))
+        ));
+        let tree = RemoteTree::fetch_with_limit(&url, None, 8).unwrap();
+        assert!(matches!(tree.read("a.txt"), Err(RemoteError::TooLarge(8))));
+    }
+
+    #[test]
+    fn remote_tree_lists_the_manifest_and_fetches_files() {
+        let base = serve(
+            &[
+                ("/golden/manifest.txt", b"# files\na.txt\n./dir/b.txt\n../outside.txt\n"),
+                ("/golden/a.txt", b"hello"),
+                ("/golden/dir/b.txt", b"world"),
+            ],
+            Some("Bearer secret"),
+        );
+        let url = format!("{base}/golden/manifest.txt");
+        assert!(matches!(RemoteTree::fetch(&url, None), Err(RemoteError::Denied(401))));
+        let tree = RemoteTree::fetch(&url, Some("Bearer secret".to_owned())).unwrap();
+        let names = |prefix| {
+            tree.children(prefix)
+                .into_iter()
+                .map(|child| match child {
+                    RemoteChild::Directory(name) => format!("{name}/"),
+                    RemoteChild::File(name) => name.to_owned(),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(""), ["dir/", "a.txt"]);
+        assert_eq!(names("dir/"), ["b.txt"]);
+        assert_eq!(tree.read("dir/b.txt").unwrap(), b"world");
+        assert!(matches!(tree.read("missing.txt"), Err(RemoteError::Status(404))));
+    }
+}
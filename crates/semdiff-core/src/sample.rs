@@ -0,0 +1,48 @@
+//! Deterministic sampling of leaves, used by [`crate::DiffOptions::with_sampling`].
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Compares each leaf with probability `rate`, decided by a hash of its path and `seed`, so that
+/// the same seed picks the same paths on every run and on both sides of a comparison.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    rate: f64,
+    seed: u64,
+}
+
+impl Sampling {
+    /// Returns `None` unless `0 < rate <= 1`.
+    pub fn new(rate: f64, seed: u64) -> Option<Sampling> {
+        (0.0 < rate && rate <= 1.0).then_some(Sampling { rate, seed })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether the leaf named `leaf` in the node at path `parent` is compared.
+    pub(crate) fn includes(&self, parent: &str, leaf: &str) -> bool {
+        // FNV-1a over the seed and the path as `AppendedName` joins it, then the SplitMix64
+        // finalizer so that paths with a common prefix spread over the whole range.
+        let separator: &[u8] = if parent.is_empty() { b"" } else { b"/" };
+        let bytes = [
+            &self.seed.to_le_bytes()[..],
+            parent.as_bytes(),
+            separator,
+            leaf.as_bytes(),
+        ];
+        let mut hash = bytes.into_iter().flatten().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+        // The top 53 bits as a fraction in [0, 1).
+        ((hash >> 11) as f64 / (1u64 << 53) as f64) < self.rate
+    }
+}
@@ -0,0 +1,244 @@
+//! Counting the changes of a run, for deciding whether it passes once it is over.
+
+use crate::{
+    DetailReporter, EntryType, FormatRegression, MayUnsupported, Reporter, Sampling, SkipReason, ToleranceCheck,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How many leaves a run reported as modified, added, or deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeCounts {
+    pub modified: usize,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+impl ChangeCounts {
+    /// Leaves that only one side has.
+    pub fn missing(&self) -> usize {
+        self.added + self.deleted
+    }
+}
+
+/// The changes counted by [`CountChanges`].
+#[derive(Debug, Default)]
+pub struct ChangeTally {
+    modified: AtomicUsize,
+    added: AtomicUsize,
+    deleted: AtomicUsize,
+}
+
+impl ChangeTally {
+    pub fn new() -> ChangeTally {
+        ChangeTally::default()
+    }
+
+    pub fn counts(&self) -> ChangeCounts {
+        ChangeCounts {
+            modified: self.modified.load(Ordering::Relaxed),
+            added: self.added.load(Ordering::Relaxed),
+            deleted: self.deleted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Wraps a reporter so that every leaf a differ has reported to it as modified, added, or deleted
+/// is counted in a [`ChangeTally`], as are the leaves of a names-only run. Every call is
+/// forwarded unchanged.
+#[derive(Debug)]
+pub struct CountChanges<'a, R> {
+    inner: R,
+    tally: Option<&'a ChangeTally>,
+}
+
+impl<'a, R> CountChanges<'a, R> {
+    /// Wraps `inner` without counting anything until `tally` is set.
+    pub fn new(inner: R) -> CountChanges<'a, R> {
+        CountChanges { inner, tally: None }
+    }
+
+    pub fn with_tally(self, tally: Option<&'a ChangeTally>) -> CountChanges<'a, R> {
+        CountChanges { tally, ..self }
+    }
+
+    fn count<E>(
+        &self,
+        counter: fn(&ChangeTally) -> &AtomicUsize,
+        result: Result<MayUnsupported<()>, E>,
+    ) -> Result<MayUnsupported<()>, E> {
+        if let (Ok(MayUnsupported::Ok(())), Some(tally)) = (&result, self.tally) {
+            counter(tally).fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<D, Diff, T, R> DetailReporter<Diff, T, CountChanges<'_, R>> for D
+where
+    D: DetailReporter<Diff, T, R>,
+{
+    type Error = D::Error;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &CountChanges<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.report_unchanged(name, diff, &reporter.inner)
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &CountChanges<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.count(
+            |tally| &tally.modified,
+            self.report_modified(name, diff, &reporter.inner),
+        )
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &CountChanges<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.count(|tally| &tally.added, self.report_added(name, data, &reporter.inner))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &CountChanges<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.count(|tally| &tally.deleted, self.report_deleted(name, data, &reporter.inner))
+    }
+}
+
+impl<R: Reporter> Reporter for CountChanges<'_, R> {
+    type Error = R::Error;
+
+    fn start(&mut self) -> Result<(), Self::Error> {
+        self.inner.start()
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        self.inner.finish()
+    }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.inner.report_kind_mismatch(name, expected_kind, actual_kind)
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.inner.report_format_regression(name, regression)
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        self.inner.report_sampling(sampling, left_out)
+    }
+
+    fn report_change_magnitude(&self, name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        self.inner.report_change_magnitude(name, magnitude)
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_reencoded(name)
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_identical(name)
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        self.inner.report_tolerance_check(name, check)
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.inner.report_skipped(name, reason)
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.inner.report_type_changed(name, expected, actual)
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_added_empty_node(name)
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_deleted_empty_node(name)
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        if let Some(tally) = self.tally {
+            tally.added.fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.report_added_leaf(name)
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        if let Some(tally) = self.tally {
+            tally.deleted.fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.report_deleted_leaf(name)
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        self.inner.report_renamed(name, from, similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert;
+
+    struct Accepting;
+
+    impl DetailReporter<(), (), ()> for Accepting {
+        type Error = convert::Infallible;
+
+        fn report_unchanged(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_modified(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_added(&self, _name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_deleted(&self, _name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Unsupported)
+        }
+    }
+
+    #[test]
+    fn count_changes_counts_reported_leaves() {
+        let tally = ChangeTally::new();
+        let reporter = CountChanges::new(()).with_tally(Some(&tally));
+        Accepting.report_unchanged("a", &(), &reporter).unwrap();
+        Accepting.report_modified("b", &(), &reporter).unwrap();
+        Accepting.report_added("c", &(), &reporter).unwrap();
+        Accepting.report_added("d", &(), &reporter).unwrap();
+        // A leaf the differ did not take is not counted.
+        Accepting.report_deleted("e", &(), &reporter).unwrap();
+
+        assert_eq!(
+            tally.counts(),
+            ChangeCounts {
+                modified: 1,
+                added: 2,
+                deleted: 0,
+            }
+        );
+        assert_eq!(tally.counts().missing(), 2);
+    }
+}
@@ -6,6 +6,9 @@ use std::sync::{Arc, Mutex};
 struct TestLeaf {
     name: String,
     value: i32,
+    kind: Option<&'static str>,
+    bytes: Option<Vec<u8>>,
+    candidates: Vec<TestLeaf>,
 }
 
 impl TestLeaf {
@@ -13,14 +16,44 @@ impl TestLeaf {
         Self {
             name: name.to_owned(),
             value,
+            kind: None,
+            bytes: None,
+            candidates: Vec::new(),
         }
     }
+
+    fn with_kind(mut self, kind: &'static str) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    fn with_bytes(mut self, bytes: &[u8]) -> Self {
+        self.bytes = Some(bytes.to_vec());
+        self
+    }
+
+    fn with_candidates(mut self, values: &[i32]) -> Self {
+        self.candidates = values.iter().map(|&value| TestLeaf::new(&self.name, value)).collect();
+        self
+    }
 }
 
 impl LeafTraverse for TestLeaf {
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn kind(&self) -> Option<&str> {
+        self.kind
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
+
+    fn candidates(&self) -> &[TestLeaf] {
+        &self.candidates
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +108,19 @@ enum ReportEvent {
     Modified(String),
     Added(String),
     Deleted(String),
+    KindMismatch(String, String, String),
+    AddedEmptyNode(String),
+    DeletedEmptyNode(String),
+    ChangeMagnitude(String),
+    Reencoded(String),
+    Identical(String),
+    Skipped(String, SkipReason),
+    AddedLeaf(String),
+    DeletedLeaf(String),
+    Renamed(String, String),
+    FormatRegression(String, String),
+    Sampling(usize),
+    TypeChanged(String, EntryType, EntryType),
 }
 
 fn event_sort_key(event: &ReportEvent) -> (u8, String) {
@@ -83,8 +129,21 @@ fn event_sort_key(event: &ReportEvent) -> (u8, String) {
         ReportEvent::Modified(name) => (1, name.clone()),
         ReportEvent::Added(name) => (2, name.clone()),
         ReportEvent::Deleted(name) => (3, name.clone()),
-        ReportEvent::Start => (4, String::new()),
-        ReportEvent::Finish => (5, String::new()),
+        ReportEvent::KindMismatch(name, _, _) => (4, name.clone()),
+        ReportEvent::AddedEmptyNode(name) => (5, name.clone()),
+        ReportEvent::DeletedEmptyNode(name) => (6, name.clone()),
+        ReportEvent::ChangeMagnitude(name) => (7, name.clone()),
+        ReportEvent::Reencoded(name) => (8, name.clone()),
+        ReportEvent::Skipped(name, _) => (9, name.clone()),
+        ReportEvent::AddedLeaf(name) => (10, name.clone()),
+        ReportEvent::DeletedLeaf(name) => (11, name.clone()),
+        ReportEvent::Renamed(name, _) => (12, name.clone()),
+        ReportEvent::FormatRegression(name, _) => (13, name.clone()),
+        ReportEvent::Sampling(_) => (14, String::new()),
+        ReportEvent::TypeChanged(name, _, _) => (15, name.clone()),
+        ReportEvent::Identical(name) => (16, name.clone()),
+        ReportEvent::Start => (17, String::new()),
+        ReportEvent::Finish => (18, String::new()),
     }
 }
 
@@ -117,6 +176,108 @@ impl Reporter for TestReporter {
         self.events.lock().unwrap().push(ReportEvent::Finish);
         Ok(())
     }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.events.lock().unwrap().push(ReportEvent::KindMismatch(
+            name.to_owned(),
+            expected_kind.to_owned(),
+            actual_kind.to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::FormatRegression(name.to_owned(), regression.to_string()));
+        Ok(())
+    }
+
+    fn report_sampling(&self, _sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        self.events.lock().unwrap().push(ReportEvent::Sampling(left_out));
+        Ok(())
+    }
+
+    fn report_change_magnitude(&self, name: &str, _magnitude: f64) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::ChangeMagnitude(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::Reencoded(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::Identical(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::Skipped(name.to_owned(), reason));
+        Ok(())
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::TypeChanged(name.to_owned(), expected, actual));
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::AddedEmptyNode(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::DeletedEmptyNode(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::AddedLeaf(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::DeletedLeaf(name.to_owned()));
+        Ok(())
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, _similarity: f64) -> Result<(), Self::Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(ReportEvent::Renamed(name.to_owned(), from.to_owned()));
+        Ok(())
+    }
 }
 
 #[derive(Clone, Default)]
@@ -174,12 +335,22 @@ impl DetailReporter<TestDiff, TestLeaf, TestReporter> for TestDetailReporter {
 #[derive(Debug)]
 struct TestDiff {
     equal: bool,
+    magnitude: Option<f64>,
+    byte_identical: Option<bool>,
 }
 
 impl Diff for TestDiff {
     fn equal(&self) -> bool {
         self.equal
     }
+
+    fn magnitude(&self) -> Option<f64> {
+        self.magnitude
+    }
+
+    fn byte_identical(&self) -> Option<bool> {
+        self.byte_identical
+    }
 }
 
 #[derive(Debug)]
@@ -197,8 +368,91 @@ impl DiffCalculator<TestLeaf> for TestDiffCalculator {
     ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
         Ok(MayUnsupported::Ok(TestDiff {
             equal: expected.value == actual.value,
+            magnitude: None,
+            byte_identical: None,
+        }))
+    }
+
+    fn similarity(&self, expected: &TestLeaf, actual: &TestLeaf) -> Option<f64> {
+        Some(1.0 / f64::from(1 + expected.value.abs_diff(actual.value)))
+    }
+}
+
+#[derive(Debug)]
+struct MeasuredDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for MeasuredDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: Some(0.5),
+            byte_identical: None,
+        }))
+    }
+}
+
+/// Treats every pair of leaves as equal, like a differ with a generous tolerance.
+#[derive(Debug)]
+struct TolerantDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for TolerantDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: true,
+            magnitude: None,
+            byte_identical: Some(expected.value == actual.value),
+        }))
+    }
+}
+
+/// Handles only leaves with a positive value, leaving the rest for later differs.
+#[derive(Debug)]
+struct PositiveDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for PositiveDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        if expected.value <= 0 || actual.value <= 0 {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: None,
+            byte_identical: None,
         }))
     }
+
+    fn format_regression(&self, expected: &TestLeaf, actual: &TestLeaf) -> Option<FormatRegression> {
+        FormatRegression::from_parsed("positive", expected.value > 0, actual.value > 0)
+    }
+
+    /// Zero stands for a leaf that claims to be positive but is not.
+    fn unreadable(&self, leaf: &TestLeaf) -> bool {
+        leaf.value == 0
+    }
 }
 
 #[test]
@@ -368,3 +622,1442 @@ fn calc_diff_deletes_missing_node_children_in_mixed_order() {
         ],
     );
 }
+
+#[test]
+fn calc_diff_adds_missing_node_children_in_mixed_order() {
+    let expected = TestNode::new("root", vec![TestChild::Leaf(TestLeaf::new("root-leaf", 1))]);
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("root-leaf", 1)),
+            TestChild::Node(TestNode::new(
+                "dir",
+                vec![
+                    TestChild::Leaf(TestLeaf::new("a", 1)),
+                    TestChild::Node(TestNode::new("sub", vec![TestChild::Leaf(TestLeaf::new("b", 1))])),
+                ],
+            )),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::Added("dir/a".to_owned()),
+            ReportEvent::Added("dir/sub/b".to_owned()),
+            ReportEvent::Unchanged("root-leaf".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_names_that_changed_between_node_and_leaf() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new("out", vec![TestChild::Leaf(TestLeaf::new("a", 1))])),
+            TestChild::Leaf(TestLeaf::new("config", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("out", 1)),
+            TestChild::Node(TestNode::new("config", vec![TestChild::Leaf(TestLeaf::new("b", 1))])),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::TypeChanged("config".to_owned(), EntryType::Leaf, EntryType::Node),
+            ReportEvent::TypeChanged("out".to_owned(), EntryType::Node, EntryType::Leaf),
+            ReportEvent::Added("config/b".to_owned()),
+            ReportEvent::Added("out".to_owned()),
+            ReportEvent::Deleted("config".to_owned()),
+            ReportEvent::Deleted("out/a".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_kind_mismatch_before_diffing() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("migrated", 1).with_kind("application/x-plist")),
+            TestChild::Leaf(TestLeaf::new("same-kind", 1).with_kind("application/json")),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("migrated", 1).with_kind("application/json")),
+            TestChild::Leaf(TestLeaf::new("same-kind", 2).with_kind("application/json")),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    let mismatch = events
+        .iter()
+        .position(|event| matches!(event, ReportEvent::KindMismatch(..)))
+        .unwrap();
+    let unchanged = events
+        .iter()
+        .position(|event| event == &ReportEvent::Unchanged("migrated".to_owned()))
+        .unwrap();
+    assert!(mismatch < unchanged);
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::KindMismatch(
+                "migrated".to_owned(),
+                "application/x-plist".to_owned(),
+                "application/json".to_owned(),
+            ),
+            ReportEvent::Unchanged("migrated".to_owned()),
+            ReportEvent::Modified("same-kind".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_empty_nodes_only_when_enabled() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new("scaffold", vec![])),
+            TestChild::Node(TestNode::new(
+                "old",
+                vec![TestChild::Node(TestNode::new("inner", vec![]))],
+            )),
+            TestChild::Node(TestNode::new("both", vec![])),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new("fresh", vec![])),
+            TestChild::Node(TestNode::new("both", vec![])),
+        ],
+    );
+
+    let run = |options: &DiffOptions| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        );
+        let result = calc_diff_with_options(expected.clone(), actual.clone(), &[Box::new(diff)], reporter, options);
+        assert!(result.is_ok());
+        events.lock().unwrap().clone()
+    };
+
+    assert_events_unordered(run(&DiffOptions::new()), vec![]);
+    assert_events_unordered(
+        run(&DiffOptions::new().with_empty_nodes(true)),
+        vec![
+            ReportEvent::AddedEmptyNode("fresh".to_owned()),
+            ReportEvent::DeletedEmptyNode("old/inner".to_owned()),
+            ReportEvent::DeletedEmptyNode("scaffold".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_summarizes_leaves_across_added_and_deleted_subtrees() {
+    let leaves = |prefix: &str, value: i32| {
+        (0..20)
+            .map(|i| TestChild::Leaf(TestLeaf::new(&format!("{prefix}{i}"), value)))
+            .collect::<Vec<_>>()
+    };
+    let expected = TestNode::new(
+        "root",
+        [
+            leaves("same", 1),
+            leaves("changed", 1),
+            vec![TestChild::Node(TestNode::new(
+                "old",
+                vec![TestChild::Node(TestNode::new("inner", leaves("gone", 1)))],
+            ))],
+        ]
+        .concat(),
+    );
+    let actual = TestNode::new(
+        "root",
+        [
+            leaves("same", 1),
+            leaves("changed", 2),
+            vec![TestChild::Node(TestNode::new("new", leaves("fresh", 1)))],
+            leaves("top", 1),
+        ]
+        .concat(),
+    );
+    let run = |options: &DiffOptions| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(TestDiffCalculator, TestDetailReporter { events });
+        calc_diff_with_options(expected.clone(), actual.clone(), &[Box::new(diff)], reporter, options).unwrap()
+    };
+
+    let summary = run(&DiffOptions::new());
+    assert_eq!(
+        summary,
+        DiffSummary {
+            unchanged: 20,
+            modified: 20,
+            added: 40,
+            deleted: 20,
+        }
+    );
+    assert!(summary.has_changes());
+    assert_eq!(
+        run(&DiffOptions::new().with_names_only(true)),
+        DiffSummary {
+            unchanged: 0,
+            modified: 0,
+            added: 40,
+            deleted: 20,
+        }
+    );
+
+    let summary = calc_diff(
+        TestNode::new("root", leaves("same", 1)),
+        TestNode::new("root", leaves("same", 1)),
+        &[Box::new(DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter::default(),
+        ))],
+        TestReporter::default(),
+    )
+    .unwrap();
+    assert_eq!(summary.unchanged, 20);
+    assert!(!summary.has_changes());
+}
+
+/// Records the most diffs it was running at the same time.
+#[derive(Clone, Default)]
+struct ConcurrencyDiffCalculator {
+    running: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl DiffCalculator<TestLeaf> for ConcurrencyDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        let running = self.running.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+        self.peak.fetch_max(running, AtomicOrdering::SeqCst);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        self.running.fetch_sub(1, AtomicOrdering::SeqCst);
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: None,
+            byte_identical: None,
+        }))
+    }
+}
+
+#[test]
+fn calc_diff_runs_at_most_max_in_flight_diffs_at_once() {
+    let tree = || {
+        TestNode::new(
+            "root",
+            (0..32)
+                .map(|i| TestChild::Leaf(TestLeaf::new(&format!("leaf{i}"), i)))
+                .collect(),
+        )
+    };
+    let run = |threads: usize, max_in_flight: Option<usize>| {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let calculator = ConcurrencyDiffCalculator::default();
+        let diff = DiffAndReport::new(calculator.clone(), TestDetailReporter::default());
+        let options = DiffOptions::new().with_max_in_flight(max_in_flight.and_then(NonZeroUsize::new));
+        let summary = pool
+            .install(|| calc_diff_with_options(tree(), tree(), &[Box::new(diff)], TestReporter::default(), &options))
+            .unwrap();
+        assert_eq!(summary.unchanged, 32);
+        calculator.peak.load(AtomicOrdering::SeqCst)
+    };
+
+    assert!(run(4, Some(2)) <= 2);
+    assert_eq!(run(4, Some(1)), 1);
+    // The walk runs on a worker of the pool; waiting must not keep the only worker from diffing.
+    assert_eq!(run(1, Some(1)), 1);
+    assert!(run(4, None) >= 1);
+}
+
+struct PanickingDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for PanickingDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        if expected.value < 0 {
+            panic!("cannot diff {name}");
+        }
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: None,
+            byte_identical: None,
+        }))
+    }
+}
+
+#[test]
+fn calc_diff_passes_on_a_differ_panic_without_losing_its_in_flight_slot() {
+    let tree = || {
+        TestNode::new(
+            "root",
+            vec![
+                TestChild::Leaf(TestLeaf::new("a", -1)),
+                TestChild::Leaf(TestLeaf::new("b", 1)),
+                TestChild::Leaf(TestLeaf::new("c", 1)),
+            ],
+        )
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let diff = DiffAndReport::new(PanickingDiffCalculator, TestDetailReporter::default());
+        let options = DiffOptions::new().with_max_in_flight(NonZeroUsize::new(1));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            calc_diff_with_options(tree(), tree(), &[Box::new(diff)], TestReporter::default(), &options)
+        }));
+        sender.send(result.is_err()).unwrap();
+    });
+    // A slot lost to the panic would keep the walk waiting for the next one forever.
+    let panicked = receiver.recv_timeout(std::time::Duration::from_secs(30)).unwrap();
+    assert!(panicked);
+}
+
+#[test]
+fn calc_diff_names_only_reports_one_sided_leaves_without_diffing() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 1)),
+            TestChild::Node(TestNode::new("old", vec![TestChild::Leaf(TestLeaf::new("gone", 1))])),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 2)),
+            TestChild::Node(TestNode::new(
+                "new",
+                vec![TestChild::Node(TestNode::new(
+                    "inner",
+                    vec![TestChild::Leaf(TestLeaf::new("fresh", 1))],
+                ))],
+            )),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let options = DiffOptions::new().with_names_only(true);
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+    assert!(result.is_ok());
+
+    assert_events_unordered(
+        events.lock().unwrap().clone(),
+        vec![
+            ReportEvent::AddedLeaf("new/inner/fresh".to_owned()),
+            ReportEvent::DeletedLeaf("old/gone".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_breadth_first_reports_shallow_leaves_before_deeper_ones() {
+    let tree = || {
+        TestNode::new(
+            "root",
+            vec![
+                TestChild::Node(TestNode::new(
+                    "a",
+                    vec![
+                        TestChild::Node(TestNode::new("b", vec![TestChild::Leaf(TestLeaf::new("deepest", 1))])),
+                        TestChild::Leaf(TestLeaf::new("deep", 1)),
+                    ],
+                )),
+                TestChild::Leaf(TestLeaf::new("top", 1)),
+            ],
+        )
+    };
+    let deleted_leaves = |breadth_first| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        );
+        let options = DiffOptions::new()
+            .with_names_only(true)
+            .with_breadth_first(breadth_first);
+        let actual = TestNode::new("root", vec![TestChild::Node(TestNode::new("a", vec![]))]);
+        calc_diff_with_options(tree(), actual, &[Box::new(diff)], reporter, &options).unwrap();
+        let events = events.lock().unwrap().clone();
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                ReportEvent::DeletedLeaf(name) => Some(name),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Names-only leaves are reported during the traversal, so their order is the visiting order.
+    assert_eq!(deleted_leaves(false), ["a/b/deepest", "a/deep", "top"]);
+    assert_eq!(deleted_leaves(true), ["top", "a/deep", "a/b/deepest"]);
+}
+
+#[test]
+fn calc_diff_pairs_names_that_differ_in_normalization_or_case_when_enabled() {
+    let run = |options: DiffOptions| {
+        // "café" spelled precomposed (NFC) on one side and decomposed (NFD) on the other.
+        let expected = TestNode::new(
+            "root",
+            vec![
+                TestChild::Leaf(TestLeaf::new("caf\u{e9}.txt", 1)),
+                TestChild::Leaf(TestLeaf::new("README.md", 1)),
+            ],
+        );
+        let actual = TestNode::new(
+            "root",
+            vec![
+                TestChild::Leaf(TestLeaf::new("cafe\u{301}.txt", 1)),
+                TestChild::Leaf(TestLeaf::new("readme.md", 2)),
+            ],
+        );
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        );
+        calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options).unwrap();
+        events.lock().unwrap().clone()
+    };
+
+    assert_events_unordered(
+        run(DiffOptions::new()),
+        vec![
+            ReportEvent::Deleted("caf\u{e9}.txt".to_owned()),
+            ReportEvent::Added("cafe\u{301}.txt".to_owned()),
+            ReportEvent::Deleted("README.md".to_owned()),
+            ReportEvent::Added("readme.md".to_owned()),
+        ],
+    );
+    assert_events_unordered(
+        run(DiffOptions::new().with_normalized_names(true)),
+        vec![
+            ReportEvent::Unchanged("caf\u{e9}.txt".to_owned()),
+            ReportEvent::Deleted("README.md".to_owned()),
+            ReportEvent::Added("readme.md".to_owned()),
+        ],
+    );
+    assert_events_unordered(
+        run(DiffOptions::new()
+            .with_normalized_names(true)
+            .with_case_folded_names(true)),
+        vec![
+            ReportEvent::Unchanged("caf\u{e9}.txt".to_owned()),
+            ReportEvent::Modified("README.md".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_pairs_similar_deleted_and_added_leaves_as_renames() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new("old", vec![TestChild::Leaf(TestLeaf::new("edited", 10))])),
+            TestChild::Leaf(TestLeaf::new("moved", 7)),
+            TestChild::Leaf(TestLeaf::new("gone", 100)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new("new", vec![TestChild::Leaf(TestLeaf::new("edited", 11))])),
+            TestChild::Leaf(TestLeaf::new("renamed", 7)),
+            TestChild::Leaf(TestLeaf::new("fresh", 1000)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let options = DiffOptions::new().with_rename_threshold(Some(0.5));
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+    assert!(result.is_ok());
+
+    assert_events_unordered(
+        events.lock().unwrap().clone(),
+        vec![
+            ReportEvent::Renamed("new/edited".to_owned(), "old/edited".to_owned()),
+            ReportEvent::Modified("new/edited".to_owned()),
+            ReportEvent::Renamed("renamed".to_owned(), "moved".to_owned()),
+            ReportEvent::Unchanged("renamed".to_owned()),
+            ReportEvent::Added("fresh".to_owned()),
+            ReportEvent::Deleted("gone".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_leaves_out_entries_rejected_by_the_entry_filter() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new(
+                "node_modules",
+                vec![TestChild::Leaf(TestLeaf::new("index.js", 1))],
+            )),
+            TestChild::Node(TestNode::new(
+                "src",
+                vec![
+                    TestChild::Leaf(TestLeaf::new("main.rs", 1)),
+                    TestChild::Leaf(TestLeaf::new("main.rs.tmp", 1)),
+                ],
+            )),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new(
+                "node_modules",
+                vec![TestChild::Leaf(TestLeaf::new("index.js", 2))],
+            )),
+            TestChild::Node(TestNode::new(
+                "src",
+                vec![
+                    TestChild::Leaf(TestLeaf::new("main.rs", 2)),
+                    TestChild::Node(TestNode::new(
+                        "main.rs.tmp",
+                        vec![TestChild::Leaf(TestLeaf::new("x", 1))],
+                    )),
+                ],
+            )),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let visited = Arc::new(Mutex::new(Vec::new()));
+    let filter = EntryFilter::new({
+        let visited = Arc::clone(&visited);
+        move |path, entry_type| {
+            visited.lock().unwrap().push((path.to_owned(), entry_type));
+            path != "node_modules" && !(entry_type == EntryType::Leaf && path.ends_with(".tmp"))
+        }
+    });
+    let options = DiffOptions::new().with_entry_filter(Some(filter));
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+    assert!(result.is_ok());
+
+    // Only the `main.rs.tmp` leaf is filtered out; the directory of that name in `actual` is kept.
+    assert_events_unordered(
+        events.lock().unwrap().clone(),
+        vec![
+            ReportEvent::Modified("src/main.rs".to_owned()),
+            ReportEvent::Added("src/main.rs.tmp/x".to_owned()),
+        ],
+    );
+    assert!(
+        !visited
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(path, _)| path.starts_with("node_modules/"))
+    );
+}
+
+#[test]
+fn calc_diff_reports_progress_as_leaves_are_scheduled_and_completed() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Node(TestNode::new(
+                "dir",
+                vec![
+                    TestChild::Leaf(TestLeaf::new("changed", 1)),
+                    TestChild::Leaf(TestLeaf::new("gone", 1)),
+                ],
+            )),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("fresh", 1)),
+            TestChild::Node(TestNode::new("dir", vec![TestChild::Leaf(TestLeaf::new("changed", 2))])),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let progress_events = Arc::new(Mutex::new(Vec::new()));
+    let progress = Progress::new({
+        let progress_events = Arc::clone(&progress_events);
+        move |event: ProgressEvent<'_>| {
+            progress_events
+                .lock()
+                .unwrap()
+                .push((event.path.to_owned(), event.kind))
+        }
+    });
+    let options = DiffOptions::new().with_progress(Some(progress));
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+    assert!(result.is_ok());
+
+    let progress_events = progress_events.lock().unwrap().clone();
+    assert_eq!(progress_events.len(), 8);
+    for path in ["same", "fresh", "dir/changed", "dir/gone"] {
+        let kinds = progress_events
+            .iter()
+            .filter(|(event_path, _)| event_path == path)
+            .map(|(_, kind)| *kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [ProgressKind::Scheduled, ProgressKind::Completed], "{path}");
+    }
+}
+
+#[test]
+fn calc_diff_stops_without_finishing_once_cancelled() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("a", 1)),
+            TestChild::Node(TestNode::new("dir", vec![TestChild::Leaf(TestLeaf::new("b", 1))])),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("a", 2)),
+            TestChild::Node(TestNode::new("dir", vec![TestChild::Leaf(TestLeaf::new("b", 2))])),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let cancel_token = CancelToken::new();
+    // Cancelled as soon as the first leaf is scheduled, so that no leaf gets diffed.
+    let progress = Progress::new({
+        let cancel_token = cancel_token.clone();
+        move |_: ProgressEvent<'_>| cancel_token.cancel()
+    });
+    let options = DiffOptions::new()
+        .with_progress(Some(progress))
+        .with_cancel_token(Some(cancel_token.clone()));
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+
+    assert!(cancel_token.is_cancelled());
+    assert!(matches!(result, Err(CalcDiffError::Cancelled)));
+    assert_eq!(*events.lock().unwrap(), [ReportEvent::Start]);
+}
+
+#[test]
+fn calc_diff_reports_identical_bytes_without_diffing_when_enabled() {
+    // The values differ, so a leaf only comes out unchanged without being diffed.
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same.png", 1).with_bytes(b"png")),
+            TestChild::Leaf(TestLeaf::new("changed.png", 1).with_bytes(b"png")),
+            TestChild::Leaf(TestLeaf::new("unread", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same.png", 2).with_bytes(b"png")),
+            TestChild::Leaf(TestLeaf::new("changed.png", 2).with_bytes(b"jpg")),
+            TestChild::Leaf(TestLeaf::new("unread", 2)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TestDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let options = DiffOptions::new().with_identical_fast_path(true);
+    let summary = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options).unwrap();
+
+    assert_eq!(summary.unchanged, 1);
+    assert_eq!(summary.modified, 2);
+    assert_events_unordered(
+        events.lock().unwrap().clone(),
+        vec![
+            ReportEvent::Identical("same.png".to_owned()),
+            ReportEvent::Modified("changed.png".to_owned()),
+            ReportEvent::Modified("unread".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_pairs_leaves_with_identical_bytes_as_renames() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new(
+                "old",
+                vec![TestChild::Leaf(TestLeaf::new("logo.png", 1).with_bytes(b"logo"))],
+            )),
+            TestChild::Leaf(TestLeaf::new("copy1", 2).with_bytes(b"copy")),
+            TestChild::Leaf(TestLeaf::new("copy2", 2).with_bytes(b"copy")),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Node(TestNode::new(
+                "new",
+                vec![TestChild::Leaf(TestLeaf::new("logo.png", 1).with_bytes(b"logo"))],
+            )),
+            TestChild::Leaf(TestLeaf::new("copy", 2).with_bytes(b"copy")),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    // MeasuredDiffCalculator cannot tell similarity, so only identical bytes pair leaves up.
+    let diff = DiffAndReport::new(
+        MeasuredDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+    let options = DiffOptions::new().with_rename_threshold(Some(1.0));
+    let result = calc_diff_with_options(expected, actual, &[Box::new(diff)], reporter, &options);
+    assert!(result.is_ok());
+
+    assert_events_unordered(
+        events.lock().unwrap().clone(),
+        vec![
+            ReportEvent::Renamed("new/logo.png".to_owned(), "old/logo.png".to_owned()),
+            ReportEvent::Unchanged("new/logo.png".to_owned()),
+            ReportEvent::Renamed("copy".to_owned(), "copy1".to_owned()),
+            ReportEvent::Unchanged("copy".to_owned()),
+            ReportEvent::Deleted("copy2".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_change_magnitude_for_modified_leaves() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 2)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        MeasuredDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::Modified("changed".to_owned()),
+            ReportEvent::ChangeMagnitude("changed".to_owned()),
+            ReportEvent::Unchanged("same".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_reencoded_leaves() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("reencoded", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("reencoded", 2)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        TolerantDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::Reencoded("reencoded".to_owned()),
+            ReportEvent::Unchanged("reencoded".to_owned()),
+            ReportEvent::Unchanged("same".to_owned()),
+        ],
+    );
+}
+
+/// A [`TestNode`] tree where nodes named `broken` cannot be listed and leaves named `unreadable`
+/// cannot be read.
+#[derive(Debug, Clone)]
+struct FallibleNode(TestNode);
+
+impl NodeTraverse for FallibleNode {
+    type Leaf = TestLeaf;
+    type TraverseError = std::io::Error;
+
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn children(
+        &mut self,
+    ) -> Result<impl Iterator<Item = Result<TraversalNode<Self, Self::Leaf>, Self::TraverseError>>, Self::TraverseError>
+    {
+        if self.0.name == "broken" {
+            return Err(std::io::Error::other("cannot list broken"));
+        }
+        Ok(self.0.children.clone().into_iter().map(|child| match child {
+            TestChild::Node(node) => Ok(TraversalNode::Node(FallibleNode(node))),
+            TestChild::Leaf(leaf) if leaf.name == "unreadable" => Err(std::io::Error::other("cannot read unreadable")),
+            TestChild::Leaf(leaf) => Ok(TraversalNode::Leaf(leaf)),
+        }))
+    }
+}
+
+/// Fails to diff leaves with a negative value.
+struct FailingDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for FailingDiffCalculator {
+    type Error = std::io::Error;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        if expected.value < 0 {
+            return Err(std::io::Error::other(format!("cannot diff {name}")));
+        }
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: None,
+            byte_identical: None,
+        }))
+    }
+}
+
+/// Runs a tree whose only leaf fails to diff, cancelling the run once the diff is done.
+fn run_failing_then_cancelled(collect: bool) -> Vec<String> {
+    let tree = || TestNode::new("root", vec![TestChild::Leaf(TestLeaf::new("boom", -1))]);
+    let reporter = TestReporter {
+        events: Arc::new(Mutex::new(Vec::new())),
+    };
+    let diff = DiffAndReport::new(
+        FailingDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&reporter.events),
+        },
+    );
+    let diff: [Box<dyn DiffReport<TestLeaf, TestReporter>>; 1] = [Box::new(diff)];
+    let cancel_token = CancelToken::new();
+    let progress = Progress::new({
+        let cancel_token = cancel_token.clone();
+        move |event: ProgressEvent<'_>| {
+            if event.kind == ProgressKind::Completed {
+                cancel_token.cancel();
+            }
+        }
+    });
+    let options = DiffOptions::new()
+        .with_progress(Some(progress))
+        .with_cancel_token(Some(cancel_token));
+    let errors = if collect {
+        calc_diff_collect(tree(), tree(), &diff, reporter, &options).unwrap_err()
+    } else {
+        vec![calc_diff_with_options(tree(), tree(), &diff, reporter, &options).unwrap_err()]
+    };
+    errors.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn calc_diff_fails_as_cancelled_even_after_another_error() {
+    assert_eq!(run_failing_then_cancelled(false), ["the comparison was cancelled"]);
+}
+
+#[test]
+fn calc_diff_collect_returns_cancelled_after_the_other_errors() {
+    assert_eq!(
+        run_failing_then_cancelled(true),
+        ["cannot diff boom", "the comparison was cancelled"]
+    );
+}
+
+#[test]
+fn calc_diff_collect_reports_every_error_and_the_rest_of_the_tree() {
+    let tree = |value| {
+        FallibleNode(TestNode::new(
+            "root",
+            vec![
+                TestChild::Node(TestNode::new("broken", vec![])),
+                TestChild::Node(TestNode::new("ok", vec![TestChild::Leaf(TestLeaf::new("a", value))])),
+                TestChild::Leaf(TestLeaf::new("unreadable", 1)),
+                TestChild::Leaf(TestLeaf::new("boom", -1)),
+                TestChild::Leaf(TestLeaf::new("same", 1)),
+            ],
+        ))
+    };
+    let run = |collect: bool| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(
+            FailingDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        );
+        let diff: [Box<dyn DiffReport<TestLeaf, TestReporter>>; 1] = [Box::new(diff)];
+        let errors = if collect {
+            calc_diff_collect(tree(1), tree(2), &diff, reporter, &DiffOptions::new()).unwrap_err()
+        } else {
+            vec![calc_diff_with_options(tree(1), tree(2), &diff, reporter, &DiffOptions::new()).unwrap_err()]
+        };
+        let mut errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        errors.sort();
+        (errors, events.lock().unwrap().clone())
+    };
+
+    let (errors, events) = run(true);
+    assert_eq!(
+        errors,
+        [
+            "cannot diff boom",
+            "cannot list broken",
+            "cannot read unreadable",
+            "cannot read unreadable",
+        ]
+    );
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::Modified("ok/a".to_owned()),
+            ReportEvent::Unchanged("same".to_owned()),
+        ],
+    );
+
+    // Without collecting, listing the root already fails and the reporter is not finished.
+    let (errors, events) = run(false);
+    assert_eq!(errors, ["cannot read unreadable"]);
+    assert_eq!(events, [ReportEvent::Start]);
+}
+
+#[test]
+fn calc_diff_reports_unsupported_leaves_as_skipped() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("supported", 1)),
+            TestChild::Leaf(TestLeaf::new("unsupported", 0)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("supported", 1)),
+            TestChild::Leaf(TestLeaf::new("unsupported", 0)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let diff = DiffAndReport::new(
+        PositiveDiffCalculator,
+        TestDetailReporter {
+            events: Arc::clone(&events),
+        },
+    );
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(
+        events,
+        vec![
+            ReportEvent::Skipped("unsupported".to_owned(), SkipReason::Unsupported),
+            ReportEvent::Unchanged("supported".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn calc_diff_reports_format_regressions_before_falling_through_when_enabled() {
+    let leaves = |values: [i32; 3]| {
+        let names = ["broke", "fixed", "neither"];
+        let children = names
+            .iter()
+            .zip(values)
+            .map(|(name, value)| TestChild::Leaf(TestLeaf::new(name, value)));
+        TestNode::new("root", children.collect())
+    };
+    for enabled in [false, true] {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let detail = TestDetailReporter {
+            events: Arc::clone(&events),
+        };
+        let diff: [Box<dyn DiffReport<TestLeaf, TestReporter>>; 2] = [
+            Box::new(DiffAndReport::new(PositiveDiffCalculator, detail.clone())),
+            Box::new(DiffAndReport::new(TestDiffCalculator, detail)),
+        ];
+        let options = DiffOptions::new().with_format_regressions(enabled);
+        let result = calc_diff_with_options(leaves([1, 0, -1]), leaves([-1, 1, -1]), &diff, reporter, &options);
+        assert!(result.is_ok());
+
+        let events = events.lock().unwrap().clone();
+        let mut expected_events = vec![
+            ReportEvent::Modified("broke".to_owned()),
+            ReportEvent::Modified("fixed".to_owned()),
+            ReportEvent::Unchanged("neither".to_owned()),
+        ];
+        if enabled {
+            let regression = events
+                .iter()
+                .position(|event| matches!(event, ReportEvent::FormatRegression(name, _) if name == "broke"))
+                .unwrap();
+            let modified = events
+                .iter()
+                .position(|event| event == &ReportEvent::Modified("broke".to_owned()))
+                .unwrap();
+            assert!(regression < modified);
+            expected_events.extend([
+                ReportEvent::FormatRegression(
+                    "broke".to_owned(),
+                    "expected parsed as positive, actual did not".to_owned(),
+                ),
+                ReportEvent::FormatRegression(
+                    "fixed".to_owned(),
+                    "actual parsed as positive, expected did not".to_owned(),
+                ),
+            ]);
+        }
+        assert_events_unordered(events, expected_events);
+    }
+}
+
+#[test]
+fn calc_diff_fails_on_unreadable_leaves_with_strict_extensions() {
+    let run_ordered = |expected: Vec<TestLeaf>, actual: Vec<TestLeaf>, strict: bool, fallback_first: bool| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let detail = TestDetailReporter { events };
+        let mut diff: Vec<Box<dyn DiffReport<TestLeaf, TestReporter>>> = vec![
+            Box::new(DiffAndReport::new(PositiveDiffCalculator, detail.clone())),
+            Box::new(DiffAndReport::new(TestDiffCalculator, detail)),
+        ];
+        if fallback_first {
+            diff.reverse();
+        }
+        let tree = |leaves: Vec<TestLeaf>| TestNode::new("root", leaves.into_iter().map(TestChild::Leaf).collect());
+        let options = DiffOptions::new().with_strict_extensions(strict);
+        calc_diff_with_options(tree(expected), tree(actual), &diff, reporter, &options).map_err(|err| err.to_string())
+    };
+    let run = |expected, actual, strict| run_ordered(expected, actual, strict, false);
+    let corrupt = || TestLeaf::new("corrupt.png", 0).with_kind("image/png");
+
+    // Leaves outside the differ's kind still fall through to the next differ.
+    assert!(run(vec![TestLeaf::new("other", -1)], vec![TestLeaf::new("other", -2)], true).is_ok());
+    assert!(run(vec![TestLeaf::new("corrupt.png", 1)], vec![corrupt()], false).is_ok());
+    assert_eq!(
+        run(vec![TestLeaf::new("corrupt.png", 1)], vec![corrupt()], true).unwrap_err(),
+        "actual corrupt.png is image/png by its extension but could not be read as one"
+    );
+    // A differ ordered after the one that handled the leaf is still asked.
+    assert_eq!(
+        run_ordered(vec![corrupt()], vec![TestLeaf::new("corrupt.png", 1)], true, true).unwrap_err(),
+        "expected corrupt.png is image/png by its extension but could not be read as one"
+    );
+}
+
+#[test]
+fn calc_diff_compares_the_same_sample_of_leaves_for_a_seed() {
+    // 100 modified leaves in a subdirectory, plus a leaf deleted and a leaf added at the root.
+    let tree = |offset: i32| {
+        let files = (0..100).map(|i| TestChild::Leaf(TestLeaf::new(&format!("file{i}"), i + offset)));
+        let one_sided = TestChild::Leaf(TestLeaf::new(&format!("only{offset}"), 0));
+        TestNode::new(
+            "root",
+            vec![TestChild::Node(TestNode::new("dir", files.collect())), one_sided],
+        )
+    };
+    let run = |sampling: Option<Sampling>| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reporter = TestReporter {
+            events: Arc::clone(&events),
+        };
+        let diff = DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        );
+        let options = DiffOptions::new().with_sampling(sampling);
+        calc_diff_with_options(tree(0), tree(1), &[Box::new(diff)], reporter, &options).unwrap();
+        let mut events = events.lock().unwrap().clone();
+        events.sort_by_key(event_sort_key);
+        events
+    };
+
+    let sample = run(Sampling::new(0.3, 7));
+    assert_eq!(sample, run(Sampling::new(0.3, 7)));
+    assert_ne!(sample, run(Sampling::new(0.3, 8)));
+    let compared = sample
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                ReportEvent::Modified(_) | ReportEvent::Added(_) | ReportEvent::Deleted(_)
+            )
+        })
+        .count();
+    assert!((15..=45).contains(&compared), "{compared}");
+    assert!(sample.contains(&ReportEvent::Sampling(102 - compared)));
+    assert!(!run(None).iter().any(|event| matches!(event, ReportEvent::Sampling(_))));
+    assert_eq!(Sampling::new(0.0, 7), None);
+}
+
+/// Measures a change as the distance between the values.
+#[derive(Debug)]
+struct DistanceDiffCalculator;
+
+impl DiffCalculator<TestLeaf> for DistanceDiffCalculator {
+    type Error = Infallible;
+    type Diff = TestDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: TestLeaf,
+        actual: TestLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        Ok(MayUnsupported::Ok(TestDiff {
+            equal: expected.value == actual.value,
+            magnitude: Some(f64::from(expected.value.abs_diff(actual.value))),
+            byte_identical: None,
+        }))
+    }
+}
+
+/// Records the magnitude of each diff it is given.
+#[derive(Clone, Default)]
+struct MagnitudeDetailReporter {
+    magnitudes: Arc<Mutex<BTreeMap<String, f64>>>,
+}
+
+impl DetailReporter<TestDiff, TestLeaf, TestReporter> for MagnitudeDetailReporter {
+    type Error = Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &TestDiff,
+        reporter: &TestReporter,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.report_modified(name, diff, reporter)
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &TestDiff,
+        _reporter: &TestReporter,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let magnitude = diff.magnitude.unwrap();
+        self.magnitudes.lock().unwrap().insert(name.to_owned(), magnitude);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        _name: &str,
+        _data: &TestLeaf,
+        _reporter: &TestReporter,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        _name: &str,
+        _data: &TestLeaf,
+        _reporter: &TestReporter,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        Ok(MayUnsupported::Ok(()))
+    }
+}
+
+#[test]
+fn calc_diff_reports_the_matching_or_closest_candidate() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("flaky", 1)),
+            TestChild::Leaf(TestLeaf::new("broken", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("flaky", 3).with_candidates(&[1])),
+            TestChild::Leaf(TestLeaf::new("broken", 9).with_candidates(&[4, 2, 7])),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let details = MagnitudeDetailReporter::default();
+    let diff = DiffAndReport::new(DistanceDiffCalculator, details.clone());
+    calc_diff(expected, actual, &[Box::new(diff)], reporter).unwrap();
+
+    let events = events.lock().unwrap().clone();
+    assert_events_unordered(events, vec![ReportEvent::ChangeMagnitude("broken".to_owned())]);
+    assert_eq!(
+        *details.magnitudes.lock().unwrap(),
+        BTreeMap::from([("broken".to_owned(), 1.0), ("flaky".to_owned(), 0.0)])
+    );
+}
+
+#[test]
+fn differ_timings_count_leaves_per_label() {
+    let expected = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 1)),
+            TestChild::Leaf(TestLeaf::new("deleted", 1)),
+        ],
+    );
+    let actual = TestNode::new(
+        "root",
+        vec![
+            TestChild::Leaf(TestLeaf::new("same", 1)),
+            TestChild::Leaf(TestLeaf::new("changed", 2)),
+        ],
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let reporter = TestReporter {
+        events: Arc::clone(&events),
+    };
+    let timings = timing::DifferTimings::new();
+    let diff = timings.wrap(
+        "test",
+        DiffAndReport::new(
+            TestDiffCalculator,
+            TestDetailReporter {
+                events: Arc::clone(&events),
+            },
+        ),
+    );
+    let _unused = timings.wrap("unused", ());
+
+    let result = calc_diff(expected, actual, &[Box::new(diff)], reporter);
+    assert!(result.is_ok());
+
+    let totals = timings.totals();
+    let labels = totals
+        .iter()
+        .map(|&(label, _, leaves)| (label, leaves))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, [("test", 3), ("unused", 0)]);
+}
+
+#[test]
+fn tolerance_check_is_borderline_near_threshold_on_either_side() {
+    let check = |measured| ToleranceCheck {
+        metric: "diff_ratio",
+        measured,
+        threshold: 0.1,
+    };
+    assert!(check(0.095).is_borderline(0.1));
+    assert!(check(0.105).is_borderline(0.1));
+    assert!(!check(0.05).is_borderline(0.1));
+    assert!(!check(0.2).is_borderline(0.1));
+    let strict = ToleranceCheck {
+        metric: "diff_ratio",
+        measured: 0.0,
+        threshold: 0.0,
+    };
+    assert_eq!(strict.relative_margin(), None);
+    assert!(!strict.is_borderline(1.0));
+}
@@ -0,0 +1,232 @@
+//! Time spent in each differ, summed over all leaves of a run, and optionally per leaf.
+
+use crate::{__sealed, DiffOutcome, DiffReport, FormatRegression, LeafTraverse, MayUnsupported};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Collects the time spent in differs wrapped with [`DifferTimings::wrap`], grouped by label.
+///
+/// Leaves are diffed in parallel, so the totals are summed over threads and can exceed the
+/// wall-clock time of the run.
+#[derive(Debug, Default)]
+pub struct DifferTimings {
+    timings: Mutex<Vec<Arc<DifferTiming>>>,
+    /// Set by [`DifferTimings::with_entries`].
+    entries: Option<Arc<EntryCosts>>,
+}
+
+/// Time spent on each leaf by all differs, and the size of its largest side, by name.
+type EntryCosts = Mutex<HashMap<String, EntryCost>>;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EntryCost {
+    nanos: u64,
+    size: u64,
+}
+
+#[derive(Debug)]
+struct DifferTiming {
+    label: &'static str,
+    nanos: AtomicU64,
+    leaves: AtomicUsize,
+}
+
+impl DifferTimings {
+    pub fn new() -> DifferTimings {
+        DifferTimings::default()
+    }
+
+    /// Also records the time spent on each leaf and the size of its larger side, for
+    /// [`DifferTimings::slowest_entries`] and [`DifferTimings::largest_entries`]. Leaves that no
+    /// wrapped differ was tried on, such as skipped ones, are not recorded.
+    pub fn with_entries(mut self, entries: bool) -> Self {
+        self.entries = entries.then(Arc::default);
+        self
+    }
+
+    /// Wraps a differ so the time spent in it, including attempts on leaves it does not support, is
+    /// added to `label`. Leaves are counted once the differ handles them.
+    pub fn wrap<T>(&self, label: &'static str, inner: T) -> Timed<T> {
+        let mut timings = self.timings.lock().unwrap();
+        let timing = match timings.iter().find(|timing| timing.label == label) {
+            Some(timing) => Arc::clone(timing),
+            None => {
+                let timing = Arc::new(DifferTiming {
+                    label,
+                    nanos: AtomicU64::new(0),
+                    leaves: AtomicUsize::new(0),
+                });
+                timings.push(Arc::clone(&timing));
+                timing
+            }
+        };
+        Timed {
+            inner,
+            timing,
+            entries: self.entries.clone(),
+        }
+    }
+
+    /// Up to `count` leaves that took the longest over all differs, slowest first.
+    pub fn slowest_entries(&self, count: usize) -> Vec<(String, Duration)> {
+        self.ranked_entries(count, |cost| cost.nanos)
+            .into_iter()
+            .map(|(name, nanos)| (name, Duration::from_nanos(nanos)))
+            .collect()
+    }
+
+    /// Up to `count` leaves with the largest side, in bytes, largest first.
+    pub fn largest_entries(&self, count: usize) -> Vec<(String, u64)> {
+        self.ranked_entries(count, |cost| cost.size)
+    }
+
+    fn ranked_entries(&self, count: usize, key: impl Fn(&EntryCost) -> u64) -> Vec<(String, u64)> {
+        let Some(entries) = &self.entries else {
+            return Vec::new();
+        };
+        let mut ranked = entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, cost)| (name.clone(), key(cost)))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(count);
+        ranked
+    }
+
+    /// Lists the `count` slowest and largest leaves, as recorded with
+    /// [`DifferTimings::with_entries`].
+    pub fn write_top_entries(&self, mut writer: impl Write, count: usize) -> io::Result<()> {
+        writeln!(writer, "Slowest files (summed over differs):")?;
+        for (name, duration) in self.slowest_entries(count) {
+            writeln!(writer, "  {:>10.3}s  {name}", duration.as_secs_f64())?;
+        }
+        writeln!(writer, "Largest files (larger side):")?;
+        for (name, size) in self.largest_entries(count) {
+            writeln!(writer, "  {size:>11}B  {name}")?;
+        }
+        Ok(())
+    }
+
+    /// Total time and number of leaves handled per label, slowest first.
+    pub fn totals(&self) -> Vec<(&'static str, Duration, usize)> {
+        let mut totals = self
+            .timings
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|timing| {
+                (
+                    timing.label,
+                    Duration::from_nanos(timing.nanos.load(Ordering::Relaxed)),
+                    timing.leaves.load(Ordering::Relaxed),
+                )
+            })
+            .collect::<Vec<_>>();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        totals
+    }
+
+    pub fn write_summary(&self, mut writer: impl Write) -> io::Result<()> {
+        let totals = self.totals();
+        let sum = totals
+            .iter()
+            .map(|(_, duration, _)| duration.as_secs_f64())
+            .sum::<f64>();
+        writeln!(writer, "Time per differ (summed over threads):")?;
+        for (label, duration, leaves) in totals {
+            let seconds = duration.as_secs_f64();
+            let share = if sum > 0.0 { seconds / sum * 100.0 } else { 0.0 };
+            writeln!(writer, "  {label:<8} {seconds:>10.3}s {share:>5.1}%  {leaves} files")?;
+        }
+        Ok(())
+    }
+}
+
+/// A differ whose time is recorded by [`DifferTimings`].
+#[derive(Debug)]
+pub struct Timed<T> {
+    inner: T,
+    timing: Arc<DifferTiming>,
+    entries: Option<Arc<EntryCosts>>,
+}
+
+impl<T> Timed<T> {
+    /// Runs `f` on the leaf `name`, whose larger side is `size` bytes, adding the time it took to
+    /// the differ's total and to the leaf's.
+    fn record<O, E>(
+        &self,
+        name: &str,
+        size: Option<u64>,
+        f: impl FnOnce() -> Result<MayUnsupported<O>, E>,
+    ) -> Result<MayUnsupported<O>, E> {
+        let start = Instant::now();
+        let result = f();
+        let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.timing.nanos.fetch_add(nanos, Ordering::Relaxed);
+        if let Ok(MayUnsupported::Ok(_)) = result {
+            self.timing.leaves.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(entries) = &self.entries {
+            let mut entries = entries.lock().unwrap();
+            let cost = entries.entry(name.to_owned()).or_default();
+            cost.nanos = cost.nanos.saturating_add(nanos);
+            cost.size = cost.size.max(size.unwrap_or(0));
+        }
+        result
+    }
+}
+
+impl<T> __sealed::Sealed for Timed<T> {}
+
+impl<T: LeafTraverse, Reporter, D: DiffReport<T, Reporter>> DiffReport<T, Reporter> for Timed<D> {
+    fn diff(
+        &self,
+        name: &str,
+        expected: T,
+        actual: T,
+        reporter: &Reporter,
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>> {
+        let size = expected.size().max(actual.size());
+        self.record(name, size, || self.inner.diff(name, expected, actual, reporter))
+    }
+
+    fn added(&self, name: &str, data: T, reporter: &Reporter) -> Result<MayUnsupported<()>, Box<dyn Error + Send>> {
+        self.record(name, data.size(), || self.inner.added(name, data, reporter))
+    }
+
+    fn deleted(&self, name: &str, data: T, reporter: &Reporter) -> Result<MayUnsupported<()>, Box<dyn Error + Send>> {
+        self.record(name, data.size(), || self.inner.deleted(name, data, reporter))
+    }
+
+    fn diff_candidates(
+        &self,
+        name: &str,
+        expected: T,
+        candidates: Vec<T>,
+        reporter: &Reporter,
+    ) -> Result<MayUnsupported<DiffOutcome>, Box<dyn Error + Send>> {
+        let size = candidates.iter().map(T::size).fold(expected.size(), Option::max);
+        self.record(name, size, || {
+            self.inner.diff_candidates(name, expected, candidates, reporter)
+        })
+    }
+
+    fn similarity(&self, expected: &T, actual: &T) -> Option<f64> {
+        self.inner.similarity(expected, actual)
+    }
+
+    fn format_regression(&self, expected: &T, actual: &T) -> Option<FormatRegression> {
+        self.inner.format_regression(expected, actual)
+    }
+
+    fn unreadable(&self, leaf: &T) -> bool {
+        self.inner.unreadable(leaf)
+    }
+}
@@ -0,0 +1,463 @@
+//! Accepting the changes of a run by writing them back to the `expected` tree, so that the next run
+//! passes, as snapshot testing tools do.
+
+use crate::{
+    DetailReporter, EntryType, FormatRegression, MayUnsupported, NameMatching, Reporter, Sampling, SkipReason,
+    ToleranceCheck,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateKind {
+    /// The file in `expected` is overwritten with the one in `actual`.
+    Modified,
+    /// The file in `actual` is copied to `expected`.
+    Added,
+    /// The file is removed from `expected`.
+    Deleted,
+}
+
+/// The leaves that a run reported as modified, added, or deleted, to be written back to the
+/// `expected` tree with [`ExpectedUpdates::apply`] once the run is over. A leaf paired up as a
+/// rename is deleted under its old name and added under its new one.
+///
+/// Leaves that no differ reported, such as skipped ones, are left as they are.
+#[derive(Debug, Default)]
+pub struct ExpectedUpdates {
+    changes: Mutex<BTreeMap<String, UpdateKind>>,
+    names: NameMatching,
+}
+
+#[derive(Debug, Error)]
+#[error("failed to update {name} in {target}: {source}")]
+pub struct UpdateError {
+    name: String,
+    target: String,
+    #[source]
+    source: io::Error,
+}
+
+impl ExpectedUpdates {
+    pub fn new() -> ExpectedUpdates {
+        ExpectedUpdates::default()
+    }
+
+    /// Finds each leaf in `actual` as the run paired names, since reports use the spelling from
+    /// `expected`. Files written to `expected` keep the reported name.
+    pub fn with_name_matching(self, names: NameMatching) -> ExpectedUpdates {
+        ExpectedUpdates { names, ..self }
+    }
+
+    /// Records the first change of `name`, so that a renamed leaf stays added when its diff is
+    /// reported as modified.
+    fn record(&self, name: &str, kind: UpdateKind) {
+        self.changes.lock().unwrap().entry(name.to_owned()).or_insert(kind);
+    }
+
+    /// The recorded changes, ordered by name.
+    pub fn changes(&self) -> Vec<(String, UpdateKind)> {
+        let changes = self.changes.lock().unwrap();
+        changes.iter().map(|(name, kind)| (name.clone(), *kind)).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.lock().unwrap().is_empty()
+    }
+
+    /// Makes the tree at `expected` match `actual` for every recorded change. Deleted files are
+    /// removed first, along with directories they leave empty, so that a file may take the place
+    /// of a directory and the other way around.
+    ///
+    /// Every file to delete and every file to copy is looked up before anything is changed, so a
+    /// missing one fails the update without touching `expected`.
+    pub fn apply(&self, expected: &Path, actual: &Path) -> Result<(), UpdateError> {
+        let changes = self.changes();
+        let error = |name: &str, source| UpdateError {
+            name: name.to_owned(),
+            target: expected.display().to_string(),
+            source,
+        };
+        let missing = |name: &str, tree| {
+            error(
+                name,
+                io::Error::new(io::ErrorKind::NotFound, format!("no such file in {tree}")),
+            )
+        };
+        if let Some((name, _)) = changes
+            .iter()
+            .find(|(name, kind)| *kind == UpdateKind::Deleted && !expected.join(name).is_file())
+        {
+            return Err(missing(name, "expected"));
+        }
+        let copies = changes
+            .iter()
+            .filter(|(_, kind)| *kind != UpdateKind::Deleted)
+            .map(|(name, _)| {
+                let source = self.names.locate(actual, name);
+                if !source.is_file() {
+                    return Err(missing(name, "actual"));
+                }
+                Ok((name, source))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, _) in changes.iter().filter(|(_, kind)| *kind == UpdateKind::Deleted) {
+            let path = expected.join(name);
+            fs::remove_file(&path).map_err(|source| error(name, source))?;
+            for parent in path.ancestors().skip(1) {
+                if parent == expected || fs::remove_dir(parent).is_err() {
+                    break;
+                }
+            }
+        }
+        for (name, from) in copies {
+            let destination = expected.join(name);
+            let copy = || {
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&from, &destination)
+            };
+            copy().map_err(|source| error(name, source))?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a reporter so that every leaf a differ has reported to it as modified, added, or deleted
+/// is recorded in an [`ExpectedUpdates`]. Every call is forwarded unchanged.
+#[derive(Debug)]
+pub struct RecordUpdates<'a, R> {
+    inner: R,
+    updates: Option<&'a ExpectedUpdates>,
+}
+
+impl<'a, R> RecordUpdates<'a, R> {
+    /// Wraps `inner` without recording anything until `updates` is set.
+    pub fn new(inner: R) -> RecordUpdates<'a, R> {
+        RecordUpdates { inner, updates: None }
+    }
+
+    pub fn with_updates(self, updates: Option<&'a ExpectedUpdates>) -> RecordUpdates<'a, R> {
+        RecordUpdates { updates, ..self }
+    }
+
+    fn record<E>(
+        &self,
+        name: &str,
+        kind: UpdateKind,
+        result: Result<MayUnsupported<()>, E>,
+    ) -> Result<MayUnsupported<()>, E> {
+        if let (Ok(MayUnsupported::Ok(())), Some(updates)) = (&result, self.updates) {
+            updates.record(name, kind);
+        }
+        result
+    }
+}
+
+impl<D, Diff, T, R> DetailReporter<Diff, T, RecordUpdates<'_, R>> for D
+where
+    D: DetailReporter<Diff, T, R>,
+{
+    type Error = D::Error;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &RecordUpdates<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.report_unchanged(name, diff, &reporter.inner)
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &Diff,
+        reporter: &RecordUpdates<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record(
+            name,
+            UpdateKind::Modified,
+            self.report_modified(name, diff, &reporter.inner),
+        )
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &RecordUpdates<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record(name, UpdateKind::Added, self.report_added(name, data, &reporter.inner))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &T,
+        reporter: &RecordUpdates<'_, R>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record(
+            name,
+            UpdateKind::Deleted,
+            self.report_deleted(name, data, &reporter.inner),
+        )
+    }
+}
+
+impl<R: Reporter> Reporter for RecordUpdates<'_, R> {
+    type Error = R::Error;
+
+    fn start(&mut self) -> Result<(), Self::Error> {
+        self.inner.start()
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        self.inner.finish()
+    }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.inner.report_kind_mismatch(name, expected_kind, actual_kind)
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.inner.report_format_regression(name, regression)
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        self.inner.report_sampling(sampling, left_out)
+    }
+
+    fn report_change_magnitude(&self, name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        self.inner.report_change_magnitude(name, magnitude)
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_reencoded(name)
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_identical(name)
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        self.inner.report_tolerance_check(name, check)
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.inner.report_skipped(name, reason)
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.inner.report_type_changed(name, expected, actual)
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_added_empty_node(name)
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_deleted_empty_node(name)
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_added_leaf(name)
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.report_deleted_leaf(name)
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        // The diff of the pair follows under the new name, which is added whether or not it
+        // changed.
+        if let Some(updates) = self.updates {
+            updates.record(from, UpdateKind::Deleted);
+            updates.record(name, UpdateKind::Added);
+        }
+        self.inner.report_renamed(name, from, similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct Accepting;
+
+    impl DetailReporter<(), (), ()> for Accepting {
+        type Error = convert::Infallible;
+
+        fn report_unchanged(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_modified(&self, _name: &str, _diff: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_added(&self, name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            if name.ends_with(".bin") {
+                return Ok(MayUnsupported::Unsupported);
+            }
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_deleted(&self, _name: &str, _data: &(), _reporter: &()) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Ok(()))
+        }
+    }
+
+    #[test]
+    fn expected_updates_make_expected_match_actual() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let root = std::env::temp_dir().join(format!("semdiff-core-update-{nanos}"));
+        let (expected, actual) = (root.join("expected"), root.join("actual"));
+        fs::create_dir_all(expected.join("gone")).unwrap();
+        fs::create_dir_all(actual.join("gone")).unwrap();
+        for (name, content) in [("same.txt", "same"), ("changed.txt", "old"), ("gone/file.txt", "gone")] {
+            fs::write(expected.join(name), content).unwrap();
+        }
+        for (name, content) in [
+            ("same.txt", "same"),
+            ("changed.txt", "new"),
+            ("new.txt", "new"),
+            ("new.bin", ""),
+        ] {
+            fs::write(actual.join(name), content).unwrap();
+        }
+        fs::remove_dir(actual.join("gone")).unwrap();
+        // `gone` is a file in `actual`, in place of the directory in `expected`.
+        fs::write(actual.join("gone"), "file").unwrap();
+
+        let updates = ExpectedUpdates::new();
+        let reporter = RecordUpdates::new(()).with_updates(Some(&updates));
+        Accepting.report_unchanged("same.txt", &(), &reporter).unwrap();
+        Accepting.report_modified("changed.txt", &(), &reporter).unwrap();
+        Accepting.report_added("new.txt", &(), &reporter).unwrap();
+        Accepting.report_added("new.bin", &(), &reporter).unwrap();
+        Accepting.report_deleted("gone/file.txt", &(), &reporter).unwrap();
+        Accepting.report_added("gone", &(), &reporter).unwrap();
+        assert_eq!(
+            updates.changes(),
+            [
+                ("changed.txt".to_owned(), UpdateKind::Modified),
+                ("gone".to_owned(), UpdateKind::Added),
+                ("gone/file.txt".to_owned(), UpdateKind::Deleted),
+                ("new.txt".to_owned(), UpdateKind::Added),
+            ]
+        );
+
+        updates.apply(&expected, &actual).unwrap();
+        assert_eq!(fs::read_to_string(expected.join("changed.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(expected.join("new.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(expected.join("gone")).unwrap(), "file");
+        assert_eq!(fs::read_to_string(expected.join("same.txt")).unwrap(), "same");
+        assert!(!expected.join("new.bin").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn temp_trees(
+        label: &str,
+        expected_files: &[(&str, &str)],
+        actual_files: &[(&str, &str)],
+    ) -> (PathBuf, PathBuf, PathBuf) {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let root = std::env::temp_dir().join(format!("semdiff-core-update-{label}-{nanos}"));
+        let (expected, actual) = (root.join("expected"), root.join("actual"));
+        for (tree, files) in [(&expected, expected_files), (&actual, actual_files)] {
+            fs::create_dir_all(tree).unwrap();
+            for (name, content) in files {
+                let path = tree.join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+        }
+        (root, expected, actual)
+    }
+
+    #[test]
+    fn expected_updates_apply_nested_paths_and_type_changes() {
+        let (root, expected, actual) = temp_trees(
+            "nested",
+            &[
+                ("a/b/changed.txt", "old"),
+                ("a/b/gone/deep.txt", "gone"),
+                ("file", "file"),
+            ],
+            &[
+                ("a/b/changed.txt", "new"),
+                ("a/b/gone", "now a file"),
+                ("file/inner/new.txt", "now a directory"),
+                ("x/y/z.txt", "new"),
+            ],
+        );
+        let updates = ExpectedUpdates::new();
+        updates.record("a/b/changed.txt", UpdateKind::Modified);
+        updates.record("a/b/gone/deep.txt", UpdateKind::Deleted);
+        updates.record("a/b/gone", UpdateKind::Added);
+        updates.record("file", UpdateKind::Deleted);
+        updates.record("file/inner/new.txt", UpdateKind::Added);
+        updates.record("x/y/z.txt", UpdateKind::Added);
+
+        updates.apply(&expected, &actual).unwrap();
+        for (name, content) in [
+            ("a/b/changed.txt", "new"),
+            ("a/b/gone", "now a file"),
+            ("file/inner/new.txt", "now a directory"),
+            ("x/y/z.txt", "new"),
+        ] {
+            assert_eq!(fs::read_to_string(expected.join(name)).unwrap(), content, "{name}");
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expected_updates_apply_checks_every_file_before_changing_any() {
+        let (root, expected, actual) = temp_trees(
+            "missing",
+            &[("gone.txt", "gone"), ("changed.txt", "old")],
+            &[("changed.txt", "new")],
+        );
+        let updates = ExpectedUpdates::new();
+        updates.record("gone.txt", UpdateKind::Deleted);
+        updates.record("changed.txt", UpdateKind::Modified);
+        updates.record("vanished.txt", UpdateKind::Added);
+        assert!(updates.apply(&expected, &actual).is_err());
+        assert_eq!(fs::read_to_string(expected.join("gone.txt")).unwrap(), "gone");
+        assert_eq!(fs::read_to_string(expected.join("changed.txt")).unwrap(), "old");
+
+        let updates = ExpectedUpdates::new();
+        updates.record("not-there.txt", UpdateKind::Deleted);
+        updates.record("changed.txt", UpdateKind::Modified);
+        assert!(updates.apply(&expected, &actual).is_err());
+        assert_eq!(fs::read_to_string(expected.join("changed.txt")).unwrap(), "old");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expected_updates_find_files_spelled_differently_in_actual() {
+        let (root, expected, actual) = temp_trees(
+            "names",
+            &[("Dir/A.txt", "old")],
+            &[("dir/a.txt", "new"), ("dir/b.txt", "added")],
+        );
+        let names = crate::DiffOptions::new().with_case_folded_names(true).name_matching();
+        let updates = ExpectedUpdates::new().with_name_matching(names);
+        updates.record("Dir/A.txt", UpdateKind::Modified);
+        updates.record("Dir/b.txt", UpdateKind::Added);
+
+        updates.apply(&expected, &actual).unwrap();
+        assert_eq!(fs::read_to_string(expected.join("Dir/A.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(expected.join("Dir/b.txt")).unwrap(), "added");
+        fs::remove_dir_all(root).unwrap();
+    }
+}
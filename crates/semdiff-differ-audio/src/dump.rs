@@ -0,0 +1,120 @@
+//! Raw samples and spectrograms written as NumPy `.npy` files, for analysis outside of semdiff.
+
+use crate::SPECTROGRAM_DATA_HEIGHT;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// `.npy` files start with this magic string and format version 1.0.
+const NPY_MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+/// The header of an `.npy` file is padded so that the data starts at a multiple of this.
+const NPY_ALIGNMENT: usize = 64;
+
+/// Writes the decoded data of each audio file to `DIR/<name>/`, one `.npy` file per channel:
+///
+/// - `<label>_samples_ch<N>.npy`: `float32` samples, shape `(samples,)`.
+/// - `<label>_spectrogram_ch<N>.npy`: `float32` `log10` of the squared FFT magnitude, shape
+///   `(frames, 1024)`, one frame per hop from 0 Hz up to the Nyquist frequency.
+/// - `aligned_<label>_samples_ch<N>.npy`: for a comparison, the samples after aligning both
+///   sides, before any gain is removed.
+#[derive(Debug, Clone)]
+pub struct AudioDataDump {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Error)]
+#[error("failed to dump audio data of {name} into {path}: {source}")]
+pub struct AudioDataDumpError {
+    name: String,
+    path: String,
+    #[source]
+    source: io::Error,
+}
+
+impl AudioDataDump {
+    pub fn new(dir: impl Into<PathBuf>) -> AudioDataDump {
+        AudioDataDump { dir: dir.into() }
+    }
+
+    /// Writes the samples and spectrograms of the leaf at path `name`, returning the written paths.
+    pub(crate) fn write_decoded(
+        &self,
+        name: &str,
+        label: &str,
+        samples: &[Vec<f32>],
+        spectrograms: &[Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>],
+    ) -> Result<Vec<String>, AudioDataDumpError> {
+        let mut paths = self.write_samples(name, label, samples)?;
+        for (index, spectrogram) in spectrograms.iter().enumerate() {
+            let path = self.path(name, &format!("{label}_spectrogram_ch{}", index + 1));
+            write_npy(
+                &path,
+                &[spectrogram.len(), SPECTROGRAM_DATA_HEIGHT],
+                spectrogram.as_flattened(),
+            )
+            .map_err(|source| self.error(name, &path, source))?;
+            paths.push(path.display().to_string());
+        }
+        Ok(paths)
+    }
+
+    /// Writes one `<label>_samples_ch<N>.npy` file per channel, returning the written paths.
+    pub(crate) fn write_samples(
+        &self,
+        name: &str,
+        label: &str,
+        samples: &[Vec<f32>],
+    ) -> Result<Vec<String>, AudioDataDumpError> {
+        let mut paths = Vec::with_capacity(samples.len());
+        for (index, channel) in samples.iter().enumerate() {
+            let path = self.path(name, &format!("{label}_samples_ch{}", index + 1));
+            write_npy(&path, &[channel.len()], channel).map_err(|source| self.error(name, &path, source))?;
+            paths.push(path.display().to_string());
+        }
+        Ok(paths)
+    }
+
+    fn path(&self, name: &str, file_stem: &str) -> PathBuf {
+        self.dir.join(name).join(format!("{file_stem}.npy"))
+    }
+
+    fn error(&self, name: &str, path: &Path, source: io::Error) -> AudioDataDumpError {
+        AudioDataDumpError {
+            name: name.to_owned(),
+            path: path.display().to_string(),
+            source,
+        }
+    }
+}
+
+/// Writes `data` as a little-endian `float32` array of `shape` in C order.
+fn write_npy(path: &Path, shape: &[usize], data: &[f32]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&npy_header(shape))?;
+    for value in data {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+fn npy_header(shape: &[usize]) -> Vec<u8> {
+    let dims = match shape {
+        [len] => format!("{len},"),
+        _ => shape.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+    };
+    let mut dict = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({dims}), }}");
+    // The length field and the trailing newline count towards the padded size.
+    let unpadded = NPY_MAGIC.len() + 2 + dict.len() + 1;
+    dict.extend(iter::repeat_n(' ', unpadded.next_multiple_of(NPY_ALIGNMENT) - unpadded));
+    dict.push('\n');
+
+    let mut header = NPY_MAGIC.to_vec();
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
@@ -1,16 +1,22 @@
+use crate::dump::{AudioDataDump, AudioDataDumpError};
+use crate::tags::AudioTags;
 use image::{Rgba, RgbaImage};
-use memmap2::Mmap;
 use mime::Mime;
 use rustfft::num_complex::Complex;
 use rustfft::num_traits::Zero;
 use rustfft::{Fft, FftPlanner};
-use semdiff_core::fs::FileLeaf;
-use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use semdiff_core::fs::{FileContent, FileLeaf};
+use semdiff_core::memory::{MemoryBudget, MemoryReservation};
+use semdiff_core::{Diff, DiffCalculator, FormatRegression, MayUnsupported, ToleranceCheck};
+use serde::Serialize;
+use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::f32::consts::PI;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
 use std::io::Cursor;
 use std::ops::Range;
+use std::str::FromStr;
 use std::sync::{Arc, LazyLock};
 use std::{convert, iter, mem};
 use symphonia::core::audio::AudioSpec;
@@ -22,9 +28,14 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use thiserror::Error;
 
+pub mod dump;
+pub mod report_collect;
 pub mod report_html;
 pub mod report_json;
 pub mod report_summary;
+mod tags;
+
+pub use tags::TagChange;
 
 #[cfg(test)]
 mod tests;
@@ -35,10 +46,97 @@ const SPECTROGRAM_WIDTH: u32 = 1024;
 const SPECTROGRAM_HEIGHT: u32 = 256;
 const SPECTROGRAM_DATA_HEIGHT: usize = 1024;
 const FFT_WINDOW_SIZE: usize = SPECTROGRAM_DATA_HEIGHT * 2;
+/// Default FFT hop size in samples (50% overlap).
+pub const DEFAULT_FFT_HOP_SIZE: usize = FFT_WINDOW_SIZE / 2;
+/// Smallest accepted FFT hop size in samples (97% overlap). Each hop computes one FFT window per
+/// channel, so smaller hops multiply the time a comparison takes.
+pub const MIN_FFT_HOP_SIZE: usize = FFT_WINDOW_SIZE / 32;
+/// Largest accepted FFT hop size in samples (no overlap).
+pub const MAX_FFT_HOP_SIZE: usize = FFT_WINDOW_SIZE;
 const LOG_EPSILON: f32 = 1e-6;
 
+/// An sRGB color written as `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexColor(pub [u8; 3]);
+
+#[derive(Debug, Error)]
+#[error("invalid color {0:?} (expected #rrggbb)")]
+pub struct ParseHexColorError(String);
+
+impl FromStr for HexColor {
+    type Err = ParseHexColorError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHexColorError(input.to_owned());
+        let hex = input.strip_prefix('#').unwrap_or(input);
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| error());
+        Ok(HexColor([channel(0)?, channel(1)?, channel(2)?]))
+    }
+}
+
+impl Display for HexColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Colors the HTML report tints audio images with. Waveforms, spectrograms, and spectrogram diffs
+/// are rendered as alpha masks, so a color only changes how they are drawn; `None` keeps the
+/// report's light or dark theme color.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioColors {
+    pub waveform: Option<HexColor>,
+    pub spectrogram: Option<HexColor>,
+    pub spectrogram_diff: Option<HexColor>,
+}
+
+/// A section of the decoded signal, in seconds from the start.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AudioRange {
+    start_seconds: f32,
+    end_seconds: f32,
+}
+
+impl AudioRange {
+    /// Returns `None` unless `0 <= start_seconds < end_seconds`.
+    pub fn new(start_seconds: f32, end_seconds: f32) -> Option<AudioRange> {
+        (0.0 <= start_seconds && start_seconds < end_seconds).then_some(AudioRange {
+            start_seconds,
+            end_seconds,
+        })
+    }
+
+    pub fn start_seconds(&self) -> f32 {
+        self.start_seconds
+    }
+
+    pub fn end_seconds(&self) -> f32 {
+        self.end_seconds
+    }
+
+    /// Sample indices covered by this range at `sample_rate`, clamped to `len` samples.
+    fn samples(&self, sample_rate: u32, len: usize) -> Range<usize> {
+        let index = |seconds: f32| ((seconds * sample_rate as f32).round() as usize).min(len);
+        index(self.start_seconds)..index(self.end_seconds)
+    }
+}
+
+impl Display for AudioRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\u{2013}{} sec", self.start_seconds, self.end_seconds)
+    }
+}
+
 pub struct AudioDiffReporter {
     spectrogram_analyzer: SpectrogramAnalyzer,
+    colors: AudioColors,
+    axes: bool,
+    minimal_unchanged: bool,
+    data_dump: Option<AudioDataDump>,
 }
 
 impl Default for AudioDiffReporter {
@@ -51,13 +149,97 @@ impl AudioDiffReporter {
     pub fn new() -> AudioDiffReporter {
         AudioDiffReporter {
             spectrogram_analyzer: SpectrogramAnalyzer::new(),
+            colors: AudioColors::default(),
+            axes: false,
+            minimal_unchanged: false,
+            data_dump: None,
         }
     }
 
-    fn build_audio_data(&self, kind: Mime, content: Arc<Mmap>) -> Result<AudioData, AudioDecodeError> {
-        let decoded = self.spectrogram_analyzer.decode_audio(&kind, &content)?;
-        let stat = AudioStat::from_one(&decoded);
-        Ok(build_audio_data_from_decoded(kind, content, &decoded, &stat))
+    /// Labels the spectrograms in the HTML report with a frequency axis in Hz and a time axis in
+    /// seconds.
+    pub fn with_axes(mut self, axes: bool) -> Self {
+        self.axes = axes;
+        self
+    }
+
+    /// Lists unchanged audio in the HTML report with only a merged waveform thumbnail, without
+    /// copying the audio file, writing per-channel waveforms and spectrograms, or a detail page.
+    pub fn with_minimal_unchanged(mut self, minimal_unchanged: bool) -> Self {
+        self.minimal_unchanged = minimal_unchanged;
+        self
+    }
+
+    /// Writes the raw samples and spectrograms of every reported file through `data_dump`, and
+    /// lists the written files in the report. Compared files only carry that data when the
+    /// calculator keeps it, see [`AudioDiffCalculator::with_raw_data`].
+    pub fn with_data_dump(mut self, data_dump: Option<AudioDataDump>) -> Self {
+        self.data_dump = data_dump;
+        self
+    }
+
+    /// Overrides the colors of waveforms, spectrograms, and spectrogram diffs in the HTML report.
+    pub fn with_colors(mut self, colors: AudioColors) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Sets the FFT hop size in samples, clamped to `MIN_FFT_HOP_SIZE..=MAX_FFT_HOP_SIZE`.
+    pub fn with_hop_size(mut self, hop_size: usize) -> Self {
+        self.spectrogram_analyzer = SpectrogramAnalyzer::with_hop_size(hop_size);
+        self
+    }
+
+    fn build_audio_data(&self, kind: Mime, content: Arc<FileContent>) -> Result<AudioData, AudioDecodeError> {
+        let analyzer = &self.spectrogram_analyzer;
+        let decoded = analyzer.decode_audio(&kind, &content)?;
+        let stat = AudioStat::from_one(&decoded, analyzer);
+        Ok(build_audio_data_from_decoded(
+            kind,
+            content,
+            decoded,
+            &stat,
+            analyzer,
+            true,
+            self.data_dump.is_some(),
+        ))
+    }
+
+    /// Dumps the raw data of both sides of `diff` and, if kept, their aligned samples.
+    fn dump_diff(&self, name: &str, diff: &AudioDiff) -> Result<Vec<String>, AudioDataDumpError> {
+        let mut paths = self.dump_data(name, "expected", diff.expected())?;
+        paths.extend(self.dump_data(name, "actual", diff.actual())?);
+        if let (Some(data_dump), Some([expected, actual])) = (
+            &self.data_dump,
+            diff.diff_detail().and_then(|detail| detail.aligned_samples.as_ref()),
+        ) {
+            paths.extend(data_dump.write_samples(name, "aligned_expected", expected)?);
+            paths.extend(data_dump.write_samples(name, "aligned_actual", actual)?);
+        }
+        Ok(paths)
+    }
+
+    /// Dumps the raw data of `data` under `label`, if kept.
+    fn dump_data(&self, name: &str, label: &str, data: &AudioData) -> Result<Vec<String>, AudioDataDumpError> {
+        match (&self.data_dump, &data.raw) {
+            (Some(data_dump), Some(raw)) => data_dump.write_decoded(name, label, &raw.samples, &raw.spectrograms),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Dumps the raw data of an added or deleted file, decoded by the reporter itself.
+    fn dump_decoded(&self, name: &str, label: &str, decoded: &AudioDecoded) -> Result<Vec<String>, AudioDataDumpError> {
+        match &self.data_dump {
+            Some(data_dump) => {
+                let spectrograms = decoded
+                    .samples
+                    .iter()
+                    .map(|channel| self.spectrogram_analyzer.compute(channel))
+                    .collect::<Vec<_>>();
+                data_dump.write_decoded(name, label, &decoded.samples, &spectrograms)
+            }
+            None => Ok(Vec::new()),
+        }
     }
 }
 
@@ -81,14 +263,39 @@ impl AudioDiffStatus {
 #[derive(Debug)]
 pub struct AudioDiff {
     status: AudioDiffStatus,
+    byte_identical: bool,
+    /// Tags that differ between the files; they only decide [`AudioDiff::status`] when tags are
+    /// compared (see [`AudioDiffCalculator::with_compare_tags`]).
+    tag_changes: Vec<TagChange>,
     expected: AudioData,
     actual: AudioData,
+    /// Held until the diff is dropped after reporting, like the data it accounts for; see
+    /// [`AudioDiffCalculator::with_memory_budget`].
+    _memory: [MemoryReservation; 2],
 }
 
 impl Diff for AudioDiff {
     fn equal(&self) -> bool {
         matches!(self.status, AudioDiffStatus::Equal(_))
     }
+
+    fn magnitude(&self) -> Option<f64> {
+        self.diff_detail().map(|detail| {
+            let stat = detail.stat();
+            stat.spectrogram_diff_rate
+                .unwrap_or_else(|| f64::from(1.0 - stat.correlation).clamp(0.0, 1.0))
+        })
+    }
+
+    fn byte_identical(&self) -> Option<bool> {
+        Some(self.byte_identical)
+    }
+
+    fn tolerance_checks(&self) -> Vec<ToleranceCheck> {
+        self.diff_detail()
+            .map(|detail| detail.tolerance_checks.clone())
+            .unwrap_or_default()
+    }
 }
 
 impl AudioDiff {
@@ -104,6 +311,10 @@ impl AudioDiff {
         &self.actual
     }
 
+    fn tag_changes(&self) -> &[TagChange] {
+        &self.tag_changes
+    }
+
     fn diff_detail(&self) -> Option<&AudioDiffDetail> {
         match &self.status {
             AudioDiffStatus::Equal(detail) | AudioDiffStatus::Different(detail) => Some(detail),
@@ -116,6 +327,9 @@ impl AudioDiff {
 pub struct AudioDiffDetail {
     spectrogram_diff: Vec<RgbaImage>,
     stat: AudioDiffStat,
+    tolerance_checks: Vec<ToleranceCheck>,
+    /// Samples of `expected` and `actual` after alignment, kept for [`AudioDataDump`].
+    aligned_samples: Option<[Vec<Vec<f32>>; 2]>,
 }
 
 impl AudioDiffDetail {
@@ -130,9 +344,16 @@ impl AudioDiffDetail {
 
 #[derive(Debug, Clone, Copy)]
 pub struct AudioDiffStat {
-    pub spectrogram_diff_rate: f64,
+    /// `None` when equality was decided by waveform correlation and spectrograms were not compared.
+    pub spectrogram_diff_rate: Option<f64>,
+    /// Normalized cross-correlation of the aligned waveforms, averaged over channels; 1.0 for
+    /// identical shapes regardless of gain.
+    pub correlation: f32,
     pub shift_samples: i32,
     pub lufs_diff_db: f32,
+    /// Gain of `actual` relative to `expected` in dB, removed before the spectral comparison when
+    /// gain is ignored.
+    pub gain_db: Option<f32>,
 }
 
 #[derive(Debug)]
@@ -141,9 +362,22 @@ pub struct AudioData {
     sample_rate: u32,
     channels: u16,
     duration_seconds: f32,
+    /// Time span the waveform and spectrogram images cover; for a comparison, the longer of both.
+    rendered_seconds: f32,
     waveform: Vec<RgbaImage>,
     spectrogram: Vec<RgbaImage>,
-    content: Arc<Mmap>,
+    anomalies: DecodeAnomalies,
+    /// Section the comparison was limited to, if any; the other fields describe that section.
+    range: Option<AudioRange>,
+    content: Arc<FileContent>,
+    /// Decoded samples and spectrograms, kept for [`AudioDataDump`].
+    raw: Option<AudioRawData>,
+}
+
+#[derive(Debug)]
+struct AudioRawData {
+    samples: Vec<Vec<f32>>,
+    spectrograms: Vec<Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>>,
 }
 
 impl AudioData {
@@ -163,6 +397,10 @@ impl AudioData {
         self.duration_seconds
     }
 
+    fn rendered_seconds(&self) -> f32 {
+        self.rendered_seconds
+    }
+
     fn waveform(&self) -> &[RgbaImage] {
         &self.waveform
     }
@@ -171,11 +409,50 @@ impl AudioData {
         &self.spectrogram
     }
 
+    fn anomalies(&self) -> DecodeAnomalies {
+        self.anomalies
+    }
+
+    fn range(&self) -> Option<AudioRange> {
+        self.range
+    }
+
     fn content(&self) -> &[u8] {
         &self.content
     }
 }
 
+/// Problems met while decoding that suggest a file is corrupt. The decoded signal only covers what
+/// could be read, so a broken file would otherwise compare as merely shorter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DecodeAnomalies {
+    /// The stream ended in the middle of a packet.
+    pub truncated: bool,
+    /// Packets that failed to decode and were skipped.
+    pub skipped_packets: usize,
+}
+
+impl DecodeAnomalies {
+    pub fn is_clean(&self) -> bool {
+        *self == DecodeAnomalies::default()
+    }
+}
+
+impl Display for DecodeAnomalies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut separator = "";
+        if self.truncated {
+            f.write_str("the stream ends unexpectedly")?;
+            separator = ", ";
+        }
+        match self.skipped_packets {
+            0 => Ok(()),
+            1 => write!(f, "{separator}1 packet failed to decode"),
+            n => write!(f, "{separator}{n} packets failed to decode"),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AudioDecodeError {
     #[error("symphonia error: {0}")]
@@ -186,15 +463,29 @@ pub enum AudioDecodeError {
     MissingAudioCodecParameters,
     #[error("missing sample rate")]
     MissingSampleRate,
+    #[error("memory budget exceeded")]
+    MemoryBudgetExceeded,
 }
 
-#[derive(Default)]
 pub struct AudioDiffCalculator {
     shift_tolerance_seconds: f32,
     lufs_tolerance_db: f32,
     spectral_tolerance: f32,
     spectrogram_diff_rate_tolerance: f64,
+    ignore_gain: bool,
+    correlation_threshold: Option<f32>,
     spectrogram_analyzer: SpectrogramAnalyzer,
+    render_visuals: bool,
+    keep_raw_data: bool,
+    memory_budget: MemoryBudget,
+    range: Option<AudioRange>,
+    compare_tags: bool,
+}
+
+impl Default for AudioDiffCalculator {
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 0.0, 0.0)
+    }
 }
 
 impl Debug for AudioDiffCalculator {
@@ -204,6 +495,14 @@ impl Debug for AudioDiffCalculator {
             .field("lufs_tolerance_db", &self.lufs_tolerance_db)
             .field("spectral_tolerance", &self.spectral_tolerance)
             .field("spectrogram_diff_rate_tolerance", &self.spectrogram_diff_rate_tolerance)
+            .field("ignore_gain", &self.ignore_gain)
+            .field("correlation_threshold", &self.correlation_threshold)
+            .field("hop_size", &self.spectrogram_analyzer.hop_size)
+            .field("render_visuals", &self.render_visuals)
+            .field("keep_raw_data", &self.keep_raw_data)
+            .field("memory_budget", &self.memory_budget)
+            .field("range", &self.range)
+            .field("compare_tags", &self.compare_tags)
             .finish()
     }
 }
@@ -217,33 +516,82 @@ impl AudioDiffCalculator {
         let sample_rate = expected.sample_rate;
         let max_shift_samples = (self.shift_tolerance_seconds * sample_rate as f32).round() as u32;
         let (aligned_expected, aligned_actual, shift_samples) =
-            align_samples(expected.samples.clone(), actual.samples.clone(), max_shift_samples);
-
-        let expected_spectrogram = aligned_expected
-            .iter()
-            .map(|channel| self.spectrogram_analyzer.compute(channel))
-            .collect::<Vec<_>>();
-        let actual_spectrogram = aligned_actual
-            .iter()
-            .map(|channel| self.spectrogram_analyzer.compute(channel))
-            .collect::<Vec<_>>();
-
-        let (spectrogram_diff, spectrogram_diff_rate) =
-            self.build_diff_images(&expected_spectrogram, &actual_spectrogram);
-
-        let lufs_diff_db = summarize_channel_metrics(&aligned_expected, &aligned_actual);
+            align_samples(&expected.samples, &actual.samples, max_shift_samples);
+
+        let lufs_diff_db = summarize_channel_metrics(&aligned_expected, &aligned_actual, 1.0);
+        // With gain ignored, only loudness differences left after matching the overall level count.
+        // `actual` is scaled as it is read rather than copied.
+        let gain = self
+            .ignore_gain
+            .then(|| gain_scale(&aligned_expected, &aligned_actual))
+            .flatten();
+        let (gain_db, actual_scale, compared_lufs_diff_db) = match gain {
+            Some((gain_db, scale)) => (
+                Some(gain_db),
+                scale,
+                summarize_channel_metrics(&aligned_expected, &aligned_actual, scale),
+            ),
+            None => (None, 1.0, lufs_diff_db),
+        };
+        let aligned_samples = self.keep_raw_data.then(|| {
+            [
+                aligned_expected.iter().map(|channel| channel.to_vec()).collect(),
+                aligned_actual
+                    .iter()
+                    .map(|channel| channel.iter().map(|sample| sample * actual_scale).collect())
+                    .collect(),
+            ]
+        });
+
+        let correlation = normalized_correlation(&aligned_expected, &aligned_actual, actual_scale);
+
+        let mut tolerance_checks = vec![ToleranceCheck {
+            metric: "lufs_diff_db",
+            measured: f64::from(compared_lufs_diff_db),
+            threshold: f64::from(self.lufs_tolerance_db),
+        }];
+        let (spectrogram_diff, spectrogram_diff_rate, shape_equal) = match self.correlation_threshold {
+            Some(threshold) => {
+                // Expressed as a shortfall from 1.0 so that, like the other checks, lower is closer.
+                tolerance_checks.push(ToleranceCheck {
+                    metric: "decorrelation",
+                    measured: f64::from(1.0 - correlation),
+                    threshold: f64::from(1.0 - threshold),
+                });
+                (Vec::new(), None, correlation >= threshold)
+            }
+            None => {
+                let (spectrogram_diff, spectrogram_diff_rate) =
+                    self.build_diff_images(&aligned_expected, &aligned_actual, actual_scale);
+                tolerance_checks.push(ToleranceCheck {
+                    metric: "spectrogram_diff_rate",
+                    measured: spectrogram_diff_rate,
+                    threshold: self.spectrogram_diff_rate_tolerance,
+                });
+                (
+                    spectrogram_diff,
+                    Some(spectrogram_diff_rate),
+                    spectrogram_diff_rate <= self.spectrogram_diff_rate_tolerance,
+                )
+            }
+        };
 
         let detail = AudioDiffDetail {
             spectrogram_diff,
             stat: AudioDiffStat {
                 spectrogram_diff_rate,
+                correlation,
                 shift_samples,
                 lufs_diff_db,
+                gain_db,
             },
+            tolerance_checks,
+            aligned_samples,
         };
 
+        // A file that only one side decoded cleanly is reported even if the signals match.
         let equal =
-            lufs_diff_db <= self.lufs_tolerance_db && spectrogram_diff_rate <= self.spectrogram_diff_rate_tolerance;
+            compared_lufs_diff_db <= self.lufs_tolerance_db && shape_equal && expected.anomalies == actual.anomalies;
         if equal {
             AudioDiffStatus::Equal(detail)
         } else {
@@ -265,30 +613,95 @@ impl DiffCalculator<FileLeaf> for AudioDiffCalculator {
         if !is_audio_kind(&expected.kind) || !is_audio_kind(&actual.kind) {
             return Ok(MayUnsupported::Unsupported);
         }
-        let Ok(expected_decoded) = self
-            .spectrogram_analyzer
-            .decode_audio(&expected.kind, expected.content.as_ref())
-        else {
+        // Decoding that does not fit in the memory budget is treated as unsupported so that the
+        // next differ handles the pair with a cheaper byte comparison.
+        let Ok((expected_decoded, expected_memory)) = self.spectrogram_analyzer.decode_audio_within(
+            &expected.kind,
+            expected.content.as_ref(),
+            &self.memory_budget,
+            self.range,
+        ) else {
             return Ok(MayUnsupported::Unsupported);
         };
-        let Ok(actual_decoded) = self
-            .spectrogram_analyzer
-            .decode_audio(&actual.kind, actual.content.as_ref())
-        else {
+        let Ok((actual_decoded, actual_memory)) = self.spectrogram_analyzer.decode_audio_within(
+            &actual.kind,
+            actual.content.as_ref(),
+            &self.memory_budget,
+            self.range,
+        ) else {
             return Ok(MayUnsupported::Unsupported);
         };
-        let stat_decoded = AudioStat::from_pair(&expected_decoded, &actual_decoded);
-        let expected_data =
-            build_audio_data_from_decoded(expected.kind, expected.content, &expected_decoded, &stat_decoded);
-        let actual_data = build_audio_data_from_decoded(actual.kind, actual.content, &actual_decoded, &stat_decoded);
-        let status = self.diff_decoded(&expected_decoded, &actual_decoded);
+        let analyzer = &self.spectrogram_analyzer;
+        let stat_decoded = AudioStat::from_pair(
+            &expected_decoded,
+            &actual_decoded,
+            self.render_visuals.then_some(analyzer),
+        );
+        let byte_identical = expected.content[..] == actual.content[..];
+        let tag_changes = tags::tag_changes(&expected_decoded.tags, &actual_decoded.tags);
+        let status = match self.diff_decoded(&expected_decoded, &actual_decoded) {
+            AudioDiffStatus::Equal(detail) if self.compare_tags && !tag_changes.is_empty() => {
+                AudioDiffStatus::Different(detail)
+            }
+            status => status,
+        };
+        let expected_data = build_audio_data_from_decoded(
+            expected.kind,
+            expected.content,
+            expected_decoded,
+            &stat_decoded,
+            analyzer,
+            self.render_visuals,
+            self.keep_raw_data,
+        );
+        let actual_data = build_audio_data_from_decoded(
+            actual.kind,
+            actual.content,
+            actual_decoded,
+            &stat_decoded,
+            analyzer,
+            self.render_visuals,
+            self.keep_raw_data,
+        );
 
         Ok(MayUnsupported::Ok(AudioDiff {
             status,
+            byte_identical,
+            tag_changes,
             expected: expected_data,
             actual: actual_data,
+            _memory: [expected_memory, actual_memory],
         }))
     }
+
+    fn format_regression(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<FormatRegression> {
+        if !is_audio_kind(&expected.kind) || !is_audio_kind(&actual.kind) {
+            return None;
+        }
+        FormatRegression::from_parsed("audio", self.decodes(expected)?, self.decodes(actual)?)
+    }
+
+    fn unreadable(&self, leaf: &FileLeaf) -> bool {
+        // Video containers may hold no audio track, so only audio kinds count.
+        leaf.kind.type_() == mime::AUDIO && audio_extension(&leaf.kind).is_some() && self.decodes(leaf) == Some(false)
+    }
+}
+
+impl AudioDiffCalculator {
+    /// Whether `leaf` decodes as audio, or `None` when it does not fit in the memory budget: such a
+    /// file was never tried, so it tells nothing.
+    fn decodes(&self, leaf: &FileLeaf) -> Option<bool> {
+        match self.spectrogram_analyzer.decode_audio_within(
+            &leaf.kind,
+            leaf.content.as_ref(),
+            &self.memory_budget,
+            self.range,
+        ) {
+            Ok(_) => Some(true),
+            Err(AudioDecodeError::MemoryBudgetExceeded) => None,
+            Err(_) => Some(false),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -300,7 +713,8 @@ struct AudioStat {
 }
 
 impl AudioStat {
-    fn from_one(decoded: &AudioDecoded) -> AudioStat {
+    /// The spectrogram range is computed frame by frame with `analyzer`.
+    fn from_one(decoded: &AudioDecoded, analyzer: &SpectrogramAnalyzer) -> AudioStat {
         let signal_max = decoded
             .samples
             .iter()
@@ -309,11 +723,10 @@ impl AudioStat {
             .map(f32::abs)
             .fold(0.0, f32::max);
         let (spectrogram_min, spectrogram_max) = decoded
-            .spectrograms
+            .samples
             .iter()
+            .flat_map(|channel| analyzer.frames(channel))
             .flatten()
-            .flatten()
-            .copied()
             .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
                 (v.min(min), v.max(max))
             });
@@ -326,7 +739,9 @@ impl AudioStat {
         }
     }
 
-    fn from_pair(expected: &AudioDecoded, actual: &AudioDecoded) -> AudioStat {
+    /// Like [`AudioStat::from_one`], but the spectrogram range is left empty without `analyzer`,
+    /// for when no spectrogram is rendered.
+    fn from_pair(expected: &AudioDecoded, actual: &AudioDecoded, analyzer: Option<&SpectrogramAnalyzer>) -> AudioStat {
         let signal_max = expected
             .samples
             .iter()
@@ -335,13 +750,16 @@ impl AudioStat {
             .copied()
             .map(f32::abs)
             .fold(0.0, f32::max);
-        let (spectrogram_min, spectrogram_max) = expected
-            .spectrograms
-            .iter()
-            .chain(actual.spectrograms.iter())
-            .flatten()
+        let (spectrogram_min, spectrogram_max) = analyzer
+            .into_iter()
+            .flat_map(|analyzer| {
+                expected
+                    .samples
+                    .iter()
+                    .chain(actual.samples.iter())
+                    .flat_map(|channel| analyzer.frames(channel))
+            })
             .flatten()
-            .copied()
             .filter(|v| v.is_finite())
             .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
                 (v.min(min), v.max(max))
@@ -368,91 +786,158 @@ impl AudioDiffCalculator {
             lufs_tolerance_db,
             spectral_tolerance,
             spectrogram_diff_rate_tolerance,
+            ignore_gain: false,
+            correlation_threshold: None,
             spectrogram_analyzer: SpectrogramAnalyzer::new(),
+            render_visuals: true,
+            keep_raw_data: false,
+            memory_budget: MemoryBudget::unlimited(),
+            range: None,
+            compare_tags: false,
         }
     }
 
-    fn build_diff_images(
-        &self,
-        expected: &[Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>],
-        actual: &[Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>],
-    ) -> (Vec<RgbaImage>, f64) {
+    /// Sets the FFT hop size in samples, clamped to `MIN_FFT_HOP_SIZE..=MAX_FFT_HOP_SIZE`.
+    pub fn with_hop_size(mut self, hop_size: usize) -> Self {
+        self.spectrogram_analyzer = SpectrogramAnalyzer::with_hop_size(hop_size);
+        self
+    }
+
+    /// Scales `actual` to the overall loudness of `expected` before comparing, so that a pure gain
+    /// change is not a difference. The removed gain is reported as [`AudioDiffStat::gain_db`].
+    pub fn with_ignore_gain(mut self, ignore_gain: bool) -> Self {
+        self.ignore_gain = ignore_gain;
+        self
+    }
+
+    /// Decides equality from the waveform correlation (see [`AudioDiffStat::correlation`]) instead
+    /// of the spectrograms, which are then not compared at all. The loudness tolerance still applies.
+    pub fn with_correlation_threshold(mut self, correlation_threshold: Option<f32>) -> Self {
+        self.correlation_threshold = correlation_threshold;
+        self
+    }
+
+    /// Skips rendering waveforms and spectrograms when `false`, keeping only the stats that decide
+    /// equality.
+    pub fn with_visuals(mut self, render_visuals: bool) -> Self {
+        self.render_visuals = render_visuals;
+        self
+    }
+
+    /// Keeps the decoded samples and spectrograms of both sides, and the aligned samples, in the
+    /// diff for [`AudioDiffReporter::with_data_dump`]. They are held until the diff is reported,
+    /// outside of the memory budget.
+    pub fn with_raw_data(mut self, keep_raw_data: bool) -> Self {
+        self.keep_raw_data = keep_raw_data;
+        self
+    }
+
+    /// Audio whose decoded samples do not fit in `memory_budget` is left to the next differ. The
+    /// memory stays reserved until the diff is dropped, since it keeps what it rendered from them.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Only aligns and compares the samples within `range` of each file; decoding stops once it is
+    /// past the end of the range. Files shorter than the range are compared up to their end.
+    pub fn with_range(mut self, range: Option<AudioRange>) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Counts files whose samples are equal but whose tags differ as modified. Otherwise only the
+    /// samples decide equality, and changed tags are reported alongside.
+    pub fn with_compare_tags(mut self, compare_tags: bool) -> Self {
+        self.compare_tags = compare_tags;
+        self
+    }
+
+    /// Diffs the spectrograms of each channel pair, computing them frame by frame so that only
+    /// the downscaled diff images are kept. `actual` is scaled by `actual_scale` as it is read.
+    fn build_diff_images(&self, expected: &[&[f32]], actual: &[&[f32]], actual_scale: f32) -> (Vec<RgbaImage>, f64) {
         assert_eq!(expected.len(), actual.len());
+        let analyzer = &self.spectrogram_analyzer;
         let mut diff_images = Vec::with_capacity(expected.len());
         let mut diff_rate_sum = 0.0;
-        for (expected_frame, actual_frame) in expected.iter().zip(actual.iter()) {
-            let (diff_image, diff_rate) = self.diff_spectrograms(expected_frame, actual_frame);
-            diff_images.push(diff_image);
+        for (expected_channel, actual_channel) in expected.iter().zip(actual.iter()) {
+            let spectrogram_len = analyzer
+                .frame_count(expected_channel.len())
+                .max(analyzer.frame_count(actual_channel.len()));
+            let (diff_image, diff_rate) = self.diff_spectrograms(
+                spectrogram_len,
+                analyzer.frames(expected_channel),
+                analyzer.scaled_frames(actual_channel, actual_scale),
+            );
+            diff_images.extend(diff_image);
             diff_rate_sum += diff_rate;
         }
         (diff_images, diff_rate_sum / expected.len() as f64)
     }
 
-    fn diff_spectrograms(
+    /// Diffs two spectrograms of `spectrogram_len` frames, consuming one frame of each at a time.
+    fn diff_spectrograms<F: Borrow<[f32; SPECTROGRAM_DATA_HEIGHT]>>(
         &self,
-        expected: &[[f32; SPECTROGRAM_DATA_HEIGHT]],
-        actual: &[[f32; SPECTROGRAM_DATA_HEIGHT]],
-    ) -> (RgbaImage, f64) {
-        let spectrogram_len = expected.len().max(actual.len());
-        let mut diff_image = RgbaImage::from_pixel(SPECTROGRAM_WIDTH, SPECTROGRAM_HEIGHT, Rgba([255, 255, 255, 0]));
+        spectrogram_len: usize,
+        mut expected: impl Iterator<Item = F>,
+        mut actual: impl Iterator<Item = F>,
+    ) -> (Option<RgbaImage>, f64) {
+        let mut diff_image = self
+            .render_visuals
+            .then(|| RgbaImage::from_pixel(SPECTROGRAM_WIDTH, SPECTROGRAM_HEIGHT, Rgba([255, 255, 255, 0])));
         let mut diff_count = 0usize;
         let mut total_count = 0usize;
         assert!(SPECTROGRAM_DATA_HEIGHT >= SPECTROGRAM_HEIGHT as usize);
-        if spectrogram_len >= SPECTROGRAM_WIDTH as usize {
-            for x in 0..SPECTROGRAM_WIDTH {
-                let x_range = x as usize * spectrogram_len / SPECTROGRAM_WIDTH as usize
-                    ..(x + 1) as usize * spectrogram_len / SPECTROGRAM_WIDTH as usize;
-                for y in 0..SPECTROGRAM_HEIGHT {
-                    let y_range = spectrogram_log_bin_range(y);
-
-                    let mut diff_sum = 0usize;
-                    for y in y_range.clone() {
-                        for x in x_range.clone() {
-                            let expected = expected.get(x).map(|x| x[y]);
-                            let actual = actual.get(x).map(|x| x[y]);
-                            let diff = (expected.unwrap_or(f32::INFINITY) - actual.unwrap_or(f32::NEG_INFINITY)).abs();
-                            total_count += 1;
-                            if diff > self.spectral_tolerance {
-                                diff_sum += 1;
-                                diff_count += 1;
-                            }
-                        }
+        // Differing bins per image row, summed over the frames of the current image column.
+        let mut column_diff = [0usize; SPECTROGRAM_HEIGHT as usize];
+        let mut column = 0u32;
+        let mut column_start = 0usize;
+        for x in 0..spectrogram_len {
+            let expected_frame = expected.next();
+            let actual_frame = actual.next();
+            for (y, column_diff) in (0..SPECTROGRAM_HEIGHT).zip(column_diff.iter_mut()) {
+                for y in spectrogram_log_bin_range(y) {
+                    let expected = expected_frame.as_ref().map(|frame| frame.borrow()[y]);
+                    let actual = actual_frame.as_ref().map(|frame| frame.borrow()[y]);
+                    let diff = (expected.unwrap_or(f32::INFINITY) - actual.unwrap_or(f32::NEG_INFINITY)).abs();
+                    total_count += 1;
+                    if diff > self.spectral_tolerance {
+                        *column_diff += 1;
+                        diff_count += 1;
                     }
-                    diff_image.put_pixel(
-                        x,
-                        SPECTROGRAM_HEIGHT - y - 1,
-                        Rgba([
-                            255,
-                            255,
-                            255,
-                            (diff_sum as f64 / (x_range.len() * y_range.len()) as f64 * 255.0) as u8,
-                        ]),
-                    );
                 }
             }
-        } else {
-            for x in 0..spectrogram_len {
+            if spectrogram_len >= SPECTROGRAM_WIDTH as usize {
+                // Several frames share an image column; it is complete after its last frame.
+                let column_end = (column + 1) as usize * spectrogram_len / SPECTROGRAM_WIDTH as usize;
+                if x + 1 < column_end {
+                    continue;
+                }
+                if let Some(diff_image) = &mut diff_image {
+                    for (y, &diff_sum) in (0..SPECTROGRAM_HEIGHT).zip(column_diff.iter()) {
+                        let bin_count = (column_end - column_start) * spectrogram_log_bin_range(y).len();
+                        diff_image.put_pixel(
+                            column,
+                            SPECTROGRAM_HEIGHT - y - 1,
+                            Rgba([255, 255, 255, (diff_sum as f64 / bin_count as f64 * 255.0) as u8]),
+                        );
+                    }
+                }
+                column += 1;
+                column_start = column_end;
+            } else if let Some(diff_image) = &mut diff_image {
+                // Each frame spans one or more image columns.
                 let image_x_range = x as u32 * SPECTROGRAM_WIDTH / spectrogram_len as u32
                     ..(x + 1) as u32 * SPECTROGRAM_WIDTH / spectrogram_len as u32;
-                for y in 0..SPECTROGRAM_HEIGHT {
-                    let y_range = spectrogram_log_bin_range(y);
-                    let mut diff_sum = 0usize;
-                    for y in y_range.clone() {
-                        let expected = expected.get(x).map(|x| x[y]);
-                        let actual = actual.get(x).map(|x| x[y]);
-                        let diff = (expected.unwrap_or(f32::INFINITY) - actual.unwrap_or(f32::NEG_INFINITY)).abs();
-                        total_count += 1;
-                        if diff > self.spectral_tolerance {
-                            diff_sum += 1;
-                            diff_count += 1;
-                        }
-                    }
-                    let color = Rgba([255, 255, 255, (diff_sum as f64 / y_range.len() as f64 * 255.0) as u8]);
+                for (y, &diff_sum) in (0..SPECTROGRAM_HEIGHT).zip(column_diff.iter()) {
+                    let bin_count = spectrogram_log_bin_range(y).len();
+                    let color = Rgba([255, 255, 255, (diff_sum as f64 / bin_count as f64 * 255.0) as u8]);
                     for x in image_x_range.clone() {
                         diff_image.put_pixel(x, SPECTROGRAM_HEIGHT - y - 1, color);
                     }
                 }
             }
+            column_diff.fill(0);
         }
         let diff_rate = if total_count == 0 {
             0.0
@@ -463,44 +948,88 @@ impl AudioDiffCalculator {
     }
 }
 
+/// Extensions passed to symphonia as a format hint, for the kinds whose first extension in
+/// `mime_guess` is not the usual one.
+const AUDIO_EXTENSIONS: [(&str, &str); 19] = [
+    ("audio/mpeg", "mp3"),
+    ("audio/wav", "wav"),
+    ("audio/x-wav", "wav"),
+    ("audio/flac", "flac"),
+    ("audio/x-flac", "flac"),
+    ("audio/aiff", "aiff"),
+    ("audio/x-aiff", "aiff"),
+    ("audio/ogg", "ogg"),
+    ("application/ogg", "ogg"),
+    ("audio/opus", "opus"),
+    ("audio/webm", "webm"),
+    ("video/webm", "webm"),
+    ("video/x-matroska", "mkv"),
+    ("audio/aac", "aac"),
+    ("audio/mp4", "m4a"),
+    ("audio/m4a", "m4a"),
+    ("audio/x-m4a", "m4a"),
+    ("video/mp4", "mp4"),
+    ("video/quicktime", "mov"),
+];
+
 pub fn audio_extension(kind: &Mime) -> Option<&'static str> {
-    match kind.essence_str() {
-        "audio/mpeg" => Some("mp3"),
-        "audio/wav" | "audio/x-wav" => Some("wav"),
-        "audio/flac" | "audio/x-flac" => Some("flac"),
-        "audio/aiff" | "audio/x-aiff" => Some("aiff"),
-        "audio/ogg" | "application/ogg" => Some("ogg"),
-        "audio/opus" => Some("opus"),
-        "audio/webm" | "video/webm" => Some("webm"),
-        "video/x-matroska" => Some("mkv"),
-        "audio/aac" => Some("aac"),
-        "audio/mp4" | "audio/m4a" | "audio/x-m4a" => Some("m4a"),
-        "video/mp4" => Some("mp4"),
-        "video/quicktime" => Some("mov"),
-        _ => mime_guess::get_mime_extensions(kind).and_then(|exts| exts.first().copied()),
-    }
+    AUDIO_EXTENSIONS
+        .iter()
+        .find(|(mime, _)| *mime == kind.essence_str())
+        .map(|(_, extension)| *extension)
+        .or_else(|| mime_guess::get_mime_extensions(kind).and_then(|exts| exts.first().copied()))
 }
 
 fn is_audio_kind(kind: &Mime) -> bool {
     kind.type_() == mime::AUDIO || kind.type_() == mime::VIDEO
 }
 
+/// Kinds with a known container format, followed by the `audio/*` and `video/*` kinds that are
+/// tried as well and compared if symphonia can decode them.
+pub fn supported_kinds() -> Vec<&'static str> {
+    AUDIO_EXTENSIONS
+        .iter()
+        .map(|(mime, _)| *mime)
+        .chain(["audio/*", "video/*"])
+        .collect()
+}
+
 fn build_audio_data_from_decoded(
     mime: Mime,
-    content: Arc<Mmap>,
-    decoded: &AudioDecoded,
+    content: Arc<FileContent>,
+    decoded: AudioDecoded,
     stat: &AudioStat,
+    analyzer: &SpectrogramAnalyzer,
+    render_visuals: bool,
+    keep_raw_data: bool,
 ) -> AudioData {
-    let waveform = render_waveforms(&decoded.samples, stat, decoded.sample_rate);
-    let spectrogram = render_spectrograms(&decoded.spectrograms, stat, decoded.sample_rate);
+    let (waveform, spectrogram) = if render_visuals {
+        (
+            render_waveforms(&decoded.samples, stat, decoded.sample_rate),
+            render_spectrograms(analyzer, &decoded.samples, stat, decoded.sample_rate),
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
     AudioData {
         mime,
         sample_rate: decoded.sample_rate,
         channels: decoded.channels,
         duration_seconds: decoded.duration_seconds,
+        rendered_seconds: stat.duration,
         waveform,
         spectrogram,
+        anomalies: decoded.anomalies,
+        range: decoded.range,
         content,
+        raw: keep_raw_data.then(|| AudioRawData {
+            spectrograms: decoded
+                .samples
+                .iter()
+                .map(|channel| analyzer.compute(channel))
+                .collect(),
+            samples: decoded.samples,
+        }),
     }
 }
 
@@ -509,14 +1038,19 @@ struct AudioDecoded {
     channels: u16,
     duration_seconds: f32,
     samples: Vec<Vec<f32>>,
-    spectrograms: Vec<Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>>,
+    anomalies: DecodeAnomalies,
+    /// Section `samples` were cut to, clamped to the decoded length.
+    range: Option<AudioRange>,
+    tags: AudioTags,
 }
 
-fn align_samples(
-    mut expected: Vec<Vec<f32>>,
-    mut actual: Vec<Vec<f32>>,
+/// The overlapping part of each channel of `expected` and `actual` at the shift of at most
+/// `max_shift_samples` that correlates them best, borrowed from the decoded signals.
+fn align_samples<'a>(
+    expected: &'a [Vec<f32>],
+    actual: &'a [Vec<f32>],
     max_shift_samples: u32,
-) -> (Vec<Vec<f32>>, Vec<Vec<f32>>, i32) {
+) -> (Vec<&'a [f32]>, Vec<&'a [f32]>, i32) {
     assert_eq!(expected.len(), actual.len());
     if max_shift_samples == 0 || expected.is_empty() || expected[0].is_empty() || actual[0].is_empty() {
         let signal_len = expected
@@ -525,11 +1059,8 @@ fn align_samples(
             .map(|signal| signal.len())
             .min()
             .unwrap_or(0);
-        expected
-            .iter_mut()
-            .chain(actual.iter_mut())
-            .for_each(|signal| signal.truncate(signal_len));
-        return (expected, actual, 0);
+        let truncate = |signals: &'a [Vec<f32>]| signals.iter().map(|signal| &signal[..signal_len]).collect();
+        return (truncate(expected), truncate(actual), 0);
     }
     let convolution_len = expected
         .iter()
@@ -642,36 +1173,96 @@ fn align_samples(
         })
         .unwrap();
 
-    for (expected, actual) in expected.iter_mut().zip(actual.iter_mut()) {
-        let (expected_range, actual_range) = overlap_range(expected.len(), actual.len(), best_shift);
-        expected.drain(..expected_range.start.min(expected.len()));
-        actual.drain(..actual_range.start.min(actual.len()));
-        expected.truncate(expected_range.len());
-        actual.truncate(actual_range.len());
-    }
+    let overlap = |signal: &'a [f32], range: Range<usize>| {
+        let start = range.start.min(signal.len());
+        &signal[start..range.end.clamp(start, signal.len())]
+    };
+    let (expected, actual) = expected
+        .iter()
+        .zip(actual.iter())
+        .map(|(expected, actual)| {
+            let (expected_range, actual_range) = overlap_range(expected.len(), actual.len(), best_shift);
+            (overlap(expected, expected_range), overlap(actual, actual_range))
+        })
+        .unzip();
 
     (expected, actual, best_shift)
 }
 
-fn summarize_channel_metrics(expected: &[Vec<f32>], actual: &[Vec<f32>]) -> f32 {
+/// Largest loudness difference between channel pairs, with `actual` scaled by `actual_scale`.
+fn summarize_channel_metrics(expected: &[&[f32]], actual: &[&[f32]], actual_scale: f32) -> f32 {
     let channel_count = expected.len().min(actual.len());
     if channel_count == 0 {
         return f32::INFINITY;
     }
     let mut max_lufs_diff = 0.0f32;
     for channel_index in 0..channel_count {
-        let expected_channel = &expected[channel_index];
-        let actual_channel = &actual[channel_index];
+        let expected_channel = expected[channel_index];
+        let actual_channel = actual[channel_index];
         if expected_channel.is_empty() || actual_channel.is_empty() {
             continue;
         }
-        let expected_lufs = loudness_db(expected_channel);
-        let actual_lufs = loudness_db(actual_channel);
+        let expected_lufs = loudness_db(expected_channel, 1.0);
+        let actual_lufs = loudness_db(actual_channel, actual_scale);
         max_lufs_diff = max_lufs_diff.max((expected_lufs - actual_lufs).abs());
     }
     max_lufs_diff
 }
 
+/// Normalized cross-correlation at zero lag of each channel pair, averaged over channels; this is
+/// the score `align_samples` maximizes, evaluated on already aligned signals with `actual` scaled
+/// by `actual_scale`.
+fn normalized_correlation(expected: &[&[f32]], actual: &[&[f32]], actual_scale: f32) -> f32 {
+    let channel_count = expected.len().min(actual.len());
+    if channel_count == 0 {
+        return 0.0;
+    }
+    let sum = expected
+        .iter()
+        .zip(actual.iter())
+        .map(|(expected, actual)| {
+            let actual = actual.iter().map(|sample| sample * actual_scale);
+            let (dot, expected_power, actual_power) = expected.iter().zip(actual).fold(
+                (0f32, 0f32, 0f32),
+                |(dot, expected_power, actual_power), (expected, actual)| {
+                    (
+                        dot + expected * actual,
+                        expected_power + expected * expected,
+                        actual_power + actual * actual,
+                    )
+                },
+            );
+            match (expected_power < LOG_EPSILON, actual_power < LOG_EPSILON) {
+                (true, true) => 1.0,
+                (true, false) | (false, true) => 0.0,
+                (false, false) => dot / (expected_power * actual_power).sqrt(),
+            }
+        })
+        .sum::<f32>();
+    sum / channel_count as f32
+}
+
+/// The removed gain in dB and the scale that makes the RMS of `actual` over all channels match
+/// `expected`, or `None` when either side is silent.
+fn gain_scale(expected: &[&[f32]], actual: &[&[f32]]) -> Option<(f32, f32)> {
+    let rms = |channels: &[&[f32]]| {
+        let len = channels.iter().map(|channel| channel.len()).sum::<usize>();
+        let power = channels
+            .iter()
+            .copied()
+            .flatten()
+            .map(|sample| sample * sample)
+            .sum::<f32>();
+        (power / len.max(1) as f32).sqrt()
+    };
+    let expected_rms = rms(expected);
+    let actual_rms = rms(actual);
+    if expected_rms < LOG_EPSILON || actual_rms < LOG_EPSILON {
+        return None;
+    }
+    Some((20.0 * (actual_rms / expected_rms).log10(), expected_rms / actual_rms))
+}
+
 fn render_waveforms(samples: &[Vec<f32>], stat: &AudioStat, sample_rate: u32) -> Vec<RgbaImage> {
     samples
         .iter()
@@ -691,11 +1282,16 @@ fn overlap_range(expected_len: usize, actual_len: usize, shift: i32) -> (Range<u
     }
 }
 
-fn loudness_db(samples: &[f32]) -> f32 {
+fn loudness_db(samples: &[f32], scale: f32) -> f32 {
     if samples.is_empty() {
         return -100.0;
     }
-    let power = samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32;
+    let power = samples
+        .iter()
+        .map(|sample| sample * scale)
+        .map(|sample| sample * sample)
+        .sum::<f32>()
+        / samples.len() as f32;
     let rms = power.sqrt();
     20.0 * rms.max(LOG_EPSILON).log10()
 }
@@ -735,19 +1331,23 @@ fn render_waveform(samples: &[f32], stat: &AudioStat, sample_rate: u32) -> RgbaI
 }
 
 fn render_spectrograms(
-    spectrograms: &[Vec<[f32; SPECTROGRAM_DATA_HEIGHT]>],
+    analyzer: &SpectrogramAnalyzer,
+    samples: &[Vec<f32>],
     stat: &AudioStat,
     sample_rate: u32,
 ) -> Vec<RgbaImage> {
-    spectrograms
+    samples
         .iter()
-        .map(|channel| render_spectrogram(channel, stat, sample_rate))
+        .map(|channel| render_spectrogram(analyzer, channel, stat, sample_rate))
         .collect()
 }
 
+/// Base of the logarithmic frequency scale of spectrogram images.
+const SPECTROGRAM_LOG_BASE: f64 = 20.0;
+
 fn spectrogram_log_bin_range(y: u32) -> Range<usize> {
     static RANGES: LazyLock<[Range<usize>; SPECTROGRAM_HEIGHT as usize]> = LazyLock::new(|| {
-        const B: f64 = 20.0;
+        const B: f64 = SPECTROGRAM_LOG_BASE;
         const A: f64 = SPECTROGRAM_DATA_HEIGHT as f64 / (B - 1.0);
         let mut ranges = [const { 0usize..0 }; SPECTROGRAM_HEIGHT as usize];
         let mut wrote = 0;
@@ -776,9 +1376,18 @@ fn spectrogram_log_bin_range(y: u32) -> Range<usize> {
     RANGES[y as usize].clone()
 }
 
+/// Height of `frequency` in a spectrogram image, as a fraction from the bottom, following the
+/// same logarithmic scale as [`spectrogram_log_bin_range`].
+fn spectrogram_frequency_position(frequency: f64, sample_rate: u32) -> f64 {
+    const A: f64 = SPECTROGRAM_DATA_HEIGHT as f64 / (SPECTROGRAM_LOG_BASE - 1.0);
+    let bin = frequency * FFT_WINDOW_SIZE as f64 / sample_rate as f64;
+    f64::log(bin / A + 1.0, SPECTROGRAM_LOG_BASE)
+}
+
 struct SpectrogramAnalyzer {
     fft: Arc<dyn Fft<f32>>,
     window: Box<[f32]>,
+    hop_size: usize,
 }
 
 impl Default for SpectrogramAnalyzer {
@@ -789,14 +1398,39 @@ impl Default for SpectrogramAnalyzer {
 
 impl SpectrogramAnalyzer {
     fn new() -> SpectrogramAnalyzer {
+        SpectrogramAnalyzer::with_hop_size(DEFAULT_FFT_HOP_SIZE)
+    }
+
+    fn with_hop_size(hop_size: usize) -> SpectrogramAnalyzer {
         let fft = FftPlanner::<f32>::new().plan_fft_forward(FFT_WINDOW_SIZE);
         let window = (0..FFT_WINDOW_SIZE)
             .map(|i| (PI * i as f32 / (FFT_WINDOW_SIZE - 1) as f32).sin())
             .collect();
-        SpectrogramAnalyzer { fft, window }
+        SpectrogramAnalyzer {
+            fft,
+            window,
+            hop_size: hop_size.clamp(MIN_FFT_HOP_SIZE, MAX_FFT_HOP_SIZE),
+        }
     }
 
     fn decode_audio(&self, mime: &Mime, content: &[u8]) -> Result<AudioDecoded, AudioDecodeError> {
+        let (decoded, _memory) = self.decode_audio_within(mime, content, &MemoryBudget::unlimited(), None)?;
+        Ok(decoded)
+    }
+
+    /// Decodes audio while accounting the decoded samples against `budget`, keeping only the
+    /// samples within `range` if given. Spectrograms are computed from the samples frame by frame
+    /// where they are needed, so they are not held here.
+    fn decode_audio_within(
+        &self,
+        mime: &Mime,
+        content: &[u8],
+        budget: &MemoryBudget,
+        range: Option<AudioRange>,
+    ) -> Result<(AudioDecoded, MemoryReservation), AudioDecodeError> {
+        let mut memory = budget
+            .try_reserve(content.len() as u64)
+            .ok_or(AudioDecodeError::MemoryBudgetExceeded)?;
         let mut hint = Hint::new();
         if let Some(extension) = audio_extension(mime) {
             hint.with_extension(extension);
@@ -821,6 +1455,7 @@ impl SpectrogramAnalyzer {
             symphonia::default::get_codecs().make_audio_decoder(audio_codec_params, &AudioDecoderOptions::default())?;
 
         let mut samples = Vec::<Vec<f32>>::new();
+        let mut anomalies = DecodeAnomalies::default();
         let mut signal_spec = match (audio_codec_params.sample_rate, audio_codec_params.channels.clone()) {
             (Some(rate), Some(channels)) => Some(AudioSpec::new(rate, channels)),
             _ => None,
@@ -833,80 +1468,158 @@ impl SpectrogramAnalyzer {
                     decoder.reset();
                     continue;
                 }
+                // Keep what was decoded before the cut.
+                Err(SymphoniaError::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    anomalies.truncated = true;
+                    break;
+                }
                 Err(err) => return Err(err.into()),
             };
             if packet.track_id != track_id {
                 continue;
             }
-            let decoded = decoder.decode(&packet)?;
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(_)) => {
+                    anomalies.skipped_packets += 1;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
             if signal_spec.is_none() {
                 signal_spec = Some(decoded.spec().clone());
             }
             let mut packet_samples = Vec::<Vec<f32>>::new();
             decoded.copy_to_vecs_planar(&mut packet_samples);
             samples.resize_with(packet_samples.len(), Vec::new);
+            let packet_bytes = packet_samples
+                .iter()
+                .map(|plane| mem::size_of_val(&plane[..]))
+                .sum::<usize>();
+            if !memory.try_grow(packet_bytes as u64) {
+                return Err(AudioDecodeError::MemoryBudgetExceeded);
+            }
             for (plane, samples) in packet_samples.into_iter().zip(samples.iter_mut()) {
                 samples.extend(plane);
             }
+            if let (Some(range), Some(signal_spec), Some(decoded)) = (range, &signal_spec, samples.first())
+                && range.samples(signal_spec.rate(), usize::MAX).end <= decoded.len()
+            {
+                break;
+            }
         }
 
         let Some(signal_spec) = signal_spec else {
             return Err(AudioDecodeError::MissingSampleRate);
         };
+        let tags = tags::read_tags(format.metadata(), u64::from(track_id));
 
-        let max_len = samples.iter().map(|channel| channel.len()).max().unwrap_or(0);
         let sample_rate = signal_spec.rate();
         if sample_rate == 0 {
             return Err(AudioDecodeError::MissingSampleRate);
         }
+        let mut max_len = samples.iter().map(|channel| channel.len()).max().unwrap_or(0);
+        let range = range.map(|range| {
+            let cut = range.samples(sample_rate, max_len);
+            for channel in &mut samples {
+                channel.truncate(cut.end);
+                channel.drain(..cut.start.min(channel.len()));
+            }
+            max_len = cut.len();
+            AudioRange {
+                start_seconds: cut.start as f32 / sample_rate as f32,
+                end_seconds: cut.end as f32 / sample_rate as f32,
+            }
+        });
         let duration_seconds = max_len as f32 / sample_rate as f32;
 
-        let spectrograms = samples.iter().map(|sample| self.compute(sample)).collect::<Vec<_>>();
-
-        Ok(AudioDecoded {
+        let decoded = AudioDecoded {
             sample_rate,
             channels: signal_spec.channels().count() as u16,
             duration_seconds,
             samples,
-            spectrograms,
-        })
+            anomalies,
+            range,
+            tags,
+        };
+        Ok((decoded, memory))
     }
 
-    fn compute(&self, samples: &[f32]) -> Vec<[f32; SPECTROGRAM_DATA_HEIGHT]> {
-        let mut buffer =
-            Box::<[Complex<f32>; FFT_WINDOW_SIZE]>::try_from(vec![Complex::zero(); FFT_WINDOW_SIZE]).unwrap();
-        let mut scratch = vec![Complex::zero(); self.fft.get_inplace_scratch_len()];
-        let mut result = Vec::with_capacity(samples.len() / (FFT_WINDOW_SIZE / 2));
-        for i in 0.. {
-            let Some(samples) = samples.get(i * (FFT_WINDOW_SIZE / 2)..) else {
-                break;
-            };
-            buffer
-                .iter_mut()
-                .zip(
-                    samples
-                        .iter()
-                        .copied()
-                        .chain(iter::repeat(0.0))
-                        .zip(self.window.iter().copied()),
-                )
-                .for_each(|(slot, (s, w))| *slot = Complex::from(s * w));
-            self.fft.process_with_scratch(&mut *buffer, &mut scratch);
-            result.push([0.0; SPECTROGRAM_DATA_HEIGHT]);
-            result
-                .last_mut()
-                .unwrap()
-                .iter_mut()
-                .zip(buffer.iter().copied())
-                .for_each(|(slot, b)| *slot = b.norm_sqr().max(1e-32).log10());
+    /// Number of frames [`SpectrogramAnalyzer::frames`] yields for `sample_len` samples.
+    fn frame_count(&self, sample_len: usize) -> usize {
+        sample_len / self.hop_size + 1
+    }
+
+    fn frames<'a>(&'a self, samples: &'a [f32]) -> SpectrogramFrames<'a> {
+        self.scaled_frames(samples, 1.0)
+    }
+
+    /// [`SpectrogramAnalyzer::frames`] of `samples` multiplied by `scale`.
+    fn scaled_frames<'a>(&'a self, samples: &'a [f32], scale: f32) -> SpectrogramFrames<'a> {
+        SpectrogramFrames {
+            analyzer: self,
+            samples,
+            scale,
+            buffer: Box::<[Complex<f32>; FFT_WINDOW_SIZE]>::try_from(vec![Complex::zero(); FFT_WINDOW_SIZE]).unwrap(),
+            scratch: vec![Complex::zero(); self.fft.get_inplace_scratch_len()],
+            index: 0,
         }
+    }
+
+    fn compute(&self, samples: &[f32]) -> Vec<[f32; SPECTROGRAM_DATA_HEIGHT]> {
+        let mut result = Vec::with_capacity(self.frame_count(samples.len()));
+        result.extend(self.frames(samples));
         result
     }
 }
 
-fn render_spectrogram(spectrogram: &[[f32; SPECTROGRAM_DATA_HEIGHT]], stat: &AudioStat, sample_rate: u32) -> RgbaImage {
+/// Spectrogram of a signal, computed one FFT window per [`Iterator::next`] call.
+struct SpectrogramFrames<'a> {
+    analyzer: &'a SpectrogramAnalyzer,
+    samples: &'a [f32],
+    scale: f32,
+    buffer: Box<[Complex<f32>; FFT_WINDOW_SIZE]>,
+    scratch: Vec<Complex<f32>>,
+    index: usize,
+}
+
+impl Iterator for SpectrogramFrames<'_> {
+    type Item = [f32; SPECTROGRAM_DATA_HEIGHT];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let analyzer = self.analyzer;
+        let samples = self.samples.get(self.index * analyzer.hop_size..)?;
+        self.index += 1;
+        self.buffer
+            .iter_mut()
+            .zip(
+                samples
+                    .iter()
+                    .copied()
+                    .chain(iter::repeat(0.0))
+                    .zip(analyzer.window.iter().copied()),
+            )
+            .for_each(|(slot, (s, w))| *slot = Complex::from(s * self.scale * w));
+        analyzer.fft.process_with_scratch(&mut *self.buffer, &mut self.scratch);
+        let mut frame = [0.0; SPECTROGRAM_DATA_HEIGHT];
+        frame
+            .iter_mut()
+            .zip(self.buffer.iter().copied())
+            .for_each(|(slot, b)| *slot = b.norm_sqr().max(1e-32).log10());
+        Some(frame)
+    }
+}
+
+/// Renders the spectrogram of `samples`, averaging the frames each image column covers as they
+/// are computed, so that only the column sums are held rather than every frame.
+fn render_spectrogram(
+    analyzer: &SpectrogramAnalyzer,
+    samples: &[f32],
+    stat: &AudioStat,
+    sample_rate: u32,
+) -> RgbaImage {
     let mut image = RgbaImage::from_pixel(SPECTROGRAM_WIDTH, SPECTROGRAM_HEIGHT, Rgba([255, 255, 255, 0]));
-    if spectrogram.is_empty() || stat.duration <= 0.0 || sample_rate == 0 {
+    if stat.duration <= 0.0 || sample_rate == 0 {
         return image;
     }
 
@@ -914,30 +1627,51 @@ fn render_spectrogram(spectrogram: &[[f32; SPECTROGRAM_DATA_HEIGHT]], stat: &Aud
     let map_value = |v: f32| (v - stat.spectrogram_min) / value_range;
     assert!(SPECTROGRAM_HEIGHT <= SPECTROGRAM_DATA_HEIGHT as u32);
     let duration = stat.duration;
-    let hop_samples = (FFT_WINDOW_SIZE / 2) as f32;
+    let hop_samples = analyzer.hop_size as f32;
     let frame_duration = hop_samples / sample_rate as f32;
     if frame_duration <= 0.0 {
         return image;
     }
 
-    for x in 0..SPECTROGRAM_WIDTH {
-        let start_time = x as f32 * duration / SPECTROGRAM_WIDTH as f32;
-        let end_time = (x + 1) as f32 * duration / SPECTROGRAM_WIDTH as f32;
-        let start = (start_time / frame_duration).floor() as usize;
-        let end = (end_time / frame_duration).ceil() as usize;
-        let start = start.min(spectrogram.len());
-        let end = end.min(spectrogram.len());
-        if end <= start {
-            continue;
+    // Frames averaged into each image column; neighboring columns may share a frame.
+    let frame_count = analyzer.frame_count(samples.len());
+    let columns = (0..SPECTROGRAM_WIDTH)
+        .map(|x| {
+            let start_time = x as f32 * duration / SPECTROGRAM_WIDTH as f32;
+            let end_time = (x + 1) as f32 * duration / SPECTROGRAM_WIDTH as f32;
+            let start = ((start_time / frame_duration).floor() as usize).min(frame_count);
+            let end = ((end_time / frame_duration).ceil() as usize).min(frame_count);
+            start..end
+        })
+        .collect::<Vec<_>>();
+    let mut sums = vec![[0f32; SPECTROGRAM_HEIGHT as usize]; SPECTROGRAM_WIDTH as usize];
+    let mut first_column = 0;
+    for (index, frame) in analyzer.frames(samples).enumerate() {
+        while columns.get(first_column).is_some_and(|column| column.end <= index) {
+            first_column += 1;
         }
+        for (column, sums) in columns[first_column..]
+            .iter()
+            .zip(&mut sums[first_column..])
+            .take_while(|(column, _)| column.start <= index)
+        {
+            if !column.contains(&index) {
+                continue;
+            }
+            for (y, sum) in (0..SPECTROGRAM_HEIGHT).zip(sums.iter_mut()) {
+                for bin in spectrogram_log_bin_range(y) {
+                    *sum += frame[bin];
+                }
+            }
+        }
+    }
 
-        for y in 0..SPECTROGRAM_HEIGHT {
-            let y_range = spectrogram_log_bin_range(y);
-            let sum = spectrogram[start..end]
-                .iter()
-                .flat_map(|spec| spec[y_range.clone()].iter().copied())
-                .sum::<f32>();
-            let value = sum / ((end - start) * y_range.len()) as f32;
+    for ((x, column), sums) in (0..SPECTROGRAM_WIDTH).zip(&columns).zip(&sums) {
+        if column.is_empty() {
+            continue;
+        }
+        for (y, &sum) in (0..SPECTROGRAM_HEIGHT).zip(sums.iter()) {
+            let value = sum / (column.len() * spectrogram_log_bin_range(y).len()) as f32;
             let intensity = map_value(value);
             image.put_pixel(
                 x,
@@ -0,0 +1,67 @@
+use crate::dump::AudioDataDumpError;
+use crate::{AudioDiff, AudioDiffReporter, audio_extension};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+
+const COMPARES_NAME: &str = "audio";
+
+impl DetailReporter<AudioDiff, FileLeaf, CollectReport<'_>> for AudioDiffReporter {
+    type Error = AudioDataDumpError;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &AudioDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.dump_diff(name, diff)?;
+        reporter.record_unchanged(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &AudioDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        self.dump_diff(name, diff)?;
+        reporter.record_modified(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if audio_extension(&data.kind).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        let Ok(decoded) = self.spectrogram_analyzer.decode_audio(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        self.dump_decoded(name, "added", &decoded)?;
+        reporter.record_added(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if audio_extension(&data.kind).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        let Ok(decoded) = self.spectrogram_analyzer.decode_audio(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        self.dump_decoded(name, "deleted", &decoded)?;
+        reporter.record_deleted(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
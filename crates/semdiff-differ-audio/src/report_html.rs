@@ -1,4 +1,6 @@
-use crate::{AudioData, AudioDiff, AudioDiffReporter, audio_extension};
+use crate::{
+    AudioColors, AudioData, AudioDiff, AudioDiffReporter, TagChange, audio_extension, spectrogram_frequency_position,
+};
 use askama::Template;
 use image::{ImageError, ImageFormat, Rgba, RgbaImage};
 use semdiff_core::fs::FileLeaf;
@@ -8,6 +10,14 @@ use std::io::Write;
 use thiserror::Error;
 
 const COMPARES_NAME: &str = "audio";
+/// Frequencies labeled on spectrogram axes, as far as they are below the Nyquist frequency.
+const FREQUENCY_TICKS: [f64; 13] = [
+    50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0, 50000.0, 100000.0, 200000.0, 500000.0,
+];
+/// Closest two axis labels may be, as a fraction of the axis length.
+const MIN_TICK_GAP: f64 = 0.08;
+/// Most labels on a time axis.
+const MAX_TIME_TICKS: f64 = 8.0;
 
 #[derive(Debug, Error)]
 pub enum AudioDiffReportError {
@@ -15,12 +25,19 @@ pub enum AudioDiffReportError {
     HtmlReport(#[from] HtmlReportError),
     #[error("image encode error: {0}")]
     ImageEncode(#[from] ImageError),
+    #[error("{0}")]
+    DataDump(#[from] crate::dump::AudioDataDumpError),
 }
 
 #[derive(Template)]
 #[template(path = "audio_preview.html")]
 struct AudioPreviewTemplate {
     body: AudioPreviewBody,
+    /// Either file did not decode cleanly.
+    possibly_corrupt: bool,
+    /// The tags of the files differ.
+    metadata_changed: bool,
+    colors: AudioColors,
 }
 
 #[derive(Clone)]
@@ -47,12 +64,19 @@ enum AudioPreviewBody {
         images: Vec<AudioPreviewImage>,
         audio_src: String,
     },
+    /// An unchanged file listed without its audio or a detail page.
+    Thumbnail { images: Vec<AudioPreviewImage> },
 }
 
 #[derive(Template)]
 #[template(path = "audio_detail.html")]
 struct AudioDetailTemplate {
     detail: AudioDetailBody,
+    colors: AudioColors,
+    /// Tags that differ between the files.
+    tag_changes: Vec<TagChange>,
+    /// Files written by the audio data dump, if enabled.
+    data_files: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -62,22 +86,42 @@ struct AudioDetailImage {
     height: u32,
 }
 
+#[derive(Clone)]
+struct AxisTick {
+    /// Percent of the axis length, from the bottom for frequencies and from the left for times.
+    position: f64,
+    label: String,
+}
+
+#[derive(Clone)]
+struct SpectrogramAxes {
+    frequencies: Vec<AxisTick>,
+    times: Vec<AxisTick>,
+}
+
 #[derive(Clone)]
 struct AudioDetailData {
     label: String,
     audio_src: String,
     waveforms: Vec<AudioDetailImage>,
     spectrograms: Vec<AudioDetailImage>,
+    /// Labels drawn around the spectrograms, if enabled.
+    axes: Option<SpectrogramAxes>,
     sample_rate: u32,
     channels: u16,
     duration_seconds: f32,
+    /// Section of the file that was compared, if the comparison was limited to one.
+    range: Option<String>,
+    /// Why the file looks corrupt, if it did not decode cleanly.
+    corruption: Option<String>,
 }
 
 enum AudioDetailBody {
     Diff {
-        expected: AudioDetailData,
-        actual: AudioDetailData,
+        expected: Box<AudioDetailData>,
+        actual: Box<AudioDetailData>,
         spectrogram_diff: Vec<AudioDetailImage>,
+        diff_axes: Option<SpectrogramAxes>,
     },
     Single {
         data: AudioDetailData,
@@ -97,10 +141,38 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
         let Some(extension) = audio_extension(expected.mime()) else {
             return Ok(MayUnsupported::Unsupported);
         };
+        let data_files = self.dump_diff(name, diff)?;
+        if !reporter.lists_unchanged() {
+            reporter.count_unchanged(name);
+            return Ok(MayUnsupported::Ok(()));
+        }
+        if self.minimal_unchanged {
+            let preview_image = write_preview_image(reporter, name, "preview_waveform", expected.waveform())?;
+            let preview_html = AudioPreviewTemplate {
+                body: AudioPreviewBody::Thumbnail {
+                    images: preview_image
+                        .as_ref()
+                        .map(|file| build_preview_images(reporter, std::slice::from_ref(file), "waveform"))
+                        .unwrap_or_default(),
+                },
+                possibly_corrupt: !expected.anomalies().is_clean(),
+                metadata_changed: !diff.tag_changes().is_empty(),
+                colors: self.colors,
+            };
+            reporter.record_unchanged_without_detail(name, COMPARES_NAME, preview_html)?;
+            return Ok(MayUnsupported::Ok(()));
+        }
         let audio_file = write_audio(reporter, name, "same", extension, expected.content())?;
         let waveform_files = write_channel_images(reporter, name, "same_waveform", expected.waveform())?;
         let spectrogram_files = write_channel_images(reporter, name, "same_spectrogram", expected.spectrogram())?;
-        let detail_data = build_detail_data("same", expected, &audio_file, &waveform_files, &spectrogram_files);
+        let detail_data = build_detail_data(
+            "same",
+            expected,
+            &audio_file,
+            &waveform_files,
+            &spectrogram_files,
+            self.axes,
+        );
         let preview_image = write_preview_image(reporter, name, "preview_waveform", expected.waveform())?;
         let preview_images = preview_image
             .as_ref()
@@ -111,9 +183,15 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
                 images: preview_images,
                 audio_src: reporter.detail_asset_path(&audio_file),
             },
+            possibly_corrupt: !expected.anomalies().is_clean(),
+            metadata_changed: !diff.tag_changes().is_empty(),
+            colors: self.colors,
         };
         let detail_html = AudioDetailTemplate {
             detail: AudioDetailBody::Single { data: detail_data },
+            colors: self.colors,
+            tag_changes: diff.tag_changes().to_vec(),
+            data_files,
         };
         reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -149,7 +227,9 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
             Vec::new()
         };
 
-        let (preview_image, preview_label) = if let Some(detail) = diff.diff_detail() {
+        let (preview_image, preview_label) = if let Some(detail) = diff.diff_detail()
+            && !detail.spectrogram_diff().is_empty()
+        {
             (
                 write_preview_image(reporter, name, "preview_spectrogram_diff", detail.spectrogram_diff())?,
                 "spectrogram diff",
@@ -169,19 +249,34 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
                 images: preview_images,
                 audio_src: reporter.detail_asset_path(&actual_audio),
             },
+            possibly_corrupt: !expected.anomalies().is_clean() || !actual.anomalies().is_clean(),
+            metadata_changed: !diff.tag_changes().is_empty(),
+            colors: self.colors,
         };
         let detail_html = AudioDetailTemplate {
             detail: AudioDetailBody::Diff {
-                expected: build_detail_data(
+                expected: Box::new(build_detail_data(
                     "expected",
                     expected,
                     &expected_audio,
                     &expected_waveforms,
                     &expected_spectrograms,
-                ),
-                actual: build_detail_data("actual", actual, &actual_audio, &actual_waveforms, &actual_spectrograms),
+                    self.axes,
+                )),
+                actual: Box::new(build_detail_data(
+                    "actual",
+                    actual,
+                    &actual_audio,
+                    &actual_waveforms,
+                    &actual_spectrograms,
+                    self.axes,
+                )),
                 spectrogram_diff: spectrogram_diff_detail,
+                diff_axes: self.axes.then(|| build_spectrogram_axes(expected)),
             },
+            colors: self.colors,
+            tag_changes: diff.tag_changes().to_vec(),
+            data_files: self.dump_diff(name, diff)?,
         };
         reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -212,11 +307,24 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
                 images: preview_images,
                 audio_src: reporter.detail_asset_path(&audio_file),
             },
+            possibly_corrupt: !audio_data.anomalies().is_clean(),
+            metadata_changed: false,
+            colors: self.colors,
         };
         let detail_html = AudioDetailTemplate {
             detail: AudioDetailBody::Single {
-                data: build_detail_data("added", &audio_data, &audio_file, &waveform_files, &spectrogram_files),
+                data: build_detail_data(
+                    "added",
+                    &audio_data,
+                    &audio_file,
+                    &waveform_files,
+                    &spectrogram_files,
+                    self.axes,
+                ),
             },
+            colors: self.colors,
+            tag_changes: Vec::new(),
+            data_files: self.dump_data(name, "added", &audio_data)?,
         };
         reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -247,11 +355,24 @@ impl DetailReporter<AudioDiff, FileLeaf, HtmlReport> for AudioDiffReporter {
                 images: preview_images,
                 audio_src: reporter.detail_asset_path(&audio_file),
             },
+            possibly_corrupt: !audio_data.anomalies().is_clean(),
+            metadata_changed: false,
+            colors: self.colors,
         };
         let detail_html = AudioDetailTemplate {
             detail: AudioDetailBody::Single {
-                data: build_detail_data("deleted", &audio_data, &audio_file, &waveform_files, &spectrogram_files),
+                data: build_detail_data(
+                    "deleted",
+                    &audio_data,
+                    &audio_file,
+                    &waveform_files,
+                    &spectrogram_files,
+                    self.axes,
+                ),
             },
+            colors: self.colors,
+            tag_changes: Vec::new(),
+            data_files: self.dump_data(name, "deleted", &audio_data)?,
         };
         reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -264,18 +385,82 @@ fn build_detail_data(
     audio_uri: &str,
     waveform_uris: &[String],
     spectrogram_uris: &[String],
+    axes: bool,
 ) -> AudioDetailData {
     AudioDetailData {
         label: label.to_string(),
         audio_src: audio_uri.to_string(),
         waveforms: build_detail_images(waveform_uris, data.waveform()),
         spectrograms: build_detail_images(spectrogram_uris, data.spectrogram()),
+        axes: axes.then(|| build_spectrogram_axes(data)),
         sample_rate: data.sample_rate(),
         channels: data.channels(),
         duration_seconds: data.duration_seconds(),
+        range: data.range().map(|range| range.to_string()),
+        corruption: (!data.anomalies().is_clean()).then(|| data.anomalies().to_string()),
     }
 }
 
+/// Labels the frequency axis with [`FREQUENCY_TICKS`] and the time axis with round times, counted
+/// from the start of the file even when only a range was compared.
+fn build_spectrogram_axes(data: &AudioData) -> SpectrogramAxes {
+    let sample_rate = data.sample_rate();
+    let mut frequencies = vec![AxisTick {
+        position: 0.0,
+        label: "0".to_owned(),
+    }];
+    for frequency in FREQUENCY_TICKS {
+        let position = spectrogram_frequency_position(frequency, sample_rate);
+        if position > 1.0 - MIN_TICK_GAP / 2.0 {
+            break;
+        }
+        if frequencies
+            .last()
+            .is_some_and(|last| percent(position) - last.position < percent(MIN_TICK_GAP))
+        {
+            continue;
+        }
+        let label = if frequency >= 1000.0 {
+            format!("{}k", frequency / 1000.0)
+        } else {
+            frequency.to_string()
+        };
+        frequencies.push(AxisTick {
+            position: percent(position),
+            label,
+        });
+    }
+
+    let span = f64::from(data.rendered_seconds());
+    let start = data.range().map_or(0.0, |range| f64::from(range.start_seconds()));
+    let mut times = Vec::new();
+    if span > 0.0 {
+        let magnitude = 10f64.powf((span / MAX_TIME_TICKS).log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|&step| span / step <= MAX_TIME_TICKS)
+            .unwrap_or(10.0 * magnitude);
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        let mut tick = (start / step).ceil() as u64;
+        // The tolerance keeps a tick at the very end that rounding would push past it.
+        while tick as f64 * step <= start + span * (1.0 + 1e-9) {
+            let time = tick as f64 * step;
+            times.push(AxisTick {
+                position: percent(((time - start) / span).min(1.0)),
+                label: format!("{time:.decimals$}"),
+            });
+            tick += 1;
+        }
+    }
+    SpectrogramAxes { frequencies, times }
+}
+
+/// Converts a fraction to a percentage rounded to hundredths, short enough for inline styles.
+fn percent(fraction: f64) -> f64 {
+    (fraction * 10000.0).round() / 100.0
+}
+
 fn build_preview_images(
     reporter: &HtmlReport,
     image_files: &[PreviewImageFile],
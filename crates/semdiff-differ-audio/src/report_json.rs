@@ -1,8 +1,9 @@
-use crate::{AudioDiff, AudioDiffReporter, audio_extension};
+use crate::{AudioDiff, AudioDiffReporter, AudioRange, DecodeAnomalies, TagChange, audio_extension};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::json::JsonReport;
 use serde::Serialize;
+use std::io::Write;
 use thiserror::Error;
 
 const COMPARES_NAME: &str = "audio";
@@ -11,18 +12,29 @@ const COMPARES_NAME: &str = "audio";
 pub enum AudioJsonReportError {
     #[error("audio decode error: {0}")]
     AudioDecode(#[from] crate::AudioDecodeError),
+    #[error("{0}")]
+    DataDump(#[from] crate::dump::AudioDataDumpError),
 }
 
-impl<W> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter {
+impl<W: Write> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter {
     type Error = AudioJsonReportError;
 
     fn report_unchanged(
         &self,
         name: &str,
-        _diff: &AudioDiff,
+        diff: &AudioDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.record_unchanged(name, COMPARES_NAME, ());
+        reporter.record_unchanged(
+            name,
+            COMPARES_NAME,
+            UnchangedReport {
+                decode_anomalies: non_clean(diff.expected().anomalies()),
+                range: diff.expected().range(),
+                metadata_changes: diff.tag_changes().to_vec(),
+                data_files: self.dump_diff(name, diff)?,
+            },
+        );
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -32,16 +44,19 @@ impl<W> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter
         diff: &AudioDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        let (spectrogram_diff_rate, shift_samples, lufs_diff_db) = if let Some(detail) = diff.diff_detail() {
-            let stat = detail.stat();
-            (
-                Some(stat.spectrogram_diff_rate),
-                Some(stat.shift_samples),
-                Some(stat.lufs_diff_db),
-            )
-        } else {
-            (None, None, None)
-        };
+        let (spectrogram_diff_rate, correlation, shift_samples, lufs_diff_db, gain_db) =
+            if let Some(detail) = diff.diff_detail() {
+                let stat = detail.stat();
+                (
+                    stat.spectrogram_diff_rate,
+                    Some(stat.correlation),
+                    Some(stat.shift_samples),
+                    Some(stat.lufs_diff_db),
+                    stat.gain_db,
+                )
+            } else {
+                (None, None, None, None, None)
+            };
         let report = ModifiedReport {
             status: diff.status().as_str().to_string(),
             expected_sample_rate: diff.expected().sample_rate(),
@@ -50,9 +65,17 @@ impl<W> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter
             actual_sample_rate: diff.actual().sample_rate(),
             actual_channels: diff.actual().channels(),
             actual_duration_seconds: diff.actual().duration_seconds(),
+            expected_decode_anomalies: non_clean(diff.expected().anomalies()),
+            actual_decode_anomalies: non_clean(diff.actual().anomalies()),
+            expected_range: diff.expected().range(),
+            actual_range: diff.actual().range(),
             spectrogram_diff_rate,
+            correlation,
             shift_samples,
             lufs_diff_db,
+            gain_db,
+            metadata_changes: diff.tag_changes().to_vec(),
+            data_files: self.dump_diff(name, diff)?,
         };
         reporter.record_modified(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -77,6 +100,8 @@ impl<W> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter
                 sample_rate: decoded.sample_rate,
                 channels: decoded.channels,
                 duration_seconds: decoded.duration_seconds,
+                decode_anomalies: non_clean(decoded.anomalies),
+                data_files: self.dump_decoded(name, "added", &decoded)?,
             },
         );
         Ok(MayUnsupported::Ok(()))
@@ -101,6 +126,8 @@ impl<W> DetailReporter<AudioDiff, FileLeaf, JsonReport<W>> for AudioDiffReporter
                 sample_rate: decoded.sample_rate,
                 channels: decoded.channels,
                 duration_seconds: decoded.duration_seconds,
+                decode_anomalies: non_clean(decoded.anomalies),
+                data_files: self.dump_decoded(name, "deleted", &decoded)?,
             },
         );
         Ok(MayUnsupported::Ok(()))
@@ -117,11 +144,43 @@ struct ModifiedReport {
     actual_channels: u16,
     actual_duration_seconds: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
+    expected_decode_anomalies: Option<DecodeAnomalies>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual_decode_anomalies: Option<DecodeAnomalies>,
+    /// Section of each file that was compared, if the comparison was limited to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_range: Option<AudioRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual_range: Option<AudioRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     spectrogram_diff_rate: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    correlation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     shift_samples: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lufs_diff_db: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gain_db: Option<f32>,
+    /// Tags that differ between the files.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metadata_changes: Vec<TagChange>,
+    /// Files written by the audio data dump, if enabled.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data_files: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct UnchangedReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decode_anomalies: Option<DecodeAnomalies>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<AudioRange>,
+    /// Tags that differ between files whose samples are equal.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metadata_changes: Vec<TagChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data_files: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -129,4 +188,12 @@ struct SingleReport {
     sample_rate: u32,
     channels: u16,
     duration_seconds: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decode_anomalies: Option<DecodeAnomalies>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data_files: Vec<String>,
+}
+
+fn non_clean(anomalies: DecodeAnomalies) -> Option<DecodeAnomalies> {
+    (!anomalies.is_clean()).then_some(anomalies)
 }
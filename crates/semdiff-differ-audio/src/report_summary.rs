@@ -1,65 +1,67 @@
+use crate::dump::AudioDataDumpError;
 use crate::{AudioDiff, AudioDiffReporter, audio_extension};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::summary::SummaryReport;
-use std::convert;
+
+const COMPARES_NAME: &str = "audio";
 
 impl<W> DetailReporter<AudioDiff, FileLeaf, SummaryReport<W>> for AudioDiffReporter {
-    type Error = convert::Infallible;
+    type Error = AudioDataDumpError;
 
     fn report_unchanged(
         &self,
-        _name: &str,
-        _diff: &AudioDiff,
+        name: &str,
+        diff: &AudioDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_unchanged();
+        self.dump_diff(name, diff)?;
+        reporter.increment_unchanged(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_modified(
         &self,
-        _name: &str,
-        _diff: &AudioDiff,
+        name: &str,
+        diff: &AudioDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_modified();
+        self.dump_diff(name, diff)?;
+        reporter.increment_modified(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_added(
         &self,
-        _name: &str,
+        name: &str,
         data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if audio_extension(&data.kind).is_none()
-            || self
-                .spectrogram_analyzer
-                .decode_audio(&data.kind, &data.content)
-                .is_err()
-        {
+        if audio_extension(&data.kind).is_none() {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_added();
+        let Ok(decoded) = self.spectrogram_analyzer.decode_audio(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        self.dump_decoded(name, "added", &decoded)?;
+        reporter.increment_added(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_deleted(
         &self,
-        _name: &str,
+        name: &str,
         data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if audio_extension(&data.kind).is_none()
-            || self
-                .spectrogram_analyzer
-                .decode_audio(&data.kind, &data.content)
-                .is_err()
-        {
+        if audio_extension(&data.kind).is_none() {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_deleted();
+        let Ok(decoded) = self.spectrogram_analyzer.decode_audio(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        self.dump_decoded(name, "deleted", &decoded)?;
+        reporter.increment_deleted(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 }
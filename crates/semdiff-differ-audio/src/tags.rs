@@ -0,0 +1,107 @@
+//! Metadata tags (ID3, Vorbis comments, and the like) of audio files, which are compared apart from
+//! the decoded samples.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use symphonia::core::meta::{Metadata, MetadataContainer, PerTrackMetadata, Visual};
+
+/// Tags of a file by key. Repeated keys are joined with `"; "`, and embedded pictures are listed
+/// under `picture` keys with their type, size, and a hash of their data.
+pub(crate) type AudioTags = BTreeMap<String, String>;
+
+/// A tag that differs between `expected` and `actual`; a missing side means the tag is absent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagChange {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+/// Reads every metadata revision of a file, including the tags of track `track_id`. A later
+/// revision replaces the values of an earlier one.
+pub(crate) fn read_tags(mut metadata: Metadata<'_>, track_id: u64) -> AudioTags {
+    let mut tags = AudioTags::new();
+    while let Some(revision) = metadata.pop() {
+        add_revision(&mut tags, &revision.media, &revision.per_track, track_id);
+    }
+    if let Some(revision) = metadata.current() {
+        add_revision(&mut tags, &revision.media, &revision.per_track, track_id);
+    }
+    tags
+}
+
+fn add_revision(tags: &mut AudioTags, media: &MetadataContainer, per_track: &[PerTrackMetadata], track_id: u64) {
+    let mut revision = AudioTags::new();
+    for container in containers(media, per_track, track_id) {
+        for tag in &container.tags {
+            let value = tag.raw.value.to_string();
+            revision
+                .entry(tag.raw.key.clone())
+                .and_modify(|joined| {
+                    joined.push_str("; ");
+                    joined.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        for (index, visual) in container.visuals.iter().enumerate() {
+            let key = match index {
+                0 => "picture".to_owned(),
+                _ => format!("picture {}", index + 1),
+            };
+            revision.insert(key, describe_visual(visual));
+        }
+    }
+    tags.extend(revision);
+}
+
+fn containers<'a>(
+    media: &'a MetadataContainer,
+    per_track: &'a [PerTrackMetadata],
+    track_id: u64,
+) -> impl Iterator<Item = &'a MetadataContainer> {
+    let track = per_track
+        .iter()
+        .filter(move |track| track.track_id == track_id)
+        .map(|track| &track.metadata);
+    std::iter::once(media).chain(track)
+}
+
+fn describe_visual(visual: &Visual) -> String {
+    // FNV-1a, so that replaced artwork of the same size is still told apart.
+    let hash = visual.data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!(
+        "{}, {} bytes, #{hash:016x}",
+        visual.media_type.as_deref().unwrap_or("unknown type"),
+        visual.data.len()
+    )
+}
+
+/// Tags that were added, removed, or changed from `expected` to `actual`, by key.
+pub(crate) fn tag_changes(expected: &AudioTags, actual: &AudioTags) -> Vec<TagChange> {
+    let mut changes = Vec::new();
+    for (key, value) in expected {
+        match actual.get(key) {
+            Some(actual_value) if actual_value == value => {}
+            actual_value => changes.push(TagChange {
+                key: key.clone(),
+                expected: Some(value.clone()),
+                actual: actual_value.cloned(),
+            }),
+        }
+    }
+    for (key, value) in actual {
+        if !expected.contains_key(key) {
+            changes.push(TagChange {
+                key: key.clone(),
+                expected: None,
+                actual: Some(value.clone()),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
@@ -1,4 +1,7 @@
 use super::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[test]
 fn spectrogram_log_bin_range_covers_full_range() {
@@ -26,25 +29,339 @@ fn diff_decoded_returns_incomparable_on_mismatched_format() {
         channels: 1,
         duration_seconds: 0.0,
         samples: vec![vec![0.0]],
-        spectrograms: vec![Vec::new()],
+        anomalies: DecodeAnomalies::default(),
+        range: None,
+        tags: AudioTags::new(),
     };
     let actual = AudioDecoded {
         sample_rate: 48_000,
         channels: 1,
         duration_seconds: 0.0,
         samples: vec![vec![0.0]],
-        spectrograms: vec![Vec::new()],
+        anomalies: DecodeAnomalies::default(),
+        range: None,
+        tags: AudioTags::new(),
     };
     let status = calculator.diff_decoded(&expected, &actual);
     assert!(matches!(status, AudioDiffStatus::Incomparable));
 }
 
+#[test]
+fn diff_decoded_without_visuals_keeps_stats() {
+    let samples = (0..FFT_WINDOW_SIZE * 4)
+        .map(|i| (i as f32 * 0.01).sin())
+        .collect::<Vec<_>>();
+    let decoded = |samples: Vec<f32>| AudioDecoded {
+        sample_rate: 44_100,
+        channels: 1,
+        duration_seconds: 0.0,
+        samples: vec![samples],
+        anomalies: DecodeAnomalies::default(),
+        range: None,
+        tags: AudioTags::new(),
+    };
+    let expected = decoded(samples.clone());
+    let actual = decoded(samples.iter().map(|sample| sample * 0.5).collect());
+    let with_visuals = AudioDiffCalculator::default().diff_decoded(&expected, &actual);
+    let without_visuals = AudioDiffCalculator::default()
+        .with_visuals(false)
+        .diff_decoded(&expected, &actual);
+    let (AudioDiffStatus::Different(with_visuals), AudioDiffStatus::Different(without_visuals)) =
+        (with_visuals, without_visuals)
+    else {
+        panic!("expected attenuated audio to differ");
+    };
+    assert_eq!(with_visuals.spectrogram_diff().len(), 1);
+    assert!(without_visuals.spectrogram_diff().is_empty());
+    assert_eq!(
+        with_visuals.stat().spectrogram_diff_rate,
+        without_visuals.stat().spectrogram_diff_rate
+    );
+}
+
+#[test]
+fn diff_decoded_ignores_gain_when_enabled() {
+    let samples = (0..FFT_WINDOW_SIZE * 4)
+        .map(|i| (i as f32 * 0.01).sin())
+        .collect::<Vec<_>>();
+    let decoded = |samples: Vec<f32>| AudioDecoded {
+        sample_rate: 44_100,
+        channels: 1,
+        duration_seconds: 0.0,
+        samples: vec![samples],
+        anomalies: DecodeAnomalies::default(),
+        range: None,
+        tags: AudioTags::new(),
+    };
+    let expected = decoded(samples.clone());
+    let actual = decoded(samples.iter().map(|sample| sample * 0.5).collect());
+
+    let status = AudioDiffCalculator::default()
+        .with_ignore_gain(true)
+        .diff_decoded(&expected, &actual);
+
+    let AudioDiffStatus::Equal(detail) = status else {
+        panic!("expected attenuated audio to be equal when gain is ignored");
+    };
+    let stat = detail.stat();
+    assert_eq!(stat.spectrogram_diff_rate, Some(0.0));
+    assert!((stat.lufs_diff_db - 6.02).abs() < 0.01);
+    assert!((stat.gain_db.unwrap() + 6.02).abs() < 0.01);
+}
+
+#[test]
+fn diff_decoded_decides_equality_from_correlation_threshold() {
+    let samples = (0..FFT_WINDOW_SIZE * 4)
+        .map(|i| (i as f32 * 0.01).sin())
+        .collect::<Vec<_>>();
+    let decoded = |samples: Vec<f32>| AudioDecoded {
+        sample_rate: 44_100,
+        channels: 1,
+        duration_seconds: 0.0,
+        samples: vec![samples],
+        anomalies: DecodeAnomalies::default(),
+        range: None,
+        tags: AudioTags::new(),
+    };
+    let expected = decoded(samples.clone());
+    let calculator = AudioDiffCalculator::new(0.0, 1.0, 0.0, 0.0).with_correlation_threshold(Some(0.99));
+
+    let noisy = decoded(
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| sample + if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect(),
+    );
+    let AudioDiffStatus::Equal(detail) = calculator.diff_decoded(&expected, &noisy) else {
+        panic!("expected slightly noisy audio to correlate");
+    };
+    assert!(detail.stat().correlation > 0.99);
+    assert_eq!(detail.stat().spectrogram_diff_rate, None);
+    assert!(detail.spectrogram_diff().is_empty());
+
+    let inverted = decoded(samples.iter().map(|sample| -sample).collect());
+    let AudioDiffStatus::Different(detail) = calculator.diff_decoded(&expected, &inverted) else {
+        panic!("expected inverted audio to differ");
+    };
+    assert!((detail.stat().correlation + 1.0).abs() < 1e-3);
+}
+
+/// Number of [`TrackedFrame`]s alive, and the most that were alive at once.
+#[derive(Default)]
+struct LiveFrames {
+    live: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// A spectrogram frame counted in [`LiveFrames`] until dropped.
+struct TrackedFrame {
+    frame: [f32; SPECTROGRAM_DATA_HEIGHT],
+    live_frames: Arc<LiveFrames>,
+}
+
+impl TrackedFrame {
+    fn new(frame: [f32; SPECTROGRAM_DATA_HEIGHT], live_frames: &Arc<LiveFrames>) -> TrackedFrame {
+        let live = live_frames.live.fetch_add(1, Ordering::Relaxed) + 1;
+        live_frames.peak.fetch_max(live, Ordering::Relaxed);
+        TrackedFrame {
+            frame,
+            live_frames: Arc::clone(live_frames),
+        }
+    }
+}
+
+impl Borrow<[f32; SPECTROGRAM_DATA_HEIGHT]> for TrackedFrame {
+    fn borrow(&self) -> &[f32; SPECTROGRAM_DATA_HEIGHT] {
+        &self.frame
+    }
+}
+
+impl Drop for TrackedFrame {
+    fn drop(&mut self) {
+        self.live_frames.live.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn diff_spectrograms_holds_one_frame_per_side_at_a_time() {
+    let samples = (0..DEFAULT_FFT_HOP_SIZE * (SPECTROGRAM_WIDTH as usize * 2 + 7))
+        .map(|i| (i as f32 * 0.01).sin())
+        .collect::<Vec<_>>();
+    let attenuated = samples.iter().map(|sample| sample * 0.5).collect::<Vec<_>>();
+    let analyzer = SpectrogramAnalyzer::new();
+    let calculator = AudioDiffCalculator::default();
+    let spectrogram_len = analyzer.frame_count(samples.len());
+    assert!(spectrogram_len > SPECTROGRAM_WIDTH as usize);
+
+    let live_frames = Arc::default();
+    let (diff_image, diff_rate) = calculator.diff_spectrograms(
+        spectrogram_len,
+        analyzer
+            .frames(&samples)
+            .map(|frame| TrackedFrame::new(frame, &live_frames)),
+        analyzer
+            .frames(&attenuated)
+            .map(|frame| TrackedFrame::new(frame, &live_frames)),
+    );
+
+    assert!(diff_image.is_some());
+    assert!(diff_rate > 0.0);
+    assert_eq!(live_frames.live.load(Ordering::Relaxed), 0);
+    assert!(live_frames.peak.load(Ordering::Relaxed) <= 2);
+}
+
+/// Order-dependent checksum of the alpha channel of `image`.
+fn alpha_checksum(image: &RgbaImage) -> u64 {
+    image.pixels().enumerate().fold(0u64, |acc, (i, pixel)| {
+        acc.wrapping_mul(31).wrapping_add(i as u64 ^ u64::from(pixel.0[3]))
+    })
+}
+
+#[test]
+fn streamed_spectrograms_match_the_batch_computation() {
+    // Rates and image checksums from the computation that held every frame of both sides, for
+    // a spectrogram wider than the image and one narrower than it.
+    for (len, hop_size, diff_rate, diff_checksum, rendered_checksum) in [
+        (
+            100_000,
+            64,
+            0.11449979417439132,
+            7764401520648006082,
+            10035584268136413420,
+        ),
+        (
+            8_000,
+            DEFAULT_FFT_HOP_SIZE,
+            0.35271317829457366,
+            9658351383323143296,
+            15981327127765676076,
+        ),
+    ] {
+        let expected = (0..len).map(|i| (i as f32 * 0.05).sin() * 0.3).collect::<Vec<_>>();
+        let actual = (0..len)
+            .map(|i| (i as f32 * 0.05).sin() * 0.3 + if i > len / 2 { (i as f32 * 0.4).sin() * 0.1 } else { 0.0 })
+            .collect::<Vec<_>>();
+        let decoded = |samples: Vec<f32>| AudioDecoded {
+            sample_rate: 8_000,
+            channels: 1,
+            duration_seconds: samples.len() as f32 / 8_000.0,
+            samples: vec![samples],
+            anomalies: DecodeAnomalies::default(),
+            range: None,
+            tags: AudioTags::new(),
+        };
+        let expected = decoded(expected);
+        let actual = decoded(actual);
+        let calculator = AudioDiffCalculator::new(0.0, 100.0, 0.5, 0.0).with_hop_size(hop_size);
+        let AudioDiffStatus::Different(detail) = calculator.diff_decoded(&expected, &actual) else {
+            panic!("expected the added tone to differ");
+        };
+        assert_eq!(detail.stat().spectrogram_diff_rate, Some(diff_rate), "{len}");
+        assert_eq!(alpha_checksum(&detail.spectrogram_diff()[0]), diff_checksum, "{len}");
+
+        let analyzer = SpectrogramAnalyzer::with_hop_size(hop_size);
+        let stat = AudioStat::from_pair(&expected, &actual, Some(&analyzer));
+        let rendered = render_spectrograms(&analyzer, &expected.samples, &stat, 8_000);
+        assert_eq!(alpha_checksum(&rendered[0]), rendered_checksum, "{len}");
+    }
+}
+
+/// Counts the bytes allocated and not yet freed by each thread, so that a test can measure the
+/// peak memory of what it runs without seeing the tests running beside it.
+struct ThreadAllocations;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+impl ThreadAllocations {
+    fn record(delta: isize) {
+        let _ = LIVE_BYTES.try_with(|live| {
+            let live = live.get() + delta;
+            LIVE_BYTES.set(live);
+            let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live)));
+        });
+    }
+
+    /// Peak of the bytes live on this thread while running `f`, above those live before it.
+    fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = LIVE_BYTES.get();
+        PEAK_BYTES.set(before);
+        let result = f();
+        (result, (PEAK_BYTES.get() - before) as usize)
+    }
+}
+
+unsafe impl GlobalAlloc for ThreadAllocations {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ThreadAllocations::record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            ThreadAllocations::record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ThreadAllocations::record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ThreadAllocations::record(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: ThreadAllocations = ThreadAllocations;
+
+#[test]
+fn diff_peak_memory_stays_below_one_full_spectrogram() {
+    let hop_size = MIN_FFT_HOP_SIZE;
+    let samples = (0..200_000)
+        .map(|i| ((i as f32 * 0.05).sin() * 10_000.0) as i16)
+        .collect::<Vec<_>>();
+    let mut changed = samples.clone();
+    changed[100_000..].iter_mut().for_each(|sample| *sample /= 2);
+    let mime = "audio/wav".parse::<Mime>().unwrap();
+    let leaf = |samples: &[i16]| FileLeaf::from_bytes("a.wav", mime.clone(), wav_bytes(samples));
+    let (expected, actual) = (leaf(&samples), leaf(&changed));
+    let calculator = AudioDiffCalculator::default().with_hop_size(hop_size);
+
+    let (diff, peak_bytes) = ThreadAllocations::peak_during(|| calculator.diff("a.wav", expected, actual).unwrap());
+    let MayUnsupported::Ok(diff) = diff else {
+        panic!("wav is supported");
+    };
+    assert!(!diff.equal());
+    assert_eq!(diff.expected().spectrogram().len(), 1);
+    // Holding the spectrogram of either side in full would take this much on its own.
+    let full_spectrogram_bytes =
+        SpectrogramAnalyzer::with_hop_size(hop_size).frame_count(samples.len()) * SPECTROGRAM_DATA_HEIGHT * 4;
+    assert!(
+        peak_bytes < full_spectrogram_bytes,
+        "peak {peak_bytes} bytes, one full spectrogram {full_spectrogram_bytes} bytes"
+    );
+}
+
 #[test]
 fn test_align_samples_no_shift() {
     let expected = vec![vec![0.0, 1.0, 2.0, 3.0]];
     let actual = vec![vec![0.0, 1.0, 2.0, 3.0]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 0);
     assert_eq!(aligned_exp, expected);
     assert_eq!(aligned_act, actual);
@@ -55,7 +372,7 @@ fn test_align_samples_all_zeros() {
     let expected = vec![vec![0.0, 0.0, 0.0, 0.0]];
     let actual = vec![vec![0.0, 0.0, 0.0, 0.0]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 0);
     assert_eq!(aligned_exp, expected);
     assert_eq!(aligned_act, actual);
@@ -66,7 +383,7 @@ fn test_align_samples_positive_shift() {
     let expected = vec![vec![1.0, 2.0, 3.0, 4.0]];
     let actual = vec![vec![0.0, 1.0, 2.0, 3.0, 4.0]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 1);
     assert_eq!(aligned_exp[0], vec![1.0, 2.0, 3.0, 4.0]);
     assert_eq!(aligned_act[0], vec![1.0, 2.0, 3.0, 4.0]);
@@ -77,7 +394,7 @@ fn test_align_samples_negative_shift() {
     let expected = vec![vec![0.0, 1.0, 2.0, 3.0, 4.0]];
     let actual = vec![vec![1.0, 2.0, 3.0, 4.0]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, -1);
     assert_eq!(aligned_exp[0], vec![1.0, 2.0, 3.0, 4.0]);
     assert_eq!(aligned_act[0], vec![1.0, 2.0, 3.0, 4.0]);
@@ -88,7 +405,7 @@ fn test_align_samples_max_shift_limit() {
     let expected = vec![vec![1.0, 2.0, 3.0]];
     let actual = vec![vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 2);
     assert_eq!(aligned_exp[0], vec![1.0, 2.0, 3.0]);
     assert_eq!(aligned_act[0], vec![0.0, 1.0, 2.0]);
@@ -99,7 +416,7 @@ fn test_align_samples_empty_input() {
     let expected: Vec<Vec<f32>> = vec![vec![]];
     let actual: Vec<Vec<f32>> = vec![vec![]];
     let max_shift = 2;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 0);
     assert_eq!(aligned_exp[0], Vec::<f32>::new());
     assert_eq!(aligned_act[0], Vec::<f32>::new());
@@ -110,8 +427,275 @@ fn test_align_samples_zero_max_shift() {
     let expected = vec![vec![1.0, 2.0, 3.0]];
     let actual = vec![vec![0.0, 1.0, 2.0, 3.0]];
     let max_shift = 0;
-    let (aligned_exp, aligned_act, shift) = align_samples(expected.clone(), actual.clone(), max_shift);
+    let (aligned_exp, aligned_act, shift) = align_samples(&expected, &actual, max_shift);
     assert_eq!(shift, 0);
     assert_eq!(aligned_exp[0], vec![1.0, 2.0, 3.0]);
     assert_eq!(aligned_act[0], vec![0.0, 1.0, 2.0]);
 }
+
+#[test]
+fn spectrogram_frame_count_follows_hop_size() {
+    let samples = vec![0.0; FFT_WINDOW_SIZE * 4];
+    let default = SpectrogramAnalyzer::new().compute(&samples);
+    let quarter = SpectrogramAnalyzer::with_hop_size(FFT_WINDOW_SIZE / 4).compute(&samples);
+    assert_eq!(default.len(), samples.len() / DEFAULT_FFT_HOP_SIZE + 1);
+    assert_eq!(quarter.len(), samples.len() / (FFT_WINDOW_SIZE / 4) + 1);
+    assert_eq!(SpectrogramAnalyzer::with_hop_size(0).hop_size, MIN_FFT_HOP_SIZE);
+    assert_eq!(
+        SpectrogramAnalyzer::with_hop_size(usize::MAX).hop_size,
+        MAX_FFT_HOP_SIZE
+    );
+}
+
+#[test]
+fn hex_color_round_trips_with_or_without_hash() {
+    assert_eq!("#1a2B3c".parse::<HexColor>().unwrap(), HexColor([0x1a, 0x2b, 0x3c]));
+    assert_eq!("ff0000".parse::<HexColor>().unwrap().to_string(), "#ff0000");
+    assert!("#fff".parse::<HexColor>().is_err());
+    assert!("#12345g".parse::<HexColor>().is_err());
+    assert!("red".parse::<HexColor>().is_err());
+}
+
+fn wav_bytes(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8_000u32.to_le_bytes());
+    wav.extend_from_slice(&16_000u32.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+#[test]
+fn decode_audio_flags_truncated_stream() {
+    let samples = (0..8_000)
+        .map(|i| ((i as f32 * 0.05).sin() * 10_000.0) as i16)
+        .collect::<Vec<_>>();
+    let wav = wav_bytes(&samples);
+    let analyzer = SpectrogramAnalyzer::new();
+    let mime = "audio/wav".parse::<Mime>().unwrap();
+
+    let full = analyzer.decode_audio(&mime, &wav).unwrap();
+    assert!(full.anomalies.is_clean());
+    assert_eq!(full.samples[0].len(), samples.len());
+
+    let cut = analyzer.decode_audio(&mime, &wav[..wav.len() / 2 + 1]).unwrap();
+    assert!(cut.anomalies.truncated);
+    assert!(cut.samples[0].len() < samples.len());
+    assert_eq!(cut.anomalies.to_string(), "the stream ends unexpectedly");
+
+    let calculator = AudioDiffCalculator::new(0.0, f32::INFINITY, f32::INFINITY, 1.0);
+    assert!(matches!(
+        calculator.diff_decoded(&full, &cut),
+        AudioDiffStatus::Different(_)
+    ));
+}
+
+#[test]
+fn format_regression_needs_one_side_to_decode() {
+    let wav = wav_bytes(&[0, 1_000, -1_000, 0]);
+    let mut broken = wav.clone();
+    broken[8..12].copy_from_slice(b"JUNK");
+    let leaf = |content: &[u8]| FileLeaf::from_bytes("", "audio/wav".parse().unwrap(), content);
+    let (valid, broken) = (leaf(&wav), leaf(&broken));
+
+    let calculator = AudioDiffCalculator::new(0.0, 0.0, 0.0, 0.0);
+    assert!(matches!(
+        calculator.diff("", valid.clone(), broken.clone()),
+        Ok(MayUnsupported::Unsupported)
+    ));
+    let regression = calculator.format_regression(&valid, &broken).unwrap();
+    assert_eq!(regression.to_string(), "expected parsed as audio, actual did not");
+    assert_eq!(calculator.format_regression(&valid, &valid), None);
+    assert_eq!(calculator.format_regression(&broken, &broken), None);
+}
+
+#[test]
+fn audio_range_limits_comparison_to_section() {
+    let samples = (0..8_000)
+        .map(|i| ((i as f32 * 0.05).sin() * 10_000.0) as i16)
+        .collect::<Vec<_>>();
+    let mut changed_tail = samples.clone();
+    changed_tail[6_000..].fill(0);
+    let mime = "audio/wav".parse::<Mime>().unwrap();
+    let analyzer = SpectrogramAnalyzer::new();
+
+    let range = AudioRange::new(0.25, 0.5).unwrap();
+    let (section, _memory) = analyzer
+        .decode_audio_within(&mime, &wav_bytes(&samples), &MemoryBudget::unlimited(), Some(range))
+        .unwrap();
+    assert_eq!(section.samples[0].len(), 2_000);
+    assert_eq!(section.samples[0][0], f32::from(samples[2_000]) / 32_768.0);
+    assert_eq!(section.range, Some(range));
+
+    let past_end = AudioRange::new(0.75, 2.0).unwrap();
+    let (tail, _memory) = analyzer
+        .decode_audio_within(&mime, &wav_bytes(&samples), &MemoryBudget::unlimited(), Some(past_end))
+        .unwrap();
+    assert_eq!(tail.samples[0].len(), 2_000);
+    assert_eq!(tail.range, AudioRange::new(0.75, 1.0));
+    assert!(AudioRange::new(1.0, 1.0).is_none());
+
+    let leaf = |samples: &[i16]| FileLeaf::from_bytes("a.wav", mime.clone(), wav_bytes(samples));
+    let calculator = AudioDiffCalculator::default().with_visuals(false);
+    let MayUnsupported::Ok(full) = calculator.diff("a.wav", leaf(&samples), leaf(&changed_tail)).unwrap() else {
+        panic!("wav is supported");
+    };
+    assert!(!full.equal());
+    let calculator = calculator.with_range(Some(range));
+    let MayUnsupported::Ok(section) = calculator.diff("a.wav", leaf(&samples), leaf(&changed_tail)).unwrap() else {
+        panic!("wav is supported");
+    };
+    assert!(section.equal());
+    assert_eq!(section.expected().range(), Some(range));
+}
+
+#[test]
+fn audio_diff_holds_its_memory_until_dropped() {
+    let samples = (0..8_000)
+        .map(|i| ((i as f32 * 0.05).sin() * 10_000.0) as i16)
+        .collect::<Vec<_>>();
+    let mime = "audio/wav".parse::<Mime>().unwrap();
+    let leaf = || FileLeaf::from_bytes("a.wav", mime.clone(), wav_bytes(&samples));
+    let budget = MemoryBudget::new(u64::MAX);
+    let calculator = AudioDiffCalculator::default().with_memory_budget(budget.clone());
+    let MayUnsupported::Ok(diff) = calculator.diff("a.wav", leaf(), leaf()).unwrap() else {
+        panic!("wav is supported");
+    };
+    assert!(budget.in_use() > 0);
+    drop(diff);
+    assert_eq!(budget.in_use(), 0);
+}
+
+#[test]
+fn spectrogram_frequency_position_follows_log_bins() {
+    assert_eq!(spectrogram_frequency_position(0.0, 48_000), 0.0);
+    assert!((spectrogram_frequency_position(24_000.0, 48_000) - 1.0).abs() < 1e-9);
+    for y in [64, 128, 192] {
+        let frequency = spectrogram_log_bin_range(y).start as f64 * 48_000.0 / FFT_WINDOW_SIZE as f64;
+        let row = spectrogram_frequency_position(frequency, 48_000) * SPECTROGRAM_HEIGHT as f64;
+        assert!((row - y as f64).abs() <= 1.0, "{y}: {row}");
+    }
+}
+
+#[test]
+fn audio_data_dump_writes_npy_arrays() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let root = std::env::temp_dir().join(format!("semdiff-audio-dump-{nanos}"));
+    let samples = vec![vec![0.5, -1.0, 0.25]];
+    let spectrograms = vec![vec![[1.0; SPECTROGRAM_DATA_HEIGHT]; 2]];
+    let paths = AudioDataDump::new(&root)
+        .write_decoded("dir/a.wav", "expected", &samples, &spectrograms)
+        .unwrap();
+    assert_eq!(
+        paths,
+        [
+            root.join("dir/a.wav/expected_samples_ch1.npy").display().to_string(),
+            root.join("dir/a.wav/expected_spectrogram_ch1.npy")
+                .display()
+                .to_string(),
+        ]
+    );
+
+    let samples_npy = std::fs::read(&paths[0]).unwrap();
+    let header_len = u16::from_le_bytes([samples_npy[8], samples_npy[9]]) as usize;
+    let header = std::str::from_utf8(&samples_npy[10..10 + header_len]).unwrap();
+    assert_eq!(samples_npy[..8], *b"\x93NUMPY\x01\x00");
+    assert_eq!((10 + header_len) % 64, 0);
+    assert!(header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (3,), }"));
+    assert!(header.ends_with('\n'));
+    assert_eq!(
+        samples_npy[10 + header_len..],
+        [0.5f32, -1.0, 0.25].map(f32::to_le_bytes).concat()
+    );
+
+    let spectrogram_npy = std::fs::read(&paths[1]).unwrap();
+    assert!(String::from_utf8_lossy(&spectrogram_npy).contains("'shape': (2, 1024)"));
+    assert_eq!(spectrogram_npy.len() % 64, 2 * SPECTROGRAM_DATA_HEIGHT * 4 % 64);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+/// `wav` preceded by an ID3v2.3 tag holding the title `title`.
+fn with_wav_title(wav: &[u8], title: &str) -> Vec<u8> {
+    let mut frame = b"TIT2".to_vec();
+    frame.extend_from_slice(&(title.len() as u32 + 1).to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0]);
+    frame.extend_from_slice(title.as_bytes());
+    // Tag sizes are stored 7 bits per byte.
+    let size = frame.len() as u32;
+    let mut tagged = b"ID3\x03\x00\x00".to_vec();
+    tagged.extend_from_slice(&[
+        (size >> 21) as u8 & 0x7f,
+        (size >> 14) as u8 & 0x7f,
+        (size >> 7) as u8 & 0x7f,
+        size as u8 & 0x7f,
+    ]);
+    tagged.extend_from_slice(&frame);
+    tagged.extend_from_slice(wav);
+    tagged
+}
+
+#[test]
+fn tag_changes_are_noted_without_changing_equality() {
+    let wav = wav_bytes(&[0, 1_000, -1_000, 0, 500, -500, 0, 0]);
+    let leaf = |content: Vec<u8>| FileLeaf::from_bytes("a.wav", "audio/wav".parse().unwrap(), content);
+    let calculator = AudioDiffCalculator::default().with_visuals(false);
+    let diff = |expected: &[u8], actual: &[u8], calculator: &AudioDiffCalculator| {
+        let MayUnsupported::Ok(diff) = calculator
+            .diff("a.wav", leaf(expected.to_vec()), leaf(actual.to_vec()))
+            .unwrap()
+        else {
+            panic!("wav is supported");
+        };
+        diff
+    };
+
+    let retitled = diff(
+        &with_wav_title(&wav, "Take 1"),
+        &with_wav_title(&wav, "Take 2"),
+        &calculator,
+    );
+    assert!(retitled.equal());
+    let [change] = retitled.tag_changes() else {
+        panic!("one tag differs: {:?}", retitled.tag_changes());
+    };
+    assert_eq!(change.expected.as_deref(), Some("Take 1"));
+    assert_eq!(change.actual.as_deref(), Some("Take 2"));
+
+    let untagged = diff(&with_wav_title(&wav, "Take 1"), &wav, &calculator);
+    assert!(untagged.equal());
+    assert_eq!(untagged.tag_changes()[0].actual, None);
+    assert!(diff(&wav, &wav, &calculator).tag_changes().is_empty());
+
+    let calculator = calculator.with_compare_tags(true);
+    assert!(
+        !diff(
+            &with_wav_title(&wav, "Take 1"),
+            &with_wav_title(&wav, "Take 2"),
+            &calculator
+        )
+        .equal()
+    );
+    assert!(
+        diff(
+            &with_wav_title(&wav, "Take 1"),
+            &with_wav_title(&wav, "Take 1"),
+            &calculator
+        )
+        .equal()
+    );
+}
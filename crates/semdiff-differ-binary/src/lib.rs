@@ -1,10 +1,17 @@
-use memmap2::Mmap;
-use semdiff_core::fs::FileLeaf;
+use mime::Mime;
+use semdiff_core::fs::{FileContent, FileLeaf};
 use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use serde::Serialize;
 use similar::{ChangeTag, TextDiffConfig};
+use std::collections::HashMap;
 use std::convert;
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::str::FromStr;
 use std::sync::Arc;
+use thiserror::Error;
 
+pub mod report_collect;
 pub mod report_html;
 pub mod report_json;
 pub mod report_summary;
@@ -12,13 +19,162 @@ pub mod report_summary;
 #[cfg(test)]
 mod tests;
 
-pub struct BinaryDiffReporter;
+#[derive(Debug, Default)]
+pub struct BinaryDiffReporter {
+    fallback: BinaryFallback,
+}
+
+impl BinaryDiffReporter {
+    /// Leaves added and deleted files that `fallback` does not accept to the next differ, or
+    /// skipped as unsupported when there is none.
+    pub fn with_fallback(mut self, fallback: BinaryFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+/// Which files the binary differ takes when every other differ passed on them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinaryFallback {
+    /// Every file.
+    #[default]
+    Always,
+    /// Files detected as a specific binary format, such as `application/zip`. Text files and
+    /// files of unknown kind (`application/octet-stream`) are left unsupported.
+    KnownBinary,
+    /// No file, so files that no other differ handled are reported as skipped.
+    Never,
+}
+
+impl BinaryFallback {
+    pub(crate) fn accepts(self, kind: &Mime) -> bool {
+        match self {
+            BinaryFallback::Always => true,
+            BinaryFallback::KnownBinary => kind.type_() != mime::TEXT && *kind != mime::APPLICATION_OCTET_STREAM,
+            BinaryFallback::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("unknown binary fallback {0:?} (expected \"always\", \"known-binary\", or \"never\")")]
+pub struct ParseBinaryFallbackError(String);
+
+impl FromStr for BinaryFallback {
+    type Err = ParseBinaryFallbackError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "always" => Ok(BinaryFallback::Always),
+            "known-binary" => Ok(BinaryFallback::KnownBinary),
+            "never" => Ok(BinaryFallback::Never),
+            _ => Err(ParseBinaryFallbackError(input.to_owned())),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct BinaryDiff {
     equal: bool,
-    expected: Arc<Mmap>,
-    actual: Arc<Mmap>,
+    expected: BinaryContent,
+    actual: BinaryContent,
+    /// Ignored ranges that overlap either file; their bytes are zeroed in `expected` and `actual`.
+    ignored_ranges: Vec<ByteRange>,
+    /// The bytes differ, but only in CRLF, CR, and LF line endings.
+    line_endings_differ: bool,
+}
+
+#[derive(Debug)]
+enum BinaryContent {
+    Mapped(Arc<FileContent>),
+    Masked(Vec<u8>),
+}
+
+impl BinaryContent {
+    fn new(content: Arc<FileContent>, ignored_ranges: &[ByteRange]) -> BinaryContent {
+        if ignored_ranges.is_empty() {
+            return BinaryContent::Mapped(content);
+        }
+        let mut masked = content.to_vec();
+        for range in ignored_ranges {
+            let range = range.clamp(masked.len());
+            masked[range].fill(0);
+        }
+        BinaryContent::Masked(masked)
+    }
+}
+
+impl Deref for BinaryContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            BinaryContent::Mapped(content) => content,
+            BinaryContent::Masked(content) => content,
+        }
+    }
+}
+
+/// A byte range given as `START:LEN`, where both numbers are decimal or `0x`-prefixed hexadecimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ByteRange {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl ByteRange {
+    pub fn new(start: usize, len: usize) -> ByteRange {
+        ByteRange { start, len }
+    }
+
+    fn end(&self) -> usize {
+        self.start.saturating_add(self.len)
+    }
+
+    fn clamp(&self, len: usize) -> Range<usize> {
+        self.start.min(len)..self.end().min(len)
+    }
+
+    pub(crate) fn contains(&self, offset: usize) -> bool {
+        (self.start..self.end()).contains(&offset)
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:X}:{}", self.start, self.len)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseByteRangeError {
+    #[error("expected START:LEN, got {0:?}")]
+    MissingLength(String),
+    #[error("invalid byte offset or length {0:?}")]
+    InvalidNumber(String),
+}
+
+impl FromStr for ByteRange {
+    type Err = ParseByteRangeError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (start, len) = input
+            .split_once(':')
+            .ok_or_else(|| ParseByteRangeError::MissingLength(input.to_owned()))?;
+        Ok(ByteRange {
+            start: parse_byte_count(start)?,
+            len: parse_byte_count(len)?,
+        })
+    }
+}
+
+fn parse_byte_count(input: &str) -> Result<usize, ParseByteRangeError> {
+    let input = input.trim();
+    let parsed = match input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => input.parse(),
+    };
+    parsed.map_err(|_| ParseByteRangeError::InvalidNumber(input.to_owned()))
 }
 
 impl Diff for BinaryDiff {
@@ -36,6 +192,14 @@ impl BinaryDiff {
         &self.actual
     }
 
+    fn ignored_ranges(&self) -> &[ByteRange] {
+        &self.ignored_ranges
+    }
+
+    fn line_endings_differ(&self) -> bool {
+        self.line_endings_differ
+    }
+
     fn changes(&self) -> similar::TextDiff<'_, '_, [u8]> {
         binary_diff_changes(&self.expected[..], &self.actual[..])
     }
@@ -84,7 +248,33 @@ impl ChangeStat {
 }
 
 #[derive(Default)]
-pub struct BinaryDiffCalculator;
+pub struct BinaryDiffCalculator {
+    ignore_ranges: Vec<ByteRange>,
+    fallback: BinaryFallback,
+    normalize_line_endings: bool,
+}
+
+impl BinaryDiffCalculator {
+    /// Zeroes these byte ranges on both sides before comparing, so volatile regions such as
+    /// embedded timestamps do not make files differ.
+    pub fn with_ignore_ranges(mut self, ignore_ranges: Vec<ByteRange>) -> Self {
+        self.ignore_ranges = ignore_ranges;
+        self
+    }
+
+    /// Passes on pairs of files unless `fallback` accepts both of their kinds.
+    pub fn with_fallback(mut self, fallback: BinaryFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Treats files whose bytes only differ in line endings (CRLF, CR, or LF) as equal, for text
+    /// files that were detected as binary because of a stray control byte.
+    pub fn with_normalize_line_endings(mut self, normalize_line_endings: bool) -> Self {
+        self.normalize_line_endings = normalize_line_endings;
+        self
+    }
+}
 
 impl DiffCalculator<FileLeaf> for BinaryDiffCalculator {
     type Error = convert::Infallible;
@@ -96,10 +286,85 @@ impl DiffCalculator<FileLeaf> for BinaryDiffCalculator {
         expected: FileLeaf,
         actual: FileLeaf,
     ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        if !self.fallback.accepts(&expected.kind) || !self.fallback.accepts(&actual.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        let len = expected.content.len().max(actual.content.len());
+        let ignored_ranges = self
+            .ignore_ranges
+            .iter()
+            .copied()
+            .filter(|range| range.len > 0 && range.start < len)
+            .collect::<Vec<_>>();
+        let expected = BinaryContent::new(expected.content, &ignored_ranges);
+        let actual = BinaryContent::new(actual.content, &ignored_ranges);
+        let equal = <[u8] as PartialEq<[u8]>>::eq(&expected, &actual);
+        let line_endings_differ =
+            !equal && self.normalize_line_endings && normalized_lines(&expected).eq(normalized_lines(&actual));
         Ok(MayUnsupported::Ok(BinaryDiff {
-            equal: <[u8] as PartialEq<[u8]>>::eq(&*expected.content, &*actual.content),
-            expected: expected.content,
-            actual: actual.content,
+            equal: equal || line_endings_differ,
+            expected,
+            actual,
+            ignored_ranges,
+            line_endings_differ,
         }))
     }
+
+    fn similarity(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<f64> {
+        Some(chunk_similarity(&expected.content, &actual.content))
+    }
+}
+
+/// The bytes of `content` with every CRLF and lone CR turned into LF.
+fn normalized_lines(content: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    let mut bytes = content.iter().copied().peekable();
+    std::iter::from_fn(move || match bytes.next()? {
+        b'\r' => {
+            bytes.next_if_eq(&b'\n');
+            Some(b'\n')
+        }
+        byte => Some(byte),
+    })
+}
+
+/// Longest run of bytes [`chunk_similarity`] compares at once.
+const SIMILARITY_CHUNK_LEN: usize = 64;
+
+/// Share of bytes in chunks found on both sides, from 0.0 to 1.0.
+///
+/// Content is split after each newline or every [`SIMILARITY_CHUNK_LEN`] bytes, as git does for
+/// rename detection, so an insertion only disturbs the chunks around it.
+fn chunk_similarity(expected: &[u8], actual: &[u8]) -> f64 {
+    if expected.is_empty() && actual.is_empty() {
+        return 1.0;
+    }
+    let mut chunks = HashMap::<&[u8], usize>::new();
+    for chunk in similarity_chunks(expected) {
+        *chunks.entry(chunk).or_default() += 1;
+    }
+    let mut common = 0;
+    for chunk in similarity_chunks(actual) {
+        if let Some(count @ 1..) = chunks.get_mut(chunk) {
+            *count -= 1;
+            common += chunk.len();
+        }
+    }
+    (2 * common) as f64 / (expected.len() + actual.len()) as f64
+}
+
+fn similarity_chunks(content: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = content;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let limit = rest.len().min(SIMILARITY_CHUNK_LEN);
+        let len = rest[..limit]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(limit, |newline| newline + 1);
+        let (chunk, tail) = rest.split_at(len);
+        rest = tail;
+        Some(chunk)
+    })
 }
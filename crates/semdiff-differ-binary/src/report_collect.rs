@@ -0,0 +1,57 @@
+use crate::{BinaryDiff, BinaryDiffReporter};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+use std::convert;
+
+const COMPARES_NAME: &str = "binary";
+
+impl DetailReporter<BinaryDiff, FileLeaf, CollectReport<'_>> for BinaryDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &BinaryDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        _diff: &BinaryDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_modified(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
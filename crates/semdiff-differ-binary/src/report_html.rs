@@ -1,4 +1,4 @@
-use crate::{BinaryDiff, BinaryDiffReporter};
+use crate::{BinaryDiff, BinaryDiffReporter, ByteRange};
 use askama::Template;
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
@@ -39,6 +39,8 @@ enum BinaryPreviewBody {
 #[template(path = "binary_detail.html")]
 struct BinaryDetailTemplate<'a> {
     detail: BinaryDetailBody<'a>,
+    /// Zeroed before comparing; their bytes are shown as `--`.
+    ignored_ranges: &'a [ByteRange],
 }
 
 enum BinaryDetailBody<'a> {
@@ -62,14 +64,17 @@ fn diff_iter<'a>(
     diff.ops().iter().flat_map(move |x| remapper.iter_slices(x))
 }
 
-fn format_line(line: &[u8]) -> impl Display + '_ {
-    fmt::from_fn(|f| {
-        let Some((first, tail)) = line.split_first() else {
-            return Ok(());
-        };
-        write!(f, "{:02X}", first)?;
-        for byte in tail {
-            write!(f, " {:02X}", byte)?;
+fn format_line<'a>(line: &'a [u8], offset: usize, ignored_ranges: &'a [ByteRange]) -> impl Display + 'a {
+    fmt::from_fn(move |f| {
+        for (i, byte) in line.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            if ignored_ranges.iter().any(|range| range.contains(offset + i)) {
+                f.write_str("--")?;
+            } else {
+                write!(f, "{:02X}", byte)?;
+            }
         }
         Ok(())
     })
@@ -113,9 +118,14 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
         };
         let detail_html = BinaryDetailTemplate {
             detail: BinaryDetailBody::Single {
-                label: "same",
+                label: if diff.line_endings_differ() {
+                    "same except line endings"
+                } else {
+                    "same"
+                },
                 body: diff.expected(),
             },
+            ignored_ranges: diff.ignored_ranges(),
         };
         reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -143,6 +153,7 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
                 actual: diff.actual(),
                 diff: &diff_changes,
             },
+            ignored_ranges: diff.ignored_ranges(),
         };
         reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -154,6 +165,9 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
         data: &FileLeaf,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
         let preview_html = BinaryPreviewTemplate {
             body: BinaryPreviewBody::Single {
                 size: data.content.len(),
@@ -164,6 +178,7 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
                 label: "added",
                 body: &data.content,
             },
+            ignored_ranges: &[],
         };
         reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -175,6 +190,9 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
         data: &FileLeaf,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
         let preview_html = BinaryPreviewTemplate {
             body: BinaryPreviewBody::Single {
                 size: data.content.len(),
@@ -185,6 +203,7 @@ impl DetailReporter<BinaryDiff, FileLeaf, HtmlReport> for BinaryDiffReporter {
                 label: "deleted",
                 body: &data.content,
             },
+            ignored_ranges: &[],
         };
         reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -1,13 +1,14 @@
-use crate::{BinaryDiff, BinaryDiffReporter};
+use crate::{BinaryDiff, BinaryDiffReporter, ByteRange};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::json::JsonReport;
 use serde::Serialize;
 use std::convert;
+use std::io::Write;
 
 const COMPARES_NAME: &str = "binary";
 
-impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReporter {
+impl<W: Write> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReporter {
     type Error = convert::Infallible;
 
     fn report_unchanged(
@@ -16,8 +17,10 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReport
         diff: &BinaryDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        let report = SingleReport {
+        let report = UnchangedReport {
             size: diff.expected().len(),
+            ignored_ranges: diff.ignored_ranges(),
+            line_endings_differ: diff.line_endings_differ(),
         };
         reporter.record_unchanged(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -37,6 +40,7 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReport
             actual_size,
             added: stat.added,
             deleted: stat.deleted,
+            ignored_ranges: diff.ignored_ranges(),
         };
         reporter.record_modified(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -48,8 +52,12 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReport
         data: &FileLeaf,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
         let report = SingleReport {
             size: data.content.len(),
+            ignored_ranges: &[],
         };
         reporter.record_added(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -61,8 +69,12 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReport
         data: &FileLeaf,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
         let report = SingleReport {
             size: data.content.len(),
+            ignored_ranges: &[],
         };
         reporter.record_deleted(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -70,14 +82,28 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, JsonReport<W>> for BinaryDiffReport
 }
 
 #[derive(Serialize)]
-struct ModifiedReport {
+struct ModifiedReport<'a> {
     expected_size: usize,
     actual_size: usize,
     added: usize,
     deleted: usize,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    ignored_ranges: &'a [ByteRange],
+}
+
+#[derive(Serialize)]
+struct UnchangedReport<'a> {
+    size: usize,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    ignored_ranges: &'a [ByteRange],
+    /// Equal only once line endings are normalized.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    line_endings_differ: bool,
 }
 
 #[derive(Serialize)]
-struct SingleReport {
+struct SingleReport<'a> {
     size: usize,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    ignored_ranges: &'a [ByteRange],
 }
@@ -4,6 +4,8 @@ use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::summary::SummaryReport;
 use std::convert;
 
+const COMPARES_NAME: &str = "binary";
+
 impl<W> DetailReporter<BinaryDiff, FileLeaf, SummaryReport<W>> for BinaryDiffReporter {
     type Error = convert::Infallible;
 
@@ -13,7 +15,7 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, SummaryReport<W>> for BinaryDiffRep
         _diff: &BinaryDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_unchanged();
+        reporter.increment_unchanged(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -23,27 +25,33 @@ impl<W> DetailReporter<BinaryDiff, FileLeaf, SummaryReport<W>> for BinaryDiffRep
         _diff: &BinaryDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_modified();
+        reporter.increment_modified(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_added(
         &self,
         _name: &str,
-        _data: &FileLeaf,
+        data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_added();
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.increment_added(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_deleted(
         &self,
         _name: &str,
-        _data: &FileLeaf,
+        data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_deleted();
+        if !self.fallback.accepts(&data.kind) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.increment_deleted(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 }
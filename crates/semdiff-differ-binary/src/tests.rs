@@ -1,4 +1,5 @@
 use super::*;
+use semdiff_core::fs::diff_bytes;
 
 #[test]
 fn binary_change_stat_counts_added_deleted() {
@@ -9,3 +10,83 @@ fn binary_change_stat_counts_added_deleted() {
     assert_eq!(stat.added, 1);
     assert_eq!(stat.deleted, 1);
 }
+
+#[test]
+fn byte_range_parses_decimal_and_hex() {
+    assert_eq!("8:4".parse::<ByteRange>().unwrap(), ByteRange::new(8, 4));
+    assert_eq!("0x10:0X8".parse::<ByteRange>().unwrap(), ByteRange::new(16, 8));
+    assert!("8".parse::<ByteRange>().is_err());
+    assert!("8:x".parse::<ByteRange>().is_err());
+}
+
+#[test]
+fn binary_fallback_limits_the_kinds_compared() {
+    let supported = |fallback: BinaryFallback, kind: Mime| {
+        let calculator = BinaryDiffCalculator::default().with_fallback(fallback);
+        let result = diff_bytes(&calculator, (kind.clone(), b"ab".to_vec()), (kind, b"ac".to_vec())).unwrap();
+        matches!(result, MayUnsupported::Ok(_))
+    };
+    assert!(supported(BinaryFallback::Always, mime::APPLICATION_OCTET_STREAM));
+    assert!(supported(
+        BinaryFallback::KnownBinary,
+        "application/zip".parse().unwrap()
+    ));
+    assert!(!supported(BinaryFallback::KnownBinary, mime::APPLICATION_OCTET_STREAM));
+    assert!(!supported(BinaryFallback::KnownBinary, mime::TEXT_PLAIN));
+    assert!(!supported(BinaryFallback::Never, "application/zip".parse().unwrap()));
+    assert_eq!(
+        "known-binary".parse::<BinaryFallback>().unwrap(),
+        BinaryFallback::KnownBinary
+    );
+    assert!("sometimes".parse::<BinaryFallback>().is_err());
+}
+
+#[test]
+fn binary_diff_zeroes_ignored_ranges() {
+    let calculator =
+        BinaryDiffCalculator::default().with_ignore_ranges(vec![ByteRange::new(2, 3), ByteRange::new(7, 100)]);
+    let expected = (mime::APPLICATION_OCTET_STREAM, b"abcdefgh".to_vec());
+    let actual = (mime::APPLICATION_OCTET_STREAM, b"abXYZfgX".to_vec());
+
+    let MayUnsupported::Ok(diff) = diff_bytes(&calculator, expected, actual).unwrap() else {
+        panic!("binary diff is always supported");
+    };
+
+    assert!(diff.equal());
+    assert_eq!(diff.expected(), b"ab\0\0\0fg\0");
+    assert_eq!(diff.ignored_ranges(), [ByteRange::new(2, 3), ByteRange::new(7, 100)]);
+}
+
+#[test]
+fn chunk_similarity_survives_insertions() {
+    let expected = b"header\nbody line one\nbody line two\nfooter\n";
+    let actual = b"header\ninserted\nbody line one\nbody line two\nfooter\n";
+    let similarity = chunk_similarity(expected, actual);
+    assert!(similarity > 0.8 && similarity < 1.0, "{similarity}");
+    assert_eq!(chunk_similarity(expected, expected), 1.0);
+    assert_eq!(chunk_similarity(b"aaaa", b"bbbb"), 0.0);
+    assert_eq!(chunk_similarity(b"", b""), 1.0);
+}
+
+#[test]
+fn binary_diff_can_ignore_line_endings() {
+    let diff = |calculator: &BinaryDiffCalculator, expected: &[u8], actual: &[u8]| {
+        let expected = (mime::APPLICATION_OCTET_STREAM, expected.to_vec());
+        let actual = (mime::APPLICATION_OCTET_STREAM, actual.to_vec());
+        let MayUnsupported::Ok(diff) = diff_bytes(calculator, expected, actual).unwrap() else {
+            panic!("binary diff is always supported");
+        };
+        diff
+    };
+    let strict = BinaryDiffCalculator::default();
+    let normalizing = BinaryDiffCalculator::default().with_normalize_line_endings(true);
+
+    assert!(!diff(&strict, b"a\x01\r\nb\r\n", b"a\x01\nb\n").equal());
+    let crlf = diff(&normalizing, b"a\x01\r\nb\r\n", b"a\x01\nb\n");
+    assert!(crlf.equal());
+    assert!(crlf.line_endings_differ());
+    assert!(diff(&normalizing, b"a\rb\r", b"a\r\nb\n").equal());
+    assert!(!diff(&normalizing, b"a\r\n", b"a\n\n").equal());
+    assert!(!diff(&normalizing, b"a\r\nb", b"a\nc").equal());
+    assert!(!diff(&normalizing, b"ab", b"ab").line_endings_differ());
+}
@@ -0,0 +1,444 @@
+//! A tolerant HTML parser that keeps only what affects the document's meaning.
+//!
+//! Comments, the doctype, and whitespace used for formatting are dropped, and attributes are kept
+//! sorted by name, so reformatting a document or reordering attributes does not change its tree.
+//! Broken markup does not fail parsing: unmatched end tags are ignored and unclosed elements end
+//! with their parent, close to how a browser recovers from it. Only elements nested deeper than
+//! [`MAX_DEPTH`] are rejected, since the tree is walked recursively.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Elements nested deeper than this are rejected rather than risking the stack.
+pub(crate) const MAX_DEPTH: usize = 512;
+
+/// Elements that never have content or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose content is text up to their end tag, even if it looks like markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements whose whitespace is significant and kept as written.
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Elements that end an open `<p>`.
+const PARAGRAPH_CLOSERS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "div",
+    "dl",
+    "fieldset",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DomNode {
+    Element(DomElement),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DomElement {
+    pub(crate) name: String,
+    pub(crate) attributes: BTreeMap<String, String>,
+    pub(crate) children: Vec<DomNode>,
+}
+
+impl DomNode {
+    /// What must match for two nodes to be compared with each other: the tag name of an
+    /// element, or `None` for text.
+    pub(crate) fn key(&self) -> Option<&str> {
+        match self {
+            DomNode::Element(element) => Some(&element.name),
+            DomNode::Text(_) => None,
+        }
+    }
+}
+
+/// Parses an HTML document or fragment into its top-level nodes, or `None` if elements are nested
+/// deeper than [`MAX_DEPTH`].
+pub(crate) fn parse(html: &str) -> Option<Vec<DomNode>> {
+    let mut builder = TreeBuilder::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // A doctype, CDATA section, or processing instruction.
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some((tag, after)) = parse_tag(rest) {
+            rest = after;
+            match tag {
+                Tag::Start {
+                    name,
+                    attributes,
+                    self_closing,
+                } => {
+                    let raw_text = !self_closing && RAW_TEXT_ELEMENTS.contains(&name.as_str());
+                    builder.open(name.clone(), attributes, self_closing);
+                    if raw_text {
+                        let (text, after) = split_raw_text(rest, &name);
+                        // Only script and style content is taken literally; the others are RCDATA.
+                        builder.text(if matches!(name.as_str(), "script" | "style") {
+                            text.to_owned()
+                        } else {
+                            decode_entities(text)
+                        });
+                        rest = after;
+                    }
+                }
+                Tag::End { name } => builder.close(&name),
+            }
+        } else {
+            // Text, including a `<` that does not start a tag.
+            let end = match rest.strip_prefix('<') {
+                Some(after) => after.find('<').map_or(rest.len(), |end| end + 1),
+                None => rest.find('<').unwrap_or(rest.len()),
+            };
+            builder.text(decode_entities(&rest[..end]));
+            rest = &rest[end..];
+        }
+        if builder.open.len() > MAX_DEPTH {
+            return None;
+        }
+    }
+    Some(builder.finish())
+}
+
+/// Renders `nodes` with one tag or text line per line, indented by depth, so that a line diff of
+/// two renderings shows their semantic differences.
+pub(crate) fn to_canonical_lines(nodes: &[DomNode]) -> String {
+    let mut out = String::new();
+    write_canonical(&mut out, nodes, 0);
+    out
+}
+
+fn write_canonical(out: &mut String, nodes: &[DomNode], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        match node {
+            DomNode::Element(element) => {
+                write!(out, "{indent}<{}", element.name).unwrap();
+                for (name, value) in &element.attributes {
+                    write!(out, " {name}=\"{}\"", escape(value, true)).unwrap();
+                }
+                out.push('>');
+                if VOID_ELEMENTS.contains(&element.name.as_str()) {
+                    out.push('\n');
+                } else if element.children.is_empty() {
+                    writeln!(out, "</{}>", element.name).unwrap();
+                } else {
+                    out.push('\n');
+                    write_canonical(out, &element.children, depth + 1);
+                    writeln!(out, "{indent}</{}>", element.name).unwrap();
+                }
+            }
+            DomNode::Text(text) => {
+                for line in text.split('\n') {
+                    if line.is_empty() {
+                        out.push('\n');
+                    } else {
+                        writeln!(out, "{indent}{}", escape(line, false)).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+enum Tag {
+    Start {
+        name: String,
+        attributes: BTreeMap<String, String>,
+        self_closing: bool,
+    },
+    End {
+        name: String,
+    },
+}
+
+/// Parses the tag at the start of `input`, returning it and the input after its `>`.
+fn parse_tag(input: &str) -> Option<(Tag, &str)> {
+    let after = input.strip_prefix('<')?;
+    if let Some(after) = after.strip_prefix('/') {
+        let (name, after) = tag_name(after)?;
+        let after = after.find('>').map_or("", |end| &after[end + 1..]);
+        return Some((Tag::End { name }, after));
+    }
+    let (name, mut rest) = tag_name(after)?;
+    let mut attributes = BTreeMap::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if let Some(after) = rest.strip_prefix('>') {
+            return Some((
+                Tag::Start {
+                    name,
+                    attributes,
+                    self_closing,
+                },
+                after,
+            ));
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            self_closing = after.starts_with('>');
+            rest = after;
+            continue;
+        }
+        // An unterminated tag at the end of the input is not a tag.
+        if rest.is_empty() {
+            return None;
+        }
+        self_closing = false;
+        let name_end = rest
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c.is_ascii_whitespace() || matches!(c, '/' | '>' | '='))
+            .map_or(rest.len(), |(end, _)| end);
+        let attribute = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+                let (value, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let after = &after[1..];
+                        let end = after.find(quote).unwrap_or(after.len());
+                        (&after[..end], after.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = after;
+                decode_entities(value)
+            }
+            None => String::new(),
+        };
+        // Like in browsers, the first of repeated attributes wins.
+        attributes.entry(attribute).or_insert(value);
+    }
+}
+
+/// Splits off a tag name, which must start with an ASCII letter, and lowercases it.
+fn tag_name(input: &str) -> Option<(String, &str)> {
+    if !input.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let end = input
+        .find(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '>'))
+        .unwrap_or(input.len());
+    Some((input[..end].to_ascii_lowercase(), &input[end..]))
+}
+
+/// Splits the content of a raw text element from the input starting at its end tag.
+fn split_raw_text<'a>(input: &'a str, name: &str) -> (&'a str, &'a str) {
+    // ASCII lowercasing keeps byte offsets intact.
+    let lowercase = input.to_ascii_lowercase();
+    let end_tag = format!("</{name}");
+    let mut offset = 0;
+    while let Some(found) = lowercase[offset..].find(&end_tag) {
+        let start = offset + found;
+        let after = &lowercase[start + end_tag.len()..];
+        if after.is_empty() || after.starts_with(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '>')) {
+            return (&input[..start], &input[start..]);
+        }
+        offset = start + end_tag.len();
+    }
+    (input, "")
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match decode_entity(rest) {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decodes the character reference at the start of `text`, returning it and its length. Only
+/// numeric references and the most common named ones are known.
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    let end = text.bytes().take(32).position(|b| b == b';')?;
+    let body = &text[1..end];
+    let c = match body.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+        None => match body {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            "nbsp" => '\u{a0}',
+            "copy" => '©',
+            "reg" => '®',
+            "hellip" => '…',
+            "ndash" => '–',
+            "mdash" => '—',
+            _ => return None,
+        },
+    };
+    Some((c, end + 1))
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    roots: Vec<DomNode>,
+    open: Vec<DomElement>,
+}
+
+impl TreeBuilder {
+    fn open(&mut self, name: String, attributes: BTreeMap<String, String>, self_closing: bool) {
+        while self
+            .open
+            .last()
+            .is_some_and(|current| is_closed_by(&current.name, &name))
+        {
+            self.pop();
+        }
+        let element = DomElement {
+            name,
+            attributes,
+            children: Vec::new(),
+        };
+        if self_closing || VOID_ELEMENTS.contains(&element.name.as_str()) {
+            self.children().push(DomNode::Element(element));
+        } else {
+            self.open.push(element);
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if let Some(index) = self.open.iter().rposition(|element| element.name == name) {
+            while self.open.len() > index {
+                self.pop();
+            }
+        }
+    }
+
+    fn text(&mut self, text: String) {
+        let children = self.children();
+        match children.last_mut() {
+            Some(DomNode::Text(previous)) => previous.push_str(&text),
+            _ => children.push(DomNode::Text(text)),
+        }
+    }
+
+    fn pop(&mut self) {
+        let element = self.open.pop().unwrap();
+        self.children().push(DomNode::Element(element));
+    }
+
+    fn children(&mut self) -> &mut Vec<DomNode> {
+        match self.open.last_mut() {
+            Some(parent) => &mut parent.children,
+            None => &mut self.roots,
+        }
+    }
+
+    fn finish(mut self) -> Vec<DomNode> {
+        while !self.open.is_empty() {
+            self.pop();
+        }
+        normalize_whitespace(&mut self.roots, false);
+        self.roots
+    }
+}
+
+/// Whether an open `current` element ends where an `opening` element starts, such as a `<li>`
+/// at the next `<li>`.
+fn is_closed_by(current: &str, opening: &str) -> bool {
+    match current {
+        "p" => PARAGRAPH_CLOSERS.contains(&opening),
+        "li" => opening == "li",
+        "dt" | "dd" => matches!(opening, "dt" | "dd"),
+        "option" => matches!(opening, "option" | "optgroup"),
+        "td" | "th" => matches!(opening, "td" | "th" | "tr"),
+        "tr" => opening == "tr",
+        _ => false,
+    }
+}
+
+/// Collapses runs of whitespace in text to a single space and drops text that is only
+/// whitespace, except inside preformatted elements.
+fn normalize_whitespace(nodes: &mut Vec<DomNode>, preformatted: bool) {
+    for node in nodes.iter_mut() {
+        match node {
+            DomNode::Element(element) => {
+                // A newline right after `<pre>` or `<textarea>` is not part of the content.
+                if matches!(element.name.as_str(), "pre" | "textarea")
+                    && let Some(DomNode::Text(text)) = element.children.first_mut()
+                    && let Some(rest) = text.strip_prefix('\n')
+                {
+                    *text = rest.to_owned();
+                }
+                let preformatted = preformatted || PREFORMATTED_ELEMENTS.contains(&element.name.as_str());
+                normalize_whitespace(&mut element.children, preformatted);
+            }
+            DomNode::Text(text) if !preformatted => {
+                *text = text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+            }
+            DomNode::Text(_) => {}
+        }
+    }
+    nodes.retain(|node| !matches!(node, DomNode::Text(text) if text.is_empty()));
+}
@@ -0,0 +1,291 @@
+use crate::dom::{DomElement, DomNode};
+use mime::Mime;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use serde::Serialize;
+use similar::{Algorithm, DiffOp, TextDiffConfig};
+use std::collections::{BTreeSet, HashMap};
+use std::{convert, fmt};
+
+mod dom;
+pub mod report_collect;
+pub mod report_html;
+pub mod report_json;
+pub mod report_summary;
+
+#[cfg(test)]
+mod tests;
+
+pub struct DomDiffReporter;
+
+#[derive(Debug)]
+pub struct DomDiff {
+    /// Both documents rendered by [`dom::to_canonical_lines`].
+    expected: String,
+    actual: String,
+    changes: Vec<DomChange>,
+}
+
+impl Diff for DomDiff {
+    fn equal(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn magnitude(&self) -> Option<f64> {
+        Some(1.0 - f64::from(self.diff().ratio()))
+    }
+}
+
+impl DomDiff {
+    fn diff(&self) -> similar::TextDiff<'_, '_, str> {
+        TextDiffConfig::default()
+            .algorithm(Algorithm::Patience)
+            .diff_lines(&self.expected, &self.actual)
+    }
+
+    fn changes(&self) -> &[DomChange] {
+        &self.changes
+    }
+}
+
+/// One semantic difference between two documents. `path` locates the node like an XPath, such as
+/// `/html/body/div[2]/text()`, with an index only where siblings share a name; for added nodes it
+/// is the path in `actual`, otherwise the path in `expected`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DomChange {
+    NodeAdded {
+        path: String,
+    },
+    NodeRemoved {
+        path: String,
+    },
+    AttributeAdded {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeRemoved {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeChanged {
+        path: String,
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    TextChanged {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for DomChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomChange::NodeAdded { path } => write!(f, "{path} added"),
+            DomChange::NodeRemoved { path } => write!(f, "{path} removed"),
+            DomChange::AttributeAdded { path, name, value } => write!(f, "{path}/@{name} added: {value:?}"),
+            DomChange::AttributeRemoved { path, name, value } => write!(f, "{path}/@{name} removed: {value:?}"),
+            DomChange::AttributeChanged {
+                path,
+                name,
+                expected,
+                actual,
+            } => write!(f, "{path}/@{name} changed: {expected:?} → {actual:?}"),
+            DomChange::TextChanged { path, expected, actual } => {
+                write!(f, "{path} changed: {expected:?} → {actual:?}")
+            }
+        }
+    }
+}
+
+/// Compares HTML documents by their element tree: attribute order, formatting whitespace, and
+/// comments are ignored, while text inside `<pre>`, `<textarea>`, `<script>`, and `<style>` is
+/// compared as written. Documents with elements nested more than 512 deep are unsupported.
+#[derive(Debug, Default)]
+pub struct DomDiffCalculator;
+
+impl DiffCalculator<FileLeaf> for DomDiffCalculator {
+    type Error = convert::Infallible;
+    type Diff = DomDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: FileLeaf,
+        actual: FileLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        let (Some(expected), Some(actual)) = (
+            parse_html(&expected.kind, &expected.content),
+            parse_html(&actual.kind, &actual.content),
+        ) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let mut changes = Vec::new();
+        compare_nodes("", &expected, &actual, &mut changes);
+        Ok(MayUnsupported::Ok(DomDiff {
+            expected: dom::to_canonical_lines(&expected),
+            actual: dom::to_canonical_lines(&actual),
+            changes,
+        }))
+    }
+}
+
+const HTML_MIMES: [&str; 2] = ["text/html", "application/xhtml+xml"];
+
+fn is_html_mime(kind: &Mime) -> bool {
+    HTML_MIMES.contains(&kind.essence_str())
+}
+
+/// Kinds that are parsed as HTML documents.
+pub fn supported_kinds() -> Vec<&'static str> {
+    HTML_MIMES.to_vec()
+}
+
+fn is_html_file(kind: &Mime, content: &[u8]) -> bool {
+    is_html_mime(kind) && str::from_utf8(content).is_ok()
+}
+
+fn parse_html(kind: &Mime, content: &[u8]) -> Option<Vec<DomNode>> {
+    if !is_html_mime(kind) {
+        return None;
+    }
+    let html = str::from_utf8(content).ok()?;
+    dom::parse(html.strip_prefix('\u{feff}').unwrap_or(html))
+}
+
+/// Renders a document for display in reports, or `None` if it is not HTML.
+fn canonical_html(kind: &Mime, content: &[u8]) -> Option<String> {
+    parse_html(kind, content).map(|nodes| dom::to_canonical_lines(&nodes))
+}
+
+/// Pairs up the children of two nodes by tag name (or as text) in document order, and records
+/// how each pair differs along with the children left without a partner.
+fn compare_nodes(parent: &str, expected: &[DomNode], actual: &[DomNode], changes: &mut Vec<DomChange>) {
+    let expected_paths = child_paths(parent, expected);
+    let actual_paths = child_paths(parent, actual);
+    let expected_keys = expected.iter().map(DomNode::key).collect::<Vec<_>>();
+    let actual_keys = actual.iter().map(DomNode::key).collect::<Vec<_>>();
+    for op in similar::capture_diff_slices(Algorithm::Myers, &expected_keys, &actual_keys) {
+        match op {
+            DiffOp::Equal {
+                old_index,
+                new_index,
+                len,
+            } => {
+                for offset in 0..len {
+                    compare_node(
+                        &expected_paths[old_index + offset],
+                        &expected[old_index + offset],
+                        &actual[new_index + offset],
+                        changes,
+                    );
+                }
+            }
+            DiffOp::Delete { old_index, old_len, .. } => {
+                removed(&expected_paths[old_index..old_index + old_len], changes);
+            }
+            DiffOp::Insert { new_index, new_len, .. } => {
+                added(&actual_paths[new_index..new_index + new_len], changes);
+            }
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => {
+                removed(&expected_paths[old_index..old_index + old_len], changes);
+                added(&actual_paths[new_index..new_index + new_len], changes);
+            }
+        }
+    }
+}
+
+fn compare_node(path: &str, expected: &DomNode, actual: &DomNode, changes: &mut Vec<DomChange>) {
+    match (expected, actual) {
+        (DomNode::Text(expected), DomNode::Text(actual)) => {
+            if expected != actual {
+                changes.push(DomChange::TextChanged {
+                    path: path.to_owned(),
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        (DomNode::Element(expected), DomNode::Element(actual)) => {
+            compare_attributes(path, expected, actual, changes);
+            compare_nodes(path, &expected.children, &actual.children, changes);
+        }
+        _ => unreachable!("only nodes with the same key are paired"),
+    }
+}
+
+fn compare_attributes(path: &str, expected: &DomElement, actual: &DomElement, changes: &mut Vec<DomChange>) {
+    let names = expected
+        .attributes
+        .keys()
+        .chain(actual.attributes.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let change = match (expected.attributes.get(name), actual.attributes.get(name)) {
+            (Some(expected), Some(actual)) if expected == actual => continue,
+            (Some(expected), Some(actual)) => DomChange::AttributeChanged {
+                path: path.to_owned(),
+                name: name.clone(),
+                expected: expected.clone(),
+                actual: actual.clone(),
+            },
+            (Some(value), None) => DomChange::AttributeRemoved {
+                path: path.to_owned(),
+                name: name.clone(),
+                value: value.clone(),
+            },
+            (None, Some(value)) => DomChange::AttributeAdded {
+                path: path.to_owned(),
+                name: name.clone(),
+                value: value.clone(),
+            },
+            (None, None) => unreachable!(),
+        };
+        changes.push(change);
+    }
+}
+
+fn removed(paths: &[String], changes: &mut Vec<DomChange>) {
+    changes.extend(paths.iter().map(|path| DomChange::NodeRemoved { path: path.clone() }));
+}
+
+fn added(paths: &[String], changes: &mut Vec<DomChange>) {
+    changes.extend(paths.iter().map(|path| DomChange::NodeAdded { path: path.clone() }));
+}
+
+/// The path of each child: its name, with a 1-based index among the siblings of that name if there
+/// is more than one.
+fn child_paths(parent: &str, children: &[DomNode]) -> Vec<String> {
+    let step = |node: &DomNode| match node {
+        DomNode::Element(element) => element.name.clone(),
+        DomNode::Text(_) => "text()".to_owned(),
+    };
+    let steps = children.iter().map(step).collect::<Vec<_>>();
+    let mut totals = HashMap::<&str, usize>::new();
+    for step in &steps {
+        *totals.entry(step).or_default() += 1;
+    }
+    let mut positions = HashMap::<&str, usize>::new();
+    steps
+        .iter()
+        .map(|step| {
+            if totals[step.as_str()] > 1 {
+                let position = positions.entry(step).or_default();
+                *position += 1;
+                format!("{parent}/{step}[{position}]")
+            } else {
+                format!("{parent}/{step}")
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+use crate::{DomDiff, DomDiffReporter, is_html_file};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+use std::convert;
+
+const COMPARES_NAME: &str = "dom";
+
+impl DetailReporter<DomDiff, FileLeaf, CollectReport<'_>> for DomDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &DomDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        _diff: &DomDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_modified(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_html_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_html_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{DomChange, DomDiff, DomDiffReporter, canonical_html};
+use askama::Template;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::html::{HtmlReport, HtmlReportError};
+use similar::ChangeTag;
+use thiserror::Error;
+
+const COMPARES_NAME: &str = "dom";
+
+#[derive(Debug, Error)]
+pub enum DomDiffReportError {
+    #[error("html report error: {0}")]
+    HtmlReport(#[from] HtmlReportError),
+}
+
+#[derive(Template)]
+#[template(path = "dom_preview.html")]
+struct DomPreviewTemplate<'a> {
+    body: DomPreviewBody<'a>,
+}
+
+enum DomPreviewBody<'a> {
+    Unchanged { body: &'a str },
+    Modified { diff: &'a similar::TextDiff<'a, 'a, str> },
+    Added { body: &'a str },
+    Deleted { body: &'a str },
+}
+
+impl DomPreviewTemplate<'_> {
+    fn is_equal(change: &similar::Change<&str>) -> bool {
+        matches!(change.tag(), ChangeTag::Equal)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "dom_detail.html")]
+struct DomDetailTemplate<'a> {
+    detail: DomDetailBody<'a>,
+    changes: &'a [DomChange],
+}
+
+enum DomDetailBody<'a> {
+    Diff { lines: &'a similar::TextDiff<'a, 'a, str> },
+    Single { label: &'a str, body: &'a str },
+}
+
+impl DomDetailBody<'_> {
+    fn is_multicolumn(&self) -> bool {
+        matches!(self, DomDetailBody::Diff { .. })
+    }
+}
+
+impl DetailReporter<DomDiff, FileLeaf, HtmlReport> for DomDiffReporter {
+    type Error = DomDiffReportError;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &DomDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let body = diff.expected.as_str();
+        let preview_html = DomPreviewTemplate {
+            body: DomPreviewBody::Unchanged { body },
+        };
+        let detail_html = DomDetailTemplate {
+            detail: DomDetailBody::Single { label: "same", body },
+            changes: &[],
+        };
+        reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &DomDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let diff_view = diff.diff();
+        let preview_html = DomPreviewTemplate {
+            body: DomPreviewBody::Modified { diff: &diff_view },
+        };
+        let detail_html = DomDetailTemplate {
+            detail: DomDetailBody::Diff { lines: &diff_view },
+            changes: diff.changes(),
+        };
+        reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(body) = canonical_html(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = DomPreviewTemplate {
+            body: DomPreviewBody::Added { body: &body },
+        };
+        let detail_html = DomDetailTemplate {
+            detail: DomDetailBody::Single {
+                label: "added",
+                body: &body,
+            },
+            changes: &[],
+        };
+        reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(body) = canonical_html(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = DomPreviewTemplate {
+            body: DomPreviewBody::Deleted { body: &body },
+        };
+        let detail_html = DomDetailTemplate {
+            detail: DomDetailBody::Single {
+                label: "deleted",
+                body: &body,
+            },
+            changes: &[],
+        };
+        reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{DomChange, DomDiff, DomDiffReporter, is_html_file};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::json::JsonReport;
+use serde::Serialize;
+use std::convert;
+use std::io::Write;
+
+const COMPARES_NAME: &str = "dom";
+
+impl<W: Write> DetailReporter<DomDiff, FileLeaf, JsonReport<W>> for DomDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &DomDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &DomDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        #[derive(Debug, Serialize)]
+        struct Report<'a> {
+            changes: &'a [DomChange],
+        }
+        reporter.record_modified(
+            name,
+            COMPARES_NAME,
+            Report {
+                changes: diff.changes(),
+            },
+        );
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_html_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_html_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+}
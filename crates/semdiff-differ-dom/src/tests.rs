@@ -0,0 +1,108 @@
+use super::*;
+
+fn changes(expected: &str, actual: &str) -> Vec<DomChange> {
+    let mut changes = Vec::new();
+    compare_nodes(
+        "",
+        &dom::parse(expected).unwrap(),
+        &dom::parse(actual).unwrap(),
+        &mut changes,
+    );
+    changes
+}
+
+#[test]
+fn dom_ignores_attribute_order_formatting_and_comments() {
+    let expected = r#"<!DOCTYPE html>
+<html>
+  <body>
+    <!-- generated -->
+    <div id="main" class="a b">
+      Hello,
+      <b>world</b>!
+      <img src=logo.png alt="">
+    </div>
+  </body>
+</html>"#;
+    let actual =
+        r#"<html><body><DIV class="a b" id='main'>Hello, <b>world</b>! <img alt src="logo.png"/></DIV></body></html>"#;
+    assert_eq!(changes(expected, actual), []);
+}
+
+#[test]
+fn dom_keeps_whitespace_in_pre_and_handles_void_and_implied_end_tags() {
+    let nodes = dom::parse("<pre>\n  a  b\n</pre><p>one<p>two<br>three<ul><li>x<li>y</ul>").unwrap();
+    assert_eq!(
+        dom::to_canonical_lines(&nodes),
+        "<pre>\n    a  b\n\n</pre>\n<p>\n  one\n</p>\n<p>\n  two\n  <br>\n  three\n</p>\n<ul>\n  <li>\n    x\n  </li>\n  <li>\n    y\n  </li>\n</ul>\n"
+    );
+    assert_eq!(
+        changes("<pre>a  b</pre>", "<pre>a b</pre>"),
+        [DomChange::TextChanged {
+            path: "/pre/text()".to_owned(),
+            expected: "a  b".to_owned(),
+            actual: "a b".to_owned(),
+        }]
+    );
+    assert_eq!(changes("<p>a  b</p>", "<p>a b</p>"), []);
+}
+
+#[test]
+fn dom_reports_node_attribute_and_text_changes_by_path() {
+    let expected = r#"<ul><li class="x">one</li><li>two</li></ul><p title="old">&amp;</p><span></span>"#;
+    let actual = r#"<ul><li>one</li><li data-id="2">zwei</li><li>three</li></ul><p title="new">&</p>"#;
+    assert_eq!(
+        changes(expected, actual),
+        [
+            DomChange::AttributeRemoved {
+                path: "/ul/li[1]".to_owned(),
+                name: "class".to_owned(),
+                value: "x".to_owned(),
+            },
+            DomChange::AttributeAdded {
+                path: "/ul/li[2]".to_owned(),
+                name: "data-id".to_owned(),
+                value: "2".to_owned(),
+            },
+            DomChange::TextChanged {
+                path: "/ul/li[2]/text()".to_owned(),
+                expected: "two".to_owned(),
+                actual: "zwei".to_owned(),
+            },
+            DomChange::NodeAdded {
+                path: "/ul/li[3]".to_owned(),
+            },
+            DomChange::AttributeChanged {
+                path: "/p".to_owned(),
+                name: "title".to_owned(),
+                expected: "old".to_owned(),
+                actual: "new".to_owned(),
+            },
+            DomChange::NodeRemoved {
+                path: "/span".to_owned(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn dom_rejects_elements_nested_too_deeply() {
+    // The 2 MiB stack of a spawned thread, which recursing once per level would overflow.
+    std::thread::Builder::new()
+        .stack_size(2 << 20)
+        .spawn(|| {
+            let html = "<div>".repeat(200_000);
+            assert!(parse_html(&mime::TEXT_HTML, html.as_bytes()).is_none());
+
+            assert!(dom::parse(&"<div>".repeat(dom::MAX_DEPTH + 1)).is_none());
+            let html = "<div>".repeat(dom::MAX_DEPTH);
+            let nodes = dom::parse(&html).unwrap();
+            assert_eq!(dom::to_canonical_lines(&nodes).lines().count(), 2 * dom::MAX_DEPTH - 1);
+            let mut changes = Vec::new();
+            compare_nodes("", &nodes, &nodes, &mut changes);
+            assert_eq!(changes, []);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -1,10 +1,18 @@
-use color::{AlphaColor, Oklab, Srgb};
-use image::{ImageError, ImageFormat, Rgba, RgbaImage};
+use color::{AlphaColor, LinearSrgb, Oklab, Srgb};
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, ImageError, ImageFormat, ImageReader, Rgba, RgbaImage};
+use metadata::{diff_metadata, read_metadata};
 use mime::Mime;
 use semdiff_core::fs::FileLeaf;
-use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use semdiff_core::memory::{MemoryBudget, MemoryReservation};
+use semdiff_core::{Diff, DiffCalculator, FormatRegression, MayUnsupported, ToleranceCheck};
+use std::io::Cursor;
+use std::str::FromStr;
 use thiserror::Error;
 
+mod metadata;
+pub mod report_collect;
 pub mod report_html;
 pub mod report_json;
 pub mod report_summary;
@@ -12,15 +20,29 @@ pub mod report_summary;
 #[cfg(test)]
 mod tests;
 
+pub use metadata::{ImageMetadata, MetadataChange};
+
 pub struct ImageDiffReporter;
 
 #[derive(Debug)]
 pub struct ImageDiff {
     equal: bool,
+    /// Whether the pixels alone are within tolerance, regardless of the metadata.
+    pixels_equal: bool,
+    byte_identical: bool,
     expected: ImageData,
     actual: ImageData,
     diff_stat: ImageDiffStat,
+    /// The `max_diff_ratio` that `diff_stat.diff_ratio` was compared against.
+    max_diff_ratio: f32,
+    /// The `max_diff_region` that `diff_stat.largest_diff_region` was compared against, if any.
+    max_diff_region: Option<u64>,
+    /// The grid that `diff_stat.tiles` were counted on, if any.
+    tile_grid: Option<ImageTiles>,
     diff_image: RgbaImage,
+    /// Differing EXIF tags, or `None` when metadata was not compared.
+    metadata_changes: Option<Vec<MetadataChange>>,
+    _memory: MemoryReservation,
 }
 
 #[derive(Debug, Clone)]
@@ -28,20 +50,136 @@ pub struct ImageData {
     pub mime: Mime,
     pub width: u32,
     pub height: u32,
+    /// The image, or the first frame of an animation.
     pub data: RgbaImage,
+    /// Number of frames; 1 for still images.
+    pub frames: usize,
+    /// Whether the image uses lossy compression, for formats that have both modes (WebP).
+    pub lossy: Option<bool>,
 }
 
 #[derive(Debug)]
 pub struct ImageDiffStat {
+    /// Differing pixels, summed over all frames.
     pub diff_pixels: u64,
     pub total_pixels: u64,
     pub diff_ratio: f32,
+    /// Frames with at least one differing pixel, including frames present on one side only.
+    pub diff_frames: usize,
+    /// Pixels in the largest 4-connected region of differing pixels of any frame; only measured
+    /// when the calculator has a `max_diff_region`.
+    pub largest_diff_region: Option<u64>,
+    /// Differing pixels per tile of the calculator's tile grid, in row-major order, or `None`
+    /// without a grid. For animations, the tiles of the first differing frame, as with the diff
+    /// image.
+    pub tiles: Option<Vec<TileDiff>>,
+}
+
+/// A grid of `columns` × `rows` tiles that images are divided into, to report which regions
+/// changed rather than only how many pixels did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageTiles {
+    columns: u32,
+    rows: u32,
+}
+
+impl ImageTiles {
+    /// Returns `None` unless both `columns` and `rows` are at least 1.
+    pub fn new(columns: u32, rows: u32) -> Option<ImageTiles> {
+        (columns > 0 && rows > 0).then_some(ImageTiles { columns, rows })
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Index of the tile that the pixel at `(x, y)` of a `width` × `height` image falls in.
+    fn index(&self, x: u32, y: u32, width: u32, height: u32) -> usize {
+        let column = u64::from(x) * u64::from(self.columns) / u64::from(width);
+        let row = u64::from(y) * u64::from(self.rows) / u64::from(height);
+        (row * u64::from(self.columns) + column) as usize
+    }
+
+    /// The tiles of a `width` × `height` image, with `diff_pixels` of each in row-major order.
+    /// Tiles differ in size by at most one pixel; with more columns or rows than pixels, some
+    /// are empty.
+    fn tiles(&self, width: u32, height: u32, diff_pixels: &[u64]) -> Vec<TileDiff> {
+        let start =
+            |index: u32, count: u32, len: u32| (u64::from(index) * u64::from(len)).div_ceil(u64::from(count)) as u32;
+        let mut tiles = Vec::with_capacity(diff_pixels.len());
+        for row in 0..self.rows {
+            let (y, end_y) = (start(row, self.rows, height), start(row + 1, self.rows, height));
+            for column in 0..self.columns {
+                let (x, end_x) = (
+                    start(column, self.columns, width),
+                    start(column + 1, self.columns, width),
+                );
+                let diff_pixels = diff_pixels[(row * self.columns + column) as usize];
+                let pixels = u64::from(end_x - x) * u64::from(end_y - y);
+                tiles.push(TileDiff {
+                    column,
+                    row,
+                    x,
+                    y,
+                    width: end_x - x,
+                    height: end_y - y,
+                    diff_pixels,
+                    diff_ratio: if pixels == 0 {
+                        0.0
+                    } else {
+                        diff_pixels as f32 / pixels as f32
+                    },
+                });
+            }
+        }
+        tiles
+    }
+}
+
+/// One tile of an [`ImageTiles`] grid, placed in pixels on the larger of the two images.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileDiff {
+    pub column: u32,
+    pub row: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub diff_pixels: u64,
+    pub diff_ratio: f32,
 }
 
 impl Diff for ImageDiff {
     fn equal(&self) -> bool {
         self.equal
     }
+
+    fn magnitude(&self) -> Option<f64> {
+        Some(f64::from(self.diff_stat.diff_ratio))
+    }
+
+    fn byte_identical(&self) -> Option<bool> {
+        Some(self.byte_identical)
+    }
+
+    fn tolerance_checks(&self) -> Vec<ToleranceCheck> {
+        match (self.diff_stat.largest_diff_region, self.max_diff_region) {
+            (Some(largest_diff_region), Some(max_diff_region)) => vec![ToleranceCheck {
+                metric: "largest_diff_region",
+                measured: largest_diff_region as f64,
+                threshold: max_diff_region as f64,
+            }],
+            _ => vec![ToleranceCheck {
+                metric: "diff_ratio",
+                measured: f64::from(self.diff_stat.diff_ratio),
+                threshold: f64::from(self.max_diff_ratio),
+            }],
+        }
+    }
 }
 
 impl ImageDiff {
@@ -57,6 +195,24 @@ impl ImageDiff {
         &self.diff_stat
     }
 
+    /// The grid of [`ImageDiffStat::tiles`], or `None` when the calculator was built without one.
+    pub fn tile_grid(&self) -> Option<ImageTiles> {
+        self.tile_grid
+    }
+
+    /// Whether the pixels are within tolerance; unlike [`Diff::equal`], this ignores the metadata.
+    pub fn pixels_equal(&self) -> bool {
+        self.pixels_equal
+    }
+
+    /// EXIF tags that differ between the images, or `None` when the calculator was built without
+    /// metadata comparison.
+    pub fn metadata_changes(&self) -> Option<&[MetadataChange]> {
+        self.metadata_changes.as_deref()
+    }
+
+    /// Highlights differing pixels of the first differing frame; empty when the calculator was
+    /// built without visuals.
     pub fn diff_image(&self) -> &RgbaImage {
         &self.diff_image
     }
@@ -68,10 +224,95 @@ pub enum ImageDiffError {
     Image(#[from] ImageError),
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+/// Transfer function the 8-bit channel values of input images are encoded with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageColorSpace {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+#[derive(Debug, Error)]
+#[error("unknown image color space {0:?} (expected \"srgb\" or \"linear\")")]
+pub struct ParseImageColorSpaceError(String);
+
+impl FromStr for ImageColorSpace {
+    type Err = ParseImageColorSpaceError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "srgb" => Ok(ImageColorSpace::Srgb),
+            "linear" => Ok(ImageColorSpace::Linear),
+            _ => Err(ParseImageColorSpaceError(input.to_owned())),
+        }
+    }
+}
+
+/// Weights of the squared OkLab lightness, green-red, and blue-yellow differences and the squared
+/// alpha difference in the distance between two pixels. All weights are 1 by default, which is the
+/// plain Euclidean distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageChannelWeights {
+    pub lightness: f32,
+    pub a: f32,
+    pub b: f32,
+    pub alpha: f32,
+}
+
+impl Default for ImageChannelWeights {
+    fn default() -> Self {
+        ImageChannelWeights {
+            lightness: 1.0,
+            a: 1.0,
+            b: 1.0,
+            alpha: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid image channel weights {0:?} (expected four non-negative numbers L,a,b,alpha)")]
+pub struct ParseImageChannelWeightsError(String);
+
+impl FromStr for ImageChannelWeights {
+    type Err = ParseImageChannelWeightsError;
+
+    /// Parses four comma-separated weights in the order L, a, b, alpha, such as `2,1,1,1`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let weights = input
+            .split(',')
+            .map(|weight| {
+                weight
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|weight| weight.is_finite() && *weight >= 0.0)
+            })
+            .collect::<Option<Vec<_>>>();
+        match weights.as_deref() {
+            Some(&[lightness, a, b, alpha]) => Ok(ImageChannelWeights { lightness, a, b, alpha }),
+            _ => Err(ParseImageChannelWeightsError(input.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ImageDiffCalculator {
     max_distance: f32,
     max_diff_ratio: f32,
+    max_diff_region: Option<u64>,
+    tiles: Option<ImageTiles>,
+    color_space: ImageColorSpace,
+    channel_weights: ImageChannelWeights,
+    render_visuals: bool,
+    compare_metadata: bool,
+    memory_budget: MemoryBudget,
+}
+
+impl Default for ImageDiffCalculator {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
 }
 
 impl ImageDiffCalculator {
@@ -79,25 +320,134 @@ impl ImageDiffCalculator {
         Self {
             max_distance,
             max_diff_ratio,
+            max_diff_region: None,
+            tiles: None,
+            color_space: ImageColorSpace::Srgb,
+            channel_weights: ImageChannelWeights::default(),
+            render_visuals: true,
+            compare_metadata: false,
+            memory_budget: MemoryBudget::unlimited(),
+        }
+    }
+
+    /// Decides equality by the largest connected region of differing pixels instead of their
+    /// ratio: images are equal when no region has more than `max_diff_region` pixels. A small
+    /// solid block of changes then fails even where scattered noise of the same ratio passes.
+    /// Pixels are connected through their edges, not their corners.
+    pub fn with_max_diff_region(mut self, max_diff_region: Option<u64>) -> Self {
+        self.max_diff_region = max_diff_region;
+        self
+    }
+
+    /// Also counts differing pixels per tile of `tiles`, reported in [`ImageDiffStat::tiles`].
+    /// Equality does not depend on the tiles.
+    pub fn with_tiles(mut self, tiles: Option<ImageTiles>) -> Self {
+        self.tiles = tiles;
+        self
+    }
+
+    /// Interprets pixel values as encoded in `color_space` when converting them to OkLab.
+    pub fn with_color_space(mut self, color_space: ImageColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Weighs the differences of the OkLab and alpha channels in the distance between two pixels,
+    /// which is compared with `max_distance`; for example, a higher lightness weight flags changes
+    /// in brightness sooner than changes in hue.
+    pub fn with_channel_weights(mut self, channel_weights: ImageChannelWeights) -> Self {
+        self.channel_weights = channel_weights;
+        self
+    }
+
+    /// Skips building the diff image when `false`, keeping only the stats that decide equality.
+    pub fn with_visuals(mut self, render_visuals: bool) -> Self {
+        self.render_visuals = render_visuals;
+        self
+    }
+
+    /// Also compares the EXIF tags of both images when `true`; images whose tags differ are
+    /// reported as modified even when their pixels are within tolerance.
+    pub fn with_metadata(mut self, compare_metadata: bool) -> Self {
+        self.compare_metadata = compare_metadata;
+        self
+    }
+
+    /// Images whose decoded buffers do not fit in `memory_budget` are left to the next differ.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Reserves memory for both decoded images and the diff image, based on the dimensions
+    /// read from the image headers.
+    fn reserve_memory(
+        &self,
+        expected: (&[u8], ImageFormat),
+        actual: (&[u8], ImageFormat),
+    ) -> Option<MemoryReservation> {
+        let dimensions = |(content, format): (&[u8], ImageFormat)| {
+            ImageReader::with_format(Cursor::new(content), format)
+                .into_dimensions()
+                .ok()
+        };
+        let (expected_width, expected_height) = dimensions(expected)?;
+        let (actual_width, actual_height) = dimensions(actual)?;
+        let rgba_bytes = |width: u32, height: u32| u64::from(width) * u64::from(height) * 4;
+        let mut bytes = rgba_bytes(expected_width, expected_height) + rgba_bytes(actual_width, actual_height);
+        let (max_width, max_height) = (expected_width.max(actual_width), expected_height.max(actual_height));
+        if self.render_visuals {
+            bytes += rgba_bytes(max_width, max_height);
+        }
+        if self.max_diff_region.is_some() {
+            bytes += u64::from(max_width) * u64::from(max_height);
+        }
+        self.memory_budget.try_reserve(bytes)
+    }
+
+    /// Whether `content` decodes as `format`, or `None` when the decoded image would not fit in the
+    /// memory budget.
+    fn decodes(&self, content: &[u8], format: ImageFormat) -> Option<bool> {
+        let Ok((width, height)) = ImageReader::with_format(Cursor::new(content), format).into_dimensions() else {
+            return Some(false);
+        };
+        let mut memory = self
+            .memory_budget
+            .try_reserve(u64::from(width) * u64::from(height) * 4)?;
+        match decode_frames(content, format, &mut memory) {
+            Ok(Some(_)) => Some(true),
+            Ok(None) => None,
+            Err(_) => Some(false),
         }
     }
 
     #[inline(always)]
     fn pixel_diff(&self, expected: Rgba<u8>, actual: Rgba<u8>) -> bool {
-        let (expected_oklab, expected_alpha) = Self::to_oklab_alpha(expected);
-        let (actual_oklab, actual_alpha) = Self::to_oklab_alpha(actual);
+        let (expected_oklab, expected_alpha) = self.to_oklab_alpha(expected);
+        let (actual_oklab, actual_alpha) = self.to_oklab_alpha(actual);
         let delta_l = expected_oklab[0] - actual_oklab[0];
         let delta_a = expected_oklab[1] - actual_oklab[1];
         let delta_b = expected_oklab[2] - actual_oklab[2];
         let delta_alpha = expected_alpha - actual_alpha;
-        let distance = (delta_l * delta_l + delta_a * delta_a + delta_b * delta_b + delta_alpha * delta_alpha).sqrt();
+        let weights = &self.channel_weights;
+        let distance = (weights.lightness * delta_l * delta_l
+            + weights.a * delta_a * delta_a
+            + weights.b * delta_b * delta_b
+            + weights.alpha * delta_alpha * delta_alpha)
+            .sqrt();
         distance > self.max_distance
     }
 
     #[inline(always)]
-    fn to_oklab_alpha(pixel: Rgba<u8>) -> ([f32; 3], f32) {
+    fn to_oklab_alpha(&self, pixel: Rgba<u8>) -> ([f32; 3], f32) {
         let [r, g, b, a] = pixel.0;
-        let oklab = AlphaColor::<Srgb>::from_rgba8(r, g, b, a).convert::<Oklab>();
+        let oklab = match self.color_space {
+            ImageColorSpace::Srgb => AlphaColor::<Srgb>::from_rgba8(r, g, b, a).convert::<Oklab>(),
+            ImageColorSpace::Linear => {
+                let components = [r, g, b, a].map(|channel| f32::from(channel) / 255.0);
+                AlphaColor::<LinearSrgb>::new(components).convert::<Oklab>()
+            }
+        };
         let [l, a, b, alpha] = oklab.components;
         ([l, a, b], alpha)
     }
@@ -111,9 +461,35 @@ impl ImageDiffCalculator {
         let min_height = expected_height.min(actual_height);
         let total_pixels = u64::from(max_width) * u64::from(max_height);
         let mut diff_pixels = 0u64;
-        let mut diff_image = RgbaImage::new(max_width, max_height);
+        let mut diff_mask = if self.max_diff_region.is_some() {
+            vec![false; max_width as usize * max_height as usize]
+        } else {
+            Vec::new()
+        };
+        let mut diff_image = if self.render_visuals {
+            RgbaImage::new(max_width, max_height)
+        } else {
+            RgbaImage::new(0, 0)
+        };
         const DIFF_PIXEL_COLOR: Rgba<u8> = Rgba([255, 255, 255, 180]);
         const SAME_PIXEL_COLOR: Rgba<u8> = Rgba([255, 255, 255, 0]);
+        let mut tile_diff_pixels = match self.tiles {
+            Some(tiles) => vec![0u64; tiles.columns as usize * tiles.rows as usize],
+            None => Vec::new(),
+        };
+        let mut put_pixel = |x: u32, y: u32, pixel| {
+            if self.render_visuals {
+                diff_image.put_pixel(x, y, pixel);
+            }
+            if pixel == DIFF_PIXEL_COLOR {
+                if !diff_mask.is_empty() {
+                    diff_mask[y as usize * max_width as usize + x as usize] = true;
+                }
+                if let Some(tiles) = self.tiles {
+                    tile_diff_pixels[tiles.index(x, y, max_width, max_height)] += 1;
+                }
+            }
+        };
         for y in 0..min_height {
             for x in 0..min_width {
                 let expected_pixel = *expected.get_pixel(x, y);
@@ -124,17 +500,17 @@ impl ImageDiffCalculator {
                 } else {
                     SAME_PIXEL_COLOR
                 };
-                diff_image.put_pixel(x, y, diff_pixel);
+                put_pixel(x, y, diff_pixel);
             }
             for x in min_width..max_width {
                 diff_pixels += 1;
-                diff_image.put_pixel(x, y, DIFF_PIXEL_COLOR);
+                put_pixel(x, y, DIFF_PIXEL_COLOR);
             }
         }
         for y in min_height..max_height {
             for x in 0..max_width {
                 diff_pixels += 1;
-                diff_image.put_pixel(x, y, DIFF_PIXEL_COLOR);
+                put_pixel(x, y, DIFF_PIXEL_COLOR);
             }
         }
         let diff_ratio = if total_pixels == 0 {
@@ -142,15 +518,63 @@ impl ImageDiffCalculator {
         } else {
             diff_pixels as f32 / total_pixels as f32
         };
+        let largest_diff_region = self
+            .max_diff_region
+            .map(|_| largest_region(&mut diff_mask, max_width as usize));
         (
             ImageDiffStat {
                 diff_pixels,
                 total_pixels,
                 diff_ratio,
+                diff_frames: usize::from(diff_pixels > 0),
+                largest_diff_region,
+                tiles: self
+                    .tiles
+                    .map(|tiles| tiles.tiles(max_width, max_height, &tile_diff_pixels)),
             },
             diff_image,
         )
     }
+
+    /// Compares animations frame by frame; frames present on one side only count as entirely
+    /// different.
+    fn compare_frames(&self, expected: &[RgbaImage], actual: &[RgbaImage]) -> (ImageDiffStat, RgbaImage) {
+        let mut stat = ImageDiffStat {
+            diff_pixels: 0,
+            total_pixels: 0,
+            diff_ratio: 0.0,
+            diff_frames: 0,
+            largest_diff_region: self.max_diff_region.map(|_| 0),
+            tiles: None,
+        };
+        let mut first_diff_image = None;
+        let mut first_frame_diff_image = None;
+        for index in 0..expected.len().max(actual.len()) {
+            let (frame_stat, diff_image) = match (expected.get(index), actual.get(index)) {
+                (Some(expected), Some(actual)) => self.compare(expected, actual),
+                (Some(frame), None) | (None, Some(frame)) => self.compare(frame, &RgbaImage::new(0, 0)),
+                (None, None) => unreachable!(),
+            };
+            stat.diff_pixels += frame_stat.diff_pixels;
+            stat.total_pixels += frame_stat.total_pixels;
+            stat.diff_frames += frame_stat.diff_frames;
+            stat.largest_diff_region = stat.largest_diff_region.max(frame_stat.largest_diff_region);
+            if first_diff_image.is_none() && frame_stat.diff_frames > 0 {
+                first_diff_image = Some(diff_image);
+                stat.tiles = frame_stat.tiles;
+            } else if index == 0 {
+                first_frame_diff_image = Some(diff_image);
+                stat.tiles = frame_stat.tiles;
+            }
+        }
+        if stat.total_pixels > 0 {
+            stat.diff_ratio = stat.diff_pixels as f32 / stat.total_pixels as f32;
+        }
+        let diff_image = first_diff_image
+            .or(first_frame_diff_image)
+            .unwrap_or_else(|| RgbaImage::new(0, 0));
+        (stat, diff_image)
+    }
 }
 
 impl DiffCalculator<FileLeaf> for ImageDiffCalculator {
@@ -167,38 +591,166 @@ impl DiffCalculator<FileLeaf> for ImageDiffCalculator {
         else {
             return Ok(MayUnsupported::Unsupported);
         };
-        let expected_image = match image::load_from_memory_with_format(&expected.content, expected_format) {
-            Ok(image) => image,
-            Err(_) => return Ok(MayUnsupported::Unsupported),
-        };
-        let actual_image = match image::load_from_memory_with_format(&actual.content, actual_format) {
-            Ok(image) => image,
-            Err(_) => return Ok(MayUnsupported::Unsupported),
-        };
-        let expected_image = expected_image.into_rgba8();
-        let actual_image = actual_image.into_rgba8();
-        let (diff_stat, diff_image) = self.compare(&expected_image, &actual_image);
-        let expected_data = ImageData {
-            mime: expected.kind,
-            width: expected_image.width(),
-            height: expected_image.height(),
-            data: expected_image,
-        };
-        let actual_data = ImageData {
-            mime: actual.kind,
-            width: actual_image.width(),
-            height: actual_image.height(),
-            data: actual_image,
-        };
-        let equal = diff_stat.diff_ratio <= self.max_diff_ratio;
+        let Some(mut memory) =
+            self.reserve_memory((&expected.content, expected_format), (&actual.content, actual_format))
+        else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let Ok(Some(expected_frames)) = decode_frames(&expected.content, expected_format, &mut memory) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let Ok(Some(actual_frames)) = decode_frames(&actual.content, actual_format, &mut memory) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let (diff_stat, diff_image) = if expected_frames.len() == 1 && actual_frames.len() == 1 {
+            self.compare(&expected_frames[0], &actual_frames[0])
+        } else {
+            self.compare_frames(&expected_frames, &actual_frames)
+        };
+        let byte_identical = expected.content[..] == actual.content[..];
+        let metadata_changes = self.compare_metadata.then(|| {
+            if byte_identical {
+                return Vec::new();
+            }
+            diff_metadata(
+                &read_metadata(&expected.content, expected_format),
+                &read_metadata(&actual.content, actual_format),
+            )
+        });
+        let image_data = |mime: Mime, content: &[u8], format, frames: Vec<RgbaImage>| {
+            let frame_count = frames.len();
+            let data = frames.into_iter().next().unwrap_or_else(|| RgbaImage::new(0, 0));
+            ImageData {
+                mime,
+                width: data.width(),
+                height: data.height(),
+                data,
+                frames: frame_count,
+                lossy: is_lossy(content, format),
+            }
+        };
+        let expected_data = image_data(expected.kind, &expected.content, expected_format, expected_frames);
+        let actual_data = image_data(actual.kind, &actual.content, actual_format, actual_frames);
+        let pixels_equal = match (diff_stat.largest_diff_region, self.max_diff_region) {
+            (Some(largest_diff_region), Some(max_diff_region)) => largest_diff_region <= max_diff_region,
+            _ => diff_stat.diff_ratio <= self.max_diff_ratio,
+        };
+        let equal = pixels_equal && metadata_changes.as_ref().is_none_or(Vec::is_empty);
         Ok(MayUnsupported::Ok(ImageDiff {
             equal,
+            pixels_equal,
+            byte_identical,
             expected: expected_data,
             actual: actual_data,
             diff_stat,
+            max_diff_ratio: self.max_diff_ratio,
+            max_diff_region: self.max_diff_region,
+            tile_grid: self.tiles,
             diff_image,
+            metadata_changes,
+            _memory: memory,
         }))
     }
+
+    fn format_regression(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<FormatRegression> {
+        let (Some(expected_format), Some(actual_format)) = (image_format(&expected.kind), image_format(&actual.kind))
+        else {
+            return None;
+        };
+        FormatRegression::from_parsed(
+            "image",
+            self.decodes(&expected.content, expected_format)?,
+            self.decodes(&actual.content, actual_format)?,
+        )
+    }
+
+    fn unreadable(&self, leaf: &FileLeaf) -> bool {
+        image_format(&leaf.kind).is_some_and(|format| self.decodes(&leaf.content, format) == Some(false))
+    }
+}
+
+/// Pixels in the largest 4-connected region of `true` cells of `mask`, a row-major image `width`
+/// cells wide. Clears the mask as it goes.
+fn largest_region(mask: &mut [bool], width: usize) -> u64 {
+    let mut largest = 0;
+    let mut stack = Vec::new();
+    for start in 0..mask.len() {
+        if !mask[start] {
+            continue;
+        }
+        mask[start] = false;
+        stack.push(start);
+        let mut size = 0;
+        while let Some(index) = stack.pop() {
+            size += 1;
+            let x = index % width;
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then_some(index + 1),
+                index.checked_sub(width),
+                Some(index + width).filter(|&below| below < mask.len()),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if mask[neighbor] {
+                    mask[neighbor] = false;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
+
+/// Decodes every frame of animated GIF and WebP images, or the single frame of other images.
+///
+/// Frames beyond the first are reserved from `memory` as they are decoded; returns `None` when
+/// the budget runs out.
+fn decode_frames(
+    content: &[u8],
+    format: ImageFormat,
+    memory: &mut MemoryReservation,
+) -> Result<Option<Vec<RgbaImage>>, ImageError> {
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(content))?.into_frames(),
+        ImageFormat::WebP if is_animated_webp(content) => WebPDecoder::new(Cursor::new(content))?.into_frames(),
+        _ => {
+            return Ok(Some(vec![
+                image::load_from_memory_with_format(content, format)?.into_rgba8(),
+            ]));
+        }
+    };
+    let mut decoded = Vec::new();
+    for frame in frames {
+        let frame = frame?.into_buffer();
+        if !decoded.is_empty() && !memory.try_grow(u64::from(frame.width()) * u64::from(frame.height()) * 4) {
+            return Ok(None);
+        }
+        decoded.push(frame);
+    }
+    Ok(Some(decoded))
+}
+
+fn is_animated_webp(content: &[u8]) -> bool {
+    image_webp::WebPDecoder::new(Cursor::new(content)).is_ok_and(|decoder| decoder.is_animated())
+}
+
+fn is_lossy(content: &[u8], format: ImageFormat) -> Option<bool> {
+    if format != ImageFormat::WebP {
+        return None;
+    }
+    image_webp::WebPDecoder::new(Cursor::new(content))
+        .ok()
+        .map(|mut decoder| decoder.is_lossy())
+}
+
+/// Kinds of the image formats that this build can decode.
+pub fn supported_kinds() -> Vec<&'static str> {
+    ImageFormat::all()
+        .filter(|format| format.reading_enabled())
+        .map(|format| format.to_mime_type())
+        .filter(|mime| mime.starts_with("image/"))
+        .collect()
 }
 
 fn image_format(mime: &Mime) -> Option<ImageFormat> {
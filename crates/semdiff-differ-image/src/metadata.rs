@@ -0,0 +1,251 @@
+//! EXIF metadata of images, compared tag by tag when enabled with
+//! [`crate::ImageDiffCalculator::with_metadata`].
+
+use image::{ImageDecoder, ImageFormat, ImageReader};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Cursor;
+
+/// EXIF fields of an image keyed by tag name, with their values rendered as text.
+pub type ImageMetadata = BTreeMap<String, String>;
+
+/// An EXIF tag whose value differs between the two images; `None` on the side that lacks it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    pub tag: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+/// Pointer from the primary IFD to the Exif IFD.
+const EXIF_IFD_POINTER: u16 = 0x8769;
+/// Pointer from the primary IFD to the GPS IFD.
+const GPS_IFD_POINTER: u16 = 0x8825;
+/// Pointer from the Exif IFD to the interoperability IFD, which only describes the file format.
+const INTEROP_IFD_POINTER: u16 = 0xa005;
+/// Byte arrays longer than this are shown by their length rather than their contents.
+const MAX_SHOWN_BYTES: usize = 16;
+
+/// Reads the EXIF tags of the primary image, with the Exif and GPS sub-IFDs; images without EXIF
+/// data, or whose EXIF data cannot be read, have no tags.
+pub(crate) fn read_metadata(content: &[u8], format: ImageFormat) -> ImageMetadata {
+    ImageReader::with_format(Cursor::new(content), format)
+        .into_decoder()
+        .and_then(|mut decoder| decoder.exif_metadata())
+        .ok()
+        .flatten()
+        .map(|exif| parse_exif(&exif))
+        .unwrap_or_default()
+}
+
+/// Tags that are missing on one side or have different values, ordered by tag name.
+pub(crate) fn diff_metadata(expected: &ImageMetadata, actual: &ImageMetadata) -> Vec<MetadataChange> {
+    let tags = expected.keys().chain(actual.keys()).collect::<BTreeSet<_>>();
+    tags.into_iter()
+        .filter(|&tag| expected.get(tag) != actual.get(tag))
+        .map(|tag| MetadataChange {
+            tag: tag.clone(),
+            expected: expected.get(tag).cloned(),
+            actual: actual.get(tag).cloned(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ifd {
+    Primary,
+    Exif,
+    Gps,
+}
+
+/// Parses a raw EXIF chunk, which is a TIFF header followed by IFDs. The thumbnail in IFD1 is
+/// skipped.
+fn parse_exif(chunk: &[u8]) -> ImageMetadata {
+    let mut metadata = ImageMetadata::new();
+    let tiff = chunk.strip_prefix(b"Exif\0\0").unwrap_or(chunk);
+    let big_endian = match tiff.get(..4) {
+        Some(b"II*\0") => false,
+        Some(b"MM\0*") => true,
+        _ => return metadata,
+    };
+    let reader = TiffReader { data: tiff, big_endian };
+    if let Some(offset) = reader.u32(4) {
+        reader.read_ifd(offset as usize, Ifd::Primary, &mut metadata);
+    }
+    metadata
+}
+
+struct TiffReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl TiffReader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        let bytes = self.data.get(offset..offset.checked_add(N)?)?;
+        let mut array = <[u8; N]>::try_from(bytes).ok()?;
+        if !self.big_endian {
+            array.reverse();
+        }
+        Some(array)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset).map(u16::from_be_bytes)
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        self.bytes(offset).map(u32::from_be_bytes)
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        self.bytes(offset).map(u64::from_be_bytes)
+    }
+
+    fn read_ifd(&self, offset: usize, ifd: Ifd, metadata: &mut ImageMetadata) {
+        let Some(count) = self.u16(offset) else {
+            return;
+        };
+        for index in 0..usize::from(count) {
+            let entry = offset + 2 + 12 * index;
+            let (Some(tag), Some(value_type), Some(value_count)) =
+                (self.u16(entry), self.u16(entry + 2), self.u32(entry + 4))
+            else {
+                return;
+            };
+            match (ifd, tag) {
+                (Ifd::Primary, EXIF_IFD_POINTER) | (Ifd::Primary, GPS_IFD_POINTER) => {
+                    if let Some(sub_offset) = self.u32(entry + 8) {
+                        let sub_ifd = if tag == EXIF_IFD_POINTER { Ifd::Exif } else { Ifd::Gps };
+                        self.read_ifd(sub_offset as usize, sub_ifd, metadata);
+                    }
+                }
+                (Ifd::Exif, INTEROP_IFD_POINTER) => {}
+                _ => {
+                    if let Some(value) = self.read_value(entry, value_type, value_count as usize) {
+                        metadata.insert(tag_name(ifd, tag), value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders the value of the IFD entry at `entry`, or `None` for unknown types and values that
+    /// point outside of the chunk.
+    fn read_value(&self, entry: usize, value_type: u16, count: usize) -> Option<String> {
+        let size = match value_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => return None,
+        };
+        let len = count.checked_mul(size)?;
+        let start = if len <= 4 {
+            entry + 8
+        } else {
+            self.u32(entry + 8)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+        let values = |render: &dyn Fn(usize) -> Option<String>| {
+            (0..count)
+                .map(|index| render(start + index * size))
+                .collect::<Option<Vec<_>>>()
+                .map(|values| values.join(", "))
+        };
+        match value_type {
+            2 => Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').to_owned()),
+            1 | 6 | 7 => Some(render_bytes(bytes)),
+            3 => values(&|offset| self.u16(offset).map(|value| value.to_string())),
+            8 => values(&|offset| self.u16(offset).map(|value| (value as i16).to_string())),
+            4 => values(&|offset| self.u32(offset).map(|value| value.to_string())),
+            9 => values(&|offset| self.u32(offset).map(|value| (value as i32).to_string())),
+            5 => values(&|offset| Some(format!("{}/{}", self.u32(offset)?, self.u32(offset + 4)?))),
+            10 => values(&|offset| Some(format!("{}/{}", self.u32(offset)? as i32, self.u32(offset + 4)? as i32))),
+            11 => values(&|offset| self.u32(offset).map(|value| f32::from_bits(value).to_string())),
+            12 => values(&|offset| self.u64(offset).map(|value| f64::from_bits(value).to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Shows byte arrays as text when they are printable ASCII (like `ExifVersion`), as hex when they
+/// are short, and by their length otherwise.
+fn render_bytes(bytes: &[u8]) -> String {
+    let text = bytes.strip_suffix(b"\0").unwrap_or(bytes);
+    if !text.is_empty() && text.iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ') {
+        String::from_utf8_lossy(text).into_owned()
+    } else if bytes.len() <= MAX_SHOWN_BYTES {
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        format!("{} bytes", bytes.len())
+    }
+}
+
+fn tag_name(ifd: Ifd, tag: u16) -> String {
+    let name = match (ifd, tag) {
+        (Ifd::Gps, 0x0000) => "GPSVersionID",
+        (Ifd::Gps, 0x0001) => "GPSLatitudeRef",
+        (Ifd::Gps, 0x0002) => "GPSLatitude",
+        (Ifd::Gps, 0x0003) => "GPSLongitudeRef",
+        (Ifd::Gps, 0x0004) => "GPSLongitude",
+        (Ifd::Gps, 0x0005) => "GPSAltitudeRef",
+        (Ifd::Gps, 0x0006) => "GPSAltitude",
+        (Ifd::Gps, 0x0007) => "GPSTimeStamp",
+        (Ifd::Gps, 0x0012) => "GPSMapDatum",
+        (Ifd::Gps, 0x001d) => "GPSDateStamp",
+        (Ifd::Gps, _) => return format!("GPSTag0x{tag:04x}"),
+        (_, 0x010e) => "ImageDescription",
+        (_, 0x010f) => "Make",
+        (_, 0x0110) => "Model",
+        (_, 0x0112) => "Orientation",
+        (_, 0x011a) => "XResolution",
+        (_, 0x011b) => "YResolution",
+        (_, 0x0128) => "ResolutionUnit",
+        (_, 0x0131) => "Software",
+        (_, 0x0132) => "DateTime",
+        (_, 0x013b) => "Artist",
+        (_, 0x0213) => "YCbCrPositioning",
+        (_, 0x8298) => "Copyright",
+        (_, 0x829a) => "ExposureTime",
+        (_, 0x829d) => "FNumber",
+        (_, 0x8822) => "ExposureProgram",
+        (_, 0x8827) => "PhotographicSensitivity",
+        (_, 0x9000) => "ExifVersion",
+        (_, 0x9003) => "DateTimeOriginal",
+        (_, 0x9004) => "DateTimeDigitized",
+        (_, 0x9010) => "OffsetTime",
+        (_, 0x9011) => "OffsetTimeOriginal",
+        (_, 0x9012) => "OffsetTimeDigitized",
+        (_, 0x9101) => "ComponentsConfiguration",
+        (_, 0x9201) => "ShutterSpeedValue",
+        (_, 0x9202) => "ApertureValue",
+        (_, 0x9204) => "ExposureBiasValue",
+        (_, 0x9207) => "MeteringMode",
+        (_, 0x9209) => "Flash",
+        (_, 0x920a) => "FocalLength",
+        (_, 0x927c) => "MakerNote",
+        (_, 0x9286) => "UserComment",
+        (_, 0x9290) => "SubSecTime",
+        (_, 0x9291) => "SubSecTimeOriginal",
+        (_, 0x9292) => "SubSecTimeDigitized",
+        (_, 0xa000) => "FlashpixVersion",
+        (_, 0xa001) => "ColorSpace",
+        (_, 0xa002) => "PixelXDimension",
+        (_, 0xa003) => "PixelYDimension",
+        (_, 0xa402) => "ExposureMode",
+        (_, 0xa403) => "WhiteBalance",
+        (_, 0xa405) => "FocalLengthIn35mmFilm",
+        (_, 0xa406) => "SceneCaptureType",
+        (_, 0xa420) => "ImageUniqueID",
+        (_, 0xa430) => "CameraOwnerName",
+        (_, 0xa431) => "BodySerialNumber",
+        (_, 0xa433) => "LensMake",
+        (_, 0xa434) => "LensModel",
+        _ => return format!("Tag0x{tag:04x}"),
+    };
+    name.to_owned()
+}
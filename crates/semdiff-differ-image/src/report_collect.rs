@@ -0,0 +1,61 @@
+use crate::{ImageDiff, ImageDiffReporter, image_format};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+use std::convert;
+
+const COMPARES_NAME: &str = "image";
+
+impl DetailReporter<ImageDiff, FileLeaf, CollectReport<'_>> for ImageDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &ImageDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        _diff: &ImageDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_modified(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if image_format(&data.kind)
+            .is_none_or(|format| image::load_from_memory_with_format(&data.content, format).is_err())
+        {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if image_format(&data.kind)
+            .is_none_or(|format| image::load_from_memory_with_format(&data.content, format).is_err())
+        {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
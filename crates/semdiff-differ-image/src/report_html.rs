@@ -1,4 +1,4 @@
-use crate::{ImageDiff, ImageDiffReporter, image_format};
+use crate::{ImageData, ImageDiff, ImageDiffReporter, MetadataChange, image_format, is_lossy};
 use askama::Template;
 use image::{ImageError, ImageFormat, RgbaImage};
 use semdiff_core::fs::FileLeaf;
@@ -37,6 +37,19 @@ enum ImagePreviewBody<'a> {
 #[template(path = "image_detail.html")]
 struct ImageDetailTemplate<'a> {
     detail: ImageDetailBody<'a>,
+    notes: Vec<String>,
+    /// Changed tiles, drawn over the actual image.
+    changed_tiles: Vec<ChangedTile>,
+    metadata_changes: &'a [MetadataChange],
+}
+
+/// A changed tile, placed in percent of the image size.
+struct ChangedTile {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    label: String,
 }
 
 #[derive(Clone)]
@@ -67,6 +80,10 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
         diff: &ImageDiff,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !reporter.lists_unchanged() {
+            reporter.count_unchanged(name);
+            return Ok(MayUnsupported::Ok(()));
+        }
         let detail_image = write_image(reporter, name, "same", &diff.expected().data)?;
         let preview_html = ImagePreviewTemplate {
             body: ImagePreviewBody::Single {
@@ -85,6 +102,9 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
                     height: diff.expected.height,
                 },
             },
+            notes: describe_image("expected", diff.expected()).into_iter().collect(),
+            changed_tiles: Vec::new(),
+            metadata_changes: &[],
         };
         reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -126,6 +146,9 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
                 },
                 diff: diff_image,
             },
+            notes: diff_notes(diff),
+            changed_tiles: changed_tiles(diff),
+            metadata_changes: diff.metadata_changes().unwrap_or_default(),
         };
         reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -144,6 +167,7 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
         let width = image.width();
         let height = image.height();
         let image_path = write_image(reporter, name, "added", &image)?;
+        let lossy = is_lossy(&data.content, format);
         let preview_html = ImagePreviewTemplate {
             body: ImagePreviewBody::Single {
                 image: ImagePreviewImage {
@@ -161,6 +185,12 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
                     height,
                 },
             },
+            notes: lossy
+                .map(|lossy| compression_label(lossy).to_owned())
+                .into_iter()
+                .collect(),
+            changed_tiles: Vec::new(),
+            metadata_changes: &[],
         };
         reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -179,6 +209,7 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
         let width = image.width();
         let height = image.height();
         let image_path = write_image(reporter, name, "deleted", &image)?;
+        let lossy = is_lossy(&data.content, format);
         let preview_html = ImagePreviewTemplate {
             body: ImagePreviewBody::Single {
                 image: ImagePreviewImage {
@@ -196,12 +227,82 @@ impl DetailReporter<ImageDiff, FileLeaf, HtmlReport> for ImageDiffReporter {
                     height,
                 },
             },
+            notes: lossy
+                .map(|lossy| compression_label(lossy).to_owned())
+                .into_iter()
+                .collect(),
+            changed_tiles: Vec::new(),
+            metadata_changes: &[],
         };
         reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
     }
 }
 
+fn compression_label(lossy: bool) -> &'static str {
+    if lossy { "lossy WebP" } else { "lossless WebP" }
+}
+
+fn describe_image(label: &str, image: &ImageData) -> Option<String> {
+    let mut parts = Vec::new();
+    if image.frames > 1 {
+        parts.push(format!("{} frames", image.frames));
+    }
+    if let Some(lossy) = image.lossy {
+        parts.push(compression_label(lossy).to_owned());
+    }
+    (!parts.is_empty()).then(|| format!("{}: {}", label, parts.join(", ")))
+}
+
+fn diff_notes(diff: &ImageDiff) -> Vec<String> {
+    let mut notes = Vec::new();
+    notes.extend(describe_image("expected", diff.expected()));
+    notes.extend(describe_image("actual", diff.actual()));
+    let frames = diff.expected().frames.max(diff.actual().frames);
+    if frames > 1 {
+        notes.push(format!(
+            "{} of {} frames differ; showing the first differing frame",
+            diff.diff_stat().diff_frames,
+            frames
+        ));
+    }
+    if let Some(tiles) = &diff.diff_stat().tiles {
+        let changed = tiles.iter().filter(|tile| tile.diff_pixels > 0).count();
+        notes.push(format!("{changed} of {} tiles changed", tiles.len()));
+    }
+    if let Some(largest_diff_region) = diff.diff_stat().largest_diff_region {
+        notes.push(format!("largest connected diff region: {largest_diff_region} pixels"));
+    }
+    if diff.pixels_equal() {
+        notes.push("pixels match; only metadata differs".to_owned());
+    }
+    notes
+}
+
+fn changed_tiles(diff: &ImageDiff) -> Vec<ChangedTile> {
+    let width = f64::from(diff.expected().width.max(diff.actual().width));
+    let height = f64::from(diff.expected().height.max(diff.actual().height));
+    let Some(tiles) = &diff.diff_stat().tiles else {
+        return Vec::new();
+    };
+    tiles
+        .iter()
+        .filter(|tile| tile.diff_pixels > 0)
+        .map(|tile| ChangedTile {
+            left: f64::from(tile.x) * 100.0 / width,
+            top: f64::from(tile.y) * 100.0 / height,
+            width: f64::from(tile.width) * 100.0 / width,
+            height: f64::from(tile.height) * 100.0 / height,
+            label: format!(
+                "column {}, row {}: {:.2}% of pixels differ",
+                tile.column + 1,
+                tile.row + 1,
+                tile.diff_ratio * 100.0
+            ),
+        })
+        .collect()
+}
+
 fn write_image(reporter: &HtmlReport, name: &str, label: &str, image: &RgbaImage) -> Result<String, HtmlReportError> {
     reporter.write_detail_asset(name, label, "png", |w| match image.write_to(w, ImageFormat::Png) {
         Ok(()) => Ok(()),
@@ -1,9 +1,10 @@
-use crate::{ImageDiff, ImageDiffReporter, image_format};
+use crate::{ImageDiff, ImageDiffReporter, MetadataChange, TileDiff, image_format, is_lossy};
 use image::ImageError;
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::json::JsonReport;
 use serde::Serialize;
+use std::io::Write;
 use thiserror::Error;
 
 const COMPARES_NAME: &str = "image";
@@ -14,7 +15,7 @@ pub enum ImageJsonReportError {
     ImageDecode(#[from] ImageError),
 }
 
-impl<W> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter {
+impl<W: Write> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter {
     type Error = ImageJsonReportError;
 
     fn report_unchanged(
@@ -39,6 +40,30 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter
             actual_width: diff.actual().width,
             actual_height: diff.actual().height,
             diff_pixels: diff.diff_stat().diff_pixels,
+            largest_diff_region: diff.diff_stat().largest_diff_region,
+            tiles: diff
+                .tile_grid()
+                .zip(diff.diff_stat().tiles.as_deref())
+                .map(|(grid, tiles)| TilesReport {
+                    columns: grid.columns(),
+                    rows: grid.rows(),
+                    changed: tiles
+                        .iter()
+                        .filter(|tile| tile.diff_pixels > 0)
+                        .map(TileReport::from)
+                        .collect(),
+                }),
+            frames: (diff.expected().frames > 1 || diff.actual().frames > 1).then(|| FramesReport {
+                expected_frames: diff.expected().frames,
+                actual_frames: diff.actual().frames,
+                diff_frames: diff.diff_stat().diff_frames,
+            }),
+            expected_lossy: diff.expected().lossy,
+            actual_lossy: diff.actual().lossy,
+            pixels_equal: diff.pixels_equal(),
+            metadata_changes: diff
+                .metadata_changes()
+                .map(|changes| changes.iter().map(MetadataChangeReport::from).collect()),
         };
         reporter.record_modified(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -57,6 +82,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter
         let report = SingleReport {
             width: image.width(),
             height: image.height(),
+            lossy: is_lossy(&data.content, format),
         };
         reporter.record_added(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -75,6 +101,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter
         let report = SingleReport {
             width: image.width(),
             height: image.height(),
+            lossy: is_lossy(&data.content, format),
         };
         reporter.record_deleted(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
@@ -82,16 +109,90 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, JsonReport<W>> for ImageDiffReporter
 }
 
 #[derive(Serialize)]
-struct ModifiedReport {
+struct ModifiedReport<'a> {
     expected_width: u32,
     expected_height: u32,
     actual_width: u32,
     actual_height: u32,
     diff_pixels: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_diff_region: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tiles: Option<TilesReport>,
+    #[serde(flatten)]
+    frames: Option<FramesReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_lossy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual_lossy: Option<bool>,
+    pixels_equal: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata_changes: Option<Vec<MetadataChangeReport<'a>>>,
+}
+
+#[derive(Serialize)]
+struct TilesReport {
+    columns: u32,
+    rows: u32,
+    /// Tiles with at least one differing pixel.
+    changed: Vec<TileReport>,
+}
+
+#[derive(Serialize)]
+struct TileReport {
+    column: u32,
+    row: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    diff_pixels: u64,
+    diff_ratio: f32,
+}
+
+impl From<&TileDiff> for TileReport {
+    fn from(tile: &TileDiff) -> Self {
+        TileReport {
+            column: tile.column,
+            row: tile.row,
+            x: tile.x,
+            y: tile.y,
+            width: tile.width,
+            height: tile.height,
+            diff_pixels: tile.diff_pixels,
+            diff_ratio: tile.diff_ratio,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MetadataChangeReport<'a> {
+    tag: &'a str,
+    expected: Option<&'a str>,
+    actual: Option<&'a str>,
+}
+
+impl<'a> From<&'a MetadataChange> for MetadataChangeReport<'a> {
+    fn from(change: &'a MetadataChange) -> Self {
+        MetadataChangeReport {
+            tag: &change.tag,
+            expected: change.expected.as_deref(),
+            actual: change.actual.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FramesReport {
+    expected_frames: usize,
+    actual_frames: usize,
+    diff_frames: usize,
 }
 
 #[derive(Serialize)]
 struct SingleReport {
     width: u32,
     height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lossy: Option<bool>,
 }
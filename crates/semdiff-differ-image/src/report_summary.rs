@@ -4,6 +4,8 @@ use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::summary::SummaryReport;
 use std::convert;
 
+const COMPARES_NAME: &str = "image";
+
 impl<W> DetailReporter<ImageDiff, FileLeaf, SummaryReport<W>> for ImageDiffReporter {
     type Error = convert::Infallible;
 
@@ -13,7 +15,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, SummaryReport<W>> for ImageDiffRepor
         _diff: &ImageDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_unchanged();
+        reporter.increment_unchanged(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -23,7 +25,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, SummaryReport<W>> for ImageDiffRepor
         _diff: &ImageDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_modified();
+        reporter.increment_modified(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -38,7 +40,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, SummaryReport<W>> for ImageDiffRepor
         {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_added();
+        reporter.increment_added(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -53,7 +55,7 @@ impl<W> DetailReporter<ImageDiff, FileLeaf, SummaryReport<W>> for ImageDiffRepor
         {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_deleted();
+        reporter.increment_deleted(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 }
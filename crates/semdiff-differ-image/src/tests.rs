@@ -1,4 +1,5 @@
 use super::*;
+use crate::metadata::read_metadata;
 use image::{Rgba, RgbaImage};
 
 #[test]
@@ -27,3 +28,360 @@ fn compare_counts_diff_pixels_with_alpha() {
     assert_eq!(stat.total_pixels, 1);
     assert!((stat.diff_ratio - 1.0).abs() < 1e-6);
 }
+
+#[test]
+fn compare_without_visuals_keeps_stats() {
+    let calculator = ImageDiffCalculator::new(0.0, 0.0).with_visuals(false);
+    let expected = RgbaImage::new(2, 2);
+    let actual = RgbaImage::new(3, 2);
+    let (stat, diff_image) = calculator.compare(&expected, &actual);
+    assert_eq!(stat.diff_pixels, 2);
+    assert_eq!(stat.total_pixels, 6);
+    assert_eq!(diff_image.dimensions(), (0, 0));
+}
+
+#[test]
+fn compare_measures_largest_connected_diff_region() {
+    let calculator = ImageDiffCalculator::new(0.0, 0.0)
+        .with_visuals(false)
+        .with_max_diff_region(Some(3));
+    let expected = RgbaImage::new(8, 8);
+    let changed = |pixels: &[(u32, u32)]| {
+        let mut actual = RgbaImage::new(8, 8);
+        for &(x, y) in pixels {
+            actual.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        }
+        actual
+    };
+
+    // Four scattered pixels, touching only at corners.
+    let (stat, _) = calculator.compare(&expected, &changed(&[(0, 0), (1, 1), (2, 2), (7, 7)]));
+    assert_eq!(stat.diff_pixels, 4);
+    assert_eq!(stat.largest_diff_region, Some(1));
+
+    // The same number of pixels in one block, plus the column ACTUAL lacks.
+    let (stat, _) = calculator.compare(&expected, &changed(&[(5, 0), (6, 0), (5, 1), (6, 1)]));
+    assert_eq!(stat.largest_diff_region, Some(4));
+    let (stat, _) = calculator.compare(&RgbaImage::new(9, 8), &changed(&[(7, 3)]));
+    assert_eq!(stat.largest_diff_region, Some(9));
+
+    // Without a limit, the regions are not measured.
+    let (stat, _) = ImageDiffCalculator::new(0.0, 0.0).compare(&expected, &changed(&[(0, 0)]));
+    assert_eq!(stat.largest_diff_region, None);
+}
+
+#[test]
+fn compare_counts_diff_pixels_per_tile() {
+    let calculator = ImageDiffCalculator::new(0.0, 0.0).with_tiles(ImageTiles::new(3, 2));
+    let expected = RgbaImage::new(7, 4);
+    let mut actual = RgbaImage::new(7, 4);
+    actual.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+    actual.put_pixel(6, 3, Rgba([255, 255, 255, 255]));
+    actual.put_pixel(5, 2, Rgba([255, 255, 255, 255]));
+    let (stat, _) = calculator.compare(&expected, &actual);
+    let tiles = stat.tiles.unwrap();
+
+    // Columns of 3, 2, and 2 pixels, and rows of 2 pixels.
+    let geometry = tiles
+        .iter()
+        .map(|tile| (tile.x, tile.y, tile.width, tile.height))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        geometry,
+        [
+            (0, 0, 3, 2),
+            (3, 0, 2, 2),
+            (5, 0, 2, 2),
+            (0, 2, 3, 2),
+            (3, 2, 2, 2),
+            (5, 2, 2, 2)
+        ]
+    );
+    let diff_pixels = tiles.iter().map(|tile| tile.diff_pixels).collect::<Vec<_>>();
+    assert_eq!(diff_pixels, [1, 0, 0, 0, 0, 2]);
+    assert!((tiles[5].diff_ratio - 0.5).abs() < 1e-6);
+
+    assert!(ImageTiles::new(0, 2).is_none());
+    assert!(
+        ImageDiffCalculator::new(0.0, 0.0)
+            .compare(&expected, &actual)
+            .0
+            .tiles
+            .is_none()
+    );
+}
+
+#[test]
+fn compare_identical_linear_images_is_equal() {
+    let calculator = ImageDiffCalculator::new(0.0, 0.0).with_color_space(ImageColorSpace::Linear);
+    let mut image = RgbaImage::new(2, 2);
+    image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    image.put_pixel(1, 0, Rgba([1, 2, 3, 255]));
+    image.put_pixel(0, 1, Rgba([128, 64, 32, 200]));
+    image.put_pixel(1, 1, Rgba([255, 255, 255, 0]));
+    let (stat, _diff_image) = calculator.compare(&image, &image.clone());
+    assert_eq!(stat.diff_pixels, 0);
+    assert_eq!(stat.diff_ratio, 0.0);
+}
+
+#[test]
+fn color_space_changes_distance_of_dark_pixels() {
+    // Linear-light values near black are perceptually much further apart than sRGB-encoded ones.
+    let mut expected = RgbaImage::new(1, 1);
+    let mut actual = RgbaImage::new(1, 1);
+    expected.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    actual.put_pixel(0, 0, Rgba([10, 10, 10, 255]));
+    let srgb = ImageDiffCalculator::new(0.2, 0.0);
+    let linear = ImageDiffCalculator::new(0.2, 0.0).with_color_space(ImageColorSpace::Linear);
+    assert_eq!(srgb.compare(&expected, &actual).0.diff_pixels, 0);
+    assert_eq!(linear.compare(&expected, &actual).0.diff_pixels, 1);
+}
+
+#[test]
+fn channel_weights_scale_the_distance_of_each_channel() {
+    // A pure lightness change: black against dark gray.
+    let mut expected = RgbaImage::new(1, 1);
+    let mut actual = RgbaImage::new(1, 1);
+    expected.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    actual.put_pixel(0, 0, Rgba([40, 40, 40, 255]));
+    let diff_pixels = |weights: &str| {
+        let calculator = ImageDiffCalculator::new(0.3, 0.0).with_channel_weights(weights.parse().unwrap());
+        calculator.compare(&expected, &actual).0.diff_pixels
+    };
+    assert_eq!(diff_pixels("1,1,1,1"), 0);
+    assert_eq!(diff_pixels("4,1,1,1"), 1);
+    assert_eq!(diff_pixels("0,1,1,1"), 0);
+
+    assert_eq!(
+        " 2, 0.5,0.5 ,1".parse::<ImageChannelWeights>().unwrap(),
+        ImageChannelWeights {
+            lightness: 2.0,
+            a: 0.5,
+            b: 0.5,
+            alpha: 1.0,
+        }
+    );
+    assert!("1,1,1".parse::<ImageChannelWeights>().is_err());
+    assert!("1,1,1,-1".parse::<ImageChannelWeights>().is_err());
+    assert!("1,1,1,nan".parse::<ImageChannelWeights>().is_err());
+}
+
+#[test]
+fn image_color_space_parses_cli_names() {
+    assert_eq!("srgb".parse::<ImageColorSpace>().unwrap(), ImageColorSpace::Srgb);
+    assert_eq!("linear".parse::<ImageColorSpace>().unwrap(), ImageColorSpace::Linear);
+    assert!("rec2020".parse::<ImageColorSpace>().is_err());
+}
+
+#[test]
+fn reserve_memory_refuses_images_over_budget() {
+    let mut png = Vec::new();
+    RgbaImage::new(4, 4)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let image = (&png[..], ImageFormat::Png);
+
+    let unlimited = ImageDiffCalculator::new(0.0, 0.0);
+    assert_eq!(unlimited.reserve_memory(image, image).unwrap().bytes(), 3 * 4 * 4 * 4);
+
+    let limited = ImageDiffCalculator::new(0.0, 0.0).with_memory_budget(MemoryBudget::new(100));
+    assert!(limited.reserve_memory(image, image).is_none());
+}
+
+#[test]
+fn format_regression_needs_one_side_to_decode() {
+    let mut png = Vec::new();
+    RgbaImage::new(4, 4)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let leaf = |content: &[u8]| FileLeaf::from_bytes("", mime::IMAGE_PNG, content);
+    let (valid, truncated) = (leaf(&png), leaf(&png[..png.len() - 20]));
+
+    let calculator = ImageDiffCalculator::new(0.0, 0.0);
+    let regression = calculator.format_regression(&valid, &truncated).unwrap();
+    assert_eq!(regression.to_string(), "expected parsed as image, actual did not");
+    assert_eq!(calculator.format_regression(&valid, &valid), None);
+    assert_eq!(calculator.format_regression(&truncated, &truncated), None);
+
+    // An image over the memory budget cannot tell either way.
+    let limited = ImageDiffCalculator::new(0.0, 0.0).with_memory_budget(MemoryBudget::new(10));
+    assert_eq!(limited.format_regression(&valid, &truncated), None);
+}
+
+#[test]
+fn unreadable_needs_an_image_kind_that_does_not_decode() {
+    let mut png = Vec::new();
+    RgbaImage::new(4, 4)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let calculator = ImageDiffCalculator::new(0.0, 0.0);
+    assert!(!calculator.unreadable(&FileLeaf::from_bytes("", mime::IMAGE_PNG, png.clone())));
+    assert!(calculator.unreadable(&FileLeaf::from_bytes("", mime::IMAGE_PNG, &png[..png.len() - 20])));
+    assert!(!calculator.unreadable(&FileLeaf::from_bytes("", mime::TEXT_PLAIN, b"not an image")));
+
+    let limited = ImageDiffCalculator::new(0.0, 0.0).with_memory_budget(MemoryBudget::new(10));
+    assert!(!limited.unreadable(&FileLeaf::from_bytes("", mime::IMAGE_PNG, &png[..png.len() - 20])));
+}
+
+fn chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], payload: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn u24(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes()[..3]);
+}
+
+/// Builds an animated WebP from lossless frames of equal size.
+fn animated_webp(frames: &[RgbaImage]) -> Vec<u8> {
+    let (width, height) = frames[0].dimensions();
+    let mut body = b"WEBP".to_vec();
+    let mut vp8x = vec![0x10 | 0x02, 0, 0, 0];
+    u24(&mut vp8x, width - 1);
+    u24(&mut vp8x, height - 1);
+    chunk(&mut body, b"VP8X", &vp8x);
+    chunk(&mut body, b"ANIM", &[0, 0, 0, 0, 0, 0]);
+    for frame in frames {
+        let mut still = Vec::new();
+        frame.write_to(&mut Cursor::new(&mut still), ImageFormat::WebP).unwrap();
+        // A still lossless WebP is `RIFF <size> WEBP VP8L <size> <bitstream>`.
+        let mut anmf = Vec::new();
+        u24(&mut anmf, 0);
+        u24(&mut anmf, 0);
+        u24(&mut anmf, width - 1);
+        u24(&mut anmf, height - 1);
+        u24(&mut anmf, 100);
+        anmf.push(0b10);
+        anmf.extend_from_slice(&still[12..]);
+        chunk(&mut body, b"ANMF", &anmf);
+    }
+    let mut webp = b"RIFF".to_vec();
+    webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    webp.extend_from_slice(&body);
+    webp
+}
+
+#[test]
+fn decode_frames_reads_every_frame_of_animated_webp() {
+    let first = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+    let second = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255]));
+    let webp = animated_webp(&[first.clone(), second.clone()]);
+    let mut memory = MemoryBudget::unlimited().try_reserve(0).unwrap();
+
+    assert!(is_animated_webp(&webp));
+    assert_eq!(is_lossy(&webp, ImageFormat::WebP), Some(false));
+    let frames = decode_frames(&webp, ImageFormat::WebP, &mut memory).unwrap().unwrap();
+    assert_eq!(frames, [first, second]);
+    assert_eq!(memory.bytes(), 2 * 2 * 4);
+
+    let mut limited = MemoryBudget::new(0).try_reserve(0).unwrap();
+    assert!(decode_frames(&webp, ImageFormat::WebP, &mut limited).unwrap().is_none());
+}
+
+#[test]
+fn compare_frames_detects_changes_after_the_first_frame() {
+    let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+    let blue = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255]));
+    let calculator = ImageDiffCalculator::new(0.0, 0.0);
+    let (stat, diff_image) =
+        calculator.compare_frames(&[red.clone(), red.clone(), red.clone()], &[red.clone(), blue.clone()]);
+    assert_eq!(stat.diff_frames, 2);
+    assert_eq!(stat.diff_pixels, 8);
+    assert_eq!(stat.total_pixels, 12);
+    assert_eq!(diff_image.get_pixel(0, 0), &Rgba([255, 255, 255, 180]));
+}
+
+/// Builds a little-endian EXIF chunk with `Make`, `Orientation`, and a GPS IFD holding
+/// `GPSLatitudeRef`.
+fn exif(make: &str, latitude_ref: u8) -> Vec<u8> {
+    fn entry(out: &mut Vec<u8>, tag: u16, value_type: u16, count: u32, value: [u8; 4]) {
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&value_type.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&value);
+    }
+    // The header, IFD0 with 3 entries at 8, the GPS IFD with 1 entry at 50, and `Make` at 68.
+    let make = format!("{make}\0");
+    let mut out = b"II*\0".to_vec();
+    out.extend_from_slice(&8u32.to_le_bytes());
+    out.extend_from_slice(&3u16.to_le_bytes());
+    entry(&mut out, 0x010f, 2, make.len() as u32, 68u32.to_le_bytes());
+    entry(&mut out, 0x0112, 3, 1, 1u32.to_le_bytes());
+    entry(&mut out, 0x8825, 4, 1, 50u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    entry(&mut out, 0x0001, 2, 2, [latitude_ref, 0, 0, 0]);
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(make.as_bytes());
+    out
+}
+
+/// Builds a still lossless WebP carrying `exif` in an `EXIF` chunk.
+fn webp_with_exif(image: &RgbaImage, exif: &[u8]) -> Vec<u8> {
+    let mut still = Vec::new();
+    image.write_to(&mut Cursor::new(&mut still), ImageFormat::WebP).unwrap();
+    let mut body = b"WEBP".to_vec();
+    let mut vp8x = vec![0x10 | 0x08, 0, 0, 0];
+    u24(&mut vp8x, image.width() - 1);
+    u24(&mut vp8x, image.height() - 1);
+    chunk(&mut body, b"VP8X", &vp8x);
+    body.extend_from_slice(&still[12..]);
+    chunk(&mut body, b"EXIF", exif);
+    let mut webp = b"RIFF".to_vec();
+    webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    webp.extend_from_slice(&body);
+    webp
+}
+
+#[test]
+fn metadata_is_compared_separately_from_pixels() {
+    let image = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+    let leaf = |content: &[u8]| FileLeaf::from_bytes("photo.webp", "image/webp".parse().unwrap(), content);
+    let expected = webp_with_exif(&image, &exif("CameraA", b'N'));
+    let actual = webp_with_exif(&image, &exif("CameraB", b'S'));
+
+    let metadata = read_metadata(&expected, ImageFormat::WebP);
+    assert_eq!(metadata["Make"], "CameraA");
+    assert_eq!(metadata["Orientation"], "1");
+    assert_eq!(metadata["GPSLatitudeRef"], "N");
+
+    let MayUnsupported::Ok(diff) = ImageDiffCalculator::new(0.0, 0.0)
+        .with_metadata(true)
+        .diff("photo.webp", leaf(&expected), leaf(&actual))
+        .unwrap()
+    else {
+        panic!("metadata comparison left the images to the next differ");
+    };
+    assert!(!diff.equal());
+    assert!(diff.pixels_equal());
+    assert_eq!(diff.diff_stat().diff_pixels, 0);
+    let change = |tag: &str, expected: &str, actual: &str| MetadataChange {
+        tag: tag.to_owned(),
+        expected: Some(expected.to_owned()),
+        actual: Some(actual.to_owned()),
+    };
+    assert_eq!(
+        diff.metadata_changes().unwrap(),
+        [change("GPSLatitudeRef", "N", "S"), change("Make", "CameraA", "CameraB")]
+    );
+
+    let MayUnsupported::Ok(diff) = ImageDiffCalculator::new(0.0, 0.0)
+        .diff("photo.webp", leaf(&expected), leaf(&actual))
+        .unwrap()
+    else {
+        panic!("pixel comparison left the images to the next differ");
+    };
+    assert!(diff.equal());
+    assert_eq!(diff.metadata_changes(), None);
+}
+
+#[test]
+fn supported_kinds_are_the_decodable_image_formats() {
+    let kinds = supported_kinds();
+    assert!(kinds.contains(&"image/png"));
+    assert!(kinds.iter().all(|kind| image_format(&kind.parse().unwrap()).is_some()));
+}
@@ -0,0 +1,118 @@
+//! Files that hold several JSON documents one after another, such as JSON Lines or a stream of
+//! concatenated values.
+//!
+//! Such a file is parsed into an array of its documents. Two files are then either compared as
+//! those arrays, in order, or, with [`DocumentMatching::Key`], as objects of their documents
+//! keyed by an identity such as `kind` and `metadata.name`, so that documents pair up wherever
+//! they are in either file.
+
+use crate::duplicate_keys;
+use crate::json_pointer::JsonPointerPattern;
+use serde::de::IgnoredAny;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// How the documents of two multi-document files are paired up.
+#[derive(Debug, Clone, Default)]
+pub enum DocumentMatching {
+    /// Documents are compared in the order they appear.
+    #[default]
+    Position,
+    /// Documents are paired by the values at these pointers, joined with `/`. Documents that have
+    /// none of them are paired by their position (`#1`, `#2`, …), and repeated identities by
+    /// their occurrence.
+    Key(Vec<JsonPointerPattern>),
+}
+
+impl DocumentMatching {
+    /// The identity of Kubernetes manifests: `/kind` and `/metadata/name`.
+    pub fn default_key() -> Vec<JsonPointerPattern> {
+        ["/kind", "/metadata/name"]
+            .into_iter()
+            .map(|pointer| pointer.parse().unwrap())
+            .collect()
+    }
+
+    /// Turns the documents of a file into the value that is diffed: an array in
+    /// [`DocumentMatching::Position`], or an object sorted by identity in
+    /// [`DocumentMatching::Key`]. `duplicate_keys` are paths below the array of documents (`$[0]…`)
+    /// and are moved below the identities.
+    pub(crate) fn arrange(&self, documents: Vec<Value>, duplicate_keys: Vec<String>) -> (Value, Vec<String>) {
+        let DocumentMatching::Key(key) = self else {
+            return (Value::Array(documents), duplicate_keys);
+        };
+        let mut occurrences = HashMap::<String, usize>::new();
+        let identities = documents
+            .iter()
+            .enumerate()
+            .map(|(index, document)| {
+                let identity = document_identity(document, key).unwrap_or_else(|| format!("#{}", index + 1));
+                let occurrence = occurrences.entry(identity.clone()).or_default();
+                *occurrence += 1;
+                match occurrence {
+                    1 => identity,
+                    n => format!("{identity} #{n}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        let duplicate_keys = duplicate_keys
+            .into_iter()
+            .map(|path| {
+                let Some((index, rest)) = path
+                    .strip_prefix("$[")
+                    .and_then(|path| path.split_once(']'))
+                    .and_then(|(index, rest)| Some((index.parse::<usize>().ok()?, rest)))
+                else {
+                    return path;
+                };
+                format!("{}{rest}", duplicate_keys::member_path("$", &identities[index]))
+            })
+            .collect();
+        let mut keyed = identities.into_iter().zip(documents).collect::<Vec<_>>();
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        (Value::Object(Map::from_iter(keyed)), duplicate_keys)
+    }
+}
+
+fn document_identity(document: &Value, key: &[JsonPointerPattern]) -> Option<String> {
+    let parts = key
+        .iter()
+        .map(|pointer| match pointer.find(document) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(value) => Some(value.to_string()),
+            None => None,
+        })
+        .collect::<Vec<_>>();
+    if parts.iter().all(Option::is_none) {
+        return None;
+    }
+    Some(
+        parts
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Parses `content` as one or more JSON documents separated by whitespace, returning each
+/// document with the duplicate keys found in it (paths relative to that document). Fails if
+/// anything between or after the documents is not JSON.
+pub(crate) fn from_slice(content: &[u8]) -> serde_json::Result<Vec<(Value, Vec<String>)>> {
+    let mut stream = serde_json::Deserializer::from_slice(content).into_iter::<IgnoredAny>();
+    let mut documents = Vec::new();
+    let mut start = 0;
+    while let Some(document) = stream.next() {
+        document?;
+        let end = stream.byte_offset();
+        documents.push(duplicate_keys::from_slice(&content[start..end])?);
+        start = end;
+    }
+    Ok(documents)
+}
+
+/// Moves the duplicate key `path` of a document (`$…`) below its `index` in the array of
+/// documents (`$[index]…`).
+pub(crate) fn rebase_duplicate_key(path: &str, index: usize) -> String {
+    format!("$[{index}]{}", path.strip_prefix('$').unwrap_or(path))
+}
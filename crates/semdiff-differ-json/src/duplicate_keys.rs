@@ -0,0 +1,119 @@
+//! JSON parsing that records duplicate object keys.
+//!
+//! `serde_json` keeps only the last value for a repeated key, so two documents can compare equal
+//! even though one of them carries values the other does not. Parsing through [`from_slice`]
+//! builds the same [`Value`] while remembering where that happened.
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+use std::fmt::Write;
+
+/// Parses `content` like `serde_json::from_slice::<Value>`, also returning the normalized path
+/// (RFC 9535, such as `$['a'][0]['b']`) of every key that appeared more than once in its object.
+pub(crate) fn from_slice(content: &[u8]) -> serde_json::Result<(Value, Vec<String>)> {
+    let mut deserializer = serde_json::Deserializer::from_slice(content);
+    let mut duplicates = Vec::new();
+    let value = ValueSeed {
+        path: "$",
+        duplicates: &mut duplicates,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok((value, duplicates))
+}
+
+struct ValueSeed<'a> {
+    path: &'a str,
+    duplicates: &'a mut Vec<String>,
+}
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ValueSeed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(value).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        let mut path = String::new();
+        loop {
+            path.clear();
+            write!(path, "{}[{}]", self.path, values.len()).unwrap();
+            let seed = ValueSeed {
+                path: &path,
+                duplicates: &mut *self.duplicates,
+            };
+            match seq.next_element_seed(seed)? {
+                Some(value) => values.push(value),
+                None => return Ok(Value::Array(values)),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let path = member_path(self.path, &key);
+            let value = map.next_value_seed(ValueSeed {
+                path: &path,
+                duplicates: &mut *self.duplicates,
+            })?;
+            if values.insert(key, value).is_some() && !self.duplicates.contains(&path) {
+                self.duplicates.push(path);
+            }
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+pub(crate) fn member_path(parent: &str, key: &str) -> String {
+    let mut path = format!("{parent}['");
+    for c in key.chars() {
+        match c {
+            '\'' => path.push_str("\\'"),
+            '\\' => path.push_str("\\\\"),
+            c if c.is_control() => write!(path, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => path.push(c),
+        }
+    }
+    path.push_str("']");
+    path
+}
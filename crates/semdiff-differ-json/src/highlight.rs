@@ -0,0 +1,112 @@
+use std::fmt;
+use std::fmt::{Display, Write};
+
+/// Renders lines of pretty-printed JSON as HTML, optionally wrapping keys, strings, numbers,
+/// booleans, and `null` in `<span class="json-…">` elements.
+///
+/// The output is already escaped, so templates emit it with `|safe`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct JsonSyntax {
+    highlight: bool,
+}
+
+impl JsonSyntax {
+    pub(crate) fn new(highlight: bool) -> JsonSyntax {
+        JsonSyntax { highlight }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.highlight
+    }
+
+    pub(crate) fn html<T: Display>(&self, text: T) -> impl Display {
+        let highlight = self.highlight;
+        fmt::from_fn(move |f| {
+            if highlight {
+                write_highlighted(f, &text.to_string())
+            } else {
+                write!(HtmlEscape(f), "{text}")
+            }
+        })
+    }
+}
+
+fn write_highlighted(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    let mut rest = line;
+    while let Some(first) = rest.chars().next() {
+        let (len, class) = match first {
+            '"' => {
+                let len = string_len(rest);
+                let class = if rest[len..].trim_start().starts_with(':') {
+                    "json-key"
+                } else {
+                    "json-string"
+                };
+                (len, Some(class))
+            }
+            '-' | '0'..='9' => (
+                rest.find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(rest.len()),
+                Some("json-number"),
+            ),
+            'a'..='z' => {
+                let len = rest.find(|c: char| !c.is_ascii_lowercase()).unwrap_or(rest.len());
+                let class = match &rest[..len] {
+                    "true" | "false" => Some("json-boolean"),
+                    "null" => Some("json-null"),
+                    _ => None,
+                };
+                (len, class)
+            }
+            _ => (first.len_utf8(), None),
+        };
+        let (token, tail) = rest.split_at(len);
+        match class {
+            Some(class) => {
+                write!(f, "<span class=\"{class}\">")?;
+                HtmlEscape(f).write_str(token)?;
+                f.write_str("</span>")?;
+            }
+            None => HtmlEscape(f).write_str(token)?,
+        }
+        rest = tail;
+    }
+    Ok(())
+}
+
+/// Length of the string literal at the start of `input`, including both quotes; the rest of the
+/// line if it is unterminated.
+fn string_len(input: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+struct HtmlEscape<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl Write for HtmlEscape<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut last = 0;
+        for (i, c) in s.char_indices() {
+            let escaped = match c {
+                '&' => "&amp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '"' => "&quot;",
+                '\'' => "&#x27;",
+                _ => continue,
+            };
+            self.0.write_str(&s[last..i])?;
+            self.0.write_str(escaped)?;
+            last = i + 1;
+        }
+        self.0.write_str(&s[last..])
+    }
+}
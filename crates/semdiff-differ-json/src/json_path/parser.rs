@@ -159,6 +159,16 @@ impl From<nom::Err<nom::error::Error<&str>>> for ParseError {
     }
 }
 
+impl JsonPath {
+    /// This path below every document of a file parsed into several documents.
+    pub(crate) fn within_documents(&self) -> JsonPath {
+        let mut segments = Vec::with_capacity(self.segments.len() + 1);
+        segments.push(Segment::Child(vec![Selector::Wildcard]));
+        segments.extend(self.segments.iter().cloned());
+        JsonPath { segments }
+    }
+}
+
 impl FromStr for JsonPath {
     type Err = ParseError;
 
@@ -0,0 +1,159 @@
+//! JSON Pointers (RFC 6901) with `*` segments, which select the arrays that
+//! [`crate::JsonDiffCalculator::with_unordered_paths`] compares regardless of order.
+
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A JSON Pointer such as `/permissions` or `/users/*/roles`. A `*` segment matches any member
+/// name or array index, and the empty pointer is the root value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointerPattern {
+    segments: Vec<PointerSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PointerSegment {
+    Token(String),
+    Wildcard,
+}
+
+/// A step from a value to one of its members or elements.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PointerStep<'a> {
+    Name(&'a str),
+    Index(usize),
+}
+
+impl PointerSegment {
+    pub(crate) fn matches(&self, step: PointerStep<'_>) -> bool {
+        match (self, step) {
+            (PointerSegment::Wildcard, _) => true,
+            (PointerSegment::Token(token), PointerStep::Name(name)) => token == name,
+            // RFC 6901 array indices have no leading zeros, so `01` matches no element.
+            (PointerSegment::Token(token), PointerStep::Index(index)) => *token == index.to_string(),
+        }
+    }
+}
+
+impl JsonPointerPattern {
+    pub(crate) fn segments(&self) -> &[PointerSegment] {
+        &self.segments
+    }
+
+    /// The value this pointer refers to in `value`. A `*` segment refers to nothing.
+    pub(crate) fn find<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.segments.iter().try_fold(value, |value, segment| {
+            let PointerSegment::Token(token) = segment else {
+                return None;
+            };
+            match value {
+                Value::Object(map) => map.get(token),
+                Value::Array(values) => token
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&index| segment.matches(PointerStep::Index(index)))
+                    .and_then(|index| values.get(index)),
+                _ => None,
+            }
+        })
+    }
+
+    /// This pointer below every document of a file parsed into several documents.
+    pub(crate) fn within_documents(&self) -> JsonPointerPattern {
+        let mut segments = Vec::with_capacity(self.segments.len() + 1);
+        segments.push(PointerSegment::Wildcard);
+        segments.extend(self.segments.iter().cloned());
+        JsonPointerPattern { segments }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PointerParseError {
+    #[error("a JSON pointer must be empty or start with '/': {0:?}")]
+    MissingSlash(String),
+    #[error("'~' must be followed by '0' or '1' in JSON pointer {0:?}")]
+    InvalidEscape(String),
+}
+
+impl FromStr for JsonPointerPattern {
+    type Err = PointerParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.is_empty() {
+            return Ok(Self { segments: Vec::new() });
+        }
+        let Some(rest) = input.strip_prefix('/') else {
+            return Err(PointerParseError::MissingSlash(input.to_owned()));
+        };
+        let segments = rest
+            .split('/')
+            .map(|token| {
+                if token == "*" {
+                    return Ok(PointerSegment::Wildcard);
+                }
+                let mut unescaped = String::with_capacity(token.len());
+                let mut chars = token.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '~' => match chars.next() {
+                            Some('0') => unescaped.push('~'),
+                            Some('1') => unescaped.push('/'),
+                            _ => return Err(PointerParseError::InvalidEscape(input.to_owned())),
+                        },
+                        c => unescaped.push(c),
+                    }
+                }
+                Ok(PointerSegment::Token(unescaped))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { segments })
+    }
+}
+
+impl fmt::Display for JsonPointerPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                PointerSegment::Wildcard => f.write_str("/*")?,
+                PointerSegment::Token(token) => write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_patterns_parse_escapes_and_wildcards() {
+        let pattern = "/a~1b/*/m~0n".parse::<JsonPointerPattern>().unwrap();
+        assert_eq!(
+            pattern.segments(),
+            [
+                PointerSegment::Token("a/b".to_owned()),
+                PointerSegment::Wildcard,
+                PointerSegment::Token("m~n".to_owned()),
+            ]
+        );
+        assert_eq!(pattern.to_string(), "/a~1b/*/m~0n");
+        assert!("".parse::<JsonPointerPattern>().unwrap().segments().is_empty());
+        assert!("permissions".parse::<JsonPointerPattern>().is_err());
+        assert!("/a~2".parse::<JsonPointerPattern>().is_err());
+
+        let index = PointerSegment::Token("1".to_owned());
+        assert!(index.matches(PointerStep::Index(1)));
+        assert!(index.matches(PointerStep::Name("1")));
+        assert!(!PointerSegment::Token("01".to_owned()).matches(PointerStep::Index(1)));
+
+        let value = serde_json::json!({"metadata": {"name": "web"}, "ports": [80, 443]});
+        let find = |pointer: &str| pointer.parse::<JsonPointerPattern>().unwrap().find(&value).cloned();
+        assert_eq!(find("/metadata/name"), Some("web".into()));
+        assert_eq!(find("/ports/1"), Some(443.into()));
+        assert_eq!(find("/ports/*"), None);
+        assert_eq!(find("/kind"), None);
+    }
+}
@@ -1,25 +1,67 @@
 use crate::json_path::JsonPath;
 use crate::json_path::eval::{JsonPathMatchState, JsonPathMatcher};
+use crate::json_pointer::{JsonPointerPattern, PointerSegment, PointerStep};
+use crate::shape::ShapeRule;
+use flate2::read::GzDecoder;
 use mime::Mime;
 use semdiff_core::fs::FileLeaf;
-use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use semdiff_core::{Diff, DiffCalculator, FormatRegression, MayUnsupported};
 use serde_json::Value;
 use similar::algorithms::DiffHook;
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::fmt::Display;
+use std::io::Read;
 use std::{convert, fmt, mem};
 
+mod documents;
+mod duplicate_keys;
+mod highlight;
 pub mod json_path;
+pub mod json_pointer;
+pub use documents::DocumentMatching;
+pub mod report_collect;
 pub mod report_html;
 pub mod report_json;
 pub mod report_summary;
+pub mod shape;
 
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct JsonDiffReporter;
+/// Upper bound on the decompressed size of a gzip-wrapped JSON file.
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct JsonDiffReporter {
+    syntax_highlight: bool,
+    compares: &'static str,
+}
+
+impl Default for JsonDiffReporter {
+    fn default() -> Self {
+        JsonDiffReporter {
+            syntax_highlight: false,
+            compares: "json",
+        }
+    }
+}
+
+impl JsonDiffReporter {
+    /// Records entries as compared by `compares` instead of `json`, for differs that decode
+    /// another format into JSON values and report them through this reporter.
+    pub fn with_compares_name(mut self, compares: &'static str) -> Self {
+        self.compares = compares;
+        self
+    }
+
+    /// Colors keys, strings, numbers, booleans, and `null` in the HTML preview and detail views.
+    /// Off by default, since every token becomes its own element.
+    pub fn with_syntax_highlight(mut self, syntax_highlight: bool) -> Self {
+        self.syntax_highlight = syntax_highlight;
+        self
+    }
+}
 
 #[derive(Debug)]
 enum JsonDiffBody {
@@ -30,6 +72,10 @@ enum JsonDiffBody {
 #[derive(Debug)]
 pub struct JsonDiff {
     body: JsonDiffBody,
+    expected_decompressed: bool,
+    actual_decompressed: bool,
+    expected_duplicate_keys: Vec<String>,
+    actual_duplicate_keys: Vec<String>,
 }
 
 impl Diff for JsonDiff {
@@ -42,12 +88,30 @@ impl JsonDiff {
     fn body(&self) -> &JsonDiffBody {
         &self.body
     }
+
+    fn decompressed(&self) -> bool {
+        self.expected_decompressed || self.actual_decompressed
+    }
+
+    fn expected_duplicate_keys(&self) -> &[String] {
+        &self.expected_duplicate_keys
+    }
+
+    fn actual_duplicate_keys(&self) -> &[String] {
+        &self.actual_duplicate_keys
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct JsonDiffCalculator {
     ignore_object_key_order: bool,
     ignore_paths: Vec<JsonPath>,
+    round_decimals: Option<u8>,
+    max_depth: Option<usize>,
+    arrays_as_multisets: bool,
+    unordered_paths: Vec<JsonPointerPattern>,
+    shape_rules: Vec<ShapeRule>,
+    document_matching: DocumentMatching,
 }
 
 impl Default for JsonDiffCalculator {
@@ -61,12 +125,142 @@ impl JsonDiffCalculator {
         Self {
             ignore_object_key_order,
             ignore_paths,
+            round_decimals: None,
+            max_depth: None,
+            arrays_as_multisets: false,
+            unordered_paths: Vec::new(),
+            shape_rules: Vec::new(),
+            document_matching: DocumentMatching::default(),
         }
     }
 
+    /// Rounds every non-integer number on both sides to `decimals` decimal places before
+    /// comparing, so the rounded values are also what the report shows.
+    ///
+    /// Numbers are rounded through `f64`, so with serde_json's `arbitrary_precision` feature
+    /// digits beyond `f64` precision are lost as well.
+    pub fn with_round_decimals(mut self, round_decimals: Option<u8>) -> Self {
+        self.round_decimals = round_decimals;
+        self
+    }
+
+    /// Compares objects and arrays nested more than `max_depth` levels below the root as a whole,
+    /// and shows them collapsed to `{…}` or `[…]`. Ignored paths below that depth are not applied.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Compares arrays whose elements are all scalars as multisets: each element counts as
+    /// unchanged if an equal element is anywhere in the other array, so only the elements left over
+    /// on either side are deleted or added. Arrays holding objects or arrays are diffed in order.
+    pub fn with_arrays_as_multisets(mut self, arrays_as_multisets: bool) -> Self {
+        self.arrays_as_multisets = arrays_as_multisets;
+        self
+    }
+
+    /// Compares the arrays at `unordered_paths` as multisets, like [`Self::with_arrays_as_multisets`]
+    /// but also when they hold objects or arrays, which are then paired only with equal elements.
+    /// Other arrays keep their positional diff. Indices in a path count elements of EXPECTED.
+    pub fn with_unordered_paths(mut self, unordered_paths: Vec<JsonPointerPattern>) -> Self {
+        self.unordered_paths = unordered_paths;
+        self
+    }
+
+    /// Compares the strings at the pointer of each rule by their shape: on each side, a value of
+    /// the rule's shape, such as an ISO 8601 timestamp, is replaced with a placeholder like
+    /// `"<iso8601>"` before comparing, so it passes whatever its value, while a value of another
+    /// shape is still compared and shown as is.
+    pub fn with_shape_rules(mut self, shape_rules: Vec<ShapeRule>) -> Self {
+        self.shape_rules = shape_rules;
+        self
+    }
+
+    /// Pairs up the documents of files that hold several JSON documents, such as JSON Lines, with
+    /// `document_matching`; by default they are compared in order. A file with a single document
+    /// is compared as a stream of one when the other file has several. Ignored and unordered
+    /// paths, and shape rules, apply to each document.
+    pub fn with_document_matching(mut self, document_matching: DocumentMatching) -> Self {
+        self.document_matching = document_matching;
+        self
+    }
+
     pub fn ignore_object_key_order(&self) -> bool {
         self.ignore_object_key_order
     }
+
+    /// Diffs two JSON values with the options of this calculator, for differs that decode
+    /// another format into JSON values.
+    pub fn diff_values(&self, expected: Value, actual: Value) -> JsonDiff {
+        self.diff_parsed(expected, actual, false, false)
+    }
+
+    fn diff_parsed(&self, mut expected: Value, mut actual: Value, modified: bool, documents: bool) -> JsonDiff {
+        if self.ignore_object_key_order {
+            expected.sort_all_objects();
+            actual.sort_all_objects();
+        }
+        if let Some(decimals) = self.round_decimals {
+            round_numbers(&mut expected, decimals);
+            round_numbers(&mut actual, decimals);
+        }
+        for rule in &self.shape_rules {
+            if documents {
+                rule.apply_within_documents(&mut expected);
+                rule.apply_within_documents(&mut actual);
+            } else {
+                rule.apply(&mut expected);
+                rule.apply(&mut actual);
+            }
+        }
+        let diff = if documents {
+            json_diff(
+                &expected,
+                &actual,
+                &self
+                    .ignore_paths
+                    .iter()
+                    .map(JsonPath::within_documents)
+                    .collect::<Vec<_>>(),
+                self.max_depth,
+                self.arrays_as_multisets,
+                &self
+                    .unordered_paths
+                    .iter()
+                    .map(JsonPointerPattern::within_documents)
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            json_diff(
+                &expected,
+                &actual,
+                &self.ignore_paths,
+                self.max_depth,
+                self.arrays_as_multisets,
+                &self.unordered_paths,
+            )
+        };
+        let body = if !modified && diff.iter().all(JsonDiffLine::is_equal_for_result) {
+            let ignored_lines = if diff.iter().any(JsonDiffLine::is_ignored) {
+                diff
+            } else {
+                JsonDiffLines::default()
+            };
+            JsonDiffBody::Equal {
+                body: to_string_pretty_within(&expected, self.max_depth.map(|max_depth| max_depth + 1)),
+                ignored_lines,
+            }
+        } else {
+            JsonDiffBody::Modified(diff)
+        };
+        JsonDiff {
+            body,
+            expected_decompressed: false,
+            actual_decompressed: false,
+            expected_duplicate_keys: Vec::new(),
+            actual_duplicate_keys: Vec::new(),
+        }
+    }
 }
 
 impl DiffCalculator<FileLeaf> for JsonDiffCalculator {
@@ -79,35 +273,122 @@ impl DiffCalculator<FileLeaf> for JsonDiffCalculator {
         expected: FileLeaf,
         actual: FileLeaf,
     ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
-        if !is_json_mime(&expected.kind) || !is_json_mime(&actual.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        let Ok(mut expected) = serde_json::from_slice::<Value>(&expected.content) else {
+        let Some(mut expected) = parse_json(&expected.kind, &expected.content) else {
             return Ok(MayUnsupported::Unsupported);
         };
-        let Ok(mut actual) = serde_json::from_slice::<Value>(&actual.content) else {
+        let Some(mut actual) = parse_json(&actual.kind, &actual.content) else {
             return Ok(MayUnsupported::Unsupported);
         };
-        if self.ignore_object_key_order {
-            expected.sort_all_objects();
-            actual.sort_all_objects();
+        let documents = expected.documents || actual.documents;
+        if documents {
+            expected = expected.into_documents(&self.document_matching);
+            actual = actual.into_documents(&self.document_matching);
         }
-        let diff = json_diff(&expected, &actual, &self.ignore_paths);
-        let body = if diff.iter().all(JsonDiffLine::is_equal_for_result) {
-            let ignored_lines = if diff.iter().any(JsonDiffLine::is_ignored) {
-                diff
-            } else {
-                JsonDiffLines::default()
-            };
-            JsonDiffBody::Equal {
-                body: serde_json::to_string_pretty(&expected).unwrap(),
-                ignored_lines,
+        let ParsedJson {
+            value: expected,
+            decompressed: expected_decompressed,
+            duplicate_keys: expected_duplicate_keys,
+            ..
+        } = expected;
+        let ParsedJson {
+            value: actual,
+            decompressed: actual_decompressed,
+            duplicate_keys: actual_duplicate_keys,
+            ..
+        } = actual;
+        // Values shadowed by a duplicate key are gone after parsing, so differing duplicates are a
+        // modification even when the parsed documents match.
+        let mut result = self.diff_parsed(
+            expected,
+            actual,
+            expected_duplicate_keys != actual_duplicate_keys,
+            documents,
+        );
+        result.expected_decompressed = expected_decompressed;
+        result.actual_decompressed = actual_decompressed;
+        result.expected_duplicate_keys = expected_duplicate_keys;
+        result.actual_duplicate_keys = actual_duplicate_keys;
+        Ok(MayUnsupported::Ok(result))
+    }
+
+    fn format_regression(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<FormatRegression> {
+        let is_json_kind = |kind: &Mime| is_json_mime(kind) || is_gzip_mime(kind);
+        if !is_json_kind(&expected.kind) || !is_json_kind(&actual.kind) {
+            return None;
+        }
+        FormatRegression::from_parsed(
+            "JSON",
+            parse_json(&expected.kind, &expected.content).is_some(),
+            parse_json(&actual.kind, &actual.content).is_some(),
+        )
+    }
+
+    fn unreadable(&self, leaf: &FileLeaf) -> bool {
+        // Any gzip file may hold something other than JSON, so only plain JSON kinds count.
+        is_json_mime(&leaf.kind) && parse_json(&leaf.kind, &leaf.content).is_none()
+    }
+}
+
+/// Rounds every float in `value` to `decimals` decimal places, leaving integers untouched.
+fn round_numbers(value: &mut Value, decimals: u8) {
+    match value {
+        Value::Number(number) => {
+            if number.is_f64()
+                && let Some(float) = number.as_f64()
+                && let Ok(rounded) = format!("{float:.0$}", usize::from(decimals)).parse::<f64>()
+                && let Some(rounded) = serde_json::Number::from_f64(rounded)
+            {
+                *number = rounded;
             }
-        } else {
-            JsonDiffBody::Modified(diff)
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| round_numbers(value, decimals)),
+        Value::Object(map) => map.values_mut().for_each(|value| round_numbers(value, decimals)),
+        Value::Null | Value::Bool(_) | Value::String(_) => {}
+    }
+}
+
+/// Pretty-prints `value` like `serde_json::to_string_pretty`, collapsing non-empty objects and
+/// arrays to `{…}` and `[…]` once `levels` nested containers have been expanded.
+fn to_string_pretty_within(value: &Value, levels: Option<usize>) -> String {
+    fn write(out: &mut String, value: &Value, indent: usize, levels: usize) {
+        let (open, close, members) = match value {
+            Value::Array(values) => ('[', ']', values.iter().map(|value| (None, value)).collect::<Vec<_>>()),
+            Value::Object(map) => ('{', '}', map.iter().map(|(key, value)| (Some(key), value)).collect()),
+            _ => return out.push_str(&serde_json::to_string(value).unwrap()),
         };
-        let result = JsonDiff { body };
-        Ok(MayUnsupported::Ok(result))
+        if members.is_empty() {
+            out.extend([open, close]);
+            return;
+        }
+        if levels == 0 {
+            out.extend([open, '…', close]);
+            return;
+        }
+        out.push(open);
+        for (i, (key, value)) in members.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent + 1));
+            if let Some(key) = key {
+                out.push_str(&serde_json::to_string(key).unwrap());
+                out.push_str(": ");
+            }
+            write(out, value, indent + 1, levels - 1);
+        }
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+        out.push(close);
+    }
+
+    match levels {
+        None => serde_json::to_string_pretty(value).unwrap(),
+        Some(levels) => {
+            let mut out = String::new();
+            write(&mut out, value, 0, levels);
+            out
+        }
     }
 }
 
@@ -123,9 +404,110 @@ fn is_json_mime(kind: &Mime) -> bool {
     kind.essence_str() == "text/json"
 }
 
-fn try_into_json(content: &[u8]) -> Option<String> {
-    let value = serde_json::from_slice::<Value>(content).ok()?;
-    Some(serde_json::to_string_pretty(&value).unwrap())
+const GZIP_MIMES: [&str; 2] = ["application/gzip", "application/x-gzip"];
+
+fn is_gzip_mime(kind: &Mime) -> bool {
+    GZIP_MIMES.contains(&kind.essence_str())
+}
+
+/// Kinds that are parsed as JSON, including the gzip kinds, which are compared as JSON when
+/// they decompress to it.
+pub fn supported_kinds() -> Vec<&'static str> {
+    ["application/json", "text/json", "application/*+json"]
+        .into_iter()
+        .chain(GZIP_MIMES)
+        .collect()
+}
+
+fn decompress_gzip(content: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(content)
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decoded)
+        .ok()?;
+    if decoded.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return None;
+    }
+    Some(decoded)
+}
+
+struct ParsedJson {
+    value: Value,
+    /// The content was gzip-compressed.
+    decompressed: bool,
+    /// Paths of keys that appeared more than once in their object; only the last value is kept.
+    duplicate_keys: Vec<String>,
+    /// The content held several documents, which `value` is an array of.
+    documents: bool,
+}
+
+impl ParsedJson {
+    /// Arranges the documents of this file (a single document counts as one) for comparing them
+    /// with `matching`.
+    fn into_documents(self, matching: &DocumentMatching) -> ParsedJson {
+        let (documents, duplicate_keys) = match self.value {
+            Value::Array(documents) if self.documents => (documents, self.duplicate_keys),
+            value => (
+                vec![value],
+                self.duplicate_keys
+                    .iter()
+                    .map(|path| documents::rebase_duplicate_key(path, 0))
+                    .collect(),
+            ),
+        };
+        let (value, duplicate_keys) = matching.arrange(documents, duplicate_keys);
+        ParsedJson {
+            value,
+            duplicate_keys,
+            documents: true,
+            ..self
+        }
+    }
+}
+
+/// Parses `content` as JSON, unwrapping gzip first when `kind` says so. Content with several
+/// documents one after another, such as JSON Lines, is parsed into an array of them.
+fn parse_json(kind: &Mime, content: &[u8]) -> Option<ParsedJson> {
+    let parse = |content: &[u8]| {
+        if let Ok((value, duplicate_keys)) = duplicate_keys::from_slice(content) {
+            return Some((value, duplicate_keys, false));
+        }
+        let parsed = documents::from_slice(content)
+            .ok()
+            .filter(|documents| documents.len() > 1)?;
+        let mut duplicate_keys = Vec::new();
+        let mut values = Vec::with_capacity(parsed.len());
+        for (index, (document, document_duplicate_keys)) in parsed.into_iter().enumerate() {
+            values.push(document);
+            duplicate_keys.extend(
+                document_duplicate_keys
+                    .iter()
+                    .map(|path| documents::rebase_duplicate_key(path, index)),
+            );
+        }
+        Some((Value::Array(values), duplicate_keys, true))
+    };
+    let (value, duplicate_keys, documents, decompressed) = if is_json_mime(kind) {
+        let (value, duplicate_keys, documents) = parse(content)?;
+        (value, duplicate_keys, documents, false)
+    } else if is_gzip_mime(kind) {
+        let decoded = decompress_gzip(content)?;
+        let (value, duplicate_keys, documents) = parse(&decoded)?;
+        (value, duplicate_keys, documents, true)
+    } else {
+        return None;
+    };
+    Some(ParsedJson {
+        value,
+        decompressed,
+        duplicate_keys,
+        documents,
+    })
+}
+
+fn try_into_json(kind: &Mime, content: &[u8]) -> Option<(String, ParsedJson)> {
+    let parsed = parse_json(kind, content)?;
+    Some((serde_json::to_string_pretty(&parsed.value).unwrap(), parsed))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -248,10 +630,18 @@ struct JsonDiffLines {
 }
 
 impl JsonDiffLines {
-    fn writer(&mut self) -> JsonDiffLineWriter<'_> {
+    fn writer<'a>(
+        &'a mut self,
+        max_depth: Option<usize>,
+        arrays_as_multisets: bool,
+        unordered_paths: &'a [JsonPointerPattern],
+    ) -> JsonDiffLineWriter<'a> {
         JsonDiffLineWriter {
             lines: &mut self.lines,
             indent: 0,
+            max_depth,
+            arrays_as_multisets,
+            unordered_paths: unordered_paths.iter().map(JsonPointerPattern::segments).collect(),
         }
     }
 }
@@ -276,6 +666,13 @@ impl IntoIterator for JsonDiffLines {
 struct JsonDiffLineWriter<'a> {
     lines: &'a mut Vec<JsonDiffLine>,
     indent: usize,
+    /// Containers nested deeper than this are written collapsed and not diffed member by member.
+    max_depth: Option<usize>,
+    /// Arrays of scalars are diffed by [`json_diff`]'s multiset comparison instead of in order.
+    arrays_as_multisets: bool,
+    /// The rest of each unordered path that still matches the path of the value being written;
+    /// an empty rest means that value is an array to diff as a multiset.
+    unordered_paths: Vec<&'a [PointerSegment]>,
 }
 
 #[derive(Clone, Copy)]
@@ -367,9 +764,44 @@ impl JsonDiffLineWriter<'_> {
         JsonDiffLineWriter {
             lines: &mut *self.lines,
             indent: self.indent + 1,
+            max_depth: self.max_depth,
+            arrays_as_multisets: self.arrays_as_multisets,
+            unordered_paths: self.unordered_paths.clone(),
         }
     }
 
+    /// Like [`Self::indent`], for the members or elements of the value reached by `step`.
+    fn indent_into(&mut self, step: PointerStep<'_>) -> JsonDiffLineWriter<'_> {
+        let unordered_paths = self
+            .unordered_paths
+            .iter()
+            .filter_map(|segments| segments.split_first())
+            .filter(|(segment, _)| segment.matches(step))
+            .map(|(_, rest)| rest)
+            .collect();
+        JsonDiffLineWriter {
+            unordered_paths,
+            ..self.indent()
+        }
+    }
+
+    /// Whether the array written at this indent is at one of the unordered paths.
+    fn is_unordered(&self) -> bool {
+        self.unordered_paths.iter().any(|segments| segments.is_empty())
+    }
+
+    /// Whether containers written at this indent are beyond the maximum depth.
+    fn collapses(&self) -> bool {
+        self.max_depth.is_some_and(|max_depth| self.indent > max_depth)
+    }
+
+    fn render(&self, value: &Value) -> String {
+        let levels = self
+            .max_depth
+            .map(|max_depth| (max_depth + 1).saturating_sub(self.indent));
+        to_string_pretty_within(value, levels)
+    }
+
     fn unchanged_display(&mut self, expected: impl Display, actual: impl Display) {
         self.lines.push(JsonDiffLine {
             indent: self.indent,
@@ -402,7 +834,7 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn unchanged_value(&mut self, value: &Value, expected_trailing_comma: bool, actual_trailing_comma: bool) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         self.unchanged_rendered_pair(
             RenderedJson {
                 body: &body,
@@ -424,7 +856,7 @@ impl JsonDiffLineWriter<'_> {
         expected_trailing_comma: bool,
         actual_trailing_comma: bool,
     ) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         let prefix = Self::member_prefix(key);
         self.unchanged_rendered_pair(
             RenderedJson {
@@ -441,7 +873,7 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn added_value(&mut self, value: &Value, trailing_comma: bool) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         self.added_rendered(RenderedJson {
             body: &body,
             prefix: None,
@@ -450,7 +882,7 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn added_member(&mut self, key: &str, value: &Value, trailing_comma: bool) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         let prefix = Self::member_prefix(key);
         self.added_rendered(RenderedJson {
             body: &body,
@@ -460,7 +892,7 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn deleted_value(&mut self, value: &Value, trailing_comma: bool) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         self.deleted_rendered(RenderedJson {
             body: &body,
             prefix: None,
@@ -469,7 +901,7 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn deleted_member(&mut self, key: &str, value: &Value, trailing_comma: bool) {
-        let body = serde_json::to_string_pretty(value).unwrap();
+        let body = self.render(value);
         let prefix = Self::member_prefix(key);
         self.deleted_rendered(RenderedJson {
             body: &body,
@@ -479,10 +911,8 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn ignored_value(&mut self, expected: Option<(&Value, bool)>, actual: Option<(&Value, bool)>) {
-        let expected =
-            expected.map(|(value, trailing_comma)| (serde_json::to_string_pretty(value).unwrap(), trailing_comma));
-        let actual =
-            actual.map(|(value, trailing_comma)| (serde_json::to_string_pretty(value).unwrap(), trailing_comma));
+        let expected = expected.map(|(value, trailing_comma)| (self.render(value), trailing_comma));
+        let actual = actual.map(|(value, trailing_comma)| (self.render(value), trailing_comma));
         self.ignored_rendered_pair(
             expected.as_ref().map(|(body, trailing_comma)| RenderedJson {
                 body,
@@ -498,20 +928,10 @@ impl JsonDiffLineWriter<'_> {
     }
 
     fn ignored_member(&mut self, expected: Option<(&str, &Value, bool)>, actual: Option<(&str, &Value, bool)>) {
-        let expected = expected.map(|(key, value, trailing_comma)| {
-            (
-                serde_json::to_string_pretty(value).unwrap(),
-                Self::member_prefix(key),
-                trailing_comma,
-            )
-        });
-        let actual = actual.map(|(key, value, trailing_comma)| {
-            (
-                serde_json::to_string_pretty(value).unwrap(),
-                Self::member_prefix(key),
-                trailing_comma,
-            )
-        });
+        let expected =
+            expected.map(|(key, value, trailing_comma)| (self.render(value), Self::member_prefix(key), trailing_comma));
+        let actual =
+            actual.map(|(key, value, trailing_comma)| (self.render(value), Self::member_prefix(key), trailing_comma));
         self.ignored_rendered_pair(
             expected.as_ref().map(|(body, prefix, trailing_comma)| RenderedJson {
                 body,
@@ -576,7 +996,14 @@ impl JsonDiffLineWriter<'_> {
     }
 }
 
-fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> JsonDiffLines {
+fn json_diff(
+    expected: &Value,
+    actual: &Value,
+    ignore_paths: &[JsonPath],
+    max_depth: Option<usize>,
+    arrays_as_multisets: bool,
+    unordered_paths: &[JsonPointerPattern],
+) -> JsonDiffLines {
     fn json_array_diff<'stack, 'path, 'value>(
         expected: &'value [Value],
         actual: &'value [Value],
@@ -584,6 +1011,11 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
         actual_state: &mut JsonPathMatchState<'stack, 'path, &'value Value>,
         writer: &mut JsonDiffLineWriter<'_>,
     ) {
+        let is_scalar = |value: &Value| !matches!(value, Value::Array(_) | Value::Object(_));
+        if writer.is_unordered() || writer.arrays_as_multisets && expected.iter().chain(actual).all(is_scalar) {
+            json_multiset_diff(expected, actual, expected_state, actual_state, writer);
+            return;
+        }
         let mut hook = ArrayDiffHook {
             expected,
             actual,
@@ -773,10 +1205,17 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
                                 );
                                 continue;
                             }
+                            if self.writer.collapses() {
+                                drop(expected_state);
+                                drop(actual_state);
+                                self.delete(expected_index, 1, 0)?;
+                                self.insert(0, actual_index, 1)?;
+                                continue;
+                            }
                             match (expected_value, actual_value) {
                                 (Value::Array(expected), Value::Array(actual)) => {
                                     self.writer.unchanged_same("[");
-                                    let mut result = self.writer.indent();
+                                    let mut result = self.writer.indent_into(PointerStep::Index(expected_index));
                                     json_array_diff(
                                         expected,
                                         actual,
@@ -797,7 +1236,7 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
                                 }
                                 (Value::Object(expected), Value::Object(actual)) => {
                                     self.writer.unchanged_same("{");
-                                    let mut result = self.writer.indent();
+                                    let mut result = self.writer.indent_into(PointerStep::Index(expected_index));
                                     json_object_diff(
                                         expected,
                                         actual,
@@ -831,6 +1270,53 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
             }
         }
     }
+    /// Pairs each expected element with an equal actual element wherever it is, keeping duplicates
+    /// apart. Expected elements are written in their order, followed by the unpaired actual ones.
+    fn json_multiset_diff<'stack, 'path, 'value>(
+        expected: &'value [Value],
+        actual: &'value [Value],
+        expected_state: &mut JsonPathMatchState<'stack, 'path, &'value Value>,
+        actual_state: &mut JsonPathMatchState<'stack, 'path, &'value Value>,
+        writer: &mut JsonDiffLineWriter<'_>,
+    ) {
+        let mut unpaired = HashMap::<String, VecDeque<usize>>::new();
+        for (index, value) in actual.iter().enumerate() {
+            unpaired
+                .entry(serde_json::to_string(value).unwrap())
+                .or_default()
+                .push_back(index);
+        }
+        let mut paired = vec![false; actual.len()];
+        // Lines written so far on the actual side, which decides where its trailing commas go.
+        let mut actual_written = 0;
+        for (index, value) in expected.iter().enumerate() {
+            let need_extra_comma_expected = index < expected.len() - 1;
+            let pair = unpaired
+                .get_mut(&serde_json::to_string(value).unwrap())
+                .and_then(VecDeque::pop_front);
+            if let Some(actual_index) = pair {
+                paired[actual_index] = true;
+                actual_written += 1;
+                writer.unchanged_value(value, need_extra_comma_expected, actual_written < actual.len());
+            } else if expected_state.advance_index(index).unwrap().is_match() {
+                writer.ignored_value(Some((value, need_extra_comma_expected)), None);
+            } else {
+                writer.deleted_value(value, need_extra_comma_expected);
+            }
+        }
+        for (index, value) in actual.iter().enumerate() {
+            if paired[index] {
+                continue;
+            }
+            actual_written += 1;
+            let need_extra_comma = actual_written < actual.len();
+            if actual_state.advance_index(index).unwrap().is_match() {
+                writer.ignored_value(None, Some((value, need_extra_comma)));
+            } else {
+                writer.added_value(value, need_extra_comma);
+            }
+        }
+    }
     fn json_object_diff<'stack, 'path, 'value>(
         expected: &'value serde_json::Map<String, Value>,
         actual: &'value serde_json::Map<String, Value>,
@@ -887,6 +1373,18 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
                     );
                     return Ok(());
                 }
+                if self.writer.collapses() {
+                    if expected_v == actual_v {
+                        self.writer
+                            .unchanged_member(k, expected_v, need_extra_comma_expected, need_extra_comma_actual);
+                    } else {
+                        drop(expected_state);
+                        drop(actual_state);
+                        self.delete(old_index, 1, 0)?;
+                        self.insert(0, new_index, 1)?;
+                    }
+                    return Ok(());
+                }
                 match (expected_v, actual_v) {
                     (expected @ Value::Null, actual @ Value::Null)
                     | (expected @ Value::Bool(_), actual @ Value::Bool(_))
@@ -903,7 +1401,7 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
                             quoted_key: &quoted_key,
                             delimiter: '[',
                         });
-                        let mut result = self.writer.indent();
+                        let mut result = self.writer.indent_into(PointerStep::Name(k));
                         json_array_diff(expected, actual, &mut expected_state, &mut actual_state, &mut result);
                         self.writer.unchanged_display(
                             ClosingLine {
@@ -922,7 +1420,7 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
                             quoted_key: &quoted_key,
                             delimiter: '{',
                         });
-                        let mut result = self.writer.indent();
+                        let mut result = self.writer.indent_into(PointerStep::Name(k));
                         json_object_diff(expected, actual, &mut expected_state, &mut actual_state, &mut result);
                         self.writer.unchanged_display(
                             ClosingLine {
@@ -989,7 +1487,7 @@ fn json_diff(expected: &Value, actual: &Value, ignore_paths: &[JsonPath]) -> Jso
     let mut expected_state = expected_matcher.root_state(expected);
     let mut actual_state = actual_matcher.root_state(actual);
     let mut result = JsonDiffLines::default();
-    let mut writer = result.writer();
+    let mut writer = result.writer(max_depth, arrays_as_multisets, unordered_paths);
     if (expected_state.is_match() || actual_state.is_match()) && expected != actual {
         writer.ignored_value(Some((expected, false)), Some((actual, false)));
     } else {
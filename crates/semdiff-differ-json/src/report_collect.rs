@@ -0,0 +1,55 @@
+use crate::{JsonDiff, JsonDiffReporter, parse_json};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+use std::convert;
+
+impl DetailReporter<JsonDiff, FileLeaf, CollectReport<'_>> for JsonDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &JsonDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, self.compares);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        _diff: &JsonDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_modified(name, self.compares);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if parse_json(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, self.compares);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if parse_json(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, self.compares);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
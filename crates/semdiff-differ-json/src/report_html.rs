@@ -1,12 +1,11 @@
-use crate::{ChangeTag, JsonDiff, JsonDiffBody, JsonDiffLine, JsonDiffReporter, is_json_mime, try_into_json};
+use crate::highlight::JsonSyntax;
+use crate::{ChangeTag, JsonDiff, JsonDiffBody, JsonDiffLine, JsonDiffReporter, try_into_json};
 use askama::Template;
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::html::{HtmlReport, HtmlReportError};
 use thiserror::Error;
 
-const COMPARES_NAME: &str = "json";
-
 #[derive(Debug, Error)]
 pub enum JsonDiffReportError {
     #[error("html report error: {0}")]
@@ -17,6 +16,9 @@ pub enum JsonDiffReportError {
 #[template(path = "json_preview.html")]
 struct JsonPreviewTemplate<'a> {
     body: JsonPreviewBody<'a>,
+    syntax: JsonSyntax,
+    /// Number of repeated keys on either side.
+    duplicate_keys: usize,
 }
 
 enum JsonPreviewBody<'a> {
@@ -36,6 +38,10 @@ impl JsonPreviewTemplate<'_> {
 #[template(path = "json_detail.html")]
 struct JsonDetailTemplate<'a> {
     detail: JsonDetailBody<'a>,
+    decompressed: bool,
+    syntax: JsonSyntax,
+    expected_duplicate_keys: &'a [String],
+    actual_duplicate_keys: &'a [String],
 }
 
 enum JsonDetailBody<'a> {
@@ -58,25 +64,36 @@ impl DetailReporter<JsonDiff, FileLeaf, HtmlReport> for JsonDiffReporter {
         diff: &JsonDiff,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        let syntax = JsonSyntax::new(self.syntax_highlight);
         let JsonDiffBody::Equal { body, ignored_lines } = diff.body() else {
             debug_assert!(false, "report_unchanged called with modified diff");
             return Ok(MayUnsupported::Ok(()));
         };
         let preview_html = JsonPreviewTemplate {
             body: JsonPreviewBody::Unchanged { body },
+            syntax,
+            duplicate_keys: diff.expected_duplicate_keys().len() + diff.actual_duplicate_keys().len(),
         };
         let detail_html = if ignored_lines.is_empty() {
             JsonDetailTemplate {
                 detail: JsonDetailBody::Single { label: "same", body },
+                decompressed: diff.decompressed(),
+                syntax,
+                expected_duplicate_keys: diff.expected_duplicate_keys(),
+                actual_duplicate_keys: diff.actual_duplicate_keys(),
             }
         } else {
             JsonDetailTemplate {
                 detail: JsonDetailBody::Diff {
                     lines: &ignored_lines[..],
                 },
+                decompressed: diff.decompressed(),
+                syntax,
+                expected_duplicate_keys: diff.expected_duplicate_keys(),
+                actual_duplicate_keys: diff.actual_duplicate_keys(),
             }
         };
-        reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
+        reporter.record_unchanged(name, self.compares, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -86,17 +103,24 @@ impl DetailReporter<JsonDiff, FileLeaf, HtmlReport> for JsonDiffReporter {
         diff: &JsonDiff,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
+        let syntax = JsonSyntax::new(self.syntax_highlight);
         let JsonDiffBody::Modified(lines) = diff.body() else {
             debug_assert!(false, "report_modified called with equal diff");
             return Ok(MayUnsupported::Ok(()));
         };
         let preview_html = JsonPreviewTemplate {
             body: JsonPreviewBody::Modified { lines: &lines[..] },
+            syntax,
+            duplicate_keys: diff.expected_duplicate_keys().len() + diff.actual_duplicate_keys().len(),
         };
         let detail_html = JsonDetailTemplate {
             detail: JsonDetailBody::Diff { lines: &lines[..] },
+            decompressed: diff.decompressed(),
+            syntax,
+            expected_duplicate_keys: diff.expected_duplicate_keys(),
+            actual_duplicate_keys: diff.actual_duplicate_keys(),
         };
-        reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
+        reporter.record_modified(name, self.compares, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -106,22 +130,26 @@ impl DetailReporter<JsonDiff, FileLeaf, HtmlReport> for JsonDiffReporter {
         data: &FileLeaf,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        let Some(body) = try_into_json(&data.content) else {
+        let syntax = JsonSyntax::new(self.syntax_highlight);
+        let Some((body, parsed)) = try_into_json(&data.kind, &data.content) else {
             return Ok(MayUnsupported::Unsupported);
         };
         let preview_html = JsonPreviewTemplate {
             body: JsonPreviewBody::Added { body: &body },
+            syntax,
+            duplicate_keys: parsed.duplicate_keys.len(),
         };
         let detail_html = JsonDetailTemplate {
             detail: JsonDetailBody::Single {
                 label: "added",
                 body: &body,
             },
+            decompressed: parsed.decompressed,
+            syntax,
+            expected_duplicate_keys: &[],
+            actual_duplicate_keys: &parsed.duplicate_keys,
         };
-        reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
+        reporter.record_added(name, self.compares, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -131,22 +159,26 @@ impl DetailReporter<JsonDiff, FileLeaf, HtmlReport> for JsonDiffReporter {
         data: &FileLeaf,
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        let Some(body) = try_into_json(&data.content) else {
+        let syntax = JsonSyntax::new(self.syntax_highlight);
+        let Some((body, parsed)) = try_into_json(&data.kind, &data.content) else {
             return Ok(MayUnsupported::Unsupported);
         };
         let preview_html = JsonPreviewTemplate {
             body: JsonPreviewBody::Deleted { body: &body },
+            syntax,
+            duplicate_keys: parsed.duplicate_keys.len(),
         };
         let detail_html = JsonDetailTemplate {
             detail: JsonDetailBody::Single {
                 label: "deleted",
                 body: &body,
             },
+            decompressed: parsed.decompressed,
+            syntax,
+            expected_duplicate_keys: &parsed.duplicate_keys,
+            actual_duplicate_keys: &[],
         };
-        reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
+        reporter.record_deleted(name, self.compares, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
     }
 }
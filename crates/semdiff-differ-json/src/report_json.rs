@@ -1,31 +1,52 @@
-use crate::{JsonDiff, JsonDiffReporter, is_json_mime, try_into_json};
+use crate::{JsonDiff, JsonDiffReporter, ParsedJson, parse_json};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::json::JsonReport;
-use std::convert;
+use serde::Serialize;
+use std::io::Write;
+use std::{convert, ops};
 
-const COMPARES_NAME: &str = "json";
+#[derive(Serialize)]
+struct EntryReport<'a> {
+    #[serde(skip_serializing_if = "ops::Not::not")]
+    decompressed: bool,
+    /// Keys repeated within an object; only their last value was compared.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    expected_duplicate_keys: &'a [String],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    actual_duplicate_keys: &'a [String],
+}
 
-impl<W> DetailReporter<JsonDiff, FileLeaf, JsonReport<W>> for JsonDiffReporter {
+impl<W: Write> DetailReporter<JsonDiff, FileLeaf, JsonReport<W>> for JsonDiffReporter {
     type Error = convert::Infallible;
 
     fn report_unchanged(
         &self,
         name: &str,
-        _diff: &JsonDiff,
+        diff: &JsonDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.record_unchanged(name, COMPARES_NAME, ());
+        let report = EntryReport {
+            decompressed: diff.decompressed(),
+            expected_duplicate_keys: diff.expected_duplicate_keys(),
+            actual_duplicate_keys: diff.actual_duplicate_keys(),
+        };
+        reporter.record_unchanged(name, self.compares, report);
         Ok(MayUnsupported::Ok(()))
     }
 
     fn report_modified(
         &self,
         name: &str,
-        _diff: &JsonDiff,
+        diff: &JsonDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.record_modified(name, COMPARES_NAME, ());
+        let report = EntryReport {
+            decompressed: diff.decompressed(),
+            expected_duplicate_keys: diff.expected_duplicate_keys(),
+            actual_duplicate_keys: diff.actual_duplicate_keys(),
+        };
+        reporter.record_modified(name, self.compares, report);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -35,13 +56,20 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, JsonReport<W>> for JsonDiffReporter {
         data: &FileLeaf,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        if try_into_json(&data.content).is_none() {
+        let Some(ParsedJson {
+            decompressed,
+            duplicate_keys,
+            ..
+        }) = parse_json(&data.kind, &data.content)
+        else {
             return Ok(MayUnsupported::Unsupported);
-        }
-        reporter.record_added(name, COMPARES_NAME, ());
+        };
+        let report = EntryReport {
+            decompressed,
+            expected_duplicate_keys: &[],
+            actual_duplicate_keys: &duplicate_keys,
+        };
+        reporter.record_added(name, self.compares, report);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -51,13 +79,20 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, JsonReport<W>> for JsonDiffReporter {
         data: &FileLeaf,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        if try_into_json(&data.content).is_none() {
+        let Some(ParsedJson {
+            decompressed,
+            duplicate_keys,
+            ..
+        }) = parse_json(&data.kind, &data.content)
+        else {
             return Ok(MayUnsupported::Unsupported);
-        }
-        reporter.record_deleted(name, COMPARES_NAME, ());
+        };
+        let report = EntryReport {
+            decompressed,
+            expected_duplicate_keys: &duplicate_keys,
+            actual_duplicate_keys: &[],
+        };
+        reporter.record_deleted(name, self.compares, report);
         Ok(MayUnsupported::Ok(()))
     }
 }
@@ -1,4 +1,4 @@
-use crate::{JsonDiff, JsonDiffReporter, is_json_mime, try_into_json};
+use crate::{JsonDiff, JsonDiffReporter, parse_json};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::summary::SummaryReport;
@@ -13,7 +13,7 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, SummaryReport<W>> for JsonDiffReporte
         _diff: &JsonDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_unchanged();
+        reporter.increment_unchanged(self.compares);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -23,7 +23,7 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, SummaryReport<W>> for JsonDiffReporte
         _diff: &JsonDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_modified();
+        reporter.increment_modified(self.compares);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -33,13 +33,10 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, SummaryReport<W>> for JsonDiffReporte
         data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
+        if parse_json(&data.kind, &data.content).is_none() {
             return Ok(MayUnsupported::Unsupported);
         }
-        if try_into_json(&data.content).is_none() {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        reporter.increment_added();
+        reporter.increment_added(self.compares);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -49,13 +46,10 @@ impl<W> DetailReporter<JsonDiff, FileLeaf, SummaryReport<W>> for JsonDiffReporte
         data: &FileLeaf,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        if !is_json_mime(&data.kind) {
-            return Ok(MayUnsupported::Unsupported);
-        }
-        if try_into_json(&data.content).is_none() {
+        if parse_json(&data.kind, &data.content).is_none() {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_deleted();
+        reporter.increment_deleted(self.compares);
         Ok(MayUnsupported::Ok(()))
     }
 }
@@ -0,0 +1,179 @@
+//! Values such as timestamps and UUIDs that change on every run, checked by their shape instead of
+//! their value; see [`crate::JsonDiffCalculator::with_shape_rules`].
+
+use crate::json_pointer::{JsonPointerPattern, PointerSegment, PointerStep};
+use regex_lite::Regex;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+const ISO8601_PATTERN: &str = r"\d{4}-\d{2}-\d{2}([T ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}(:?\d{2})?)?)?";
+const UUID_PATTERN: &str = r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}";
+
+/// The form a string must have, written `iso8601`, `uuid`, or `regex:PATTERN`.
+#[derive(Debug, Clone)]
+pub struct ValueShape {
+    name: String,
+    /// The pattern anchored to match the whole string.
+    regex: Regex,
+}
+
+impl ValueShape {
+    fn matches(&self, value: &Value) -> bool {
+        matches!(value, Value::String(value) if self.regex.is_match(value))
+    }
+
+    /// What a value of this shape is shown as in the diff.
+    fn placeholder(&self) -> Value {
+        Value::String(format!("<{}>", self.name))
+    }
+}
+
+impl fmt::Display for ValueShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ShapeParseError {
+    #[error("unknown value shape {0:?} (expected \"iso8601\", \"uuid\", or \"regex:PATTERN\")")]
+    UnknownShape(String),
+    #[error("invalid regex in value shape {0:?}: {1}")]
+    InvalidRegex(String, String),
+    #[error("expected POINTER=SHAPE, got {0:?}")]
+    MissingShape(String),
+    #[error(transparent)]
+    Pointer(#[from] crate::json_pointer::PointerParseError),
+}
+
+impl FromStr for ValueShape {
+    type Err = ShapeParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let pattern = match input {
+            "iso8601" => ISO8601_PATTERN,
+            "uuid" => UUID_PATTERN,
+            _ => input
+                .strip_prefix("regex:")
+                .ok_or_else(|| ShapeParseError::UnknownShape(input.to_owned()))?,
+        };
+        let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|err| ShapeParseError::InvalidRegex(input.to_owned(), err.to_string()))?;
+        Ok(ValueShape {
+            name: input.to_owned(),
+            regex,
+        })
+    }
+}
+
+/// A JSON pointer whose values pass as equal when they have `shape` on both sides, written
+/// `POINTER=SHAPE` such as `/createdAt=iso8601`.
+#[derive(Debug, Clone)]
+pub struct ShapeRule {
+    pointer: JsonPointerPattern,
+    shape: ValueShape,
+}
+
+impl ShapeRule {
+    pub fn new(pointer: JsonPointerPattern, shape: ValueShape) -> ShapeRule {
+        ShapeRule { pointer, shape }
+    }
+
+    /// Replaces each value at the pointer that has the shape with a placeholder naming it, so
+    /// that values of the same shape compare equal while any other value is still diffed as is.
+    pub(crate) fn apply(&self, value: &mut Value) {
+        mask(value, self.pointer.segments(), &self.shape);
+    }
+
+    /// [`ShapeRule::apply`] below every document of a file parsed into several documents.
+    pub(crate) fn apply_within_documents(&self, documents: &mut Value) {
+        mask_children(
+            documents,
+            &PointerSegment::Wildcard,
+            self.pointer.segments(),
+            &self.shape,
+        );
+    }
+}
+
+fn mask(value: &mut Value, segments: &[PointerSegment], shape: &ValueShape) {
+    let Some((segment, rest)) = segments.split_first() else {
+        if shape.matches(value) {
+            *value = shape.placeholder();
+        }
+        return;
+    };
+    mask_children(value, segment, rest, shape);
+}
+
+/// Masks the values at `rest` below each child of `value` that `segment` matches.
+fn mask_children(value: &mut Value, segment: &PointerSegment, rest: &[PointerSegment], shape: &ValueShape) {
+    match value {
+        Value::Object(map) => map
+            .iter_mut()
+            .filter(|(name, _)| segment.matches(PointerStep::Name(name)))
+            .for_each(|(_, value)| mask(value, rest, shape)),
+        Value::Array(values) => values
+            .iter_mut()
+            .enumerate()
+            .filter(|(index, _)| segment.matches(PointerStep::Index(*index)))
+            .for_each(|(_, value)| mask(value, rest, shape)),
+        _ => {}
+    }
+}
+
+impl FromStr for ShapeRule {
+    type Err = ShapeParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (pointer, shape) = input
+            .split_once('=')
+            .ok_or_else(|| ShapeParseError::MissingShape(input.to_owned()))?;
+        Ok(ShapeRule {
+            pointer: pointer.parse()?,
+            shape: shape.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn shape_rules_mask_values_of_their_shape() {
+        let rule = "/items/*/createdAt=iso8601".parse::<ShapeRule>().unwrap();
+        let mut value = json!({
+            "items": [
+                {"createdAt": "2024-05-01T12:30:00Z"},
+                {"createdAt": "2024-05-01T12:30:00.123+09:00"},
+                {"createdAt": "yesterday"},
+            ],
+            "createdAt": "2024-05-01",
+        });
+        rule.apply(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "items": [{"createdAt": "<iso8601>"}, {"createdAt": "<iso8601>"}, {"createdAt": "yesterday"}],
+                "createdAt": "2024-05-01",
+            })
+        );
+
+        let uuid = "uuid".parse::<ValueShape>().unwrap();
+        assert!(uuid.matches(&json!("123e4567-e89b-12d3-a456-426614174000")));
+        assert!(!uuid.matches(&json!("123e4567")));
+        assert!(!uuid.matches(&json!(1)));
+        let regex = "regex:v\\d+".parse::<ValueShape>().unwrap();
+        assert!(regex.matches(&json!("v12")));
+        assert!(!regex.matches(&json!("v12-beta")));
+
+        assert!("/id".parse::<ShapeRule>().is_err());
+        assert!("/id=guid".parse::<ShapeRule>().is_err());
+        assert!("/id=regex:(".parse::<ShapeRule>().is_err());
+        assert!("id=uuid".parse::<ShapeRule>().is_err());
+    }
+}
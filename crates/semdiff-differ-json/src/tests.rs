@@ -1,4 +1,5 @@
 use super::*;
+use semdiff_core::fs::diff_bytes;
 use serde_json::json;
 use std::fmt::Formatter;
 
@@ -6,7 +7,7 @@ use std::fmt::Formatter;
 fn json_diff_marks_equal_value() {
     let expected = json!("same");
     let actual = json!("same");
-    let diff = json_diff(&expected, &actual, &[]);
+    let diff = json_diff(&expected, &actual, &[], None, false, &[]);
     assert_eq!(diff.len(), 1);
     assert!(matches!(diff[0].tag(), ChangeTag::Unchanged));
 }
@@ -15,7 +16,7 @@ fn json_diff_marks_equal_value() {
 fn json_diff_marks_changed_value() {
     let expected = json!(1);
     let actual = json!(2);
-    let diff = json_diff(&expected, &actual, &[]);
+    let diff = json_diff(&expected, &actual, &[], None, false, &[]);
     assert_eq!(diff.len(), 2);
     assert!(matches!(diff[0].tag(), ChangeTag::Deleted));
     assert!(matches!(diff[1].tag(), ChangeTag::Added));
@@ -57,7 +58,7 @@ fn json_diff_handles_nested_structures() {
         ]
     });
 
-    let diff = json_diff(&expected, &actual, &[]);
+    let diff = json_diff(&expected, &actual, &[], None, false, &[]);
 
     let mut expected_rendered = String::new();
     let mut actual_rendered = String::new();
@@ -171,7 +172,7 @@ fn json_diff_treats_ignored_object_value_as_equal() {
     });
     let ignore_paths = vec!["$.volatile".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
     let ignored = diff.iter().find(|line| line.is_ignored()).unwrap();
@@ -185,7 +186,7 @@ fn json_diff_treats_ignored_array_index_as_equal() {
     let actual = json!([3, 2]);
     let ignore_paths = vec!["$[0]".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
     let ignored = diff.iter().find(|line| line.is_ignored()).unwrap();
@@ -211,7 +212,7 @@ fn json_diff_treats_filter_ignored_array_member_as_equal() {
     });
     let ignore_paths = vec!["$.items[?@.id == $.ignore_id].value".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
     assert!(diff.iter().any(JsonDiffLine::is_ignored));
@@ -237,7 +238,7 @@ fn json_diff_treats_regex_filter_ignored_array_member_as_equal() {
             .unwrap(),
     ];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
     assert!(diff.iter().any(JsonDiffLine::is_ignored));
@@ -249,7 +250,7 @@ fn json_diff_does_not_pair_negative_index_ignore_with_non_ignored_array_element(
     let actual = json!([1, 3, 200, 2]);
     let ignore_paths = vec!["$[-2]".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().any(JsonDiffLine::is_ignored));
     assert!(
@@ -267,7 +268,7 @@ fn json_diff_matches_ignored_array_elements_by_similarity_when_counts_differ() {
     let actual = json!([0, [10, 20, 30], 9]);
     let ignore_paths = vec!["$[1:3]".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
     let ignored_pairs = diff
         .iter()
         .filter(|line| line.is_ignored())
@@ -292,7 +293,7 @@ fn json_diff_treats_ignored_root_as_equal() {
     let actual = json!({"a": 2});
     let ignore_paths = vec!["$".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
     assert!(diff.iter().any(JsonDiffLine::is_ignored));
@@ -310,7 +311,7 @@ fn json_diff_keeps_non_ignored_changes_modified() {
     });
     let ignore_paths = vec!["$.ignored".parse::<JsonPath>().unwrap()];
 
-    let diff = json_diff(&expected, &actual, &ignore_paths);
+    let diff = json_diff(&expected, &actual, &ignore_paths, None, false, &[]);
 
     assert!(diff.iter().any(JsonDiffLine::is_ignored));
     assert!(
@@ -318,3 +319,327 @@ fn json_diff_keeps_non_ignored_changes_modified() {
             .any(|line| matches!(line.tag(), ChangeTag::Added | ChangeTag::Deleted))
     );
 }
+
+fn gzip(content: &[u8]) -> Vec<u8> {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn parse_json_decompresses_gzip_wrapped_json() {
+    let gzip_mime = "application/gzip".parse::<Mime>().unwrap();
+    let content = gzip(br#"{"a": [1, 2]}"#);
+
+    let ParsedJson {
+        value, decompressed, ..
+    } = parse_json(&gzip_mime, &content).unwrap();
+
+    assert_eq!(value, json!({"a": [1, 2]}));
+    assert!(decompressed);
+}
+
+#[test]
+fn parse_json_rejects_gzip_wrapped_non_json() {
+    let gzip_mime = "application/gzip".parse::<Mime>().unwrap();
+    let content = gzip(b"not json");
+
+    assert!(parse_json(&gzip_mime, &content).is_none());
+    assert!(parse_json(&gzip_mime, b"not gzip").is_none());
+}
+
+#[test]
+fn parse_json_leaves_plain_json_uncompressed() {
+    let ParsedJson {
+        value, decompressed, ..
+    } = parse_json(&mime::APPLICATION_JSON, b"[1]").unwrap();
+
+    assert_eq!(value, json!([1]));
+    assert!(!decompressed);
+}
+
+#[test]
+fn round_numbers_rounds_floats_recursively() {
+    let mut value = json!({"a": 1.23456, "list": [6.54321, 10], "name": "x"});
+
+    round_numbers(&mut value, 3);
+
+    assert_eq!(value, json!({"a": 1.235, "list": [6.543, 10], "name": "x"}));
+}
+
+#[test]
+fn round_numbers_makes_nearby_floats_equal() {
+    let mut expected = json!([1.23456]);
+    let mut actual = json!([1.23464]);
+
+    round_numbers(&mut expected, 4);
+    round_numbers(&mut actual, 4);
+
+    let diff = json_diff(&expected, &actual, &[], None, false, &[]);
+    assert!(diff.iter().all(JsonDiffLine::is_equal_for_result));
+}
+
+#[test]
+fn json_syntax_highlights_tokens_and_escapes_html() {
+    let line = r#"  "k<\"": ["a&b", -1.5e3, true, null],"#;
+    assert_eq!(
+        highlight::JsonSyntax::new(true).html(line).to_string(),
+        concat!(
+            r#"  <span class="json-key">&quot;k&lt;\&quot;&quot;</span>: ["#,
+            r#"<span class="json-string">&quot;a&amp;b&quot;</span>, "#,
+            r#"<span class="json-number">-1.5e3</span>, "#,
+            r#"<span class="json-boolean">true</span>, "#,
+            r#"<span class="json-null">null</span>],"#,
+        )
+    );
+    assert_eq!(
+        highlight::JsonSyntax::new(false).html(line).to_string(),
+        r#"  &quot;k&lt;\&quot;&quot;: [&quot;a&amp;b&quot;, -1.5e3, true, null],"#
+    );
+}
+
+#[test]
+fn parse_json_reports_duplicate_keys() {
+    let content = br#"{"a": 1, "b": [{"c": 1, "c": 2, "c": 3}], "it's": 0, "a": 2, "it's": 1}"#;
+
+    let ParsedJson {
+        value, duplicate_keys, ..
+    } = parse_json(&mime::APPLICATION_JSON, content).unwrap();
+
+    assert_eq!(value, json!({"a": 2, "b": [{"c": 3}], "it's": 1}));
+    assert_eq!(duplicate_keys, ["$['b'][0]['c']", "$['a']", "$['it\\'s']"]);
+    assert!(
+        parse_json(&mime::APPLICATION_JSON, br#"{"a": 2}"#)
+            .unwrap()
+            .duplicate_keys
+            .is_empty()
+    );
+}
+
+#[test]
+fn json_diff_calculator_treats_dropped_duplicate_keys_as_modified() {
+    let calculator = JsonDiffCalculator::default();
+    let diff = |expected: &str, actual: &str| {
+        let expected = (mime::APPLICATION_JSON, expected.as_bytes().to_vec());
+        let actual = (mime::APPLICATION_JSON, actual.as_bytes().to_vec());
+        match diff_bytes(&calculator, expected, actual).unwrap() {
+            MayUnsupported::Ok(diff) => diff,
+            MayUnsupported::Unsupported => panic!("both sides are JSON"),
+        }
+    };
+
+    let deduped = diff(r#"{"a": 1, "a": 2}"#, r#"{"a": 2}"#);
+    assert!(!deduped.equal());
+    assert_eq!(deduped.expected_duplicate_keys(), ["$['a']"]);
+    assert!(deduped.actual_duplicate_keys().is_empty());
+
+    assert!(diff(r#"{"a": 1, "a": 2}"#, r#"{"a": 0, "a": 2}"#).equal());
+}
+
+#[test]
+fn parse_json_reads_several_documents_as_an_array() {
+    let content = b"{\"kind\": \"A\", \"x\": 1, \"x\": 2}\n{\"kind\": \"B\"}\n";
+
+    let ParsedJson {
+        value,
+        duplicate_keys,
+        documents,
+        ..
+    } = parse_json(&mime::APPLICATION_JSON, content).unwrap();
+
+    assert_eq!(value, json!([{"kind": "A", "x": 2}, {"kind": "B"}]));
+    assert_eq!(duplicate_keys, ["$[0]['x']"]);
+    assert!(documents);
+    assert!(!parse_json(&mime::APPLICATION_JSON, b"[1]").unwrap().documents);
+    assert!(parse_json(&mime::APPLICATION_JSON, b"{} {").is_none());
+}
+
+#[test]
+fn json_diff_calculator_pairs_documents_by_key() {
+    let diff = |calculator: &JsonDiffCalculator, expected: &str, actual: &str| {
+        let expected = (mime::APPLICATION_JSON, expected.as_bytes().to_vec());
+        let actual = (mime::APPLICATION_JSON, actual.as_bytes().to_vec());
+        match diff_bytes(calculator, expected, actual).unwrap() {
+            MayUnsupported::Ok(diff) => diff,
+            MayUnsupported::Unsupported => panic!("both sides are JSON"),
+        }
+    };
+    let expected = concat!(
+        r#"{"kind": "Service", "metadata": {"name": "web", "uid": 1}, "port": 80}"#,
+        "\n",
+        r#"{"kind": "Deployment", "metadata": {"name": "web", "uid": 2}, "replicas": 1}"#,
+    );
+    let actual = concat!(
+        r#"{"kind": "Deployment", "metadata": {"name": "web", "uid": 3}, "replicas": 2}"#,
+        "\n",
+        r#"{"kind": "Service", "metadata": {"name": "web", "uid": 4}, "port": 80}"#,
+        "\n",
+        r#"{"kind": "ConfigMap", "metadata": {"name": "env"}}"#,
+    );
+    let calculator = JsonDiffCalculator::new(false, vec!["$.metadata.uid".parse().unwrap()])
+        .with_document_matching(DocumentMatching::Key(DocumentMatching::default_key()));
+    let JsonDiffBody::Modified(lines) = diff(&calculator, expected, actual).body else {
+        panic!("the documents differ");
+    };
+    let changes = lines
+        .iter()
+        .filter(|line| matches!(line.tag(), ChangeTag::Added | ChangeTag::Deleted))
+        .map(|line| (line.tag(), line.indent, line.preview_text()))
+        .collect::<Vec<_>>();
+    // Documents pair up by kind and name wherever they are, and uids are ignored in each of them.
+    assert_eq!(
+        changes,
+        [
+            (ChangeTag::Added, 1, "\"ConfigMap/env\": {"),
+            (ChangeTag::Added, 1, "  \"kind\": \"ConfigMap\","),
+            (ChangeTag::Added, 1, "  \"metadata\": {"),
+            (ChangeTag::Added, 1, "    \"name\": \"env\""),
+            (ChangeTag::Added, 1, "  }"),
+            (ChangeTag::Added, 1, "},"),
+            (ChangeTag::Deleted, 2, "\"replicas\": 1"),
+            (ChangeTag::Added, 2, "\"replicas\": 2"),
+        ]
+    );
+
+    // In order, the swapped documents no longer line up.
+    let in_order = JsonDiffCalculator::default();
+    assert!(!diff(&in_order, expected, &actual[..actual.rfind('\n').unwrap()]).equal());
+}
+
+#[test]
+fn json_diff_calculator_compares_shaped_values_by_their_shape() {
+    let calculator = JsonDiffCalculator::default()
+        .with_shape_rules(vec!["/createdAt=iso8601".parse().unwrap(), "/id=uuid".parse().unwrap()]);
+    let diff = |expected: Value, actual: Value| calculator.diff_values(expected, actual);
+
+    let expected = json!({"id": "123e4567-e89b-12d3-a456-426614174000", "createdAt": "2024-05-01T12:30:00Z"});
+    let actual = json!({"id": "9b2f0c1e-0000-4000-8000-000000000001", "createdAt": "2025-01-31T08:00:00+01:00"});
+    assert!(diff(expected.clone(), actual).equal());
+
+    let JsonDiffBody::Modified(lines) = diff(expected, json!({"id": "42", "createdAt": "2024-05-02"})).body else {
+        panic!("an id that is not a UUID differs");
+    };
+    let changes = lines
+        .iter()
+        .filter(|line| matches!(line.tag(), ChangeTag::Added | ChangeTag::Deleted))
+        .map(|line| (line.tag(), line.preview_text()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            (ChangeTag::Deleted, "\"id\": \"<uuid>\","),
+            (ChangeTag::Added, "\"id\": \"42\",")
+        ]
+    );
+}
+
+#[test]
+fn json_diff_calculator_finds_format_regression_only_for_json_kinds() {
+    let calculator = JsonDiffCalculator::default();
+    let leaf = |kind: Mime, content: &str| FileLeaf::from_bytes("", kind, content);
+    let valid = leaf(mime::APPLICATION_JSON, r#"{"a": 1}"#);
+    let broken = leaf(mime::APPLICATION_JSON, r#"{"a": 1"#);
+
+    let regression = calculator.format_regression(&valid, &broken).unwrap();
+    assert_eq!(regression.to_string(), "expected parsed as JSON, actual did not");
+    assert!(!calculator.format_regression(&broken, &valid).unwrap().expected_parsed);
+    assert_eq!(calculator.format_regression(&broken, &broken), None);
+    assert_eq!(calculator.format_regression(&valid, &valid), None);
+    assert_eq!(calculator.format_regression(&valid, &leaf(mime::TEXT_PLAIN, "{")), None);
+}
+
+#[test]
+fn json_diff_collapses_containers_beyond_max_depth() {
+    let expected = json!({ "a": { "b": { "c": 1 } }, "d": { "e": [1] }, "f": [] });
+    let actual = json!({ "a": { "b": { "c": 2 } }, "d": { "e": [1] }, "f": [] });
+    let diff = json_diff(&expected, &actual, &[], Some(1), false, &[]);
+    let lines = diff
+        .iter()
+        .map(|line| (line.tag(), line.indent, line.preview_text()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            (ChangeTag::Unchanged, 0, "{"),
+            (ChangeTag::Unchanged, 1, "\"a\": {"),
+            (ChangeTag::Deleted, 2, "\"b\": {…}"),
+            (ChangeTag::Added, 2, "\"b\": {…}"),
+            (ChangeTag::Unchanged, 1, "},"),
+            (ChangeTag::Unchanged, 1, "\"d\": {"),
+            (ChangeTag::Unchanged, 2, "\"e\": […]"),
+            (ChangeTag::Unchanged, 1, "},"),
+            (ChangeTag::Unchanged, 1, "\"f\": ["),
+            (ChangeTag::Unchanged, 1, "]"),
+            (ChangeTag::Unchanged, 0, "}"),
+        ]
+    );
+    assert_eq!(
+        to_string_pretty_within(&expected, Some(1)),
+        "{\n  \"a\": {…},\n  \"d\": {…},\n  \"f\": []\n}"
+    );
+}
+
+#[test]
+fn json_diff_compares_scalar_arrays_as_multisets_when_enabled() {
+    let expected = json!({ "tags": ["a", "b", "b", "c"], "rows": [{ "id": 1 }, { "id": 2 }] });
+    let actual = json!({ "tags": ["c", "b", "a", "d"], "rows": [{ "id": 2 }, { "id": 1 }] });
+    let diff = json_diff(&expected, &actual, &[], None, true, &[]);
+    let lines = diff
+        .iter()
+        .filter(|line| line.tag() != ChangeTag::Unchanged)
+        .map(|line| (line.tag(), line.preview_text()))
+        .collect::<Vec<_>>();
+    // The duplicate "b" has no partner and "d" is new, but the reordered scalars are unchanged;
+    // arrays of objects are still diffed in order.
+    assert_eq!(
+        lines,
+        [
+            (ChangeTag::Deleted, "\"b\","),
+            (ChangeTag::Added, "\"d\""),
+            (ChangeTag::Added, "{"),
+            (ChangeTag::Added, "  \"id\": 2"),
+            (ChangeTag::Added, "},"),
+            (ChangeTag::Deleted, "{"),
+            (ChangeTag::Deleted, "  \"id\": 2"),
+            (ChangeTag::Deleted, "}"),
+        ]
+    );
+
+    let reordered = json_diff(&json!([3, 1, 2, 1]), &json!([1, 1, 2, 3]), &[], None, true, &[]);
+    assert!(reordered.iter().all(JsonDiffLine::is_equal_for_result));
+    let actual_lines = reordered
+        .iter()
+        .map(|line| line.display_actual().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(actual_lines, ["[", "  3,", "  1,", "  2,", "  1", "]"]);
+}
+
+#[test]
+fn json_diff_compares_only_arrays_at_unordered_paths_as_multisets() {
+    let expected = json!({
+        "permissions": [{ "role": "admin" }, { "role": "user" }],
+        "users": [{ "groups": ["a", "b"] }],
+        "steps": ["build", "test"],
+    });
+    let actual = json!({
+        "permissions": [{ "role": "user" }, { "role": "admin" }],
+        "users": [{ "groups": ["b", "a"] }],
+        "steps": ["test", "build"],
+    });
+    let unordered_paths = ["/permissions".parse().unwrap(), "/users/*/groups".parse().unwrap()];
+    let diff = json_diff(&expected, &actual, &[], None, false, &unordered_paths);
+    let lines = diff
+        .iter()
+        .filter(|line| line.tag() != ChangeTag::Unchanged)
+        .map(|line| (line.tag(), line.preview_text()))
+        .collect::<Vec<_>>();
+    // Objects at an unordered path pair up wherever they are; "steps" keeps its order.
+    assert_eq!(
+        lines,
+        [(ChangeTag::Added, "\"test\","), (ChangeTag::Deleted, "\"test\"")]
+    );
+}
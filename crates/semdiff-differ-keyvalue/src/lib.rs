@@ -0,0 +1,192 @@
+use crate::parse::{Entries, parse};
+use mime::Mime;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use serde::Serialize;
+use std::{convert, fmt};
+
+mod parse;
+pub mod report_collect;
+pub mod report_html;
+pub mod report_json;
+pub mod report_summary;
+
+#[cfg(test)]
+mod tests;
+
+pub use parse::KeyValueFormat;
+
+/// Kind given to dotenv files (`.env`, `.env.*`, `*.env`) by the file tree.
+pub const DOTENV_MIME: &str = "text/x-dotenv";
+/// Kind given to `*.properties` files by the file tree.
+pub const PROPERTIES_MIME: &str = "text/x-java-properties";
+
+pub struct KeyValueDiffReporter;
+
+#[derive(Debug)]
+pub struct KeyValueDiff {
+    format: KeyValueFormat,
+    changes: Vec<KeyChange>,
+    /// Keys found on either side.
+    compared_keys: usize,
+}
+
+impl Diff for KeyValueDiff {
+    fn equal(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn magnitude(&self) -> Option<f64> {
+        if self.compared_keys == 0 {
+            return Some(0.0);
+        }
+        Some(self.changes.len() as f64 / self.compared_keys as f64)
+    }
+}
+
+impl KeyValueDiff {
+    pub fn format(&self) -> KeyValueFormat {
+        self.format
+    }
+
+    /// Added, removed, and changed keys, in key order.
+    pub fn changes(&self) -> &[KeyChange] {
+        &self.changes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KeyChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyChange::Added { key, value } => write!(f, "+ {key}={value}"),
+            KeyChange::Removed { key, value } => write!(f, "- {key}={value}"),
+            KeyChange::Changed { key, expected, actual } => write!(f, "~ {key}: {expected} → {actual}"),
+        }
+    }
+}
+
+impl KeyChange {
+    fn key(&self) -> &str {
+        match self {
+            KeyChange::Added { key, .. } | KeyChange::Removed { key, .. } | KeyChange::Changed { key, .. } => key,
+        }
+    }
+
+    /// Class of the status colors for this change in HTML reports.
+    fn status_class(&self) -> &'static str {
+        match self {
+            KeyChange::Added { .. } => "added",
+            KeyChange::Removed { .. } => "deleted",
+            KeyChange::Changed { .. } => "modified",
+        }
+    }
+}
+
+/// Compares dotenv and `.properties` files by key, so reordered keys, comments, blank lines,
+/// and quoting or escaping that yields the same value do not count as changes.
+///
+/// Files that do not parse, such as dotenv files with a line that is not `KEY=VALUE`, are left to
+/// the text differ.
+#[derive(Debug, Default)]
+pub struct KeyValueDiffCalculator;
+
+impl DiffCalculator<FileLeaf> for KeyValueDiffCalculator {
+    type Error = convert::Infallible;
+    type Diff = KeyValueDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: FileLeaf,
+        actual: FileLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        let Some(format) = key_value_format(&expected.kind) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        if key_value_format(&actual.kind) != Some(format) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        let (Some(expected), Some(actual)) = (
+            parse_entries(format, &expected.content),
+            parse_entries(format, &actual.content),
+        ) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let mut changes = Vec::new();
+        for (key, expected_value) in &expected {
+            match actual.get(key) {
+                None => changes.push(KeyChange::Removed {
+                    key: key.clone(),
+                    value: expected_value.clone(),
+                }),
+                Some(actual_value) if actual_value != expected_value => changes.push(KeyChange::Changed {
+                    key: key.clone(),
+                    expected: expected_value.clone(),
+                    actual: actual_value.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, actual_value) in &actual {
+            if !expected.contains_key(key) {
+                changes.push(KeyChange::Added {
+                    key: key.clone(),
+                    value: actual_value.clone(),
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        let compared_keys = expected.len() + actual.keys().filter(|key| !expected.contains_key(*key)).count();
+        Ok(MayUnsupported::Ok(KeyValueDiff {
+            format,
+            changes,
+            compared_keys,
+        }))
+    }
+
+    fn unreadable(&self, leaf: &FileLeaf) -> bool {
+        key_value_format(&leaf.kind).is_some_and(|format| parse_entries(format, &leaf.content).is_none())
+    }
+}
+
+/// Kinds that are compared by key. The file tree gives them to files by name, as neither format
+/// has a registered MIME type.
+pub fn supported_kinds() -> Vec<&'static str> {
+    vec![DOTENV_MIME, PROPERTIES_MIME]
+}
+
+fn key_value_format(kind: &Mime) -> Option<KeyValueFormat> {
+    match kind.essence_str() {
+        DOTENV_MIME => Some(KeyValueFormat::Dotenv),
+        PROPERTIES_MIME => Some(KeyValueFormat::Properties),
+        _ => None,
+    }
+}
+
+fn parse_entries(format: KeyValueFormat, content: &[u8]) -> Option<Entries> {
+    parse(format, str::from_utf8(content).ok()?).ok()
+}
+
+/// The entries of an added or deleted file in key order, or `None` if it is not a key-value file
+/// that parses.
+fn entries(kind: &Mime, content: &[u8]) -> Option<Entries> {
+    parse_entries(key_value_format(kind)?, content)
+}
@@ -0,0 +1,226 @@
+//! Parsers for dotenv and Java `.properties` files.
+
+use std::collections::BTreeMap;
+use std::str::Lines;
+use thiserror::Error;
+
+/// Entries of a file by key. A key that is assigned more than once keeps its last value, as
+/// both dotenv loaders and `java.util.Properties` do.
+pub(crate) type Entries = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyValueFormat {
+    /// `KEY=VALUE` lines with `#` comments, an optional `export` prefix, and single- or
+    /// double-quoted values.
+    Dotenv,
+    /// Java `.properties`: `key=value`, `key: value`, or `key value` lines with `#` and `!`
+    /// comments, backslash line continuations, and `\uXXXX` escapes.
+    Properties,
+}
+
+#[derive(Debug, Error)]
+#[error("line {line}: {message}")]
+pub(crate) struct ParseError {
+    line: usize,
+    message: &'static str,
+}
+
+pub(crate) fn parse(format: KeyValueFormat, text: &str) -> Result<Entries, ParseError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = NumberedLines {
+        lines: text.lines(),
+        line: 0,
+    };
+    let mut entries = Entries::new();
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        let comment = match format {
+            KeyValueFormat::Dotenv => line.starts_with('#'),
+            KeyValueFormat::Properties => line.starts_with(['#', '!']),
+        };
+        if line.is_empty() || comment {
+            continue;
+        }
+        let (key, value) = match format {
+            KeyValueFormat::Dotenv => parse_dotenv_entry(line, &mut lines)?,
+            KeyValueFormat::Properties => parse_properties_entry(line, &mut lines)?,
+        };
+        entries.insert(key, value);
+    }
+    Ok(entries)
+}
+
+struct NumberedLines<'a> {
+    lines: Lines<'a>,
+    /// 1-based number of the line returned last.
+    line: usize,
+}
+
+impl<'a> NumberedLines<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let line = self.lines.next()?;
+        self.line += 1;
+        Some(line)
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            line: self.line,
+            message,
+        }
+    }
+}
+
+fn parse_dotenv_entry(line: &str, lines: &mut NumberedLines) -> Result<(String, String), ParseError> {
+    let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+    let Some((key, rest)) = line.split_once('=') else {
+        return Err(lines.error("expected KEY=VALUE"));
+    };
+    let key = key.trim_end();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(lines.error("invalid key"));
+    }
+    let rest = rest.trim_start();
+    let value = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            // Quoted values may span lines, up to the closing quote.
+            let mut raw = rest[1..].to_owned();
+            let end = loop {
+                if let Some(end) = closing_quote(&raw, quote) {
+                    break end;
+                }
+                let Some(next) = lines.next() else {
+                    return Err(lines.error("unterminated quoted value"));
+                };
+                raw.push('\n');
+                raw.push_str(next);
+            };
+            let trailing = raw[end + 1..].trim_start();
+            if !trailing.is_empty() && !trailing.starts_with('#') {
+                return Err(lines.error("unexpected text after quoted value"));
+            }
+            raw.truncate(end);
+            if quote == '"' {
+                unescape_double_quoted(&raw)
+            } else {
+                raw
+            }
+        }
+        _ => strip_inline_comment(rest).trim_end().to_owned(),
+    };
+    Ok((key.to_owned(), value))
+}
+
+/// Byte index of the quote that ends a value, skipping backslash-escaped double quotes.
+fn closing_quote(raw: &str, quote: char) -> Option<usize> {
+    if quote == '\'' {
+        return raw.find('\'');
+    }
+    let mut escaped = false;
+    for (index, char) in raw.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape_double_quoted(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            value.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some(escaped @ ('\\' | '"' | '$')) => value.push(escaped),
+            Some(other) => {
+                value.push('\\');
+                value.push(other);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+/// Cuts an unquoted value at a `#` that starts it or follows whitespace.
+fn strip_inline_comment(value: &str) -> &str {
+    if value.starts_with('#') {
+        return "";
+    }
+    value
+        .char_indices()
+        .zip(value.chars().skip(1))
+        .find(|((_, char), next)| char.is_whitespace() && *next == '#')
+        .map_or(value, |((index, _), _)| &value[..index])
+}
+
+fn parse_properties_entry(line: &str, lines: &mut NumberedLines) -> Result<(String, String), ParseError> {
+    let mut logical = line.to_owned();
+    while ends_with_continuation(&logical) {
+        logical.pop();
+        let Some(next) = lines.next() else {
+            break;
+        };
+        logical.push_str(next.trim_start());
+    }
+    let mut chars = logical.chars().peekable();
+    let mut key = String::new();
+    while let Some(&char) = chars.peek() {
+        if char == '=' || char == ':' || char.is_whitespace() {
+            break;
+        }
+        chars.next();
+        push_unescaped(&mut key, char, &mut chars, lines)?;
+    }
+    while chars.next_if(|char| char.is_whitespace()).is_some() {}
+    chars.next_if(|&char| char == '=' || char == ':');
+    while chars.next_if(|char| char.is_whitespace()).is_some() {}
+    let mut value = String::new();
+    while let Some(char) = chars.next() {
+        push_unescaped(&mut value, char, &mut chars, lines)?;
+    }
+    Ok((key, value))
+}
+
+/// Whether `line` ends with an odd number of backslashes, which joins it with the next line.
+fn ends_with_continuation(line: &str) -> bool {
+    line.bytes().rev().take_while(|&byte| byte == b'\\').count() % 2 == 1
+}
+
+fn push_unescaped(
+    out: &mut String,
+    char: char,
+    chars: &mut impl Iterator<Item = char>,
+    lines: &NumberedLines,
+) -> Result<(), ParseError> {
+    if char != '\\' {
+        out.push(char);
+        return Ok(());
+    }
+    match chars.next() {
+        Some('t') => out.push('\t'),
+        Some('n') => out.push('\n'),
+        Some('r') => out.push('\r'),
+        Some('f') => out.push('\u{c}'),
+        Some('u') => {
+            let hex = chars.by_ref().take(4).collect::<String>();
+            let code = (hex.len() == 4)
+                .then(|| u32::from_str_radix(&hex, 16).ok())
+                .flatten()
+                .ok_or_else(|| lines.error("malformed \\uXXXX escape"))?;
+            out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        Some(other) => out.push(other),
+        None => {}
+    }
+    Ok(())
+}
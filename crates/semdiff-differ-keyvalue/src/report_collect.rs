@@ -0,0 +1,57 @@
+use crate::{KeyValueDiff, KeyValueDiffReporter, entries};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::collect::CollectReport;
+use std::convert;
+
+const COMPARES_NAME: &str = "keyvalue";
+
+impl DetailReporter<KeyValueDiff, FileLeaf, CollectReport<'_>> for KeyValueDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &KeyValueDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        _diff: &KeyValueDiff,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_modified(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &CollectReport<'_>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
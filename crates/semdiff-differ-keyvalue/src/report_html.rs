@@ -0,0 +1,145 @@
+use crate::{KeyChange, KeyValueDiff, KeyValueDiffReporter, entries};
+use askama::Template;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::html::{HtmlReport, HtmlReportError};
+use thiserror::Error;
+
+const COMPARES_NAME: &str = "keyvalue";
+
+/// Lines shown in the preview of a file.
+const PREVIEW_LINES: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum KeyValueDiffReportError {
+    #[error("html report error: {0}")]
+    HtmlReport(#[from] HtmlReportError),
+}
+
+#[derive(Template)]
+#[template(path = "keyvalue_preview.html")]
+struct KeyValuePreviewTemplate<'a> {
+    body: KeyValuePreviewBody<'a>,
+}
+
+enum KeyValuePreviewBody<'a> {
+    Unchanged {
+        keys: usize,
+    },
+    Modified {
+        changes: &'a [KeyChange],
+    },
+    Single {
+        label: &'a str,
+        entries: Vec<(&'a String, &'a String)>,
+    },
+}
+
+#[derive(Template)]
+#[template(path = "keyvalue_detail.html")]
+struct KeyValueDetailTemplate<'a> {
+    detail: KeyValueDetailBody<'a>,
+}
+
+enum KeyValueDetailBody<'a> {
+    Unchanged {
+        keys: usize,
+    },
+    Diff {
+        changes: &'a [KeyChange],
+    },
+    Single {
+        label: &'a str,
+        entries: Vec<(&'a String, &'a String)>,
+    },
+}
+
+impl DetailReporter<KeyValueDiff, FileLeaf, HtmlReport> for KeyValueDiffReporter {
+    type Error = KeyValueDiffReportError;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &KeyValueDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let keys = diff.compared_keys;
+        let preview_html = KeyValuePreviewTemplate {
+            body: KeyValuePreviewBody::Unchanged { keys },
+        };
+        let detail_html = KeyValueDetailTemplate {
+            detail: KeyValueDetailBody::Unchanged { keys },
+        };
+        reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &KeyValueDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let changes = diff.changes();
+        let preview_html = KeyValuePreviewTemplate {
+            body: KeyValuePreviewBody::Modified {
+                changes: &changes[..changes.len().min(PREVIEW_LINES)],
+            },
+        };
+        let detail_html = KeyValueDetailTemplate {
+            detail: KeyValueDetailBody::Diff { changes },
+        };
+        reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(entries) = entries(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = KeyValuePreviewTemplate {
+            body: KeyValuePreviewBody::Single {
+                label: "added",
+                entries: entries.iter().take(PREVIEW_LINES).collect(),
+            },
+        };
+        let detail_html = KeyValueDetailTemplate {
+            detail: KeyValueDetailBody::Single {
+                label: "added",
+                entries: entries.iter().collect(),
+            },
+        };
+        reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(entries) = entries(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = KeyValuePreviewTemplate {
+            body: KeyValuePreviewBody::Single {
+                label: "deleted",
+                entries: entries.iter().take(PREVIEW_LINES).collect(),
+            },
+        };
+        let detail_html = KeyValueDetailTemplate {
+            detail: KeyValueDetailBody::Single {
+                label: "deleted",
+                entries: entries.iter().collect(),
+            },
+        };
+        reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{KeyChange, KeyValueDiff, KeyValueDiffReporter, entries};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::json::JsonReport;
+use serde::Serialize;
+use std::convert;
+use std::io::Write;
+
+const COMPARES_NAME: &str = "keyvalue";
+
+impl<W: Write> DetailReporter<KeyValueDiff, FileLeaf, JsonReport<W>> for KeyValueDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &KeyValueDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &KeyValueDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        #[derive(Debug, Serialize)]
+        struct Report<'a> {
+            changes: &'a [KeyChange],
+        }
+        reporter.record_modified(
+            name,
+            COMPARES_NAME,
+            Report {
+                changes: diff.changes(),
+            },
+        );
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{KeyValueDiff, KeyValueDiffReporter, entries};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::summary::SummaryReport;
+use std::convert;
+
+const COMPARES_NAME: &str = "keyvalue";
+
+impl<W> DetailReporter<KeyValueDiff, FileLeaf, SummaryReport<W>> for KeyValueDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        _name: &str,
+        _diff: &KeyValueDiff,
+        reporter: &SummaryReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.increment_unchanged(COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        _name: &str,
+        _diff: &KeyValueDiff,
+        reporter: &SummaryReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.increment_modified(COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        _name: &str,
+        data: &FileLeaf,
+        reporter: &SummaryReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.increment_added(COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        _name: &str,
+        data: &FileLeaf,
+        reporter: &SummaryReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if entries(&data.kind, &data.content).is_none() {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.increment_deleted(COMPARES_NAME);
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,110 @@
+use super::*;
+use crate::parse::KeyValueFormat;
+
+fn diff(kind: &str, expected: &str, actual: &str) -> KeyValueDiff {
+    let leaf = |content: &str| FileLeaf::from_bytes("", kind.parse().unwrap(), content.as_bytes());
+    match KeyValueDiffCalculator
+        .diff("config", leaf(expected), leaf(actual))
+        .unwrap()
+    {
+        MayUnsupported::Ok(diff) => diff,
+        MayUnsupported::Unsupported => panic!("file was not parsed"),
+    }
+}
+
+fn entries(format: KeyValueFormat, text: &str) -> Vec<(String, String)> {
+    parse(format, text).unwrap().into_iter().collect()
+}
+
+fn pair(key: &str, value: &str) -> (String, String) {
+    (key.to_owned(), value.to_owned())
+}
+
+#[test]
+fn dotenv_parses_comments_quotes_and_export() {
+    let text = "\
+# comment
+export PATH_PREFIX=/usr/local
+PLAIN = value with spaces # trailing comment
+HASH=a#b
+EMPTY=
+SINGLE='no $ or \\n escapes'
+DOUBLE=\"line\\nbreak \\\"quoted\\\"\" # comment
+MULTI=\"first
+second\"
+PLAIN=overridden
+";
+    assert_eq!(
+        entries(KeyValueFormat::Dotenv, text),
+        [
+            pair("DOUBLE", "line\nbreak \"quoted\""),
+            pair("EMPTY", ""),
+            pair("HASH", "a#b"),
+            pair("MULTI", "first\nsecond"),
+            pair("PATH_PREFIX", "/usr/local"),
+            pair("PLAIN", "overridden"),
+            pair("SINGLE", "no $ or \\n escapes"),
+        ]
+    );
+    assert!(parse(KeyValueFormat::Dotenv, "not a pair\n").is_err());
+    assert!(parse(KeyValueFormat::Dotenv, "OPEN=\"never closed\n").is_err());
+}
+
+#[test]
+fn properties_parses_separators_continuations_and_escapes() {
+    let text = "\
+! comment
+# comment
+equals=1
+colon: 2
+space 3
+long = first, \\
+       second
+escaped\\ key=caf\\u00e9\\tend
+flag
+";
+    assert_eq!(
+        entries(KeyValueFormat::Properties, text),
+        [
+            pair("colon", "2"),
+            pair("equals", "1"),
+            pair("escaped key", "café\tend"),
+            pair("flag", ""),
+            pair("long", "first, second"),
+            pair("space", "3"),
+        ]
+    );
+}
+
+#[test]
+fn key_value_diff_ignores_order_and_comments() {
+    let diff = diff(
+        DOTENV_MIME,
+        "# database\nHOST=localhost\nPORT=5432\n",
+        "PORT=\"5432\"\n\nHOST=localhost # same host\n",
+    );
+    assert!(diff.equal(), "{diff:?}");
+    assert_eq!(diff.magnitude(), Some(0.0));
+}
+
+#[test]
+fn key_value_diff_reports_added_removed_and_changed_keys() {
+    let diff = diff(PROPERTIES_MIME, "b=1\na=1\nc=old\n", "c=new\nd=2\nb=1\n");
+    let changes = diff.changes().iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(changes, ["- a=1", "~ c: old → new", "+ d=2"]);
+    assert_eq!(diff.magnitude(), Some(3.0 / 4.0));
+}
+
+#[test]
+fn key_value_diff_leaves_other_files_to_the_next_differ() {
+    let leaf = |kind: &str, content: &[u8]| FileLeaf::from_bytes("", kind.parse().unwrap(), content);
+    let unsupported = |expected, actual| {
+        matches!(
+            KeyValueDiffCalculator.diff("config", expected, actual).unwrap(),
+            MayUnsupported::Unsupported
+        )
+    };
+    assert!(unsupported(leaf("text/plain", b"A=1"), leaf("text/plain", b"A=2")));
+    assert!(unsupported(leaf(DOTENV_MIME, b"A=1"), leaf(DOTENV_MIME, b"not a pair")));
+    assert!(unsupported(leaf(DOTENV_MIME, b"A=1"), leaf(PROPERTIES_MIME, b"A=1")));
+}
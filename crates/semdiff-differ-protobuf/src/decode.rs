@@ -0,0 +1,243 @@
+//! Decoding messages into JSON values, following the protobuf JSON mapping where it keeps diffs
+//! readable.
+//!
+//! Fields are keyed by their JSON name in field number order, whatever their order on the wire.
+//! Maps become objects sorted by key, enums their value names, and bytes base64 strings. 64-bit
+//! integers stay numbers rather than strings. Fields that the schema does not know are kept
+//! under their number in brackets, such as `"[7]"`, as an array of their raw values.
+
+use crate::schema::{FieldKind, MessageType, ProtobufSchema};
+use crate::wire::{DecodeError, WireReader, WireValue};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+
+/// Messages nested deeper than this are rejected rather than risking the stack.
+const MAX_DEPTH: usize = 100;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decodes `bytes` as the root message of `schema`.
+pub(crate) fn decode(schema: &ProtobufSchema, bytes: &[u8]) -> Result<Value, DecodeError> {
+    decode_message(schema, &schema.messages[&schema.root], bytes, 0)
+}
+
+/// Sorts the elements of every array in `value`, so that repeated fields compare regardless of
+/// their order.
+pub(crate) fn sort_arrays(value: &mut Value) {
+    match value {
+        Value::Array(values) => {
+            values.iter_mut().for_each(sort_arrays);
+            values.sort_by_cached_key(|value| value.to_string());
+        }
+        Value::Object(map) => map.values_mut().for_each(sort_arrays),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+    }
+}
+
+enum Slot {
+    Single(Value),
+    Repeated(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    Unknown(Vec<Value>),
+}
+
+fn decode_message(
+    schema: &ProtobufSchema,
+    message: &MessageType,
+    bytes: &[u8],
+    depth: usize,
+) -> Result<Value, DecodeError> {
+    let mut reader = WireReader::new(bytes);
+    if depth > MAX_DEPTH {
+        return Err(reader.error("messages nested too deeply"));
+    }
+    let mut slots = BTreeMap::new();
+    while let Some((number, value)) = reader.field()? {
+        let Some(field) = message.field(number) else {
+            let Slot::Unknown(values) = slots.entry(number).or_insert(Slot::Unknown(Vec::new())) else {
+                unreachable!("field numbers are either known or unknown");
+            };
+            values.push(raw_value(value));
+            continue;
+        };
+        let map_entry = match &field.kind {
+            FieldKind::Message(type_name) => Some(&schema.messages[type_name]).filter(|entry| entry.map_entry),
+            _ => None,
+        };
+        if let Some(entry) = map_entry {
+            let Slot::Map(map) = slots.entry(number).or_insert(Slot::Map(BTreeMap::new())) else {
+                unreachable!("map fields are always maps");
+            };
+            let (key, value) = decode_map_entry(schema, entry, value, depth, &reader)?;
+            map.insert(key, value);
+        } else if field.repeated {
+            let Slot::Repeated(values) = slots.entry(number).or_insert(Slot::Repeated(Vec::new())) else {
+                unreachable!("repeated fields are always repeated");
+            };
+            match value {
+                WireValue::Bytes(packed) if field.kind.packable() => {
+                    let mut packed = WireReader::new(packed);
+                    while !packed.is_empty() {
+                        let value = match field.kind {
+                            FieldKind::Double | FieldKind::Fixed64 | FieldKind::SFixed64 => {
+                                WireValue::Fixed64(packed.fixed64()?)
+                            }
+                            FieldKind::Float | FieldKind::Fixed32 | FieldKind::SFixed32 => {
+                                WireValue::Fixed32(packed.fixed32()?)
+                            }
+                            _ => WireValue::Varint(packed.varint()?),
+                        };
+                        values.push(decode_value(schema, &field.kind, value, depth, &packed)?);
+                    }
+                }
+                value => values.push(decode_value(schema, &field.kind, value, depth, &reader)?),
+            }
+        } else {
+            // A later occurrence of a singular field replaces the earlier one.
+            slots.insert(
+                number,
+                Slot::Single(decode_value(schema, &field.kind, value, depth, &reader)?),
+            );
+        }
+    }
+    let mut object = Map::new();
+    for (number, slot) in slots {
+        let value = match slot {
+            Slot::Single(value) => value,
+            Slot::Repeated(values) | Slot::Unknown(values) => Value::Array(values),
+            Slot::Map(map) => Value::Object(map.into_iter().collect()),
+        };
+        let key = match message.field(number) {
+            Some(field) => field.name.clone(),
+            None => format!("[{number}]"),
+        };
+        object.insert(key, value);
+    }
+    Ok(Value::Object(object))
+}
+
+/// Decodes one entry of a map field into its key, as a string, and its value. A missing key or
+/// value takes the default of its type.
+fn decode_map_entry(
+    schema: &ProtobufSchema,
+    entry: &MessageType,
+    value: WireValue,
+    depth: usize,
+    reader: &WireReader,
+) -> Result<(String, Value), DecodeError> {
+    let WireValue::Bytes(bytes) = value else {
+        return Err(reader.error("map entry is not length-delimited"));
+    };
+    let Value::Object(mut decoded) = decode_message(schema, entry, bytes, depth + 1)? else {
+        unreachable!("messages decode to objects");
+    };
+    let (Some(key_field), Some(value_field)) = (entry.field(1), entry.field(2)) else {
+        return Err(reader.error("map entry type lacks a key or value field"));
+    };
+    let key = match decoded.remove(&key_field.name) {
+        Some(Value::String(key)) => key,
+        Some(key) => key.to_string(),
+        None => match default_value(schema, &key_field.kind) {
+            Value::String(key) => key,
+            key => key.to_string(),
+        },
+    };
+    let value = decoded
+        .remove(&value_field.name)
+        .unwrap_or_else(|| default_value(schema, &value_field.kind));
+    Ok((key, value))
+}
+
+fn decode_value(
+    schema: &ProtobufSchema,
+    kind: &FieldKind,
+    value: WireValue,
+    depth: usize,
+    reader: &WireReader,
+) -> Result<Value, DecodeError> {
+    let decoded = match (kind, value) {
+        (FieldKind::Int64, WireValue::Varint(value)) => Value::from(value as i64),
+        (FieldKind::UInt64, WireValue::Varint(value)) => Value::from(value),
+        (FieldKind::Int32, WireValue::Varint(value)) => Value::from(value as i32),
+        (FieldKind::UInt32, WireValue::Varint(value)) => Value::from(value as u32),
+        (FieldKind::SInt32, WireValue::Varint(value)) => Value::from(zigzag(value) as i32),
+        (FieldKind::SInt64, WireValue::Varint(value)) => Value::from(zigzag(value)),
+        (FieldKind::Bool, WireValue::Varint(value)) => Value::Bool(value != 0),
+        (FieldKind::Enum(type_name), WireValue::Varint(value)) => enum_value(schema, type_name, value as i32),
+        (FieldKind::Double, WireValue::Fixed64(bits)) => float_value(f64::from_bits(bits)),
+        (FieldKind::Fixed64, WireValue::Fixed64(value)) => Value::from(value),
+        (FieldKind::SFixed64, WireValue::Fixed64(value)) => Value::from(value as i64),
+        // The shortest decimal that reads back as the same `f32`, rather than its `f64` widening.
+        (FieldKind::Float, WireValue::Fixed32(bits)) => {
+            float_value(f32::from_bits(bits).to_string().parse().unwrap_or(f64::NAN))
+        }
+        (FieldKind::Fixed32, WireValue::Fixed32(value)) => Value::from(value),
+        (FieldKind::SFixed32, WireValue::Fixed32(value)) => Value::from(value as i32),
+        (FieldKind::String, WireValue::Bytes(bytes)) => {
+            Value::String(String::from_utf8(bytes.to_vec()).map_err(|_| reader.error("string field is not UTF-8"))?)
+        }
+        (FieldKind::Bytes, WireValue::Bytes(bytes)) => Value::String(base64(bytes)),
+        (FieldKind::Message(type_name), WireValue::Bytes(bytes)) => {
+            decode_message(schema, &schema.messages[type_name], bytes, depth + 1)?
+        }
+        _ => return Err(reader.error("wire type does not match the field type")),
+    };
+    Ok(decoded)
+}
+
+/// A field the schema does not know, as it is on the wire.
+fn raw_value(value: WireValue) -> Value {
+    match value {
+        WireValue::Varint(value) | WireValue::Fixed64(value) => Value::from(value),
+        WireValue::Fixed32(value) => Value::from(value),
+        WireValue::Bytes(bytes) => Value::String(base64(bytes)),
+    }
+}
+
+fn default_value(schema: &ProtobufSchema, kind: &FieldKind) -> Value {
+    match kind {
+        FieldKind::Double | FieldKind::Float => Value::from(0.0),
+        FieldKind::Bool => Value::Bool(false),
+        FieldKind::String | FieldKind::Bytes => Value::String(String::new()),
+        FieldKind::Message(_) => Value::Object(Map::new()),
+        FieldKind::Enum(type_name) => enum_value(schema, type_name, 0),
+        _ => Value::from(0),
+    }
+}
+
+fn enum_value(schema: &ProtobufSchema, type_name: &str, number: i32) -> Value {
+    match schema.enums[type_name].get(&number) {
+        Some(name) => Value::String(name.clone()),
+        None => Value::from(number),
+    }
+}
+
+fn float_value(value: f64) -> Value {
+    match Number::from_f64(value) {
+        Some(number) => Value::Number(number),
+        None if value.is_nan() => Value::String("NaN".to_owned()),
+        None if value > 0.0 => Value::String("Infinity".to_owned()),
+        None => Value::String("-Infinity".to_owned()),
+    }
+}
+
+fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, &byte)| {
+            group | u32::from(byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(char::from(BASE64_ALPHABET[(group >> (18 - 6 * index)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
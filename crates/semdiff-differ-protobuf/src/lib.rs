@@ -0,0 +1,202 @@
+use mime::Mime;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, DiffCalculator, FormatRegression, MayUnsupported};
+use semdiff_differ_json::{JsonDiff, JsonDiffCalculator, JsonDiffReporter};
+use semdiff_output::collect::CollectReport;
+use semdiff_output::html::HtmlReport;
+use semdiff_output::json::JsonReport;
+use semdiff_output::summary::SummaryReport;
+use serde_json::Value;
+use std::convert;
+use std::io::Write;
+use std::sync::Arc;
+
+mod decode;
+mod schema;
+mod wire;
+
+#[cfg(test)]
+mod tests;
+
+pub use schema::{ProtobufSchema, SchemaError};
+pub use wire::DecodeError;
+
+/// Kind given to `*.pb` and `*.binpb` files by the file tree.
+pub const PROTOBUF_MIME: &str = "application/x-protobuf";
+
+const COMPARES_NAME: &str = "protobuf";
+
+/// Compares serialized protobuf messages by decoding both with a schema into JSON values, which
+/// are then diffed like JSON files.
+///
+/// Without a schema, or when a file does not decode as the schema's message type, the files are
+/// left to the binary differ.
+#[derive(Debug, Clone, Default)]
+pub struct ProtobufDiffCalculator {
+    schema: Option<Arc<ProtobufSchema>>,
+    json: JsonDiffCalculator,
+    unordered_repeated: bool,
+}
+
+impl ProtobufDiffCalculator {
+    pub fn with_schema(mut self, schema: Option<Arc<ProtobufSchema>>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Diffs the decoded messages with the options of `json`, such as ignored paths and rounding.
+    pub fn with_json_options(mut self, json: JsonDiffCalculator) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// Compares repeated fields regardless of the order of their elements. Map fields never
+    /// depend on order.
+    pub fn with_unordered_repeated(mut self, unordered_repeated: bool) -> Self {
+        self.unordered_repeated = unordered_repeated;
+        self
+    }
+
+    fn decode(&self, leaf: &FileLeaf) -> Option<Value> {
+        let mut value = decode_leaf(self.schema.as_deref()?, leaf)?;
+        if self.unordered_repeated {
+            decode::sort_arrays(&mut value);
+        }
+        Some(value)
+    }
+}
+
+impl DiffCalculator<FileLeaf> for ProtobufDiffCalculator {
+    type Error = convert::Infallible;
+    type Diff = JsonDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: FileLeaf,
+        actual: FileLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        let (Some(expected), Some(actual)) = (self.decode(&expected), self.decode(&actual)) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        Ok(MayUnsupported::Ok(self.json.diff_values(expected, actual)))
+    }
+
+    fn format_regression(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<FormatRegression> {
+        if self.schema.is_none() || !is_protobuf_mime(&expected.kind) || !is_protobuf_mime(&actual.kind) {
+            return None;
+        }
+        FormatRegression::from_parsed(
+            "protobuf",
+            self.decode(expected).is_some(),
+            self.decode(actual).is_some(),
+        )
+    }
+
+    fn unreadable(&self, leaf: &FileLeaf) -> bool {
+        self.schema.is_some() && is_protobuf_mime(&leaf.kind) && self.decode(leaf).is_none()
+    }
+}
+
+/// Reports decoded messages through the JSON differ's reporters, as compared by `protobuf`.
+#[derive(Debug, Clone, Default)]
+pub struct ProtobufDiffReporter {
+    schema: Option<Arc<ProtobufSchema>>,
+    json: JsonDiffReporter,
+}
+
+impl ProtobufDiffReporter {
+    /// The schema to decode added and deleted files with; the calculator's schema.
+    pub fn with_schema(mut self, schema: Option<Arc<ProtobufSchema>>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    pub fn with_syntax_highlight(mut self, syntax_highlight: bool) -> Self {
+        self.json = self.json.with_syntax_highlight(syntax_highlight);
+        self
+    }
+
+    fn json(&self) -> JsonDiffReporter {
+        self.json.with_compares_name(COMPARES_NAME)
+    }
+
+    /// An added or deleted file as the JSON file of its decoded message.
+    fn as_json_leaf(&self, leaf: &FileLeaf) -> Option<FileLeaf> {
+        let value = decode_leaf(self.schema.as_deref()?, leaf)?;
+        let content = serde_json::to_vec(&value).unwrap();
+        Some(FileLeaf::from_bytes(leaf.name.clone(), mime::APPLICATION_JSON, content))
+    }
+}
+
+macro_rules! report_through_json {
+    (impl<$($lifetime:lifetime)? $($generic:ident $(: $bound:path)?),*> $reporter:ty) => {
+        impl<$($lifetime)? $($generic $(: $bound)?),*> DetailReporter<JsonDiff, FileLeaf, $reporter> for ProtobufDiffReporter {
+            type Error = <JsonDiffReporter as DetailReporter<JsonDiff, FileLeaf, $reporter>>::Error;
+
+            fn report_unchanged(
+                &self,
+                name: &str,
+                diff: &JsonDiff,
+                reporter: &$reporter,
+            ) -> Result<MayUnsupported<()>, Self::Error> {
+                self.json().report_unchanged(name, diff, reporter)
+            }
+
+            fn report_modified(
+                &self,
+                name: &str,
+                diff: &JsonDiff,
+                reporter: &$reporter,
+            ) -> Result<MayUnsupported<()>, Self::Error> {
+                self.json().report_modified(name, diff, reporter)
+            }
+
+            fn report_added(
+                &self,
+                name: &str,
+                data: &FileLeaf,
+                reporter: &$reporter,
+            ) -> Result<MayUnsupported<()>, Self::Error> {
+                let Some(data) = self.as_json_leaf(data) else {
+                    return Ok(MayUnsupported::Unsupported);
+                };
+                self.json().report_added(name, &data, reporter)
+            }
+
+            fn report_deleted(
+                &self,
+                name: &str,
+                data: &FileLeaf,
+                reporter: &$reporter,
+            ) -> Result<MayUnsupported<()>, Self::Error> {
+                let Some(data) = self.as_json_leaf(data) else {
+                    return Ok(MayUnsupported::Unsupported);
+                };
+                self.json().report_deleted(name, &data, reporter)
+            }
+        }
+    };
+}
+
+report_through_json!(impl<'a> CollectReport<'a>);
+report_through_json!(impl<> HtmlReport);
+report_through_json!(impl<W: Write> JsonReport<W>);
+report_through_json!(impl<W> SummaryReport<W>);
+
+/// Kinds that are decoded with the schema. Protobuf has no registered MIME type, so the file
+/// tree gives this one to files by extension.
+pub fn supported_kinds() -> Vec<&'static str> {
+    vec![PROTOBUF_MIME]
+}
+
+fn is_protobuf_mime(kind: &Mime) -> bool {
+    kind.essence_str() == PROTOBUF_MIME
+}
+
+fn decode_leaf(schema: &ProtobufSchema, leaf: &FileLeaf) -> Option<Value> {
+    if !is_protobuf_mime(&leaf.kind) {
+        return None;
+    }
+    decode::decode(schema, &leaf.content).ok()
+}
@@ -0,0 +1,294 @@
+//! Message types read from a `FileDescriptorSet`, as written by
+//! `protoc --include_imports --descriptor_set_out`.
+
+use crate::wire::{DecodeError, WireReader, WireValue};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("invalid descriptor set: {0}")]
+    Malformed(#[from] DecodeError),
+    #[error("invalid descriptor set: {0}")]
+    Invalid(&'static str),
+    #[error("message type {0} is not in the descriptor set")]
+    UnknownMessage(String),
+    #[error("{field} refers to {type_name}, which is not in the descriptor set")]
+    UnknownType { field: String, type_name: String },
+}
+
+/// The message types of a descriptor set, with the one that files are decoded as.
+#[derive(Debug)]
+pub struct ProtobufSchema {
+    /// Message types by full name, without the leading dot.
+    pub(crate) messages: HashMap<String, MessageType>,
+    /// Enum types by full name: value names by number.
+    pub(crate) enums: HashMap<String, HashMap<i32, String>>,
+    pub(crate) root: String,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MessageType {
+    /// Fields in field number order.
+    pub(crate) fields: Vec<Field>,
+    /// A synthesized `map<K, V>` entry with the key as field 1 and the value as field 2.
+    pub(crate) map_entry: bool,
+}
+
+impl MessageType {
+    pub(crate) fn field(&self, number: u32) -> Option<&Field> {
+        self.fields
+            .binary_search_by_key(&number, |field| field.number)
+            .ok()
+            .map(|index| &self.fields[index])
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Field {
+    /// The name in JSON: `json_name` if the descriptor has one, else the field name.
+    pub(crate) name: String,
+    pub(crate) number: u32,
+    pub(crate) repeated: bool,
+    pub(crate) kind: FieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FieldKind {
+    Double,
+    Float,
+    Int64,
+    UInt64,
+    Int32,
+    Fixed64,
+    Fixed32,
+    Bool,
+    String,
+    /// A group or message, by full name.
+    Message(String),
+    Bytes,
+    UInt32,
+    /// By full name.
+    Enum(String),
+    SFixed32,
+    SFixed64,
+    SInt32,
+    SInt64,
+}
+
+impl FieldKind {
+    /// Whether repeated fields of this kind may be packed into one length-delimited value.
+    pub(crate) fn packable(&self) -> bool {
+        !matches!(self, FieldKind::String | FieldKind::Bytes | FieldKind::Message(_))
+    }
+}
+
+impl ProtobufSchema {
+    /// Reads the descriptor set `bytes`, decoding files as the message type `message`, given by
+    /// its full name such as `my.package.Message`.
+    pub fn from_descriptor_set(bytes: &[u8], message: &str) -> Result<ProtobufSchema, SchemaError> {
+        let mut schema = ProtobufSchema {
+            messages: HashMap::new(),
+            enums: HashMap::new(),
+            root: message.strip_prefix('.').unwrap_or(message).to_owned(),
+        };
+        let mut reader = WireReader::new(bytes);
+        while let Some((number, value)) = reader.field()? {
+            if number == 1 {
+                schema.read_file(bytes_of(value)?)?;
+            }
+        }
+        if !schema.messages.contains_key(&schema.root) {
+            return Err(SchemaError::UnknownMessage(schema.root));
+        }
+        schema.check_types()?;
+        Ok(schema)
+    }
+
+    /// Reads a `FileDescriptorProto`.
+    fn read_file(&mut self, bytes: &[u8]) -> Result<(), SchemaError> {
+        let mut package = String::new();
+        let mut messages = Vec::new();
+        let mut enums = Vec::new();
+        let mut reader = WireReader::new(bytes);
+        while let Some((number, value)) = reader.field()? {
+            match number {
+                2 => package = string_of(value)?,
+                4 => messages.push(bytes_of(value)?),
+                5 => enums.push(bytes_of(value)?),
+                _ => {}
+            }
+        }
+        for message in messages {
+            self.read_message(&package, message)?;
+        }
+        for enum_type in enums {
+            self.read_enum(&package, enum_type)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a `DescriptorProto` and the types nested in it, in the scope `scope`.
+    fn read_message(&mut self, scope: &str, bytes: &[u8]) -> Result<(), SchemaError> {
+        let mut name = String::new();
+        let mut message = MessageType::default();
+        let mut nested = Vec::new();
+        let mut enums = Vec::new();
+        let mut reader = WireReader::new(bytes);
+        while let Some((number, value)) = reader.field()? {
+            match number {
+                1 => name = string_of(value)?,
+                2 => message.fields.push(read_field(bytes_of(value)?)?),
+                3 => nested.push(bytes_of(value)?),
+                4 => enums.push(bytes_of(value)?),
+                7 => message.map_entry = read_map_entry_option(bytes_of(value)?)?,
+                _ => {}
+            }
+        }
+        let full_name = qualify(scope, &name);
+        for nested in nested {
+            self.read_message(&full_name, nested)?;
+        }
+        for enum_type in enums {
+            self.read_enum(&full_name, enum_type)?;
+        }
+        message.fields.sort_by_key(|field| field.number);
+        self.messages.insert(full_name, message);
+        Ok(())
+    }
+
+    /// Reads an `EnumDescriptorProto`.
+    fn read_enum(&mut self, scope: &str, bytes: &[u8]) -> Result<(), SchemaError> {
+        let mut name = String::new();
+        let mut values = HashMap::new();
+        let mut reader = WireReader::new(bytes);
+        while let Some((number, value)) = reader.field()? {
+            match number {
+                1 => name = string_of(value)?,
+                2 => {
+                    let (mut value_name, mut value_number) = (String::new(), 0);
+                    let mut reader = WireReader::new(bytes_of(value)?);
+                    while let Some((number, value)) = reader.field()? {
+                        match number {
+                            1 => value_name = string_of(value)?,
+                            2 => value_number = varint_of(value)? as i32,
+                            _ => {}
+                        }
+                    }
+                    // Aliases share a number; the first name is the canonical one.
+                    values.entry(value_number).or_insert(value_name);
+                }
+                _ => {}
+            }
+        }
+        self.enums.insert(qualify(scope, &name), values);
+        Ok(())
+    }
+
+    fn check_types(&self) -> Result<(), SchemaError> {
+        for message in self.messages.values() {
+            for field in &message.fields {
+                let type_name = match &field.kind {
+                    FieldKind::Message(type_name) if !self.messages.contains_key(type_name) => type_name,
+                    FieldKind::Enum(type_name) if !self.enums.contains_key(type_name) => type_name,
+                    _ => continue,
+                };
+                return Err(SchemaError::UnknownType {
+                    field: field.name.clone(),
+                    type_name: type_name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a `FieldDescriptorProto`.
+fn read_field(bytes: &[u8]) -> Result<Field, SchemaError> {
+    let mut name = String::new();
+    let mut json_name = None;
+    let mut field_number = 0;
+    let mut label = 0;
+    let mut field_type = 0;
+    let mut type_name = String::new();
+    let mut reader = WireReader::new(bytes);
+    while let Some((number, value)) = reader.field()? {
+        match number {
+            1 => name = string_of(value)?,
+            3 => field_number = varint_of(value)?,
+            4 => label = varint_of(value)?,
+            5 => field_type = varint_of(value)?,
+            6 => type_name = string_of(value)?,
+            10 => json_name = Some(string_of(value)?),
+            _ => {}
+        }
+    }
+    let type_name = type_name.strip_prefix('.').unwrap_or(&type_name).to_owned();
+    let kind = match field_type {
+        1 => FieldKind::Double,
+        2 => FieldKind::Float,
+        3 => FieldKind::Int64,
+        4 => FieldKind::UInt64,
+        5 => FieldKind::Int32,
+        6 => FieldKind::Fixed64,
+        7 => FieldKind::Fixed32,
+        8 => FieldKind::Bool,
+        9 => FieldKind::String,
+        10 | 11 => FieldKind::Message(type_name),
+        12 => FieldKind::Bytes,
+        13 => FieldKind::UInt32,
+        14 => FieldKind::Enum(type_name),
+        15 => FieldKind::SFixed32,
+        16 => FieldKind::SFixed64,
+        17 => FieldKind::SInt32,
+        18 => FieldKind::SInt64,
+        _ => return Err(SchemaError::Invalid("unknown field type")),
+    };
+    Ok(Field {
+        name: json_name.unwrap_or(name),
+        number: u32::try_from(field_number)
+            .ok()
+            .filter(|&number| number > 0)
+            .ok_or(SchemaError::Invalid("invalid field number"))?,
+        repeated: label == 3,
+        kind,
+    })
+}
+
+/// Reads `map_entry` (field 7) of `MessageOptions`.
+fn read_map_entry_option(bytes: &[u8]) -> Result<bool, SchemaError> {
+    let mut map_entry = false;
+    let mut reader = WireReader::new(bytes);
+    while let Some((number, value)) = reader.field()? {
+        if number == 7 {
+            map_entry = varint_of(value)? != 0;
+        }
+    }
+    Ok(map_entry)
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+fn bytes_of(value: WireValue<'_>) -> Result<&[u8], SchemaError> {
+    match value {
+        WireValue::Bytes(bytes) => Ok(bytes),
+        _ => Err(SchemaError::Invalid("expected a length-delimited field")),
+    }
+}
+
+fn string_of(value: WireValue<'_>) -> Result<String, SchemaError> {
+    String::from_utf8(bytes_of(value)?.to_vec()).map_err(|_| SchemaError::Invalid("name is not UTF-8"))
+}
+
+fn varint_of(value: WireValue<'_>) -> Result<u64, SchemaError> {
+    match value {
+        WireValue::Varint(value) => Ok(value),
+        _ => Err(SchemaError::Invalid("expected a varint field")),
+    }
+}
@@ -0,0 +1,221 @@
+use super::*;
+use semdiff_core::Diff;
+use serde_json::json;
+
+const OPTIONAL: u64 = 1;
+const REPEATED: u64 = 3;
+
+fn varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn varint_field(number: u64, value: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint(number << 3, &mut out);
+    varint(value, &mut out);
+    out
+}
+
+fn bytes_field(number: u64, bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint(number << 3 | 2, &mut out);
+    varint(bytes.len() as u64, &mut out);
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn fixed64_field(number: u64, value: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint(number << 3 | 1, &mut out);
+    out.extend_from_slice(&value.to_le_bytes());
+    out
+}
+
+fn field_descriptor(name: &str, number: u64, label: u64, field_type: u64, type_name: &str) -> Vec<u8> {
+    let mut field = [
+        bytes_field(1, name.as_bytes()),
+        varint_field(3, number),
+        varint_field(4, label),
+        varint_field(5, field_type),
+    ]
+    .concat();
+    if !type_name.is_empty() {
+        field.extend(bytes_field(6, type_name.as_bytes()));
+    }
+    bytes_field(2, &field)
+}
+
+/// ```proto
+/// package test;
+/// enum Color { RED = 0; GREEN = 1; }
+/// message Item { string name = 1; int32 count = 2; }
+/// message Root {
+///   string title = 1;
+///   repeated int32 values = 2;
+///   Item item = 3;
+///   Color color = 4;
+///   map<string, int64> tags = 5;
+///   repeated Item items = 6;
+///   bytes data = 7;
+///   sint32 delta = 8;
+///   double ratio = 9;
+/// }
+/// ```
+fn descriptor_set() -> Vec<u8> {
+    let item = [
+        bytes_field(1, b"Item"),
+        field_descriptor("name", 1, OPTIONAL, 9, ""),
+        field_descriptor("count", 2, OPTIONAL, 5, ""),
+    ]
+    .concat();
+    let tags_entry = [
+        bytes_field(1, b"TagsEntry"),
+        field_descriptor("key", 1, OPTIONAL, 9, ""),
+        field_descriptor("value", 2, OPTIONAL, 3, ""),
+        bytes_field(7, &varint_field(7, 1)),
+    ]
+    .concat();
+    let root = [
+        bytes_field(1, b"Root"),
+        field_descriptor("title", 1, OPTIONAL, 9, ""),
+        field_descriptor("values", 2, REPEATED, 5, ""),
+        field_descriptor("item", 3, OPTIONAL, 11, ".test.Item"),
+        field_descriptor("color", 4, OPTIONAL, 14, ".test.Color"),
+        field_descriptor("tags", 5, REPEATED, 11, ".test.Root.TagsEntry"),
+        field_descriptor("items", 6, REPEATED, 11, ".test.Item"),
+        field_descriptor("data", 7, OPTIONAL, 12, ""),
+        field_descriptor("delta", 8, OPTIONAL, 17, ""),
+        field_descriptor("ratio", 9, OPTIONAL, 1, ""),
+        bytes_field(3, &tags_entry),
+    ]
+    .concat();
+    let color = [
+        bytes_field(1, b"Color"),
+        bytes_field(2, &[bytes_field(1, b"RED"), varint_field(2, 0)].concat()),
+        bytes_field(2, &[bytes_field(1, b"GREEN"), varint_field(2, 1)].concat()),
+    ]
+    .concat();
+    let file = [
+        bytes_field(1, b"test.proto"),
+        bytes_field(2, b"test"),
+        bytes_field(4, &item),
+        bytes_field(4, &root),
+        bytes_field(5, &color),
+    ]
+    .concat();
+    bytes_field(1, &file)
+}
+
+fn schema() -> Arc<ProtobufSchema> {
+    Arc::new(ProtobufSchema::from_descriptor_set(&descriptor_set(), "test.Root").unwrap())
+}
+
+fn item(name: &str, count: u64) -> Vec<u8> {
+    [bytes_field(1, name.as_bytes()), varint_field(2, count)].concat()
+}
+
+fn leaf(content: Vec<u8>) -> FileLeaf {
+    FileLeaf::from_bytes("message.pb", PROTOBUF_MIME.parse().unwrap(), content)
+}
+
+fn calculator() -> ProtobufDiffCalculator {
+    ProtobufDiffCalculator::default().with_schema(Some(schema()))
+}
+
+fn equal(calculator: &ProtobufDiffCalculator, expected: Vec<u8>, actual: Vec<u8>) -> Option<bool> {
+    match calculator.diff("message.pb", leaf(expected), leaf(actual)).unwrap() {
+        MayUnsupported::Ok(diff) => Some(diff.equal()),
+        MayUnsupported::Unsupported => None,
+    }
+}
+
+#[test]
+fn decode_maps_fields_to_their_json_names() {
+    let mut packed = Vec::new();
+    for value in [3, 1, 2] {
+        varint(value, &mut packed);
+    }
+    let message = [
+        // Fields on the wire out of order, and an unknown field 15.
+        varint_field(4, 1),
+        bytes_field(1, b"hello"),
+        bytes_field(2, &packed),
+        bytes_field(3, &item("a", 7)),
+        bytes_field(5, &[bytes_field(1, b"b"), varint_field(2, 2)].concat()),
+        bytes_field(5, &[bytes_field(1, b"a"), varint_field(2, 1)].concat()),
+        bytes_field(6, &item("x", 1)),
+        bytes_field(7, b"\x00\xff\x10"),
+        varint_field(8, 3),
+        fixed64_field(9, 0.5f64.to_bits()),
+        varint_field(15, 42),
+    ]
+    .concat();
+
+    assert_eq!(
+        decode::decode(&schema(), &message).unwrap(),
+        json!({
+            "title": "hello",
+            "values": [3, 1, 2],
+            "item": { "name": "a", "count": 7 },
+            "color": "GREEN",
+            "tags": { "a": 1, "b": 2 },
+            "items": [{ "name": "x", "count": 1 }],
+            "data": "AP8Q",
+            "delta": -2,
+            "ratio": 0.5,
+            "[15]": [42],
+        })
+    );
+    assert!(decode::decode(&schema(), &[0x0a, 0x05, b'h']).is_err());
+}
+
+#[test]
+fn diff_compares_decoded_messages() {
+    let calculator = calculator();
+    let title = |title: &str| bytes_field(1, title.as_bytes());
+    let items = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| bytes_field(6, &item(name, 1)))
+            .collect::<Vec<_>>()
+    };
+
+    // Field order on the wire does not matter, but values do.
+    assert_eq!(
+        equal(
+            &calculator,
+            [title("a"), varint_field(4, 1)].concat(),
+            [varint_field(4, 1), title("a")].concat()
+        ),
+        Some(true)
+    );
+    assert_eq!(equal(&calculator, title("a"), title("b")), Some(false));
+
+    // Repeated fields keep their order unless told otherwise.
+    assert_eq!(
+        equal(&calculator, items(&["x", "y"]).concat(), items(&["y", "x"]).concat()),
+        Some(false)
+    );
+    let unordered = calculator.clone().with_unordered_repeated(true);
+    assert_eq!(
+        equal(&unordered, items(&["x", "y"]).concat(), items(&["y", "x"]).concat()),
+        Some(true)
+    );
+
+    // Messages that do not decode, and any message without a schema, are left to other differs.
+    assert_eq!(equal(&calculator, title("a"), vec![0xff]), None);
+    assert_eq!(equal(&ProtobufDiffCalculator::default(), title("a"), title("a")), None);
+    assert!(calculator.unreadable(&leaf(vec![0xff])));
+}
+
+#[test]
+fn schema_needs_the_message_type() {
+    assert!(matches!(
+        ProtobufSchema::from_descriptor_set(&descriptor_set(), "test.Missing"),
+        Err(SchemaError::UnknownMessage(name)) if name == "test.Missing"
+    ));
+}
@@ -0,0 +1,152 @@
+//! Reader for the protobuf wire format, shared by the descriptor parser and the message decoder.
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("malformed protobuf at byte {offset}: {message}")]
+pub struct DecodeError {
+    offset: usize,
+    message: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WireType {
+    Varint,
+    Fixed64,
+    Len,
+    StartGroup,
+    EndGroup,
+    Fixed32,
+}
+
+/// A field value as it appears on the wire, before its type is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Fixed32(u32),
+    /// A length-delimited value, or the contents of a group.
+    Bytes(&'a [u8]),
+}
+
+pub(crate) struct WireReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> WireReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> WireReader<'a> {
+        WireReader { data, offset: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offset == self.data.len()
+    }
+
+    pub(crate) fn error(&self, message: &'static str) -> DecodeError {
+        DecodeError {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..70).step_by(7) {
+            let Some(&byte) = self.data.get(self.offset) else {
+                return Err(self.error("truncated varint"));
+            };
+            self.offset += 1;
+            if shift == 63 && byte > 1 {
+                return Err(self.error("varint overflows 64 bits"));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("varint longer than 10 bytes"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.error("value runs past the end"))?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn fixed32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn fixed64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads the key of the next field: its number and wire type.
+    pub(crate) fn key(&mut self) -> Result<(u32, WireType), DecodeError> {
+        let key = self.varint()?;
+        let wire_type = match key & 7 {
+            0 => WireType::Varint,
+            1 => WireType::Fixed64,
+            2 => WireType::Len,
+            3 => WireType::StartGroup,
+            4 => WireType::EndGroup,
+            5 => WireType::Fixed32,
+            _ => return Err(self.error("invalid wire type")),
+        };
+        let number = u32::try_from(key >> 3)
+            .ok()
+            .filter(|&number| number > 0)
+            .ok_or_else(|| self.error("invalid field number"))?;
+        Ok((number, wire_type))
+    }
+
+    /// Reads the next field, or `None` at the end of the data.
+    pub(crate) fn field(&mut self) -> Result<Option<(u32, WireValue<'a>)>, DecodeError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let (number, wire_type) = self.key()?;
+        let value = match wire_type {
+            WireType::Varint => WireValue::Varint(self.varint()?),
+            WireType::Fixed64 => WireValue::Fixed64(self.fixed64()?),
+            WireType::Fixed32 => WireValue::Fixed32(self.fixed32()?),
+            WireType::Len => {
+                let len = usize::try_from(self.varint()?).map_err(|_| self.error("length too large"))?;
+                WireValue::Bytes(self.take(len)?)
+            }
+            WireType::StartGroup => WireValue::Bytes(self.group(number)?),
+            WireType::EndGroup => return Err(self.error("unexpected end of group")),
+        };
+        Ok(Some((number, value)))
+    }
+
+    /// Reads the fields of the group `number` up to its end, returning their bytes.
+    fn group(&mut self, number: u32) -> Result<&'a [u8], DecodeError> {
+        let start = self.offset;
+        loop {
+            if self.is_empty() {
+                return Err(self.error("unterminated group"));
+            }
+            let end = self.offset;
+            let (field, wire_type) = self.key()?;
+            match wire_type {
+                WireType::EndGroup if field == number => return Ok(&self.data[start..end]),
+                WireType::EndGroup => return Err(self.error("mismatched end of group")),
+                WireType::Varint => drop(self.varint()?),
+                WireType::Fixed64 => drop(self.fixed64()?),
+                WireType::Fixed32 => drop(self.fixed32()?),
+                WireType::Len => {
+                    let len = usize::try_from(self.varint()?).map_err(|_| self.error("length too large"))?;
+                    self.take(len)?;
+                }
+                WireType::StartGroup => drop(self.group(field)?),
+            }
+        }
+    }
+}
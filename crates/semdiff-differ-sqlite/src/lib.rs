@@ -0,0 +1,609 @@
+use crate::sqlite::{Database, RawRow, SchemaObject, SqliteError, TableDefinition, Value};
+use mime::Mime;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use serde::Serialize;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::{convert, fmt};
+
+pub mod report_collect;
+pub mod report_html;
+pub mod report_json;
+pub mod report_summary;
+mod sqlite;
+
+#[cfg(test)]
+mod tests;
+
+pub const SQLITE_MIME: &str = "application/vnd.sqlite3";
+
+/// Number of row changes kept per table by default; see [`SqliteDiffCalculator::with_max_row_changes`].
+pub const DEFAULT_MAX_ROW_CHANGES: usize = 100;
+
+pub struct SqliteDiffReporter;
+
+#[derive(Debug)]
+pub struct SqliteDiff {
+    schema_changes: Vec<SchemaChange>,
+    tables: Vec<TableDiff>,
+    /// Tables found in both databases, and the rows compared over all of them.
+    compared_tables: usize,
+    compared_rows: usize,
+}
+
+impl Diff for SqliteDiff {
+    fn equal(&self) -> bool {
+        self.schema_changes.is_empty() && self.tables.is_empty()
+    }
+
+    fn magnitude(&self) -> Option<f64> {
+        let changed_rows = self.tables.iter().map(TableDiff::changed_rows).sum::<usize>();
+        if self.compared_rows == 0 {
+            return Some(if self.equal() { 0.0 } else { 1.0 });
+        }
+        Some(changed_rows as f64 / self.compared_rows as f64)
+    }
+}
+
+impl SqliteDiff {
+    fn schema_changes(&self) -> &[SchemaChange] {
+        &self.schema_changes
+    }
+
+    fn tables(&self) -> &[TableDiff] {
+        &self.tables
+    }
+
+    /// One line per schema change and per table with changed rows, for previews.
+    fn change_lines(&self) -> Vec<String> {
+        let schema = self.schema_changes.iter().map(ToString::to_string);
+        let tables = self
+            .tables
+            .iter()
+            .map(|table| format!("{}: {}", table.table, table.counts()));
+        schema.chain(tables).collect()
+    }
+}
+
+/// A difference between the `sqlite_schema` entries (tables, indexes, views, and triggers) of two
+/// databases.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaChange {
+    Added {
+        object_type: String,
+        name: String,
+        sql: Option<String>,
+    },
+    Removed {
+        object_type: String,
+        name: String,
+        sql: Option<String>,
+    },
+    Changed {
+        object_type: String,
+        name: String,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::Added { object_type, name, .. } => write!(f, "{object_type} {name} added"),
+            SchemaChange::Removed { object_type, name, .. } => write!(f, "{object_type} {name} removed"),
+            SchemaChange::Changed { object_type, name, .. } => write!(f, "{object_type} {name} changed"),
+        }
+    }
+}
+
+/// Row differences of a table that exists in both databases. `key` names the row by its primary
+/// key like `id = 3`, or by all of its columns for tables without one, and values are SQL
+/// literals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    /// The first row changes in key order, up to the calculator's limit.
+    pub rows: Vec<RowChange>,
+    /// Whether more rows changed than `rows` holds.
+    pub truncated: bool,
+}
+
+impl TableDiff {
+    fn changed_rows(&self) -> usize {
+        self.added + self.removed + self.changed
+    }
+
+    fn counts(&self) -> String {
+        format!("+{} -{} ~{} rows", self.added, self.removed, self.changed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RowChange {
+    Added { key: String },
+    Removed { key: String },
+    Changed { key: String, columns: Vec<ColumnChange> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnChange {
+    pub column: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for RowChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowChange::Added { key } => write!(f, "+ {key}"),
+            RowChange::Removed { key } => write!(f, "- {key}"),
+            RowChange::Changed { key, columns } => {
+                write!(f, "~ {key}:")?;
+                for (index, change) in columns.iter().enumerate() {
+                    let separator = if index == 0 { " " } else { ", " };
+                    write!(
+                        f,
+                        "{separator}{} {} → {}",
+                        change.column, change.expected, change.actual
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compares SQLite databases by content: the schema, and the rows of each table paired up by
+/// primary key, so files that differ only in page layout (after a `VACUUM`, or from a different
+/// insertion order) are equal.
+///
+/// Tables keyed by an `INTEGER PRIMARY KEY` rowid alias or declared `WITHOUT ROWID` are stored in
+/// key order, and are streamed from both files at once when both sides are one of these. Only the
+/// other tables are gathered in memory first: those with a primary key that is not a rowid alias,
+/// and those without one, whose rows are compared as a whole since `VACUUM` may renumber their
+/// rowids. A table whose rows turn out not to be stored in key order, such as one with a `DESC`
+/// or `COLLATE` key, is gathered in memory too.
+#[derive(Debug)]
+pub struct SqliteDiffCalculator {
+    max_row_changes: usize,
+}
+
+impl Default for SqliteDiffCalculator {
+    fn default() -> SqliteDiffCalculator {
+        SqliteDiffCalculator {
+            max_row_changes: DEFAULT_MAX_ROW_CHANGES,
+        }
+    }
+}
+
+impl SqliteDiffCalculator {
+    /// Keeps at most `max` row changes per table in the diff; further changes are still counted.
+    pub fn with_max_row_changes(self, max: usize) -> SqliteDiffCalculator {
+        SqliteDiffCalculator { max_row_changes: max }
+    }
+}
+
+impl DiffCalculator<FileLeaf> for SqliteDiffCalculator {
+    type Error = convert::Infallible;
+    type Diff = SqliteDiff;
+
+    fn diff(
+        &self,
+        _name: &str,
+        expected: FileLeaf,
+        actual: FileLeaf,
+    ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
+        if !is_sqlite_file(&expected.kind, &expected.content) || !is_sqlite_file(&actual.kind, &actual.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        // A file that cannot be read as a database is left to the binary differ.
+        match self.compare(&expected.content, &actual.content) {
+            Ok(diff) => Ok(MayUnsupported::Ok(diff)),
+            Err(_) => Ok(MayUnsupported::Unsupported),
+        }
+    }
+}
+
+impl SqliteDiffCalculator {
+    fn compare(&self, expected: &[u8], actual: &[u8]) -> Result<SqliteDiff, SqliteError> {
+        let expected = Database::open(expected)?;
+        let actual = Database::open(actual)?;
+        let expected_schema = expected.schema()?;
+        let actual_schema = actual.schema()?;
+        let schema_changes = compare_schemas(&expected_schema, &actual_schema);
+        let mut tables = Vec::new();
+        let mut compared_tables = 0;
+        let mut compared_rows = 0;
+        for expected_table in expected_schema.iter().filter(|object| is_table(object)) {
+            let Some(actual_table) = actual_schema
+                .iter()
+                .find(|object| is_table(object) && object.name == expected_table.name)
+            else {
+                continue;
+            };
+            let Some(expected_table) = Table::new(expected_table) else {
+                continue;
+            };
+            let Some(actual_table) = Table::new(actual_table) else {
+                continue;
+            };
+            let mut table_diff = TableDiff {
+                table: expected_table.name.clone(),
+                added: 0,
+                removed: 0,
+                changed: 0,
+                rows: Vec::new(),
+                truncated: false,
+            };
+            compared_tables += 1;
+            compared_rows += self.compare_rows(&expected, &expected_table, &actual, &actual_table, &mut table_diff)?;
+            if table_diff.changed_rows() > 0 {
+                tables.push(table_diff);
+            }
+        }
+        Ok(SqliteDiff {
+            schema_changes,
+            tables,
+            compared_tables,
+            compared_rows,
+        })
+    }
+
+    /// Pairs up the rows of both tables by key and records how they differ, returning the number
+    /// of distinct keys seen.
+    fn compare_rows(
+        &self,
+        expected_db: &Database,
+        expected: &Table,
+        actual_db: &Database,
+        actual: &Table,
+        diff: &mut TableDiff,
+    ) -> Result<usize, SqliteError> {
+        let key_columns = expected.key_columns();
+        // Tables whose keys do not match are only reported through their schema change.
+        if key_columns != actual.key_columns() {
+            return Ok(0);
+        }
+        let common = expected
+            .definition
+            .columns
+            .iter()
+            .enumerate()
+            .filter_map(|(index, column)| Some((column.clone(), index, actual.column_index(column)?)))
+            .collect::<Vec<_>>();
+        let record =
+            |diff: &mut TableDiff, expected_row: Option<&[Value]>, actual_row: Option<&[Value]>, key: &[Value]| {
+                let change = match (expected_row, actual_row) {
+                    (Some(expected_row), Some(actual_row)) => {
+                        let columns = common
+                            .iter()
+                            .filter(|(_, e, a)| expected_row.get(*e) != actual_row.get(*a))
+                            .map(|(column, e, a)| ColumnChange {
+                                column: column.clone(),
+                                expected: value_literal(expected_row.get(*e)),
+                                actual: value_literal(actual_row.get(*a)),
+                            })
+                            .collect::<Vec<_>>();
+                        if columns.is_empty() {
+                            return;
+                        }
+                        diff.changed += 1;
+                        RowChange::Changed {
+                            key: key_label(&key_columns, key),
+                            columns,
+                        }
+                    }
+                    (Some(_), None) => {
+                        diff.removed += 1;
+                        RowChange::Removed {
+                            key: key_label(&key_columns, key),
+                        }
+                    }
+                    (None, Some(_)) => {
+                        diff.added += 1;
+                        RowChange::Added {
+                            key: key_label(&key_columns, key),
+                        }
+                    }
+                    (None, None) => unreachable!(),
+                };
+                if diff.rows.len() < self.max_row_changes {
+                    diff.rows.push(change);
+                } else {
+                    diff.truncated = true;
+                }
+            };
+        if expected.stored_in_key_order() && actual.stored_in_key_order() {
+            let unordered = Cell::new(false);
+            let keys = merge_rows(
+                in_key_order(
+                    expected_db.rows(expected.root_page).map(|row| expected.keyed(row?)),
+                    &unordered,
+                ),
+                in_key_order(
+                    actual_db.rows(actual.root_page).map(|row| actual.keyed(row?)),
+                    &unordered,
+                ),
+                &mut |expected_row, actual_row, key| record(diff, expected_row, actual_row, key),
+            )?;
+            if !unordered.get() {
+                return Ok(keys);
+            }
+            // The merge stopped partway, so the table is compared again from the start.
+            *diff = TableDiff {
+                table: diff.table.clone(),
+                added: 0,
+                removed: 0,
+                changed: 0,
+                rows: Vec::new(),
+                truncated: false,
+            };
+        }
+        let expected_rows = collect_rows(expected_db, expected)?;
+        let actual_rows = collect_rows(actual_db, actual)?;
+        merge_rows(
+            expected_rows.into_iter().map(Ok),
+            actual_rows.into_iter().map(Ok),
+            &mut |expected_row, actual_row, key| record(diff, expected_row, actual_row, key),
+        )
+    }
+}
+
+/// Passes on `rows` while their keys strictly increase, and ends them early, setting `unordered`,
+/// at the first key that does not.
+fn in_key_order<'a>(
+    rows: impl Iterator<Item = Result<KeyedRow, SqliteError>> + 'a,
+    unordered: &'a Cell<bool>,
+) -> impl Iterator<Item = Result<KeyedRow, SqliteError>> + 'a {
+    let mut previous = None::<Vec<Value>>;
+    rows.map_while(move |row| {
+        if let Ok((key, _)) = &row {
+            if previous.as_ref().is_some_and(|previous| previous >= key) {
+                unordered.set(true);
+            }
+            previous = Some(key.clone());
+        }
+        (!unordered.get()).then_some(row)
+    })
+}
+
+type KeyedRow = (Vec<Value>, Vec<Value>);
+
+/// Walks two sequences of rows sorted by key side by side, like a merge join.
+fn merge_rows(
+    expected: impl Iterator<Item = Result<KeyedRow, SqliteError>>,
+    actual: impl Iterator<Item = Result<KeyedRow, SqliteError>>,
+    record: &mut impl FnMut(Option<&[Value]>, Option<&[Value]>, &[Value]),
+) -> Result<usize, SqliteError> {
+    let mut expected = expected.peekable();
+    let mut actual = actual.peekable();
+    let mut keys = 0;
+    loop {
+        let order = match (expected.peek(), actual.peek()) {
+            (None, None) => return Ok(keys),
+            (Some(Err(_)), _) => return Err(expected.next().unwrap().unwrap_err()),
+            (_, Some(Err(_))) => return Err(actual.next().unwrap().unwrap_err()),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(Ok((expected_key, _))), Some(Ok((actual_key, _)))) => expected_key.cmp(actual_key),
+        };
+        keys += 1;
+        match order {
+            Ordering::Less => {
+                let (key, row) = expected.next().unwrap()?;
+                record(Some(&row), None, &key);
+            }
+            Ordering::Greater => {
+                let (key, row) = actual.next().unwrap()?;
+                record(None, Some(&row), &key);
+            }
+            Ordering::Equal => {
+                let (key, expected_row) = expected.next().unwrap()?;
+                let (_, actual_row) = actual.next().unwrap()?;
+                record(Some(&expected_row), Some(&actual_row), &key);
+            }
+        }
+    }
+}
+
+fn collect_rows(database: &Database, table: &Table) -> Result<BTreeMap<Vec<Value>, Vec<Value>>, SqliteError> {
+    let mut rows = BTreeMap::new();
+    let mut occurrences = BTreeMap::<Vec<Value>, i64>::new();
+    for row in database.rows(table.root_page) {
+        let (mut key, values) = table.keyed(row?)?;
+        if table.definition.primary_key.is_empty() {
+            // Identical rows of a table without a key are told apart by their count.
+            let occurrence = occurrences.entry(key.clone()).or_default();
+            key.push(Value::Integer(*occurrence));
+            *occurrence += 1;
+        }
+        rows.insert(key, values);
+    }
+    Ok(rows)
+}
+
+/// A table with rows to compare, as declared in one database.
+struct Table {
+    name: String,
+    root_page: u32,
+    definition: TableDefinition,
+    rowid_alias: Option<usize>,
+}
+
+impl Table {
+    fn new(object: &SchemaObject) -> Option<Table> {
+        if object.root_page == 0 {
+            return None;
+        }
+        let (definition, declared_types) = sqlite::parse_create_table(object.sql.as_deref()?)?;
+        let rowid_alias = definition.rowid_alias(&declared_types);
+        Some(Table {
+            name: object.name.clone(),
+            root_page: object.root_page,
+            definition,
+            rowid_alias,
+        })
+    }
+
+    /// Whether the rows are stored ordered by their key, unless the key is declared `DESC` or
+    /// with a collation.
+    fn stored_in_key_order(&self) -> bool {
+        self.rowid_alias.is_some() || self.definition.without_rowid
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.definition
+            .columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+    }
+
+    /// The primary key columns, or all columns if there is no primary key.
+    fn key_columns(&self) -> Vec<String> {
+        let columns = &self.definition.columns;
+        if self.definition.primary_key.is_empty() {
+            return columns.iter().map(|column| column.to_ascii_lowercase()).collect();
+        }
+        self.definition
+            .primary_key
+            .iter()
+            .map(|&index| columns[index].to_ascii_lowercase())
+            .collect()
+    }
+
+    /// Puts the values of a stored row in column order and pairs them with the row's key.
+    fn keyed(&self, row: RawRow) -> Result<KeyedRow, SqliteError> {
+        let RawRow { rowid, values } = row;
+        let columns = self.definition.columns.len();
+        let mut values = if self.definition.without_rowid {
+            // WITHOUT ROWID records hold the primary key columns first, then the other columns
+            // in declaration order.
+            let primary_key = &self.definition.primary_key;
+            let mut ordered = vec![Value::Null; columns];
+            let others = (0..columns).filter(|index| !primary_key.contains(index));
+            for (index, value) in primary_key.iter().copied().chain(others).zip(values) {
+                ordered[index] = value;
+            }
+            ordered
+        } else {
+            values
+        };
+        // Columns added by ALTER TABLE are missing from older records.
+        values.resize(columns, Value::Null);
+        if let (Some(alias), Some(rowid)) = (self.rowid_alias, rowid) {
+            values[alias] = Value::Integer(rowid);
+        }
+        let key = if self.definition.primary_key.is_empty() {
+            values.clone()
+        } else {
+            self.definition
+                .primary_key
+                .iter()
+                .map(|&index| values[index].clone())
+                .collect()
+        };
+        Ok((key, values))
+    }
+}
+
+fn is_table(object: &SchemaObject) -> bool {
+    object.kind == "table"
+}
+
+fn compare_schemas(expected: &[SchemaObject], actual: &[SchemaObject]) -> Vec<SchemaChange> {
+    let index = |objects: &[SchemaObject]| {
+        objects
+            .iter()
+            .map(|object| {
+                (
+                    (object.kind.clone(), object.name.clone()),
+                    object.sql.as_deref().map(normalize_sql),
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let expected = index(expected);
+    let actual = index(actual);
+    let mut changes = Vec::new();
+    for ((object_type, name), expected_sql) in &expected {
+        match actual.get(&(object_type.clone(), name.clone())) {
+            None => changes.push(SchemaChange::Removed {
+                object_type: object_type.clone(),
+                name: name.clone(),
+                sql: expected_sql.clone(),
+            }),
+            Some(actual_sql) if actual_sql != expected_sql => changes.push(SchemaChange::Changed {
+                object_type: object_type.clone(),
+                name: name.clone(),
+                expected: expected_sql.clone(),
+                actual: actual_sql.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for ((object_type, name), actual_sql) in &actual {
+        if !expected.contains_key(&(object_type.clone(), name.clone())) {
+            changes.push(SchemaChange::Added {
+                object_type: object_type.clone(),
+                name: name.clone(),
+                sql: actual_sql.clone(),
+            });
+        }
+    }
+    changes
+}
+
+/// Collapses runs of whitespace, so reformatted but equivalent statements compare equal.
+fn normalize_sql(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn key_label(columns: &[String], key: &[Value]) -> String {
+    columns
+        .iter()
+        .zip(key)
+        .map(|(column, value)| format!("{column} = {value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn value_literal(value: Option<&Value>) -> String {
+    value.unwrap_or(&Value::Null).to_string()
+}
+
+/// Kinds that are opened as SQLite databases, when they start with the SQLite header.
+pub fn supported_kinds() -> Vec<&'static str> {
+    vec![SQLITE_MIME]
+}
+
+fn is_sqlite_mime(kind: &Mime) -> bool {
+    kind.essence_str() == SQLITE_MIME
+}
+
+fn is_sqlite_file(kind: &Mime, content: &[u8]) -> bool {
+    is_sqlite_mime(kind) && content.starts_with(sqlite::MAGIC)
+}
+
+/// Table names with their row counts, for showing an added or deleted database in reports, or
+/// `None` if the file cannot be read as one.
+fn table_overview(kind: &Mime, content: &[u8]) -> Option<Vec<(String, usize)>> {
+    if !is_sqlite_file(kind, content) {
+        return None;
+    }
+    let database = Database::open(content).ok()?;
+    database
+        .schema()
+        .ok()?
+        .iter()
+        .filter(|object| is_table(object) && object.root_page != 0)
+        .map(|object| Some((object.name.clone(), database.rows(object.root_page).count())))
+        .collect()
+}
@@ -0,0 +1,137 @@
+use crate::{SchemaChange, SqliteDiff, SqliteDiffReporter, TableDiff, table_overview};
+use askama::Template;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::html::{HtmlReport, HtmlReportError};
+use thiserror::Error;
+
+const COMPARES_NAME: &str = "sqlite";
+
+/// Lines shown in the preview of a modified database.
+const PREVIEW_LINES: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum SqliteDiffReportError {
+    #[error("html report error: {0}")]
+    HtmlReport(#[from] HtmlReportError),
+}
+
+#[derive(Template)]
+#[template(path = "sqlite_preview.html")]
+struct SqlitePreviewTemplate<'a> {
+    body: SqlitePreviewBody<'a>,
+}
+
+enum SqlitePreviewBody<'a> {
+    Unchanged { tables: usize, rows: usize },
+    Modified { lines: Vec<String> },
+    Added { tables: &'a [(String, usize)] },
+    Deleted { tables: &'a [(String, usize)] },
+}
+
+#[derive(Template)]
+#[template(path = "sqlite_detail.html")]
+struct SqliteDetailTemplate<'a> {
+    detail: SqliteDetailBody<'a>,
+}
+
+enum SqliteDetailBody<'a> {
+    Unchanged {
+        tables: usize,
+        rows: usize,
+    },
+    Diff {
+        schema_changes: &'a [SchemaChange],
+        tables: &'a [TableDiff],
+    },
+    Single {
+        label: &'a str,
+        tables: &'a [(String, usize)],
+    },
+}
+
+impl DetailReporter<SqliteDiff, FileLeaf, HtmlReport> for SqliteDiffReporter {
+    type Error = SqliteDiffReportError;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        diff: &SqliteDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let (tables, rows) = (diff.compared_tables, diff.compared_rows);
+        let preview_html = SqlitePreviewTemplate {
+            body: SqlitePreviewBody::Unchanged { tables, rows },
+        };
+        let detail_html = SqliteDetailTemplate {
+            detail: SqliteDetailBody::Unchanged { tables, rows },
+        };
+        reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &SqliteDiff,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let mut lines = diff.change_lines();
+        lines.truncate(PREVIEW_LINES);
+        let preview_html = SqlitePreviewTemplate {
+            body: SqlitePreviewBody::Modified { lines },
+        };
+        let detail_html = SqliteDetailTemplate {
+            detail: SqliteDetailBody::Diff {
+                schema_changes: diff.schema_changes(),
+                tables: diff.tables(),
+            },
+        };
+        reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(tables) = table_overview(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = SqlitePreviewTemplate {
+            body: SqlitePreviewBody::Added { tables: &tables },
+        };
+        let detail_html = SqliteDetailTemplate {
+            detail: SqliteDetailBody::Single {
+                label: "added",
+                tables: &tables,
+            },
+        };
+        reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &HtmlReport,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        let Some(tables) = table_overview(&data.kind, &data.content) else {
+            return Ok(MayUnsupported::Unsupported);
+        };
+        let preview_html = SqlitePreviewTemplate {
+            body: SqlitePreviewBody::Deleted { tables: &tables },
+        };
+        let detail_html = SqliteDetailTemplate {
+            detail: SqliteDetailBody::Single {
+                label: "deleted",
+                tables: &tables,
+            },
+        };
+        reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
+        Ok(MayUnsupported::Ok(()))
+    }
+}
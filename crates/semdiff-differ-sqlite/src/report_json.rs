@@ -0,0 +1,71 @@
+use crate::{SchemaChange, SqliteDiff, SqliteDiffReporter, TableDiff, is_sqlite_file};
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{DetailReporter, MayUnsupported};
+use semdiff_output::json::JsonReport;
+use serde::Serialize;
+use std::convert;
+use std::io::Write;
+
+const COMPARES_NAME: &str = "sqlite";
+
+impl<W: Write> DetailReporter<SqliteDiff, FileLeaf, JsonReport<W>> for SqliteDiffReporter {
+    type Error = convert::Infallible;
+
+    fn report_unchanged(
+        &self,
+        name: &str,
+        _diff: &SqliteDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        reporter.record_unchanged(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_modified(
+        &self,
+        name: &str,
+        diff: &SqliteDiff,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        #[derive(Debug, Serialize)]
+        struct Report<'a> {
+            schema_changes: &'a [SchemaChange],
+            tables: &'a [TableDiff],
+        }
+        reporter.record_modified(
+            name,
+            COMPARES_NAME,
+            Report {
+                schema_changes: diff.schema_changes(),
+                tables: diff.tables(),
+            },
+        );
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_added(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_sqlite_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_added(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+
+    fn report_deleted(
+        &self,
+        name: &str,
+        data: &FileLeaf,
+        reporter: &JsonReport<W>,
+    ) -> Result<MayUnsupported<()>, Self::Error> {
+        if !is_sqlite_file(&data.kind, &data.content) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        reporter.record_deleted(name, COMPARES_NAME, ());
+        Ok(MayUnsupported::Ok(()))
+    }
+}
@@ -0,0 +1,662 @@
+//! A read-only reader for the SQLite 3 file format: the schema table, and the rows of each table
+//! walked in b-tree order straight from the pages, without loading whole tables.
+//!
+//! Only the main database file is read; changes still sitting in a `-wal` file are not seen.
+
+use std::cmp::Ordering;
+use std::fmt;
+use thiserror::Error;
+
+pub const MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// Deepest b-tree accepted before the file is considered corrupt (real trees stay far below it).
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum SqliteError {
+    #[error("not a SQLite 3 database")]
+    NotSqlite,
+    #[error("corrupt database: {0}")]
+    Corrupt(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+pub struct Database<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    usable_size: usize,
+    page_count: u32,
+    encoding: TextEncoding,
+}
+
+/// A value as stored in a record.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// One row of `sqlite_schema`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaObject {
+    pub kind: String,
+    pub name: String,
+    pub table_name: String,
+    pub root_page: u32,
+    pub sql: Option<String>,
+}
+
+/// A row read from a table b-tree: its rowid (absent in `WITHOUT ROWID` tables) and the values of
+/// its record in storage order.
+#[derive(Debug)]
+pub struct RawRow {
+    pub rowid: Option<i64>,
+    pub values: Vec<Value>,
+}
+
+impl<'a> Database<'a> {
+    pub fn open(data: &'a [u8]) -> Result<Database<'a>, SqliteError> {
+        if data.len() < 100 || !data.starts_with(MAGIC) {
+            return Err(SqliteError::NotSqlite);
+        }
+        let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+            1 => 65536,
+            size if size >= 512 && size.is_power_of_two() => usize::from(size),
+            _ => return Err(SqliteError::Corrupt("invalid page size")),
+        };
+        let usable_size = page_size - usize::from(data[20]);
+        if usable_size < 480 {
+            return Err(SqliteError::Corrupt("invalid reserved space"));
+        }
+        let encoding = match u32::from_be_bytes([data[56], data[57], data[58], data[59]]) {
+            0 | 1 => TextEncoding::Utf8,
+            2 => TextEncoding::Utf16Le,
+            3 => TextEncoding::Utf16Be,
+            _ => return Err(SqliteError::Corrupt("invalid text encoding")),
+        };
+        let page_count = u32::try_from(data.len() / page_size).unwrap_or(u32::MAX);
+        Ok(Database {
+            data,
+            page_size,
+            usable_size,
+            page_count,
+            encoding,
+        })
+    }
+
+    /// All rows of `sqlite_schema`, in storage order.
+    pub fn schema(&self) -> Result<Vec<SchemaObject>, SqliteError> {
+        self.rows(1)
+            .map(|row| {
+                let row = row?;
+                let text = |index: usize| match row.values.get(index) {
+                    Some(Value::Text(text)) => Some(text.clone()),
+                    _ => None,
+                };
+                let root_page = match row.values.get(3) {
+                    Some(Value::Integer(page)) => u32::try_from(*page).unwrap_or(0),
+                    _ => 0,
+                };
+                Ok(SchemaObject {
+                    kind: text(0).unwrap_or_default(),
+                    name: text(1).unwrap_or_default(),
+                    table_name: text(2).unwrap_or_default(),
+                    root_page,
+                    sql: text(4),
+                })
+            })
+            .collect()
+    }
+
+    /// The rows of the b-tree rooted at `root_page`, in key order: by rowid for ordinary tables,
+    /// and by primary key for `WITHOUT ROWID` tables.
+    pub fn rows(&self, root_page: u32) -> Rows<'_, 'a> {
+        Rows {
+            database: self,
+            stack: Vec::new(),
+            root_page: Some(root_page),
+            failed: false,
+        }
+    }
+
+    fn page(&self, number: u32) -> Result<&'a [u8], SqliteError> {
+        if number == 0 || number > self.page_count {
+            return Err(SqliteError::Corrupt("page number out of range"));
+        }
+        let start = (number as usize - 1) * self.page_size;
+        Ok(&self.data[start..start + self.page_size])
+    }
+
+    /// Reads a cell's payload of `size` bytes starting at `offset`, following its overflow pages.
+    fn payload(&self, page: &'a [u8], offset: usize, size: usize, max_local: usize) -> Result<Vec<u8>, SqliteError> {
+        let usable = self.usable_size;
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = if size <= max_local {
+            size
+        } else {
+            let surplus = min_local + (size - min_local) % (usable - 4);
+            if surplus <= max_local { surplus } else { min_local }
+        };
+        let mut payload = Vec::with_capacity(size);
+        payload.extend_from_slice(slice(page, offset, local)?);
+        if local == size {
+            return Ok(payload);
+        }
+        let mut next = read_u32(page, offset + local)?;
+        let mut visited = 0u32;
+        while payload.len() < size {
+            visited += 1;
+            if next == 0 || visited > self.page_count {
+                return Err(SqliteError::Corrupt("broken overflow chain"));
+            }
+            let overflow = self.page(next)?;
+            let take = (size - payload.len()).min(usable - 4);
+            payload.extend_from_slice(slice(overflow, 4, take)?);
+            next = read_u32(overflow, 0)?;
+        }
+        Ok(payload)
+    }
+
+    fn decode_record(&self, record: &[u8]) -> Result<Vec<Value>, SqliteError> {
+        let (header_size, mut header_offset) = read_varint(record, 0)?;
+        let header_size = usize::try_from(header_size).map_err(|_| SqliteError::Corrupt("record header"))?;
+        if header_size > record.len() {
+            return Err(SqliteError::Corrupt("record header"));
+        }
+        let mut body_offset = header_size;
+        let mut values = Vec::new();
+        while header_offset < header_size {
+            let (serial_type, next) = read_varint(record, header_offset)?;
+            header_offset = next;
+            let (value, size) = self.decode_value(serial_type, record, body_offset)?;
+            body_offset += size;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn decode_value(&self, serial_type: u64, record: &[u8], offset: usize) -> Result<(Value, usize), SqliteError> {
+        let int = |size: usize| -> Result<(Value, usize), SqliteError> {
+            let bytes = slice(record, offset, size)?;
+            let mut value = if bytes[0] & 0x80 != 0 { -1i64 } else { 0 };
+            for &byte in bytes {
+                value = (value << 8) | i64::from(byte);
+            }
+            Ok((Value::Integer(value), size))
+        };
+        match serial_type {
+            0 => Ok((Value::Null, 0)),
+            1..=4 => int(serial_type as usize),
+            5 => int(6),
+            6 => int(8),
+            7 => {
+                let bytes = slice(record, offset, 8)?;
+                let bits = u64::from_be_bytes(bytes.try_into().unwrap());
+                Ok((Value::Real(f64::from_bits(bits)), 8))
+            }
+            8 => Ok((Value::Integer(0), 0)),
+            9 => Ok((Value::Integer(1), 0)),
+            10 | 11 => Err(SqliteError::Corrupt("reserved serial type")),
+            _ => {
+                let size = usize::try_from((serial_type - 12) / 2).map_err(|_| SqliteError::Corrupt("value size"))?;
+                let bytes = slice(record, offset, size)?;
+                let value = if serial_type.is_multiple_of(2) {
+                    Value::Blob(bytes.to_vec())
+                } else {
+                    Value::Text(self.decode_text(bytes))
+                };
+                Ok((value, size))
+            }
+        }
+    }
+
+    fn decode_text(&self, bytes: &[u8]) -> String {
+        let units = |from: fn([u8; 2]) -> u16| {
+            let units = bytes
+                .chunks_exact(2)
+                .map(|pair| from([pair[0], pair[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        };
+        match self.encoding {
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            TextEncoding::Utf16Le => units(u16::from_le_bytes),
+            TextEncoding::Utf16Be => units(u16::from_be_bytes),
+        }
+    }
+}
+
+/// Page types of the b-tree page header.
+const INTERIOR_INDEX: u8 = 0x02;
+const INTERIOR_TABLE: u8 = 0x05;
+const LEAF_INDEX: u8 = 0x0A;
+const LEAF_TABLE: u8 = 0x0D;
+
+struct Frame<'a> {
+    page: &'a [u8],
+    kind: u8,
+    cell_count: usize,
+    /// Offset of the cell pointer array.
+    cells: usize,
+    /// Position in the walk: on interior pages, even steps descend into a child and odd steps
+    /// visit the cell between two children.
+    step: usize,
+}
+
+/// Iterator over the rows of one b-tree, see [`Database::rows`].
+pub struct Rows<'d, 'a> {
+    database: &'d Database<'a>,
+    stack: Vec<Frame<'a>>,
+    root_page: Option<u32>,
+    failed: bool,
+}
+
+impl<'a> Rows<'_, 'a> {
+    fn push(&mut self, number: u32) -> Result<(), SqliteError> {
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(SqliteError::Corrupt("b-tree too deep"));
+        }
+        let page = self.database.page(number)?;
+        let header = if number == 1 { 100 } else { 0 };
+        let kind = *page.get(header).ok_or(SqliteError::Corrupt("page header"))?;
+        let header_size = match kind {
+            LEAF_TABLE | LEAF_INDEX => 8,
+            INTERIOR_TABLE | INTERIOR_INDEX => 12,
+            _ => return Err(SqliteError::Corrupt("unexpected page type")),
+        };
+        let cell_count = usize::from(read_u16(page, header + 3)?);
+        self.stack.push(Frame {
+            page,
+            kind,
+            cell_count,
+            cells: header + header_size,
+            step: 0,
+        });
+        Ok(())
+    }
+
+    fn cell_offset(frame: &Frame<'a>, index: usize) -> Result<usize, SqliteError> {
+        Ok(usize::from(read_u16(frame.page, frame.cells + index * 2)?))
+    }
+
+    fn next_row(&mut self) -> Result<Option<RawRow>, SqliteError> {
+        if let Some(root_page) = self.root_page.take() {
+            self.push(root_page)?;
+        }
+        let database = self.database;
+        let usable = database.usable_size;
+        loop {
+            let Some(frame) = self.stack.last_mut() else {
+                return Ok(None);
+            };
+            let page = frame.page;
+            match frame.kind {
+                LEAF_TABLE | LEAF_INDEX => {
+                    if frame.step == frame.cell_count {
+                        self.stack.pop();
+                        continue;
+                    }
+                    let offset = Self::cell_offset(frame, frame.step)?;
+                    frame.step += 1;
+                    let (size, offset) = read_varint(page, offset)?;
+                    let size = usize::try_from(size).map_err(|_| SqliteError::Corrupt("payload size"))?;
+                    let (rowid, offset, max_local) = if frame.kind == LEAF_TABLE {
+                        let (rowid, offset) = read_varint(page, offset)?;
+                        (Some(rowid as i64), offset, usable - 35)
+                    } else {
+                        (None, offset, (usable - 12) * 64 / 255 - 23)
+                    };
+                    let payload = database.payload(page, offset, size, max_local)?;
+                    let values = database.decode_record(&payload)?;
+                    return Ok(Some(RawRow { rowid, values }));
+                }
+                _ => {
+                    let step = frame.step;
+                    frame.step += 1;
+                    if step > frame.cell_count * 2 {
+                        self.stack.pop();
+                        continue;
+                    }
+                    if step == frame.cell_count * 2 {
+                        let header = frame.cells - 12;
+                        let right_most = read_u32(page, header + 8)?;
+                        self.push(right_most)?;
+                        continue;
+                    }
+                    let offset = Self::cell_offset(frame, step / 2)?;
+                    if step.is_multiple_of(2) {
+                        let child = read_u32(page, offset)?;
+                        self.push(child)?;
+                        continue;
+                    }
+                    // Interior cells of index b-trees hold rows of their own; those of table
+                    // b-trees only repeat a key.
+                    if frame.kind == INTERIOR_INDEX {
+                        let (size, offset) = read_varint(page, offset + 4)?;
+                        let size = usize::try_from(size).map_err(|_| SqliteError::Corrupt("payload size"))?;
+                        let payload = database.payload(page, offset, size, (usable - 12) * 64 / 255 - 23)?;
+                        let values = database.decode_record(&payload)?;
+                        return Ok(Some(RawRow { rowid: None, values }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Rows<'_, '_> {
+    type Item = Result<RawRow, SqliteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let row = self.next_row().transpose();
+        self.failed = matches!(row, Some(Err(_)));
+        row
+    }
+}
+
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], SqliteError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(SqliteError::Corrupt("read past the end of a page"))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, SqliteError> {
+    Ok(u16::from_be_bytes(slice(bytes, offset, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, SqliteError> {
+    Ok(u32::from_be_bytes(slice(bytes, offset, 4)?.try_into().unwrap()))
+}
+
+/// Reads a big-endian varint of 1 to 9 bytes, returning it and the offset after it.
+fn read_varint(bytes: &[u8], offset: usize) -> Result<(u64, usize), SqliteError> {
+    let mut value = 0u64;
+    for index in 0..9 {
+        let byte = *bytes
+            .get(offset + index)
+            .ok_or(SqliteError::Corrupt("truncated varint"))?;
+        if index == 8 {
+            return Ok(((value << 8) | u64::from(byte), offset + 9));
+        }
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((value, offset + index + 1));
+        }
+    }
+    unreachable!()
+}
+
+impl Value {
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+}
+
+/// Orders values like SQLite does with the `BINARY` collation: NULLs, then numbers, then text,
+/// then blobs.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Real(a), Value::Real(b)) => a.total_cmp(b),
+            (Value::Integer(a), Value::Real(b)) => (*a as f64).total_cmp(b).then(Ordering::Less),
+            (Value::Real(a), Value::Integer(b)) => a.total_cmp(&(*b as f64)).then(Ordering::Greater),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+/// Formats the value as an SQL literal.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Real(value) => write!(f, "{value:?}"),
+            Value::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Value::Blob(bytes) => {
+                f.write_str("X'")?;
+                for byte in bytes {
+                    write!(f, "{byte:02X}")?;
+                }
+                f.write_str("'")
+            }
+        }
+    }
+}
+
+/// What the comparison needs to know about a table, taken from its `CREATE TABLE` statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDefinition {
+    pub columns: Vec<String>,
+    /// Indexes into `columns` of the primary key, in key order; empty if there is none.
+    pub primary_key: Vec<usize>,
+    pub without_rowid: bool,
+}
+
+impl TableDefinition {
+    /// The column that is an alias for the rowid (`INTEGER PRIMARY KEY`); its value is stored as
+    /// NULL in the record and must be taken from the rowid.
+    pub fn rowid_alias(&self, declared_types: &[String]) -> Option<usize> {
+        match self.primary_key.as_slice() {
+            [column] if !self.without_rowid && declared_types[*column].eq_ignore_ascii_case("integer") => Some(*column),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the column list, primary key, and `WITHOUT ROWID` option of a `CREATE TABLE`
+/// statement, along with each column's declared type. Returns `None` for statements it cannot
+/// follow, such as `CREATE TABLE ... AS SELECT`.
+pub fn parse_create_table(sql: &str) -> Option<(TableDefinition, Vec<String>)> {
+    let tokens = tokenize(sql);
+    let open = tokens.iter().position(|token| token == "(")?;
+    let mut definitions = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut close = None;
+    for (index, token) in tokens.iter().enumerate().skip(open + 1) {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" if depth == 0 => {
+                close = Some(index);
+                break;
+            }
+            ")" => depth -= 1,
+            "," if depth == 0 => {
+                definitions.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        definitions.last_mut()?.push(token.clone());
+    }
+    let close = close?;
+    let is_keyword = |token: &str, keyword: &str| token.eq_ignore_ascii_case(keyword);
+    let without_rowid = tokens[close + 1..]
+        .windows(2)
+        .any(|pair| is_keyword(&pair[0], "without") && is_keyword(&pair[1], "rowid"));
+
+    let mut columns = Vec::new();
+    let mut declared_types = Vec::new();
+    let mut key_names = Vec::new();
+    for definition in definitions {
+        let Some(first) = definition.first() else {
+            continue;
+        };
+        let is_table_constraint = ["constraint", "primary", "unique", "check", "foreign"]
+            .iter()
+            .any(|keyword| is_keyword(first, keyword));
+        if is_table_constraint {
+            if let Some(primary) = definition.iter().position(|token| is_keyword(token, "primary"))
+                && let Some(open) = definition[primary..].iter().position(|token| token == "(")
+            {
+                // Only the column names matter, not `COLLATE x` or `ASC`/`DESC` after them.
+                key_names.clear();
+                let mut tokens = definition[primary + open + 1..].iter().peekable();
+                let mut depth = 0usize;
+                while let Some(token) = tokens.next() {
+                    match token.as_str() {
+                        "(" => depth += 1,
+                        ")" if depth == 0 => break,
+                        ")" => depth -= 1,
+                        _ if depth > 0 => {}
+                        _ if is_keyword(token, "collate") => {
+                            tokens.next();
+                        }
+                        "," => {}
+                        _ if is_keyword(token, "asc") || is_keyword(token, "desc") => {}
+                        _ => key_names.push(token.clone()),
+                    }
+                }
+            }
+            continue;
+        }
+        let constraint_start = definition[1..]
+            .iter()
+            .position(|token| {
+                [
+                    "constraint",
+                    "primary",
+                    "not",
+                    "null",
+                    "unique",
+                    "check",
+                    "default",
+                    "collate",
+                    "references",
+                    "generated",
+                    "as",
+                ]
+                .iter()
+                .any(|keyword| is_keyword(token, keyword))
+            })
+            .map_or(definition.len(), |position| position + 1);
+        let declared_type = definition[1..constraint_start]
+            .iter()
+            .take_while(|token| *token != "(")
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let is_primary = definition[constraint_start..]
+            .windows(2)
+            .any(|pair| is_keyword(&pair[0], "primary") && is_keyword(&pair[1], "key"));
+        if is_primary {
+            key_names = vec![first.clone()];
+        }
+        columns.push(first.clone());
+        declared_types.push(declared_type);
+    }
+    if columns.is_empty() {
+        return None;
+    }
+    let primary_key = key_names
+        .iter()
+        .map(|name| columns.iter().position(|column| column.eq_ignore_ascii_case(name)))
+        .collect::<Option<Vec<_>>>()?;
+    Some((
+        TableDefinition {
+            columns,
+            primary_key,
+            without_rowid,
+        },
+        declared_types,
+    ))
+}
+
+/// Splits SQL into identifiers, keywords, literals, and single punctuation characters, with
+/// quoted identifiers unquoted and comments dropped.
+fn tokenize(sql: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '-' if peek_second(&chars) == Some('-') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if peek_second(&chars) == Some('*') => {
+                chars.next();
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' | '`' | '[' | '\'' => {
+                chars.next();
+                let close = if c == '[' { ']' } else { c };
+                let mut token = String::new();
+                while let Some(c) = chars.next() {
+                    if c == close {
+                        // A doubled quote stands for itself, except in [brackets].
+                        if close != ']' && chars.next_if_eq(&close).is_some() {
+                            token.push(close);
+                            continue;
+                        }
+                        break;
+                    }
+                    token.push(c);
+                }
+                if c == '\'' {
+                    token = format!("'{token}'");
+                }
+                tokens.push(token);
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut token = String::new();
+                while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.') {
+                    token.push(c);
+                }
+                tokens.push(token);
+            }
+            _ => {
+                chars.next();
+                tokens.push(c.to_string());
+            }
+        }
+    }
+    tokens
+}
+
+fn peek_second(chars: &std::iter::Peekable<std::str::Chars<'_>>) -> Option<char> {
+    let mut chars = chars.clone();
+    chars.next();
+    chars.next()
+}
@@ -0,0 +1,111 @@
+use super::*;
+
+const EXPECTED: &[u8] = include_bytes!("../testdata/expected.sqlite");
+const REORDERED: &[u8] = include_bytes!("../testdata/reordered.sqlite");
+const ACTUAL: &[u8] = include_bytes!("../testdata/actual.sqlite");
+const REDECLARED: &[u8] = include_bytes!("../testdata/redeclared.sqlite");
+
+fn diff(calculator: &SqliteDiffCalculator, expected: &[u8], actual: &[u8]) -> SqliteDiff {
+    let leaf = |content: &[u8]| FileLeaf::from_bytes("", SQLITE_MIME.parse().unwrap(), content);
+    match calculator.diff("db.sqlite", leaf(expected), leaf(actual)).unwrap() {
+        MayUnsupported::Ok(diff) => diff,
+        MayUnsupported::Unsupported => panic!("database was not read"),
+    }
+}
+
+#[test]
+fn sqlite_ignores_page_layout_and_insertion_order() {
+    let diff = diff(&SqliteDiffCalculator::default(), EXPECTED, REORDERED);
+    assert!(diff.equal(), "{diff:?}");
+    assert_eq!(diff.compared_tables, 5);
+    assert_eq!(diff.compared_rows, 300 + 86 + 200 + 50 + 2);
+}
+
+#[test]
+fn sqlite_pairs_rows_of_tables_declared_differently() {
+    // The key of users is a rowid alias on one side only, notes is WITHOUT ROWID on one side, and
+    // the WITHOUT ROWID settings are stored in descending key order on one side.
+    for (expected, actual) in [(EXPECTED, REDECLARED), (REDECLARED, EXPECTED)] {
+        let diff = diff(&SqliteDiffCalculator::default(), expected, actual);
+        assert_eq!(diff.tables(), [], "{diff:?}");
+        // Three tables change their declaration, and users gains or loses its key index.
+        assert_eq!(diff.schema_changes().len(), 4);
+        assert_eq!(diff.compared_rows, 300 + 86 + 200 + 50 + 2);
+    }
+}
+
+#[test]
+fn sqlite_reports_schema_and_row_changes_by_primary_key() {
+    let diff = diff(&SqliteDiffCalculator::default(), EXPECTED, ACTUAL);
+    assert_eq!(
+        diff.schema_changes(),
+        [SchemaChange::Added {
+            object_type: "index".to_owned(),
+            name: "users_name".to_owned(),
+            sql: Some("CREATE INDEX users_name ON users (name)".to_owned()),
+        }]
+    );
+    let changes = diff
+        .tables()
+        .iter()
+        .map(|table| {
+            let rows = table.rows.iter().map(ToString::to_string).collect::<Vec<_>>();
+            (table.table.as_str(), rows)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            (
+                "users",
+                vec![
+                    "~ id = 5: name 'user 5' → 'renamed'".to_owned(),
+                    "- id = 300".to_owned(),
+                    "+ id = 301".to_owned(),
+                ]
+            ),
+            ("tags", vec!["+ user_id = 2, tag = 'c'".to_owned()]),
+            (
+                "settings",
+                vec!["~ key = 'key 010': value 'value 10' → 'changed'".to_owned()]
+            ),
+            (
+                "notes",
+                vec![format!(
+                    "~ id = 1: body '{}' → '{}y'",
+                    "x".repeat(5000),
+                    "x".repeat(4999)
+                )]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn sqlite_caps_listed_row_changes_but_counts_all() {
+    let diff = diff(
+        &SqliteDiffCalculator::default().with_max_row_changes(1),
+        EXPECTED,
+        ACTUAL,
+    );
+    let users = &diff.tables()[0];
+    assert_eq!((users.added, users.removed, users.changed), (1, 1, 1));
+    assert_eq!(users.rows.len(), 1);
+    assert!(users.truncated);
+}
+
+#[test]
+fn sqlite_parses_create_table_keys() {
+    let (definition, types) = sqlite::parse_create_table(
+        "CREATE TABLE \"t\" (\n  [a b] integer, -- comment\n  c TEXT COLLATE nocase DEFAULT 'x, y',\n  PRIMARY KEY (c COLLATE nocase DESC, \"a b\")\n) WITHOUT ROWID",
+    )
+    .unwrap();
+    assert_eq!(definition.columns, ["a b", "c"]);
+    assert_eq!(definition.primary_key, [1, 0]);
+    assert!(definition.without_rowid);
+    assert_eq!(types, ["integer", "TEXT"]);
+    assert_eq!(definition.rowid_alias(&types), None);
+
+    let (definition, types) = sqlite::parse_create_table("CREATE TABLE t (id INTEGER PRIMARY KEY, name)").unwrap();
+    assert_eq!(definition.rowid_alias(&types), Some(0));
+}
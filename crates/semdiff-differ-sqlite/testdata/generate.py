@@ -0,0 +1,75 @@
+"""Generates the databases used by the tests in src/tests.rs.
+
+expected.sqlite and reordered.sqlite hold the same data written in a different order and page
+size; actual.sqlite changes a few rows and adds an index. redeclared.sqlite holds the same data
+again with tables declared differently: a primary key that is not a rowid alias, a WITHOUT ROWID
+table, and a descending key, so rows are stored in another order than in expected.sqlite.
+"""
+
+import os
+import sqlite3
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+SCHEMA = [
+    "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL)",
+    "CREATE TABLE tags (user_id INTEGER, tag TEXT, PRIMARY KEY (user_id, tag))",
+    "CREATE TABLE settings (key TEXT PRIMARY KEY, value) WITHOUT ROWID",
+    "CREATE TABLE log (message TEXT)",
+    "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)",
+]
+
+REDECLARED_SCHEMA = [
+    "CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL, score REAL)",
+    "CREATE TABLE tags (user_id INTEGER, tag TEXT, PRIMARY KEY (user_id, tag))",
+    "CREATE TABLE settings (key TEXT, value, PRIMARY KEY (key DESC)) WITHOUT ROWID",
+    "CREATE TABLE log (message TEXT)",
+    "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT) WITHOUT ROWID",
+]
+
+
+def rows():
+    users = [(i, f"user {i}", i / 4) for i in range(1, 301)]
+    tags = [(i, tag) for i in range(1, 301, 7) for tag in ("a", "b")]
+    settings = [(f"key {i:03}", i if i % 2 else f"value {i}") for i in range(200)]
+    log = [(f"line {i}",) for i in range(50)]
+    notes = [(1, "x" * 5000), (2, "short")]
+    return {"users": users, "tags": tags, "settings": settings, "log": log, "notes": notes}
+
+
+def write(name, data, page_size, reverse=False, extra=(), schema=SCHEMA):
+    path = os.path.join(HERE, name)
+    if os.path.exists(path):
+        os.remove(path)
+    connection = sqlite3.connect(path)
+    connection.execute(f"PRAGMA page_size = {page_size}")
+    for statement in schema:
+        connection.execute(statement)
+    for table, table_rows in data.items():
+        table_rows = list(reversed(table_rows)) if reverse else table_rows
+        placeholders = ", ".join("?" * len(table_rows[0]))
+        connection.executemany(f"INSERT INTO {table} VALUES ({placeholders})", table_rows)
+    for statement in extra:
+        connection.execute(statement)
+    connection.commit()
+    connection.execute("VACUUM")
+    connection.close()
+
+
+def main():
+    write("expected.sqlite", rows(), 1024)
+    write("reordered.sqlite", rows(), 4096, reverse=True)
+    write("redeclared.sqlite", rows(), 1024, reverse=True, schema=REDECLARED_SCHEMA)
+
+    data = rows()
+    data["users"][4] = (5, "renamed", 1.25)
+    del data["users"][299]
+    data["users"].append((301, "user 301", 75.25))
+    data["tags"].append((2, "c"))
+    data["settings"][10] = ("key 010", "changed")
+    data["notes"][0] = (1, "x" * 4999 + "y")
+    write("actual.sqlite", data, 1024, extra=["CREATE INDEX users_name ON users (name)"])
+
+
+if __name__ == "__main__":
+    main()
@@ -1,14 +1,16 @@
-use memmap2::Mmap;
 use mime::Mime;
-use semdiff_core::fs::FileLeaf;
+use semdiff_core::fs::{FileContent, FileLeaf};
 use semdiff_core::{Diff, DiffCalculator, MayUnsupported};
+use serde::Serialize;
 use similar::TextDiffConfig;
-use std::convert;
 use std::sync::Arc;
+use std::{convert, iter};
 
+pub mod report_collect;
 pub mod report_html;
 pub mod report_json;
 pub mod report_summary;
+mod semantic;
 
 #[cfg(test)]
 mod tests;
@@ -18,20 +20,85 @@ pub struct TextDiffReporter;
 #[derive(Debug)]
 pub struct TextDiff {
     equal: bool,
-    expected: Arc<Mmap>,
-    actual: Arc<Mmap>,
+    expected: Arc<FileContent>,
+    actual: Arc<FileContent>,
+    trailing_newline: Option<TrailingNewlineChange>,
+    /// The language the files were compared as tokens of, in which case `expected` and `actual`
+    /// hold their normalized code unless the files are equal.
+    language: Option<&'static str>,
+}
+
+/// How the newline at the end of the file changed from expected to actual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingNewlineChange {
+    Added,
+    Removed,
+}
+
+impl TrailingNewlineChange {
+    fn detect(expected: &[u8], actual: &[u8]) -> Option<TrailingNewlineChange> {
+        match (misses_final_newline(expected), misses_final_newline(actual)) {
+            (true, false) => Some(TrailingNewlineChange::Added),
+            (false, true) => Some(TrailingNewlineChange::Removed),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TrailingNewlineChange::Added => "newline added at end of file",
+            TrailingNewlineChange::Removed => "no newline at end of file",
+        }
+    }
 }
 
 impl Diff for TextDiff {
     fn equal(&self) -> bool {
         self.equal
     }
+
+    fn magnitude(&self) -> Option<f64> {
+        Some(1.0 - f64::from(self.diff().ratio()))
+    }
 }
 
 impl TextDiff {
     fn diff(&self) -> similar::TextDiff<'_, '_, [u8]> {
         text_diff_lines(&self.expected[..], &self.actual[..])
     }
+
+    fn trailing_newline(&self) -> Option<TrailingNewlineChange> {
+        self.trailing_newline
+    }
+
+    fn language(&self) -> Option<&'static str> {
+        self.language
+    }
+
+    /// Whether the final newline is the only difference between expected and actual.
+    fn is_trailing_newline_only(&self) -> bool {
+        differs_only_in_final_newline(&self.expected[..], &self.actual[..])
+    }
+}
+
+fn differs_only_in_final_newline(expected: &[u8], actual: &[u8]) -> bool {
+    match TrailingNewlineChange::detect(expected, actual) {
+        Some(TrailingNewlineChange::Added) => strip_final_newline(actual) == expected,
+        Some(TrailingNewlineChange::Removed) => strip_final_newline(expected) == actual,
+        None => false,
+    }
+}
+
+fn misses_final_newline(content: &[u8]) -> bool {
+    !content.is_empty() && !content.ends_with(b"\n")
+}
+
+fn strip_final_newline(content: &[u8]) -> &[u8] {
+    content
+        .strip_suffix(b"\r\n")
+        .or_else(|| content.strip_suffix(b"\n"))
+        .unwrap_or(content)
 }
 
 fn text_diff_lines<'a>(expected: &'a [u8], actual: &'a [u8]) -> similar::TextDiff<'a, 'a, [u8]> {
@@ -57,23 +124,62 @@ fn is_text_file(kind: &Mime, body: &[u8]) -> bool {
     is_printable_text(text)
 }
 
+/// Application kinds that are text, besides every `text/*` kind.
+const TEXT_APPLICATION_MIMES: [&str; 8] = [
+    "application/json",
+    "application/xml",
+    "application/javascript",
+    "application/x-javascript",
+    "application/x-www-form-urlencoded",
+    "application/yaml",
+    "application/x-yaml",
+    "application/toml",
+];
+
 fn is_text_mime(kind: &Mime) -> bool {
-    kind.type_() == mime::TEXT
-        || matches!(
-            kind.essence_str(),
-            "application/json"
-                | "application/xml"
-                | "application/javascript"
-                | "application/x-javascript"
-                | "application/x-www-form-urlencoded"
-                | "application/yaml"
-                | "application/x-yaml"
-                | "application/toml"
-        )
+    kind.type_() == mime::TEXT || TEXT_APPLICATION_MIMES.contains(&kind.essence_str())
+}
+
+/// Kinds that are always compared as text. Files of any other kind are compared as text too when
+/// they are printable UTF-8.
+pub fn supported_kinds() -> Vec<&'static str> {
+    iter::once("text/*").chain(TEXT_APPLICATION_MIMES).collect()
 }
 
 #[derive(Default)]
-pub struct TextDiffCalculator;
+pub struct TextDiffCalculator {
+    semantic: bool,
+}
+
+impl TextDiffCalculator {
+    /// Compares source code in a language known by its file extension as tokens, ignoring
+    /// comments and formatting. Other text files are compared as they are.
+    pub fn with_semantic(mut self, semantic: bool) -> Self {
+        self.semantic = semantic;
+        self
+    }
+
+    /// The normalized code of both files, when they are compared as tokens of a language.
+    fn normalize(&self, name: &str, expected: &FileLeaf, actual: &FileLeaf) -> Option<SemanticPair> {
+        if !self.semantic {
+            return None;
+        }
+        let language = semantic::language_of(name)?;
+        let expected = semantic::normalize(language, str::from_utf8(&expected.content).ok()?);
+        let actual = semantic::normalize(language, str::from_utf8(&actual.content).ok()?);
+        Some(SemanticPair {
+            language: language.name,
+            expected,
+            actual,
+        })
+    }
+}
+
+struct SemanticPair {
+    language: &'static str,
+    expected: semantic::Normalized,
+    actual: semantic::Normalized,
+}
 
 impl DiffCalculator<FileLeaf> for TextDiffCalculator {
     type Error = convert::Infallible;
@@ -81,30 +187,59 @@ impl DiffCalculator<FileLeaf> for TextDiffCalculator {
 
     fn diff(
         &self,
-        _name: &str,
+        name: &str,
         expected: FileLeaf,
         actual: FileLeaf,
     ) -> Result<MayUnsupported<Self::Diff>, Self::Error> {
-        'available: {
-            let Ok(expected_str) = str::from_utf8(&expected.content) else {
-                return Ok(MayUnsupported::Unsupported);
-            };
-            let Ok(actual_str) = str::from_utf8(&actual.content) else {
-                return Ok(MayUnsupported::Unsupported);
+        if !is_text_pair(&expected, &actual) {
+            return Ok(MayUnsupported::Unsupported);
+        }
+        if let Some(pair) = self.normalize(name, &expected, &actual) {
+            let equal = pair.expected.key == pair.actual.key;
+            // Equal files are shown as they are; changed ones as their normalized code, so that
+            // the diff leaves out comments and formatting.
+            let (expected, actual) = if equal {
+                (expected.content, actual.content)
+            } else {
+                (
+                    Arc::new(FileContent::Owned(pair.expected.text.into_bytes())),
+                    Arc::new(FileContent::Owned(pair.actual.text.into_bytes())),
+                )
             };
-
-            if is_text_mime(&expected.kind) && is_text_mime(&actual.kind) {
-                break 'available;
-            }
-
-            if !is_printable_text(expected_str) || !is_printable_text(actual_str) {
-                return Ok(MayUnsupported::Unsupported);
-            }
+            return Ok(MayUnsupported::Ok(TextDiff {
+                equal,
+                expected,
+                actual,
+                trailing_newline: None,
+                language: Some(pair.language),
+            }));
         }
         Ok(MayUnsupported::Ok(TextDiff {
             equal: <[u8] as PartialEq<[u8]>>::eq(&expected.content, &actual.content),
+            trailing_newline: TrailingNewlineChange::detect(&expected.content, &actual.content),
             expected: expected.content,
             actual: actual.content,
+            language: None,
         }))
     }
+
+    fn similarity(&self, expected: &FileLeaf, actual: &FileLeaf) -> Option<f64> {
+        if !is_text_pair(expected, actual) {
+            return None;
+        }
+        if let Some(pair) = self.normalize(&expected.name, expected, actual) {
+            let diff = text_diff_lines(pair.expected.text.as_bytes(), pair.actual.text.as_bytes());
+            return Some(f64::from(diff.ratio()));
+        }
+        Some(f64::from(text_diff_lines(&expected.content, &actual.content).ratio()))
+    }
+}
+
+fn is_text_pair(expected: &FileLeaf, actual: &FileLeaf) -> bool {
+    let (Ok(expected_str), Ok(actual_str)) = (str::from_utf8(&expected.content), str::from_utf8(&actual.content))
+    else {
+        return false;
+    };
+    (is_text_mime(&expected.kind) && is_text_mime(&actual.kind))
+        || (is_printable_text(expected_str) && is_printable_text(actual_str))
 }
@@ -1,4 +1,4 @@
-use crate::{TextDiff, TextDiffReporter, is_text_file};
+use crate::{TextDiff, TextDiffReporter, TrailingNewlineChange, is_text_file};
 use askama::Template;
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
@@ -23,6 +23,7 @@ struct TextPreviewTemplate<'a> {
 enum TextPreviewBody<'a> {
     Unchanged { body: &'a str },
     Modified { diff: &'a similar::TextDiff<'a, 'a, [u8]> },
+    TrailingNewline { label: &'a str },
     Added { body: &'a str },
     Deleted { body: &'a str },
 }
@@ -37,6 +38,9 @@ impl TextPreviewTemplate<'_> {
 #[template(path = "text_detail.html")]
 struct TextDetailTemplate<'a> {
     detail: TextDetailBody<'a>,
+    similarity: Option<f32>,
+    trailing_newline: Option<&'a str>,
+    language: Option<&'a str>,
 }
 
 enum TextDetailBody<'a> {
@@ -66,6 +70,9 @@ impl DetailReporter<TextDiff, FileLeaf, HtmlReport> for TextDiffReporter {
         };
         let detail_html = TextDetailTemplate {
             detail: TextDetailBody::Single { label: "same", body },
+            similarity: None,
+            trailing_newline: None,
+            language: diff.language(),
         };
         reporter.record_unchanged(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -78,11 +85,17 @@ impl DetailReporter<TextDiff, FileLeaf, HtmlReport> for TextDiffReporter {
         reporter: &HtmlReport,
     ) -> Result<MayUnsupported<()>, Self::Error> {
         let diff_view = diff.diff();
-        let preview_html = TextPreviewTemplate {
-            body: TextPreviewBody::Modified { diff: &diff_view },
+        let trailing_newline = diff.trailing_newline().map(TrailingNewlineChange::label);
+        let preview_body = match trailing_newline {
+            Some(label) if diff.is_trailing_newline_only() => TextPreviewBody::TrailingNewline { label },
+            _ => TextPreviewBody::Modified { diff: &diff_view },
         };
+        let preview_html = TextPreviewTemplate { body: preview_body };
         let detail_html = TextDetailTemplate {
             detail: TextDetailBody::Diff { lines: &diff_view },
+            similarity: Some(diff_view.ratio()),
+            trailing_newline,
+            language: diff.language(),
         };
         reporter.record_modified(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -106,6 +119,9 @@ impl DetailReporter<TextDiff, FileLeaf, HtmlReport> for TextDiffReporter {
                 label: "added",
                 body: actual_text,
             },
+            similarity: None,
+            trailing_newline: None,
+            language: None,
         };
         reporter.record_added(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -129,6 +145,9 @@ impl DetailReporter<TextDiff, FileLeaf, HtmlReport> for TextDiffReporter {
                 label: "deleted",
                 body: expected_text,
             },
+            similarity: None,
+            trailing_newline: None,
+            language: None,
         };
         reporter.record_deleted(name, COMPARES_NAME, preview_html, detail_html)?;
         Ok(MayUnsupported::Ok(()))
@@ -1,14 +1,15 @@
-use crate::{TextDiff, TextDiffReporter, is_text_file};
+use crate::{TextDiff, TextDiffReporter, TrailingNewlineChange, is_text_file};
 use semdiff_core::fs::FileLeaf;
 use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::json::JsonReport;
 use serde::Serialize;
 use similar::ChangeTag;
 use std::convert;
+use std::io::Write;
 
 const COMPARES_NAME: &str = "text";
 
-impl<W> DetailReporter<TextDiff, FileLeaf, JsonReport<W>> for TextDiffReporter {
+impl<W: Write> DetailReporter<TextDiff, FileLeaf, JsonReport<W>> for TextDiffReporter {
     type Error = convert::Infallible;
 
     fn report_unchanged(
@@ -27,26 +28,50 @@ impl<W> DetailReporter<TextDiff, FileLeaf, JsonReport<W>> for TextDiffReporter {
         diff: &TextDiff,
         reporter: &JsonReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        let s = diff
-            .diff()
-            .iter_all_changes()
-            .fold(S::default(), |S { added, deleted }, change| match change.tag() {
-                ChangeTag::Equal => S { added, deleted },
-                ChangeTag::Delete => S {
-                    added,
-                    deleted: deleted + 1,
-                },
-                ChangeTag::Insert => S {
-                    added: added + 1,
-                    deleted,
-                },
-            });
+        let diff_view = diff.diff();
+        // A change of the final newline alone is reported through `trailing_newline` instead of
+        // as a rewritten last line.
+        let changes = if diff.is_trailing_newline_only() {
+            S::default()
+        } else {
+            diff_view
+                .iter_all_changes()
+                .fold(S::default(), |S { added, deleted }, change| match change.tag() {
+                    ChangeTag::Equal => S { added, deleted },
+                    ChangeTag::Delete => S {
+                        added,
+                        deleted: deleted + 1,
+                    },
+                    ChangeTag::Insert => S {
+                        added: added + 1,
+                        deleted,
+                    },
+                })
+        };
         #[derive(Debug, Default, Serialize)]
         struct S {
             added: usize,
             deleted: usize,
         }
-        reporter.record_modified(name, COMPARES_NAME, s);
+        #[derive(Debug, Serialize)]
+        struct Report {
+            #[serde(flatten)]
+            changes: S,
+            similarity: f32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            trailing_newline: Option<TrailingNewlineChange>,
+            /// The language compared as tokens, with `added` and `deleted` counting lines of
+            /// normalized code.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            language: Option<&'static str>,
+        }
+        let report = Report {
+            changes,
+            similarity: diff_view.ratio(),
+            trailing_newline: diff.trailing_newline(),
+            language: diff.language(),
+        };
+        reporter.record_modified(name, COMPARES_NAME, report);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -4,6 +4,8 @@ use semdiff_core::{DetailReporter, MayUnsupported};
 use semdiff_output::summary::SummaryReport;
 use std::convert;
 
+const COMPARES_NAME: &str = "text";
+
 impl<W> DetailReporter<TextDiff, FileLeaf, SummaryReport<W>> for TextDiffReporter {
     type Error = convert::Infallible;
 
@@ -13,7 +15,7 @@ impl<W> DetailReporter<TextDiff, FileLeaf, SummaryReport<W>> for TextDiffReporte
         _diff: &TextDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_unchanged();
+        reporter.increment_unchanged(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -23,7 +25,7 @@ impl<W> DetailReporter<TextDiff, FileLeaf, SummaryReport<W>> for TextDiffReporte
         _diff: &TextDiff,
         reporter: &SummaryReport<W>,
     ) -> Result<MayUnsupported<()>, Self::Error> {
-        reporter.increment_modified();
+        reporter.increment_modified(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -36,7 +38,7 @@ impl<W> DetailReporter<TextDiff, FileLeaf, SummaryReport<W>> for TextDiffReporte
         if !is_text_file(&data.kind, &data.content) {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_added();
+        reporter.increment_added(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 
@@ -49,7 +51,7 @@ impl<W> DetailReporter<TextDiff, FileLeaf, SummaryReport<W>> for TextDiffReporte
         if !is_text_file(&data.kind, &data.content) {
             return Ok(MayUnsupported::Unsupported);
         }
-        reporter.increment_deleted();
+        reporter.increment_deleted(COMPARES_NAME);
         Ok(MayUnsupported::Ok(()))
     }
 }
@@ -0,0 +1,282 @@
+//! Token-level comparison of source code, which ignores comments and formatting.
+//!
+//! This is a lexer per language family rather than a parser: it knows each language's comments
+//! and string literals, and splits the rest into words and punctuation. Two files are equal when
+//! they have the same tokens; for languages where line breaks or indentation carry meaning, the
+//! tokens must also be on the same lines, with the same indentation.
+
+/// The lexical rules of a language.
+pub(crate) struct Language {
+    pub(crate) name: &'static str,
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Quote characters of string literals, and whether backslash escapes apply inside them.
+    quotes: &'static [(char, bool)],
+    /// Python's `"""` and `'''` strings.
+    triple_quotes: bool,
+    /// `'` starts a character literal only when one closes it right after, as Rust lifetimes
+    /// (`'a`) also start with `'`.
+    char_literals: bool,
+    /// Line breaks separate statements, so tokens must stay on their lines.
+    line_sensitive: bool,
+    /// Leading whitespace is significant.
+    indentation: bool,
+}
+
+const C_QUOTES: &[(char, bool)] = &[('"', true), ('\'', true)];
+const JS_QUOTES: &[(char, bool)] = &[('"', true), ('\'', true), ('`', true)];
+
+const fn c_like(name: &'static str, extensions: &'static [&'static str]) -> Language {
+    Language {
+        name,
+        extensions,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: C_QUOTES,
+        triple_quotes: false,
+        char_literals: false,
+        line_sensitive: false,
+        indentation: false,
+    }
+}
+
+const fn hash_commented(name: &'static str, extensions: &'static [&'static str]) -> Language {
+    Language {
+        name,
+        extensions,
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: C_QUOTES,
+        triple_quotes: false,
+        char_literals: false,
+        line_sensitive: true,
+        indentation: false,
+    }
+}
+
+const LANGUAGES: &[Language] = &[
+    c_like("c", &["c", "h"]),
+    c_like("cpp", &["cc", "cpp", "cxx", "hh", "hpp", "hxx"]),
+    c_like("csharp", &["cs"]),
+    c_like("java", &["java"]),
+    c_like("kotlin", &["kt", "kts"]),
+    c_like("scala", &["scala"]),
+    c_like("swift", &["swift"]),
+    Language {
+        quotes: &[('"', true), ('\'', true), ('`', false)],
+        ..c_like("go", &["go"])
+    },
+    Language {
+        quotes: JS_QUOTES,
+        ..c_like("javascript", &["js", "mjs", "cjs", "jsx"])
+    },
+    Language {
+        quotes: JS_QUOTES,
+        ..c_like("typescript", &["ts", "mts", "cts", "tsx"])
+    },
+    Language {
+        char_literals: true,
+        ..c_like("rust", &["rs"])
+    },
+    Language {
+        line_comments: &[],
+        ..c_like("css", &["css"])
+    },
+    Language {
+        line_comments: &["--"],
+        quotes: &[('\'', false), ('"', false)],
+        ..c_like("sql", &["sql"])
+    },
+    Language {
+        triple_quotes: true,
+        indentation: true,
+        ..hash_commented("python", &["py", "pyi"])
+    },
+    hash_commented("ruby", &["rb"]),
+    hash_commented("shell", &["sh", "bash", "zsh"]),
+];
+
+/// The language of the file `name`, by extension.
+pub(crate) fn language_of(name: &str) -> Option<&'static Language> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+}
+
+/// Source code with comments removed and whitespace normalized.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Normalized {
+    /// One line per source line with tokens, for display: the tokens separated only where
+    /// needed, and indented only in languages where indentation is significant.
+    pub(crate) text: String,
+    /// What equality is decided on: `text`, or the tokens of all lines joined into one for
+    /// languages where line breaks do not matter.
+    pub(crate) key: String,
+}
+
+/// Operators of more than one character, longest first, which are single tokens.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "===", "!==", "...", "..=", "**=", "??=", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "->", "=>",
+    "::", "<<", ">>", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "**", "..", "??", "?.", ":=",
+];
+
+/// Punctuation that never forms an operator with its neighbours.
+const DELIMITERS: &str = "()[]{},;";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    /// Identifiers, keywords, numbers, and string literals.
+    Word,
+    Operator,
+    Delimiter,
+}
+
+struct Token<'a> {
+    text: &'a str,
+    kind: TokenKind,
+}
+
+pub(crate) fn normalize(language: &Language, source: &str) -> Normalized {
+    let lines = tokenize(language, source);
+    let mut text = String::new();
+    for (indent, tokens) in &lines {
+        if language.indentation {
+            text.push_str(indent);
+        }
+        join(&mut text, tokens);
+        text.push('\n');
+    }
+    let key = if language.line_sensitive {
+        text.clone()
+    } else {
+        let mut key = String::new();
+        join(&mut key, lines.iter().flat_map(|(_, tokens)| tokens));
+        key
+    };
+    Normalized { text, key }
+}
+
+/// Appends `tokens`, with a space between two words and between two operators (so that the
+/// tokens `-` `-` do not read as the token `--`).
+fn join<'a>(out: &mut String, tokens: impl IntoIterator<Item = &'a Token<'a>>) {
+    let mut previous = None;
+    for token in tokens {
+        if previous == Some(token.kind) && token.kind != TokenKind::Delimiter {
+            out.push(' ');
+        }
+        out.push_str(token.text);
+        previous = Some(token.kind);
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+/// Splits `source` into lines of tokens, each with the indentation of its line. Lines without
+/// tokens are left out; a token spanning lines, such as a block string, belongs to the line it
+/// starts on.
+fn tokenize<'a>(language: &Language, source: &'a str) -> Vec<(&'a str, Vec<Token<'a>>)> {
+    let mut lines = Vec::new();
+    let mut tokens = Vec::new();
+    let mut indent = "";
+    let mut rest = source;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '\n' {
+            if !tokens.is_empty() {
+                lines.push((indent, std::mem::take(&mut tokens)));
+            }
+            rest = &rest[1..];
+            continue;
+        }
+        if ch.is_whitespace() {
+            rest = rest.trim_start_matches(|ch: char| ch != '\n' && ch.is_whitespace());
+            continue;
+        }
+        if language.line_comments.iter().any(|prefix| rest.starts_with(prefix)) {
+            rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+            continue;
+        }
+        if let Some((open, close)) = language.block_comment
+            && let Some(body) = rest.strip_prefix(open)
+        {
+            let end = body.find(close).map_or(body.len(), |end| end + close.len());
+            rest = &body[end..];
+            continue;
+        }
+        let (len, kind) = match string_literal(language, rest) {
+            Some(len) => (len, TokenKind::Word),
+            None => match rest.find(|ch: char| !is_word_char(ch)).unwrap_or(rest.len()) {
+                0 if DELIMITERS.contains(ch) => (ch.len_utf8(), TokenKind::Delimiter),
+                0 => match OPERATORS.iter().find(|operator| rest.starts_with(*operator)) {
+                    Some(operator) => (operator.len(), TokenKind::Operator),
+                    None => (ch.len_utf8(), TokenKind::Operator),
+                },
+                len => (len, TokenKind::Word),
+            },
+        };
+        if tokens.is_empty() {
+            let offset = source.len() - rest.len();
+            let line = &source[source[..offset].rfind('\n').map_or(0, |start| start + 1)..];
+            indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        }
+        tokens.push(Token {
+            text: &rest[..len],
+            kind,
+        });
+        rest = &rest[len..];
+    }
+    if !tokens.is_empty() {
+        lines.push((indent, tokens));
+    }
+    lines
+}
+
+/// The length of the string or character literal at the start of `rest`, if one starts there.
+/// An unterminated literal runs to the end of the source.
+fn string_literal(language: &Language, rest: &str) -> Option<usize> {
+    if language.triple_quotes {
+        for delimiter in ["\"\"\"", "'''"] {
+            if let Some(body) = rest.strip_prefix(delimiter) {
+                let end = find_closing(body, delimiter, true);
+                return Some(delimiter.len() + end);
+            }
+        }
+    }
+    let quote = rest.chars().next()?;
+    let &(_, escapes) = language.quotes.iter().find(|(candidate, _)| *candidate == quote)?;
+    let body = &rest[1..];
+    if language.char_literals && quote == '\'' {
+        // A character literal is one character or one escape; anything else is a lifetime.
+        let mut chars = body.char_indices();
+        let end = match chars.next()? {
+            (_, '\\') => body[1..].find('\'').map(|end| end + 2)?,
+            (_, ch) => {
+                let after = ch.len_utf8();
+                body[after..].starts_with('\'').then_some(after + 1)?
+            }
+        };
+        return Some(1 + end);
+    }
+    Some(1 + find_closing(body, &quote.to_string(), escapes))
+}
+
+/// The length of `body` up to and including the first `delimiter` that is not escaped.
+fn find_closing(body: &str, delimiter: &str, escapes: bool) -> usize {
+    let mut index = 0;
+    while index < body.len() {
+        let rest = &body[index..];
+        if rest.starts_with(delimiter) {
+            return index + delimiter.len();
+        }
+        let ch = rest.chars().next().unwrap();
+        index += ch.len_utf8();
+        if escapes && ch == '\\' {
+            index += rest[1..].chars().next().map_or(0, char::len_utf8);
+        }
+    }
+    body.len()
+}
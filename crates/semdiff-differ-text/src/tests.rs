@@ -18,3 +18,114 @@ fn text_diff_lines_counts_line_changes() {
     assert_eq!(added, 1);
     assert_eq!(deleted, 1);
 }
+
+#[test]
+fn text_diff_lines_ratio_reflects_similarity() {
+    assert_eq!(text_diff_lines(b"a\nb\n", b"a\nb\n").ratio(), 1.0);
+    assert_eq!(text_diff_lines(b"a\nb\n", b"c\nd\n").ratio(), 0.0);
+    let partial = text_diff_lines(b"a\nb\nc\nd\n", b"a\nb\nc\ne\n").ratio();
+    assert!(partial > 0.5 && partial < 1.0);
+}
+
+#[test]
+fn trailing_newline_change_detects_only_final_newline_difference() {
+    assert_eq!(
+        TrailingNewlineChange::detect(b"a\nb", b"a\nb\n"),
+        Some(TrailingNewlineChange::Added)
+    );
+    assert_eq!(
+        TrailingNewlineChange::detect(b"a\nb\r\n", b"a\nb"),
+        Some(TrailingNewlineChange::Removed)
+    );
+    assert_eq!(TrailingNewlineChange::detect(b"a\n", b"b\n"), None);
+    assert_eq!(TrailingNewlineChange::detect(b"", b"a\n"), None);
+
+    assert!(differs_only_in_final_newline(b"a\nb", b"a\nb\n"));
+    assert!(differs_only_in_final_newline(b"a\nb\r\n", b"a\nb"));
+    assert!(!differs_only_in_final_newline(b"a\nb", b"a\nc\n"));
+    assert!(!differs_only_in_final_newline(b"a\n", b"a\n"));
+}
+
+fn semantic_equal(name: &str, expected: &str, actual: &str) -> bool {
+    let leaf = |content: &str| FileLeaf::from_bytes(name, mime::TEXT_PLAIN, content.as_bytes());
+    let calculator = TextDiffCalculator::default().with_semantic(true);
+    match calculator.diff(name, leaf(expected), leaf(actual)).unwrap() {
+        MayUnsupported::Ok(diff) => diff.equal(),
+        MayUnsupported::Unsupported => panic!("{name} is text"),
+    }
+}
+
+#[test]
+fn semantic_diff_ignores_comments_and_formatting() {
+    let expected = "fn main() {\n    let x = 1; // one\n    println!(\"{x}\");\n}\n";
+    let reformatted = "/* entry */\nfn main()\n{\n  let x=1;\n\n  println!( \"{x}\" );\n}";
+    assert!(semantic_equal("main.rs", expected, reformatted));
+    assert!(!semantic_equal("main.rs", expected, &expected.replace("1;", "2;")));
+    // Comment markers inside strings are not comments, and lifetimes are not character literals.
+    assert!(!semantic_equal("a.rs", "s(\"// a\");", "s(\"// b\");"));
+    assert!(semantic_equal(
+        "a.rs",
+        "fn f<'a>(x: &'a str) {}",
+        "fn f<'a>(x:&'a str){} // c"
+    ));
+    // Separated operators stay apart.
+    assert!(!semantic_equal("a.c", "a - -b;", "a--b;"));
+
+    // Without a known language, or without the option, whitespace counts.
+    assert!(!semantic_equal("notes.txt", "a b\n", "a  b\n"));
+    let leaf = |content: &str| FileLeaf::from_bytes("main.rs", mime::TEXT_PLAIN, content.as_bytes());
+    let MayUnsupported::Ok(diff) = TextDiffCalculator::default()
+        .diff("main.rs", leaf(expected), leaf(reformatted))
+        .unwrap()
+    else {
+        panic!("main.rs is text");
+    };
+    assert!(!diff.equal());
+}
+
+#[test]
+fn semantic_diff_keeps_significant_lines_and_indentation() {
+    let expected = "def f(x):\n    return x  # same\n";
+    assert!(semantic_equal("a.py", expected, "def f(x):  # doc\n\n    return x\n"));
+    assert!(!semantic_equal("a.py", expected, "def f(x):\n        return x\n"));
+    assert!(semantic_equal("a.py", "s = '''a # b'''\n", "s = '''a # b'''  # c\n"));
+    assert!(!semantic_equal("a.sh", "echo a\necho b\n", "echo a echo b\n"));
+    // Joining lines is a formatting change in free-form languages.
+    assert!(semantic_equal("a.js", "f(a,\n  b)\n", "f(a, b)\n"));
+}
+
+#[test]
+fn collect_report_records_text_entries_with_their_status() {
+    use semdiff_core::fs::FileLeaf;
+    use semdiff_core::{DiffAndReport, DiffOptions, DiffReport, Reporter, calc_leaf_diff};
+    use semdiff_output::collect::{CollectReport, CollectedEntries, DiffEntry};
+    use semdiff_output::decorate::EntryStatus;
+
+    let entries = CollectedEntries::new();
+    let report = CollectReport::new(&entries);
+    let differs: Vec<Box<dyn DiffReport<FileLeaf, CollectReport>>> = vec![Box::new(DiffAndReport::new(
+        TextDiffCalculator::default(),
+        TextDiffReporter,
+    ))];
+    let leaf = |content: &str| FileLeaf::from_bytes("notes.txt", mime::TEXT_PLAIN, content.as_bytes().to_vec());
+    calc_leaf_diff(
+        "notes.txt",
+        &leaf("a\nb\n"),
+        &leaf("a\nc\n"),
+        &differs,
+        &report,
+        &DiffOptions::new(),
+    )
+    .unwrap();
+    report.finish().unwrap();
+    drop(differs);
+
+    assert_eq!(
+        entries.into_entries(),
+        [DiffEntry {
+            path: "notes.txt".to_owned(),
+            status: EntryStatus::Modified,
+            compares: "text",
+        }]
+    );
+}
@@ -0,0 +1,72 @@
+use dashmap::DashMap;
+use semdiff_core::ToleranceCheck;
+
+/// Collects the entries whose equality was a near miss: one of their tolerance checks measured
+/// within `band` (a fraction of the threshold) of its threshold, on either side.
+///
+/// Such entries would flip between unchanged and modified with a small change in the input or in
+/// the tolerance, so they point at comparisons that are flaky or tolerances worth revisiting.
+#[derive(Debug)]
+pub struct BorderlineEntries {
+    band: f64,
+    checks: DashMap<String, ToleranceCheck>,
+}
+
+impl BorderlineEntries {
+    pub fn new(band: f64) -> BorderlineEntries {
+        BorderlineEntries {
+            band,
+            checks: DashMap::new(),
+        }
+    }
+
+    /// Keeps `check` for `name` if it is borderline and closer to its threshold than any check
+    /// recorded for `name` before.
+    pub fn record(&self, name: &str, check: &ToleranceCheck) {
+        if !check.is_borderline(self.band) {
+            return;
+        }
+        self.checks
+            .entry(name.to_owned())
+            .and_modify(|closest| {
+                if check.relative_margin() < closest.relative_margin() {
+                    *closest = *check;
+                }
+            })
+            .or_insert(*check);
+    }
+
+    pub fn get(&self, name: &str) -> Option<ToleranceCheck> {
+        self.checks.get(name).map(|check| *check)
+    }
+
+    pub fn remove(&self, name: &str) -> Option<ToleranceCheck> {
+        self.checks.remove(name).map(|(_, check)| check)
+    }
+
+    pub fn into_entries(self) -> impl Iterator<Item = (String, ToleranceCheck)> {
+        self.checks.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borderline_entries_keep_the_closest_near_miss() {
+        let entries = BorderlineEntries::new(0.1);
+        let check = |metric, measured, threshold| ToleranceCheck {
+            metric,
+            measured,
+            threshold,
+        };
+        entries.record("a.png", &check("diff_ratio", 0.5, 0.01));
+        assert_eq!(entries.get("a.png"), None);
+
+        entries.record("b.wav", &check("lufs_diff_db", 0.95, 1.0));
+        entries.record("b.wav", &check("spectrogram_diff_rate", 0.0499, 0.05));
+        entries.record("b.wav", &check("lufs_diff_db", 1.08, 1.0));
+        assert_eq!(entries.get("b.wav"), Some(check("spectrogram_diff_rate", 0.0499, 0.05)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Collecting the entries of a run as values, for embedders that look at the results themselves
+//! instead of reading a written report.
+
+use crate::decorate::EntryStatus;
+use semdiff_core::Reporter;
+use std::convert;
+use std::sync::Mutex;
+
+const EMPTY_NODE_COMPARES: &str = "directory";
+const IDENTICAL_COMPARES: &str = "identical";
+
+/// A leaf, or an empty node, as reported by a differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// Path from the root, joined with `/`.
+    pub path: String,
+    pub status: EntryStatus,
+    /// Name of the differ that compared the entry, such as `json` or `image`, `directory` for an
+    /// empty node, or `identical` for a leaf with the same bytes on both sides that no differ saw
+    /// (see `DiffOptions::with_identical_fast_path`).
+    pub compares: &'static str,
+}
+
+/// The entries of a run, filled in by a [`CollectReport`] when the run finishes.
+#[derive(Debug, Default)]
+pub struct CollectedEntries {
+    entries: Mutex<Vec<DiffEntry>>,
+}
+
+impl CollectedEntries {
+    pub fn new() -> CollectedEntries {
+        CollectedEntries::default()
+    }
+
+    /// The entries sorted by path; empty until the report has finished.
+    pub fn into_entries(self) -> Vec<DiffEntry> {
+        self.entries.into_inner().unwrap()
+    }
+}
+
+/// Records every entry in memory and hands them to a [`CollectedEntries`] at `finish`, so that
+/// `calc_diff` can be run without writing anything: pass `CollectReport::new(&entries)` as the
+/// reporter and read `entries.into_entries()` once the run has returned. Skipped leaves and the
+/// paths of a names-only run are not collected, since no differ reports them.
+#[derive(Debug)]
+pub struct CollectReport<'a> {
+    target: &'a CollectedEntries,
+    entries: Mutex<Vec<DiffEntry>>,
+}
+
+impl<'a> CollectReport<'a> {
+    pub fn new(target: &'a CollectedEntries) -> CollectReport<'a> {
+        CollectReport {
+            target,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record_unchanged(&self, name: &str, compares: &'static str) {
+        self.record(name, EntryStatus::Unchanged, compares);
+    }
+
+    pub fn record_modified(&self, name: &str, compares: &'static str) {
+        self.record(name, EntryStatus::Modified, compares);
+    }
+
+    pub fn record_added(&self, name: &str, compares: &'static str) {
+        self.record(name, EntryStatus::Added, compares);
+    }
+
+    pub fn record_deleted(&self, name: &str, compares: &'static str) {
+        self.record(name, EntryStatus::Deleted, compares);
+    }
+
+    fn record(&self, name: &str, status: EntryStatus, compares: &'static str) {
+        self.entries.lock().unwrap().push(DiffEntry {
+            path: name.to_owned(),
+            status,
+            compares,
+        });
+    }
+}
+
+impl Reporter for CollectReport<'_> {
+    type Error = convert::Infallible;
+
+    fn start(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        let mut entries = self.entries.into_inner().unwrap();
+        entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        *self.target.entries.lock().unwrap() = entries;
+        Ok(())
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_unchanged(name, IDENTICAL_COMPARES);
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_added(name, EMPTY_NODE_COMPARES);
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_deleted(name, EMPTY_NODE_COMPARES);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_report_hands_over_the_entries_sorted_by_path_at_finish() {
+        let entries = CollectedEntries::new();
+        let report = CollectReport::new(&entries);
+        report.record_modified("b.json", "json");
+        report.record_unchanged("a.txt", "text");
+        report.report_deleted_empty_node("c").unwrap();
+        report.finish().unwrap();
+
+        assert_eq!(
+            entries.into_entries(),
+            [
+                DiffEntry {
+                    path: "a.txt".to_owned(),
+                    status: EntryStatus::Unchanged,
+                    compares: "text",
+                },
+                DiffEntry {
+                    path: "b.json".to_owned(),
+                    status: EntryStatus::Modified,
+                    compares: "json",
+                },
+                DiffEntry {
+                    path: "c".to_owned(),
+                    status: EntryStatus::Deleted,
+                    compares: "directory",
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+/// Status of a report entry, as passed to entry decorators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    Unchanged,
+    Modified,
+    Added,
+    Deleted,
+}
+
+impl EntryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryStatus::Unchanged => "unchanged",
+            EntryStatus::Modified => "modified",
+            EntryStatus::Added => "added",
+            EntryStatus::Deleted => "deleted",
+        }
+    }
+}
+
+/// User-supplied hook producing an extra fragment (a link to the source, commit info, ...) for
+/// each report entry, given its path and status. Entries for which it returns `None` are left
+/// as they are.
+pub type EntryDecorator<T> = Box<dyn Fn(&str, EntryStatus) -> Option<T> + Send + Sync>;
+
+#[cfg(test)]
+mod tests {
+    use crate::json::JsonReport;
+    use semdiff_core::Reporter;
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    #[test]
+    fn json_report_embeds_entry_decorations() {
+        let mut output = Vec::new();
+        let report = JsonReport::new(&mut output).with_entry_decorator(|name, status| {
+            (status != EntryStatus::Unchanged).then(|| json!({ "source": format!("https://example.com/{name}") }))
+        });
+        report.record_modified("a.txt", "text", ());
+        report.record_unchanged("b.txt", "text", ());
+        report.finish().unwrap();
+
+        let output = serde_json::from_slice::<Value>(&output).unwrap();
+        assert_eq!(
+            output["entries"]["a.txt"]["decoration"],
+            json!({ "source": "https://example.com/a.txt" })
+        );
+        assert!(output["entries"]["b.txt"].get("decoration").is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+use thiserror::Error;
+
+/// How an entry moved between a baseline JSON report and a current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftKind {
+    /// Unchanged (or missing) in the baseline, changed in the current report.
+    NewlyChanged,
+    /// Changed in the baseline, unchanged (or missing) in the current report.
+    StoppedChanging,
+    /// Changed in both reports, with a different status, differ, or stats.
+    StatsMoved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftEntry {
+    pub name: String,
+    pub kind: DriftKind,
+    pub baseline_status: Option<String>,
+    pub current_status: Option<String>,
+    /// Entry fields whose values differ between the two reports.
+    pub moved_fields: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ReportDriftError {
+    #[error("{0} report is not valid JSON: {1}")]
+    InvalidJson(&'static str, serde_json::Error),
+    #[error("{0} report is NDJSON (written by --output-ndjson); compare reports written by --output-json")]
+    Ndjson(&'static str),
+    #[error("{0} report has no \"entries\" object")]
+    MissingEntries(&'static str),
+    #[error("entry {0:?} has no \"status\"")]
+    MissingStatus(String),
+}
+
+/// Differences between the entries of two reports written by `JsonReport`.
+#[derive(Debug, Default)]
+pub struct ReportDrift {
+    entries: Vec<DriftEntry>,
+}
+
+impl ReportDrift {
+    /// Parses the text of a report written by `JsonReport`, telling the NDJSON that
+    /// `JsonReport::new_ndjson` writes apart from other invalid input. `which` names the report
+    /// in errors.
+    pub fn parse_report(text: &str, which: &'static str) -> Result<Value, ReportDriftError> {
+        let is_ndjson_line = |line: &str| {
+            serde_json::from_str::<Value>(line).is_ok_and(|line| line.get("type").is_some_and(Value::is_string))
+        };
+        match serde_json::from_str::<Value>(text) {
+            // A report of a single line, such as only the summary of an empty run.
+            Ok(report) if report.get("entries").is_none() && report.get("type").is_some_and(Value::is_string) => {
+                Err(ReportDriftError::Ndjson(which))
+            }
+            Ok(report) => Ok(report),
+            Err(_) if text.lines().next().is_some_and(is_ndjson_line) => Err(ReportDriftError::Ndjson(which)),
+            Err(err) => Err(ReportDriftError::InvalidJson(which, err)),
+        }
+    }
+
+    pub fn from_reports(baseline: &Value, current: &Value) -> Result<ReportDrift, ReportDriftError> {
+        let baseline = report_entries(baseline, "baseline")?;
+        let current = report_entries(current, "current")?;
+        let names = BTreeSet::from_iter(baseline.keys().chain(current.keys()));
+        let mut entries = Vec::new();
+        for name in names {
+            let baseline_entry = baseline.get(name).map(|entry| entry_status(name, entry)).transpose()?;
+            let current_entry = current.get(name).map(|entry| entry_status(name, entry)).transpose()?;
+            let baseline_changed = baseline_entry.is_some_and(|(status, _)| status != "unchanged");
+            let current_changed = current_entry.is_some_and(|(status, _)| status != "unchanged");
+            let moved_fields = match (baseline_entry, current_entry) {
+                (Some((_, baseline_entry)), Some((_, current_entry))) => moved_fields(baseline_entry, current_entry),
+                _ => Vec::new(),
+            };
+            let kind = match (baseline_changed, current_changed) {
+                (false, true) => DriftKind::NewlyChanged,
+                (true, false) => DriftKind::StoppedChanging,
+                (true, true) if !moved_fields.is_empty() => DriftKind::StatsMoved,
+                _ => continue,
+            };
+            entries.push(DriftEntry {
+                name: name.clone(),
+                kind,
+                baseline_status: baseline_entry.map(|(status, _)| status.to_owned()),
+                current_status: current_entry.map(|(status, _)| status.to_owned()),
+                moved_fields,
+            });
+        }
+        Ok(ReportDrift { entries })
+    }
+
+    pub fn entries(&self) -> &[DriftEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn count(&self, kind: DriftKind) -> usize {
+        self.entries.iter().filter(|entry| entry.kind == kind).count()
+    }
+
+    pub fn write_summary(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            r#"Report Drift
+Newly changed:    {}
+Stopped changing: {}
+Stats moved:      {}"#,
+            self.count(DriftKind::NewlyChanged),
+            self.count(DriftKind::StoppedChanging),
+            self.count(DriftKind::StatsMoved)
+        )?;
+        for (kind, heading) in [
+            (DriftKind::NewlyChanged, "Newly changed"),
+            (DriftKind::StoppedChanging, "Stopped changing"),
+            (DriftKind::StatsMoved, "Stats moved"),
+        ] {
+            let mut entries = self.entries.iter().filter(|entry| entry.kind == kind).peekable();
+            if entries.peek().is_none() {
+                continue;
+            }
+            writeln!(writer, "\n{heading}:")?;
+            for entry in entries {
+                let status = |status: &Option<String>| status.as_deref().unwrap_or("missing").to_owned();
+                write!(
+                    writer,
+                    "  {} ({} -> {})",
+                    entry.name,
+                    status(&entry.baseline_status),
+                    status(&entry.current_status)
+                )?;
+                if kind == DriftKind::StatsMoved {
+                    write!(writer, " [{}]", entry.moved_fields.join(", "))?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn report_entries<'a>(report: &'a Value, which: &'static str) -> Result<&'a Map<String, Value>, ReportDriftError> {
+    report
+        .get("entries")
+        .and_then(Value::as_object)
+        .ok_or(ReportDriftError::MissingEntries(which))
+}
+
+fn entry_status<'a>(name: &str, entry: &'a Value) -> Result<(&'a str, &'a Value), ReportDriftError> {
+    let status = entry
+        .get("status")
+        .and_then(Value::as_str)
+        .ok_or_else(|| ReportDriftError::MissingStatus(name.to_owned()))?;
+    Ok((status, entry))
+}
+
+fn moved_fields(baseline: &Value, current: &Value) -> Vec<String> {
+    let (Some(baseline), Some(current)) = (baseline.as_object(), current.as_object()) else {
+        return Vec::new();
+    };
+    BTreeSet::from_iter(baseline.keys().chain(current.keys()))
+        .into_iter()
+        .filter(|key| baseline.get(*key) != current.get(*key))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn report_drift_classifies_entries() {
+        let baseline = json!({
+            "entries": {
+                "fixed.txt": { "status": "modified", "compares": "text", "added": 1, "deleted": 0 },
+                "regressed.txt": { "status": "unchanged", "compares": "text" },
+                "moved.png": { "status": "modified", "compares": "image", "diff_ratio": 0.1 },
+                "same.png": { "status": "modified", "compares": "image", "diff_ratio": 0.2 },
+                "removed.txt": { "status": "deleted", "compares": "text" },
+            }
+        });
+        let current = json!({
+            "entries": {
+                "fixed.txt": { "status": "unchanged", "compares": "text" },
+                "regressed.txt": { "status": "modified", "compares": "text", "added": 2, "deleted": 2 },
+                "moved.png": { "status": "modified", "compares": "image", "diff_ratio": 0.3 },
+                "same.png": { "status": "modified", "compares": "image", "diff_ratio": 0.2 },
+                "new.txt": { "status": "added", "compares": "text" },
+            }
+        });
+        let drift = ReportDrift::from_reports(&baseline, &current).unwrap();
+        let kinds = drift
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                ("fixed.txt", DriftKind::StoppedChanging),
+                ("moved.png", DriftKind::StatsMoved),
+                ("new.txt", DriftKind::NewlyChanged),
+                ("regressed.txt", DriftKind::NewlyChanged),
+                ("removed.txt", DriftKind::StoppedChanging),
+            ]
+        );
+        assert_eq!(drift.entries()[1].moved_fields, ["diff_ratio"]);
+    }
+
+    #[test]
+    fn report_drift_rejects_non_report_input() {
+        let err = ReportDrift::from_reports(&json!({ "entries": {} }), &json!([])).unwrap_err();
+        assert!(matches!(err, ReportDriftError::MissingEntries("current")));
+
+        let ndjson = concat!(
+            r#"{"type":"entry","name":"a.txt","status":"modified","compares":"text"}"#,
+            "\n",
+            r#"{"type":"summary","unchanged":0,"modified":1,"added":0,"deleted":0}"#,
+            "\n",
+        );
+        let err = ReportDrift::parse_report(ndjson, "baseline").unwrap_err();
+        assert!(matches!(err, ReportDriftError::Ndjson("baseline")));
+        let summary_only = r#"{"type":"summary","unchanged":0,"modified":0,"added":0,"deleted":0}"#;
+        let err = ReportDrift::parse_report(summary_only, "current").unwrap_err();
+        assert!(matches!(err, ReportDriftError::Ndjson("current")));
+        let err = ReportDrift::parse_report("{", "current").unwrap_err();
+        assert!(matches!(err, ReportDriftError::InvalidJson("current", _)));
+        assert!(ReportDrift::parse_report(r#"{"entries":{}}"#, "current").is_ok());
+    }
+}
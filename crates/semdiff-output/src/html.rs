@@ -1,11 +1,14 @@
+use crate::borderline::BorderlineEntries;
+use crate::decorate::{EntryDecorator, EntryStatus};
 use askama::Template;
-use dashmap::DashMap;
-use semdiff_core::Reporter;
-use std::collections::BTreeMap;
+use dashmap::{DashMap, DashSet};
+use semdiff_core::{EntryType, FormatRegression, Reporter, Sampling, SkipReason, ToleranceCheck};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use thiserror::Error;
 use xxhash_rust::xxh3::xxh3_128;
 
@@ -13,11 +16,25 @@ pub struct HtmlReport {
     root: PathBuf,
     detail_dir: PathBuf,
     detail_dir_name: String,
+    root_file_name: String,
     back_link: String,
+    paginate_by_dir: bool,
+    changes_only: bool,
     unchanged_entries: DashMap<String, HtmlReportEntry>,
+    /// Unchanged entries that are only counted, with [`HtmlReport::with_changes_only`].
+    unlisted_unchanged: DashSet<String>,
     modified_entries: DashMap<String, HtmlReportEntry>,
     added_entries: DashMap<String, HtmlReportEntry>,
     deleted_entries: DashMap<String, HtmlReportEntry>,
+    kind_changes: DashMap<String, String>,
+    format_regressions: DashMap<String, String>,
+    renames: DashMap<String, String>,
+    reencoded: DashSet<String>,
+    skipped: DashMap<String, SkipReason>,
+    type_changes: DashMap<String, String>,
+    sampling: Mutex<Option<String>>,
+    borderline: Option<BorderlineEntries>,
+    entry_decorator: Option<EntryDecorator<String>>,
 }
 
 impl HtmlReport {
@@ -36,14 +53,71 @@ impl HtmlReport {
             root,
             detail_dir,
             detail_dir_name,
+            root_file_name,
             back_link,
+            paginate_by_dir: false,
+            changes_only: false,
             unchanged_entries: DashMap::new(),
+            unlisted_unchanged: DashSet::new(),
             modified_entries: DashMap::new(),
             added_entries: DashMap::new(),
             deleted_entries: DashMap::new(),
+            kind_changes: DashMap::new(),
+            format_regressions: DashMap::new(),
+            renames: DashMap::new(),
+            reencoded: DashSet::new(),
+            skipped: DashMap::new(),
+            type_changes: DashMap::new(),
+            sampling: Mutex::new(None),
+            borderline: None,
+            entry_decorator: None,
         }
     }
 
+    /// Marks entries with a tolerance check that measured within `band` (a fraction of the
+    /// threshold) of its threshold with a "borderline" badge; see [`BorderlineEntries`].
+    pub fn with_borderline_band(mut self, band: f64) -> Self {
+        self.borderline = Some(BorderlineEntries::new(band));
+        self
+    }
+
+    /// Writes a page per directory instead of listing every entry on the root page. Each page lists
+    /// the entries directly in its directory and links to its subdirectories, with their counts,
+    /// and to its parent directories. Pages other than the root page go in the detail directory.
+    pub fn with_paginate_by_dir(mut self) -> Self {
+        self.paginate_by_dir = true;
+        self
+    }
+
+    /// Counts unchanged entries without listing them or writing their previews, detail pages, or
+    /// assets, so the report only grows with the changed entries.
+    pub fn with_changes_only(mut self) -> Self {
+        self.changes_only = true;
+        self
+    }
+
+    /// Whether unchanged entries are listed; when they are not, differs should report them through
+    /// [`Self::count_unchanged`] instead of writing assets for them.
+    pub fn lists_unchanged(&self) -> bool {
+        !self.changes_only
+    }
+
+    /// Counts an unchanged entry on the root page (and its directory pages) without listing it.
+    pub fn count_unchanged(&self, name: &str) {
+        let inserted = self.unlisted_unchanged.insert(name.to_owned());
+        assert!(inserted, "entry already exists: {name}");
+    }
+
+    /// Inserts the HTML returned by `decorator` verbatim into each entry's detail page. The
+    /// fragment is not escaped.
+    pub fn with_entry_decorator(
+        mut self,
+        decorator: impl Fn(&str, EntryStatus) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.entry_decorator = Some(Box::new(decorator));
+        self
+    }
+
     pub fn record_unchanged(
         &self,
         name: &str,
@@ -51,6 +125,10 @@ impl HtmlReport {
         preview_html: impl Template,
         detail_html: impl Template,
     ) -> Result<(), HtmlReportError> {
+        if !self.lists_unchanged() {
+            self.count_unchanged(name);
+            return Ok(());
+        }
         let preview_html = preview_html.render()?;
         let detail_html = detail_html.render()?;
         let detail_file_name = Some(self.write_detail(name, HtmlEntryStatus::Unchanged, compares, &detail_html)?);
@@ -116,6 +194,55 @@ impl HtmlReport {
         Ok(())
     }
 
+    /// Lists an unchanged entry with only its preview, without writing a detail page for it.
+    pub fn record_unchanged_without_detail(
+        &self,
+        name: &str,
+        compares: &'static str,
+        preview_html: impl Template,
+    ) -> Result<(), HtmlReportError> {
+        if !self.lists_unchanged() {
+            self.count_unchanged(name);
+            return Ok(());
+        }
+        let preview_html = preview_html.render()?;
+        self.insert_entry(
+            HtmlEntryStatus::Unchanged,
+            name,
+            HtmlReportEntry::new(HtmlEntryStatus::Unchanged, compares, preview_html, None),
+        );
+        Ok(())
+    }
+
+    /// Lists a leaf with the same bytes on both sides, which no differ has seen, without a preview
+    /// or detail page.
+    fn record_identical(&self, name: &str) -> Result<(), HtmlReportError> {
+        const COMPARES: &str = "identical";
+        const BODY_HTML: &str = "<p>Same bytes on both sides.</p>";
+        if !self.lists_unchanged() {
+            self.count_unchanged(name);
+            return Ok(());
+        }
+        self.insert_entry(
+            HtmlEntryStatus::Unchanged,
+            name,
+            HtmlReportEntry::new(HtmlEntryStatus::Unchanged, COMPARES, BODY_HTML.to_owned(), None),
+        );
+        Ok(())
+    }
+
+    fn record_empty_node(&self, status: HtmlEntryStatus, name: &str) -> Result<(), HtmlReportError> {
+        const COMPARES: &str = "directory";
+        const BODY_HTML: &str = "<p>Empty directory.</p>";
+        let detail_file_name = Some(self.write_detail(name, status, COMPARES, BODY_HTML)?);
+        self.insert_entry(
+            status,
+            name,
+            HtmlReportEntry::new(status, COMPARES, BODY_HTML.to_owned(), detail_file_name),
+        );
+        Ok(())
+    }
+
     fn insert_entry(&self, status: HtmlEntryStatus, name: &str, entry: HtmlReportEntry) {
         let key = name.to_owned();
         let previous = match status {
@@ -131,6 +258,17 @@ impl HtmlReport {
         format!("{}.html", Self::make_detail_stem(name))
     }
 
+    /// The page of directory `dir`, in the detail directory. Entry names never end with `/`, so
+    /// it does not clash with the detail page of a file of the same name.
+    fn make_directory_filename(dir: &str) -> String {
+        Self::make_detail_filename(&format!("{dir}/"))
+    }
+
+    /// The directory of entry `name`, or `""` for entries at the root.
+    fn parent_dir(name: &str) -> &str {
+        name.rsplit_once('/').map_or("", |(dir, _)| dir)
+    }
+
     fn make_detail_stem(name: &str) -> String {
         let sanitized = Self::sanitize_segment(name);
         let hash = xxh3_128(name.as_bytes());
@@ -190,13 +328,35 @@ impl HtmlReport {
     ) -> Result<String, HtmlReportError> {
         fs::create_dir_all(&self.detail_dir)?;
         let file_name = Self::make_detail_filename(name);
+        let kind_change = self.kind_changes.get(name);
+        let format_regression = self.format_regressions.get(name);
+        let renamed_from = self.renames.get(name);
+        let borderline = self
+            .borderline
+            .as_ref()
+            .and_then(|borderline| borderline.get(name))
+            .map(borderline_label);
+        let decoration_html = self
+            .entry_decorator
+            .as_ref()
+            .and_then(|decorator| decorator(name, status.into()));
+        let back_link = match Self::parent_dir(name) {
+            dir if self.paginate_by_dir && !dir.is_empty() => Self::make_directory_filename(dir),
+            _ => self.back_link.clone(),
+        };
         let template = DetailTemplate {
             name,
             status_label: status.label(),
             status_class: status.class(),
             compares,
+            kind_change: kind_change.as_deref().map(String::as_str),
+            format_regression: format_regression.as_deref().map(String::as_str),
+            renamed_from: renamed_from.as_deref().map(String::as_str),
+            borderline: borderline.as_deref(),
+            reencoded: self.reencoded.contains(name),
+            decoration_html: decoration_html.as_deref(),
             body_html,
-            back_link: &self.back_link,
+            back_link: &back_link,
         };
         let rendered = template.render()?;
         fs::write(self.detail_dir.join(&file_name), rendered)?;
@@ -255,6 +415,17 @@ impl HtmlEntryStatus {
     }
 }
 
+impl From<HtmlEntryStatus> for EntryStatus {
+    fn from(status: HtmlEntryStatus) -> Self {
+        match status {
+            HtmlEntryStatus::Unchanged => EntryStatus::Unchanged,
+            HtmlEntryStatus::Modified => EntryStatus::Modified,
+            HtmlEntryStatus::Added => EntryStatus::Added,
+            HtmlEntryStatus::Deleted => EntryStatus::Deleted,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum HtmlReportError {
     #[error("io error: {0}")]
@@ -263,15 +434,99 @@ pub enum HtmlReportError {
     Template(#[from] askama::Error),
 }
 
+/// The order of the entry groups on a page.
+const STATUS_ORDER: [HtmlEntryStatus; 4] = [
+    HtmlEntryStatus::Modified,
+    HtmlEntryStatus::Deleted,
+    HtmlEntryStatus::Added,
+    HtmlEntryStatus::Unchanged,
+];
+
+/// The entries of one page, grouped in [`STATUS_ORDER`].
+#[derive(Default)]
+struct HtmlPage {
+    entry_groups: [Vec<HtmlEntryView>; 4],
+    skipped: Vec<HtmlSkippedView>,
+    type_changes: Vec<HtmlTypeChangeView>,
+    /// Full names of the directories directly below this one.
+    directories: BTreeSet<String>,
+    /// Entries in this directory and below, in [`STATUS_ORDER`].
+    subtree_counts: [usize; 4],
+    /// Unchanged entries in this directory that are counted but not listed.
+    unlisted_unchanged: usize,
+}
+
 #[derive(Template)]
 #[template(path = "report_root.html")]
 struct RootTemplate<'a> {
+    /// Set on pages in the detail directory, so that links read the same on every page.
+    base_href: Option<&'a str>,
+    breadcrumbs: &'a [HtmlLinkView],
+    directory_counts: Option<String>,
+    directories: &'a [HtmlDirectoryView],
     total: usize,
     unchanged: usize,
+    unlisted_unchanged: usize,
     modified: usize,
     added: usize,
     deleted: usize,
     entry_groups: &'a [HtmlEntryGroup],
+    skipped: &'a [HtmlSkippedView],
+    type_changes: &'a [HtmlTypeChangeView],
+    sampling: Option<String>,
+}
+
+struct HtmlLinkView {
+    name: String,
+    link: String,
+}
+
+struct HtmlDirectoryView {
+    name: String,
+    link: String,
+    counts: String,
+}
+
+/// Counts in [`STATUS_ORDER`] as "3 modified, 1 added".
+fn count_label(counts: &[usize; 4]) -> String {
+    let parts = STATUS_ORDER
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| **count > 0)
+        .map(|(status, count)| format!("{count} {}", status.label()))
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "no files".to_owned()
+    } else {
+        parts.join(", ")
+    }
+}
+
+struct HtmlSkippedView {
+    name: String,
+    reason: &'static str,
+}
+
+struct HtmlTypeChangeView {
+    name: String,
+    change: String,
+}
+
+fn borderline_label(check: ToleranceCheck) -> String {
+    format!(
+        "{} {:.4} vs threshold {:.4}",
+        check.metric, check.measured, check.threshold
+    )
+}
+
+fn skip_reason_label(reason: SkipReason) -> &'static str {
+    match reason {
+        SkipReason::TooSmall => "too small",
+        SkipReason::TooLarge => "too large",
+        SkipReason::Unsupported => "unsupported",
+        SkipReason::SpecialFile => "special file",
+        SkipReason::Unreadable => "unreadable",
+    }
 }
 
 struct HtmlEntryGroup {
@@ -287,6 +542,12 @@ struct DetailTemplate<'a> {
     status_label: &'a str,
     status_class: &'a str,
     compares: &'a str,
+    kind_change: Option<&'a str>,
+    format_regression: Option<&'a str>,
+    renamed_from: Option<&'a str>,
+    borderline: Option<&'a str>,
+    reencoded: bool,
+    decoration_html: Option<&'a str>,
     body_html: &'a str,
     back_link: &'a str,
 }
@@ -296,6 +557,11 @@ struct HtmlEntryView {
     status_label: &'static str,
     status_class: &'static str,
     compares: &'static str,
+    kind_change: Option<String>,
+    format_regression: Option<String>,
+    renamed_from: Option<String>,
+    borderline: Option<String>,
+    reencoded: bool,
     preview_html: String,
     detail_link: String,
 }
@@ -310,59 +576,257 @@ impl Reporter for HtmlReport {
     fn finish(self) -> Result<(), Self::Error> {
         let HtmlReport {
             root,
+            detail_dir,
             detail_dir_name,
+            root_file_name,
+            paginate_by_dir,
             unchanged_entries,
+            unlisted_unchanged,
             modified_entries,
             added_entries,
             deleted_entries,
+            kind_changes,
+            format_regressions,
+            renames,
+            reencoded,
+            skipped,
+            type_changes,
+            sampling,
+            borderline,
             ..
         } = self;
-        let unchanged_count = unchanged_entries.len();
-        let modified_count = modified_entries.len();
-        let added_count = added_entries.len();
-        let deleted_count = deleted_entries.len();
-        let status_order = [
-            (HtmlEntryStatus::Modified, modified_entries),
-            (HtmlEntryStatus::Deleted, deleted_entries),
-            (HtmlEntryStatus::Added, added_entries),
-            (HtmlEntryStatus::Unchanged, unchanged_entries),
-        ];
-        let mut entry_groups = Vec::with_capacity(status_order.len());
-        for (status, entries) in status_order {
+        // Without pagination, every entry goes on the root page, keyed by "".
+        let page_of = |name: &str| {
+            if paginate_by_dir {
+                HtmlReport::parent_dir(name)
+            } else {
+                ""
+            }
+            .to_owned()
+        };
+        let mut pages = BTreeMap::<String, HtmlPage>::new();
+        let entries_by_status = [modified_entries, deleted_entries, added_entries, unchanged_entries];
+        let count_in_subtrees = |pages: &mut BTreeMap<String, HtmlPage>, name: &str, index: usize| {
+            let mut dir = page_of(name);
+            loop {
+                pages.entry(dir.clone()).or_default().subtree_counts[index] += 1;
+                if dir.is_empty() {
+                    break;
+                }
+                dir = HtmlReport::parent_dir(&dir).to_owned();
+            }
+        };
+        for (index, entries) in entries_by_status.iter().enumerate() {
+            for entry in entries.iter() {
+                count_in_subtrees(&mut pages, entry.key(), index);
+            }
+        }
+        // Unchanged entries come last in `entries_by_status`.
+        let unchanged_index = entries_by_status.len() - 1;
+        for name in unlisted_unchanged {
+            count_in_subtrees(&mut pages, &name, unchanged_index);
+            pages.entry(page_of(&name)).or_default().unlisted_unchanged += 1;
+        }
+        for (index, entries) in entries_by_status.into_iter().enumerate() {
             let sorted_entries = BTreeMap::from_iter(entries);
-            let mut group_entries = Vec::new();
             for (name, entry) in sorted_entries {
                 let detail_link = entry
                     .detail_file_name
                     .as_ref()
                     .map(|file_name| format!("{}/{}", detail_dir_name, file_name))
                     .unwrap_or_default();
-                group_entries.push(HtmlEntryView {
+                let kind_change = kind_changes.get(&name).map(|kind_change| kind_change.clone());
+                let format_regression = format_regressions.get(&name).map(|regression| regression.clone());
+                let renamed_from = renames.get(&name).map(|renamed_from| renamed_from.clone());
+                let borderline = borderline
+                    .as_ref()
+                    .and_then(|borderline| borderline.get(&name))
+                    .map(borderline_label);
+                let is_reencoded = reencoded.contains(&name);
+                let page = pages.entry(page_of(&name)).or_default();
+                page.entry_groups[index].push(HtmlEntryView {
                     name,
                     status_label: entry.status.label(),
                     status_class: entry.status.class(),
                     compares: entry.compares,
+                    kind_change,
+                    format_regression,
+                    renamed_from,
+                    borderline,
+                    reencoded: is_reencoded,
                     preview_html: entry.preview_html.clone(),
                     detail_link,
                 });
             }
-            entry_groups.push(HtmlEntryGroup {
-                status_label: status.label(),
-                status_class: status.class(),
-                entries: group_entries,
+        }
+        for (name, reason) in BTreeMap::from_iter(skipped) {
+            pages.entry(page_of(&name)).or_default().skipped.push(HtmlSkippedView {
+                reason: skip_reason_label(reason),
+                name,
             });
         }
+        for (name, change) in BTreeMap::from_iter(type_changes) {
+            pages
+                .entry(page_of(&name))
+                .or_default()
+                .type_changes
+                .push(HtmlTypeChangeView { name, change });
+        }
+        // Link every page from its parent, creating pages for directories with only subdirectories.
+        for dir in pages.keys().cloned().collect::<Vec<_>>() {
+            let mut dir = dir;
+            while !dir.is_empty() {
+                let parent = HtmlReport::parent_dir(&dir).to_owned();
+                pages.entry(parent.clone()).or_default().directories.insert(dir);
+                dir = parent;
+            }
+        }
 
-        let template = RootTemplate {
-            total: unchanged_count + modified_count + added_count + deleted_count,
-            unchanged: unchanged_count,
-            modified: modified_count,
-            added: added_count,
-            deleted: deleted_count,
-            entry_groups: &entry_groups,
+        let sampling = sampling.into_inner().unwrap();
+        let link_to = |dir: &str| {
+            if dir.is_empty() {
+                root_file_name.clone()
+            } else {
+                format!("{}/{}", detail_dir_name, HtmlReport::make_directory_filename(dir))
+            }
         };
-        let rendered = template.render()?;
-        fs::write(root, rendered)?;
+        let root_page = pages.remove("").unwrap_or_default();
+        let subtree_counts = pages
+            .iter()
+            .map(|(dir, page)| (dir.clone(), page.subtree_counts))
+            .collect::<BTreeMap<_, _>>();
+        for (dir, page) in std::iter::once((String::new(), root_page)).chain(pages) {
+            let entry_groups = STATUS_ORDER
+                .into_iter()
+                .zip(page.entry_groups)
+                .map(|(status, entries)| HtmlEntryGroup {
+                    status_label: status.label(),
+                    status_class: status.class(),
+                    entries,
+                })
+                .collect::<Vec<_>>();
+            let count = |status: HtmlEntryStatus| {
+                entry_groups
+                    .iter()
+                    .find(|group| group.status_class == status.class())
+                    .map_or(0, |group| group.entries.len())
+            };
+            let directories = page
+                .directories
+                .iter()
+                .map(|child| HtmlDirectoryView {
+                    name: child[dir.len()..].trim_start_matches('/').to_owned(),
+                    link: link_to(child),
+                    counts: count_label(&subtree_counts[child]),
+                })
+                .collect::<Vec<_>>();
+            let mut breadcrumbs = Vec::new();
+            if paginate_by_dir && !dir.is_empty() {
+                breadcrumbs.push(HtmlLinkView {
+                    name: "(root)".to_owned(),
+                    link: link_to(""),
+                });
+                let mut end = 0;
+                for segment in dir.split('/') {
+                    end += segment.len();
+                    breadcrumbs.push(HtmlLinkView {
+                        name: segment.to_owned(),
+                        link: link_to(&dir[..end]),
+                    });
+                    end += 1;
+                }
+            }
+            let template = RootTemplate {
+                base_href: (!dir.is_empty()).then_some("../"),
+                breadcrumbs: &breadcrumbs,
+                directory_counts: paginate_by_dir.then(|| count_label(&page.subtree_counts)),
+                directories: &directories,
+                total: count(HtmlEntryStatus::Unchanged)
+                    + page.unlisted_unchanged
+                    + count(HtmlEntryStatus::Modified)
+                    + count(HtmlEntryStatus::Added)
+                    + count(HtmlEntryStatus::Deleted),
+                unchanged: count(HtmlEntryStatus::Unchanged) + page.unlisted_unchanged,
+                unlisted_unchanged: page.unlisted_unchanged,
+                modified: count(HtmlEntryStatus::Modified),
+                added: count(HtmlEntryStatus::Added),
+                deleted: count(HtmlEntryStatus::Deleted),
+                entry_groups: &entry_groups,
+                skipped: &page.skipped,
+                type_changes: &page.type_changes,
+                sampling: sampling.clone(),
+            };
+            let rendered = template.render()?;
+            if dir.is_empty() {
+                fs::write(&root, rendered)?;
+            } else {
+                fs::create_dir_all(&detail_dir)?;
+                fs::write(detail_dir.join(HtmlReport::make_directory_filename(&dir)), rendered)?;
+            }
+        }
         Ok(())
     }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.type_changes.insert(
+            name.to_owned(),
+            format!("{} \u{2192} {}", expected.as_str(), actual.as_str()),
+        );
+        Ok(())
+    }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        self.kind_changes
+            .insert(name.to_owned(), format!("{expected_kind} \u{2192} {actual_kind}"));
+        Ok(())
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.format_regressions.insert(name.to_owned(), regression.to_string());
+        Ok(())
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        *self.sampling.lock().unwrap() = Some(format!(
+            "Sampled at rate {} (seed {}); {left_out} files were not compared.",
+            sampling.rate(),
+            sampling.seed()
+        ));
+        Ok(())
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        if let Some(borderline) = &self.borderline {
+            borderline.record(name, check);
+        }
+        Ok(())
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.reencoded.insert(name.to_owned());
+        Ok(())
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_identical(name)
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        self.renames
+            .insert(name.to_owned(), format!("{from} ({:.0}% similar)", similarity * 100.0));
+        Ok(())
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        self.skipped.insert(name.to_owned(), reason);
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_empty_node(HtmlEntryStatus::Added, name)
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_empty_node(HtmlEntryStatus::Deleted, name)
+    }
 }
@@ -1,32 +1,141 @@
-use dashmap::DashMap;
-use semdiff_core::Reporter;
+use crate::borderline::BorderlineEntries;
+use crate::decorate::{EntryDecorator, EntryStatus};
+use crate::numstat::numstat_columns;
+use crate::score::{DiffScore, DiffScoreWeights};
+use dashmap::{DashMap, DashSet};
+use semdiff_core::{EntryType, FormatRegression, Reporter, Sampling, SkipReason, ToleranceCheck};
 use serde::Serialize;
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::io::Write;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const EMPTY_NODE_COMPARES: &str = "directory";
+/// What leaves with the same bytes on both sides are listed as compared by, since no differ saw
+/// them.
+const IDENTICAL_COMPARES: &str = "identical";
 
 pub struct JsonReport<W> {
-    writer: W,
+    writer: Mutex<W>,
+    /// Write each entry as its own line as soon as it is recorded, instead of one document at the
+    /// end.
+    ndjson: bool,
+    /// Write a numstat line per modified entry at `finish` instead of the JSON document.
+    numstat: bool,
+    /// Decimal places that non-integer numbers are rounded to when written.
+    precision: Option<u8>,
+    /// How long written lines may stay in the writer's buffer, if they are flushed before `finish`.
+    flush_interval: Option<Duration>,
+    last_flush: Mutex<Instant>,
+    /// First error from writing a line, returned from `finish`.
+    write_error: Mutex<Option<serde_json::Error>>,
     unchanged: AtomicUsize,
     modified: AtomicUsize,
     added: AtomicUsize,
     deleted: AtomicUsize,
     entries: DashMap<String, JsonReportEntry>,
+    kind_changes: DashMap<String, JsonKindChange>,
+    format_regressions: DashMap<String, JsonFormatRegression>,
+    renames: DashMap<String, JsonRename>,
+    reencoded: DashSet<String>,
+    skipped: DashMap<String, SkipReason>,
+    type_changes: DashMap<String, JsonKindChange>,
+    sampling: Mutex<Option<JsonSampling>>,
+    diff_score: Option<DiffScore>,
+    borderline: Option<BorderlineEntries>,
+    entry_decorator: Option<EntryDecorator<Value>>,
 }
 
 impl<W> JsonReport<W> {
     pub fn new(writer: W) -> JsonReport<W> {
         JsonReport {
-            writer,
+            writer: Mutex::new(writer),
+            ndjson: false,
+            numstat: false,
+            precision: None,
+            flush_interval: None,
+            last_flush: Mutex::new(Instant::now()),
+            write_error: Mutex::new(None),
             unchanged: AtomicUsize::new(0),
             modified: AtomicUsize::new(0),
             added: AtomicUsize::new(0),
             deleted: AtomicUsize::new(0),
             entries: DashMap::new(),
+            kind_changes: DashMap::new(),
+            format_regressions: DashMap::new(),
+            renames: DashMap::new(),
+            reencoded: DashSet::new(),
+            skipped: DashMap::new(),
+            type_changes: DashMap::new(),
+            sampling: Mutex::new(None),
+            diff_score: None,
+            borderline: None,
+            entry_decorator: None,
+        }
+    }
+
+    /// Writes newline-delimited JSON: one line per entry as it completes, then a summary line.
+    /// Every line is an object whose `type` is `entry`, `skipped`, `type_change`, or `summary`.
+    pub fn new_ndjson(writer: W) -> JsonReport<W> {
+        JsonReport {
+            ndjson: true,
+            ..JsonReport::new(writer)
+        }
+    }
+
+    /// Writes one line per modified entry at `finish`, like `git diff --numstat`: two counts, the
+    /// compared type, and the path, separated by tabs and sorted by path. See
+    /// [`crate::numstat`] for what the counts are.
+    pub fn new_numstat(writer: W) -> JsonReport<W> {
+        JsonReport {
+            numstat: true,
+            ..JsonReport::new(writer)
         }
     }
 
+    /// Flushes the writer after writing a line once `interval` has passed since the last flush,
+    /// so that a consumer reading the lines as they come does not wait for a buffered writer to
+    /// fill up. Every line is flushed with [`Duration::ZERO`]. Only affects NDJSON, since the
+    /// single document is written at `finish`.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+
+    /// Rounds every non-integer number in the output, such as `duration_seconds` or `diff_ratio`,
+    /// to `decimals` decimal places, so that reports of the same inputs compare equal across
+    /// platforms. Integers are written as they are.
+    pub fn with_precision(mut self, decimals: u8) -> Self {
+        self.precision = Some(decimals);
+        self
+    }
+
+    /// Adds a `diff_score` for the whole run, computed with `weights`.
+    pub fn with_diff_score(mut self, weights: DiffScoreWeights) -> Self {
+        self.diff_score = Some(DiffScore::new(weights));
+        self
+    }
+
+    /// Adds a `borderline` field to entries with a tolerance check that measured within `band` (a
+    /// fraction of the threshold) of its threshold; see [`BorderlineEntries`].
+    pub fn with_borderline_band(mut self, band: f64) -> Self {
+        self.borderline = Some(BorderlineEntries::new(band));
+        self
+    }
+
+    /// Embeds the value returned by `decorator` as the `decoration` field of each entry.
+    pub fn with_entry_decorator(
+        mut self,
+        decorator: impl Fn(&str, EntryStatus) -> Option<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.entry_decorator = Some(Box::new(decorator));
+        self
+    }
+}
+
+impl<W: Write> JsonReport<W> {
     pub fn record_unchanged(&self, name: &str, compares: &'static str, additional: impl Serialize) {
         self.unchanged.fetch_add(1, Ordering::Relaxed);
         self.insert_entry(
@@ -56,10 +165,118 @@ impl<W> JsonReport<W> {
         );
     }
 
-    fn insert_entry(&self, name: &str, entry: JsonReportEntry) {
+    fn insert_entry(&self, name: &str, mut entry: JsonReportEntry) {
+        entry.decoration = self
+            .entry_decorator
+            .as_ref()
+            .and_then(|decorator| decorator(name, entry.status.into()));
+        if self.ndjson {
+            // Kind mismatches, format regressions, renames, tolerance checks, and re-encodings are
+            // reported before the entry itself.
+            entry.kind_change = self.kind_changes.remove(name).map(|(_, kind_change)| kind_change);
+            entry.format_regression = self.format_regressions.remove(name).map(|(_, regression)| regression);
+            entry.renamed_from = self.renames.remove(name).map(|(_, rename)| rename);
+            entry.borderline = self
+                .borderline
+                .as_ref()
+                .and_then(|borderline| borderline.remove(name))
+                .map(JsonToleranceCheck::from);
+            entry.reencoded = self.reencoded.remove(name).is_some();
+            self.write_line(&JsonLine::Entry { name, entry: &entry });
+            return;
+        }
         let key = name.to_owned();
         assert!(self.entries.insert(key, entry).is_none());
     }
+
+    /// Removes the entry recorded for `name` along with the kind change, format regression, and
+    /// re-encoding reported for it, or returns `None` if no entry was recorded.
+    pub(crate) fn take_entry(&self, name: &str) -> Option<JsonReportEntry> {
+        let (_, mut entry) = self.entries.remove(name)?;
+        entry.kind_change = self.kind_changes.remove(name).map(|(_, kind_change)| kind_change);
+        entry.format_regression = self.format_regressions.remove(name).map(|(_, regression)| regression);
+        entry.reencoded = self.reencoded.remove(name).is_some();
+        Some(entry)
+    }
+
+    fn write_line(&self, line: &JsonLine) {
+        let mut writer = self.writer.lock().unwrap();
+        let result = match self.precision {
+            Some(decimals) => {
+                round_floats_of(line, decimals).and_then(|line| serde_json::to_writer(&mut *writer, &line))
+            }
+            None => serde_json::to_writer(&mut *writer, line),
+        }
+        .and_then(|()| writer.write_all(b"\n").map_err(serde_json::Error::io))
+        .and_then(|()| match self.flush_interval {
+            Some(interval) => {
+                let mut last_flush = self.last_flush.lock().unwrap();
+                if last_flush.elapsed() < interval {
+                    return Ok(());
+                }
+                *last_flush = Instant::now();
+                writer.flush().map_err(serde_json::Error::io)
+            }
+            None => Ok(()),
+        });
+        if let Err(err) = result {
+            self.write_error.lock().unwrap().get_or_insert(err);
+        }
+    }
+}
+
+/// Serializes `value` with its non-integer numbers rounded to `decimals` decimal places.
+fn round_floats_of(value: &impl Serialize, decimals: u8) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    round_floats(&mut value, decimals);
+    Ok(value)
+}
+
+fn round_floats(value: &mut Value, decimals: u8) {
+    match value {
+        Value::Number(number) => {
+            if number.is_f64()
+                && let Some(float) = number.as_f64()
+                && let Ok(rounded) = format!("{float:.0$}", usize::from(decimals)).parse::<f64>()
+                && let Some(rounded) = serde_json::Number::from_f64(rounded)
+            {
+                *number = rounded;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| round_floats(value, decimals)),
+        Value::Object(map) => map.values_mut().for_each(|value| round_floats(value, decimals)),
+        Value::Null | Value::Bool(_) | Value::String(_) => {}
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLine<'a> {
+    Entry {
+        name: &'a str,
+        #[serde(flatten)]
+        entry: &'a JsonReportEntry,
+    },
+    Skipped {
+        name: &'a str,
+        reason: &'static str,
+    },
+    #[serde(rename = "type_change")]
+    TypeChange {
+        name: &'a str,
+        #[serde(flatten)]
+        change: &'a JsonKindChange,
+    },
+    Summary {
+        unchanged: usize,
+        modified: usize,
+        added: usize,
+        deleted: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diff_score: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sampling: Option<JsonSampling>,
+    },
 }
 
 #[derive(Serialize)]
@@ -68,37 +285,136 @@ struct JsonReportOutput {
     modified: usize,
     added: usize,
     deleted: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff_score: Option<f64>,
     entries: BTreeMap<String, JsonReportEntry>,
+    /// Leaves left out of the comparison, with the reason.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    skipped: BTreeMap<String, &'static str>,
+    /// Names that are a directory on one side and a file on the other.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    type_changes: BTreeMap<String, JsonKindChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<JsonSampling>,
+}
+
+#[derive(Serialize)]
+struct JsonSampling {
+    rate: f64,
+    seed: u64,
+    /// Leaves that were not compared because the sample did not include them.
+    left_out: usize,
 }
 
 #[derive(Serialize)]
-struct JsonReportEntry {
-    status: JsonEntryStatus,
-    compares: &'static str,
+pub(crate) struct JsonReportEntry {
+    pub(crate) status: JsonEntryStatus,
+    pub(crate) compares: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind_change: Option<JsonKindChange>,
+    /// Only one side parsed as the format of a differ that then left the entry to the next one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format_regression: Option<JsonFormatRegression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    renamed_from: Option<JsonRename>,
+    /// The tolerance check that came closest to flipping the result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    borderline: Option<JsonToleranceCheck>,
+    /// Decoded content is equal, but the bytes differ.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reencoded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decoration: Option<Value>,
     #[serde(flatten)]
     additional: Value,
 }
 
+#[derive(Serialize)]
+struct JsonKindChange {
+    expected: String,
+    actual: String,
+}
+
+#[derive(Serialize)]
+struct JsonFormatRegression {
+    format: &'static str,
+    /// The side that parsed, `expected` or `actual`.
+    parsed: &'static str,
+}
+
+impl From<&FormatRegression> for JsonFormatRegression {
+    fn from(regression: &FormatRegression) -> Self {
+        JsonFormatRegression {
+            format: regression.format,
+            parsed: if regression.expected_parsed {
+                "expected"
+            } else {
+                "actual"
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonToleranceCheck {
+    metric: &'static str,
+    measured: f64,
+    threshold: f64,
+}
+
+impl From<ToleranceCheck> for JsonToleranceCheck {
+    fn from(check: ToleranceCheck) -> Self {
+        JsonToleranceCheck {
+            metric: check.metric,
+            measured: check.measured,
+            threshold: check.threshold,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRename {
+    name: String,
+    similarity: f64,
+}
+
 impl JsonReportEntry {
     fn new(status: JsonEntryStatus, compares: &'static str, additional: impl Serialize) -> JsonReportEntry {
         let additional = serde_json::to_value(additional).unwrap();
         JsonReportEntry {
             status,
             compares,
+            kind_change: None,
+            format_regression: None,
+            renamed_from: None,
+            borderline: None,
+            reencoded: false,
+            decoration: None,
             additional,
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Serialize)]
 #[serde(rename_all = "lowercase")]
-enum JsonEntryStatus {
+pub(crate) enum JsonEntryStatus {
     Unchanged,
     Modified,
     Added,
     Deleted,
 }
 
+impl From<JsonEntryStatus> for EntryStatus {
+    fn from(status: JsonEntryStatus) -> Self {
+        match status {
+            JsonEntryStatus::Unchanged => EntryStatus::Unchanged,
+            JsonEntryStatus::Modified => EntryStatus::Modified,
+            JsonEntryStatus::Added => EntryStatus::Added,
+            JsonEntryStatus::Deleted => EntryStatus::Deleted,
+        }
+    }
+}
+
 impl<W: Write> Reporter for JsonReport<W> {
     type Error = serde_json::Error;
 
@@ -107,21 +423,342 @@ impl<W: Write> Reporter for JsonReport<W> {
     }
 
     fn finish(self) -> Result<(), Self::Error> {
+        if self.ndjson {
+            let (unchanged, modified, added, deleted) = (
+                self.unchanged.load(Ordering::Relaxed),
+                self.modified.load(Ordering::Relaxed),
+                self.added.load(Ordering::Relaxed),
+                self.deleted.load(Ordering::Relaxed),
+            );
+            self.write_line(&JsonLine::Summary {
+                unchanged,
+                modified,
+                added,
+                deleted,
+                diff_score: self
+                    .diff_score
+                    .as_ref()
+                    .map(|diff_score| diff_score.score(unchanged, modified, added, deleted)),
+                sampling: self.sampling.lock().unwrap().take(),
+            });
+            if let Some(err) = self.write_error.into_inner().unwrap() {
+                return Err(err);
+            }
+            return self.writer.into_inner().unwrap().flush().map_err(serde_json::Error::io);
+        }
+        if self.numstat {
+            let mut writer = self.writer.into_inner().unwrap();
+            let entries = BTreeMap::from_iter(self.entries);
+            for (name, entry) in entries {
+                if let JsonEntryStatus::Modified = entry.status {
+                    let mut additional = entry.additional;
+                    if let Some(decimals) = self.precision {
+                        round_floats(&mut additional, decimals);
+                    }
+                    let (first, second) = numstat_columns(&additional);
+                    writeln!(writer, "{first}\t{second}\t{}\t{name}", entry.compares).map_err(serde_json::Error::io)?;
+                }
+            }
+            return writer.flush().map_err(serde_json::Error::io);
+        }
         let JsonReport {
-            mut writer,
+            writer,
             unchanged,
             modified,
             added,
             deleted,
             entries,
+            kind_changes,
+            format_regressions,
+            renames,
+            reencoded,
+            skipped,
+            type_changes,
+            sampling,
+            diff_score,
+            borderline,
+            precision,
+            ..
         } = self;
+        let mut entries = BTreeMap::from_iter(entries);
+        for (name, kind_change) in kind_changes {
+            if let Some(entry) = entries.get_mut(&name) {
+                entry.kind_change = Some(kind_change);
+            }
+        }
+        for (name, regression) in format_regressions {
+            if let Some(entry) = entries.get_mut(&name) {
+                entry.format_regression = Some(regression);
+            }
+        }
+        for (name, rename) in renames {
+            if let Some(entry) = entries.get_mut(&name) {
+                entry.renamed_from = Some(rename);
+            }
+        }
+        for (name, check) in borderline.into_iter().flat_map(BorderlineEntries::into_entries) {
+            if let Some(entry) = entries.get_mut(&name) {
+                entry.borderline = Some(check.into());
+            }
+        }
+        for name in reencoded {
+            if let Some(entry) = entries.get_mut(&name) {
+                entry.reencoded = true;
+            }
+        }
+        let (unchanged, modified, added, deleted) = (
+            unchanged.into_inner(),
+            modified.into_inner(),
+            added.into_inner(),
+            deleted.into_inner(),
+        );
         let output = JsonReportOutput {
-            unchanged: unchanged.into_inner(),
-            modified: modified.into_inner(),
-            added: added.into_inner(),
-            deleted: deleted.into_inner(),
-            entries: BTreeMap::from_iter(entries),
+            unchanged,
+            modified,
+            added,
+            deleted,
+            diff_score: diff_score.map(|diff_score| diff_score.score(unchanged, modified, added, deleted)),
+            entries,
+            skipped: skipped
+                .into_iter()
+                .map(|(name, reason)| (name, reason.as_str()))
+                .collect(),
+            type_changes: type_changes.into_iter().collect(),
+            sampling: sampling.into_inner().unwrap(),
+        };
+        let mut writer = writer.into_inner().unwrap();
+        match precision {
+            Some(decimals) => serde_json::to_writer_pretty(&mut writer, &round_floats_of(&output, decimals)?)?,
+            None => serde_json::to_writer_pretty(&mut writer, &output)?,
+        }
+        writer.flush().map_err(serde_json::Error::io)
+    }
+
+    fn report_kind_mismatch(&self, name: &str, expected_kind: &str, actual_kind: &str) -> Result<(), Self::Error> {
+        let kind_change = JsonKindChange {
+            expected: expected_kind.to_owned(),
+            actual: actual_kind.to_owned(),
+        };
+        self.kind_changes.insert(name.to_owned(), kind_change);
+        Ok(())
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.format_regressions.insert(name.to_owned(), regression.into());
+        Ok(())
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        *self.sampling.lock().unwrap() = Some(JsonSampling {
+            rate: sampling.rate(),
+            seed: sampling.seed(),
+            left_out,
+        });
+        Ok(())
+    }
+
+    fn report_change_magnitude(&self, _name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        if let Some(diff_score) = &self.diff_score {
+            diff_score.record_magnitude(magnitude);
+        }
+        Ok(())
+    }
+
+    fn report_renamed(&self, name: &str, from: &str, similarity: f64) -> Result<(), Self::Error> {
+        let rename = JsonRename {
+            name: from.to_owned(),
+            similarity,
         };
-        serde_json::to_writer_pretty(&mut writer, &output)
+        self.renames.insert(name.to_owned(), rename);
+        Ok(())
+    }
+
+    fn report_tolerance_check(&self, name: &str, check: &ToleranceCheck) -> Result<(), Self::Error> {
+        if let Some(borderline) = &self.borderline {
+            borderline.record(name, check);
+        }
+        Ok(())
+    }
+
+    fn report_reencoded(&self, name: &str) -> Result<(), Self::Error> {
+        self.reencoded.insert(name.to_owned());
+        Ok(())
+    }
+
+    fn report_identical(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_unchanged(name, IDENTICAL_COMPARES, ());
+        Ok(())
+    }
+
+    fn report_skipped(&self, name: &str, reason: SkipReason) -> Result<(), Self::Error> {
+        if self.ndjson {
+            self.write_line(&JsonLine::Skipped {
+                name,
+                reason: reason.as_str(),
+            });
+        } else {
+            self.skipped.insert(name.to_owned(), reason);
+        }
+        Ok(())
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        let change = JsonKindChange {
+            expected: expected.as_str().to_owned(),
+            actual: actual.as_str().to_owned(),
+        };
+        if self.ndjson {
+            self.write_line(&JsonLine::TypeChange { name, change: &change });
+        } else {
+            self.type_changes.insert(name.to_owned(), change);
+        }
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_added(name, EMPTY_NODE_COMPARES, ());
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.record_deleted(name, EMPTY_NODE_COMPARES, ());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Counts how often the report flushes it.
+    #[derive(Default)]
+    struct FlushCounter {
+        flushes: usize,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn ndjson_report_flushes_lines_after_the_flush_interval() {
+        let flushes = |interval| {
+            let mut writer = FlushCounter::default();
+            let report = JsonReport::new_ndjson(&mut writer).with_flush_interval(interval);
+            report.record_added("a.txt", "text", ());
+            report.record_deleted("b.txt", "text", ());
+            report.finish().unwrap();
+            writer.flushes
+        };
+
+        // Two entry lines and the summary line, then the final flush.
+        assert_eq!(flushes(Duration::ZERO), 4);
+        assert_eq!(flushes(Duration::from_secs(3600)), 1);
+    }
+
+    #[test]
+    fn precision_rounds_non_integer_numbers() {
+        let mut output = Vec::new();
+        let report = JsonReport::new(&mut output).with_precision(3);
+        report.record_modified(
+            "a.wav",
+            "audio",
+            json!({"duration_seconds": 1.5f32 / 3.0f32 * 2.2f32, "lufs_diff_db": 0.1f32, "frames": 48000}),
+        );
+        report.report_renamed("a.wav", "old.wav", 2.0 / 3.0).unwrap();
+        report.finish().unwrap();
+
+        let output = serde_json::from_slice::<Value>(&output).unwrap();
+        assert_eq!(
+            output["entries"]["a.wav"],
+            json!({
+                "status": "modified",
+                "compares": "audio",
+                "renamed_from": { "name": "old.wav", "similarity": 0.667 },
+                "duration_seconds": 1.1,
+                "lufs_diff_db": 0.1,
+                "frames": 48000,
+            })
+        );
+    }
+
+    #[test]
+    fn numstat_report_lists_modified_entries() {
+        let mut output = Vec::new();
+        let report = JsonReport::new_numstat(&mut output);
+        report.record_modified("b.txt", "text", json!({"added": 2, "deleted": 0}));
+        report.record_modified("a.png", "image", json!({"diff_pixels": 7}));
+        report.record_added("c.txt", "text", ());
+        report.record_unchanged("d.txt", "text", ());
+        report.finish().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "7\t-\timage\ta.png\n2\t0\ttext\tb.txt\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_report_writes_one_object_per_line() {
+        let mut output = Vec::new();
+        let report = JsonReport::new_ndjson(&mut output).with_borderline_band(0.1);
+        report.report_kind_mismatch("a.bin", "image/png", "image/jpeg").unwrap();
+        let regression = FormatRegression {
+            format: "image",
+            expected_parsed: true,
+        };
+        report.report_format_regression("a.bin", &regression).unwrap();
+        report.record_modified("a.bin", "binary", ());
+        report.report_renamed("b.txt", "old/b.txt", 0.75).unwrap();
+        let check = ToleranceCheck {
+            metric: "diff_ratio",
+            measured: 0.5,
+            threshold: 0.52,
+        };
+        report.report_tolerance_check("b.txt", &check).unwrap();
+        report.record_unchanged("b.txt", "text", ());
+        report.report_skipped("c.dat", SkipReason::Unsupported).unwrap();
+        report
+            .report_type_changed("out", EntryType::Node, EntryType::Leaf)
+            .unwrap();
+        report.finish().unwrap();
+
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                json!({
+                    "type": "entry",
+                    "name": "a.bin",
+                    "status": "modified",
+                    "compares": "binary",
+                    "kind_change": { "expected": "image/png", "actual": "image/jpeg" },
+                    "format_regression": { "format": "image", "parsed": "expected" },
+                }),
+                json!({
+                    "type": "entry",
+                    "name": "b.txt",
+                    "status": "unchanged",
+                    "compares": "text",
+                    "renamed_from": { "name": "old/b.txt", "similarity": 0.75 },
+                    "borderline": { "metric": "diff_ratio", "measured": 0.5, "threshold": 0.52 },
+                }),
+                json!({ "type": "skipped", "name": "c.dat", "reason": "unsupported" }),
+                json!({ "type": "type_change", "name": "out", "expected": "directory", "actual": "file" }),
+                json!({ "type": "summary", "unchanged": 1, "modified": 1, "added": 0, "deleted": 0 }),
+            ]
+        );
     }
 }
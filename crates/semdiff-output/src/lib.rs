@@ -1,3 +1,11 @@
+pub mod borderline;
+pub mod collect;
+pub mod decorate;
+pub mod drift;
 pub mod html;
 pub mod json;
+pub mod names;
+pub mod numstat;
+pub mod score;
 pub mod summary;
+pub mod verdict;
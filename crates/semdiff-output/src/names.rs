@@ -0,0 +1,85 @@
+use semdiff_core::{EntryType, Reporter};
+use std::io;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Lists the paths that exist on only one side, one per line: `+ path` for added and `- path` for
+/// deleted. Directories reported as empty nodes end with `/`. A path that is a directory on one
+/// side and a file on the other is also listed as `~ path`.
+pub struct NameListReport<W> {
+    writer: W,
+    lines: Mutex<Vec<(String, char)>>,
+}
+
+impl<W> NameListReport<W> {
+    pub fn new(writer: W) -> NameListReport<W> {
+        NameListReport {
+            writer,
+            lines: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn push(&self, name: &str, sign: char) {
+        self.lines.lock().unwrap().push((name.to_owned(), sign));
+    }
+}
+
+impl<W: Write> Reporter for NameListReport<W> {
+    type Error = io::Error;
+
+    fn start(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        let NameListReport { mut writer, lines } = self;
+        let mut lines = lines.into_inner().unwrap();
+        lines.sort_unstable();
+        for (name, sign) in lines {
+            writeln!(writer, "{sign} {name}")?;
+        }
+        writer.flush()
+    }
+
+    fn report_added_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.push(name, '+');
+        Ok(())
+    }
+
+    fn report_deleted_leaf(&self, name: &str) -> Result<(), Self::Error> {
+        self.push(name, '-');
+        Ok(())
+    }
+
+    fn report_type_changed(&self, name: &str, _expected: EntryType, _actual: EntryType) -> Result<(), Self::Error> {
+        self.push(name, '~');
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.push(&format!("{name}/"), '+');
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, name: &str) -> Result<(), Self::Error> {
+        self.push(&format!("{name}/"), '-');
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_list_report_sorts_paths_with_their_side() {
+        let mut output = Vec::new();
+        let report = NameListReport::new(&mut output);
+        report.report_added_leaf("b/new.txt").unwrap();
+        report.report_deleted_leaf("a.txt").unwrap();
+        report.report_added_empty_node("c").unwrap();
+        report.finish().unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "- a.txt\n+ b/new.txt\n+ c/\n");
+    }
+}
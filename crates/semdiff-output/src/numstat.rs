@@ -0,0 +1,55 @@
+//! `git diff --numstat`-like lines for the modified entries of a [`crate::json::JsonReport`].
+
+use serde_json::Value;
+
+/// The two count columns of the numstat line of an entry, taken from the fields its differ recorded
+/// for the JSON report. Differs that count additions and deletions separately (lines of text, bytes
+/// of binary files) fill both columns; differs that measure a change as one number (differing
+/// pixels of an image, the spectrogram diff rate of audio, the changes of a document) fill the
+/// first one. A column without a count is `-`.
+pub fn numstat_columns(additional: &Value) -> (String, String) {
+    let field = |name| additional.get(name).filter(|value| value.is_number());
+    if let (Some(added), Some(deleted)) = (field("added"), field("deleted")) {
+        return (added.to_string(), deleted.to_string());
+    }
+    let changed = field("diff_pixels")
+        .or_else(|| field("spectrogram_diff_rate"))
+        .map(Value::to_string)
+        .or_else(|| {
+            // Audio compared by correlation has no spectrogram diff rate.
+            field("correlation")
+                .and_then(Value::as_f64)
+                .map(|correlation| (1.0 - correlation).to_string())
+        })
+        .or_else(|| {
+            additional
+                .get("changes")
+                .and_then(Value::as_array)
+                .map(|changes| changes.len().to_string())
+        })
+        .unwrap_or_else(|| "-".to_owned());
+    (changed, "-".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn numstat_columns_follow_the_recorded_stats() {
+        let columns = |value: Value| {
+            let (first, second) = numstat_columns(&value);
+            format!("{first}\t{second}")
+        };
+        assert_eq!(columns(json!({"added": 3, "deleted": 1, "similarity": 0.5})), "3\t1");
+        assert_eq!(columns(json!({"diff_pixels": 42, "expected_width": 8})), "42\t-");
+        assert_eq!(
+            columns(json!({"spectrogram_diff_rate": 0.25, "correlation": 0.5})),
+            "0.25\t-"
+        );
+        assert_eq!(columns(json!({"correlation": 0.75})), "0.25\t-");
+        assert_eq!(columns(json!({"changes": [{}, {}]})), "2\t-");
+        assert_eq!(columns(Value::Null), "-\t-");
+    }
+}
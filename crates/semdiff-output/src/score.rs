@@ -0,0 +1,136 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Contribution of each kind of change to the diff score.
+///
+/// Every entry contributes its weight times its magnitude: unchanged entries contribute 0,
+/// added and deleted entries a magnitude of 1, and modified entries the magnitude reported by
+/// their differ (image pixel ratio, audio spectrogram rate, `1 - similarity` for text), or 1
+/// when the differ cannot tell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffScoreWeights {
+    pub added: f64,
+    pub deleted: f64,
+    pub modified: f64,
+}
+
+impl Default for DiffScoreWeights {
+    fn default() -> Self {
+        DiffScoreWeights {
+            added: 1.0,
+            deleted: 1.0,
+            modified: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseDiffScoreWeightsError {
+    #[error("expected KIND=WEIGHT, got {0:?}")]
+    MissingWeight(String),
+    #[error("unknown change kind {0:?} (expected added, deleted, or modified)")]
+    UnknownKind(String),
+    #[error("invalid weight {0:?}")]
+    InvalidWeight(String),
+}
+
+impl FromStr for DiffScoreWeights {
+    type Err = ParseDiffScoreWeightsError;
+
+    /// Parses comma-separated `KIND=WEIGHT` pairs, such as `added=1,deleted=0.5`; kinds that are
+    /// not listed keep their default weight.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut weights = DiffScoreWeights::default();
+        for pair in input.split(',').filter(|pair| !pair.is_empty()) {
+            let (kind, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| ParseDiffScoreWeightsError::MissingWeight(pair.to_owned()))?;
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                .ok_or_else(|| ParseDiffScoreWeightsError::InvalidWeight(weight.to_owned()))?;
+            match kind.trim() {
+                "added" => weights.added = weight,
+                "deleted" => weights.deleted = weight,
+                "modified" => weights.modified = weight,
+                kind => return Err(ParseDiffScoreWeightsError::UnknownKind(kind.to_owned())),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+/// Aggregates per-entry change magnitudes into a single 0–100 score for the whole run.
+///
+/// The score is the mean contribution over all entries (see [`DiffScoreWeights`]), scaled to
+/// 0–100 and capped at 100.
+#[derive(Debug)]
+pub struct DiffScore {
+    weights: DiffScoreWeights,
+    magnitudes: Mutex<(f64, usize)>,
+}
+
+impl DiffScore {
+    pub fn new(weights: DiffScoreWeights) -> DiffScore {
+        DiffScore {
+            weights,
+            magnitudes: Mutex::new((0.0, 0)),
+        }
+    }
+
+    pub fn record_magnitude(&self, magnitude: f64) {
+        let mut magnitudes = self.magnitudes.lock().unwrap();
+        magnitudes.0 += magnitude.clamp(0.0, 1.0);
+        magnitudes.1 += 1;
+    }
+
+    pub fn score(&self, unchanged: usize, modified: usize, added: usize, deleted: usize) -> f64 {
+        let entries = unchanged + modified + added + deleted;
+        if entries == 0 {
+            return 0.0;
+        }
+        let (magnitude_sum, magnitude_count) = *self.magnitudes.lock().unwrap();
+        let modified_magnitude = magnitude_sum + modified.saturating_sub(magnitude_count) as f64;
+        let contribution = self.weights.added * added as f64
+            + self.weights.deleted * deleted as f64
+            + self.weights.modified * modified_magnitude;
+        (contribution / entries as f64 * 100.0).min(100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_score_weights_magnitudes_by_entry_count() {
+        let score = DiffScore::new(DiffScoreWeights::default());
+        assert_eq!(score.score(0, 0, 0, 0), 0.0);
+        assert_eq!(score.score(4, 0, 0, 0), 0.0);
+        score.record_magnitude(0.5);
+        // 2 unchanged, 2 modified (0.5 reported, 1 unknown), 1 added.
+        assert!((score.score(2, 2, 1, 0) - 50.0).abs() < 1e-9);
+
+        let weights = "added=0,modified=0.5".parse().unwrap();
+        let score = DiffScore::new(weights);
+        score.record_magnitude(0.5);
+        assert!((score.score(0, 1, 1, 0) - 12.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn diff_score_weights_reject_unknown_kinds() {
+        assert!("renamed=1".parse::<DiffScoreWeights>().is_err());
+        assert!("added".parse::<DiffScoreWeights>().is_err());
+        assert!("added=-1".parse::<DiffScoreWeights>().is_err());
+        assert_eq!(
+            "deleted=2".parse::<DiffScoreWeights>().unwrap(),
+            DiffScoreWeights {
+                deleted: 2.0,
+                ..DiffScoreWeights::default()
+            }
+        );
+    }
+}
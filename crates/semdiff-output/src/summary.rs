@@ -1,15 +1,51 @@
-use semdiff_core::Reporter;
+use crate::score::{DiffScore, DiffScoreWeights};
+use semdiff_core::{EntryType, FormatRegression, Reporter, Sampling, SkipReason};
+use std::collections::BTreeMap;
 use std::io;
 use std::io::Write;
+use std::sync::Mutex;
 use std::sync::atomic;
 use std::sync::atomic::AtomicUsize;
 
+/// What [`SummaryReport::with_per_type_table`] counts empty nodes as.
+const EMPTY_NODE_TYPE: &str = "directory";
+/// What [`SummaryReport::with_per_type_table`] counts leaves with the same bytes as.
+const IDENTICAL_TYPE: &str = "identical";
+
 pub struct SummaryReport<W> {
     writer: W,
     unchanged: AtomicUsize,
     modified: AtomicUsize,
     added: AtomicUsize,
     deleted: AtomicUsize,
+    kind_changed: AtomicUsize,
+    /// Listed by name, since each one is likely a broken file.
+    format_regressions: Mutex<BTreeMap<String, FormatRegression>>,
+    /// Listed by name, since the two sides are reported as unrelated additions and deletions.
+    type_changes: Mutex<BTreeMap<String, (EntryType, EntryType)>>,
+    renamed: AtomicUsize,
+    reencoded: AtomicUsize,
+    skipped: AtomicUsize,
+    /// The sampling and how many leaves it left out, so the counts are not read as the whole tree.
+    sampling: Mutex<Option<(Sampling, usize)>>,
+    diff_score: Option<DiffScore>,
+    per_type: Option<(PerTypeLayout, Mutex<BTreeMap<&'static str, TypeCounts>>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PerTypeLayout {
+    /// A table in place of the totals.
+    Table,
+    /// A line per changed type after the totals.
+    Breakdown,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct TypeCounts {
+    unchanged: usize,
+    modified: usize,
+    added: usize,
+    deleted: usize,
 }
 
 impl<W> SummaryReport<W> {
@@ -20,24 +56,108 @@ impl<W> SummaryReport<W> {
             modified: AtomicUsize::new(0),
             added: AtomicUsize::new(0),
             deleted: AtomicUsize::new(0),
+            kind_changed: AtomicUsize::new(0),
+            format_regressions: Mutex::new(BTreeMap::new()),
+            type_changes: Mutex::new(BTreeMap::new()),
+            renamed: AtomicUsize::new(0),
+            reencoded: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            sampling: Mutex::new(None),
+            diff_score: None,
+            per_type: None,
         }
     }
 
-    pub fn increment_unchanged(&self) {
+    /// Prints a diff score for the whole run, computed with `weights`.
+    pub fn with_diff_score(mut self, weights: DiffScoreWeights) -> Self {
+        self.diff_score = Some(DiffScore::new(weights));
+        self
+    }
+
+    /// Prints the counts as a table with one row per compared type (the differ's `compares` name),
+    /// like `git diff --stat`, instead of the totals alone.
+    pub fn with_per_type_table(mut self) -> Self {
+        self.per_type = Some((PerTypeLayout::Table, Mutex::new(BTreeMap::new())));
+        self
+    }
+
+    /// Follows the totals with the changes of each compared type, such as
+    /// `image: 3 modified, 1 added`, leaving out types without changes.
+    pub fn with_type_breakdown(mut self) -> Self {
+        self.per_type = Some((PerTypeLayout::Breakdown, Mutex::new(BTreeMap::new())));
+        self
+    }
+
+    pub fn increment_unchanged(&self, compares: &'static str) {
         self.unchanged.fetch_add(1, atomic::Ordering::Relaxed);
+        self.count_type(compares, |counts| &mut counts.unchanged);
     }
 
-    pub fn increment_modified(&self) {
+    pub fn increment_modified(&self, compares: &'static str) {
         self.modified.fetch_add(1, atomic::Ordering::Relaxed);
+        self.count_type(compares, |counts| &mut counts.modified);
     }
 
-    pub fn increment_added(&self) {
+    pub fn increment_added(&self, compares: &'static str) {
         self.added.fetch_add(1, atomic::Ordering::Relaxed);
+        self.count_type(compares, |counts| &mut counts.added);
     }
 
-    pub fn increment_deleted(&self) {
+    pub fn increment_deleted(&self, compares: &'static str) {
         self.deleted.fetch_add(1, atomic::Ordering::Relaxed);
+        self.count_type(compares, |counts| &mut counts.deleted);
+    }
+
+    fn count_type(&self, compares: &'static str, field: impl FnOnce(&mut TypeCounts) -> &mut usize) {
+        if let Some((_, per_type)) = &self.per_type {
+            *field(per_type.lock().unwrap().entry(compares).or_default()) += 1;
+        }
+    }
+}
+
+fn write_per_type_table(
+    writer: &mut impl Write,
+    per_type: BTreeMap<&'static str, TypeCounts>,
+    total: TypeCounts,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{:<10} {:>9} {:>9} {:>9} {:>9}",
+        "Type", "Unchanged", "Modified", "Added", "Deleted"
+    )?;
+    for (compares, counts) in per_type.into_iter().chain([("Total", total)]) {
+        writeln!(
+            writer,
+            "{:<10} {:>9} {:>9} {:>9} {:>9}",
+            compares, counts.unchanged, counts.modified, counts.added, counts.deleted
+        )?;
     }
+    Ok(())
+}
+
+fn write_type_breakdown(writer: &mut impl Write, per_type: BTreeMap<&'static str, TypeCounts>) -> io::Result<()> {
+    let changed = per_type
+        .into_iter()
+        .filter_map(|(compares, counts)| {
+            let changes = [
+                (counts.modified, "modified"),
+                (counts.added, "added"),
+                (counts.deleted, "deleted"),
+            ]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, status)| format!("{count} {status}"))
+            .collect::<Vec<_>>();
+            (!changes.is_empty()).then(|| format!("  {compares}: {}", changes.join(", ")))
+        })
+        .collect::<Vec<_>>();
+    if !changed.is_empty() {
+        writeln!(writer, "By type:")?;
+        for line in changed {
+            writeln!(writer, "{line}")?;
+        }
+    }
+    Ok(())
 }
 
 impl<W: Write> Reporter for SummaryReport<W> {
@@ -54,20 +174,208 @@ impl<W: Write> Reporter for SummaryReport<W> {
             modified,
             added,
             deleted,
+            kind_changed,
+            format_regressions,
+            type_changes,
+            renamed,
+            reencoded,
+            skipped,
+            sampling,
+            diff_score,
+            per_type,
         } = self;
         let unchanged = unchanged.into_inner();
         let modified = modified.into_inner();
         let added = added.into_inner();
         let deleted = deleted.into_inner();
+        let kind_changed = kind_changed.into_inner();
+        let renamed = renamed.into_inner();
+        let reencoded = reencoded.into_inner();
+        let skipped = skipped.into_inner();
 
-        writeln!(
-            writer,
-            r#"Summary Report
+        match per_type {
+            Some((PerTypeLayout::Table, per_type)) => {
+                let total = TypeCounts {
+                    unchanged,
+                    modified,
+                    added,
+                    deleted,
+                };
+                write_per_type_table(&mut writer, per_type.into_inner().unwrap(), total)?;
+            }
+            per_type => {
+                writeln!(
+                    writer,
+                    r#"Summary Report
 Unchanged: {}
 Modified:  {}
 Added:     {}
 Deleted:   {}"#,
-            unchanged, modified, added, deleted
-        )
+                    unchanged, modified, added, deleted
+                )?;
+                if let Some((_, per_type)) = per_type {
+                    write_type_breakdown(&mut writer, per_type.into_inner().unwrap())?;
+                }
+            }
+        }
+        if kind_changed > 0 {
+            writeln!(writer, "Kind changed: {}", kind_changed)?;
+        }
+        let format_regressions = format_regressions.into_inner().unwrap();
+        if !format_regressions.is_empty() {
+            writeln!(writer, "Format regressions: {}", format_regressions.len())?;
+            for (name, regression) in format_regressions {
+                writeln!(writer, "  {name}: {regression}")?;
+            }
+        }
+        let type_changes = type_changes.into_inner().unwrap();
+        if !type_changes.is_empty() {
+            writeln!(writer, "Type changed: {}", type_changes.len())?;
+            for (name, (expected, actual)) in type_changes {
+                writeln!(writer, "  {name}: {} \u{2192} {}", expected.as_str(), actual.as_str())?;
+            }
+        }
+        if renamed > 0 {
+            writeln!(writer, "Renamed:   {}", renamed)?;
+        }
+        if reencoded > 0 {
+            writeln!(writer, "Same content, different encoding: {}", reencoded)?;
+        }
+        if skipped > 0 {
+            writeln!(writer, "Skipped:   {}", skipped)?;
+        }
+        if let Some((sampling, left_out)) = sampling.into_inner().unwrap() {
+            writeln!(
+                writer,
+                "Sampled:   rate {} (seed {}), {} left out",
+                sampling.rate(),
+                sampling.seed(),
+                left_out
+            )?;
+        }
+        if let Some(diff_score) = diff_score {
+            writeln!(
+                writer,
+                "Diff score: {:.2}",
+                diff_score.score(unchanged, modified, added, deleted)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn report_kind_mismatch(&self, _name: &str, _expected_kind: &str, _actual_kind: &str) -> Result<(), Self::Error> {
+        self.kind_changed.fetch_add(1, atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn report_format_regression(&self, name: &str, regression: &FormatRegression) -> Result<(), Self::Error> {
+        self.format_regressions
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), *regression);
+        Ok(())
+    }
+
+    fn report_sampling(&self, sampling: &Sampling, left_out: usize) -> Result<(), Self::Error> {
+        *self.sampling.lock().unwrap() = Some((*sampling, left_out));
+        Ok(())
+    }
+
+    fn report_change_magnitude(&self, _name: &str, magnitude: f64) -> Result<(), Self::Error> {
+        if let Some(diff_score) = &self.diff_score {
+            diff_score.record_magnitude(magnitude);
+        }
+        Ok(())
+    }
+
+    fn report_reencoded(&self, _name: &str) -> Result<(), Self::Error> {
+        self.reencoded.fetch_add(1, atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn report_identical(&self, _name: &str) -> Result<(), Self::Error> {
+        self.increment_unchanged(IDENTICAL_TYPE);
+        Ok(())
+    }
+
+    fn report_renamed(&self, _name: &str, _from: &str, _similarity: f64) -> Result<(), Self::Error> {
+        self.renamed.fetch_add(1, atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn report_skipped(&self, _name: &str, _reason: SkipReason) -> Result<(), Self::Error> {
+        self.skipped.fetch_add(1, atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn report_type_changed(&self, name: &str, expected: EntryType, actual: EntryType) -> Result<(), Self::Error> {
+        self.type_changes
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), (expected, actual));
+        Ok(())
+    }
+
+    fn report_added_empty_node(&self, _name: &str) -> Result<(), Self::Error> {
+        self.increment_added(EMPTY_NODE_TYPE);
+        Ok(())
+    }
+
+    fn report_deleted_empty_node(&self, _name: &str) -> Result<(), Self::Error> {
+        self.increment_deleted(EMPTY_NODE_TYPE);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_type_table_counts_each_compared_type() {
+        let mut output = Vec::new();
+        let report = SummaryReport::new(&mut output).with_per_type_table();
+        report.increment_unchanged("text");
+        report.increment_unchanged("text");
+        report.increment_modified("json");
+        report.increment_added("image");
+        report.report_deleted_empty_node("old").unwrap();
+        report.finish().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Type       Unchanged  Modified     Added   Deleted\n\
+             directory          0         0         0         1\n\
+             image              0         0         1         0\n\
+             json               0         1         0         0\n\
+             text               2         0         0         0\n\
+             Total              2         1         1         1\n"
+        );
+    }
+
+    #[test]
+    fn type_breakdown_lists_the_changes_of_each_type() {
+        let mut output = Vec::new();
+        let report = SummaryReport::new(&mut output).with_type_breakdown();
+        report.increment_unchanged("text");
+        for _ in 0..3 {
+            report.increment_modified("image");
+        }
+        report.increment_added("image");
+        report.increment_modified("json");
+        report.increment_modified("json");
+        report.finish().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Summary Report\n\
+             Unchanged: 1\n\
+             Modified:  5\n\
+             Added:     1\n\
+             Deleted:   0\n\
+             By type:\n  \
+             image: 3 modified, 1 added\n  \
+             json: 2 modified\n"
+        );
     }
 }
@@ -0,0 +1,236 @@
+//! Comparing two in-memory files with the whole differ chain, returning the result as a value
+//! instead of writing a report.
+
+use crate::json::{JsonEntryStatus, JsonReport};
+use mime::Mime;
+use semdiff_core::fs::FileLeaf;
+use semdiff_core::{CalcDiffError, DiffOptions, DiffReport, calc_leaf_diff};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{convert, io};
+
+/// A differ run by [`compare_bytes`]: `DiffAndReport::new(calculator, reporter)` with the JSON
+/// reporter of a differ crate, as for a JSON report.
+pub type VerdictDiffer = Box<dyn DiffReport<FileLeaf, JsonReport<io::Sink>>>;
+
+pub type VerdictError = CalcDiffError<convert::Infallible, serde_json::Error>;
+
+/// The differs tried by [`compare_bytes`], in order, and the options they run with.
+///
+/// A config is `Sync`, so one config can be set up once and shared by reference between threads,
+/// for example in a `static` `LazyLock`.
+pub struct VerdictConfig {
+    differs: Vec<VerdictDiffer>,
+    options: DiffOptions,
+}
+
+impl VerdictConfig {
+    pub fn new(differs: Vec<VerdictDiffer>) -> VerdictConfig {
+        VerdictConfig {
+            differs,
+            options: DiffOptions::default(),
+        }
+    }
+
+    /// Sets the differ order, format regression, and strict extension options; the options for
+    /// walking trees have no effect on a single pair of files.
+    pub fn with_options(mut self, options: DiffOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerdictStatus {
+    Unchanged,
+    Modified,
+    /// None of the differs could compare the files.
+    Unsupported,
+}
+
+/// The outcome of [`compare_bytes`]; serializes like an entry of the JSON report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffVerdict {
+    pub status: VerdictStatus,
+    /// Name of the differ that compared the files, such as `json` or `image`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compares: Option<&'static str>,
+    /// The stats of that differ, plus `format_regression` and `reencoded` when they apply, keyed
+    /// as in the JSON report.
+    #[serde(flatten)]
+    pub details: Map<String, Value>,
+}
+
+/// Compares two versions of the file `name` of kind `kind`, given as bytes, with the differs of
+/// `config` and returns what the first differ that supports them found.
+///
+/// Nothing is read from or written to disk. Differs still compute what their calculators are set
+/// up to compute, so calculators should be built with visuals off, since the verdict leaves out
+/// diff images and plots.
+///
+/// Calls are independent: any number of threads may call `compare_bytes` at the same time with the
+/// same config, because the differs only take `&self` and every call records into a report of its
+/// own.
+pub fn compare_bytes(
+    name: &str,
+    kind: Mime,
+    expected: &[u8],
+    actual: &[u8],
+    config: &VerdictConfig,
+) -> Result<DiffVerdict, VerdictError> {
+    let report = JsonReport::new(io::sink());
+    let expected = FileLeaf::from_bytes(name, kind.clone(), expected);
+    let actual = FileLeaf::from_bytes(name, kind, actual);
+    calc_leaf_diff(name, &expected, &actual, &config.differs, &report, &config.options)?;
+    let Some(entry) = report.take_entry(name) else {
+        return Ok(DiffVerdict {
+            status: VerdictStatus::Unsupported,
+            compares: None,
+            details: Map::new(),
+        });
+    };
+    let status = match entry.status {
+        JsonEntryStatus::Unchanged => VerdictStatus::Unchanged,
+        _ => VerdictStatus::Modified,
+    };
+    let compares = entry.compares;
+    let Value::Object(mut details) = serde_json::to_value(entry).map_err(CalcDiffError::ReporterError)? else {
+        unreachable!("report entries serialize to objects");
+    };
+    details.remove("status");
+    details.remove("compares");
+    Ok(DiffVerdict {
+        status,
+        compares: Some(compares),
+        details,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semdiff_core::{DetailReporter, Diff, DiffAndReport, DiffCalculator, MayUnsupported};
+    use serde_json::json;
+    use std::thread;
+
+    /// Compares UTF-8 files by their length.
+    struct LengthCalculator;
+
+    struct LengthDiff {
+        expected: usize,
+        actual: usize,
+    }
+
+    impl Diff for LengthDiff {
+        fn equal(&self) -> bool {
+            self.expected == self.actual
+        }
+    }
+
+    impl DiffCalculator<FileLeaf> for LengthCalculator {
+        type Error = convert::Infallible;
+        type Diff = LengthDiff;
+
+        fn diff(
+            &self,
+            _name: &str,
+            expected: FileLeaf,
+            actual: FileLeaf,
+        ) -> Result<MayUnsupported<LengthDiff>, Self::Error> {
+            if str::from_utf8(&expected.content).is_err() || str::from_utf8(&actual.content).is_err() {
+                return Ok(MayUnsupported::Unsupported);
+            }
+            Ok(MayUnsupported::Ok(LengthDiff {
+                expected: expected.content.len(),
+                actual: actual.content.len(),
+            }))
+        }
+    }
+
+    struct LengthReporter;
+
+    impl DetailReporter<LengthDiff, FileLeaf, JsonReport<io::Sink>> for LengthReporter {
+        type Error = convert::Infallible;
+
+        fn report_unchanged(
+            &self,
+            name: &str,
+            _diff: &LengthDiff,
+            reporter: &JsonReport<io::Sink>,
+        ) -> Result<MayUnsupported<()>, Self::Error> {
+            reporter.record_unchanged(name, "length", ());
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_modified(
+            &self,
+            name: &str,
+            diff: &LengthDiff,
+            reporter: &JsonReport<io::Sink>,
+        ) -> Result<MayUnsupported<()>, Self::Error> {
+            reporter.record_modified(
+                name,
+                "length",
+                json!({ "expected": diff.expected, "actual": diff.actual }),
+            );
+            Ok(MayUnsupported::Ok(()))
+        }
+
+        fn report_added(
+            &self,
+            _name: &str,
+            _data: &FileLeaf,
+            _reporter: &JsonReport<io::Sink>,
+        ) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Unsupported)
+        }
+
+        fn report_deleted(
+            &self,
+            _name: &str,
+            _data: &FileLeaf,
+            _reporter: &JsonReport<io::Sink>,
+        ) -> Result<MayUnsupported<()>, Self::Error> {
+            Ok(MayUnsupported::Unsupported)
+        }
+    }
+
+    #[test]
+    fn compare_bytes_returns_the_verdict_of_the_differ() {
+        let config = VerdictConfig::new(vec![Box::new(DiffAndReport::new(LengthCalculator, LengthReporter))]);
+        let compare = |expected: &[u8], actual: &[u8]| {
+            compare_bytes("a.txt", mime::TEXT_PLAIN, expected, actual, &config).unwrap()
+        };
+
+        let verdict = compare(b"abc", b"abcd");
+        assert_eq!(verdict.status, VerdictStatus::Modified);
+        assert_eq!(verdict.compares, Some("length"));
+        assert_eq!(
+            serde_json::to_value(&verdict).unwrap(),
+            json!({ "status": "modified", "compares": "length", "expected": 3, "actual": 4 })
+        );
+        assert_eq!(compare(b"abc", b"xyz").status, VerdictStatus::Unchanged);
+        assert_eq!(compare(b"abc", b"\xff").status, VerdictStatus::Unsupported);
+
+        // One config serves concurrent calls.
+        thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|len| scope.spawn(move || compare(b"ab", &b"abcd"[..len]).status))
+                .collect::<Vec<_>>();
+            let statuses = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                statuses,
+                [
+                    VerdictStatus::Modified,
+                    VerdictStatus::Modified,
+                    VerdictStatus::Unchanged,
+                    VerdictStatus::Modified
+                ]
+            );
+        });
+    }
+}